@@ -0,0 +1,763 @@
+use crate::{
+    accel::{
+        stats::{Recorder, TraversalStats},
+        Accelerator,
+    },
+    core::{
+        geometry::{Axis3, Box3, Point3, Ray, RayBundle, Vector3},
+        units::Time,
+    },
+    shape::{Shape, SurfaceInteraction},
+};
+use num_traits::Float;
+
+/// Deepest possible traversal stack, which bounds the depth of the built trees
+pub(super) const STACK_SIZE: usize = 64;
+
+/// How the builder chooses where to split a set of primitives
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum SplitMethod {
+    /// Splits into halves at the median centroid; fast to build but slower to traverse
+    Median,
+    /// Minimizes the surface area heuristic over a number of binned candidate splits
+    Sah,
+}
+
+/// Options trading build time for traversal performance
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BvhBuildOptions {
+    pub split_method: SplitMethod,
+    /// Nodes with more primitives than this are always split
+    pub max_leaf_size: usize,
+    /// The number of bins candidate SAH splits are evaluated between
+    pub sah_bins: usize,
+    /// The cost of visiting an interior node, relative to intersecting a primitive
+    pub traversal_cost: f32,
+}
+
+impl Default for BvhBuildOptions {
+    fn default() -> Self {
+        Self {
+            split_method: SplitMethod::Sah,
+            max_leaf_size: 4,
+            sah_bins: 12,
+            traversal_cost: 0.5,
+        }
+    }
+}
+
+/// A node of a BVH, stored in depth-first order
+///
+/// The first child of an interior node immediately follows it.
+pub(crate) struct BvhNode<T, U> {
+    pub(crate) bounds: Box3<T, U>,
+    /// The first primitive of a leaf or the second child of an interior node
+    pub(crate) offset: u32,
+    /// The number of primitives in a leaf, or zero for interior nodes
+    pub(crate) count: u32,
+    /// The axis an interior node was split along
+    pub(crate) axis: Axis3,
+}
+
+common_impls!(BvhNode {
+    bounds,
+    offset,
+    count,
+    axis
+});
+
+impl<T, U> BvhNode<T, U> {
+    #[inline]
+    pub(crate) fn is_leaf(&self) -> bool {
+        self.count > 0
+    }
+}
+
+pub(super) struct BuildPrimitive<T, U> {
+    pub(super) index: usize,
+    pub(super) bounds: Box3<T, U>,
+    pub(super) centroid: Point3<T, U>,
+}
+
+impl<T: Float, U> BuildPrimitive<T, U> {
+    #[inline]
+    pub(super) fn new(index: usize, bounds: Box3<T, U>) -> Self {
+        let two = T::one() + T::one();
+        let centroid = bounds.min + (bounds.max - bounds.min) / two;
+        Self {
+            index,
+            bounds,
+            centroid,
+        }
+    }
+}
+
+/// A binary bounding volume hierarchy over a set of primitives
+///
+/// The primitives are reordered during the build, and the `primitive` index of the reported hits
+/// refers to [`primitives`](Self::primitives).
+pub struct Bvh<T, U, P> {
+    primitives: Vec<P>,
+    nodes: Vec<BvhNode<T, U>>,
+    /// The index each primitive had before the build reordered them
+    order: Vec<u32>,
+}
+
+impl<T, U, P> Bvh<T, U, P> {
+    #[inline]
+    #[must_use]
+    pub fn into_primitives(self) -> Vec<P> {
+        self.primitives
+    }
+
+    #[inline]
+    pub(super) fn nodes(&self) -> &[BvhNode<T, U>] {
+        &self.nodes
+    }
+
+    #[inline]
+    pub(super) fn order(&self) -> &[u32] {
+        &self.order
+    }
+}
+
+impl<T: Float, U, P: Shape<T, U>> Bvh<T, U, P> {
+    /// Builds a hierarchy with the default options
+    #[inline]
+    #[must_use]
+    pub fn new(primitives: Vec<P>) -> Self {
+        Self::with_options(primitives, &BvhBuildOptions::default())
+    }
+
+    #[must_use]
+    pub fn with_options(primitives: Vec<P>, options: &BvhBuildOptions) -> Self {
+        let mut build: Vec<_> = primitives
+            .iter()
+            .enumerate()
+            .map(|(index, primitive)| BuildPrimitive::new(index, primitive.bounds()))
+            .collect();
+
+        let mut builder = Builder {
+            options,
+            traversal_cost: T::from(options.traversal_cost).unwrap(),
+            nodes: Vec::with_capacity(2 * build.len()),
+        };
+        if !build.is_empty() {
+            builder.build(&mut build, 0, 0);
+        }
+
+        Self::from_build(primitives, &build, builder.nodes)
+    }
+
+    /// Assembles a hierarchy whose leaves refer to the primitives in the order of `build`
+    pub(super) fn from_build(
+        primitives: Vec<P>,
+        build: &[BuildPrimitive<T, U>],
+        nodes: Vec<BvhNode<T, U>>,
+    ) -> Self {
+        let order = build.iter().map(|p| p.index as u32).collect();
+        Self::from_parts(primitives, nodes, order)
+    }
+
+    /// Assembles a hierarchy from its nodes and the original index of each primitive in the
+    /// final order, which must be a permutation
+    pub(super) fn from_parts(
+        primitives: Vec<P>,
+        nodes: Vec<BvhNode<T, U>>,
+        order: Vec<u32>,
+    ) -> Self {
+        let mut primitives: Vec<_> = primitives.into_iter().map(Some).collect();
+        let primitives = order
+            .iter()
+            .map(|&i| primitives[i as usize].take().unwrap())
+            .collect();
+        Self {
+            primitives,
+            nodes,
+            order,
+        }
+    }
+}
+
+struct Builder<'a, T, U> {
+    options: &'a BvhBuildOptions,
+    traversal_cost: T,
+    nodes: Vec<BvhNode<T, U>>,
+}
+
+impl<T: Float, U> Builder<'_, T, U> {
+    /// Builds the subtree over `primitives`, which start at `offset` in the final order, and
+    /// returns the index of its root
+    fn build(
+        &mut self,
+        primitives: &mut [BuildPrimitive<T, U>],
+        offset: usize,
+        depth: usize,
+    ) -> usize {
+        let bounds = primitives[1..]
+            .iter()
+            .fold(primitives[0].bounds, |b, p| b.union_unchecked(&p.bounds));
+        let index = self.nodes.len();
+        self.nodes.push(BvhNode {
+            bounds,
+            offset: offset as u32,
+            count: primitives.len() as u32,
+            axis: Axis3::X,
+        });
+        if primitives.len() == 1 || depth + 1 >= STACK_SIZE {
+            return index;
+        }
+
+        let centroid_bounds = primitives[1..].iter().fold(
+            Box3::new(primitives[0].centroid, primitives[0].centroid),
+            |b, p| Box3::new(b.min.min(p.centroid), b.max.max(p.centroid)),
+        );
+        let axis = largest_axis(&centroid_bounds);
+        if centroid_bounds.max[axis] == centroid_bounds.min[axis] {
+            // Splitting primitives with identical centroids would not make traversal any cheaper
+            return index;
+        }
+
+        let mid = match self.options.split_method {
+            SplitMethod::Median if primitives.len() <= self.options.max_leaf_size => None,
+            SplitMethod::Median => Some(median_split(primitives, axis)),
+            SplitMethod::Sah => self.sah_split(primitives, &bounds, &centroid_bounds, axis),
+        };
+        let Some(mid) = mid else {
+            return index;
+        };
+
+        let (left, right) = primitives.split_at_mut(mid);
+        self.build(left, offset, depth + 1);
+        let second = self.build(right, offset + mid, depth + 1);
+
+        let node = &mut self.nodes[index];
+        node.offset = second as u32;
+        node.count = 0;
+        node.axis = axis;
+        index
+    }
+
+    /// Partitions the primitives at the cheapest binned split along `axis`, returning `None` if
+    /// a leaf would be cheaper
+    fn sah_split(
+        &self,
+        primitives: &mut [BuildPrimitive<T, U>],
+        bounds: &Box3<T, U>,
+        centroid_bounds: &Box3<T, U>,
+        axis: Axis3,
+    ) -> Option<usize> {
+        if primitives.len() <= 2 {
+            return (primitives.len() > self.options.max_leaf_size)
+                .then(|| median_split(primitives, axis));
+        }
+
+        let bin_count = self.options.sah_bins.max(2);
+        let scale =
+            T::from(bin_count).unwrap() / (centroid_bounds.max[axis] - centroid_bounds.min[axis]);
+        let bin_of = |p: &BuildPrimitive<T, U>| {
+            let bin = ((p.centroid[axis] - centroid_bounds.min[axis]) * scale)
+                .to_usize()
+                .unwrap_or(0);
+            bin.min(bin_count - 1)
+        };
+
+        let mut bins: Vec<(usize, Option<Box3<T, U>>)> = vec![(0, None); bin_count];
+        for p in primitives.iter() {
+            let (count, bin_bounds) = &mut bins[bin_of(p)];
+            *count += 1;
+            *bin_bounds = Some(bin_bounds.map_or(p.bounds, |b| b.union_unchecked(&p.bounds)));
+        }
+
+        // Sweeps from both ends to find the area-weighted primitive counts on either side of
+        // each of the `bin_count - 1` splits
+        let sweep = |bins: &mut dyn Iterator<Item = &(usize, Option<Box3<T, U>>)>| {
+            let mut count = 0;
+            let mut swept: Option<Box3<T, U>> = None;
+            bins.take(bin_count - 1)
+                .map(|(bin_count, bin_bounds)| {
+                    count += bin_count;
+                    if let Some(b) = bin_bounds {
+                        swept = Some(swept.map_or(*b, |s| s.union_unchecked(b)));
+                    }
+                    let area = swept.map_or(T::zero(), |b| b.surface_area());
+                    T::from(count).unwrap() * area
+                })
+                .collect::<Vec<_>>()
+        };
+        let below = sweep(&mut bins.iter());
+        let mut above = sweep(&mut bins.iter().rev());
+        above.reverse();
+
+        let (split, cost) = below
+            .iter()
+            .zip(&above)
+            .map(|(below, above)| *below + *above)
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))?;
+
+        let area = bounds.surface_area();
+        let leaf_cost = T::from(primitives.len()).unwrap();
+        let split_cost = if area > T::zero() {
+            self.traversal_cost + cost / area
+        } else {
+            self.traversal_cost
+        };
+        if primitives.len() <= self.options.max_leaf_size && leaf_cost <= split_cost {
+            return None;
+        }
+
+        let mid = partition(primitives, |p| bin_of(p) <= split);
+        Some(if mid == 0 || mid == primitives.len() {
+            median_split(primitives, axis)
+        } else {
+            mid
+        })
+    }
+}
+
+/// Splits the primitives into halves around the median centroid along `axis`
+fn median_split<T: Float, U>(primitives: &mut [BuildPrimitive<T, U>], axis: Axis3) -> usize {
+    let mid = primitives.len() / 2;
+    primitives.select_nth_unstable_by(mid, |a, b| {
+        a.centroid[axis]
+            .partial_cmp(&b.centroid[axis])
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    mid
+}
+
+/// Moves the elements satisfying `pred` to the front, returning how many there are
+fn partition<E>(elements: &mut [E], mut pred: impl FnMut(&E) -> bool) -> usize {
+    let mut mid = 0;
+    for i in 0..elements.len() {
+        if pred(&elements[i]) {
+            elements.swap(i, mid);
+            mid += 1;
+        }
+    }
+    mid
+}
+
+#[inline]
+pub(super) fn largest_axis<T: Float, U>(b: &Box3<T, U>) -> Axis3 {
+    let extent = b.max - b.min;
+    if extent.x >= extent.y && extent.x >= extent.z {
+        Axis3::X
+    } else if extent.y >= extent.z {
+        Axis3::Y
+    } else {
+        Axis3::Z
+    }
+}
+
+/// Slab tests a box with the reciprocal ray direction precomputed, returning where the ray
+/// enters it
+#[inline]
+pub(super) fn bounds_entry<T: Float, U>(
+    bounds: &Box3<T, U>,
+    origin: Point3<T, U>,
+    inv_dir: Vector3<T, U>,
+    t_max: T,
+) -> Option<T> {
+    let (mut t0, mut t1) = (T::zero(), t_max);
+    for axis in Axis3::AXES {
+        let mut near = (bounds.min[axis] - origin[axis]) * inv_dir[axis];
+        let mut far = (bounds.max[axis] - origin[axis]) * inv_dir[axis];
+        if near > far {
+            std::mem::swap(&mut near, &mut far);
+        }
+        t0 = if near > t0 { near } else { t0 };
+        t1 = if far < t1 { far } else { t1 };
+        if t0 > t1 {
+            return None;
+        }
+    }
+    Some(t0)
+}
+
+impl<T: Float, U, P: Shape<T, U>> Bvh<T, U, P> {
+    /// Visits the leaves whose bounds the ray enters before `t_max`, nearest first
+    ///
+    /// The visitor returns the new `t_max`, or `None` to stop the traversal.
+    #[inline]
+    pub(super) fn traverse<S: Recorder>(
+        &self,
+        ray: &Ray<T, U>,
+        mut t_max: T,
+        stats: &mut S,
+        mut visit_leaf: impl FnMut(usize, usize, T, &mut S) -> Option<T>,
+    ) {
+        if self.nodes.is_empty() {
+            return;
+        }
+        stats.ray();
+        let d = ray.dir;
+        let inv_dir = Vector3::new(d.x.recip(), d.y.recip(), d.z.recip());
+        let dir_is_neg = [d.x < T::zero(), d.y < T::zero(), d.z < T::zero()];
+
+        let mut stack = [0; STACK_SIZE];
+        let mut stack_len = 0;
+        let mut current = 0;
+        loop {
+            let node = &self.nodes[current];
+            stats.node_visit();
+            if bounds_entry(&node.bounds, ray.origin, inv_dir, t_max).is_some() {
+                if node.is_leaf() {
+                    let start = node.offset as usize;
+                    match visit_leaf(start, start + node.count as usize, t_max, stats) {
+                        Some(t) => t_max = t,
+                        None => return,
+                    }
+                } else {
+                    // Visit the child on the near side of the split first
+                    let (near, far) = if dir_is_neg[node.axis as usize] {
+                        (node.offset as usize, current + 1)
+                    } else {
+                        (current + 1, node.offset as usize)
+                    };
+                    stack[stack_len] = far;
+                    stack_len += 1;
+                    stats.depth(stack_len);
+                    current = near;
+                    continue;
+                }
+            }
+            if stack_len == 0 {
+                return;
+            }
+            stack_len -= 1;
+            current = stack[stack_len];
+        }
+    }
+}
+
+impl<T: Float, U, P: Shape<T, U>> Bvh<T, U, P> {
+    fn intersect_recorded(
+        &self,
+        ray: &Ray<T, U>,
+        t_max: Time<T>,
+        stats: &mut impl Recorder,
+    ) -> Option<SurfaceInteraction<T, U>> {
+        let mut closest = None;
+        self.traverse(ray, t_max.0, stats, |start, end, mut t_max, stats| {
+            for i in start..end {
+                stats.primitive_test();
+                if let Some(mut hit) = self.primitives[i].intersect(ray, Time(t_max)) {
+                    t_max = hit.t.0;
+                    hit.primitive = i;
+                    closest = Some(hit);
+                }
+            }
+            Some(t_max)
+        });
+        closest
+    }
+
+    fn intersect_any_recorded(
+        &self,
+        ray: &Ray<T, U>,
+        t_max: Time<T>,
+        stats: &mut impl Recorder,
+    ) -> bool {
+        let mut occluded = false;
+        self.traverse(ray, t_max.0, stats, |start, end, t_max, stats| {
+            occluded = self.primitives[start..end].iter().any(|p| {
+                stats.primitive_test();
+                p.intersect_any(ray, Time(t_max))
+            });
+            (!occluded).then_some(t_max)
+        });
+        occluded
+    }
+}
+
+impl<T: Float, U, P: Shape<T, U>> Shape<T, U> for Bvh<T, U, P> {
+    #[inline]
+    fn bounds(&self) -> Box3<T, U> {
+        self.nodes
+            .first()
+            .map_or_else(Box3::empty, |root| root.bounds)
+    }
+
+    #[inline]
+    fn intersect(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<SurfaceInteraction<T, U>> {
+        self.intersect_recorded(ray, t_max, &mut ())
+    }
+
+    #[inline]
+    fn intersect_any(&self, ray: &Ray<T, U>, t_max: Time<T>) -> bool {
+        self.intersect_any_recorded(ray, t_max, &mut ())
+    }
+}
+
+impl<T: Float, U, P: Shape<T, U>> Accelerator<T, U> for Bvh<T, U, P> {
+    type Primitive = P;
+
+    #[inline]
+    fn primitives(&self) -> &[P] {
+        &self.primitives
+    }
+
+    #[inline]
+    fn intersect_with_stats(
+        &self,
+        ray: &Ray<T, U>,
+        t_max: Time<T>,
+        stats: &mut TraversalStats,
+    ) -> Option<SurfaceInteraction<T, U>> {
+        self.intersect_recorded(ray, t_max, stats)
+    }
+
+    #[inline]
+    fn intersect_any_with_stats(
+        &self,
+        ray: &Ray<T, U>,
+        t_max: Time<T>,
+        stats: &mut TraversalStats,
+    ) -> bool {
+        self.intersect_any_recorded(ray, t_max, stats)
+    }
+
+    #[inline]
+    fn intersect_bundle<const N: usize>(
+        &self,
+        bundle: &RayBundle<T, U, N>,
+    ) -> [Option<SurfaceInteraction<T, U>>; N] {
+        self.intersect_bundle_packet(bundle)
+    }
+
+    #[inline]
+    fn intersect_any_bundle<const N: usize>(&self, bundle: &RayBundle<T, U, N>) -> [bool; N] {
+        self.intersect_any_bundle_packet(bundle)
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use crate::{
+        accel::{Bvh4, Bvh8, Grid, GridOptions, Octree, OctreeOptions},
+        core::geometry::UnknownUnit,
+        shape::{Sphere, Triangle, TriangleMesh},
+    };
+    use std::sync::Arc;
+
+    type S = Sphere<f32, UnknownUnit>;
+    type R = Ray<f32, UnknownUnit>;
+    type P = Point3<f32, UnknownUnit>;
+
+    /// A deterministic stream of numbers in `[0, 1)`
+    pub(in crate::accel) fn random(state: &mut u32) -> f32 {
+        *state ^= *state << 13;
+        *state ^= *state >> 17;
+        *state ^= *state << 5;
+        (*state >> 8) as f32 / (1 << 24) as f32
+    }
+
+    fn random_point(state: &mut u32, scale: f32) -> P {
+        let mut r = || (random(state) - 0.5) * scale;
+        P::new(r(), r(), r())
+    }
+
+    /// Random triangles of up to a couple of units across, in a cube of side 20 about the origin
+    pub(in crate::accel) fn triangle_soup(
+        state: &mut u32,
+        count: usize,
+    ) -> Vec<Triangle<f32, UnknownUnit>> {
+        let mut positions = Vec::with_capacity(3 * count);
+        for _ in 0..count {
+            let center = random_point(state, 20.);
+            for _ in 0..3 {
+                positions.push(center + random_point(state, 2.).to_vector());
+            }
+        }
+        let indices = (0..count as u32).map(|i| [3 * i, 3 * i + 1, 3 * i + 2]);
+        let mesh = Arc::new(TriangleMesh::new(positions, indices.collect()));
+        Triangle::from_mesh(&mesh).collect()
+    }
+
+    /// A ray from within a cube of side 30 about the origin, many of which miss the soup
+    pub(in crate::accel) fn random_ray(state: &mut u32) -> R {
+        R::new(
+            random_point(state, 30.),
+            random_point(state, 1.).to_vector(),
+        )
+    }
+
+    /// The closest hit of the ray with any of the primitives, tested one by one
+    pub(in crate::accel) fn brute_force(
+        primitives: &[impl Shape<f32, UnknownUnit>],
+        ray: &R,
+        t_max: f32,
+    ) -> Option<f32> {
+        primitives
+            .iter()
+            .filter_map(|p| p.intersect(ray, Time(t_max)))
+            .map(|hit| hit.t.0)
+            .min_by(f32::total_cmp)
+    }
+
+    fn check<A: Accelerator<f32, UnknownUnit, Primitive = S>>(
+        accel: &A,
+        ray: &R,
+        expected: Option<f32>,
+    ) {
+        let t_max = Time(f32::INFINITY);
+        let hit = accel.intersect(ray, t_max);
+        assert_eq!(hit.map(|hit| hit.t.0), expected);
+        assert_eq!(accel.intersect_any(ray, t_max), expected.is_some());
+        let mut stats = TraversalStats::default();
+        assert_eq!(accel.intersect_with_stats(ray, t_max, &mut stats), hit);
+        assert!(stats.rays <= 1);
+        assert!(hit.is_none() || stats.primitive_tests > 0 && stats.node_visits > 0);
+        if let Some(hit) = hit {
+            let primitive = &accel.primitives()[hit.primitive];
+            assert_eq!(primitive.intersect(ray, t_max).unwrap().t, hit.t);
+        }
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        let mut state = 0x1234_5678;
+        let mut point = |scale: f32| {
+            P::new(
+                (random(&mut state) - 0.5) * scale,
+                (random(&mut state) - 0.5) * scale,
+                (random(&mut state) - 0.5) * scale,
+            )
+        };
+
+        let spheres: Vec<_> = (0..5000)
+            .map(|_| S::new(point(20.), 0.1 + point(1.).x.abs()))
+            .collect();
+        let mut bvhs: Vec<_> = [SplitMethod::Median, SplitMethod::Sah]
+            .into_iter()
+            .map(|split_method| {
+                let options = BvhBuildOptions {
+                    split_method,
+                    ..Default::default()
+                };
+                Bvh::with_options(spheres.clone(), &options)
+            })
+            .collect();
+        bvhs.push(Bvh::new_linear(spheres.clone()));
+        let bvh4 = Bvh4::from(Bvh::new(spheres.clone()));
+        let bvh8 = Bvh8::from(Bvh::new(spheres.clone()));
+        let octrees: Vec<_> = [false, true]
+            .into_iter()
+            .map(|loose| {
+                let options = OctreeOptions {
+                    loose,
+                    ..Default::default()
+                };
+                Octree::with_options(spheres.clone(), &options)
+            })
+            .collect();
+        let grids: Vec<_> = [0, 2]
+            .into_iter()
+            .map(|max_depth| {
+                let options = GridOptions {
+                    max_depth,
+                    density: 1.,
+                    subgrid_threshold: 2,
+                    ..Default::default()
+                };
+                Grid::with_options(spheres.clone(), &options)
+            })
+            .collect();
+
+        for _ in 0..500 {
+            let ray = R::new(point(30.), point(1.).to_vector());
+            let t_max = Time(f32::INFINITY);
+            let expected = spheres
+                .iter()
+                .filter_map(|s| s.intersect(&ray, t_max))
+                .map(|hit| hit.t.0)
+                .min_by(f32::total_cmp);
+            for bvh in &bvhs {
+                check(bvh, &ray, expected);
+            }
+            check(&bvh4, &ray, expected);
+            check(&bvh8, &ray, expected);
+            for octree in &octrees {
+                check(octree, &ray, expected);
+            }
+            for grid in &grids {
+                check(grid, &ray, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bundle_matches_single_rays() {
+        let mut state = 0x9abc_def0;
+        let spheres: Vec<_> = (0..1000)
+            .map(|_| {
+                let mut r = || (random(&mut state) - 0.5) * 20.;
+                S::new(P::new(r(), r(), r()), 0.5)
+            })
+            .collect();
+        let bvh = Bvh::new(spheres.clone());
+        let octree = Octree::new(spheres);
+
+        for _ in 0..50 {
+            let origin = P::new(0., 0., -30.);
+            let mut rays = Vec::new();
+            for _ in 0..7 {
+                let (x, y) = (random(&mut state) - 0.5, random(&mut state) - 0.5);
+                let t_max = if random(&mut state) < 0.5 { 30. } else { 100. };
+                rays.push((R::new(origin, Vector3::new(x, y, 1.)), Time(t_max)));
+            }
+            let bundle = RayBundle::<_, _, 8>::new(rays.iter().copied());
+
+            let hits = bvh.intersect_bundle(&bundle);
+            let occluded = bvh.intersect_any_bundle(&bundle);
+            // The octree orders its primitives differently, and traces the rays one by one
+            let times = |hits: [Option<SurfaceInteraction<f32, UnknownUnit>>; 8]| {
+                hits.map(|hit| hit.map(|hit| hit.t))
+            };
+            assert_eq!(times(octree.intersect_bundle(&bundle)), times(hits));
+            assert_eq!(octree.intersect_any_bundle(&bundle), occluded);
+            for (lane, (ray, t_max)) in rays.iter().enumerate() {
+                assert_eq!(hits[lane], bvh.intersect(ray, *t_max));
+                assert_eq!(occluded[lane], bvh.intersect_any(ray, *t_max));
+            }
+            assert!(hits[7].is_none() && !occluded[7]);
+        }
+    }
+
+    #[test]
+    fn test_sah_matches_median() {
+        let mut state = 0x2468_ace0;
+        let triangles = triangle_soup(&mut state, 2000);
+        let build = |split_method| {
+            let options = BvhBuildOptions {
+                split_method,
+                ..Default::default()
+            };
+            Bvh::with_options(triangles.clone(), &options)
+        };
+        let (median, sah) = (build(SplitMethod::Median), build(SplitMethod::Sah));
+        assert_ne!(median.nodes(), sah.nodes());
+
+        let mut hits = 0;
+        for _ in 0..1000 {
+            let ray = random_ray(&mut state);
+            let t_max = Time(f32::INFINITY);
+            let (a, b) = (median.intersect(&ray, t_max), sah.intersect(&ray, t_max));
+            assert_eq!(a.map(|hit| hit.t), b.map(|hit| hit.t));
+            assert_eq!(a.map(|hit| hit.p), b.map(|hit| hit.p));
+            if let (Some(a), Some(b)) = (a, b) {
+                let index = |bvh: &Bvh<_, _, Triangle<_, _>>, i: usize| bvh.primitives()[i].index;
+                assert_eq!(index(&median, a.primitive), index(&sah, b.primitive));
+                hits += 1;
+            }
+            assert_eq!(
+                a.map(|hit| hit.t.0),
+                brute_force(&triangles, &ray, f32::INFINITY)
+            );
+        }
+        assert!(hits > 100 && hits < 900, "{hits}");
+    }
+}
@@ -0,0 +1,360 @@
+use crate::{
+    accel::{
+        bvh::{BvhNode, STACK_SIZE},
+        Accelerator, Bvh, BvhBuildOptions, SplitMethod,
+    },
+    core::geometry::{Axis3, Box3, Point3},
+    shape::Shape,
+};
+use num_traits::Float;
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+const MAGIC: [u8; 4] = *b"RBVH";
+const VERSION: u32 = 2;
+
+/// Why a serialized BVH could not be loaded
+#[derive(Debug)]
+pub enum BvhCacheError {
+    Io(io::Error),
+    /// The data is not a serialized BVH, or is corrupt
+    InvalidFormat,
+    /// The data was written by an incompatible version
+    UnsupportedVersion(u32),
+    /// The BVH was built over different primitives, or with different options
+    SourceMismatch,
+}
+
+impl fmt::Display for BvhCacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "failed to read BVH: {e}"),
+            Self::InvalidFormat => f.write_str("invalid BVH data"),
+            Self::UnsupportedVersion(v) => write!(f, "unsupported BVH format version {v}"),
+            Self::SourceMismatch => f.write_str("BVH was built over different primitives"),
+        }
+    }
+}
+
+impl std::error::Error for BvhCacheError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for BvhCacheError {
+    #[inline]
+    fn from(e: io::Error) -> Self {
+        if e.kind() == io::ErrorKind::UnexpectedEof {
+            Self::InvalidFormat
+        } else {
+            Self::Io(e)
+        }
+    }
+}
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// Continues an FNV-1a hash over the bytes
+fn fnv1a(hash: u64, bytes: impl IntoIterator<Item = u8>) -> u64 {
+    bytes.into_iter().fold(hash, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Hashes the bounds of the primitives
+///
+/// The hierarchy only depends on the primitive bounds, so changes to the primitives that keep
+/// them intact don't invalidate it.
+fn source_hash<T: Float, U>(bounds: impl Iterator<Item = Box3<T, U>>) -> u64 {
+    bounds.fold(FNV_OFFSET, |hash, b| {
+        [b.min.x, b.min.y, b.min.z, b.max.x, b.max.y, b.max.z]
+            .iter()
+            .fold(hash, |hash, x| {
+                fnv1a(hash, x.to_f64().unwrap_or(f64::NAN).to_le_bytes())
+            })
+    })
+}
+
+/// Hashes build options, whose hash is recorded as zero for hierarchies written without them
+fn options_hash(options: &BvhBuildOptions) -> u64 {
+    let split = match options.split_method {
+        SplitMethod::Median => 0,
+        SplitMethod::Sah => 1,
+    };
+    let hash = fnv1a(FNV_OFFSET, [split]);
+    let hash = fnv1a(hash, (options.max_leaf_size as u64).to_le_bytes());
+    let hash = fnv1a(hash, (options.sah_bins as u64).to_le_bytes());
+    fnv1a(hash, options.traversal_cost.to_le_bytes())
+}
+
+impl<T: Float, U, P: Shape<T, U>> Bvh<T, U, P> {
+    /// Writes the hierarchy and the order of its primitives to a compact binary format
+    ///
+    /// The primitives themselves are not written, and must be supplied again when reading.
+    /// Neither are the options the hierarchy was built with, so
+    /// [`load_or_build`](Self::load_or_build) rebuilds rather than loads it.
+    pub fn write_to(&self, writer: impl Write) -> io::Result<()> {
+        self.write(writer, 0)
+    }
+
+    /// Writes the hierarchy, with the hash of the options it was built with
+    fn write(&self, writer: impl Write, options: u64) -> io::Result<()> {
+        let mut w = BufWriter::new(writer);
+        w.write_all(&MAGIC)?;
+        w.write_all(&VERSION.to_le_bytes())?;
+        let hash = source_hash(self.primitives().iter().map(Shape::bounds));
+        w.write_all(&hash.to_le_bytes())?;
+        w.write_all(&options.to_le_bytes())?;
+        w.write_all(&(self.order().len() as u32).to_le_bytes())?;
+        w.write_all(&(self.nodes().len() as u32).to_le_bytes())?;
+        for node in self.nodes() {
+            let b = &node.bounds;
+            for x in [b.min.x, b.min.y, b.min.z, b.max.x, b.max.y, b.max.z] {
+                w.write_all(&x.to_f64().unwrap_or(f64::NAN).to_le_bytes())?;
+            }
+            w.write_all(&node.offset.to_le_bytes())?;
+            w.write_all(&node.count.to_le_bytes())?;
+            w.write_all(&[node.axis as u8])?;
+        }
+        for &i in self.order() {
+            w.write_all(&i.to_le_bytes())?;
+        }
+        w.flush()
+    }
+
+    /// Reads a hierarchy written by [`write_to`](Self::write_to) over the same primitives, in
+    /// their original order
+    pub fn read_from(primitives: Vec<P>, reader: impl Read) -> Result<Self, BvhCacheError> {
+        let (nodes, order, _) = read(&primitives, reader)?;
+        Ok(Self::from_parts(primitives, nodes, order))
+    }
+
+    /// Loads the hierarchy cached at `path`, or builds it and writes the cache if it is missing
+    /// or was built over different primitives or with different options
+    ///
+    /// Failing to write the cache is not an error, since it is only an optimization.
+    pub fn load_or_build(
+        primitives: Vec<P>,
+        path: impl AsRef<Path>,
+        options: &BvhBuildOptions,
+    ) -> Self {
+        let path = path.as_ref();
+        let hash = options_hash(options);
+        let cached = File::open(path)
+            .map_err(BvhCacheError::from)
+            .and_then(|file| read(&primitives, BufReader::new(file)));
+        if let Ok((nodes, order, built_with)) = cached {
+            if built_with == hash {
+                return Self::from_parts(primitives, nodes, order);
+            }
+        }
+
+        let bvh = Self::with_options(primitives, options);
+        if let Ok(file) = File::create(path) {
+            let _ = bvh.write(file, hash);
+        }
+        bvh
+    }
+}
+
+struct Reader<R> {
+    reader: R,
+}
+
+impl<R: Read> Reader<R> {
+    fn bytes<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        let mut bytes = [0; N];
+        self.reader.read_exact(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        self.bytes().map(u32::from_le_bytes)
+    }
+
+    fn scalar<T: Float>(&mut self) -> Result<T, BvhCacheError> {
+        let x = f64::from_le_bytes(self.bytes()?);
+        T::from(x).ok_or(BvhCacheError::InvalidFormat)
+    }
+
+    fn point<T: Float, U>(&mut self) -> Result<Point3<T, U>, BvhCacheError> {
+        Ok(Point3::new(self.scalar()?, self.scalar()?, self.scalar()?))
+    }
+}
+
+/// The nodes of a hierarchy, the original index of each primitive in its final order and the
+/// hash of the options it was built with
+type Parts<T, U> = (Vec<BvhNode<T, U>>, Vec<u32>, u64);
+
+/// Reads and validates the nodes and primitive order of a serialized hierarchy
+fn read<T: Float, U, P: Shape<T, U>>(
+    primitives: &[P],
+    reader: impl Read,
+) -> Result<Parts<T, U>, BvhCacheError> {
+    let mut r = Reader { reader };
+    if r.bytes()? != MAGIC {
+        return Err(BvhCacheError::InvalidFormat);
+    }
+    let version = r.u32()?;
+    if version != VERSION {
+        return Err(BvhCacheError::UnsupportedVersion(version));
+    }
+    let hash = u64::from_le_bytes(r.bytes()?);
+    let options = u64::from_le_bytes(r.bytes()?);
+    let primitive_count = r.u32()? as usize;
+    if primitive_count != primitives.len() {
+        return Err(BvhCacheError::SourceMismatch);
+    }
+    let node_count = r.u32()? as usize;
+    // Every primitive is in at most one leaf, so larger counts can only come from corrupt data
+    if node_count > 2 * primitive_count {
+        return Err(BvhCacheError::InvalidFormat);
+    }
+
+    let mut nodes = Vec::with_capacity(node_count);
+    for _ in 0..node_count {
+        let bounds = Box3::new(r.point()?, r.point()?);
+        let offset = r.u32()?;
+        let count = r.u32()?;
+        let [axis] = r.bytes()?;
+        let axis = *Axis3::AXES
+            .get(usize::from(axis))
+            .ok_or(BvhCacheError::InvalidFormat)?;
+        nodes.push(BvhNode {
+            bounds,
+            offset,
+            count,
+            axis,
+        });
+    }
+    if !nodes.is_empty() && validate_subtree(&nodes, 0, 0, primitive_count) != Some(node_count) {
+        return Err(BvhCacheError::InvalidFormat);
+    }
+
+    let mut order = Vec::with_capacity(primitive_count);
+    let mut seen = vec![false; primitive_count];
+    for _ in 0..primitive_count {
+        let i = r.u32()?;
+        match seen.get_mut(i as usize) {
+            Some(seen @ false) => *seen = true,
+            _ => return Err(BvhCacheError::InvalidFormat),
+        }
+        order.push(i);
+    }
+
+    let bounds = order.iter().map(|&i| primitives[i as usize].bounds());
+    if source_hash(bounds) != hash {
+        return Err(BvhCacheError::SourceMismatch);
+    }
+    Ok((nodes, order, options))
+}
+
+/// Checks that the subtree at `index` is laid out depth-first and fits the traversal stack,
+/// returning the index following it
+fn validate_subtree<T, U>(
+    nodes: &[BvhNode<T, U>],
+    index: usize,
+    depth: usize,
+    primitive_count: usize,
+) -> Option<usize> {
+    let node = nodes.get(index)?;
+    if depth >= STACK_SIZE {
+        return None;
+    }
+    if node.is_leaf() {
+        let end = node.offset as usize + node.count as usize;
+        return (end <= primitive_count).then_some(index + 1);
+    }
+    let second = validate_subtree(nodes, index + 1, depth + 1, primitive_count)?;
+    if node.offset as usize != second {
+        return None;
+    }
+    validate_subtree(nodes, second, depth + 1, primitive_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::{
+            geometry::{Ray, UnknownUnit, Vector3},
+            units::Time,
+        },
+        shape::Sphere,
+    };
+
+    type S = Sphere<f32, UnknownUnit>;
+
+    #[test]
+    fn test_round_trip() {
+        let spheres: Vec<_> = (0..100)
+            .map(|i| {
+                let x = i as f32;
+                S::new(
+                    Point3::new(x, (x * 0.7).sin() * 10., (x * 1.3).cos() * 10.),
+                    0.5,
+                )
+            })
+            .collect();
+        let bvh = Bvh::new(spheres.clone());
+        let mut bytes = Vec::new();
+        bvh.write_to(&mut bytes).unwrap();
+
+        let loaded = Bvh::read_from(spheres.clone(), bytes.as_slice()).unwrap();
+        assert_eq!(loaded.nodes(), bvh.nodes());
+        assert_eq!(loaded.order(), bvh.order());
+        let ray = Ray::new(Point3::new(-10., 0., 0.), Vector3::new(1., 0.05, 0.));
+        assert_eq!(
+            loaded.intersect(&ray, Time(f32::INFINITY)),
+            bvh.intersect(&ray, Time(f32::INFINITY)),
+        );
+
+        let mut moved = spheres.clone();
+        moved[3].radius = 2.;
+        assert!(matches!(
+            Bvh::read_from(moved, bytes.as_slice()),
+            Err(BvhCacheError::SourceMismatch)
+        ));
+        assert!(matches!(
+            Bvh::read_from(spheres.clone(), &bytes[..bytes.len() - 1]),
+            Err(BvhCacheError::InvalidFormat)
+        ));
+        bytes[4] = 1;
+        assert!(matches!(
+            Bvh::read_from(spheres, bytes.as_slice()),
+            Err(BvhCacheError::UnsupportedVersion(1))
+        ));
+    }
+
+    #[test]
+    fn test_load_or_build() {
+        let spheres: Vec<_> = (0..20)
+            .map(|i| S::new(Point3::new(i as f32, 0., 0.), 0.5))
+            .collect();
+        let path = std::env::temp_dir().join(format!("rt3-bvh-cache-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let median = BvhBuildOptions {
+            split_method: SplitMethod::Median,
+            max_leaf_size: 1,
+            ..BvhBuildOptions::default()
+        };
+        let sah = BvhBuildOptions::default();
+
+        let built = Bvh::load_or_build(spheres.clone(), &path, &median);
+        let loaded = Bvh::load_or_build(spheres.clone(), &path, &median);
+        assert_eq!(loaded.nodes(), built.nodes());
+        // Other options rebuild the hierarchy, rather than reusing the one built with these
+        let rebuilt = Bvh::load_or_build(spheres.clone(), &path, &sah);
+        assert_eq!(rebuilt.nodes(), Bvh::with_options(spheres, &sah).nodes());
+        assert_ne!(rebuilt.nodes(), built.nodes());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
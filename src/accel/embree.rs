@@ -0,0 +1,413 @@
+//! An accelerator backed by Embree 4, which must be installed for the `embree` feature to link
+//!
+//! Primitives are registered as Embree user geometry, so any [`Shape`] can be used while
+//! Embree builds and traverses the hierarchy.
+
+use crate::{
+    accel::{Accelerator, TraversalStats},
+    core::{
+        geometry::{Box3, Point3, Ray, Vector3},
+        units::Time,
+    },
+    shape::{Shape, SurfaceInteraction},
+};
+use std::{ffi::c_void, fmt, marker::PhantomData, ptr};
+
+mod sys {
+    #![allow(non_camel_case_types, non_snake_case)]
+
+    use std::{ffi::c_void, os::raw::c_int};
+
+    pub type RTCDevice = *mut c_void;
+    pub type RTCScene = *mut c_void;
+    pub type RTCGeometry = *mut c_void;
+
+    pub const RTC_GEOMETRY_TYPE_USER: u32 = 120;
+    pub const RTC_INVALID_GEOMETRY_ID: u32 = u32::MAX;
+
+    #[repr(C, align(16))]
+    pub struct RTCBounds {
+        pub lower_x: f32,
+        pub lower_y: f32,
+        pub lower_z: f32,
+        pub align0: f32,
+        pub upper_x: f32,
+        pub upper_y: f32,
+        pub upper_z: f32,
+        pub align1: f32,
+    }
+
+    #[repr(C, align(16))]
+    pub struct RTCRay {
+        pub org_x: f32,
+        pub org_y: f32,
+        pub org_z: f32,
+        pub tnear: f32,
+        pub dir_x: f32,
+        pub dir_y: f32,
+        pub dir_z: f32,
+        pub time: f32,
+        pub tfar: f32,
+        pub mask: u32,
+        pub id: u32,
+        pub flags: u32,
+    }
+
+    #[repr(C, align(16))]
+    pub struct RTCHit {
+        pub Ng_x: f32,
+        pub Ng_y: f32,
+        pub Ng_z: f32,
+        pub u: f32,
+        pub v: f32,
+        pub primID: u32,
+        pub geomID: u32,
+        pub instID: [u32; 1],
+        pub instPrimID: [u32; 1],
+    }
+
+    #[repr(C, align(16))]
+    pub struct RTCRayHit {
+        pub ray: RTCRay,
+        pub hit: RTCHit,
+    }
+
+    #[repr(C)]
+    pub struct RTCBoundsFunctionArguments {
+        pub geometryUserPtr: *mut c_void,
+        pub primID: u32,
+        pub timeStep: u32,
+        pub bounds_o: *mut RTCBounds,
+    }
+
+    #[repr(C)]
+    pub struct RTCIntersectFunctionNArguments {
+        pub valid: *mut c_int,
+        pub geometryUserPtr: *mut c_void,
+        pub primID: u32,
+        pub context: *mut c_void,
+        pub rayhit: *mut RTCRayHit,
+        pub N: u32,
+        pub geomID: u32,
+    }
+
+    #[repr(C)]
+    pub struct RTCOccludedFunctionNArguments {
+        pub valid: *mut c_int,
+        pub geometryUserPtr: *mut c_void,
+        pub primID: u32,
+        pub context: *mut c_void,
+        pub ray: *mut RTCRay,
+        pub N: u32,
+        pub geomID: u32,
+    }
+
+    pub type RTCBoundsFunction = unsafe extern "C" fn(*const RTCBoundsFunctionArguments);
+    pub type RTCIntersectFunctionN = unsafe extern "C" fn(*const RTCIntersectFunctionNArguments);
+    pub type RTCOccludedFunctionN = unsafe extern "C" fn(*const RTCOccludedFunctionNArguments);
+
+    #[link(name = "embree4")]
+    extern "C" {
+        pub fn rtcNewDevice(config: *const i8) -> RTCDevice;
+        pub fn rtcGetDeviceError(device: RTCDevice) -> u32;
+        pub fn rtcReleaseDevice(device: RTCDevice);
+        pub fn rtcNewScene(device: RTCDevice) -> RTCScene;
+        pub fn rtcCommitScene(scene: RTCScene);
+        pub fn rtcReleaseScene(scene: RTCScene);
+        pub fn rtcNewGeometry(device: RTCDevice, ty: u32) -> RTCGeometry;
+        pub fn rtcSetGeometryUserPrimitiveCount(geometry: RTCGeometry, count: u32);
+        pub fn rtcSetGeometryUserData(geometry: RTCGeometry, ptr: *mut c_void);
+        pub fn rtcSetGeometryBoundsFunction(
+            geometry: RTCGeometry,
+            bounds: RTCBoundsFunction,
+            user_ptr: *mut c_void,
+        );
+        pub fn rtcSetGeometryIntersectFunction(
+            geometry: RTCGeometry,
+            intersect: RTCIntersectFunctionN,
+        );
+        pub fn rtcSetGeometryOccludedFunction(
+            geometry: RTCGeometry,
+            occluded: RTCOccludedFunctionN,
+        );
+        pub fn rtcCommitGeometry(geometry: RTCGeometry);
+        pub fn rtcAttachGeometry(scene: RTCScene, geometry: RTCGeometry) -> u32;
+        pub fn rtcReleaseGeometry(geometry: RTCGeometry);
+        pub fn rtcIntersect1(scene: RTCScene, rayhit: *mut RTCRayHit, args: *mut c_void);
+        pub fn rtcOccluded1(scene: RTCScene, ray: *mut RTCRay, args: *mut c_void);
+    }
+}
+
+/// An error code reported by Embree
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct EmbreeError(pub u32);
+
+impl fmt::Display for EmbreeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Embree error {}", self.0)
+    }
+}
+
+impl std::error::Error for EmbreeError {}
+
+/// A set of primitives whose hierarchy is built and traversed by Embree
+///
+/// Only `f32` is supported, as by Embree itself. Primitives keep their order, which the
+/// `primitive` index of the reported hits refers to.
+pub struct EmbreeScene<U, P> {
+    device: sys::RTCDevice,
+    scene: sys::RTCScene,
+    /// Embree holds a pointer to the elements, which stay in place as they are never modified
+    primitives: Vec<P>,
+    bounds: Box3<f32, U>,
+    _unit: PhantomData<U>,
+}
+
+// Committed scenes can be queried from any thread, and the callbacks only read the primitives
+unsafe impl<U, P: Send + Sync> Send for EmbreeScene<U, P> {}
+unsafe impl<U, P: Sync> Sync for EmbreeScene<U, P> {}
+
+impl<U, P: Shape<f32, U> + Sync> EmbreeScene<U, P> {
+    /// Builds the scene on a new Embree device with the default configuration
+    pub fn new(primitives: Vec<P>) -> Result<Self, EmbreeError> {
+        let bounds = primitives
+            .iter()
+            .fold(Box3::empty(), |bounds, p| bounds.union(&p.bounds()));
+
+        unsafe {
+            let device = sys::rtcNewDevice(ptr::null());
+            if device.is_null() {
+                return Err(EmbreeError(sys::rtcGetDeviceError(ptr::null_mut())));
+            }
+            let scene = sys::rtcNewScene(device);
+            let geometry = sys::rtcNewGeometry(device, sys::RTC_GEOMETRY_TYPE_USER);
+            if scene.is_null() || geometry.is_null() {
+                let error = EmbreeError(sys::rtcGetDeviceError(device));
+                if !scene.is_null() {
+                    sys::rtcReleaseScene(scene);
+                }
+                sys::rtcReleaseDevice(device);
+                return Err(error);
+            }
+
+            let user_ptr = primitives.as_ptr() as *mut c_void;
+            sys::rtcSetGeometryUserPrimitiveCount(geometry, primitives.len() as u32);
+            sys::rtcSetGeometryUserData(geometry, user_ptr);
+            sys::rtcSetGeometryBoundsFunction(geometry, bounds_fn::<U, P>, user_ptr);
+            sys::rtcSetGeometryIntersectFunction(geometry, intersect_fn::<U, P>);
+            sys::rtcSetGeometryOccludedFunction(geometry, occluded_fn::<U, P>);
+            sys::rtcCommitGeometry(geometry);
+            sys::rtcAttachGeometry(scene, geometry);
+            // The scene keeps its own reference
+            sys::rtcReleaseGeometry(geometry);
+            sys::rtcCommitScene(scene);
+
+            let error = sys::rtcGetDeviceError(device);
+            let scene = Self {
+                device,
+                scene,
+                primitives,
+                bounds,
+                _unit: PhantomData,
+            };
+            if error != 0 {
+                return Err(EmbreeError(error));
+            }
+            Ok(scene)
+        }
+    }
+}
+
+impl<U, P> Drop for EmbreeScene<U, P> {
+    fn drop(&mut self) {
+        unsafe {
+            sys::rtcReleaseScene(self.scene);
+            sys::rtcReleaseDevice(self.device);
+        }
+    }
+}
+
+fn to_ray<U>(ray: &sys::RTCRay) -> Ray<f32, U> {
+    Ray::new(
+        Point3::new(ray.org_x, ray.org_y, ray.org_z),
+        Vector3::new(ray.dir_x, ray.dir_y, ray.dir_z),
+    )
+}
+
+fn from_ray<U>(ray: &Ray<f32, U>, t_max: Time<f32>) -> sys::RTCRay {
+    sys::RTCRay {
+        org_x: ray.origin.x,
+        org_y: ray.origin.y,
+        org_z: ray.origin.z,
+        tnear: 0.,
+        dir_x: ray.dir.x,
+        dir_y: ray.dir.y,
+        dir_z: ray.dir.z,
+        time: ray.time,
+        tfar: t_max.0,
+        mask: u32::MAX,
+        id: 0,
+        flags: 0,
+    }
+}
+
+unsafe extern "C" fn bounds_fn<U, P: Shape<f32, U>>(args: *const sys::RTCBoundsFunctionArguments) {
+    let args = &*args;
+    let primitive = &*(args.geometryUserPtr as *const P).add(args.primID as usize);
+    let b = primitive.bounds();
+    *args.bounds_o = sys::RTCBounds {
+        lower_x: b.min.x,
+        lower_y: b.min.y,
+        lower_z: b.min.z,
+        align0: 0.,
+        upper_x: b.max.x,
+        upper_y: b.max.y,
+        upper_z: b.max.z,
+        align1: 0.,
+    };
+}
+
+/// Only single rays are traced, for which Embree passes `N == 1` and the plain ray layouts
+unsafe extern "C" fn intersect_fn<U, P: Shape<f32, U>>(
+    args: *const sys::RTCIntersectFunctionNArguments,
+) {
+    let args = &*args;
+    if args.N != 1 || *args.valid == 0 {
+        return;
+    }
+    let primitive = &*(args.geometryUserPtr as *const P).add(args.primID as usize);
+    let rayhit = &mut *args.rayhit;
+    let ray = to_ray::<U>(&rayhit.ray);
+    if let Some(hit) = primitive.intersect(&ray, Time(rayhit.ray.tfar)) {
+        rayhit.ray.tfar = hit.t.0;
+        rayhit.hit.primID = args.primID;
+        rayhit.hit.geomID = args.geomID;
+    }
+}
+
+unsafe extern "C" fn occluded_fn<U, P: Shape<f32, U>>(
+    args: *const sys::RTCOccludedFunctionNArguments,
+) {
+    let args = &*args;
+    if args.N != 1 || *args.valid == 0 {
+        return;
+    }
+    let primitive = &*(args.geometryUserPtr as *const P).add(args.primID as usize);
+    let ray = &mut *args.ray;
+    if primitive.intersect_any(&to_ray::<U>(ray), Time(ray.tfar)) {
+        ray.tfar = f32::NEG_INFINITY;
+    }
+}
+
+impl<U, P: Shape<f32, U>> Shape<f32, U> for EmbreeScene<U, P> {
+    #[inline]
+    fn bounds(&self) -> Box3<f32, U> {
+        self.bounds
+    }
+
+    fn intersect(&self, ray: &Ray<f32, U>, t_max: Time<f32>) -> Option<SurfaceInteraction<f32, U>> {
+        let mut rayhit = sys::RTCRayHit {
+            ray: from_ray(ray, t_max),
+            hit: sys::RTCHit {
+                Ng_x: 0.,
+                Ng_y: 0.,
+                Ng_z: 0.,
+                u: 0.,
+                v: 0.,
+                primID: sys::RTC_INVALID_GEOMETRY_ID,
+                geomID: sys::RTC_INVALID_GEOMETRY_ID,
+                instID: [sys::RTC_INVALID_GEOMETRY_ID],
+                instPrimID: [sys::RTC_INVALID_GEOMETRY_ID],
+            },
+        };
+        unsafe { sys::rtcIntersect1(self.scene, &mut rayhit, ptr::null_mut()) };
+        if rayhit.hit.geomID == sys::RTC_INVALID_GEOMETRY_ID {
+            return None;
+        }
+
+        // Embree only keeps the distance, so the interaction is recomputed for the closest hit
+        let index = rayhit.hit.primID as usize;
+        let mut hit = self.primitives[index].intersect(ray, Time(rayhit.ray.tfar))?;
+        hit.primitive = index;
+        Some(hit)
+    }
+
+    fn intersect_any(&self, ray: &Ray<f32, U>, t_max: Time<f32>) -> bool {
+        let mut ray = from_ray(ray, t_max);
+        unsafe { sys::rtcOccluded1(self.scene, &mut ray, ptr::null_mut()) };
+        ray.tfar == f32::NEG_INFINITY
+    }
+}
+
+impl<U, P: Shape<f32, U>> Accelerator<f32, U> for EmbreeScene<U, P> {
+    type Primitive = P;
+
+    #[inline]
+    fn primitives(&self) -> &[P] {
+        &self.primitives
+    }
+
+    /// Embree does not expose its traversal, so only rays are counted
+    fn intersect_with_stats(
+        &self,
+        ray: &Ray<f32, U>,
+        t_max: Time<f32>,
+        stats: &mut TraversalStats,
+    ) -> Option<SurfaceInteraction<f32, U>> {
+        stats.rays += 1;
+        self.intersect(ray, t_max)
+    }
+
+    fn intersect_any_with_stats(
+        &self,
+        ray: &Ray<f32, U>,
+        t_max: Time<f32>,
+        stats: &mut TraversalStats,
+    ) -> bool {
+        stats.rays += 1;
+        self.intersect_any(ray, t_max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accel::{
+        bvh::tests::{random, random_ray, triangle_soup},
+        Bvh,
+    };
+
+    #[test]
+    fn test_matches_bvh() {
+        let mut state = 0x3eb_bee5;
+        let triangles = triangle_soup(&mut state, 2000);
+        let bvh = Bvh::new(triangles.clone());
+        let embree = EmbreeScene::new(triangles).unwrap();
+        assert_eq!(embree.bounds(), bvh.bounds());
+
+        let mut hits = 0;
+        for _ in 0..1000 {
+            let ray = random_ray(&mut state);
+            let t_max = Time(if random(&mut state) < 0.3 {
+                10.
+            } else {
+                f32::INFINITY
+            });
+            let hit = embree.intersect(&ray, t_max);
+            let expected = bvh.intersect(&ray, t_max);
+            assert_eq!(hit.map(|hit| hit.t), expected.map(|hit| hit.t));
+            // Embree keeps the order of the primitives, unlike the BVH
+            if let (Some(hit), Some(expected)) = (hit, expected) {
+                let index = embree.primitives()[hit.primitive].index;
+                assert_eq!(index, bvh.primitives()[expected.primitive].index);
+                hits += 1;
+            }
+            assert_eq!(embree.intersect_any(&ray, t_max), expected.is_some());
+
+            let mut stats = TraversalStats::default();
+            assert_eq!(embree.intersect_with_stats(&ray, t_max, &mut stats), hit);
+            assert_eq!(stats.rays, 1);
+        }
+        assert!(hits > 100 && hits < 900, "{hits}");
+    }
+}
@@ -0,0 +1,545 @@
+//! An accelerator tracing batches of rays in compute shaders through wgpu, with the `gpu`
+//! feature
+//!
+//! Only the traversal runs on the GPU: the hierarchy is the [`Bvh`] of the CPU path, whose
+//! nodes and triangles are uploaded as they are and traversed by the shader in `gpu.wgsl` to
+//! find the triangle each ray hits. Hits are then verified and completed on the CPU by the
+//! primitives that were hit. Shading on the GPU is out of scope: it stays on the CPU, so that
+//! it sees the same interactions, materials and lights as with any other accelerator.
+
+use crate::{
+    accel::{Accelerator, Bvh, TraversalStats},
+    core::{
+        geometry::{Box3, Ray},
+        units::Time,
+    },
+    shape::{Shape, SurfaceInteraction},
+};
+use num_traits::Float;
+use rayon::prelude::*;
+use std::{
+    fmt,
+    future::Future,
+    pin::pin,
+    sync::{mpsc, Arc},
+    task::{Context, Poll, Wake, Waker},
+    thread::{self, Thread},
+};
+use wgpu::util::DeviceExt;
+
+/// The number of rays traced by each dispatch, which keeps it within the workgroup limits
+const MAX_RAYS: usize = 1 << 20;
+const WORKGROUP_SIZE: usize = 64;
+/// The triangle index of the rays the shader found no hit for
+const MISS: u32 = u32::MAX;
+
+/// A [`Bvh`] over triangles whose batches of rays are traced on a GPU
+///
+/// Primitives are uploaded by their [`Shape::triangle`]. The CPU traverses the same hierarchy
+/// for single rays and bundles, and for batches too when there is no GPU, some primitives are
+/// not triangles, such as other shapes or triangles with cutouts, or the GPU fails to trace
+/// them, so that the accelerator works everywhere. The GPU traces in `f32` whatever the
+/// precision of `T`, and the hits it finds are checked again by the primitives in `T`. Only
+/// the traversal is done on the GPU, not the shading of the hits.
+pub struct GpuBvh<T, U, P> {
+    bvh: Bvh<T, U, P>,
+    gpu: Option<Gpu>,
+}
+
+impl<T, U, P> fmt::Debug for GpuBvh<T, U, P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GpuBvh")
+            .field("gpu", &self.gpu.as_ref().map(|gpu| &gpu.adapter))
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Float, U, P: Shape<T, U>> GpuBvh<T, U, P> {
+    /// Uploads the hierarchy to the default GPU, or keeps it on the CPU if there is none or it
+    /// cannot be traced there
+    #[must_use]
+    pub fn new(bvh: Bvh<T, U, P>) -> Self {
+        let gpu = Gpu::new(&bvh);
+        Self { bvh, gpu }
+    }
+
+    /// Keeps the hierarchy on the CPU
+    #[inline]
+    #[must_use]
+    pub fn cpu(bvh: Bvh<T, U, P>) -> Self {
+        Self { bvh, gpu: None }
+    }
+
+    /// Returns whether batches are traced on a GPU
+    #[inline]
+    #[must_use]
+    pub fn is_gpu(&self) -> bool {
+        self.gpu.is_some()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn bvh(&self) -> &Bvh<T, U, P> {
+        &self.bvh
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn into_bvh(self) -> Bvh<T, U, P> {
+        self.bvh
+    }
+}
+
+impl<T: Float, U, P: Shape<T, U>> Shape<T, U> for GpuBvh<T, U, P> {
+    #[inline]
+    fn bounds(&self) -> Box3<T, U> {
+        self.bvh.bounds()
+    }
+
+    #[inline]
+    fn intersect(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<SurfaceInteraction<T, U>> {
+        self.bvh.intersect(ray, t_max)
+    }
+
+    #[inline]
+    fn intersect_any(&self, ray: &Ray<T, U>, t_max: Time<T>) -> bool {
+        self.bvh.intersect_any(ray, t_max)
+    }
+}
+
+impl<T, U, P> Accelerator<T, U> for GpuBvh<T, U, P>
+where
+    T: Float + Send + Sync,
+    U: Send + Sync,
+    P: Shape<T, U> + Sync,
+{
+    type Primitive = P;
+
+    #[inline]
+    fn primitives(&self) -> &[P] {
+        self.bvh.primitives()
+    }
+
+    #[inline]
+    fn intersect_with_stats(
+        &self,
+        ray: &Ray<T, U>,
+        t_max: Time<T>,
+        stats: &mut TraversalStats,
+    ) -> Option<SurfaceInteraction<T, U>> {
+        self.bvh.intersect_with_stats(ray, t_max, stats)
+    }
+
+    #[inline]
+    fn intersect_any_with_stats(
+        &self,
+        ray: &Ray<T, U>,
+        t_max: Time<T>,
+        stats: &mut TraversalStats,
+    ) -> bool {
+        self.bvh.intersect_any_with_stats(ray, t_max, stats)
+    }
+
+    /// Traces the rays on the GPU, or in parallel on the CPU without one
+    fn intersect_batch(
+        &self,
+        rays: &[(Ray<T, U>, Time<T>)],
+    ) -> Vec<Option<SurfaceInteraction<T, U>>> {
+        let Some(hits) = self.gpu.as_ref().and_then(|gpu| gpu.trace(rays, false)) else {
+            return rays
+                .par_iter()
+                .map(|(ray, t_max)| self.bvh.intersect(ray, *t_max))
+                .collect();
+        };
+        rays.par_iter()
+            .zip(hits)
+            .map(|((ray, t_max), (_, triangle))| {
+                if triangle == MISS {
+                    return None;
+                }
+                let i = triangle as usize;
+                // The triangle may be missed in the precision of `T` when the ray grazes it
+                match self.bvh.primitives()[i].intersect(ray, *t_max) {
+                    Some(mut hit) => {
+                        hit.primitive = i;
+                        Some(hit)
+                    }
+                    None => self.bvh.intersect(ray, *t_max),
+                }
+            })
+            .collect()
+    }
+
+    /// Traces the rays on the GPU, or in parallel on the CPU without one
+    fn intersect_any_batch(&self, rays: &[(Ray<T, U>, Time<T>)]) -> Vec<bool> {
+        let Some(hits) = self.gpu.as_ref().and_then(|gpu| gpu.trace(rays, true)) else {
+            return rays
+                .par_iter()
+                .map(|(ray, t_max)| self.bvh.intersect_any(ray, *t_max))
+                .collect();
+        };
+        rays.par_iter()
+            .zip(hits)
+            .map(|((ray, t_max), (_, triangle))| {
+                // The triangle may be missed in the precision of `T` when the ray grazes it
+                triangle != MISS
+                    && (self.bvh.primitives()[triangle as usize].intersect_any(ray, *t_max)
+                        || self.bvh.intersect_any(ray, *t_max))
+            })
+            .collect()
+    }
+}
+
+/// The device holding a hierarchy, and the pipelines tracing rays through it
+struct Gpu {
+    adapter: wgpu::AdapterInfo,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    layout: wgpu::BindGroupLayout,
+    closest: wgpu::ComputePipeline,
+    occluded: wgpu::ComputePipeline,
+    nodes: wgpu::Buffer,
+    vertices: wgpu::Buffer,
+}
+
+impl Gpu {
+    /// Uploads the hierarchy to the default adapter, unless there is none, the hierarchy is
+    /// empty, a primitive is not a triangle or the buffers exceed the limits of the adapter
+    fn new<T: Float, U, P: Shape<T, U>>(bvh: &Bvh<T, U, P>) -> Option<Self> {
+        if bvh.nodes().is_empty() {
+            return None;
+        }
+        let mut vertices = Vec::with_capacity(bvh.primitives().len() * 48);
+        for primitive in bvh.primitives() {
+            for p in primitive.triangle()? {
+                for x in [p.x, p.y, p.z, T::zero()] {
+                    vertices.extend_from_slice(&x.to_f32()?.to_le_bytes());
+                }
+            }
+        }
+        let mut nodes = Vec::with_capacity(bvh.nodes().len() * 32);
+        for node in bvh.nodes() {
+            let (min, max) = (node.bounds.min, node.bounds.max);
+            // Rounded outwards so that the boxes still contain their triangles
+            let lower = |x: T| x.to_f32().map_or(f32::NEG_INFINITY, f32::next_down);
+            let upper = |x: T| x.to_f32().map_or(f32::INFINITY, f32::next_up);
+            for x in [lower(min.x), lower(min.y), lower(min.z)] {
+                nodes.extend_from_slice(&x.to_le_bytes());
+            }
+            nodes.extend_from_slice(&node.offset.to_le_bytes());
+            for x in [upper(max.x), upper(max.y), upper(max.z)] {
+                nodes.extend_from_slice(&x.to_le_bytes());
+            }
+            let info = node.count << 2 | node.axis as u32;
+            nodes.extend_from_slice(&info.to_le_bytes());
+        }
+
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::new_without_display_handle());
+        let adapter = block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            ..Default::default()
+        }))
+        .ok()?;
+        let limits = adapter.limits();
+        let largest = [nodes.len(), vertices.len(), MAX_RAYS * 32]
+            .into_iter()
+            .max()
+            .unwrap() as u64;
+        if largest
+            > limits
+                .max_storage_buffer_binding_size
+                .min(limits.max_buffer_size)
+        {
+            return None;
+        }
+        let (device, queue) = block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+            label: Some("rt3"),
+            required_limits: limits,
+            ..Default::default()
+        }))
+        .ok()?;
+
+        let storage = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("rt3 traversal"),
+            entries: &[
+                storage(0, true),
+                storage(1, true),
+                storage(2, true),
+                storage(3, false),
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("rt3 traversal"),
+            bind_group_layouts: &[Some(&layout)],
+            immediate_size: 0,
+        });
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("rt3 traversal"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("gpu.wgsl").into()),
+        });
+        let pipeline = |entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&pipeline_layout),
+                module: &module,
+                entry_point: Some(entry_point),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                cache: None,
+            })
+        };
+        let (closest, occluded) = (pipeline("closest"), pipeline("occluded"));
+
+        let buffer = |label, contents: &[u8]| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents,
+                usage: wgpu::BufferUsages::STORAGE,
+            })
+        };
+        let nodes = buffer("rt3 nodes", &nodes);
+        let vertices = buffer("rt3 vertices", &vertices);
+        Some(Self {
+            adapter: adapter.get_info(),
+            device,
+            queue,
+            layout,
+            closest,
+            occluded,
+            nodes,
+            vertices,
+        })
+    }
+
+    /// Traces the rays, returning the ray parameter and triangle index of the closest hit of
+    /// each, or of any hit if `any_hit` is set, unless the GPU fails to
+    fn trace<T: Float, U>(
+        &self,
+        rays: &[(Ray<T, U>, Time<T>)],
+        any_hit: bool,
+    ) -> Option<Vec<(f32, u32)>> {
+        let mut hits = Vec::with_capacity(rays.len());
+        for rays in rays.chunks(MAX_RAYS) {
+            hits.extend(self.dispatch(rays, any_hit)?);
+        }
+        Some(hits)
+    }
+
+    fn dispatch<T: Float, U>(
+        &self,
+        rays: &[(Ray<T, U>, Time<T>)],
+        any_hit: bool,
+    ) -> Option<impl Iterator<Item = (f32, u32)>> {
+        let mut data = Vec::with_capacity(rays.len() * 32);
+        for (ray, t_max) in rays {
+            let (o, d) = (ray.origin, ray.dir);
+            for x in [o.x, o.y, o.z, t_max.0, d.x, d.y, d.z, T::zero()] {
+                data.extend_from_slice(&x.to_f32().unwrap_or(f32::NAN).to_le_bytes());
+            }
+        }
+        let size = (rays.len() * 8) as u64;
+        let rays_buffer = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("rt3 rays"),
+                contents: &data,
+                usage: wgpu::BufferUsages::STORAGE,
+            });
+        let hits_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("rt3 hits"),
+            size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("rt3 readback"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("rt3 traversal"),
+            layout: &self.layout,
+            entries: &[
+                (0, &self.nodes),
+                (1, &self.vertices),
+                (2, &rays_buffer),
+                (3, &hits_buffer),
+            ]
+            .map(|(binding, buffer)| wgpu::BindGroupEntry {
+                binding,
+                resource: buffer.as_entire_binding(),
+            }),
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+            pass.set_pipeline(if any_hit {
+                &self.occluded
+            } else {
+                &self.closest
+            });
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(rays.len().div_ceil(WORKGROUP_SIZE) as u32, 1, 1);
+        }
+        encoder.copy_buffer_to_buffer(&hits_buffer, 0, &readback, 0, size);
+        self.queue.submit([encoder.finish()]);
+        let (sender, receiver) = mpsc::channel();
+        readback.map_async(wgpu::MapMode::Read, .., move |result| {
+            // The receiver only goes away once the hits are no longer waited for
+            let _ = sender.send(result);
+        });
+        self.device.poll(wgpu::PollType::wait_indefinitely()).ok()?;
+        receiver.recv().ok()?.ok()?;
+
+        let bytes = readback.get_mapped_range(..).to_vec();
+        readback.unmap();
+        Some((0..rays.len()).map(move |i| {
+            let word = |j: usize| bytes[8 * i + 4 * j..8 * i + 4 * j + 4].try_into().unwrap();
+            (f32::from_le_bytes(word(0)), u32::from_le_bytes(word(1)))
+        }))
+    }
+}
+
+/// Wakes a thread parked on a future
+struct Unpark(Thread);
+
+impl Wake for Unpark {
+    #[inline]
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Waits for a future of wgpu, parking the thread until it is woken
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let waker = Waker::from(Arc::new(Unpark(thread::current())));
+    let mut context = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+        thread::park();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        color::Rgb,
+        core::geometry::{Point3, UnknownUnit, Vector3},
+        material::{DiffuseMaterial, MaskedMaterial},
+        sampler::Pcg32,
+        scene::{Primitive, Scene},
+        shape::{Sphere, Triangle, TriangleMesh},
+    };
+
+    #[test]
+    fn test_gpu_bvh() {
+        // A grid of triangles over which rays are cast from all around
+        let n = 16;
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        for y in 0..=n {
+            for x in 0..=n {
+                let h = ((x * 7 + y * 3) % 5) as f64 * 0.1;
+                positions.push(Point3::new(x as f64, y as f64, h));
+            }
+        }
+        for y in 0..n {
+            for x in 0..n {
+                let i = y * (n + 1) + x;
+                indices.push([i, i + 1, i + n + 2]);
+                indices.push([i, i + n + 2, i + n + 1]);
+            }
+        }
+        let mesh = Arc::new(TriangleMesh::<f64, UnknownUnit>::new(positions, indices));
+        let triangles: Vec<_> = Triangle::from_mesh(&mesh).collect();
+        let bvh = GpuBvh::new(Bvh::new(triangles.clone()));
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::new_without_display_handle());
+        if block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default())).is_err() {
+            eprintln!("skipping test_gpu_bvh: no GPU adapter");
+            return;
+        }
+        assert!(bvh.is_gpu());
+
+        let mut rng = Pcg32::new(3);
+        let mut point = |scale: f64| {
+            Point3::new(rng.uniform(), rng.uniform(), rng.uniform::<f64>() - 0.5) * scale
+        };
+        let rays: Vec<_> = (0..1000)
+            .map(|i| {
+                let origin = point(20.);
+                let ray = Ray::new(origin, point(16.) + Vector3::new(0., 0., 0.2) - origin);
+                (ray, Time(if i % 3 == 0 { 0.5 } else { f64::INFINITY }))
+            })
+            .collect();
+        let hits = bvh.intersect_batch(&rays);
+        let occluded = bvh.intersect_any_batch(&rays);
+        let cpu = Bvh::new(triangles);
+        let mut misses = 0;
+        for ((ray, t_max), (hit, occluded)) in rays.iter().zip(hits.iter().zip(occluded)) {
+            let expected = cpu.intersect(ray, *t_max);
+            misses += usize::from(expected.is_none());
+            match (hit, expected) {
+                (Some(hit), Some(expected)) => {
+                    assert!((hit.t.0 - expected.t.0).abs() < 1e-4);
+                    assert_eq!(hit.primitive, expected.primitive);
+                }
+                (hit, expected) => assert_eq!(hit.is_some(), expected.is_some()),
+            }
+            assert_eq!(occluded, cpu.intersect_any(ray, *t_max));
+        }
+        assert!(misses > 100 && misses < 900, "{misses}");
+
+        // Scenes trace their batches through it
+        let shapes: Vec<_> = Triangle::from_mesh(&mesh).map(Arc::new).collect();
+        let primitives = shapes
+            .iter()
+            .map(|triangle| Primitive::new(triangle.clone(), None))
+            .collect::<Vec<_>>();
+        let aggregate = GpuBvh::new(Bvh::new(primitives.clone()));
+        assert!(aggregate.is_gpu());
+        let scene = Scene::new(aggregate, Vec::new());
+        let scene_hits = scene.intersect_batch(&rays);
+        for (hit, expected) in scene_hits.iter().zip(&hits) {
+            assert_eq!(
+                hit.as_ref().map(|h| h.primitive),
+                expected.as_ref().map(|h| h.primitive)
+            );
+        }
+
+        // Hierarchies with other shapes stay on the CPU
+        let sphere = Sphere::<f64, UnknownUnit>::new(Point3::new(8., 8., -5.), 1.);
+        let mut primitives = primitives;
+        primitives.push(Primitive::new(Arc::new(sphere), None));
+        let mixed = GpuBvh::new(Bvh::new(primitives));
+        assert!(!mixed.is_gpu());
+        let ray = Ray::new(Point3::new(8., 8., -10.), Vector3::new(0., 0., 1.));
+        let hits = mixed.intersect_batch(&[(ray, Time(f64::INFINITY))]);
+        assert_eq!(hits[0].as_ref().map(|hit| hit.t.0), Some(4.));
+
+        // And so do those with cutouts
+        let masked = MaskedMaterial::new(DiffuseMaterial::new(Rgb::splat(0.5)), 0.5);
+        let mut primitives: Vec<_> = shapes
+            .iter()
+            .map(|triangle| Primitive::new(triangle.clone(), None))
+            .collect();
+        primitives[0] = Primitive::new(shapes[0].clone(), Some(Arc::new(masked)));
+        assert!(!GpuBvh::new(Bvh::new(primitives)).is_gpu());
+    }
+}
@@ -0,0 +1,130 @@
+// Traverses the nodes of a BVH built on the CPU for a batch of rays, with one invocation per ray
+// visiting the nodes in the same order as the CPU traversal does
+
+struct Node {
+    min: vec3<f32>,
+    // The first triangle of a leaf or the second child of an interior node
+    offset: u32,
+    max: vec3<f32>,
+    // The number of triangles of a leaf, zero for interior nodes, times four plus the split axis
+    info: u32,
+}
+
+struct Ray {
+    // The origin, and the ray parameter up to which hits count in `w`
+    origin: vec4<f32>,
+    dir: vec4<f32>,
+}
+
+struct Hit {
+    t: f32,
+    // The index of the triangle hit, or `MISS`
+    triangle: u32,
+}
+
+@group(0) @binding(0) var<storage, read> nodes: array<Node>;
+// The three vertices of each triangle in turn
+@group(0) @binding(1) var<storage, read> vertices: array<vec4<f32>>;
+@group(0) @binding(2) var<storage, read> rays: array<Ray>;
+@group(0) @binding(3) var<storage, read_write> hits: array<Hit>;
+
+// The depth of the hierarchy is limited to this by the build
+const STACK_SIZE: u32 = 64u;
+const MISS: u32 = 0xffffffffu;
+
+fn enters_bounds(node: Node, origin: vec3<f32>, inv_dir: vec3<f32>, t_max: f32) -> bool {
+    let t0 = (node.min - origin) * inv_dir;
+    let t1 = (node.max - origin) * inv_dir;
+    let near = max(max(min(t0.x, t1.x), min(t0.y, t1.y)), max(min(t0.z, t1.z), 0.0));
+    let far = min(min(max(t0.x, t1.x), max(t0.y, t1.y)), min(max(t0.z, t1.z), t_max));
+    // Widened so that rounding errors don't miss boxes the ray grazes
+    return near <= far * 1.0000004;
+}
+
+// Returns the ray parameter of the hit with a triangle, or zero if there is none before `t_max`
+fn intersect_triangle(index: u32, origin: vec3<f32>, dir: vec3<f32>, t_max: f32) -> f32 {
+    let p0 = vertices[3u * index].xyz;
+    let e1 = vertices[3u * index + 1u].xyz - p0;
+    let e2 = vertices[3u * index + 2u].xyz - p0;
+    let pvec = cross(dir, e2);
+    let det = dot(e1, pvec);
+    if det == 0.0 {
+        return 0.0;
+    }
+    let inv_det = 1.0 / det;
+    let tvec = origin - p0;
+    let b1 = dot(tvec, pvec) * inv_det;
+    if b1 < 0.0 || b1 > 1.0 {
+        return 0.0;
+    }
+    let qvec = cross(tvec, e1);
+    let b2 = dot(dir, qvec) * inv_det;
+    if b2 < 0.0 || b1 + b2 > 1.0 {
+        return 0.0;
+    }
+    let t = dot(e2, qvec) * inv_det;
+    return select(0.0, t, t > 0.0 && t <= t_max);
+}
+
+fn trace(index: u32, any_hit: bool) -> Hit {
+    let ray = rays[index];
+    let origin = ray.origin.xyz;
+    let dir = ray.dir.xyz;
+    var hit = Hit(ray.origin.w, MISS);
+    // Division by zero is not guaranteed to be infinite, so tiny components are replaced
+    let tiny = select(vec3(1e-20), vec3(-1e-20), dir < vec3(0.0));
+    let inv_dir = 1.0 / select(dir, tiny, abs(dir) < vec3(1e-20));
+
+    var stack: array<u32, STACK_SIZE>;
+    var stack_len = 0u;
+    var current = 0u;
+    loop {
+        let node = nodes[current];
+        if enters_bounds(node, origin, inv_dir, hit.t) {
+            let count = node.info >> 2u;
+            if count > 0u {
+                for (var i = node.offset; i < node.offset + count; i++) {
+                    let t = intersect_triangle(i, origin, dir, hit.t);
+                    if t > 0.0 {
+                        hit = Hit(t, i);
+                        if any_hit {
+                            return hit;
+                        }
+                    }
+                }
+            } else {
+                // Visit the child on the near side of the split first
+                var near = current + 1u;
+                var far = node.offset;
+                if dir[node.info & 3u] < 0.0 {
+                    near = node.offset;
+                    far = current + 1u;
+                }
+                stack[stack_len] = far;
+                stack_len++;
+                current = near;
+                continue;
+            }
+        }
+        if stack_len == 0u {
+            break;
+        }
+        stack_len--;
+        current = stack[stack_len];
+    }
+    return hit;
+}
+
+@compute @workgroup_size(64)
+fn closest(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x < arrayLength(&rays) {
+        hits[id.x] = trace(id.x, false);
+    }
+}
+
+@compute @workgroup_size(64)
+fn occluded(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x < arrayLength(&rays) {
+        hits[id.x] = trace(id.x, true);
+    }
+}
@@ -0,0 +1,457 @@
+use crate::{
+    accel::{
+        stats::{Recorder, TraversalStats},
+        Accelerator,
+    },
+    core::{
+        geometry::{Axis3, Box3, Ray},
+        units::Time,
+    },
+    shape::{Shape, SurfaceInteraction},
+};
+use num_traits::Float;
+
+/// Options controlling the resolution of a [`Grid`]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GridOptions {
+    /// Scales the number of cells along the longest axis, which is `density * cbrt(n)` for `n`
+    /// primitives
+    pub density: f32,
+    /// The largest number of cells along any axis of a single grid
+    pub max_resolution: usize,
+    /// Cells with more primitives than this get a nested grid, if allowed by `max_depth`
+    pub subgrid_threshold: usize,
+    /// How deeply grids may be nested, with zero giving a uniform grid
+    pub max_depth: usize,
+}
+
+impl Default for GridOptions {
+    fn default() -> Self {
+        Self {
+            density: 3.,
+            max_resolution: 64,
+            subgrid_threshold: 16,
+            max_depth: 0,
+        }
+    }
+}
+
+struct Cell<T> {
+    /// The range of `items` listing the primitives overlapping the cell
+    start: u32,
+    end: u32,
+    /// A finer grid over the cell's primitives, which replaces them
+    child: Option<Box<GridLevel<T>>>,
+}
+
+struct GridLevel<T> {
+    min: [T; 3],
+    cell_size: [T; 3],
+    resolution: [usize; 3],
+    cells: Vec<Cell<T>>,
+    items: Vec<u32>,
+}
+
+/// A regular grid of cells listing the primitives overlapping them, traversed with a 3D-DDA
+///
+/// Primitives are referenced from every cell they overlap, and the `primitive` index of the
+/// reported hits refers to [`primitives`](Accelerator::primitives), which keep their order.
+pub struct Grid<T, U, P> {
+    primitives: Vec<P>,
+    bounds: Box3<T, U>,
+    root: Option<GridLevel<T>>,
+}
+
+impl<T, U, P> Grid<T, U, P> {
+    #[inline]
+    #[must_use]
+    pub fn into_primitives(self) -> Vec<P> {
+        self.primitives
+    }
+}
+
+impl<T: Float, U, P: Shape<T, U>> Grid<T, U, P> {
+    /// Builds a uniform grid with the default options
+    #[inline]
+    #[must_use]
+    pub fn new(primitives: Vec<P>) -> Self {
+        Self::with_options(primitives, &GridOptions::default())
+    }
+
+    #[must_use]
+    pub fn with_options(primitives: Vec<P>, options: &GridOptions) -> Self {
+        let bounds: Vec<_> = primitives.iter().map(|p| p.bounds()).collect();
+        let Some(&first) = bounds.first() else {
+            return Self {
+                primitives,
+                bounds: Box3::empty(),
+                root: None,
+            };
+        };
+        let grid_bounds = bounds[1..]
+            .iter()
+            .fold(first, |b, other| b.union_unchecked(other));
+        let indices: Vec<_> = (0..bounds.len() as u32).collect();
+        let root = GridLevel::build(&grid_bounds, &indices, &bounds, options, 0);
+        Self {
+            primitives,
+            bounds: grid_bounds,
+            root: Some(root),
+        }
+    }
+}
+
+impl<T: Float> GridLevel<T> {
+    fn build<U>(
+        grid_bounds: &Box3<T, U>,
+        indices: &[u32],
+        bounds: &[Box3<T, U>],
+        options: &GridOptions,
+        depth: usize,
+    ) -> Self {
+        let extent = Axis3::AXES.map(|axis| grid_bounds.max[axis] - grid_bounds.min[axis]);
+        let longest = extent.into_iter().fold(T::zero(), T::max);
+        let cells_per_unit = if longest > T::zero() {
+            let n = T::from(indices.len()).unwrap();
+            T::from(options.density).unwrap() * n.cbrt() / longest
+        } else {
+            T::zero()
+        };
+        let max_resolution = options.max_resolution.max(1);
+        let resolution = extent.map(|e| {
+            (e * cells_per_unit)
+                .to_usize()
+                .unwrap_or(1)
+                .clamp(1, max_resolution)
+        });
+        let min = Axis3::AXES.map(|axis| grid_bounds.min[axis]);
+        let cell_size = [0, 1, 2].map(|a| extent[a] / T::from(resolution[a]).unwrap());
+        let mut level = Self {
+            min,
+            cell_size,
+            resolution,
+            cells: Vec::new(),
+            items: Vec::new(),
+        };
+
+        let mut cells = vec![Vec::new(); resolution.iter().product()];
+        for &i in indices {
+            let b = &bounds[i as usize];
+            let lo = Axis3::AXES.map(|axis| b.min[axis]);
+            let hi = Axis3::AXES.map(|axis| b.max[axis]);
+            let (lo, hi) = (level.cell_of(lo), level.cell_of(hi));
+            for z in lo[2]..=hi[2] {
+                for y in lo[1]..=hi[1] {
+                    for x in lo[0]..=hi[0] {
+                        cells[level.cell_index([x, y, z])].push(i);
+                    }
+                }
+            }
+        }
+
+        for (index, primitives) in cells.into_iter().enumerate() {
+            let child = (depth < options.max_depth && primitives.len() > options.subgrid_threshold)
+                .then(|| {
+                    let cell_bounds = level.cell_bounds(index);
+                    Box::new(Self::build(
+                        &cell_bounds,
+                        &primitives,
+                        bounds,
+                        options,
+                        depth + 1,
+                    ))
+                });
+            let start = level.items.len() as u32;
+            if child.is_none() {
+                level.items.extend(primitives);
+            }
+            level.cells.push(Cell {
+                start,
+                end: level.items.len() as u32,
+                child,
+            });
+        }
+        level
+    }
+
+    /// Returns the cell containing the point, clamped to the grid
+    #[inline]
+    fn cell_of(&self, p: [T; 3]) -> [usize; 3] {
+        [0, 1, 2].map(|a| {
+            let cell = ((p[a] - self.min[a]) / self.cell_size[a]).floor();
+            cell.to_usize().unwrap_or(0).min(self.resolution[a] - 1)
+        })
+    }
+
+    #[inline]
+    fn cell_index(&self, [x, y, z]: [usize; 3]) -> usize {
+        (z * self.resolution[1] + y) * self.resolution[0] + x
+    }
+
+    fn cell_bounds<U>(&self, index: usize) -> Box3<T, U> {
+        let x = index % self.resolution[0];
+        let y = index / self.resolution[0] % self.resolution[1];
+        let z = index / (self.resolution[0] * self.resolution[1]);
+        let lo = [x, y, z].map(|c| T::from(c).unwrap());
+        let mut b = Box3::empty();
+        for (a, axis) in Axis3::AXES.into_iter().enumerate() {
+            b.min[axis] = self.min[a] + lo[a] * self.cell_size[a];
+            b.max[axis] = self.min[a] + (lo[a] + T::one()) * self.cell_size[a];
+        }
+        b
+    }
+
+    /// Steps through the cells the ray passes between `t_enter` and `t_exit`, nearest first
+    ///
+    /// Returns `false` once the visitor has stopped the traversal.
+    #[allow(clippy::too_many_arguments)]
+    fn walk<S: Recorder>(
+        &self,
+        ray: &PreparedRay<T>,
+        t_enter: T,
+        t_exit: T,
+        t_max: &mut T,
+        depth: usize,
+        stats: &mut S,
+        visit: &mut impl FnMut(&[u32], T, &mut S) -> Option<T>,
+    ) -> bool {
+        stats.depth(depth);
+        let p = [0, 1, 2].map(|a| ray.origin[a] + ray.dir[a] * t_enter);
+        let mut cell = self.cell_of(p);
+        let mut next = [T::infinity(); 3];
+        let mut delta = [T::infinity(); 3];
+        for a in 0..3 {
+            if ray.dir[a] == T::zero() {
+                continue;
+            }
+            let forward = ray.dir[a] > T::zero();
+            let index = T::from(cell[a] + usize::from(forward)).unwrap();
+            let plane = self.min[a] + index * self.cell_size[a];
+            next[a] = t_enter + (plane - p[a]) * ray.inv_dir[a];
+            delta[a] = self.cell_size[a] * ray.inv_dir[a].abs();
+        }
+
+        let mut t_cell = t_enter;
+        loop {
+            let axis = if next[0] < next[1] && next[0] < next[2] {
+                0
+            } else if next[1] < next[2] {
+                1
+            } else {
+                2
+            };
+            let cell_exit = next[axis].min(t_exit);
+            let c = &self.cells[self.cell_index(cell)];
+            stats.node_visit();
+            if let Some(child) = &c.child {
+                if !child.walk(ray, t_cell, cell_exit, t_max, depth + 1, stats, visit) {
+                    return false;
+                }
+            } else if c.start < c.end {
+                match visit(&self.items[c.start as usize..c.end as usize], *t_max, stats) {
+                    Some(t) => *t_max = t,
+                    None => return false,
+                }
+            }
+
+            // Hits beyond the cell may still be beaten by primitives in later cells
+            if *t_max <= cell_exit || next[axis] > t_exit {
+                return true;
+            }
+            if ray.dir[axis] > T::zero() {
+                cell[axis] += 1;
+                if cell[axis] == self.resolution[axis] {
+                    return true;
+                }
+            } else {
+                if cell[axis] == 0 {
+                    return true;
+                }
+                cell[axis] -= 1;
+            }
+            t_cell = next[axis];
+            next[axis] = next[axis] + delta[axis];
+        }
+    }
+}
+
+struct PreparedRay<T> {
+    origin: [T; 3],
+    dir: [T; 3],
+    inv_dir: [T; 3],
+}
+
+impl<T: Float, U, P: Shape<T, U>> Grid<T, U, P> {
+    /// Visits the primitives of the cells the ray passes before `t_max`, nearest first
+    ///
+    /// The visitor returns the new `t_max`, or `None` to stop the traversal.
+    fn traverse<S: Recorder>(
+        &self,
+        ray: &Ray<T, U>,
+        mut t_max: T,
+        stats: &mut S,
+        mut visit: impl FnMut(&[u32], T, &mut S) -> Option<T>,
+    ) {
+        let Some(root) = &self.root else {
+            return;
+        };
+        stats.ray();
+        let Some((t_enter, t_exit)) = self.bounds.intersect_ray(ray, Time(t_max)) else {
+            return;
+        };
+        let dir = Axis3::AXES.map(|axis| ray.dir[axis]);
+        let prepared = PreparedRay {
+            origin: Axis3::AXES.map(|axis| ray.origin[axis]),
+            dir,
+            inv_dir: dir.map(T::recip),
+        };
+        root.walk(&prepared, t_enter, t_exit, &mut t_max, 0, stats, &mut visit);
+    }
+}
+
+impl<T: Float, U, P: Shape<T, U>> Grid<T, U, P> {
+    fn intersect_recorded(
+        &self,
+        ray: &Ray<T, U>,
+        t_max: Time<T>,
+        stats: &mut impl Recorder,
+    ) -> Option<SurfaceInteraction<T, U>> {
+        let mut closest = None;
+        self.traverse(ray, t_max.0, stats, |indices, mut t_max, stats| {
+            for &i in indices {
+                let i = i as usize;
+                stats.primitive_test();
+                if let Some(mut hit) = self.primitives[i].intersect(ray, Time(t_max)) {
+                    t_max = hit.t.0;
+                    hit.primitive = i;
+                    closest = Some(hit);
+                }
+            }
+            Some(t_max)
+        });
+        closest
+    }
+
+    fn intersect_any_recorded(
+        &self,
+        ray: &Ray<T, U>,
+        t_max: Time<T>,
+        stats: &mut impl Recorder,
+    ) -> bool {
+        let mut occluded = false;
+        self.traverse(ray, t_max.0, stats, |indices, t_max, stats| {
+            occluded = indices.iter().any(|&i| {
+                stats.primitive_test();
+                self.primitives[i as usize].intersect_any(ray, Time(t_max))
+            });
+            (!occluded).then_some(t_max)
+        });
+        occluded
+    }
+}
+
+impl<T: Float, U, P: Shape<T, U>> Shape<T, U> for Grid<T, U, P> {
+    #[inline]
+    fn bounds(&self) -> Box3<T, U> {
+        self.bounds
+    }
+
+    #[inline]
+    fn intersect(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<SurfaceInteraction<T, U>> {
+        self.intersect_recorded(ray, t_max, &mut ())
+    }
+
+    #[inline]
+    fn intersect_any(&self, ray: &Ray<T, U>, t_max: Time<T>) -> bool {
+        self.intersect_any_recorded(ray, t_max, &mut ())
+    }
+}
+
+impl<T: Float, U, P: Shape<T, U>> Accelerator<T, U> for Grid<T, U, P> {
+    type Primitive = P;
+
+    #[inline]
+    fn primitives(&self) -> &[P] {
+        &self.primitives
+    }
+
+    #[inline]
+    fn intersect_with_stats(
+        &self,
+        ray: &Ray<T, U>,
+        t_max: Time<T>,
+        stats: &mut TraversalStats,
+    ) -> Option<SurfaceInteraction<T, U>> {
+        self.intersect_recorded(ray, t_max, stats)
+    }
+
+    #[inline]
+    fn intersect_any_with_stats(
+        &self,
+        ray: &Ray<T, U>,
+        t_max: Time<T>,
+        stats: &mut TraversalStats,
+    ) -> bool {
+        self.intersect_any_recorded(ray, t_max, stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        accel::bvh::tests::{brute_force, random, random_ray, triangle_soup},
+        core::geometry::{Point3, UnknownUnit, Vector3},
+    };
+
+    #[test]
+    fn test_matches_brute_force() {
+        let mut state = 0x0bad_cafe;
+        let triangles = triangle_soup(&mut state, 1000);
+        for max_depth in [0, 2] {
+            let options = GridOptions {
+                subgrid_threshold: 4,
+                max_depth,
+                ..Default::default()
+            };
+            let grid = Grid::with_options(triangles.clone(), &options);
+
+            let (mut hits, mut misses) = (0, 0);
+            for i in 0..600 {
+                let mut ray = random_ray(&mut state);
+                // Rays along an axis step through a single row of cells
+                if i % 6 == 0 {
+                    ray.dir = [Vector3::new(1., 0., 0.), Vector3::new(0., -1., 0.)][i % 12 / 6];
+                }
+                let t_max = if random(&mut state) < 0.3 {
+                    10.
+                } else {
+                    f32::INFINITY
+                };
+                let expected = brute_force(&triangles, &ray, t_max);
+                let hit = grid.intersect(&ray, Time(t_max));
+                assert_eq!(hit.map(|hit| hit.t.0), expected);
+                assert_eq!(grid.intersect_any(&ray, Time(t_max)), expected.is_some());
+                match hit {
+                    // The primitives keep their order
+                    Some(hit) => {
+                        let primitive = &triangles[hit.primitive];
+                        assert_eq!(primitive.intersect(&ray, Time(t_max)).unwrap().t, hit.t);
+                        hits += 1;
+                    }
+                    None => misses += 1,
+                }
+            }
+            assert!(hits > 50 && misses > 50, "{hits} {misses}");
+
+            // Rays leaving the bounds, or stopping short of every primitive
+            let origin = Point3::<f32, UnknownUnit>::new(0., 0., -30.);
+            let away = Ray::new(origin, Vector3::new(0., 0., -1.));
+            assert!(grid.intersect(&away, Time(f32::INFINITY)).is_none());
+            assert!(!grid.intersect_any(&away, Time(f32::INFINITY)));
+            let short = Ray::new(origin, Vector3::new(0., 0., 1.));
+            assert!(grid.intersect(&short, Time(5.)).is_none());
+            assert!(!grid.intersect_any(&short, Time(5.)));
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use crate::{
+    accel::{Accelerator, TraversalStats},
+    camera::{Camera, CameraSample},
+    color::Rgb,
+    core::{
+        geometry::{Point2, UnknownUnit},
+        units::Time,
+    },
+    image::Image,
+};
+use num_traits::Float;
+use rayon::prelude::*;
+
+/// The colors counts are mapped through by [`Heatmap::resolve`], from zero to the maximum
+const RAMP: [[f32; 3]; 6] = [
+    [0., 0., 0.],
+    [0., 0., 1.],
+    [0., 1., 1.],
+    [0., 1., 0.],
+    [1., 1., 0.],
+    [1., 0., 0.],
+];
+
+/// A counter of [`TraversalStats`] shown by a [`Heatmap`]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum HeatmapMetric {
+    /// Interior nodes, leaves or grid cells visited
+    NodeVisits,
+    /// Intersection tests against primitives
+    PrimitiveTests,
+}
+
+/// A debug image of the work an accelerator does to find the closest hit of each camera ray
+///
+/// Both counters of [`TraversalStats`] are recorded for every pixel by the same traversals, and
+/// [`resolve`](Self::resolve) maps either of them to false colors. Hot spots show where the
+/// accelerator is poorly built, such as where boxes overlap a lot or leaves hold too many
+/// primitives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heatmap {
+    width: usize,
+    height: usize,
+    pixels: Vec<TraversalStats>,
+}
+
+impl Heatmap {
+    /// Traces a ray through the center of every pixel and of the lens of the camera as the
+    /// shutter opens, recording the work done to find its closest hit
+    #[must_use]
+    pub fn render<T, U, A, C>(accel: &A, camera: &C, width: usize, height: usize) -> Self
+    where
+        T: Float + Send + Sync,
+        A: Accelerator<T, U> + Sync,
+        C: Camera<T, U> + Sync,
+    {
+        let mut pixels = vec![TraversalStats::default(); width * height];
+        let half = T::from(0.5).unwrap();
+        let size = [width, height].map(|n| T::from(n).unwrap());
+        pixels
+            .par_chunks_mut(width.max(1))
+            .enumerate()
+            .for_each(|(y, row)| {
+                for (x, stats) in row.iter_mut().enumerate() {
+                    let sample = CameraSample {
+                        film: Point2::new(
+                            (T::from(x).unwrap() + half) / size[0],
+                            (T::from(y).unwrap() + half) / size[1],
+                        ),
+                        lens: Point2::new(half, half),
+                        time: T::zero(),
+                    };
+                    let ray = camera.generate_ray(&sample);
+                    let _ = accel.intersect_with_stats(&ray, Time(T::infinity()), stats);
+                }
+            });
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the work recorded for a pixel
+    #[inline]
+    #[must_use]
+    pub fn get(&self, p: Point2<usize, UnknownUnit>) -> Option<&TraversalStats> {
+        (p.x < self.width && p.y < self.height).then(|| &self.pixels[p.y * self.width + p.x])
+    }
+
+    /// Maps the count of `metric` of each pixel per ray from black at zero through blue, cyan,
+    /// green and yellow to red at `max`, or at the highest count of the image without one
+    #[must_use]
+    pub fn resolve<T: Float>(&self, metric: HeatmapMetric, max: Option<T>) -> Image<T> {
+        let count = |stats: &TraversalStats| {
+            let count = match metric {
+                HeatmapMetric::NodeVisits => stats.node_visits,
+                HeatmapMetric::PrimitiveTests => stats.primitive_tests,
+            };
+            if stats.rays == 0 {
+                T::zero()
+            } else {
+                T::from(count).unwrap() / T::from(stats.rays).unwrap()
+            }
+        };
+        let max = max.unwrap_or_else(|| self.pixels.iter().map(count).fold(T::zero(), T::max));
+        let ramp = RAMP.map(|c| c.map(|c| T::from(c).unwrap()));
+        let last = T::from(RAMP.len() - 1).unwrap();
+        let pixels = self
+            .pixels
+            .iter()
+            .map(|stats| {
+                if max <= T::zero() {
+                    return Rgb::black();
+                }
+                let x = (count(stats) / max).max(T::zero()).min(T::one()) * last;
+                let i = x.floor().to_usize().unwrap_or(0).min(RAMP.len() - 2);
+                let t = x - T::from(i).unwrap();
+                let (a, b) = (ramp[i], ramp[i + 1]);
+                let lerp = |c: usize| a[c] + (b[c] - a[c]) * t;
+                Rgb::new(lerp(0), lerp(1), lerp(2))
+            })
+            .collect();
+        Image::new(self.width, self.height, pixels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        accel::{Bvh, BvhBuildOptions, SplitMethod},
+        camera::ThinLensCamera,
+        core::{
+            geometry::{Point3, Ray, Vector3},
+            units::Angle,
+        },
+        shape::Sphere,
+    };
+
+    type V = Vector3<f64, UnknownUnit>;
+
+    #[test]
+    fn test_heatmap() {
+        // A root over two leaves of one sphere each, at x = -2 and x = 2
+        let spheres = [-2., 2.]
+            .map(|x| Sphere::<f64, UnknownUnit>::new(Point3::new(x, 0., 0.), 1.))
+            .into();
+        let options = BvhBuildOptions {
+            split_method: SplitMethod::Median,
+            max_leaf_size: 1,
+            ..Default::default()
+        };
+        let bvh = Bvh::with_options(spheres, &options);
+        // Rays through the root visit both of its children, and test the spheres they enter
+        let stats = |x: f64| {
+            let ray = Ray::new(Point3::new(x, 0., 5.), V::new(0., 0., -1.));
+            let mut stats = TraversalStats::default();
+            let _ = bvh.intersect_with_stats(&ray, Time(f64::INFINITY), &mut stats);
+            (stats.node_visits, stats.primitive_tests)
+        };
+        assert_eq!((stats(2.), stats(0.), stats(20.)), ((3, 1), (3, 0), (1, 0)));
+
+        // Both counts are recorded for every pixel, where the spheres stand out
+        let camera = ThinLensCamera::look_at(
+            Point3::new(0., 0., 10.),
+            Point3::origin(),
+            V::new(0., 1., 0.),
+            Angle::from_degrees(40.),
+            2.,
+        );
+        let heatmap = Heatmap::render(&bvh, &camera, 16, 8);
+        assert_eq!((heatmap.width(), heatmap.height()), (16, 8));
+        let counts = |x, y| {
+            let stats = heatmap.get(Point2::new(x, y)).unwrap();
+            (stats.rays, stats.node_visits, stats.primitive_tests)
+        };
+        assert_eq!(counts(5, 3), (1, 3, 1));
+        assert_eq!(counts(10, 4), (1, 3, 1));
+        assert_eq!(counts(0, 0), (1, 1, 0));
+        assert!(heatmap.get(Point2::new(16, 0)).is_none());
+
+        // Either is mapped to colors, which are red at the highest count
+        let color = |image: &Image<f64>, x, y| *image.get(Point2::new(x, y)).unwrap();
+        let visits = heatmap.resolve(HeatmapMetric::NodeVisits, None);
+        assert_eq!(color(&visits, 5, 3), Rgb::new(1., 0., 0.));
+        let background = color(&visits, 0, 0);
+        assert!(background.r == 0. && background.g > 0.5 && background.b == 1.);
+        let tests = heatmap.resolve(HeatmapMetric::PrimitiveTests, None);
+        assert_eq!(color(&tests, 10, 4), Rgb::new(1., 0., 0.));
+        assert_eq!(color(&tests, 0, 0), Rgb::black());
+
+        // Or scaled to a fixed maximum, to compare several images
+        let visits = heatmap.resolve(HeatmapMetric::NodeVisits, Some(5.));
+        assert_eq!(color(&visits, 5, 3), Rgb::new(0., 1., 0.));
+        assert_eq!(color(&visits, 0, 0), Rgb::new(0., 0., 1.));
+        let visits = heatmap.resolve(HeatmapMetric::NodeVisits, Some(10.));
+        assert_eq!(color(&visits, 0, 0), Rgb::new(0., 0., 0.5));
+        let empty = Heatmap::render(&bvh, &camera, 0, 0);
+        assert!(empty
+            .resolve::<f64>(HeatmapMetric::PrimitiveTests, None)
+            .pixels()
+            .is_empty());
+    }
+}
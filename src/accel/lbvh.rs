@@ -0,0 +1,259 @@
+use crate::{
+    accel::{
+        bvh::{BuildPrimitive, BvhNode, STACK_SIZE},
+        Bvh,
+    },
+    core::geometry::{Axis3, Box3},
+    shape::Shape,
+};
+use num_traits::Float;
+use rayon::prelude::*;
+
+/// The most primitives a leaf is built with, unless they share a Morton code
+const MAX_LEAF_SIZE: usize = 4;
+
+/// Subtrees over fewer primitives are built on the current thread
+const PARALLEL_THRESHOLD: usize = 4096;
+
+/// Bits of each coordinate quantized into a Morton code
+const BITS_PER_AXIS: u32 = 10;
+
+impl<T, U, P> Bvh<T, U, P>
+where
+    T: Float + Send + Sync,
+    U: Send + Sync,
+    P: Shape<T, U> + Sync,
+{
+    /// Builds a linear BVH, splitting the primitives along a Morton curve through their centroids
+    ///
+    /// Building is much faster than with [`new`](Self::new) and runs in parallel, at the cost of
+    /// a somewhat slower traversal, which suits scenes that are rebuilt every frame.
+    #[must_use]
+    pub fn new_linear(primitives: Vec<P>) -> Self {
+        let build: Vec<_> = primitives
+            .par_iter()
+            .enumerate()
+            .map(|(index, primitive)| BuildPrimitive::new(index, primitive.bounds()))
+            .collect();
+        if build.is_empty() {
+            return Self::from_build(primitives, &build, Vec::new());
+        }
+
+        let centroid_bounds = build
+            .par_iter()
+            .map(|p| Box3::new(p.centroid, p.centroid))
+            .reduce_with(|a, b| a.union_unchecked(&b))
+            .unwrap();
+        let extent = centroid_bounds.max - centroid_bounds.min;
+        let scale = T::from(1 << BITS_PER_AXIS).unwrap();
+        let quantize = |p: &BuildPrimitive<T, U>, axis: Axis3| {
+            let offset = p.centroid[axis] - centroid_bounds.min[axis];
+            let x = if extent[axis] > T::zero() {
+                offset / extent[axis] * scale
+            } else {
+                T::zero()
+            };
+            x.to_u32().unwrap_or(0).min((1 << BITS_PER_AXIS) - 1)
+        };
+
+        let mut build: Vec<_> = build
+            .into_par_iter()
+            .map(|p| {
+                let code = morton_code(
+                    quantize(&p, Axis3::X),
+                    quantize(&p, Axis3::Y),
+                    quantize(&p, Axis3::Z),
+                );
+                (code, p)
+            })
+            .collect();
+        build.par_sort_unstable_by_key(|(code, _)| *code);
+
+        let (codes, build): (Vec<_>, Vec<_>) = build.into_iter().unzip();
+        let nodes = build_subtree(&codes, &build, 0, 0);
+        Self::from_build(primitives, &build, nodes)
+    }
+}
+
+/// Interleaves the low bits of the coordinates as `...zyxzyx`, with `x` the most significant
+#[inline]
+pub(super) fn morton_code(x: u32, y: u32, z: u32) -> u32 {
+    /// Spreads out the low 10 bits so that there are two zero bits between each of them
+    #[inline]
+    fn spread(mut v: u32) -> u32 {
+        v = (v | (v << 16)) & 0x0300_00ff;
+        v = (v | (v << 8)) & 0x0300_f00f;
+        v = (v | (v << 4)) & 0x030c_30c3;
+        v = (v | (v << 2)) & 0x0924_9249;
+        v
+    }
+    (spread(x) << 2) | (spread(y) << 1) | spread(z)
+}
+
+/// Returns where to split a sorted range of codes and the axis the split is along
+///
+/// Ranges are split where the highest bit that differs across them flips, while primitives that
+/// share a code are split in half.
+fn split(codes: &[u32]) -> (usize, Axis3) {
+    let (first, last) = (codes[0], codes[codes.len() - 1]);
+    if first == last {
+        return (codes.len() / 2, Axis3::X);
+    }
+    let bit = 31 - (first ^ last).leading_zeros();
+    let mid = codes.partition_point(|code| code & (1 << bit) == 0);
+    let axis = match bit % 3 {
+        2 => Axis3::X,
+        1 => Axis3::Y,
+        _ => Axis3::Z,
+    };
+    (mid, axis)
+}
+
+#[inline]
+fn is_leaf(len: usize, depth: usize) -> bool {
+    len <= MAX_LEAF_SIZE || depth + 1 >= STACK_SIZE
+}
+
+fn leaf<T: Float, U>(primitives: &[BuildPrimitive<T, U>], offset: usize) -> BvhNode<T, U> {
+    let bounds = primitives[1..]
+        .iter()
+        .fold(primitives[0].bounds, |b, p| b.union_unchecked(&p.bounds));
+    BvhNode {
+        bounds,
+        offset: offset as u32,
+        count: primitives.len() as u32,
+        axis: Axis3::X,
+    }
+}
+
+/// Builds the subtree over the sorted primitives starting at `offset` in the final order
+///
+/// Interior node offsets in the result are relative to its start. Large subtrees are built in
+/// parallel and then stitched together.
+fn build_subtree<T, U>(
+    codes: &[u32],
+    primitives: &[BuildPrimitive<T, U>],
+    offset: usize,
+    depth: usize,
+) -> Vec<BvhNode<T, U>>
+where
+    T: Float + Send + Sync,
+    U: Send + Sync,
+{
+    let len = codes.len();
+    if len < PARALLEL_THRESHOLD {
+        let mut nodes = Vec::with_capacity(2 * len / MAX_LEAF_SIZE);
+        build_serial(codes, primitives, offset, depth, &mut nodes);
+        return nodes;
+    }
+    if is_leaf(len, depth) {
+        return vec![leaf(primitives, offset)];
+    }
+
+    let (mid, axis) = split(codes);
+    let (left, right) = rayon::join(
+        || build_subtree(&codes[..mid], &primitives[..mid], offset, depth + 1),
+        || build_subtree(&codes[mid..], &primitives[mid..], offset + mid, depth + 1),
+    );
+
+    let mut nodes = Vec::with_capacity(1 + left.len() + right.len());
+    nodes.push(BvhNode {
+        bounds: left[0].bounds.union_unchecked(&right[0].bounds),
+        offset: (1 + left.len()) as u32,
+        count: 0,
+        axis,
+    });
+    for (base, subtree) in [(1, &left), (1 + left.len(), &right)] {
+        nodes.extend(subtree.iter().map(|node| {
+            let mut node = *node;
+            if !node.is_leaf() {
+                node.offset += base as u32;
+            }
+            node
+        }));
+    }
+    nodes
+}
+
+/// Appends the subtree over the sorted primitives to `nodes`, returning the index of its root
+fn build_serial<T: Float, U>(
+    codes: &[u32],
+    primitives: &[BuildPrimitive<T, U>],
+    offset: usize,
+    depth: usize,
+    nodes: &mut Vec<BvhNode<T, U>>,
+) -> usize {
+    let index = nodes.len();
+    if is_leaf(codes.len(), depth) {
+        nodes.push(leaf(primitives, offset));
+        return index;
+    }
+
+    let (mid, axis) = split(codes);
+    nodes.push(BvhNode {
+        bounds: primitives[0].bounds,
+        offset: 0,
+        count: 0,
+        axis,
+    });
+    let first = build_serial(&codes[..mid], &primitives[..mid], offset, depth + 1, nodes);
+    let second = build_serial(
+        &codes[mid..],
+        &primitives[mid..],
+        offset + mid,
+        depth + 1,
+        nodes,
+    );
+    let bounds = nodes[first].bounds.union_unchecked(&nodes[second].bounds);
+    let node = &mut nodes[index];
+    node.bounds = bounds;
+    node.offset = second as u32;
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        accel::{
+            bvh::tests::{random, random_ray, triangle_soup},
+            Accelerator,
+        },
+        core::units::Time,
+    };
+
+    #[test]
+    fn test_matches_binary_bvh() {
+        // Enough triangles for subtrees to be built in parallel
+        let mut state = 0x1357_9bdf;
+        let triangles = triangle_soup(&mut state, 5000);
+        let bvh = Bvh::new(triangles.clone());
+        let linear = Bvh::new_linear(triangles);
+        assert_eq!(linear.primitives().len(), bvh.primitives().len());
+
+        let mut hits = 0;
+        for _ in 0..1000 {
+            let ray = random_ray(&mut state);
+            let t_max = Time(if random(&mut state) < 0.3 {
+                10.
+            } else {
+                f32::INFINITY
+            });
+            let (a, b) = (bvh.intersect(&ray, t_max), linear.intersect(&ray, t_max));
+            assert_eq!(a.map(|hit| hit.t), b.map(|hit| hit.t));
+            if let (Some(a), Some(b)) = (a, b) {
+                let (a, b) = (
+                    &bvh.primitives()[a.primitive],
+                    &linear.primitives()[b.primitive],
+                );
+                assert_eq!(a.index, b.index);
+                hits += 1;
+            }
+            assert_eq!(
+                bvh.intersect_any(&ray, t_max),
+                linear.intersect_any(&ray, t_max)
+            );
+        }
+        assert!(hits > 100 && hits < 900, "{hits}");
+    }
+}
@@ -0,0 +1,121 @@
+mod bvh;
+mod cache;
+#[cfg(feature = "embree")]
+mod embree;
+#[cfg(feature = "gpu")]
+mod gpu;
+mod grid;
+mod heatmap;
+mod lbvh;
+mod octree;
+mod packet;
+mod stats;
+mod stream;
+mod tlas;
+mod wide;
+
+pub use bvh::{Bvh, BvhBuildOptions, SplitMethod};
+pub use cache::BvhCacheError;
+#[cfg(feature = "embree")]
+pub use embree::{EmbreeError, EmbreeScene};
+#[cfg(feature = "gpu")]
+pub use gpu::GpuBvh;
+pub use grid::{Grid, GridOptions};
+pub use heatmap::{Heatmap, HeatmapMetric};
+pub use octree::{Octree, OctreeOptions};
+pub use stats::TraversalStats;
+pub use stream::RayStream;
+pub use tlas::{BlasInstance, InstanceTransformError, Tlas};
+pub use wide::{Bvh4, Bvh8, WideBvh};
+
+use crate::{
+    core::{
+        geometry::{Ray, RayBundle},
+        units::Time,
+    },
+    shape::{Shape, SurfaceInteraction},
+};
+
+/// A spatial index over primitives, which is intersected like a single shape
+///
+/// The `primitive` index of the reported hits refers to [`primitives`](Self::primitives).
+pub trait Accelerator<T, U>: Shape<T, U> {
+    type Primitive;
+
+    #[must_use]
+    fn primitives(&self) -> &[Self::Primitive];
+
+    /// Intersects the ray like [`intersect`](Shape::intersect), adding the work done to `stats`
+    fn intersect_with_stats(
+        &self,
+        ray: &Ray<T, U>,
+        t_max: Time<T>,
+        stats: &mut TraversalStats,
+    ) -> Option<SurfaceInteraction<T, U>>;
+
+    /// Tests for occlusion like [`intersect_any`](Shape::intersect_any), adding the work done to
+    /// `stats`
+    fn intersect_any_with_stats(
+        &self,
+        ray: &Ray<T, U>,
+        t_max: Time<T>,
+        stats: &mut TraversalStats,
+    ) -> bool;
+
+    /// Intersects every active ray of the bundle, leaving the inactive lanes empty
+    ///
+    /// The rays are traced one by one unless the accelerator can traverse them together, which
+    /// is much faster for coherent rays such as primary or shadow rays.
+    fn intersect_bundle<const N: usize>(
+        &self,
+        bundle: &RayBundle<T, U, N>,
+    ) -> [Option<SurfaceInteraction<T, U>>; N]
+    where
+        Self: Sized,
+        T: Copy,
+    {
+        std::array::from_fn(|lane| {
+            let t_max = Time(bundle.t_max[lane]);
+            bundle.active[lane]
+                .then(|| self.intersect(&bundle.ray(lane), t_max))
+                .flatten()
+        })
+    }
+
+    /// Tests every active ray of the bundle for occlusion, leaving the inactive lanes `false`
+    fn intersect_any_bundle<const N: usize>(&self, bundle: &RayBundle<T, U, N>) -> [bool; N]
+    where
+        Self: Sized,
+        T: Copy,
+    {
+        std::array::from_fn(|lane| {
+            bundle.active[lane] && self.intersect_any(&bundle.ray(lane), Time(bundle.t_max[lane]))
+        })
+    }
+
+    /// Intersects a batch of rays, each extending up to its own `t_max`
+    ///
+    /// The rays are traced one by one unless the accelerator can trace many at once, such as
+    /// on a GPU.
+    fn intersect_batch(
+        &self,
+        rays: &[(Ray<T, U>, Time<T>)],
+    ) -> Vec<Option<SurfaceInteraction<T, U>>>
+    where
+        T: Copy,
+    {
+        rays.iter()
+            .map(|(ray, t_max)| self.intersect(ray, *t_max))
+            .collect()
+    }
+
+    /// Tests a batch of rays for occlusion like [`intersect_batch`](Self::intersect_batch)
+    fn intersect_any_batch(&self, rays: &[(Ray<T, U>, Time<T>)]) -> Vec<bool>
+    where
+        T: Copy,
+    {
+        rays.iter()
+            .map(|(ray, t_max)| self.intersect_any(ray, *t_max))
+            .collect()
+    }
+}
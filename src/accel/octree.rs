@@ -0,0 +1,370 @@
+use crate::{
+    accel::{
+        bvh::bounds_entry,
+        stats::{Recorder, TraversalStats},
+        Accelerator,
+    },
+    core::{
+        geometry::{Box3, Point3, Ray, Vector3},
+        units::Time,
+    },
+    shape::{Shape, SurfaceInteraction},
+};
+use num_traits::Float;
+
+/// Marks a missing child
+const EMPTY: u32 = u32::MAX;
+
+/// Options controlling how finely an [`Octree`] subdivides space
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct OctreeOptions {
+    /// Nodes at this depth are never subdivided
+    pub max_depth: usize,
+    /// Nodes holding at most this many primitives are not subdivided
+    pub max_primitives: usize,
+    /// Whether children extend halfway into their neighbors, so that primitives straddling a
+    /// split can still move down the tree
+    pub loose: bool,
+}
+
+impl Default for OctreeOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: 10,
+            max_primitives: 8,
+            loose: true,
+        }
+    }
+}
+
+struct OctreeNode<T, U> {
+    /// Bounds containing every primitive in the subtree
+    bounds: Box3<T, U>,
+    children: [u32; 8],
+    /// The range of primitives stored in the node itself
+    start: u32,
+    end: u32,
+}
+
+/// A spatial index that recursively splits space into octants
+///
+/// Every primitive is stored once, in the deepest node it fits into. The primitives are
+/// reordered during the build, and the `primitive` index of the reported hits refers to
+/// [`primitives`](Accelerator::primitives).
+pub struct Octree<T, U, P> {
+    primitives: Vec<P>,
+    nodes: Vec<OctreeNode<T, U>>,
+}
+
+impl<T: Float, U, P: Shape<T, U>> Octree<T, U, P> {
+    #[inline]
+    #[must_use]
+    pub fn new(primitives: Vec<P>) -> Self {
+        Self::with_options(primitives, &OctreeOptions::default())
+    }
+
+    #[must_use]
+    pub fn with_options(primitives: Vec<P>, options: &OctreeOptions) -> Self {
+        let bounds: Vec<_> = primitives.iter().map(|p| p.bounds()).collect();
+        let mut order = Vec::with_capacity(primitives.len());
+        let mut nodes = Vec::new();
+        if let Some(&first) = bounds.first() {
+            let root = bounds[1..]
+                .iter()
+                .fold(first, |root, b| root.union_unchecked(b));
+            let mut builder = Builder {
+                options,
+                bounds: &bounds,
+                order: &mut order,
+                nodes: &mut nodes,
+            };
+            builder.build(root, (0..primitives.len()).collect(), 0);
+        }
+
+        let mut primitives: Vec<_> = primitives.into_iter().map(Some).collect();
+        let primitives = order
+            .into_iter()
+            .map(|i| primitives[i].take().unwrap())
+            .collect();
+        Self { primitives, nodes }
+    }
+}
+
+struct Builder<'a, T, U> {
+    options: &'a OctreeOptions,
+    bounds: &'a [Box3<T, U>],
+    order: &'a mut Vec<usize>,
+    nodes: &'a mut Vec<OctreeNode<T, U>>,
+}
+
+impl<T: Float, U> Builder<'_, T, U> {
+    /// Appends the node covering `cell` and its subtree, returning its index
+    fn build(&mut self, cell: Box3<T, U>, primitives: Vec<usize>, depth: usize) -> u32 {
+        let center = cell.center();
+
+        // Primitives that fit into no octant stay in this node
+        let mut octants: [Vec<usize>; 8] = Default::default();
+        let mut own = Vec::new();
+        if primitives.len() <= self.options.max_primitives || depth >= self.options.max_depth {
+            own = primitives;
+        } else {
+            for i in primitives {
+                match self.octant(&cell, center, &self.bounds[i]) {
+                    Some(octant) => octants[octant].push(i),
+                    None => own.push(i),
+                }
+            }
+        }
+
+        let index = self.nodes.len();
+        let start = self.order.len() as u32;
+        self.order.extend(&own);
+        let mut bounds = own
+            .iter()
+            .map(|&i| self.bounds[i])
+            .reduce(|a, b| a.union_unchecked(&b));
+        self.nodes.push(OctreeNode {
+            bounds: cell,
+            children: [EMPTY; 8],
+            start,
+            end: self.order.len() as u32,
+        });
+
+        for (octant, primitives) in octants.into_iter().enumerate() {
+            if primitives.is_empty() {
+                continue;
+            }
+            let child = self.build(child_cell(&cell, center, octant), primitives, depth + 1);
+            let child_bounds = self.nodes[child as usize].bounds;
+            bounds = Some(bounds.map_or(child_bounds, |b| b.union_unchecked(&child_bounds)));
+            self.nodes[index].children[octant] = child;
+        }
+        // Tighter than the cell, which is especially important for loose octrees
+        self.nodes[index].bounds = bounds.unwrap_or(cell);
+        index as u32
+    }
+
+    /// Returns the octant the primitive fits into, if any
+    fn octant(&self, cell: &Box3<T, U>, center: Point3<T, U>, b: &Box3<T, U>) -> Option<usize> {
+        let two = T::one() + T::one();
+        let centroid = b.center();
+        let octant = usize::from(centroid.x >= center.x)
+            | usize::from(centroid.y >= center.y) << 1
+            | usize::from(centroid.z >= center.z) << 2;
+        let mut child = child_cell(cell, center, octant);
+        if self.options.loose {
+            let half = (child.max - child.min) / two;
+            child = Box3::new(child.min - half, child.max + half);
+        }
+        child.contains_box(b).then_some(octant)
+    }
+}
+
+/// Returns the octant of `cell` whose corner is selected by the bits of `octant`
+fn child_cell<T: Float, U>(cell: &Box3<T, U>, center: Point3<T, U>, octant: usize) -> Box3<T, U> {
+    let pick = |bit: usize, min: T, mid: T, max: T| {
+        if octant & bit == 0 {
+            (min, mid)
+        } else {
+            (mid, max)
+        }
+    };
+    let (x0, x1) = pick(1, cell.min.x, center.x, cell.max.x);
+    let (y0, y1) = pick(2, cell.min.y, center.y, cell.max.y);
+    let (z0, z1) = pick(4, cell.min.z, center.z, cell.max.z);
+    Box3::new(Point3::new(x0, y0, z0), Point3::new(x1, y1, z1))
+}
+
+impl<T: Float, U, P: Shape<T, U>> Octree<T, U, P> {
+    /// Visits the nodes whose bounds the ray enters before `t_max`, roughly nearest first
+    ///
+    /// The visitor returns the new `t_max`, or `None` to stop the traversal.
+    fn traverse<S: Recorder>(
+        &self,
+        ray: &Ray<T, U>,
+        mut t_max: T,
+        stats: &mut S,
+        mut visit: impl FnMut(usize, usize, T, &mut S) -> Option<T>,
+    ) {
+        let Some(root) = self.nodes.first() else {
+            return;
+        };
+        stats.ray();
+        let d = ray.dir;
+        let inv_dir = Vector3::new(d.x.recip(), d.y.recip(), d.z.recip());
+        let Some(entry) = bounds_entry(&root.bounds, ray.origin, inv_dir, t_max) else {
+            return;
+        };
+
+        let mut stack = vec![(entry, 0)];
+        while let Some((entry, index)) = stack.pop() {
+            if entry > t_max {
+                continue;
+            }
+            let node = &self.nodes[index];
+            stats.node_visit();
+            if node.start < node.end {
+                match visit(node.start as usize, node.end as usize, t_max, stats) {
+                    Some(t) => t_max = t,
+                    None => return,
+                }
+            }
+
+            let first = stack.len();
+            for &child in node.children.iter().filter(|&&child| child != EMPTY) {
+                let bounds = &self.nodes[child as usize].bounds;
+                if let Some(entry) = bounds_entry(bounds, ray.origin, inv_dir, t_max) {
+                    stack.push((entry, child as usize));
+                }
+            }
+            stats.depth(stack.len());
+            // Farthest first, so that the nearest child is popped next
+            stack[first..].sort_unstable_by(|a, b| {
+                b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal)
+            });
+        }
+    }
+}
+
+impl<T: Float, U, P: Shape<T, U>> Octree<T, U, P> {
+    fn intersect_recorded(
+        &self,
+        ray: &Ray<T, U>,
+        t_max: Time<T>,
+        stats: &mut impl Recorder,
+    ) -> Option<SurfaceInteraction<T, U>> {
+        let mut closest = None;
+        self.traverse(ray, t_max.0, stats, |start, end, mut t_max, stats| {
+            for i in start..end {
+                stats.primitive_test();
+                if let Some(mut hit) = self.primitives[i].intersect(ray, Time(t_max)) {
+                    t_max = hit.t.0;
+                    hit.primitive = i;
+                    closest = Some(hit);
+                }
+            }
+            Some(t_max)
+        });
+        closest
+    }
+
+    fn intersect_any_recorded(
+        &self,
+        ray: &Ray<T, U>,
+        t_max: Time<T>,
+        stats: &mut impl Recorder,
+    ) -> bool {
+        let mut occluded = false;
+        self.traverse(ray, t_max.0, stats, |start, end, t_max, stats| {
+            occluded = self.primitives[start..end].iter().any(|p| {
+                stats.primitive_test();
+                p.intersect_any(ray, Time(t_max))
+            });
+            (!occluded).then_some(t_max)
+        });
+        occluded
+    }
+}
+
+impl<T: Float, U, P: Shape<T, U>> Shape<T, U> for Octree<T, U, P> {
+    #[inline]
+    fn bounds(&self) -> Box3<T, U> {
+        self.nodes
+            .first()
+            .map_or_else(Box3::empty, |root| root.bounds)
+    }
+
+    #[inline]
+    fn intersect(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<SurfaceInteraction<T, U>> {
+        self.intersect_recorded(ray, t_max, &mut ())
+    }
+
+    #[inline]
+    fn intersect_any(&self, ray: &Ray<T, U>, t_max: Time<T>) -> bool {
+        self.intersect_any_recorded(ray, t_max, &mut ())
+    }
+}
+
+impl<T: Float, U, P: Shape<T, U>> Accelerator<T, U> for Octree<T, U, P> {
+    type Primitive = P;
+
+    #[inline]
+    fn primitives(&self) -> &[P] {
+        &self.primitives
+    }
+
+    #[inline]
+    fn intersect_with_stats(
+        &self,
+        ray: &Ray<T, U>,
+        t_max: Time<T>,
+        stats: &mut TraversalStats,
+    ) -> Option<SurfaceInteraction<T, U>> {
+        self.intersect_recorded(ray, t_max, stats)
+    }
+
+    #[inline]
+    fn intersect_any_with_stats(
+        &self,
+        ray: &Ray<T, U>,
+        t_max: Time<T>,
+        stats: &mut TraversalStats,
+    ) -> bool {
+        self.intersect_any_recorded(ray, t_max, stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        accel::bvh::tests::{brute_force, random, random_ray, triangle_soup},
+        core::geometry::UnknownUnit,
+    };
+
+    #[test]
+    fn test_matches_brute_force() {
+        let mut state = 0x0c7_7ee5;
+        let triangles = triangle_soup(&mut state, 1000);
+        for loose in [true, false] {
+            let options = OctreeOptions {
+                loose,
+                ..Default::default()
+            };
+            let octree = Octree::with_options(triangles.clone(), &options);
+            assert_eq!(octree.primitives().len(), triangles.len());
+
+            let (mut hits, mut misses) = (0, 0);
+            for _ in 0..500 {
+                let ray = random_ray(&mut state);
+                let t_max = if random(&mut state) < 0.3 {
+                    10.
+                } else {
+                    f32::INFINITY
+                };
+                let expected = brute_force(&triangles, &ray, t_max);
+                let hit = octree.intersect(&ray, Time(t_max));
+                assert_eq!(hit.map(|hit| hit.t.0), expected);
+                assert_eq!(octree.intersect_any(&ray, Time(t_max)), expected.is_some());
+                match hit {
+                    Some(hit) => {
+                        let primitive = &octree.primitives()[hit.primitive];
+                        assert_eq!(primitive.intersect(&ray, Time(t_max)).unwrap().t, hit.t);
+                        hits += 1;
+                    }
+                    None => misses += 1,
+                }
+            }
+            assert!(hits > 50 && misses > 50, "{hits} {misses}");
+
+            // Rays leaving the bounds, or stopping short of every primitive
+            let away =
+                Ray::<f32, UnknownUnit>::new(Point3::new(0., 0., -30.), Vector3::new(0., 0., -1.));
+            assert!(octree.intersect(&away, Time(f32::INFINITY)).is_none());
+            assert!(!octree.intersect_any(&away, Time(f32::INFINITY)));
+            let short = Ray::new(Point3::new(0., 0., -30.), Vector3::new(0., 0., 1.));
+            assert!(octree.intersect(&short, Time(5.)).is_none());
+            assert!(!octree.intersect_any(&short, Time(5.)));
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use crate::{
+    accel::{bvh::STACK_SIZE, Accelerator, Bvh},
+    core::{
+        geometry::{Box3, RayBundle},
+        units::Time,
+    },
+    shape::{Shape, SurfaceInteraction},
+};
+use num_traits::Float;
+
+struct PreparedBundle<T, const N: usize> {
+    origin: [[T; N]; 3],
+    inv_dir: [[T; N]; 3],
+}
+
+impl<T: Float, const N: usize> PreparedBundle<T, N> {
+    #[inline]
+    fn new<U>(bundle: &RayBundle<T, U, N>) -> Self {
+        Self {
+            origin: bundle.origin,
+            inv_dir: bundle.dir.map(|d| d.map(T::recip)),
+        }
+    }
+
+    /// Slab tests every active ray against the box, returning which of them hit it
+    ///
+    /// The loops are over the lanes of fixed-size arrays, which the compiler turns into SIMD.
+    #[inline]
+    fn intersect<U>(&self, bounds: &Box3<T, U>, t_max: &[T; N], active: &[bool; N]) -> [bool; N] {
+        let mut t0 = [T::zero(); N];
+        let mut t1 = *t_max;
+        for (axis, (min, max)) in [
+            (bounds.min.x, bounds.max.x),
+            (bounds.min.y, bounds.max.y),
+            (bounds.min.z, bounds.max.z),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            for lane in 0..N {
+                let inv_dir = self.inv_dir[axis][lane];
+                let mut near = (min - self.origin[axis][lane]) * inv_dir;
+                let mut far = (max - self.origin[axis][lane]) * inv_dir;
+                if near > far {
+                    std::mem::swap(&mut near, &mut far);
+                }
+                t0[lane] = if near > t0[lane] { near } else { t0[lane] };
+                t1[lane] = if far < t1[lane] { far } else { t1[lane] };
+            }
+        }
+        std::array::from_fn(|lane| active[lane] && t0[lane] <= t1[lane])
+    }
+}
+
+impl<T: Float, U, P: Shape<T, U>> Bvh<T, U, P> {
+    /// Traverses the hierarchy once for all rays of the bundle, visiting each leaf with the
+    /// rays that hit its bounds
+    ///
+    /// The visitor receives a lane and returns its new `t_max`, or `None` to deactivate it.
+    fn traverse_bundle<const N: usize>(
+        &self,
+        bundle: &RayBundle<T, U, N>,
+        mut visit_leaf: impl FnMut(usize, usize, usize, T) -> Option<T>,
+    ) {
+        if self.nodes().is_empty() {
+            return;
+        }
+        let prepared = PreparedBundle::new(bundle);
+        let mut t_max = bundle.t_max;
+        let mut active = bundle.active;
+
+        let mut stack = [0; STACK_SIZE];
+        let mut stack_len = 0;
+        let mut current = 0;
+        loop {
+            let node = &self.nodes()[current];
+            let hits = prepared.intersect(&node.bounds, &t_max, &active);
+            if hits.contains(&true) {
+                if node.is_leaf() {
+                    let start = node.offset as usize;
+                    let end = start + node.count as usize;
+                    for lane in (0..N).filter(|&lane| hits[lane]) {
+                        match visit_leaf(lane, start, end, t_max[lane]) {
+                            Some(t) => t_max[lane] = t,
+                            None => active[lane] = false,
+                        }
+                    }
+                    if !active.contains(&true) {
+                        return;
+                    }
+                } else {
+                    // Order the children for the first ray, assuming the others are coherent
+                    let lane = hits.iter().position(|&hit| hit).unwrap();
+                    let dir = bundle.dir[node.axis as usize][lane];
+                    let (near, far) = if dir < T::zero() {
+                        (node.offset as usize, current + 1)
+                    } else {
+                        (current + 1, node.offset as usize)
+                    };
+                    stack[stack_len] = far;
+                    stack_len += 1;
+                    current = near;
+                    continue;
+                }
+            }
+            if stack_len == 0 {
+                return;
+            }
+            stack_len -= 1;
+            current = stack[stack_len];
+        }
+    }
+
+    pub(super) fn intersect_bundle_packet<const N: usize>(
+        &self,
+        bundle: &RayBundle<T, U, N>,
+    ) -> [Option<SurfaceInteraction<T, U>>; N] {
+        let mut closest = std::array::from_fn(|_| None);
+        self.traverse_bundle(bundle, |lane, start, end, mut t_max| {
+            let ray = bundle.ray(lane);
+            for i in start..end {
+                if let Some(mut hit) = self.primitives()[i].intersect(&ray, Time(t_max)) {
+                    t_max = hit.t.0;
+                    hit.primitive = i;
+                    closest[lane] = Some(hit);
+                }
+            }
+            Some(t_max)
+        });
+        closest
+    }
+
+    pub(super) fn intersect_any_bundle_packet<const N: usize>(
+        &self,
+        bundle: &RayBundle<T, U, N>,
+    ) -> [bool; N] {
+        let mut occluded = [false; N];
+        self.traverse_bundle(bundle, |lane, start, end, t_max| {
+            let ray = bundle.ray(lane);
+            occluded[lane] = self.primitives()[start..end]
+                .iter()
+                .any(|p| p.intersect_any(&ray, Time(t_max)));
+            (!occluded[lane]).then_some(t_max)
+        });
+        occluded
+    }
+}
@@ -0,0 +1,145 @@
+use std::ops::{Add, AddAssign};
+
+/// Counts of the work done by accelerators while tracing rays, for performance tuning
+///
+/// Stats of rays traced in parallel can be collected separately and summed afterwards.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct TraversalStats {
+    pub rays: u64,
+    /// Interior nodes, leaves or grid cells visited
+    pub node_visits: u64,
+    /// Intersection tests against primitives
+    pub primitive_tests: u64,
+    /// The most nodes deferred on the traversal stack at once, or the deepest nesting reached in
+    /// grids
+    pub max_depth: usize,
+}
+
+impl Add for TraversalStats {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, other: Self) -> Self {
+        Self {
+            rays: self.rays + other.rays,
+            node_visits: self.node_visits + other.node_visits,
+            primitive_tests: self.primitive_tests + other.primitive_tests,
+            max_depth: self.max_depth.max(other.max_depth),
+        }
+    }
+}
+
+impl AddAssign for TraversalStats {
+    #[inline]
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+/// Receives traversal events, so that recording them costs nothing when ignored with `()`
+pub(super) trait Recorder {
+    fn ray(&mut self);
+    fn node_visit(&mut self);
+    fn primitive_test(&mut self);
+    fn depth(&mut self, depth: usize);
+}
+
+impl Recorder for () {
+    #[inline(always)]
+    fn ray(&mut self) {}
+
+    #[inline(always)]
+    fn node_visit(&mut self) {}
+
+    #[inline(always)]
+    fn primitive_test(&mut self) {}
+
+    #[inline(always)]
+    fn depth(&mut self, _: usize) {}
+}
+
+impl Recorder for TraversalStats {
+    #[inline]
+    fn ray(&mut self) {
+        self.rays += 1;
+    }
+
+    #[inline]
+    fn node_visit(&mut self) {
+        self.node_visits += 1;
+    }
+
+    #[inline]
+    fn primitive_test(&mut self) {
+        self.primitive_tests += 1;
+    }
+
+    #[inline]
+    fn depth(&mut self, depth: usize) {
+        self.max_depth = self.max_depth.max(depth);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        accel::{Accelerator, Bvh, BvhBuildOptions, SplitMethod},
+        core::{
+            geometry::{Point3, Ray, UnknownUnit, Vector3},
+            units::Time,
+        },
+        shape::{Shape, Sphere},
+    };
+
+    #[test]
+    fn test_counts() {
+        // A root over two leaves of one sphere each, at x = -2 and x = 2
+        let spheres = vec![
+            Sphere::<f32, UnknownUnit>::new(Point3::new(-2., 0., 0.), 1.),
+            Sphere::new(Point3::new(2., 0., 0.), 1.),
+        ];
+        let options = BvhBuildOptions {
+            split_method: SplitMethod::Median,
+            max_leaf_size: 1,
+            ..Default::default()
+        };
+        let bvh = Bvh::with_options(spheres, &options);
+        assert_eq!(bvh.nodes().len(), 3);
+        let t_max = Time(f32::INFINITY);
+        let trace = |origin: Point3<f32, UnknownUnit>, any: bool| {
+            let ray = Ray::new(origin, Vector3::new(1., 0., 0.));
+            let mut stats = TraversalStats::default();
+            if any {
+                assert!(bvh.intersect_any_with_stats(&ray, t_max, &mut stats));
+                assert!(bvh.intersect_any(&ray, t_max));
+            } else {
+                let hit = bvh.intersect_with_stats(&ray, t_max, &mut stats);
+                assert_eq!(hit, bvh.intersect(&ray, t_max));
+            }
+            stats
+        };
+        let stats = |rays, node_visits, primitive_tests, max_depth| TraversalStats {
+            rays,
+            node_visits,
+            primitive_tests,
+            max_depth,
+        };
+
+        // The far leaf is deferred, then culled by the hit in the near one
+        let hit_near = trace(Point3::new(-5., 0., 0.), false);
+        assert_eq!(hit_near, stats(1, 3, 1, 1));
+        // Occlusion stops at the first hit
+        assert_eq!(trace(Point3::new(-5., 0., 0.), true), stats(1, 2, 1, 1));
+        // The near leaf is missed, and the far one hit
+        let hit_far = trace(Point3::new(0., 0., 0.), false);
+        assert_eq!(hit_far, stats(1, 3, 1, 1));
+        // Missing the root bounds visits nothing else
+        let miss = trace(Point3::new(-5., 5., 0.), false);
+        assert_eq!(miss, stats(1, 1, 0, 0));
+
+        let mut total = hit_near + hit_far;
+        total += miss;
+        assert_eq!(total, stats(3, 7, 2, 1));
+    }
+}
@@ -0,0 +1,166 @@
+use crate::{
+    accel::{lbvh::morton_code, Accelerator},
+    core::{
+        geometry::{Axis3, Box3, Ray, RayBundle},
+        units::Time,
+    },
+    shape::SurfaceInteraction,
+};
+use num_traits::Float;
+
+/// Bits per axis of the grid the ray origins are binned into
+const ORIGIN_BITS: u32 = 6;
+
+/// A batch of rays that are reordered to be coherent before they are traced
+///
+/// Rays are sorted by the octant of their direction and then along a Morton curve through the
+/// cells their origins lie in, so that rays traced one after another visit similar nodes and
+/// primitives. This mostly helps incoherent secondary rays, and lets them be traced in bundles.
+pub struct RayStream<T, U, D> {
+    bounds: Box3<T, U>,
+    rays: Vec<QueuedRay<T, U, D>>,
+}
+
+struct QueuedRay<T, U, D> {
+    key: u32,
+    ray: Ray<T, U, D>,
+    t_max: Time<T>,
+}
+
+impl<T: Float, U, D> RayStream<T, U, D> {
+    /// Creates an empty stream binning origins within `bounds`, usually those of the scene
+    #[inline]
+    #[must_use]
+    pub fn new(bounds: Box3<T, U>) -> Self {
+        Self {
+            bounds,
+            rays: Vec::new(),
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.rays.len()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.rays.is_empty()
+    }
+
+    /// Queues a ray extending up to `t_max`, with its payload in `ray.data`
+    pub fn push(&mut self, ray: Ray<T, U, D>, t_max: Time<T>) {
+        let key = self.key(&ray);
+        self.rays.push(QueuedRay { key, ray, t_max });
+    }
+
+    fn key(&self, ray: &Ray<T, U, D>) -> u32 {
+        let cells = T::from(1 << ORIGIN_BITS).unwrap();
+        let [x, y, z] = Axis3::AXES.map(|axis| {
+            let (min, max) = (self.bounds.min[axis], self.bounds.max[axis]);
+            let offset = (ray.origin[axis] - min) / (max - min) * cells;
+            // Origins outside the bounds or in flat bounds are clamped, or NaN and end up in 0
+            offset
+                .max(T::zero())
+                .to_u32()
+                .unwrap_or(0)
+                .min((1 << ORIGIN_BITS) - 1)
+        });
+        let octant = u32::from(ray.dir.x < T::zero())
+            | u32::from(ray.dir.y < T::zero()) << 1
+            | u32::from(ray.dir.z < T::zero()) << 2;
+        octant << (3 * ORIGIN_BITS) | morton_code(x, y, z)
+    }
+
+    /// Removes the queued rays in coherent order
+    pub fn drain(&mut self) -> impl Iterator<Item = (Ray<T, U, D>, Time<T>)> + '_ {
+        self.rays.sort_by_key(|queued| queued.key);
+        self.rays.drain(..).map(|queued| (queued.ray, queued.t_max))
+    }
+
+    /// Traces the queued rays in coherent order, in bundles of `N`, emptying the stream
+    ///
+    /// `f` receives every ray with its closest hit.
+    pub fn intersect<A: Accelerator<T, U>, const N: usize>(
+        &mut self,
+        accel: &A,
+        f: impl FnMut(Ray<T, U, D>, Option<SurfaceInteraction<T, U>>),
+    ) {
+        self.trace::<N, _>(|bundle| accel.intersect_bundle(bundle), f);
+    }
+
+    /// Tests the queued rays for occlusion in coherent order, in bundles of `N`, emptying the
+    /// stream
+    ///
+    /// `f` receives every ray with whether it is occluded.
+    pub fn intersect_any<A: Accelerator<T, U>, const N: usize>(
+        &mut self,
+        accel: &A,
+        f: impl FnMut(Ray<T, U, D>, bool),
+    ) {
+        self.trace::<N, _>(|bundle| accel.intersect_any_bundle(bundle), f);
+    }
+
+    fn trace<const N: usize, R>(
+        &mut self,
+        mut trace: impl FnMut(&RayBundle<T, U, N>) -> [R; N],
+        mut f: impl FnMut(Ray<T, U, D>, R),
+    ) {
+        let mut chunk = Vec::with_capacity(N);
+        let mut rays = self.drain().peekable();
+        while rays.peek().is_some() {
+            chunk.extend(rays.by_ref().take(N));
+            let bundle = RayBundle::new(chunk.iter().map(|(ray, t_max): &(Ray<T, U, D>, _)| {
+                (Ray::with_time(ray.origin, ray.dir, ray.time), *t_max)
+            }));
+            for ((ray, _), result) in chunk.drain(..).zip(trace(&bundle)) {
+                f(ray, result);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        accel::Bvh,
+        core::geometry::{Point3, UnknownUnit, Vector3},
+        shape::{Shape, Sphere},
+    };
+
+    #[test]
+    fn test_matches_unsorted() {
+        let spheres: Vec<_> = (0..64)
+            .map(|i| {
+                let p = Point3::new((i % 4) as f32, (i / 4 % 4) as f32, (i / 16) as f32) * 4.;
+                Sphere::<f32, UnknownUnit>::new(p, 1.)
+            })
+            .collect();
+        let bvh = Bvh::new(spheres);
+
+        let mut stream = RayStream::new(bvh.bounds());
+        let mut rays = Vec::new();
+        for i in 0..100_usize {
+            let f = i as f32;
+            let origin = Point3::new(f.sin() * 8., f.cos() * 8., (f * 0.37).sin() * 8.);
+            let dir = Vector3::new((f * 1.3).cos(), (f * 0.7).sin(), (f * 2.1).cos());
+            let ray = Ray::with_data(origin, dir, i);
+            stream.push(ray, Time(f32::INFINITY));
+            rays.push(ray);
+        }
+        assert_eq!(stream.len(), 100);
+
+        let mut seen = vec![false; rays.len()];
+        stream.intersect::<_, 8>(&bvh, |ray, hit| {
+            let expected = bvh.intersect(&Ray::new(ray.origin, ray.dir), Time(f32::INFINITY));
+            assert_eq!(ray, rays[ray.data]);
+            assert_eq!(hit, expected);
+            seen[ray.data] = true;
+        });
+        assert!(stream.is_empty());
+        assert!(seen.into_iter().all(|seen| seen));
+    }
+}
@@ -0,0 +1,211 @@
+use crate::{
+    accel::{Accelerator, Bvh},
+    core::{
+        geometry::{
+            transform::{Transform3, Transformation},
+            Box3, Ray,
+        },
+        units::Time,
+    },
+    shape::{Shape, SurfaceInteraction},
+};
+use num_traits::Float;
+use std::{fmt, sync::Arc};
+
+/// The error of placing an instance with a projective transform, whose bounds cannot be
+/// transformed
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct InstanceTransformError;
+
+impl fmt::Display for InstanceTransformError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("instance transforms must be affine")
+    }
+}
+
+impl std::error::Error for InstanceTransformError {}
+
+/// A placement of a shared bottom-level structure, whose geometry lives in its own space `O`
+pub struct BlasInstance<T, U, O, B: ?Sized> {
+    blas: Arc<B>,
+    object_to_world: Transform3<T, O, U>,
+    world_to_object: Transform3<T, U, O>,
+    bounds: Box3<T, U>,
+}
+
+impl<T: Float + fmt::Debug, U, O, B: ?Sized> fmt::Debug for BlasInstance<T, U, O, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlasInstance")
+            .field("object_to_world", &self.object_to_world)
+            .field("bounds", &self.bounds)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Copy, U, O, B: ?Sized> Clone for BlasInstance<T, U, O, B> {
+    fn clone(&self) -> Self {
+        Self {
+            blas: Arc::clone(&self.blas),
+            object_to_world: self.object_to_world,
+            world_to_object: self.world_to_object,
+            bounds: self.bounds,
+        }
+    }
+}
+
+impl<T: Float, U, O, B: Shape<T, O> + ?Sized> BlasInstance<T, U, O, B> {
+    /// Places the structure in the world, failing if the transform is projective
+    pub fn new(
+        blas: Arc<B>,
+        object_to_world: Transform3<T, O, U>,
+    ) -> Result<Self, InstanceTransformError> {
+        let bounds = object_to_world
+            .transform(blas.bounds())
+            .ok_or(InstanceTransformError)?;
+        Ok(Self {
+            blas,
+            world_to_object: object_to_world.inverse(),
+            object_to_world,
+            bounds,
+        })
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn blas(&self) -> &Arc<B> {
+        &self.blas
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn object_to_world(&self) -> &Transform3<T, O, U> {
+        &self.object_to_world
+    }
+}
+
+impl<T: Float, U, O, B: Shape<T, O> + ?Sized> Shape<T, U> for BlasInstance<T, U, O, B> {
+    #[inline]
+    fn bounds(&self) -> Box3<T, U> {
+        self.bounds
+    }
+
+    fn intersect(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<SurfaceInteraction<T, U>> {
+        let ray = self.world_to_object.transform(*ray)?;
+        self.blas
+            .intersect(&ray, t_max)?
+            .transform(&self.object_to_world)
+    }
+
+    fn intersect_any(&self, ray: &Ray<T, U>, t_max: Time<T>) -> bool {
+        self.world_to_object
+            .transform(*ray)
+            .is_some_and(|ray| self.blas.intersect_any(&ray, t_max))
+    }
+}
+
+/// A top-level hierarchy over instances of shared bottom-level structures
+///
+/// Reported hits carry the index of the instance in [`instances`](Self::instances), while
+/// `primitive` is left as set by the bottom-level structure.
+pub struct Tlas<T, U, O, B> {
+    bvh: Bvh<T, U, BlasInstance<T, U, O, B>>,
+}
+
+impl<T: Float, U, O, B: Shape<T, O>> Tlas<T, U, O, B> {
+    #[must_use]
+    pub fn new(instances: Vec<BlasInstance<T, U, O, B>>) -> Self {
+        Self {
+            bvh: Bvh::new(instances),
+        }
+    }
+
+    /// Returns the instances, in the order reported in hits
+    #[inline]
+    #[must_use]
+    pub fn instances(&self) -> &[BlasInstance<T, U, O, B>] {
+        self.bvh.primitives()
+    }
+}
+
+impl<T: Float, U, O, B: Shape<T, O>> Shape<T, U> for Tlas<T, U, O, B> {
+    #[inline]
+    fn bounds(&self) -> Box3<T, U> {
+        self.bvh.bounds()
+    }
+
+    fn intersect(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<SurfaceInteraction<T, U>> {
+        let instances = self.instances();
+        let mut closest = None;
+        self.bvh
+            .traverse(ray, t_max.0, &mut (), |start, end, mut t_max, _| {
+                for (i, instance) in instances.iter().enumerate().take(end).skip(start) {
+                    if let Some(mut hit) = instance.intersect(ray, Time(t_max)) {
+                        t_max = hit.t.0;
+                        hit.instance = Some(i);
+                        closest = Some(hit);
+                    }
+                }
+                Some(t_max)
+            });
+        closest
+    }
+
+    #[inline]
+    fn intersect_any(&self, ray: &Ray<T, U>, t_max: Time<T>) -> bool {
+        self.bvh.intersect_any(ray, t_max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::{
+            geometry::{transform::Scale, Point3, UnknownUnit, Vector3},
+            num::ApproxEq,
+        },
+        shape::Sphere,
+    };
+
+    enum Object {}
+
+    type V = Vector3<f32, UnknownUnit>;
+    type P = Point3<f32, UnknownUnit>;
+
+    #[test]
+    fn test_intersect() {
+        let blas = Arc::new(Bvh::new(vec![Sphere::<f32, Object>::new(
+            Point3::origin(),
+            1.,
+        )]));
+        let scale = Transform3::scale(Scale::new(2.), Scale::new(2.), Scale::new(2.));
+        let tlas = Tlas::new(
+            [-5., 5.]
+                .map(|x| {
+                    let object_to_world = scale * Transform3::translation(V::new(x, 0., 0.));
+                    BlasInstance::new(Arc::clone(&blas), object_to_world).unwrap()
+                })
+                .into(),
+        );
+        assert!(tlas.bounds().min.approx_eq(&P::new(-7., -2., -2.)));
+
+        let ray = Ray::new(P::new(-20., 0., 0.), V::new(1., 0., 0.));
+        let hit = tlas.intersect(&ray, Time(f32::INFINITY)).unwrap();
+        assert!(hit.t.approx_eq(&Time(13.)));
+        assert!(hit.p.approx_eq(&P::new(-7., 0., 0.)));
+        assert!(hit.n.to_vector().approx_eq(&V::new(-1., 0., 0.)));
+        assert!(tlas.instances()[hit.instance.unwrap()].bounds().max.x < 0.);
+
+        let ray = Ray::new(P::new(20., 0., 0.), V::new(1., 0., 0.));
+        assert!(!tlas.intersect_any(&ray, Time(f32::INFINITY)));
+
+        // A projection which sends the top of the sphere to infinity cannot place it
+        let mut mat = Transform3::<f32, Object, UnknownUnit>::identity().to_array();
+        mat[2][3] = -1.;
+        let projective = Transform3::<_, _, UnknownUnit>::try_new(mat).unwrap();
+        assert_eq!(
+            BlasInstance::new(blas, projective).err(),
+            Some(InstanceTransformError)
+        );
+    }
+}
@@ -0,0 +1,696 @@
+use crate::{
+    accel::{
+        bvh::{BvhNode, STACK_SIZE},
+        stats::{Recorder, TraversalStats},
+        Accelerator, Bvh,
+    },
+    core::{
+        geometry::{Box3, Ray},
+        units::Time,
+    },
+    shape::{Shape, SurfaceInteraction},
+};
+use num_traits::Float;
+
+/// Marks a lane of a node that has no child
+const EMPTY: u32 = u32::MAX;
+
+/// A node with up to `N` children, whose bounds are stored per axis across the children so that
+/// they are tested all at once
+#[derive(Debug, Clone)]
+pub(crate) struct WideNode<T, const N: usize> {
+    pub(crate) min: [[T; N]; 3],
+    pub(crate) max: [[T; N]; 3],
+    /// The index of an interior child, or the first primitive of a leaf
+    pub(crate) child: [u32; N],
+    /// The number of primitives in a leaf, or zero for interior children
+    pub(crate) count: [u32; N],
+}
+
+impl<T: Float, const N: usize> WideNode<T, N> {
+    /// Returns a node whose lanes are all empty, with inverted bounds that no ray can hit
+    fn empty() -> Self {
+        Self {
+            min: [[T::infinity(); N]; 3],
+            max: [[T::neg_infinity(); N]; 3],
+            child: [EMPTY; N],
+            count: [0; N],
+        }
+    }
+
+    fn set_bounds<U>(&mut self, lane: usize, bounds: &Box3<T, U>) {
+        for (axis, (min, max)) in [
+            (bounds.min.x, bounds.max.x),
+            (bounds.min.y, bounds.max.y),
+            (bounds.min.z, bounds.max.z),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            self.min[axis][lane] = min;
+            self.max[axis][lane] = max;
+        }
+    }
+
+    /// Returns the entry distances of the ray into every lane, with misses set to infinity
+    #[inline]
+    fn intersect(&self, ray: &PreparedRay<T>, t_max: T) -> [T; N] {
+        intersect_lanes(ray, t_max, |axis, lane, is_max| {
+            if is_max {
+                self.max[axis][lane]
+            } else {
+                self.min[axis][lane]
+            }
+        })
+    }
+}
+
+/// Slab tests the ray against the boxes of every lane, returning the entry distances with misses
+/// set to infinity
+///
+/// `bound(axis, lane, is_max)` returns a side of the box of a lane. The loops are over the lanes
+/// of fixed-size arrays, which the compiler turns into SIMD.
+#[inline(always)]
+fn intersect_lanes<T: Float, const N: usize>(
+    ray: &PreparedRay<T>,
+    t_max: T,
+    bound: impl Fn(usize, usize, bool) -> T,
+) -> [T; N] {
+    let mut t0 = [T::zero(); N];
+    let mut t1 = [t_max; N];
+    for axis in 0..3 {
+        let near_is_max = ray.dir_is_neg[axis];
+        for lane in 0..N {
+            let t_near = (bound(axis, lane, near_is_max) - ray.origin[axis]) * ray.inv_dir[axis];
+            let t_far = (bound(axis, lane, !near_is_max) - ray.origin[axis]) * ray.inv_dir[axis];
+            t0[lane] = if t_near > t0[lane] { t_near } else { t0[lane] };
+            t1[lane] = if t_far < t1[lane] { t_far } else { t1[lane] };
+        }
+    }
+    let mut hits = [T::infinity(); N];
+    for lane in 0..N {
+        if t0[lane] <= t1[lane] {
+            hits[lane] = t0[lane];
+        }
+    }
+    hits
+}
+
+/// The largest quantized coordinate
+const STEPS: u8 = u8::MAX;
+
+/// The quantized coordinates converted to `T`, so that traversal needn't convert them
+type Levels<T> = [T; STEPS as usize + 1];
+
+fn levels<T: Float>() -> Box<Levels<T>> {
+    Box::new(std::array::from_fn(|q| T::from(q).unwrap()))
+}
+
+/// A [`WideNode`] whose child bounds are quantized to 8 bits per coordinate, relative to the
+/// bounds of the node
+///
+/// The quantized boxes are rounded outwards, so that they still contain the children.
+#[derive(Debug, Clone)]
+pub(crate) struct QuantizedNode<T, const N: usize> {
+    /// The minimum corner of the bounds of the node
+    pub(crate) origin: [T; 3],
+    /// The size of a quantization step along each axis
+    pub(crate) scale: [T; 3],
+    pub(crate) min: [[u8; N]; 3],
+    pub(crate) max: [[u8; N]; 3],
+    pub(crate) child: [u32; N],
+    pub(crate) count: [u32; N],
+}
+
+impl<T: Float, const N: usize> QuantizedNode<T, N> {
+    /// Quantizes the bounds of a node, unless some are unbounded
+    fn new(node: &WideNode<T, N>, levels: &Levels<T>) -> Option<Self> {
+        let lanes = || (0..N).filter(|&lane| node.child[lane] != EMPTY);
+        let steps = T::from(STEPS).unwrap();
+        let mut origin = [T::zero(); 3];
+        let mut scale = [T::zero(); 3];
+        // Empty lanes keep inverted boxes
+        let mut min = [[STEPS; N]; 3];
+        let mut max = [[0; N]; 3];
+        for axis in 0..3 {
+            let lower = lanes()
+                .map(|lane| node.min[axis][lane])
+                .fold(T::infinity(), T::min);
+            let upper = lanes()
+                .map(|lane| node.max[axis][lane])
+                .fold(T::neg_infinity(), T::max);
+            if !(lower.is_finite() && upper.is_finite()) {
+                return None;
+            }
+            // Grown until the last step reaches the top of the node despite rounding
+            let mut step = (upper - lower) / steps;
+            while lower + steps * step < upper {
+                step = step * (T::one() + T::epsilon()) + T::min_positive_value();
+            }
+            let decode = |q: u8| lower + levels[usize::from(q)] * step;
+            for lane in lanes() {
+                let (lo, hi) = (node.min[axis][lane], node.max[axis][lane]);
+                let (mut q_min, mut q_max) = if step > T::zero() {
+                    // Only rounding past the top of the node leaves the range of a byte
+                    let q = |x: T| x.to_u8().unwrap_or(STEPS);
+                    (
+                        q(((lo - lower) / step).floor()),
+                        q(((hi - lower) / step).ceil()),
+                    )
+                } else {
+                    (0, 0)
+                };
+                while q_min > 0 && decode(q_min) > lo {
+                    q_min -= 1;
+                }
+                while q_max < STEPS && decode(q_max) < hi {
+                    q_max += 1;
+                }
+                min[axis][lane] = q_min;
+                max[axis][lane] = q_max;
+            }
+            (origin[axis], scale[axis]) = (lower, step);
+        }
+        Some(Self {
+            origin,
+            scale,
+            min,
+            max,
+            child: node.child,
+            count: node.count,
+        })
+    }
+
+    /// Returns a side of the box of a lane, which contains the box it was quantized from
+    #[inline(always)]
+    fn bound(&self, levels: &Levels<T>, axis: usize, lane: usize, is_max: bool) -> T {
+        let q = if is_max {
+            self.max[axis][lane]
+        } else {
+            self.min[axis][lane]
+        };
+        self.origin[axis] + levels[usize::from(q)] * self.scale[axis]
+    }
+
+    /// Returns the entry distances of the ray into every lane, with misses set to infinity
+    #[inline]
+    fn intersect(&self, levels: &Levels<T>, ray: &PreparedRay<T>, t_max: T) -> [T; N] {
+        intersect_lanes(ray, t_max, |axis, lane, is_max| {
+            self.bound(levels, axis, lane, is_max)
+        })
+    }
+}
+
+/// The layouts of the nodes of a wide BVH
+trait Node<const N: usize> {
+    fn child(&self) -> &[u32; N];
+
+    fn count(&self) -> &[u32; N];
+}
+
+impl<T, const N: usize> Node<N> for WideNode<T, N> {
+    #[inline]
+    fn child(&self) -> &[u32; N] {
+        &self.child
+    }
+
+    #[inline]
+    fn count(&self) -> &[u32; N] {
+        &self.count
+    }
+}
+
+impl<T, const N: usize> Node<N> for QuantizedNode<T, N> {
+    #[inline]
+    fn child(&self) -> &[u32; N] {
+        &self.child
+    }
+
+    #[inline]
+    fn count(&self) -> &[u32; N] {
+        &self.count
+    }
+}
+
+/// The nodes of a wide BVH, in either layout
+#[derive(Debug, Clone)]
+enum Nodes<T, const N: usize> {
+    Full(Vec<WideNode<T, N>>),
+    Quantized {
+        nodes: Vec<QuantizedNode<T, N>>,
+        levels: Box<Levels<T>>,
+    },
+}
+
+struct PreparedRay<T> {
+    origin: [T; 3],
+    inv_dir: [T; 3],
+    dir_is_neg: [bool; 3],
+}
+
+impl<T: Float> PreparedRay<T> {
+    #[inline]
+    fn new<U>(ray: &Ray<T, U>) -> Self {
+        let (o, d) = (ray.origin, ray.dir);
+        Self {
+            origin: [o.x, o.y, o.z],
+            inv_dir: [d.x.recip(), d.y.recip(), d.z.recip()],
+            dir_is_neg: [d.x < T::zero(), d.y < T::zero(), d.z < T::zero()],
+        }
+    }
+}
+
+/// A BVH whose nodes have up to `N` children, collapsed from a binary [`Bvh`]
+///
+/// Wider nodes make for shallower trees and test several boxes per step, which makes traversal
+/// considerably faster. `N` must be between 2 and 8.
+///
+/// The child bounds can be [quantized](Self::quantized) to a byte per coordinate, which makes
+/// the nodes about half as large for slightly looser boxes.
+pub struct WideBvh<T, U, P, const N: usize> {
+    primitives: Vec<P>,
+    nodes: Nodes<T, N>,
+    bounds: Box3<T, U>,
+}
+
+pub type Bvh4<T, U, P> = WideBvh<T, U, P, 4>;
+pub type Bvh8<T, U, P> = WideBvh<T, U, P, 8>;
+
+impl<T, U, P, const N: usize> WideBvh<T, U, P, N> {
+    #[inline]
+    #[must_use]
+    pub fn into_primitives(self) -> Vec<P> {
+        self.primitives
+    }
+
+    /// Returns whether the child bounds of the nodes are quantized
+    #[inline]
+    #[must_use]
+    pub fn is_quantized(&self) -> bool {
+        matches!(self.nodes, Nodes::Quantized { .. })
+    }
+}
+
+impl<T: Float, U, P: Shape<T, U>, const N: usize> WideBvh<T, U, P, N> {
+    /// Collapses the binary BVH into nodes whose child bounds are quantized, unless some
+    /// primitives are unbounded
+    #[must_use]
+    pub fn quantized(bvh: Bvh<T, U, P>) -> Self {
+        let mut wide = Self::from(bvh);
+        if let Nodes::Full(nodes) = &wide.nodes {
+            let levels = levels();
+            let quantized = nodes.iter().map(|node| QuantizedNode::new(node, &levels));
+            if let Some(nodes) = quantized.collect() {
+                wide.nodes = Nodes::Quantized { nodes, levels };
+            }
+        }
+        wide
+    }
+}
+
+impl<T: Float, U, P: Shape<T, U>, const N: usize> From<Bvh<T, U, P>> for WideBvh<T, U, P, N> {
+    fn from(bvh: Bvh<T, U, P>) -> Self {
+        assert!(
+            (2..=8).contains(&N),
+            "wide BVH nodes must have 2 to 8 lanes"
+        );
+        let bounds = bvh.bounds();
+        let binary = bvh.nodes().to_vec();
+        let mut nodes = Vec::with_capacity(binary.len() / (N - 1) + 1);
+        match binary.first() {
+            None => {}
+            Some(root) if root.is_leaf() => {
+                let mut node = WideNode::empty();
+                node.set_bounds(0, &root.bounds);
+                node.child[0] = root.offset;
+                node.count[0] = root.count;
+                nodes.push(node);
+            }
+            Some(_) => {
+                collapse(&binary, 0, &mut nodes);
+            }
+        }
+        Self {
+            primitives: bvh.into_primitives(),
+            nodes: Nodes::Full(nodes),
+            bounds,
+        }
+    }
+}
+
+/// Appends the wide node replacing the binary interior node at `index`, returning its index
+fn collapse<T: Float, U, const N: usize>(
+    binary: &[BvhNode<T, U>],
+    index: usize,
+    nodes: &mut Vec<WideNode<T, N>>,
+) -> u32 {
+    let node = &binary[index];
+    let mut children = vec![index + 1, node.offset as usize];
+    // Pull up the grandchildren of the largest interior children until all lanes are used
+    while children.len() < N {
+        let largest = children
+            .iter()
+            .enumerate()
+            .filter(|(_, &child)| !binary[child].is_leaf())
+            .max_by(|(_, &a), (_, &b)| {
+                let (a, b) = (
+                    binary[a].bounds.surface_area(),
+                    binary[b].bounds.surface_area(),
+                );
+                a.partial_cmp(&b).unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .map(|(i, _)| i);
+        let Some(i) = largest else {
+            break;
+        };
+        let child = children.swap_remove(i);
+        children.push(child + 1);
+        children.push(binary[child].offset as usize);
+    }
+
+    let wide_index = nodes.len();
+    nodes.push(WideNode::empty());
+    for (lane, child_index) in children.into_iter().enumerate() {
+        let child = &binary[child_index];
+        let (target, count) = if child.is_leaf() {
+            (child.offset, child.count)
+        } else {
+            (collapse(binary, child_index, nodes), 0)
+        };
+        let node = &mut nodes[wide_index];
+        node.set_bounds(lane, &child.bounds);
+        node.child[lane] = target;
+        node.count[lane] = count;
+    }
+    wide_index as u32
+}
+
+impl<T: Float, U, P: Shape<T, U>, const N: usize> WideBvh<T, U, P, N> {
+    /// Visits the leaves whose bounds the ray enters before `t_max`, roughly nearest first
+    ///
+    /// The visitor returns the new `t_max`, or `None` to stop the traversal.
+    #[inline]
+    fn traverse<S: Recorder>(
+        &self,
+        ray: &Ray<T, U>,
+        t_max: T,
+        stats: &mut S,
+        visit_leaf: impl FnMut(usize, usize, T, &mut S) -> Option<T>,
+    ) {
+        match &self.nodes {
+            Nodes::Full(nodes) => {
+                Self::traverse_nodes(nodes, WideNode::intersect, ray, t_max, stats, visit_leaf);
+            }
+            Nodes::Quantized { nodes, levels } => {
+                let intersect =
+                    |node: &QuantizedNode<T, N>, ray: &_, t_max| node.intersect(levels, ray, t_max);
+                Self::traverse_nodes(nodes, intersect, ray, t_max, stats, visit_leaf);
+            }
+        }
+    }
+
+    #[inline]
+    fn traverse_nodes<S: Recorder, Nd: Node<N>>(
+        nodes: &[Nd],
+        intersect: impl Fn(&Nd, &PreparedRay<T>, T) -> [T; N],
+        ray: &Ray<T, U>,
+        mut t_max: T,
+        stats: &mut S,
+        mut visit_leaf: impl FnMut(usize, usize, T, &mut S) -> Option<T>,
+    ) {
+        if nodes.is_empty() {
+            return;
+        }
+        stats.ray();
+        let prepared = PreparedRay::new(ray);
+
+        // Entries are `(t_near, child, count)`, as in the node lanes
+        let mut stack = [(T::zero(), 0, 0); STACK_SIZE * 8];
+        let mut stack_len = 1;
+        while stack_len > 0 {
+            stack_len -= 1;
+            let (t_near, child, count) = stack[stack_len];
+            if t_near > t_max {
+                continue;
+            }
+            stats.node_visit();
+            if count > 0 {
+                let start = child as usize;
+                match visit_leaf(start, start + count as usize, t_max, stats) {
+                    Some(t) => t_max = t,
+                    None => return,
+                }
+                continue;
+            }
+
+            let node = &nodes[child as usize];
+            let hits = intersect(node, &prepared, t_max);
+            let first = stack_len;
+            for ((&t, &child), &count) in hits.iter().zip(node.child()).zip(node.count()) {
+                // Misses are infinitely far, which an unbounded ray would still reach
+                if t < T::infinity() && t <= t_max && child != EMPTY {
+                    stack[stack_len] = (t, child, count);
+                    stack_len += 1;
+                }
+            }
+            stats.depth(stack_len);
+            // Farthest first, so that the nearest child is popped next
+            stack[first..stack_len].sort_unstable_by(|a, b| {
+                b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal)
+            });
+        }
+    }
+}
+
+impl<T: Float, U, P: Shape<T, U>, const N: usize> WideBvh<T, U, P, N> {
+    fn intersect_recorded(
+        &self,
+        ray: &Ray<T, U>,
+        t_max: Time<T>,
+        stats: &mut impl Recorder,
+    ) -> Option<SurfaceInteraction<T, U>> {
+        let mut closest = None;
+        self.traverse(ray, t_max.0, stats, |start, end, mut t_max, stats| {
+            for i in start..end {
+                stats.primitive_test();
+                if let Some(mut hit) = self.primitives[i].intersect(ray, Time(t_max)) {
+                    t_max = hit.t.0;
+                    hit.primitive = i;
+                    closest = Some(hit);
+                }
+            }
+            Some(t_max)
+        });
+        closest
+    }
+
+    fn intersect_any_recorded(
+        &self,
+        ray: &Ray<T, U>,
+        t_max: Time<T>,
+        stats: &mut impl Recorder,
+    ) -> bool {
+        let mut occluded = false;
+        self.traverse(ray, t_max.0, stats, |start, end, t_max, stats| {
+            occluded = self.primitives[start..end].iter().any(|p| {
+                stats.primitive_test();
+                p.intersect_any(ray, Time(t_max))
+            });
+            (!occluded).then_some(t_max)
+        });
+        occluded
+    }
+}
+
+impl<T: Float, U, P: Shape<T, U>, const N: usize> Shape<T, U> for WideBvh<T, U, P, N> {
+    #[inline]
+    fn bounds(&self) -> Box3<T, U> {
+        self.bounds
+    }
+
+    #[inline]
+    fn intersect(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<SurfaceInteraction<T, U>> {
+        self.intersect_recorded(ray, t_max, &mut ())
+    }
+
+    #[inline]
+    fn intersect_any(&self, ray: &Ray<T, U>, t_max: Time<T>) -> bool {
+        self.intersect_any_recorded(ray, t_max, &mut ())
+    }
+}
+
+impl<T: Float, U, P: Shape<T, U>, const N: usize> Accelerator<T, U> for WideBvh<T, U, P, N> {
+    type Primitive = P;
+
+    #[inline]
+    fn primitives(&self) -> &[P] {
+        &self.primitives
+    }
+
+    #[inline]
+    fn intersect_with_stats(
+        &self,
+        ray: &Ray<T, U>,
+        t_max: Time<T>,
+        stats: &mut TraversalStats,
+    ) -> Option<SurfaceInteraction<T, U>> {
+        self.intersect_recorded(ray, t_max, stats)
+    }
+
+    #[inline]
+    fn intersect_any_with_stats(
+        &self,
+        ray: &Ray<T, U>,
+        t_max: Time<T>,
+        stats: &mut TraversalStats,
+    ) -> bool {
+        self.intersect_any_recorded(ray, t_max, stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        accel::bvh::tests::{random, random_ray, triangle_soup},
+        core::{
+            geometry::{RayBundle, UnknownUnit},
+            prelude::Normal3,
+        },
+        shape::{Plane, Triangle},
+    };
+
+    type Hit = Option<SurfaceInteraction<f32, UnknownUnit>>;
+
+    /// Checks every entry point of the wide BVH against the hits of the binary one
+    fn check<const N: usize>(
+        wide: &WideBvh<f32, UnknownUnit, Triangle<f32, UnknownUnit>, N>,
+        rays: &[(Ray<f32, UnknownUnit>, Time<f32>)],
+        hits: &[Hit],
+        occluded: &[bool],
+    ) {
+        for (i, (ray, t_max)) in rays.iter().enumerate() {
+            assert_eq!(wide.intersect(ray, *t_max), hits[i]);
+            assert_eq!(wide.intersect_any(ray, *t_max), occluded[i]);
+        }
+        assert_eq!(wide.intersect_batch(rays), hits);
+        assert_eq!(wide.intersect_any_batch(rays), occluded);
+
+        // Bundles with an inactive lane
+        for (i, chunk) in rays.chunks(7).enumerate() {
+            let bundle = RayBundle::<_, _, 8>::new(chunk.iter().copied());
+            let range = i * 7..i * 7 + chunk.len();
+            let bundle_hits = wide.intersect_bundle(&bundle);
+            let bundle_occluded = wide.intersect_any_bundle(&bundle);
+            assert_eq!(bundle_hits[..chunk.len()], hits[range.clone()]);
+            assert_eq!(bundle_occluded[..chunk.len()], occluded[range]);
+            assert!(bundle_hits[chunk.len()..].iter().all(Option::is_none));
+            assert!(!bundle_occluded[chunk.len()..].contains(&true));
+        }
+    }
+
+    #[test]
+    fn test_matches_binary_bvh() {
+        let mut state = 0xfeed_beef;
+        let triangles = triangle_soup(&mut state, 2000);
+        let rays: Vec<_> = (0..1000)
+            .map(|_| {
+                let ray = random_ray(&mut state);
+                let t_max = if random(&mut state) < 0.3 {
+                    10.
+                } else {
+                    f32::INFINITY
+                };
+                (ray, Time(t_max))
+            })
+            .collect();
+
+        let bvh = Bvh::new(triangles.clone());
+        let hits: Vec<_> = rays.iter().map(|(ray, t)| bvh.intersect(ray, *t)).collect();
+        let occluded: Vec<_> = rays
+            .iter()
+            .map(|(ray, t)| bvh.intersect_any(ray, *t))
+            .collect();
+        let count = hits.iter().filter(|hit| hit.is_some()).count();
+        assert!(count > 100 && count < 900, "{count}");
+
+        let mut binary_stats = TraversalStats::default();
+        for (ray, t_max) in &rays {
+            bvh.intersect_with_stats(ray, *t_max, &mut binary_stats);
+        }
+
+        // Collapsing keeps the order of the primitives, and so the indices of the hits
+        let bvh4 = Bvh4::from(bvh);
+        check(&bvh4, &rays, &hits, &occluded);
+        check(
+            &Bvh8::from(Bvh::new(triangles.clone())),
+            &rays,
+            &hits,
+            &occluded,
+        );
+
+        // Shallower trees visit fewer nodes, for about the same primitive tests
+        let mut stats = TraversalStats::default();
+        for (ray, t_max) in &rays {
+            bvh4.intersect_with_stats(ray, *t_max, &mut stats);
+        }
+        assert!(
+            stats.node_visits * 2 < binary_stats.node_visits,
+            "{stats:?}"
+        );
+        assert!(
+            stats.primitive_tests * 10 < binary_stats.primitive_tests * 11,
+            "{stats:?}"
+        );
+
+        // Quantized boxes find the same hits, for a few more visits through their looser bounds
+        let full = bvh4;
+        let quantized = Bvh4::quantized(Bvh::new(triangles.clone()));
+        assert!(!full.is_quantized() && quantized.is_quantized());
+        check(&quantized, &rays, &hits, &occluded);
+        check(
+            &Bvh8::quantized(Bvh::new(triangles)),
+            &rays,
+            &hits,
+            &occluded,
+        );
+        let mut quantized_stats = TraversalStats::default();
+        for (ray, t_max) in &rays {
+            quantized.intersect_with_stats(ray, *t_max, &mut quantized_stats);
+        }
+        assert!(
+            quantized_stats.node_visits >= stats.node_visits
+                && quantized_stats.node_visits * 10 < stats.node_visits * 12,
+            "{quantized_stats:?}"
+        );
+        assert!(
+            std::mem::size_of::<QuantizedNode<f32, 8>>() * 10
+                < std::mem::size_of::<WideNode<f32, 8>>() * 6
+        );
+
+        // The quantized boxes contain the children, within a step of their bounds
+        let (Nodes::Full(full), Nodes::Quantized { nodes, levels }) =
+            (&full.nodes, &quantized.nodes)
+        else {
+            unreachable!();
+        };
+        assert_eq!(full.len(), nodes.len());
+        for (node, encoded) in full.iter().zip(nodes) {
+            assert_eq!((node.child, node.count), (encoded.child, encoded.count));
+            for (axis, scale) in encoded.scale.into_iter().enumerate() {
+                for lane in (0..4).filter(|&lane| node.child[lane] != EMPTY) {
+                    let (lo, hi) = (node.min[axis][lane], node.max[axis][lane]);
+                    let min = encoded.bound(levels, axis, lane, false);
+                    let max = encoded.bound(levels, axis, lane, true);
+                    assert!(min <= lo && max >= hi);
+                    assert!(lo - min <= scale && max - hi <= scale);
+                }
+            }
+        }
+
+        // Unbounded primitives keep the bounds at full precision
+        let plane = Plane::<f32, UnknownUnit>::new(Normal3::new(0., 0., 1.), 0.);
+        let unbounded = Bvh4::quantized(Bvh::new(vec![plane, plane]));
+        assert!(!unbounded.is_quantized());
+    }
+}
@@ -0,0 +1,204 @@
+use crate::{
+    bsdf::{
+        fresnel_complex_rgb, reflect, same_hemisphere, Bsdf, BsdfFlags, BsdfSample, ShadingSpace,
+        TrowbridgeReitz,
+    },
+    color::Rgb,
+    core::geometry::{Point2, UnknownUnit, Vector3},
+};
+use num_traits::{Float, FloatConst};
+
+/// Metals with built-in optical constants, fitted to the sRGB primaries from measured spectra
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Metal {
+    Gold,
+    Silver,
+    Copper,
+    Aluminum,
+}
+
+impl Metal {
+    /// The real part of the index of refraction
+    #[must_use]
+    pub fn eta<T: Float>(self) -> Rgb<T> {
+        let eta = match self {
+            Self::Gold => [0.18299, 0.42108, 1.3734],
+            Self::Silver => [0.15943, 0.14512, 0.13547],
+            Self::Copper => [0.27105, 0.67693, 1.31640],
+            Self::Aluminum => [1.6574, 0.88037, 0.52123],
+        };
+        Rgb::from(eta.map(|x| T::from(x).unwrap()))
+    }
+
+    /// The absorption coefficient, the imaginary part of the index of refraction
+    #[must_use]
+    pub fn k<T: Float>(self) -> Rgb<T> {
+        let k = match self {
+            Self::Gold => [3.4242, 2.3459, 1.7704],
+            Self::Silver => [3.9291, 3.1900, 2.3808],
+            Self::Copper => [3.60920, 2.62480, 2.29210],
+            Self::Aluminum => [9.2238, 6.2695, 4.837],
+        };
+        Rgb::from(k.map(|x| T::from(x).unwrap()))
+    }
+}
+
+/// The surface of a metal, reflecting light as given by the Fresnel equations for its complex
+/// index of refraction
+///
+/// Rough surfaces are modeled with a microfacet distribution, and ones that are effectively
+/// smooth as a mirror. Both sides of the surface reflect alike.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct ConductorBsdf<T> {
+    pub distribution: TrowbridgeReitz<T>,
+    /// The index of refraction relative to the outside
+    pub eta: Rgb<T>,
+    /// The absorption coefficient relative to the outside
+    pub k: Rgb<T>,
+}
+
+impl<T> ConductorBsdf<T> {
+    #[inline]
+    #[must_use]
+    pub const fn new(distribution: TrowbridgeReitz<T>, eta: Rgb<T>, k: Rgb<T>) -> Self {
+        Self {
+            distribution,
+            eta,
+            k,
+        }
+    }
+}
+
+impl<T: Float> ConductorBsdf<T> {
+    /// Returns a smooth or rough surface of a built-in metal
+    #[inline]
+    #[must_use]
+    pub fn from_metal(distribution: TrowbridgeReitz<T>, metal: Metal) -> Self {
+        Self::new(distribution, metal.eta(), metal.k())
+    }
+}
+
+/// Flips a direction to the side of +z, where reflection is evaluated
+#[inline]
+fn upper<T: Float>(w: Vector3<T, ShadingSpace>) -> Vector3<T, ShadingSpace> {
+    Vector3::new(w.x, w.y, w.z.abs())
+}
+
+impl<T: Float + FloatConst> Bsdf<T> for ConductorBsdf<T> {
+    #[inline]
+    fn flags(&self) -> BsdfFlags {
+        if self.distribution.effectively_smooth() {
+            BsdfFlags::REFLECTION | BsdfFlags::SPECULAR
+        } else {
+            BsdfFlags::REFLECTION | BsdfFlags::GLOSSY
+        }
+    }
+
+    fn f(&self, wo: Vector3<T, ShadingSpace>, wi: Vector3<T, ShadingSpace>) -> Rgb<T> {
+        if !same_hemisphere(wo, wi) || self.distribution.effectively_smooth() {
+            return Rgb::black();
+        }
+        let (wo, wi) = (upper(wo), upper(wi));
+        let Some(wm) = (wo + wi).try_normalize() else {
+            return Rgb::black();
+        };
+        let fresnel = fresnel_complex_rgb(wo.dot(wm).abs(), self.eta, self.k);
+        let d = self.distribution.d(wm);
+        let g = self.distribution.g(wo, wi);
+        fresnel * (d * g / (T::from(4).unwrap() * wo.z * wi.z))
+    }
+
+    fn sample_f(
+        &self,
+        wo: Vector3<T, ShadingSpace>,
+        _uc: T,
+        u: Point2<T, UnknownUnit>,
+    ) -> Option<BsdfSample<T>> {
+        if wo.z == T::zero() {
+            return None;
+        }
+        if self.distribution.effectively_smooth() {
+            let wi = reflect(wo);
+            let fresnel = fresnel_complex_rgb(wi.z.abs(), self.eta, self.k);
+            return Some(BsdfSample {
+                f: fresnel / wi.z.abs(),
+                wi,
+                pdf: T::one(),
+                flags: BsdfFlags::REFLECTION | BsdfFlags::SPECULAR,
+            });
+        }
+
+        // Reflects about a visible microfacet normal
+        let wm = self.distribution.sample_wm(upper(wo), u);
+        let wi = -upper(wo) + wm * (upper(wo).dot(wm) * (T::one() + T::one()));
+        if wi.z <= T::zero() {
+            return None;
+        }
+        let wi = if wo.z < T::zero() {
+            Vector3::new(wi.x, wi.y, -wi.z)
+        } else {
+            wi
+        };
+        let pdf = self.pdf(wo, wi);
+        (pdf > T::zero()).then(|| BsdfSample {
+            f: self.f(wo, wi),
+            wi,
+            pdf,
+            flags: BsdfFlags::REFLECTION | BsdfFlags::GLOSSY,
+        })
+    }
+
+    fn pdf(&self, wo: Vector3<T, ShadingSpace>, wi: Vector3<T, ShadingSpace>) -> T {
+        if !same_hemisphere(wo, wi) || self.distribution.effectively_smooth() {
+            return T::zero();
+        }
+        let (wo, wi) = (upper(wo), upper(wi));
+        let Some(wm) = (wo + wi).try_normalize() else {
+            return T::zero();
+        };
+        // The change of variables from the half vector to the reflected direction
+        self.distribution.pdf(wo, wm) / (T::from(4).unwrap() * wo.dot(wm).abs())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::Pcg32;
+
+    #[test]
+    fn test_conductor() {
+        // At normal incidence, the reflectance is ((n - 1)^2 + k^2) / ((n + 1)^2 + k^2)
+        let smooth = ConductorBsdf::from_metal(TrowbridgeReitz::new(0., 0.), Metal::Gold);
+        let wo = Vector3::new(0., 0., 1.);
+        let sample = smooth.sample_f(wo, 0.5, Point2::new(0.5, 0.5)).unwrap();
+        assert!(sample.flags.is_specular());
+        let (eta, k): (Rgb<f64>, Rgb<f64>) = (Metal::Gold.eta(), Metal::Gold.k());
+        let f0 = (eta - Rgb::splat(1.)) * (eta - Rgb::splat(1.)) + k * k;
+        let f0 = f0 / ((eta + Rgb::splat(1.)) * (eta + Rgb::splat(1.)) + k * k);
+        for (a, b) in sample.f.to_array().into_iter().zip(f0.to_array()) {
+            assert!((a - b).abs() < 1e-12, "{a} != {b}");
+        }
+        assert!((f0.b - 0.373).abs() < 1e-3);
+
+        let rough = ConductorBsdf::from_metal(TrowbridgeReitz::new(0.3, 0.3), Metal::Silver);
+        let mut rng = Pcg32::new(2);
+        let n = 10_000;
+        let uc: Vec<f64> = (0..n).map(|_| rng.uniform()).collect();
+        let u: Vec<_> = (0..n)
+            .map(|_| Point2::new(rng.uniform(), rng.uniform()))
+            .collect();
+        for wo in [Vector3::new(0.2, 0.4, 0.8), Vector3::new(-0.6, 0.1, -0.3)] {
+            let wo = wo.normalize();
+            let sample = rough.sample_f(wo, uc[0], u[0]).unwrap();
+            assert!(same_hemisphere(wo, sample.wi));
+            assert_eq!(sample.f, rough.f(wo, sample.wi));
+            assert!((sample.pdf - rough.pdf(wo, sample.wi)).abs() < 1e-9);
+            let reciprocal = rough.f(sample.wi, wo);
+            assert!((sample.f - reciprocal).map(f64::abs).max_component() < 1e-12);
+            // Energy is lost to absorption and masking, but never gained
+            let rho = rough.rho(wo, &uc, &u);
+            assert!(rho.max_component() <= 1. && rho.b > 0.7, "{rho:?}");
+        }
+    }
+}
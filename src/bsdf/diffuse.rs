@@ -0,0 +1,102 @@
+use crate::{
+    bsdf::{same_hemisphere, Bsdf, BsdfFlags, BsdfSample, ShadingSpace},
+    color::Rgb,
+    core::geometry::{Point2, UnknownUnit, Vector3},
+    sampling::{cosine_hemisphere_pdf, sample_cosine_hemisphere},
+};
+use num_traits::{Float, FloatConst};
+
+/// The Lambertian BRDF, scattering light equally in all directions of the hemisphere
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct DiffuseBsdf<T> {
+    pub reflectance: Rgb<T>,
+}
+
+impl<T> DiffuseBsdf<T> {
+    #[inline]
+    #[must_use]
+    pub const fn new(reflectance: Rgb<T>) -> Self {
+        Self { reflectance }
+    }
+}
+
+impl<T: Float + FloatConst> Bsdf<T> for DiffuseBsdf<T> {
+    #[inline]
+    fn flags(&self) -> BsdfFlags {
+        if self.reflectance.is_black() {
+            BsdfFlags::empty()
+        } else {
+            BsdfFlags::REFLECTION | BsdfFlags::DIFFUSE
+        }
+    }
+
+    #[inline]
+    fn f(&self, wo: Vector3<T, ShadingSpace>, wi: Vector3<T, ShadingSpace>) -> Rgb<T> {
+        if same_hemisphere(wo, wi) {
+            self.reflectance * T::FRAC_1_PI()
+        } else {
+            Rgb::black()
+        }
+    }
+
+    fn sample_f(
+        &self,
+        wo: Vector3<T, ShadingSpace>,
+        _uc: T,
+        u: Point2<T, UnknownUnit>,
+    ) -> Option<BsdfSample<T>> {
+        if wo.z == T::zero() {
+            return None;
+        }
+        let mut wi: Vector3<T, ShadingSpace> = sample_cosine_hemisphere(u);
+        if wo.z < T::zero() {
+            wi.z = -wi.z;
+        }
+        let pdf = cosine_hemisphere_pdf(wi.z.abs());
+        (pdf > T::zero()).then(|| BsdfSample {
+            f: self.reflectance * T::FRAC_1_PI(),
+            wi,
+            pdf,
+            flags: BsdfFlags::REFLECTION | BsdfFlags::DIFFUSE,
+        })
+    }
+
+    #[inline]
+    fn pdf(&self, wo: Vector3<T, ShadingSpace>, wi: Vector3<T, ShadingSpace>) -> T {
+        if same_hemisphere(wo, wi) {
+            cosine_hemisphere_pdf(wi.z.abs())
+        } else {
+            T::zero()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::Pcg32;
+
+    #[test]
+    fn test_lambertian() {
+        let bsdf = DiffuseBsdf::new(Rgb::new(0.2, 0.5, 0.8));
+        let mut rng = Pcg32::new(0);
+        let n = 1000;
+        let uc: Vec<f64> = (0..n).map(|_| rng.uniform()).collect();
+        let u: Vec<_> = (0..n)
+            .map(|_| Point2::new(rng.uniform(), rng.uniform()))
+            .collect();
+
+        // Sampling proportionally to the cosine estimates the reflectance without variance
+        for wo in [Vector3::new(0., 0.6, 0.8), Vector3::new(0.3, 0., -0.2)] {
+            let rho = bsdf.rho(wo.normalize(), &uc, &u);
+            assert!((rho.b - 0.8).abs() < 1e-12, "{rho:?}");
+            let sample = bsdf.sample_f(wo, uc[0], u[0]).unwrap();
+            assert!(same_hemisphere(wo, sample.wi));
+            assert_eq!(sample.f, bsdf.f(wo, sample.wi));
+            assert_eq!(sample.pdf, bsdf.pdf(wo, sample.wi));
+            // Reciprocal, and not transmitting
+            assert_eq!(bsdf.f(wo, sample.wi), bsdf.f(sample.wi, wo));
+            assert_eq!(bsdf.f(wo, -sample.wi), Rgb::black());
+        }
+    }
+}
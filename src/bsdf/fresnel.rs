@@ -0,0 +1,165 @@
+use crate::{bsdf::ShadingSpace, color::Rgb, core::geometry::Vector3};
+use num_traits::Float;
+use std::ops::{Add, Div, Mul, Sub};
+
+/// Returns the fraction of light reflected by the smooth boundary of a dielectric with relative
+/// index of refraction `eta`, for the cosine of the angle of incidence
+///
+/// Negative cosines are for light arriving from inside the dielectric.
+#[must_use]
+pub fn fresnel_dielectric<T: Float>(cos_theta_i: T, eta: T) -> T {
+    let mut cos_theta_i = cos_theta_i.max(-T::one()).min(T::one());
+    let mut eta = eta;
+    if cos_theta_i < T::zero() {
+        eta = eta.recip();
+        cos_theta_i = -cos_theta_i;
+    }
+    let sin2_theta_t = (T::one() - cos_theta_i * cos_theta_i) / (eta * eta);
+    if sin2_theta_t >= T::one() {
+        // Total internal reflection
+        return T::one();
+    }
+    let cos_theta_t = (T::one() - sin2_theta_t).max(T::zero()).sqrt();
+    let parallel = (eta * cos_theta_i - cos_theta_t) / (eta * cos_theta_i + cos_theta_t);
+    let perpendicular = (cos_theta_i - eta * cos_theta_t) / (cos_theta_i + eta * cos_theta_t);
+    (parallel * parallel + perpendicular * perpendicular) / (T::one() + T::one())
+}
+
+/// Returns the fraction of light reflected by the smooth boundary of a conductor, whose complex
+/// index of refraction relative to the outside is `eta + i k`, for the cosine of the angle of
+/// incidence
+#[must_use]
+pub fn fresnel_complex<T: Float>(cos_theta_i: T, eta: T, k: T) -> T {
+    let cos_theta_i = cos_theta_i.max(T::zero()).min(T::one());
+    let eta = Complex::new(eta, k);
+    let cos_i = Complex::new(cos_theta_i, T::zero());
+    let one = Complex::new(T::one(), T::zero());
+    let sin2_theta_i = Complex::new(T::one() - cos_theta_i * cos_theta_i, T::zero());
+    let sin2_theta_t = sin2_theta_i / (eta * eta);
+    let cos_t = (one - sin2_theta_t).sqrt();
+    let parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    (parallel.norm() + perpendicular.norm()) / (T::one() + T::one())
+}
+
+/// Evaluates [`fresnel_complex`] for each channel
+#[inline]
+#[must_use]
+pub fn fresnel_complex_rgb<T: Float>(cos_theta_i: T, eta: Rgb<T>, k: Rgb<T>) -> Rgb<T> {
+    Rgb::new(
+        fresnel_complex(cos_theta_i, eta.r, k.r),
+        fresnel_complex(cos_theta_i, eta.g, k.g),
+        fresnel_complex(cos_theta_i, eta.b, k.b),
+    )
+}
+
+/// Reflects `wo` about the shading normal
+#[inline]
+#[must_use]
+pub fn reflect<T: Float>(wo: Vector3<T, ShadingSpace>) -> Vector3<T, ShadingSpace> {
+    Vector3::new(-wo.x, -wo.y, wo.z)
+}
+
+/// Refracts `wo` through a boundary with relative index of refraction `eta`, which is that of
+/// the side the normal points away from over the other
+///
+/// Returns the refracted direction and the relative index of refraction it was refracted with,
+/// or `None` for total internal reflection.
+#[must_use]
+pub fn refract<T: Float>(
+    wo: Vector3<T, ShadingSpace>,
+    eta: T,
+) -> Option<(Vector3<T, ShadingSpace>, T)> {
+    let (mut cos_theta_i, mut eta, mut sign) = (wo.z, eta, T::one());
+    if cos_theta_i < T::zero() {
+        eta = eta.recip();
+        cos_theta_i = -cos_theta_i;
+        sign = -T::one();
+    }
+    let sin2_theta_t = (T::one() - cos_theta_i * cos_theta_i).max(T::zero()) / (eta * eta);
+    if sin2_theta_t >= T::one() {
+        return None;
+    }
+    let cos_theta_t = (T::one() - sin2_theta_t).sqrt();
+    let wi =
+        -wo / eta + Vector3::new(T::zero(), T::zero(), sign) * (cos_theta_i / eta - cos_theta_t);
+    Some((wi, eta))
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Complex<T> {
+    re: T,
+    im: T,
+}
+
+impl<T: Float> Complex<T> {
+    #[inline]
+    fn new(re: T, im: T) -> Self {
+        Self { re, im }
+    }
+
+    /// The squared magnitude
+    #[inline]
+    fn norm(self) -> T {
+        self.re * self.re + self.im * self.im
+    }
+
+    /// The principal square root
+    fn sqrt(self) -> Self {
+        let n = self.norm().sqrt();
+        if n == T::zero() {
+            return Self::new(T::zero(), T::zero());
+        }
+        let half = T::from(0.5).unwrap();
+        let t1 = (half * (n + self.re.abs())).sqrt();
+        let t2 = half * self.im / t1;
+        if self.re >= T::zero() {
+            Self::new(t1, t2)
+        } else {
+            Self::new(t2.abs(), t1.copysign(self.im))
+        }
+    }
+}
+
+impl<T: Float> Add for Complex<T> {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.re + rhs.re, self.im + rhs.im)
+    }
+}
+
+impl<T: Float> Sub for Complex<T> {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.re - rhs.re, self.im - rhs.im)
+    }
+}
+
+impl<T: Float> Mul for Complex<T> {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: Self) -> Self {
+        Self::new(
+            self.re * rhs.re - self.im * rhs.im,
+            self.re * rhs.im + self.im * rhs.re,
+        )
+    }
+}
+
+impl<T: Float> Div for Complex<T> {
+    type Output = Self;
+
+    #[inline]
+    fn div(self, rhs: Self) -> Self {
+        let scale = rhs.norm().recip();
+        Self::new(
+            (self.re * rhs.re + self.im * rhs.im) * scale,
+            (self.im * rhs.re - self.re * rhs.im) * scale,
+        )
+    }
+}
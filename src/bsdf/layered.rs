@@ -0,0 +1,498 @@
+use crate::{
+    bsdf::{same_hemisphere, Bsdf, BsdfFlags, BsdfSample, ShadingSpace},
+    color::Rgb,
+    core::geometry::{Point2, UnknownUnit, Vector3},
+    medium::{HenyeyGreenstein, PhaseFunction},
+    sampler::{hash, Pcg32},
+    sampling::power_heuristic,
+};
+use num_traits::{Float, FloatConst};
+
+/// A coating over an opaque base, such as a clear coat over metal or varnish over wood, with
+/// an optional scattering medium in between
+///
+/// Light is transported between the two interfaces by random walks, following Guo et al.,
+/// "Position-Free Monte Carlo Simulation for Arbitrary Layered BSDFs" (2018), as in pbrt-v4.
+/// Values and densities are unbiased but noisy estimates, deterministic for each pair of
+/// directions. Both sides of the surface are coated alike.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct LayeredBsdf<Top, Bottom, T> {
+    /// The coating, usually a dielectric
+    pub top: Top,
+    /// The base, which only reflects
+    pub bottom: Bottom,
+    /// The thickness of the medium between the layers, in units of its mean free path
+    pub thickness: T,
+    /// The single-scattering albedo of the medium, with black for a clear coating
+    pub albedo: Rgb<T>,
+    /// The asymmetry of the Henyey–Greenstein phase function of the medium
+    pub g: T,
+    /// The maximum number of scattering events of each random walk
+    pub max_depth: usize,
+    /// The number of random walks averaged by each estimate
+    pub samples: usize,
+}
+
+impl<Top, Bottom, T: Float> LayeredBsdf<Top, Bottom, T> {
+    /// Returns a coating with a clear, infinitely thin medium
+    #[must_use]
+    pub fn new(top: Top, bottom: Bottom) -> Self {
+        Self {
+            top,
+            bottom,
+            thickness: T::from(0.01).unwrap(),
+            albedo: Rgb::black(),
+            g: T::zero(),
+            max_depth: 10,
+            samples: 1,
+        }
+    }
+
+    /// Returns the coating with a medium of the given thickness and scattering between the
+    /// layers
+    #[must_use]
+    pub fn with_medium(self, thickness: T, albedo: Rgb<T>, g: T) -> Self {
+        Self {
+            thickness: thickness.max(T::min_positive_value()),
+            albedo,
+            g,
+            ..self
+        }
+    }
+}
+
+impl<Top: Bsdf<T>, Bottom: Bsdf<T>, T: Float + FloatConst + Send + Sync>
+    LayeredBsdf<Top, Bottom, T>
+{
+    /// Returns the transmittance of the medium along `w` over a difference of depth
+    #[inline]
+    fn tr(dz: T, w: Vector3<T, ShadingSpace>) -> T {
+        if dz.abs() <= T::min_positive_value() {
+            T::one()
+        } else {
+            (-(dz / w.z).abs()).exp()
+        }
+    }
+
+    /// Samples a transmission through the coating into the layers, reweighted so that it
+    /// carries light from `w` into the layers rather than out of them
+    fn sample_entry(&self, w: Vector3<T, ShadingSpace>, rng: &mut Pcg32) -> Option<BsdfSample<T>> {
+        let sample = self.top.sample_f(w, rng.uniform(), uniform_2d(rng))?;
+        if same_hemisphere(w, sample.wi) || sample.pdf == T::zero() || sample.f.is_black() {
+            return None;
+        }
+        let eta = self.top.eta();
+        Some(BsdfSample {
+            f: sample.f * (eta * eta),
+            ..sample
+        })
+    }
+
+    fn phase(&self) -> HenyeyGreenstein<T> {
+        HenyeyGreenstein::new(self.g)
+    }
+}
+
+/// Returns a uniform sample of the unit square
+#[inline]
+fn uniform_2d<T: Float>(rng: &mut Pcg32) -> Point2<T, UnknownUnit> {
+    Point2::new(rng.uniform(), rng.uniform())
+}
+
+/// Hashes a direction, to seed the random walks of an estimate
+#[inline]
+fn hash_direction<T: Float>(w: Vector3<T, ShadingSpace>) -> u64 {
+    hash(&[w.x, w.y, w.z].map(|c| c.to_f64().unwrap_or(0.).to_bits()))
+}
+
+/// Flips a pair of directions to the side of +z, where the coating is
+#[inline]
+fn upper<T: Float>(
+    wo: Vector3<T, ShadingSpace>,
+    wi: Vector3<T, ShadingSpace>,
+) -> (Vector3<T, ShadingSpace>, Vector3<T, ShadingSpace>) {
+    if wo.z < T::zero() {
+        (-wo, -wi)
+    } else {
+        (wo, wi)
+    }
+}
+
+impl<Top: Bsdf<T>, Bottom: Bsdf<T>, T: Float + FloatConst + Send + Sync> Bsdf<T>
+    for LayeredBsdf<Top, Bottom, T>
+{
+    fn flags(&self) -> BsdfFlags {
+        let (top, bottom) = (self.top.flags(), self.bottom.flags());
+        let mut flags = BsdfFlags::REFLECTION;
+        if top.is_specular() {
+            flags |= BsdfFlags::SPECULAR;
+        }
+        if bottom.contains(BsdfFlags::DIFFUSE) || !self.albedo.is_black() {
+            flags |= BsdfFlags::DIFFUSE;
+        } else if top.is_non_specular() || bottom.is_non_specular() {
+            flags |= BsdfFlags::GLOSSY;
+        }
+        flags
+    }
+
+    fn f(&self, wo: Vector3<T, ShadingSpace>, wi: Vector3<T, ShadingSpace>) -> Rgb<T> {
+        if !same_hemisphere(wo, wi) {
+            return Rgb::black();
+        }
+        let (wo, wi) = upper(wo, wi);
+        let samples = T::from(self.samples.max(1)).unwrap();
+        let thickness = self.thickness;
+        let phase = self.phase();
+        let specular_top = self.top.flags().is_specular();
+        let specular_bottom = self.bottom.flags().is_specular();
+        let scattering = !self.albedo.is_black();
+        let mut rng = Pcg32::new(hash(&[hash_direction(wo), hash_direction(wi)]));
+
+        // Reflection by the coating itself
+        let mut f = self.top.f(wo, wi) * samples;
+        for _ in 0..self.samples.max(1) {
+            // Light enters the layers along `wos`, and leaves them towards `wi` along `wis`
+            let Some(wos) = self.top.sample_f(wo, rng.uniform(), uniform_2d(&mut rng)) else {
+                continue;
+            };
+            if same_hemisphere(wo, wos.wi) || wos.pdf == T::zero() || wos.f.is_black() {
+                continue;
+            }
+            let Some(wis) = self.sample_entry(wi, &mut rng) else {
+                continue;
+            };
+
+            let mut beta = wos.f * (wos.wi.z.abs() / wos.pdf);
+            let mut z = thickness;
+            let mut w = wos.wi;
+            for depth in 0..self.max_depth {
+                if depth > 3 && beta.max_component() < T::from(0.25).unwrap() {
+                    let q = (T::one() - beta.max_component()).max(T::zero());
+                    if rng.uniform::<T>() < q {
+                        break;
+                    }
+                    beta /= T::one() - q;
+                }
+
+                if scattering {
+                    let dz = -(T::one() - rng.uniform::<T>()).ln() * w.z.abs();
+                    let zp = if w.z > T::zero() { z + dz } else { z - dz };
+                    if zp > T::zero() && zp < thickness {
+                        // Scattering in the medium, towards the sampled exit and a new direction
+                        let weight = if specular_top {
+                            T::one()
+                        } else {
+                            power_heuristic(wis.pdf, phase.pdf(-w, -wis.wi))
+                        };
+                        f += beta
+                            * self.albedo
+                            * wis.f
+                            * (phase.p(-w, -wis.wi) * weight * Self::tr(zp - thickness, wis.wi)
+                                / wis.pdf);
+                        let Some(ps) = phase.sample_p(-w, uniform_2d(&mut rng)) else {
+                            continue;
+                        };
+                        if ps.pdf == T::zero() || ps.wi.z == T::zero() {
+                            continue;
+                        }
+                        beta *= self.albedo * (ps.p / ps.pdf);
+                        w = ps.wi;
+                        z = zp;
+                        if w.z > T::zero() && !specular_top {
+                            let f_exit = self.top.f(-w, wi);
+                            if !f_exit.is_black() {
+                                let weight = power_heuristic(ps.pdf, self.top.pdf(wi, -w));
+                                f += beta * f_exit * (Self::tr(zp - thickness, ps.wi) * weight);
+                            }
+                        }
+                        continue;
+                    }
+                    z = zp.max(T::zero()).min(thickness);
+                } else {
+                    z = if z == thickness { T::zero() } else { thickness };
+                    beta *= Self::tr(thickness, w);
+                }
+
+                if z == thickness {
+                    // Reflection back into the layers by the underside of the coating
+                    let Some(bs) = self.top.sample_f(-w, rng.uniform(), uniform_2d(&mut rng))
+                    else {
+                        break;
+                    };
+                    if !same_hemisphere(-w, bs.wi) || bs.pdf == T::zero() || bs.wi.z == T::zero() {
+                        break;
+                    }
+                    beta *= bs.f * (bs.wi.z.abs() / bs.pdf);
+                    w = bs.wi;
+                    continue;
+                }
+
+                // Reflection by the base, with light arriving along the sampled exit
+                if !specular_bottom {
+                    let weight = if specular_top {
+                        T::one()
+                    } else {
+                        power_heuristic(wis.pdf, self.bottom.pdf(-w, -wis.wi))
+                    };
+                    f += beta
+                        * self.bottom.f(-w, -wis.wi)
+                        * wis.f
+                        * (wis.wi.z.abs() * weight * Self::tr(thickness, wis.wi) / wis.pdf);
+                }
+                let Some(bs) = self
+                    .bottom
+                    .sample_f(-w, rng.uniform(), uniform_2d(&mut rng))
+                else {
+                    break;
+                };
+                if !same_hemisphere(-w, bs.wi) || bs.pdf == T::zero() || bs.wi.z == T::zero() {
+                    break;
+                }
+                beta *= bs.f * (bs.wi.z.abs() / bs.pdf);
+                w = bs.wi;
+                if !specular_top {
+                    let f_exit = self.top.f(-w, wi);
+                    if !f_exit.is_black() {
+                        let weight = if specular_bottom {
+                            T::one()
+                        } else {
+                            power_heuristic(bs.pdf, self.top.pdf(wi, -w))
+                        };
+                        f += beta * f_exit * (Self::tr(thickness, bs.wi) * weight);
+                    }
+                }
+            }
+        }
+        f / samples
+    }
+
+    /// Follows a random walk from `wo` until it leaves the layers
+    fn sample_f(
+        &self,
+        wo: Vector3<T, ShadingSpace>,
+        uc: T,
+        u: Point2<T, UnknownUnit>,
+    ) -> Option<BsdfSample<T>> {
+        let flip = wo.z < T::zero();
+        let wo = if flip { -wo } else { wo };
+        let unflip = |w: Vector3<T, ShadingSpace>| if flip { -w } else { w };
+
+        let entry = self.top.sample_f(wo, uc, u)?;
+        if entry.pdf == T::zero() || entry.f.is_black() || entry.wi.z == T::zero() {
+            return None;
+        }
+        if same_hemisphere(wo, entry.wi) {
+            return self.finish(wo, unflip(entry.wi), entry.f, entry.pdf, entry.flags, flip);
+        }
+
+        let mut rng = Pcg32::new(hash(&[
+            hash_direction(wo),
+            uc.to_f64().unwrap_or(0.).to_bits(),
+            hash_direction(Vector3::new(u.x, u.y, T::zero())),
+        ]));
+        let phase = self.phase();
+        let mut specular = entry.flags.is_specular();
+        let mut f = entry.f * entry.wi.z.abs();
+        let mut pdf = entry.pdf;
+        let mut z = self.thickness;
+        let mut w = entry.wi;
+        for depth in 0..self.max_depth {
+            let rr_beta = f.max_component() / pdf;
+            if depth > 3 && rr_beta < T::from(0.25).unwrap() {
+                let q = (T::one() - rr_beta).max(T::zero());
+                if rng.uniform::<T>() < q {
+                    return None;
+                }
+                pdf = pdf * (T::one() - q);
+            }
+            if w.z == T::zero() {
+                return None;
+            }
+
+            if self.albedo.is_black() {
+                z = if z == self.thickness {
+                    T::zero()
+                } else {
+                    self.thickness
+                };
+                f *= Self::tr(self.thickness, w);
+            } else {
+                let dz = -(T::one() - rng.uniform::<T>()).ln() * w.z.abs();
+                let zp = if w.z > T::zero() { z + dz } else { z - dz };
+                if zp == z {
+                    return None;
+                }
+                if zp > T::zero() && zp < self.thickness {
+                    let ps = phase.sample_p(-w, uniform_2d(&mut rng))?;
+                    if ps.pdf == T::zero() || ps.wi.z == T::zero() {
+                        return None;
+                    }
+                    f *= self.albedo * ps.p;
+                    pdf = pdf * ps.pdf;
+                    specular = false;
+                    w = ps.wi;
+                    z = zp;
+                    continue;
+                }
+                z = zp.max(T::zero()).min(self.thickness);
+            }
+
+            let bs = if z == T::zero() {
+                let bs = self
+                    .bottom
+                    .sample_f(-w, rng.uniform(), uniform_2d(&mut rng))?;
+                // Light passing through the base is lost
+                if !same_hemisphere(-w, bs.wi) {
+                    return None;
+                }
+                bs
+            } else {
+                self.top.sample_f(-w, rng.uniform(), uniform_2d(&mut rng))?
+            };
+            if bs.pdf == T::zero() || bs.f.is_black() || bs.wi.z == T::zero() {
+                return None;
+            }
+            let transmitted = !same_hemisphere(-w, bs.wi);
+            f *= bs.f;
+            pdf = pdf * bs.pdf;
+            specular &= bs.flags.is_specular();
+            w = bs.wi;
+            if transmitted {
+                let flags = if specular {
+                    BsdfFlags::REFLECTION | BsdfFlags::SPECULAR
+                } else {
+                    BsdfFlags::REFLECTION | BsdfFlags::GLOSSY
+                };
+                return self.finish(wo, unflip(w), f, pdf, flags, flip);
+            }
+            f *= bs.wi.z.abs();
+        }
+        None
+    }
+
+    fn pdf(&self, wo: Vector3<T, ShadingSpace>, wi: Vector3<T, ShadingSpace>) -> T {
+        if !same_hemisphere(wo, wi) {
+            return T::zero();
+        }
+        let (wo, wi) = upper(wo, wi);
+        let samples = self.samples.max(1);
+        let mut rng = Pcg32::new(hash(&[hash_direction(wi), hash_direction(wo)]));
+        let specular_top = self.top.flags().is_specular();
+        let specular_bottom = self.bottom.flags().is_specular();
+
+        let mut sum = T::from(samples).unwrap() * self.top.pdf(wo, wi);
+        for _ in 0..samples {
+            // Light transmitted in and out of the coating, with a reflection by the base
+            let wos = self.top.sample_f(wo, rng.uniform(), uniform_2d(&mut rng));
+            let wis = self.top.sample_f(wi, rng.uniform(), uniform_2d(&mut rng));
+            let (Some(wos), Some(wis)) = (wos, wis) else {
+                continue;
+            };
+            let transmitted = |s: &BsdfSample<T>| s.wi.z < T::zero() && s.pdf > T::zero();
+            if !transmitted(&wos) || !transmitted(&wis) {
+                continue;
+            }
+            if specular_top {
+                sum = sum + self.bottom.pdf(-wos.wi, -wis.wi);
+                continue;
+            }
+            let Some(rs) = self
+                .bottom
+                .sample_f(-wos.wi, rng.uniform(), uniform_2d(&mut rng))
+            else {
+                continue;
+            };
+            if rs.pdf == T::zero() {
+                continue;
+            }
+            if specular_bottom {
+                sum = sum + self.top.pdf(-rs.wi, wi);
+            } else {
+                let r_pdf = self.bottom.pdf(-wos.wi, -wis.wi);
+                sum = sum + power_heuristic(wis.pdf, r_pdf) * r_pdf;
+                let t_pdf = self.top.pdf(-rs.wi, wi);
+                sum = sum + power_heuristic(rs.pdf, t_pdf) * t_pdf;
+            }
+        }
+        // Mixes in uniform sampling of the sphere, as the estimate may miss directions
+        let uniform = T::FRAC_1_PI() / T::from(4).unwrap();
+        let estimate = sum / T::from(samples).unwrap();
+        uniform + (estimate - uniform) * T::from(0.9).unwrap()
+    }
+}
+
+impl<Top: Bsdf<T>, Bottom: Bsdf<T>, T: Float + FloatConst + Send + Sync>
+    LayeredBsdf<Top, Bottom, T>
+{
+    /// Returns a sample of the random walks with the value `f` and density `pdf`
+    ///
+    /// Except for specular paths, the density is replaced by that of [`pdf`](Bsdf::pdf) for
+    /// consistency with the value integrators weigh other strategies with, and the value scaled
+    /// to keep the ratio of the random walk.
+    fn finish(
+        &self,
+        wo: Vector3<T, ShadingSpace>,
+        wi: Vector3<T, ShadingSpace>,
+        f: Rgb<T>,
+        pdf: T,
+        flags: BsdfFlags,
+        flip: bool,
+    ) -> Option<BsdfSample<T>> {
+        if flags.is_specular() {
+            return Some(BsdfSample { f, wi, pdf, flags });
+        }
+        let wo = if flip { -wo } else { wo };
+        let consistent = self.pdf(wo, wi);
+        (consistent > T::zero()).then(|| BsdfSample {
+            f: f * (consistent / pdf),
+            wi,
+            pdf: consistent,
+            flags,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bsdf::{DielectricBsdf, DiffuseBsdf, RoughDielectricBsdf, TrowbridgeReitz},
+        sampling::sample_cosine_hemisphere,
+    };
+
+    #[test]
+    fn test_layered() {
+        let mut rng = Pcg32::new(6);
+        let n = 20_000;
+        let uc: Vec<f64> = (0..n).map(|_| rng.uniform()).collect();
+        let u: Vec<_> = (0..n).map(|_| uniform_2d(&mut rng)).collect();
+        let wo = Vector3::new(0.3, -0.2, 0.8).normalize();
+
+        // A clear coat over a white base loses no light, once the random walks last long
+        // enough for what total internal reflection traps
+        let mut coated =
+            LayeredBsdf::new(DielectricBsdf::new(1.5), DiffuseBsdf::new(Rgb::splat(1.)));
+        coated.max_depth = 100;
+        let rho = coated.rho(wo, &uc, &u);
+        assert!(rho.r <= 1.01 && rho.r > 0.9, "{rho:?}");
+        let rho = coated.rho(-wo, &uc, &u);
+        assert!(rho.r <= 1.01 && rho.r > 0.9, "{rho:?}");
+
+        // The estimates of values agree with the random walks of sampling
+        let rough = RoughDielectricBsdf::new(TrowbridgeReitz::new(0.3, 0.3), 1.5);
+        let coated = LayeredBsdf::new(rough, DiffuseBsdf::new(Rgb::new(0.5, 0.2, 0.8)))
+            .with_medium(0.1, Rgb::splat(0.5), 0.3);
+        let integral = u
+            .iter()
+            .map(|&u| coated.f(wo, sample_cosine_hemisphere(u)) * std::f64::consts::PI)
+            .sum::<Rgb<f64>>()
+            / f64::from(n);
+        let rho = coated.rho(wo, &uc, &u);
+        for (a, b) in integral.to_array().into_iter().zip(rho.to_array()) {
+            assert!((a - b).abs() < 0.02, "{integral:?} != {rho:?}");
+        }
+        let sample = coated.sample_f(wo, uc[0], u[0]).unwrap();
+        assert!(sample.flags.contains(BsdfFlags::REFLECTION) && sample.wi.z > 0.);
+        assert_eq!(sample.pdf, coated.pdf(wo, sample.wi));
+    }
+}
@@ -0,0 +1,291 @@
+use crate::{
+    bsdf::ShadingSpace,
+    core::geometry::{Point2, UnknownUnit, Vector3},
+    sampling::sample_uniform_disk_concentric,
+};
+use num_traits::{Float, FloatConst};
+
+/// The microfacet distribution of Trowbridge and Reitz, also known as GGX, describing rough
+/// surfaces as many small mirrors
+///
+/// Masking and shadowing follow the Smith model, and normals are sampled from the
+/// distribution of those visible from a direction. Different roughnesses along the two axes
+/// give anisotropic highlights, such as those of brushed metal.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct TrowbridgeReitz<T> {
+    /// The roughness along the `s` axis of the shading frame, after rotation
+    pub alpha_x: T,
+    /// The roughness along the `t` axis of the shading frame, after rotation
+    pub alpha_y: T,
+    /// The counterclockwise angle about the normal, in radians, from the `s` axis of the
+    /// shading frame to the direction of `alpha_x`
+    pub rotation: T,
+}
+
+impl<T: Float + FloatConst> TrowbridgeReitz<T> {
+    #[inline]
+    #[must_use]
+    pub fn new(alpha_x: T, alpha_y: T) -> Self {
+        Self {
+            alpha_x,
+            alpha_y,
+            rotation: T::zero(),
+        }
+    }
+
+    /// Returns the distribution with its axes rotated about the normal by `angle` radians
+    #[inline]
+    #[must_use]
+    pub fn with_rotation(self, angle: T) -> Self {
+        Self {
+            rotation: angle,
+            ..self
+        }
+    }
+
+    /// Maps a perceptually linear roughness in `[0, 1]` to the parameter of the distribution
+    #[inline]
+    #[must_use]
+    pub fn roughness_to_alpha(roughness: T) -> T {
+        roughness.max(T::zero()).sqrt()
+    }
+
+    /// Whether the surface is so smooth that it is better treated as specular
+    #[inline]
+    #[must_use]
+    pub fn effectively_smooth(&self) -> bool {
+        self.alpha_x.max(self.alpha_y) < T::from(1e-3).unwrap()
+    }
+
+    /// Returns the density of microfacet normals `wm`, with respect to the projected area of
+    /// the surface
+    #[must_use]
+    pub fn d(&self, wm: Vector3<T, ShadingSpace>) -> T {
+        let wm = self.aligned(wm);
+        let cos2_theta = wm.z * wm.z;
+        let cos4_theta = cos2_theta * cos2_theta;
+        if cos4_theta < T::from(1e-16).unwrap() {
+            return T::zero();
+        }
+        let tan2_theta = (T::one() - cos2_theta).max(T::zero()) / cos2_theta;
+        let (cos_phi, sin_phi) = cos_sin_phi(wm);
+        let e = tan2_theta * ((cos_phi / self.alpha_x).powi(2) + (sin_phi / self.alpha_y).powi(2));
+        let one_e = T::one() + e;
+        (T::PI() * self.alpha_x * self.alpha_y * cos4_theta * one_e * one_e).recip()
+    }
+
+    /// The auxiliary function of the Smith model, giving the masked area of microfacets per
+    /// visible area
+    #[must_use]
+    pub fn lambda(&self, w: Vector3<T, ShadingSpace>) -> T {
+        let w = self.aligned(w);
+        let cos2_theta = w.z * w.z;
+        if cos2_theta == T::zero() {
+            return T::zero();
+        }
+        let tan2_theta = (T::one() - cos2_theta).max(T::zero()) / cos2_theta;
+        let (cos_phi, sin_phi) = cos_sin_phi(w);
+        let alpha2 = (cos_phi * self.alpha_x).powi(2) + (sin_phi * self.alpha_y).powi(2);
+        ((T::one() + alpha2 * tan2_theta).sqrt() - T::one()) / (T::one() + T::one())
+    }
+
+    /// The fraction of microfacets visible from `w`
+    #[inline]
+    #[must_use]
+    pub fn g1(&self, w: Vector3<T, ShadingSpace>) -> T {
+        (T::one() + self.lambda(w)).recip()
+    }
+
+    /// The fraction of microfacets visible from both directions
+    #[inline]
+    #[must_use]
+    pub fn g(&self, wo: Vector3<T, ShadingSpace>, wi: Vector3<T, ShadingSpace>) -> T {
+        (T::one() + self.lambda(wo) + self.lambda(wi)).recip()
+    }
+
+    /// Returns the density of microfacet normals `wm` visible from `w`, with respect to solid
+    /// angle
+    #[must_use]
+    pub fn d_visible(&self, w: Vector3<T, ShadingSpace>, wm: Vector3<T, ShadingSpace>) -> T {
+        if w.z == T::zero() {
+            return T::zero();
+        }
+        self.g1(w) / w.z.abs() * self.d(wm) * w.dot(wm).abs()
+    }
+
+    /// Returns the density with which [`sample_wm`](Self::sample_wm) picks `wm`
+    #[inline]
+    #[must_use]
+    pub fn pdf(&self, w: Vector3<T, ShadingSpace>, wm: Vector3<T, ShadingSpace>) -> T {
+        self.d_visible(w, wm)
+    }
+
+    /// Samples a microfacet normal visible from `w`, in the hemisphere of +z
+    #[must_use]
+    pub fn sample_wm(
+        &self,
+        w: Vector3<T, ShadingSpace>,
+        u: Point2<T, UnknownUnit>,
+    ) -> Vector3<T, ShadingSpace> {
+        let w = self.aligned(w);
+        // Stretches the direction to where the distribution is that of a hemisphere
+        let mut wh: Vector3<T, ShadingSpace> =
+            Vector3::new(self.alpha_x * w.x, self.alpha_y * w.y, w.z).normalize();
+        if wh.z < T::zero() {
+            wh = -wh;
+        }
+        let t1 = if wh.z < T::from(0.99999).unwrap() {
+            Vector3::new(T::zero(), T::zero(), T::one())
+                .cross(wh)
+                .normalize()
+        } else {
+            Vector3::new(T::one(), T::zero(), T::zero())
+        };
+        let t2 = wh.cross(t1);
+
+        // Warps a point of the disk to the projection of the visible half of the hemisphere
+        let p = sample_uniform_disk_concentric(u);
+        let h = (T::one() - p.x * p.x).max(T::zero()).sqrt();
+        let s = (T::one() + wh.z) / (T::one() + T::one());
+        let py = h + (p.y - h) * s;
+        let pz = (T::one() - p.x * p.x - py * py).max(T::zero()).sqrt();
+        let nh = t1 * p.x + t2 * py + wh * pz;
+        let wm = Vector3::new(
+            self.alpha_x * nh.x,
+            self.alpha_y * nh.y,
+            nh.z.max(T::from(1e-6).unwrap()),
+        )
+        .normalize();
+        rotate(wm, self.rotation)
+    }
+
+    /// Rotates a direction into the frame whose axes are those of the roughnesses
+    #[inline]
+    fn aligned(&self, w: Vector3<T, ShadingSpace>) -> Vector3<T, ShadingSpace> {
+        rotate(w, -self.rotation)
+    }
+}
+
+/// Rotates a direction counterclockwise about the normal
+#[inline]
+fn rotate<T: Float>(w: Vector3<T, ShadingSpace>, angle: T) -> Vector3<T, ShadingSpace> {
+    if angle == T::zero() {
+        return w;
+    }
+    let (sin, cos) = angle.sin_cos();
+    Vector3::new(cos * w.x - sin * w.y, sin * w.x + cos * w.y, w.z)
+}
+
+/// Returns the cosine and sine of the azimuth of a direction
+#[inline]
+fn cos_sin_phi<T: Float>(w: Vector3<T, ShadingSpace>) -> (T, T) {
+    let sin_theta = (w.x * w.x + w.y * w.y).sqrt();
+    if sin_theta == T::zero() {
+        return (T::one(), T::zero());
+    }
+    let clamp = |x: T| x.max(-T::one()).min(T::one());
+    (clamp(w.x / sin_theta), clamp(w.y / sin_theta))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bsdf::{Bsdf, ConductorBsdf, Metal},
+        sampler::Pcg32,
+        sampling::sample_uniform_sphere,
+    };
+
+    #[test]
+    fn test_normalization() {
+        let mut rng = Pcg32::new(1);
+        let wo = Vector3::new(0.3, -0.5, 0.6).normalize();
+        for (alpha_x, alpha_y) in [(0.3, 0.3), (0.6, 0.2)] {
+            let distribution = TrowbridgeReitz::new(alpha_x, alpha_y);
+            let n = 100_000;
+            // The projected areas of microfacets add up to that of the surface, and visible
+            // normals are a distribution
+            let (mut projected, mut visible) = (0., 0.);
+            for _ in 0..n {
+                let wm: Vector3<f64, ShadingSpace> =
+                    sample_uniform_sphere(Point2::new(rng.uniform(), rng.uniform()));
+                if wm.z > 0. {
+                    projected += distribution.d(wm) * wm.z;
+                    visible += distribution.d_visible(wo, wm);
+                }
+            }
+            let scale = 4. * std::f64::consts::PI / f64::from(n);
+            assert!((projected * scale - 1.).abs() < 0.03, "{projected}");
+            assert!((visible * scale - 1.).abs() < 0.03, "{visible}");
+
+            // Sampled normals are visible, and the masking is symmetric
+            let u = Point2::new(rng.uniform(), rng.uniform());
+            let wm = distribution.sample_wm(wo, u);
+            assert!(wm.z > 0. && wo.dot(wm) > 0.);
+            assert!((distribution.g(wo, wm) - distribution.g(wm, wo)).abs() < 1e-12);
+        }
+
+        // A quarter turn swaps the roughnesses of the two axes
+        let rotated = TrowbridgeReitz::new(0.6, 0.2).with_rotation(std::f64::consts::FRAC_PI_2);
+        let swapped = TrowbridgeReitz::new(0.2, 0.6);
+        let wm = Vector3::new(0.3, 0.1, 0.9).normalize();
+        assert!((rotated.d(wm) - swapped.d(wm)).abs() < 1e-12);
+        assert!((rotated.lambda(wo) - swapped.lambda(wo)).abs() < 1e-12);
+        let u = Point2::new(0.3, 0.8);
+        let wm = rotated.sample_wm(wo, u);
+        assert!((rotated.pdf(wo, wm) - swapped.pdf(wo, wm)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_anisotropic() {
+        let mut rng = Pcg32::new(3);
+        let mut random_direction = || -> Vector3<f64, ShadingSpace> {
+            sample_uniform_sphere(Point2::new(rng.uniform(), rng.uniform()))
+        };
+
+        // Equal roughnesses give the isotropic distribution at any rotation
+        let alpha = 0.4_f64;
+        for rotation in [0., 1.1] {
+            let distribution = TrowbridgeReitz::new(alpha, alpha).with_rotation(rotation);
+            for _ in 0..20 {
+                let w = random_direction();
+                let w = Vector3::new(w.x, w.y, w.z.abs());
+                let tan2_theta = (1. - w.z * w.z) / (w.z * w.z);
+                let d = alpha * alpha
+                    / (std::f64::consts::PI * w.z.powi(4) * (alpha * alpha + tan2_theta).powi(2));
+                let lambda = ((1. + alpha * alpha * tan2_theta).sqrt() - 1.) / 2.;
+                assert!((distribution.d(w) - d).abs() <= 1e-9 * d, "{w:?}");
+                assert!((distribution.lambda(w) - lambda).abs() <= 1e-9 * lambda);
+            }
+        }
+
+        // The masking of the visible normals accounts for all of them: integrating the
+        // reflection of a perfect mirror without its shadowing term gives one (the weak white
+        // furnace test)
+        let distribution = TrowbridgeReitz::new(0.5, 0.15).with_rotation(0.7);
+        for wo in [Vector3::new(0., 0., 1.), Vector3::new(0.5, -0.3, 0.4)] {
+            let wo = wo.normalize();
+            let n = 200_000;
+            let mut sum = 0.;
+            for _ in 0..n {
+                let wi = random_direction();
+                let wm = (wo + wi).normalize();
+                if wm.z > 0. {
+                    sum += distribution.g1(wo) * distribution.d(wm) / (4. * wo.z);
+                }
+            }
+            let furnace = sum * 4. * std::f64::consts::PI / f64::from(n);
+            assert!((furnace - 1.).abs() < 0.03, "{furnace}");
+
+            // Shadowing only ever hides more of the microfacets, and reflection is reciprocal
+            let conductor = ConductorBsdf::from_metal(distribution, Metal::Silver);
+            for _ in 0..20 {
+                let wi = random_direction();
+                let wi = Vector3::new(wi.x, wi.y, wi.z.abs());
+                let (f, reciprocal) = (conductor.f(wo, wi), conductor.f(wi, wo));
+                assert!((f - reciprocal).map(f64::abs).max_component() <= 1e-12 * f.g);
+                assert!(distribution.g(wo, wi) <= distribution.g1(wo));
+            }
+        }
+    }
+}
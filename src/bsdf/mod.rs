@@ -0,0 +1,240 @@
+//! Scattering functions describing how light is reflected and transmitted at surfaces
+
+mod conductor;
+mod diffuse;
+mod fresnel;
+mod layered;
+mod microfacet;
+mod oren_nayar;
+mod principled;
+mod rough_dielectric;
+mod sheen;
+mod specular;
+
+pub use conductor::{ConductorBsdf, Metal};
+pub use diffuse::DiffuseBsdf;
+pub use fresnel::{
+    fresnel_complex, fresnel_complex_rgb, fresnel_dielectric, reflect, refract,
+};
+pub use layered::LayeredBsdf;
+pub use microfacet::TrowbridgeReitz;
+pub use oren_nayar::OrenNayarBsdf;
+pub use principled::{PrincipledBsdf, PrincipledParameters};
+pub use rough_dielectric::RoughDielectricBsdf;
+pub use sheen::SheenBsdf;
+pub use specular::{DielectricBsdf, MirrorBsdf};
+
+use crate::{
+    color::Rgb,
+    core::geometry::{Point2, UnknownUnit, Vector3},
+    shape::SurfaceInteraction,
+};
+use num_traits::Float;
+use std::ops::{BitOr, BitOrAssign};
+
+/// The local space of a surface point, with the shading normal along +z
+pub enum ShadingSpace {}
+
+/// An orthonormal basis, mapping directions between a space and [`ShadingSpace`]
+pub struct Frame<T, U> {
+    pub s: Vector3<T, U>,
+    pub t: Vector3<T, U>,
+    pub n: Vector3<T, U>,
+}
+
+common_impls!(Frame { s, t, n });
+
+impl<T: Float, U> Frame<T, U> {
+    /// Returns the shading frame of an interaction, with `s` along `dpdu`
+    #[must_use]
+    pub fn from_interaction(si: &SurfaceInteraction<T, U>) -> Self {
+        let n = si.shading.n.to_vector();
+        let dpdu = si.shading.dpdu;
+        let s = (dpdu - n * n.dot(dpdu))
+            .try_normalize()
+            .unwrap_or_else(|| n.coordinate_system().0);
+        Self {
+            s,
+            t: n.cross(s),
+            n,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn to_local(&self, v: Vector3<T, U>) -> Vector3<T, ShadingSpace> {
+        Vector3::new(v.dot(self.s), v.dot(self.t), v.dot(self.n))
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn from_local(&self, v: Vector3<T, ShadingSpace>) -> Vector3<T, U> {
+        self.s * v.x + self.t * v.y + self.n * v.z
+    }
+}
+
+/// The kinds of scattering of a BSDF, or of a sampled direction
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct BsdfFlags(u8);
+
+impl BsdfFlags {
+    pub const REFLECTION: Self = Self(1);
+    pub const TRANSMISSION: Self = Self(1 << 1);
+    pub const DIFFUSE: Self = Self(1 << 2);
+    pub const GLOSSY: Self = Self(1 << 3);
+    /// Scattering into a single direction, which can only be sampled
+    pub const SPECULAR: Self = Self(1 << 4);
+
+    #[inline]
+    #[must_use]
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn is_specular(self) -> bool {
+        self.contains(Self::SPECULAR)
+    }
+
+    /// Whether some of the scattering can be evaluated for arbitrary pairs of directions
+    #[inline]
+    #[must_use]
+    pub const fn is_non_specular(self) -> bool {
+        self.0 & (Self::DIFFUSE.0 | Self::GLOSSY.0) != 0
+    }
+}
+
+impl BitOr for BsdfFlags {
+    type Output = Self;
+
+    #[inline]
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for BsdfFlags {
+    #[inline]
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// A direction sampled from a BSDF
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BsdfSample<T> {
+    /// The value of the BSDF for the sampled pair of directions
+    pub f: Rgb<T>,
+    pub wi: Vector3<T, ShadingSpace>,
+    /// The density of `wi` with respect to solid angle, or the probability of choosing it for
+    /// specular scattering
+    pub pdf: T,
+    pub flags: BsdfFlags,
+}
+
+/// A bidirectional scattering distribution function, in [`ShadingSpace`]
+///
+/// Both directions point away from the surface, `wo` towards the viewer and `wi` towards the
+/// light. Values exclude the cosine factor of the incident direction.
+pub trait Bsdf<T> {
+    #[must_use]
+    fn flags(&self) -> BsdfFlags;
+
+    /// Returns the scattering between two directions, which is zero for specular scattering
+    #[must_use]
+    fn f(&self, wo: Vector3<T, ShadingSpace>, wi: Vector3<T, ShadingSpace>) -> Rgb<T>;
+
+    /// Samples an incident direction, using `uc` to pick among lobes and `u` within them
+    #[must_use]
+    fn sample_f(
+        &self,
+        wo: Vector3<T, ShadingSpace>,
+        uc: T,
+        u: Point2<T, UnknownUnit>,
+    ) -> Option<BsdfSample<T>>;
+
+    /// Returns the density with which [`sample_f`](Self::sample_f) picks `wi`
+    #[must_use]
+    fn pdf(&self, wo: Vector3<T, ShadingSpace>, wi: Vector3<T, ShadingSpace>) -> T;
+
+    /// The index of refraction of the inside, which the normal points away from, relative to
+    /// the outside, or one for BSDFs that don't refract light
+    #[inline]
+    #[must_use]
+    fn eta(&self) -> T
+    where
+        T: Float,
+    {
+        T::one()
+    }
+
+    /// Estimates the fraction of light arriving from all directions that is scattered towards
+    /// `wo`, with a sample of `sample_f` for each pair of `uc` and `u`
+    #[must_use]
+    fn rho(&self, wo: Vector3<T, ShadingSpace>, uc: &[T], u: &[Point2<T, UnknownUnit>]) -> Rgb<T>
+    where
+        T: Float,
+    {
+        let n = uc.len().min(u.len());
+        if n == 0 {
+            return Rgb::black();
+        }
+        let sum: Rgb<T> = uc
+            .iter()
+            .zip(u)
+            .filter_map(|(&uc, &u)| self.sample_f(wo, uc, u))
+            .filter(|sample| sample.pdf > T::zero())
+            .map(|sample| sample.f * (sample.wi.z.abs() / sample.pdf))
+            .sum();
+        sum / T::from(n).unwrap()
+    }
+}
+
+impl<T, B: Bsdf<T> + ?Sized> Bsdf<T> for Box<B> {
+    #[inline]
+    fn flags(&self) -> BsdfFlags {
+        (**self).flags()
+    }
+
+    #[inline]
+    fn f(&self, wo: Vector3<T, ShadingSpace>, wi: Vector3<T, ShadingSpace>) -> Rgb<T> {
+        (**self).f(wo, wi)
+    }
+
+    #[inline]
+    fn sample_f(
+        &self,
+        wo: Vector3<T, ShadingSpace>,
+        uc: T,
+        u: Point2<T, UnknownUnit>,
+    ) -> Option<BsdfSample<T>> {
+        (**self).sample_f(wo, uc, u)
+    }
+
+    #[inline]
+    fn pdf(&self, wo: Vector3<T, ShadingSpace>, wi: Vector3<T, ShadingSpace>) -> T {
+        (**self).pdf(wo, wi)
+    }
+
+    #[inline]
+    fn eta(&self) -> T
+    where
+        T: Float,
+    {
+        (**self).eta()
+    }
+}
+
+/// Whether two directions of [`ShadingSpace`] lie on the same side of the surface
+#[inline]
+#[must_use]
+pub fn same_hemisphere<T: Float>(a: Vector3<T, ShadingSpace>, b: Vector3<T, ShadingSpace>) -> bool {
+    a.z * b.z > T::zero()
+}
@@ -0,0 +1,159 @@
+use crate::{
+    bsdf::{same_hemisphere, Bsdf, BsdfFlags, BsdfSample, ShadingSpace},
+    color::Rgb,
+    core::geometry::{Point2, UnknownUnit, Vector3},
+    sampling::{cosine_hemisphere_pdf, sample_cosine_hemisphere},
+};
+use num_traits::{Float, FloatConst};
+
+/// The Oren–Nayar BRDF of rough matte surfaces, whose facets scatter more light back towards
+/// its source than a Lambertian surface does
+///
+/// This is the energy-preserving variant of Portsmouth et al., "EON: A practical
+/// energy-preserving rough diffuse BRDF" (2025), which adds the light scattered between facets
+/// so that a white surface reflects all light at any roughness.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct OrenNayarBsdf<T> {
+    pub reflectance: Rgb<T>,
+    /// The roughness in `[0, 1]`, with zero for a Lambertian surface
+    pub roughness: T,
+}
+
+impl<T> OrenNayarBsdf<T> {
+    #[inline]
+    #[must_use]
+    pub const fn new(reflectance: Rgb<T>, roughness: T) -> Self {
+        Self {
+            reflectance,
+            roughness,
+        }
+    }
+}
+
+impl<T: Float + FloatConst> OrenNayarBsdf<T> {
+    /// The first constant of Fujii's Oren–Nayar model, `1/2 - 2/(3 pi)`
+    fn c1() -> T {
+        T::from(0.5).unwrap() - T::from(2. / 3.).unwrap() * T::FRAC_1_PI()
+    }
+
+    /// The albedo of the single-scattering lobe of a white surface, seen with the cosine `mu`
+    fn albedo(&self, mu: T) -> T {
+        let r = self.roughness;
+        let a = (T::one() + Self::c1() * r).recip();
+        let b = r * a;
+        let mu = mu.max(T::from(1e-7).unwrap()).min(T::one());
+        let sin = (T::one() - mu * mu).sqrt();
+        let g = sin * (mu.acos() - sin * mu)
+            + T::from(2. / 3.).unwrap() * ((sin / mu) * (T::one() - sin * sin * sin) - sin);
+        a + b * T::FRAC_1_PI() * g
+    }
+
+    /// The albedo of the single-scattering lobe of a white surface, averaged over directions
+    fn average_albedo(&self) -> T {
+        let c2 = T::from(2. / 3.).unwrap() - T::from(28. / 15.).unwrap() * T::FRAC_1_PI();
+        (T::one() + c2 * self.roughness) / (T::one() + Self::c1() * self.roughness)
+    }
+}
+
+impl<T: Float + FloatConst> Bsdf<T> for OrenNayarBsdf<T> {
+    #[inline]
+    fn flags(&self) -> BsdfFlags {
+        if self.reflectance.is_black() {
+            BsdfFlags::empty()
+        } else {
+            BsdfFlags::REFLECTION | BsdfFlags::DIFFUSE
+        }
+    }
+
+    fn f(&self, wo: Vector3<T, ShadingSpace>, wi: Vector3<T, ShadingSpace>) -> Rgb<T> {
+        if !same_hemisphere(wo, wi) {
+            return Rgb::black();
+        }
+        let (mu_o, mu_i) = (wo.z.abs(), wi.z.abs());
+        let r = self.roughness;
+        let a = (T::one() + Self::c1() * r).recip();
+
+        // Light scattered once, brighter towards retroreflection
+        let s = wo.x * wi.x + wo.y * wi.y;
+        let s_over_t = if s > T::zero() { s / mu_o.max(mu_i) } else { s };
+        let single = self.reflectance * (T::FRAC_1_PI() * a * (T::one() + r * s_over_t));
+
+        // Light scattered between facets, making up for what the single scattering lacks
+        let eps = T::from(1e-7).unwrap();
+        let average = self.average_albedo();
+        let rho_ms = self.reflectance * self.reflectance * average
+            / (Rgb::splat(T::one()) - self.reflectance * (T::one() - average));
+        let lost = (T::one() - self.albedo(mu_o)).max(eps)
+            * (T::one() - self.albedo(mu_i)).max(eps)
+            / (T::one() - average).max(eps);
+        single + rho_ms * (T::FRAC_1_PI() * lost)
+    }
+
+    fn sample_f(
+        &self,
+        wo: Vector3<T, ShadingSpace>,
+        _uc: T,
+        u: Point2<T, UnknownUnit>,
+    ) -> Option<BsdfSample<T>> {
+        if wo.z == T::zero() {
+            return None;
+        }
+        let mut wi: Vector3<T, ShadingSpace> = sample_cosine_hemisphere(u);
+        if wo.z < T::zero() {
+            wi.z = -wi.z;
+        }
+        let pdf = cosine_hemisphere_pdf(wi.z.abs());
+        (pdf > T::zero()).then(|| BsdfSample {
+            f: self.f(wo, wi),
+            wi,
+            pdf,
+            flags: BsdfFlags::REFLECTION | BsdfFlags::DIFFUSE,
+        })
+    }
+
+    #[inline]
+    fn pdf(&self, wo: Vector3<T, ShadingSpace>, wi: Vector3<T, ShadingSpace>) -> T {
+        if same_hemisphere(wo, wi) {
+            cosine_hemisphere_pdf(wi.z.abs())
+        } else {
+            T::zero()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::Pcg32;
+
+    #[test]
+    fn test_oren_nayar() {
+        let mut rng = Pcg32::new(4);
+        let n = 100_000;
+        let uc: Vec<f64> = (0..n).map(|_| rng.uniform()).collect();
+        let u: Vec<_> = (0..n)
+            .map(|_| Point2::new(rng.uniform(), rng.uniform()))
+            .collect();
+
+        // A white surface reflects all light whatever its roughness
+        for roughness in [0., 0.5, 1.] {
+            let bsdf = OrenNayarBsdf::new(Rgb::new(1., 0.5, 0.), roughness);
+            for wo in [Vector3::new(0., 0., 1.), Vector3::new(0.9, 0.1, -0.2)] {
+                let rho = bsdf.rho(wo.normalize(), &uc, &u);
+                assert!((rho.r - 1.).abs() < 0.01, "{roughness} {rho:?}");
+                assert!(rho.g > 0.4 && rho.g < 0.6 && rho.b == 0.);
+            }
+        }
+
+        // Without roughness, the surface is Lambertian
+        let bsdf = OrenNayarBsdf::new(Rgb::splat(0.5), 0.);
+        let (wo, wi) = (Vector3::new(0.6, 0., 0.8), Vector3::new(0., 0.8, 0.6));
+        assert!((bsdf.f(wo, wi).r - 0.5 * std::f64::consts::FRAC_1_PI).abs() < 1e-6);
+
+        // Rough surfaces are brighter towards the light, and reciprocal
+        let bsdf = OrenNayarBsdf::new(Rgb::splat(0.5), 1.);
+        let mirrored = Vector3::new(-0.6, 0., 0.8);
+        assert!(bsdf.f(wo, wo).r > bsdf.f(wo, mirrored).r);
+        assert!((bsdf.f(wo, wi).r - bsdf.f(wi, wo).r).abs() < 1e-12);
+    }
+}
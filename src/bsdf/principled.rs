@@ -0,0 +1,519 @@
+use crate::{
+    bsdf::{
+        same_hemisphere, Bsdf, BsdfFlags, BsdfSample, DielectricBsdf, RoughDielectricBsdf,
+        ShadingSpace, SheenBsdf, TrowbridgeReitz,
+    },
+    color::Rgb,
+    core::geometry::{Point2, UnknownUnit, Vector3},
+    sampling::{cosine_hemisphere_pdf, sample_cosine_hemisphere},
+};
+use num_traits::{Float, FloatConst};
+
+/// The parameters of the principled BSDF, after Burley, "Physically Based Shading at Disney"
+/// (2012) and "Extending the Disney BRDF to a BSDF with Integrated Subsurface Scattering"
+/// (2015)
+///
+/// All parameters except `eta` are in `[0, 1]`. Start from [`new`](Self::new) and override
+/// the ones of interest with the struct update syntax.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct PrincipledParameters<T> {
+    /// The diffuse color, or the specular color of metals
+    pub base_color: Rgb<T>,
+    /// Blends from a dielectric to a metal
+    pub metallic: T,
+    /// The roughness of the specular and diffuse lobes, squared for the microfacet
+    /// distribution
+    pub roughness: T,
+    /// Stretches specular highlights along the tangent
+    pub anisotropic: T,
+    /// The strength of specular reflection of dielectrics, with 0.5 for an index of refraction
+    /// of 1.5
+    pub specular: T,
+    /// Tints the specular reflection of dielectrics towards the base color
+    pub specular_tint: T,
+    /// The strength of the grazing sheen of cloth
+    pub sheen: T,
+    /// Tints the sheen towards the base color
+    pub sheen_tint: T,
+    /// The roughness of the sheen, from a narrow rim to a broad haze
+    pub sheen_roughness: T,
+    /// The strength of a clear coat of varnish
+    pub clearcoat: T,
+    /// The glossiness of the clear coat, from satin to gloss
+    pub clearcoat_gloss: T,
+    /// Blends from an opaque surface to one transmitting light like glass
+    pub transmission: T,
+    /// The index of refraction of transmitting surfaces
+    pub eta: T,
+}
+
+impl<T: Float> PrincipledParameters<T> {
+    /// Returns the parameters of a glossy dielectric of the given color
+    #[must_use]
+    pub fn new(base_color: Rgb<T>) -> Self {
+        let half = T::from(0.5).unwrap();
+        Self {
+            base_color,
+            metallic: T::zero(),
+            roughness: half,
+            anisotropic: T::zero(),
+            specular: half,
+            specular_tint: T::zero(),
+            sheen: T::zero(),
+            sheen_tint: half,
+            sheen_roughness: half,
+            clearcoat: T::zero(),
+            clearcoat_gloss: T::one(),
+            transmission: T::zero(),
+            eta: T::from(1.5).unwrap(),
+        }
+    }
+}
+
+/// An "uber" BSDF combining diffuse, metallic, sheen, clear coat and transmission lobes from
+/// intuitive parameters, as most materials are authored
+///
+/// The sheen is that of [`SheenBsdf`] rather than Burley's original.
+pub struct PrincipledBsdf<T> {
+    lobes: Vec<Lobe<T>>,
+}
+
+struct Lobe<T> {
+    bsdf: Box<dyn Bsdf<T>>,
+    /// The probability of sampling the lobe
+    probability: T,
+}
+
+impl<T: Float + FloatConst + 'static> PrincipledBsdf<T> {
+    #[must_use]
+    pub fn new(params: &PrincipledParameters<T>) -> Self {
+        let p = params;
+        let one = T::one();
+        let lerp = |a: Rgb<T>, b: Rgb<T>, t: T| a * (one - t) + b * t;
+        let white = Rgb::splat(one);
+        let luminance = p.base_color.luminance();
+        let tint = if luminance > T::zero() {
+            p.base_color / luminance
+        } else {
+            white
+        };
+
+        let aspect = (one - T::from(0.9).unwrap() * p.anisotropic).sqrt();
+        let alpha = p.roughness * p.roughness;
+        let (alpha_x, alpha_y) = (alpha / aspect, alpha * aspect);
+        // The specular lobe is kept glossy, but polished glass is specular
+        let min_alpha = T::from(1e-3).unwrap();
+        let distribution = TrowbridgeReitz::new(alpha_x.max(min_alpha), alpha_y.max(min_alpha));
+        let glass_distribution = TrowbridgeReitz::new(alpha_x, alpha_y);
+
+        let diffuse_weight = (one - p.metallic) * (one - p.transmission);
+        let glass_weight = (one - p.metallic) * p.transmission;
+        let f0 = lerp(
+            lerp(white, tint, p.specular_tint) * (T::from(0.08).unwrap() * p.specular),
+            p.base_color,
+            p.metallic,
+        );
+        let sheen = lerp(white, tint, p.sheen_tint) * (p.sheen * diffuse_weight);
+        let glass: Box<dyn Bsdf<T>> = if glass_distribution.effectively_smooth() {
+            Box::new(DielectricBsdf::new(p.eta))
+        } else {
+            Box::new(RoughDielectricBsdf::new(glass_distribution, p.eta))
+        };
+        let clearcoat_alpha = T::from(0.1).unwrap() * (one - p.clearcoat_gloss)
+            + T::from(0.001).unwrap() * p.clearcoat_gloss;
+
+        let candidates: [(Box<dyn Bsdf<T>>, T); 5] = [
+            (
+                Box::new(DisneyDiffuse {
+                    color: p.base_color * diffuse_weight,
+                    roughness: p.roughness,
+                }),
+                diffuse_weight * luminance.max(T::from(0.01).unwrap()),
+            ),
+            (
+                Box::new(SheenBsdf::new(sheen, p.sheen_roughness)),
+                sheen.max_component(),
+            ),
+            (
+                Box::new(SchlickMicrofacet { distribution, f0 }),
+                f0.max_component().max(T::from(0.1).unwrap()),
+            ),
+            (
+                Box::new(Tinted {
+                    bsdf: glass,
+                    tint: p.base_color.map(T::sqrt) * glass_weight,
+                }),
+                glass_weight,
+            ),
+            (
+                Box::new(Clearcoat {
+                    alpha: clearcoat_alpha,
+                    weight: T::from(0.25).unwrap() * p.clearcoat,
+                }),
+                T::from(0.25).unwrap() * p.clearcoat,
+            ),
+        ];
+        let total = candidates
+            .iter()
+            .fold(T::zero(), |sum, (_, weight)| sum + *weight);
+        let lobes = candidates
+            .into_iter()
+            .filter(|(_, weight)| *weight > T::zero())
+            .map(|(bsdf, weight)| Lobe {
+                bsdf,
+                probability: weight / total,
+            })
+            .collect();
+        Self { lobes }
+    }
+}
+
+impl<T: Float> Bsdf<T> for PrincipledBsdf<T> {
+    fn flags(&self) -> BsdfFlags {
+        self.lobes
+            .iter()
+            .fold(BsdfFlags::empty(), |flags, lobe| flags | lobe.bsdf.flags())
+    }
+
+    fn f(&self, wo: Vector3<T, ShadingSpace>, wi: Vector3<T, ShadingSpace>) -> Rgb<T> {
+        self.lobes.iter().map(|lobe| lobe.bsdf.f(wo, wi)).sum()
+    }
+
+    /// Picks a lobe with `uc` and samples it with what remains of `uc`
+    fn sample_f(
+        &self,
+        wo: Vector3<T, ShadingSpace>,
+        uc: T,
+        u: Point2<T, UnknownUnit>,
+    ) -> Option<BsdfSample<T>> {
+        let mut start = T::zero();
+        let lobe = self.lobes.iter().find(|lobe| {
+            start = start + lobe.probability;
+            uc < start
+        });
+        let lobe = lobe.or(self.lobes.last())?;
+        let uc = ((uc - (start - lobe.probability)) / lobe.probability)
+            .max(T::zero())
+            .min(T::one() - T::epsilon());
+        let sample = lobe.bsdf.sample_f(wo, uc, u)?;
+        if sample.flags.is_specular() {
+            return Some(BsdfSample {
+                pdf: sample.pdf * lobe.probability,
+                ..sample
+            });
+        }
+        let pdf = self.pdf(wo, sample.wi);
+        (pdf > T::zero()).then(|| BsdfSample {
+            f: self.f(wo, sample.wi),
+            pdf,
+            ..sample
+        })
+    }
+
+    fn pdf(&self, wo: Vector3<T, ShadingSpace>, wi: Vector3<T, ShadingSpace>) -> T {
+        self.lobes.iter().fold(T::zero(), |pdf, lobe| {
+            pdf + lobe.probability * lobe.bsdf.pdf(wo, wi)
+        })
+    }
+}
+
+/// Returns `(1 - cos)^5`, how much Schlick's approximation of the Fresnel reflectance
+/// increases towards grazing angles
+#[inline]
+fn schlick_weight<T: Float>(cos_theta: T) -> T {
+    (T::one() - cos_theta.abs())
+        .max(T::zero())
+        .min(T::one())
+        .powi(5)
+}
+
+/// The half vector of two directions in the same hemisphere, on the side of +z
+#[inline]
+fn half_vector<T: Float>(
+    wo: Vector3<T, ShadingSpace>,
+    wi: Vector3<T, ShadingSpace>,
+) -> Option<Vector3<T, ShadingSpace>> {
+    if !same_hemisphere(wo, wi) {
+        return None;
+    }
+    let wm = (wo + wi).try_normalize()?;
+    Some(if wm.z < T::zero() { -wm } else { wm })
+}
+
+/// Burley's diffuse lobe, with retroreflection at grazing angles on rough surfaces
+struct DisneyDiffuse<T> {
+    color: Rgb<T>,
+    roughness: T,
+}
+
+impl<T: Float + FloatConst> Bsdf<T> for DisneyDiffuse<T> {
+    fn flags(&self) -> BsdfFlags {
+        BsdfFlags::REFLECTION | BsdfFlags::DIFFUSE
+    }
+
+    fn f(&self, wo: Vector3<T, ShadingSpace>, wi: Vector3<T, ShadingSpace>) -> Rgb<T> {
+        let Some(wm) = half_vector(wo, wi) else {
+            return Rgb::black();
+        };
+        let half = T::from(0.5).unwrap();
+        let (fo, fi) = (schlick_weight(wo.z), schlick_weight(wi.z));
+        let cos_d = wi.dot(wm);
+        let rr = (T::one() + T::one()) * self.roughness * cos_d * cos_d;
+        let lambert = (T::one() - half * fo) * (T::one() - half * fi);
+        let retro = rr * (fo + fi + fo * fi * (rr - T::one()));
+        self.color * (T::FRAC_1_PI() * (lambert + retro))
+    }
+
+    fn sample_f(
+        &self,
+        wo: Vector3<T, ShadingSpace>,
+        _uc: T,
+        u: Point2<T, UnknownUnit>,
+    ) -> Option<BsdfSample<T>> {
+        if wo.z == T::zero() {
+            return None;
+        }
+        let mut wi: Vector3<T, ShadingSpace> = sample_cosine_hemisphere(u);
+        if wo.z < T::zero() {
+            wi.z = -wi.z;
+        }
+        Some(BsdfSample {
+            f: self.f(wo, wi),
+            wi,
+            pdf: self.pdf(wo, wi),
+            flags: BsdfFlags::REFLECTION | BsdfFlags::DIFFUSE,
+        })
+    }
+
+    fn pdf(&self, wo: Vector3<T, ShadingSpace>, wi: Vector3<T, ShadingSpace>) -> T {
+        if same_hemisphere(wo, wi) {
+            cosine_hemisphere_pdf(wi.z.abs())
+        } else {
+            T::zero()
+        }
+    }
+}
+
+/// Microfacet reflection with Schlick's approximation of the Fresnel reflectance
+struct SchlickMicrofacet<T> {
+    distribution: TrowbridgeReitz<T>,
+    /// The reflectance at normal incidence
+    f0: Rgb<T>,
+}
+
+impl<T: Float + FloatConst> Bsdf<T> for SchlickMicrofacet<T> {
+    fn flags(&self) -> BsdfFlags {
+        BsdfFlags::REFLECTION | BsdfFlags::GLOSSY
+    }
+
+    fn f(&self, wo: Vector3<T, ShadingSpace>, wi: Vector3<T, ShadingSpace>) -> Rgb<T> {
+        let Some(wm) = half_vector(wo, wi) else {
+            return Rgb::black();
+        };
+        let fresnel = self.f0 + (Rgb::splat(T::one()) - self.f0) * schlick_weight(wo.dot(wm));
+        let d = self.distribution.d(wm);
+        let g = self.distribution.g(wo, wi);
+        fresnel * (d * g / (T::from(4).unwrap() * wo.z * wi.z).abs())
+    }
+
+    fn sample_f(
+        &self,
+        wo: Vector3<T, ShadingSpace>,
+        _uc: T,
+        u: Point2<T, UnknownUnit>,
+    ) -> Option<BsdfSample<T>> {
+        if wo.z == T::zero() {
+            return None;
+        }
+        let wm = self.distribution.sample_wm(wo, u);
+        let wi = -wo + wm * (wo.dot(wm) * (T::one() + T::one()));
+        if !same_hemisphere(wo, wi) {
+            return None;
+        }
+        Some(BsdfSample {
+            f: self.f(wo, wi),
+            wi,
+            pdf: self.pdf(wo, wi),
+            flags: BsdfFlags::REFLECTION | BsdfFlags::GLOSSY,
+        })
+    }
+
+    fn pdf(&self, wo: Vector3<T, ShadingSpace>, wi: Vector3<T, ShadingSpace>) -> T {
+        let Some(wm) = half_vector(wo, wi) else {
+            return T::zero();
+        };
+        self.distribution.pdf(wo, wm) / (T::from(4).unwrap() * wo.dot(wm).abs())
+    }
+}
+
+/// The clear coat, a specular lobe with the long-tailed GTR1 distribution of Burley and a
+/// fixed index of refraction of 1.5
+struct Clearcoat<T> {
+    alpha: T,
+    weight: T,
+}
+
+impl<T: Float + FloatConst> Clearcoat<T> {
+    /// The density of normals of the GTR1 distribution, with respect to projected area
+    fn d(&self, cos_theta: T) -> T {
+        let alpha2 = self.alpha * self.alpha;
+        let t = T::one() + (alpha2 - T::one()) * cos_theta * cos_theta;
+        (alpha2 - T::one()) / (T::PI() * alpha2.ln() * t)
+    }
+
+    /// The Smith masking term of a GGX distribution with a fixed roughness
+    fn g1(cos_theta: T) -> T {
+        let alpha2 = T::from(0.0625).unwrap();
+        let cos2 = cos_theta * cos_theta;
+        let tan2 = (T::one() - cos2).max(T::zero()) / cos2;
+        (T::one() + T::one()) / (T::one() + (T::one() + alpha2 * tan2).sqrt())
+    }
+}
+
+impl<T: Float + FloatConst> Bsdf<T> for Clearcoat<T> {
+    fn flags(&self) -> BsdfFlags {
+        BsdfFlags::REFLECTION | BsdfFlags::GLOSSY
+    }
+
+    fn f(&self, wo: Vector3<T, ShadingSpace>, wi: Vector3<T, ShadingSpace>) -> Rgb<T> {
+        let Some(wm) = half_vector(wo, wi) else {
+            return Rgb::black();
+        };
+        let f0 = T::from(0.04).unwrap();
+        let fresnel = f0 + (T::one() - f0) * schlick_weight(wo.dot(wm));
+        let g = Self::g1(wo.z) * Self::g1(wi.z);
+        let f = self.weight * self.d(wm.z) * fresnel * g / (T::from(4).unwrap() * wo.z * wi.z);
+        Rgb::splat(f.abs())
+    }
+
+    /// Samples the normals proportionally to their projected area
+    fn sample_f(
+        &self,
+        wo: Vector3<T, ShadingSpace>,
+        _uc: T,
+        u: Point2<T, UnknownUnit>,
+    ) -> Option<BsdfSample<T>> {
+        if wo.z == T::zero() {
+            return None;
+        }
+        let alpha2 = self.alpha * self.alpha;
+        let cos2_theta = (T::one() - alpha2.powf(T::one() - u.x)) / (T::one() - alpha2);
+        let cos_theta = cos2_theta.max(T::zero()).sqrt();
+        let sin_theta = (T::one() - cos2_theta).max(T::zero()).sqrt();
+        let (sin_phi, cos_phi) = (T::TAU() * u.y).sin_cos();
+        let mut wm = Vector3::new(sin_theta * cos_phi, sin_theta * sin_phi, cos_theta);
+        if wo.z < T::zero() {
+            wm.z = -wm.z;
+        }
+        let wi = -wo + wm * (wo.dot(wm) * (T::one() + T::one()));
+        if !same_hemisphere(wo, wi) {
+            return None;
+        }
+        Some(BsdfSample {
+            f: self.f(wo, wi),
+            wi,
+            pdf: self.pdf(wo, wi),
+            flags: BsdfFlags::REFLECTION | BsdfFlags::GLOSSY,
+        })
+    }
+
+    fn pdf(&self, wo: Vector3<T, ShadingSpace>, wi: Vector3<T, ShadingSpace>) -> T {
+        let Some(wm) = half_vector(wo, wi) else {
+            return T::zero();
+        };
+        self.d(wm.z) * wm.z / (T::from(4).unwrap() * wo.dot(wm).abs())
+    }
+}
+
+/// A BSDF with its scattering multiplied by a color
+struct Tinted<T> {
+    bsdf: Box<dyn Bsdf<T>>,
+    tint: Rgb<T>,
+}
+
+impl<T: Float> Bsdf<T> for Tinted<T> {
+    fn flags(&self) -> BsdfFlags {
+        self.bsdf.flags()
+    }
+
+    fn f(&self, wo: Vector3<T, ShadingSpace>, wi: Vector3<T, ShadingSpace>) -> Rgb<T> {
+        self.bsdf.f(wo, wi) * self.tint
+    }
+
+    fn sample_f(
+        &self,
+        wo: Vector3<T, ShadingSpace>,
+        uc: T,
+        u: Point2<T, UnknownUnit>,
+    ) -> Option<BsdfSample<T>> {
+        let sample = self.bsdf.sample_f(wo, uc, u)?;
+        Some(BsdfSample {
+            f: sample.f * self.tint,
+            ..sample
+        })
+    }
+
+    fn pdf(&self, wo: Vector3<T, ShadingSpace>, wi: Vector3<T, ShadingSpace>) -> T {
+        self.bsdf.pdf(wo, wi)
+    }
+
+    fn eta(&self) -> T {
+        self.bsdf.eta()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::Pcg32;
+
+    #[test]
+    fn test_principled() {
+        let mut rng = Pcg32::new(5);
+        let n = 20_000;
+        let uc: Vec<f64> = (0..n).map(|_| rng.uniform()).collect();
+        let u: Vec<_> = (0..n)
+            .map(|_| Point2::new(rng.uniform(), rng.uniform()))
+            .collect();
+        let base = PrincipledParameters::new(Rgb::new(0.8, 0.5, 0.2));
+        let wo = Vector3::new(0.3, -0.2, 0.9).normalize();
+
+        let plastic = PrincipledBsdf::new(&base);
+        let metal = PrincipledBsdf::new(&PrincipledParameters {
+            metallic: 1.,
+            roughness: 0.3,
+            ..base
+        });
+        let coated = PrincipledBsdf::new(&PrincipledParameters {
+            clearcoat: 1.,
+            sheen: 1.,
+            anisotropic: 0.8,
+            ..base
+        });
+        for bsdf in [&plastic, &metal, &coated] {
+            for (&uc, &u) in uc.iter().zip(&u).take(100) {
+                let Some(sample) = bsdf.sample_f(wo, uc, u) else {
+                    continue;
+                };
+                assert_eq!(sample.f, bsdf.f(wo, sample.wi));
+                assert!((sample.pdf - bsdf.pdf(wo, sample.wi)).abs() < 1e-9 * sample.pdf);
+            }
+            let rho = bsdf.rho(wo, &uc, &u);
+            assert!(rho.max_component() < 1.05, "{rho:?}");
+        }
+
+        // Metals take on the base color
+        let rho = metal.rho(wo, &uc, &u);
+        assert!(rho.r > rho.g && rho.g > rho.b && rho.b > 0.1, "{rho:?}");
+
+        // Glass transmits most light, and smooth glass only in specular directions
+        let glass = PrincipledBsdf::new(&PrincipledParameters {
+            transmission: 1.,
+            roughness: 0.,
+            ..PrincipledParameters::new(Rgb::splat(1.))
+        });
+        assert!(glass.flags().contains(BsdfFlags::TRANSMISSION));
+        let sample = glass.sample_f(wo, 0.99, u[0]).unwrap();
+        assert!(sample.flags.is_specular() && sample.wi.z < 0.);
+    }
+}
@@ -0,0 +1,202 @@
+use crate::{
+    bsdf::{
+        fresnel_dielectric, same_hemisphere, Bsdf, BsdfFlags, BsdfSample, ShadingSpace,
+        TrowbridgeReitz,
+    },
+    color::Rgb,
+    core::geometry::{Point2, UnknownUnit, Vector3},
+};
+use num_traits::{Float, FloatConst};
+
+/// The rough boundary of a dielectric, made of microfacets which each reflect and refract light
+/// like a smooth [`DielectricBsdf`](crate::bsdf::DielectricBsdf)
+///
+/// Microfacets follow the GGX distribution, are masked and shadowed as in the Smith model, and
+/// are sampled among those visible from the outgoing direction.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct RoughDielectricBsdf<T> {
+    pub distribution: TrowbridgeReitz<T>,
+    /// The index of refraction of the inside, which the normal points away from, relative to
+    /// the outside
+    pub eta: T,
+}
+
+impl<T> RoughDielectricBsdf<T> {
+    #[inline]
+    #[must_use]
+    pub const fn new(distribution: TrowbridgeReitz<T>, eta: T) -> Self {
+        Self { distribution, eta }
+    }
+}
+
+impl<T: Float + FloatConst> RoughDielectricBsdf<T> {
+    /// Returns the microfacet normal scattering `wo` into `wi`, on the side of +z, and the
+    /// relative index of refraction along the way
+    fn half_vector(
+        &self,
+        wo: Vector3<T, ShadingSpace>,
+        wi: Vector3<T, ShadingSpace>,
+    ) -> Option<(Vector3<T, ShadingSpace>, T)> {
+        if wo.z == T::zero() || wi.z == T::zero() {
+            return None;
+        }
+        let etap = if same_hemisphere(wo, wi) {
+            T::one()
+        } else if wo.z > T::zero() {
+            self.eta
+        } else {
+            self.eta.recip()
+        };
+        let wm = (wi * etap + wo).try_normalize()?;
+        let wm = if wm.z < T::zero() { -wm } else { wm };
+        // Microfacets facing away from either direction don't scatter between them
+        if wm.dot(wi) * wi.z < T::zero() || wm.dot(wo) * wo.z < T::zero() {
+            return None;
+        }
+        Some((wm, etap))
+    }
+}
+
+impl<T: Float + FloatConst> Bsdf<T> for RoughDielectricBsdf<T> {
+    #[inline]
+    fn flags(&self) -> BsdfFlags {
+        if self.eta == T::one() {
+            BsdfFlags::TRANSMISSION | BsdfFlags::GLOSSY
+        } else {
+            BsdfFlags::REFLECTION | BsdfFlags::TRANSMISSION | BsdfFlags::GLOSSY
+        }
+    }
+
+    fn f(&self, wo: Vector3<T, ShadingSpace>, wi: Vector3<T, ShadingSpace>) -> Rgb<T> {
+        let Some((wm, etap)) = self.half_vector(wo, wi) else {
+            return Rgb::black();
+        };
+        let fresnel = fresnel_dielectric(wo.dot(wm), self.eta);
+        let d = self.distribution.d(wm);
+        let g = self.distribution.g(wo, wi);
+        if etap == T::one() {
+            return Rgb::splat((d * g * fresnel / (T::from(4).unwrap() * wi.z * wo.z)).abs());
+        }
+        let denom = (wi.dot(wm) + wo.dot(wm) / etap).powi(2) * wi.z * wo.z;
+        let f = d * (T::one() - fresnel) * g * (wi.dot(wm) * wo.dot(wm) / denom).abs();
+        // Radiance is compressed into the smaller solid angle on the denser side
+        Rgb::splat(f / (etap * etap))
+    }
+
+    /// Picks reflection or refraction by `uc` in proportion to the Fresnel reflectance of a
+    /// visible microfacet sampled with `u`
+    fn sample_f(
+        &self,
+        wo: Vector3<T, ShadingSpace>,
+        uc: T,
+        u: Point2<T, UnknownUnit>,
+    ) -> Option<BsdfSample<T>> {
+        if wo.z == T::zero() {
+            return None;
+        }
+        let wm = self.distribution.sample_wm(wo, u);
+        let cos_o = wo.dot(wm);
+        let (wi, flags) = if uc < fresnel_dielectric(cos_o, self.eta) {
+            let wi = -wo + wm * (cos_o * (T::one() + T::one()));
+            if !same_hemisphere(wo, wi) {
+                return None;
+            }
+            (wi, BsdfFlags::REFLECTION | BsdfFlags::GLOSSY)
+        } else {
+            let wi = refract_about(wo, wm, self.eta)?;
+            if same_hemisphere(wo, wi) || wi.z == T::zero() {
+                return None;
+            }
+            (wi, BsdfFlags::TRANSMISSION | BsdfFlags::GLOSSY)
+        };
+        let pdf = self.pdf(wo, wi);
+        (pdf > T::zero()).then(|| BsdfSample {
+            f: self.f(wo, wi),
+            wi,
+            pdf,
+            flags,
+        })
+    }
+
+    fn pdf(&self, wo: Vector3<T, ShadingSpace>, wi: Vector3<T, ShadingSpace>) -> T {
+        let Some((wm, etap)) = self.half_vector(wo, wi) else {
+            return T::zero();
+        };
+        let r = fresnel_dielectric(wo.dot(wm), self.eta);
+        // The change of variables from the microfacet normal to the scattered direction
+        let (dwm_dwi, choice) = if etap == T::one() {
+            ((T::from(4).unwrap() * wo.dot(wm).abs()).recip(), r)
+        } else {
+            let denom = (wi.dot(wm) + wo.dot(wm) / etap).powi(2);
+            (wi.dot(wm).abs() / denom, T::one() - r)
+        };
+        self.distribution.pdf(wo, wm) * dwm_dwi * choice
+    }
+
+    #[inline]
+    fn eta(&self) -> T {
+        self.eta
+    }
+}
+
+/// Refracts `wo` through a boundary with normal `n` and relative index of refraction `eta`,
+/// like [`refract`](crate::bsdf::refract) does about +z
+fn refract_about<T: Float>(
+    wo: Vector3<T, ShadingSpace>,
+    n: Vector3<T, ShadingSpace>,
+    eta: T,
+) -> Option<Vector3<T, ShadingSpace>> {
+    let (mut cos_theta_i, mut eta, mut n) = (n.dot(wo), eta, n);
+    if cos_theta_i < T::zero() {
+        eta = eta.recip();
+        cos_theta_i = -cos_theta_i;
+        n = -n;
+    }
+    let sin2_theta_t = (T::one() - cos_theta_i * cos_theta_i).max(T::zero()) / (eta * eta);
+    if sin2_theta_t >= T::one() {
+        return None;
+    }
+    let cos_theta_t = (T::one() - sin2_theta_t).sqrt();
+    Some(-wo / eta + n * (cos_theta_i / eta - cos_theta_t))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bsdf::DielectricBsdf, sampler::Pcg32};
+
+    #[test]
+    fn test_rough_dielectric() {
+        let glass = RoughDielectricBsdf::new(TrowbridgeReitz::new(0.3, 0.2), 1.5);
+        let mut rng = Pcg32::new(3);
+        let n = 10_000;
+        let uc: Vec<f64> = (0..n).map(|_| rng.uniform()).collect();
+        let u: Vec<_> = (0..n)
+            .map(|_| Point2::new(rng.uniform(), rng.uniform()))
+            .collect();
+        for wo in [Vector3::new(0.2, 0.4, 0.8), Vector3::new(-0.6, 0.1, -0.5)] {
+            let wo = wo.normalize();
+            let (mut reflected, mut transmitted) = (false, false);
+            for (&uc, &u) in uc.iter().zip(&u).take(100) {
+                let Some(sample) = glass.sample_f(wo, uc, u) else {
+                    continue;
+                };
+                reflected |= sample.flags.contains(BsdfFlags::REFLECTION);
+                transmitted |= sample.flags.contains(BsdfFlags::TRANSMISSION);
+                assert_eq!(
+                    same_hemisphere(wo, sample.wi),
+                    sample.flags.contains(BsdfFlags::REFLECTION)
+                );
+                assert_eq!(sample.f, glass.f(wo, sample.wi));
+                assert!((sample.pdf - glass.pdf(wo, sample.wi)).abs() < 1e-9 * sample.pdf);
+            }
+            assert!(reflected && transmitted);
+        }
+
+        // Close to the smooth boundary, with radiance compressed on entering the glass
+        let wo = Vector3::new(0.2, 0.4, 0.8).normalize();
+        let rho = glass.rho(wo, &uc, &u);
+        let smooth = DielectricBsdf::new(1.5).rho(wo, &uc, &u);
+        assert!((rho.r - smooth.r).abs() < 0.02, "{rho:?} != {smooth:?}");
+    }
+}
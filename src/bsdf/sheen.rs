@@ -0,0 +1,151 @@
+use crate::{
+    bsdf::{same_hemisphere, Bsdf, BsdfFlags, BsdfSample, ShadingSpace},
+    color::Rgb,
+    core::geometry::{Point2, UnknownUnit, Vector3},
+    sampling::{cosine_hemisphere_pdf, sample_cosine_hemisphere},
+};
+use num_traits::{Float, FloatConst};
+
+/// The sheen of fabrics such as velvet, whose fibers scatter light at grazing angles, after
+/// Estevez and Kulla, "Production Friendly Microfacet Sheen BRDF" (2017)
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct SheenBsdf<T> {
+    pub color: Rgb<T>,
+    /// The roughness in `[0, 1]`, squared for the width of the distribution of fibers
+    pub roughness: T,
+}
+
+impl<T> SheenBsdf<T> {
+    #[inline]
+    #[must_use]
+    pub const fn new(color: Rgb<T>, roughness: T) -> Self {
+        Self { color, roughness }
+    }
+}
+
+impl<T: Float + FloatConst> SheenBsdf<T> {
+    #[inline]
+    fn alpha(&self) -> T {
+        (self.roughness * self.roughness)
+            .max(T::from(1e-3).unwrap())
+            .min(T::one())
+    }
+
+    /// The "Charlie" distribution of normals, whose density grows towards the horizon
+    fn d(&self, cos_theta: T) -> T {
+        let inv_alpha = self.alpha().recip();
+        let sin_theta = (T::one() - cos_theta * cos_theta).max(T::zero()).sqrt();
+        (T::one() + T::one() + inv_alpha) * sin_theta.powf(inv_alpha) * T::FRAC_1_PI()
+            / (T::one() + T::one())
+    }
+
+    /// The curve fitted to the masking of the distribution
+    fn l(&self, x: T) -> T {
+        let r = T::one() - (T::one() - self.alpha()).powi(2);
+        let lerp = |a: f64, b: f64| T::from(a).unwrap() * (T::one() - r) + T::from(b).unwrap() * r;
+        let (a, b, c) = (
+            lerp(25.3245, 21.5473),
+            lerp(3.32435, 3.82987),
+            lerp(0.16801, 0.19823),
+        );
+        let (d, e) = (lerp(-1.27393, -1.97760), lerp(-4.85967, -4.32054));
+        a / (T::one() + b * x.powf(c)) + d * x + e
+    }
+
+    fn lambda(&self, cos_theta: T) -> T {
+        let half = T::from(0.5).unwrap();
+        let cos_theta = cos_theta.abs();
+        if cos_theta < half {
+            self.l(cos_theta).exp()
+        } else {
+            (self.l(half) * (T::one() + T::one()) - self.l(T::one() - cos_theta)).exp()
+        }
+    }
+}
+
+impl<T: Float + FloatConst> Bsdf<T> for SheenBsdf<T> {
+    #[inline]
+    fn flags(&self) -> BsdfFlags {
+        if self.color.is_black() {
+            BsdfFlags::empty()
+        } else {
+            BsdfFlags::REFLECTION | BsdfFlags::DIFFUSE
+        }
+    }
+
+    fn f(&self, wo: Vector3<T, ShadingSpace>, wi: Vector3<T, ShadingSpace>) -> Rgb<T> {
+        if !same_hemisphere(wo, wi) {
+            return Rgb::black();
+        }
+        let Some(wm) = (wo + wi).try_normalize() else {
+            return Rgb::black();
+        };
+        let g = (T::one() + self.lambda(wo.z) + self.lambda(wi.z)).recip();
+        let f = self.d(wm.z) * g / (T::from(4).unwrap() * wo.z * wi.z).abs();
+        self.color * f
+    }
+
+    fn sample_f(
+        &self,
+        wo: Vector3<T, ShadingSpace>,
+        _uc: T,
+        u: Point2<T, UnknownUnit>,
+    ) -> Option<BsdfSample<T>> {
+        if wo.z == T::zero() {
+            return None;
+        }
+        let mut wi: Vector3<T, ShadingSpace> = sample_cosine_hemisphere(u);
+        if wo.z < T::zero() {
+            wi.z = -wi.z;
+        }
+        let pdf = cosine_hemisphere_pdf(wi.z.abs());
+        (pdf > T::zero()).then(|| BsdfSample {
+            f: self.f(wo, wi),
+            wi,
+            pdf,
+            flags: BsdfFlags::REFLECTION | BsdfFlags::DIFFUSE,
+        })
+    }
+
+    #[inline]
+    fn pdf(&self, wo: Vector3<T, ShadingSpace>, wi: Vector3<T, ShadingSpace>) -> T {
+        if same_hemisphere(wo, wi) {
+            cosine_hemisphere_pdf(wi.z.abs())
+        } else {
+            T::zero()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::Pcg32;
+
+    #[test]
+    fn test_sheen() {
+        let mut rng = Pcg32::new(7);
+        let n = 50_000;
+        let uc: Vec<f64> = (0..n).map(|_| rng.uniform()).collect();
+        let u: Vec<_> = (0..n)
+            .map(|_| Point2::new(rng.uniform(), rng.uniform()))
+            .collect();
+        let normal = Vector3::new(0., 0., 1.);
+        let grazing = Vector3::new(0.95, 0., 0.1).normalize();
+        for roughness in [0.3, 0.7, 1.] {
+            let sheen = SheenBsdf::new(Rgb::splat(1.), roughness);
+            // Fibers reflect more light towards grazing angles, and never more than arrives
+            let (rho_normal, rho_grazing) =
+                (sheen.rho(normal, &uc, &u), sheen.rho(grazing, &uc, &u));
+            assert!(
+                rho_normal.r < rho_grazing.r && rho_grazing.r <= 1.,
+                "{rho_grazing:?}"
+            );
+            let wi = Vector3::new(-0.3, 0.4, 0.5).normalize();
+            assert!((sheen.f(grazing, wi).r - sheen.f(wi, grazing).r).abs() < 1e-12);
+            let sample = sheen.sample_f(-grazing, uc[0], u[0]).unwrap();
+            assert_eq!(sample.f, sheen.f(-grazing, sample.wi));
+            assert_eq!(sample.pdf, sheen.pdf(-grazing, sample.wi));
+        }
+    }
+}
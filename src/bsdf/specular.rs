@@ -0,0 +1,151 @@
+use crate::{
+    bsdf::{fresnel_dielectric, reflect, refract, Bsdf, BsdfFlags, BsdfSample, ShadingSpace},
+    color::Rgb,
+    core::geometry::{Point2, UnknownUnit, Vector3},
+};
+use num_traits::Float;
+
+/// A perfect mirror, reflecting a fraction of the light into the mirrored direction
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct MirrorBsdf<T> {
+    pub reflectance: Rgb<T>,
+}
+
+impl<T> MirrorBsdf<T> {
+    #[inline]
+    #[must_use]
+    pub const fn new(reflectance: Rgb<T>) -> Self {
+        Self { reflectance }
+    }
+}
+
+impl<T: Float> Bsdf<T> for MirrorBsdf<T> {
+    #[inline]
+    fn flags(&self) -> BsdfFlags {
+        BsdfFlags::REFLECTION | BsdfFlags::SPECULAR
+    }
+
+    #[inline]
+    fn f(&self, _wo: Vector3<T, ShadingSpace>, _wi: Vector3<T, ShadingSpace>) -> Rgb<T> {
+        Rgb::black()
+    }
+
+    fn sample_f(
+        &self,
+        wo: Vector3<T, ShadingSpace>,
+        _uc: T,
+        _u: Point2<T, UnknownUnit>,
+    ) -> Option<BsdfSample<T>> {
+        let wi = reflect(wo);
+        (wi.z != T::zero()).then(|| BsdfSample {
+            f: self.reflectance / wi.z.abs(),
+            wi,
+            pdf: T::one(),
+            flags: BsdfFlags::REFLECTION | BsdfFlags::SPECULAR,
+        })
+    }
+
+    #[inline]
+    fn pdf(&self, _wo: Vector3<T, ShadingSpace>, _wi: Vector3<T, ShadingSpace>) -> T {
+        T::zero()
+    }
+}
+
+/// The smooth boundary of a dielectric such as glass or water, which reflects and refracts
+/// light in proportions given by the Fresnel equations
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct DielectricBsdf<T> {
+    /// The index of refraction of the inside, which the normal points away from, relative to
+    /// the outside
+    pub eta: T,
+}
+
+impl<T> DielectricBsdf<T> {
+    #[inline]
+    #[must_use]
+    pub const fn new(eta: T) -> Self {
+        Self { eta }
+    }
+}
+
+impl<T: Float> Bsdf<T> for DielectricBsdf<T> {
+    #[inline]
+    fn flags(&self) -> BsdfFlags {
+        if self.eta == T::one() {
+            BsdfFlags::TRANSMISSION | BsdfFlags::SPECULAR
+        } else {
+            BsdfFlags::REFLECTION | BsdfFlags::TRANSMISSION | BsdfFlags::SPECULAR
+        }
+    }
+
+    #[inline]
+    fn f(&self, _wo: Vector3<T, ShadingSpace>, _wi: Vector3<T, ShadingSpace>) -> Rgb<T> {
+        Rgb::black()
+    }
+
+    /// Reflects if `uc` is below the Fresnel reflectance, and refracts otherwise
+    fn sample_f(
+        &self,
+        wo: Vector3<T, ShadingSpace>,
+        uc: T,
+        _u: Point2<T, UnknownUnit>,
+    ) -> Option<BsdfSample<T>> {
+        let r = fresnel_dielectric(wo.z, self.eta);
+        let t = T::one() - r;
+        if uc < r {
+            let wi = reflect(wo);
+            return (wi.z != T::zero()).then(|| BsdfSample {
+                f: Rgb::splat(r / wi.z.abs()),
+                wi,
+                pdf: r,
+                flags: BsdfFlags::REFLECTION | BsdfFlags::SPECULAR,
+            });
+        }
+        let (wi, eta) = refract(wo, self.eta)?;
+        // Radiance is compressed into the smaller solid angle on the denser side
+        let f = t / (wi.z.abs() * eta * eta);
+        (wi.z != T::zero()).then(|| BsdfSample {
+            f: Rgb::splat(f),
+            wi,
+            pdf: t,
+            flags: BsdfFlags::TRANSMISSION | BsdfFlags::SPECULAR,
+        })
+    }
+
+    #[inline]
+    fn pdf(&self, _wo: Vector3<T, ShadingSpace>, _wi: Vector3<T, ShadingSpace>) -> T {
+        T::zero()
+    }
+
+    #[inline]
+    fn eta(&self) -> T {
+        self.eta
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dielectric() {
+        assert!((fresnel_dielectric(1_f64, 1.5) - 0.04).abs() < 1e-12);
+        assert_eq!(fresnel_dielectric(0.1_f64, 1. / 1.5), 1.);
+
+        let glass = DielectricBsdf::new(1.5_f64);
+        let wo = Vector3::new(0.6, 0., 0.8);
+        let u = Point2::new(0.5, 0.5);
+        let r = glass.sample_f(wo, 0., u).unwrap();
+        assert!(r.flags.contains(BsdfFlags::REFLECTION));
+        assert_eq!(r.wi, Vector3::new(-0.6, 0., 0.8));
+
+        // Snell's law holds for the refracted direction, from either side
+        let t = glass.sample_f(wo, 1., u).unwrap();
+        assert!(t.flags.contains(BsdfFlags::TRANSMISSION));
+        assert!(t.wi.z < 0. && (t.wi.length() - 1.).abs() < 1e-12);
+        assert!((0.6 - 1.5 * -t.wi.x).abs() < 1e-12);
+        let back = glass.sample_f(t.wi, 1., u).unwrap();
+        assert!((back.wi - wo).length() < 1e-12);
+        assert!((r.pdf + t.pdf - 1.).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,62 @@
+use crate::{
+    core::{
+        geometry::{Point2, UnknownUnit},
+        units::Angle,
+    },
+    sampling::{sample_regular_polygon, sample_uniform_disk_concentric, PiecewiseConstant2D},
+};
+use num_traits::{Float, FloatConst};
+use std::sync::Arc;
+
+/// The shape of a lens opening, which out-of-focus highlights (bokeh) take on
+#[derive(Debug, Clone, PartialEq)]
+pub enum Aperture<T> {
+    /// A perfect disk
+    Circular,
+    /// A regular polygon formed by the blades of an iris diaphragm, with a corner at `rotation`
+    Polygonal { blades: u32, rotation: Angle<T> },
+    /// An arbitrary shape whose transmittance is given by an image spanning the square around
+    /// the lens
+    Textured(Arc<PiecewiseConstant2D<T>>),
+}
+
+impl<T> Default for Aperture<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::Circular
+    }
+}
+
+impl<T: Float> Aperture<T> {
+    /// Creates a textured aperture from `width * height` transmittances in row-major order,
+    /// starting at the top row
+    ///
+    /// Panics if the number of values does not match either dimension.
+    #[must_use]
+    pub fn textured(width: usize, height: usize, transmittance: &[T]) -> Self {
+        Self::Textured(Arc::new(PiecewiseConstant2D::new(
+            transmittance,
+            width,
+            height,
+        )))
+    }
+}
+
+impl<T: Float + FloatConst> Aperture<T> {
+    /// Warps a uniform sample to a position on the aperture, within `[-1, 1]^2`
+    ///
+    /// Panics if a polygonal aperture has fewer than 3 blades.
+    #[must_use]
+    pub fn sample(&self, u: Point2<T, UnknownUnit>) -> Point2<T, UnknownUnit> {
+        match self {
+            Self::Circular => sample_uniform_disk_concentric(u),
+            Self::Polygonal { blades, rotation } => sample_regular_polygon(u, *blades, *rotation),
+            Self::Textured(distribution) => {
+                let (p, _) = distribution.sample(u);
+                let two = T::one() + T::one();
+                // Image rows go down while lens coordinates go up
+                Point2::new(p.x * two - T::one(), T::one() - p.y * two)
+            }
+        }
+    }
+}
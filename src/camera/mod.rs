@@ -0,0 +1,58 @@
+mod aperture;
+mod thin_lens;
+
+pub use aperture::Aperture;
+pub use thin_lens::ThinLensCamera;
+
+use crate::core::geometry::{Point2, Ray, RayDifferential, UnknownUnit};
+use num_traits::Float;
+
+/// The space of a camera, looking down -z with +y up
+pub enum CameraSpace {}
+
+/// Positions on the film and the lens from which a camera ray is generated
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct CameraSample<T> {
+    /// Normalized film position, from the top-left corner at `(0, 0)` to the bottom-right one at
+    /// `(1, 1)`
+    pub film: Point2<T, UnknownUnit>,
+    /// A uniform sample in `[0, 1)^2`, warped to a position on the aperture
+    pub lens: Point2<T, UnknownUnit>,
+    /// The moment within the shutter interval, in `[0, 1)`
+    pub time: T,
+}
+
+pub trait Camera<T, U> {
+    /// Returns the normalized ray leaving the camera through the sampled film and lens positions,
+    /// at the sampled moment
+    #[must_use]
+    fn generate_ray(&self, sample: &CameraSample<T>) -> Ray<T, U>;
+
+    /// Returns the ray of [`generate_ray`](Self::generate_ray) along with the rays through the
+    /// film positions `dx` and `dy` further along each axis, through the same lens position
+    #[must_use]
+    fn generate_ray_differential(
+        &self,
+        sample: &CameraSample<T>,
+        dx: T,
+        dy: T,
+    ) -> (Ray<T, U>, RayDifferential<T, U>)
+    where
+        T: Float,
+    {
+        let offset = |dx: T, dy: T| {
+            self.generate_ray(&CameraSample {
+                film: Point2::new(sample.film.x + dx, sample.film.y + dy),
+                ..*sample
+            })
+        };
+        let (rx, ry) = (offset(dx, T::zero()), offset(T::zero(), dy));
+        let differential = RayDifferential {
+            rx_origin: rx.origin,
+            rx_dir: rx.dir,
+            ry_origin: ry.origin,
+            ry_dir: ry.dir,
+        };
+        (self.generate_ray(sample), differential)
+    }
+}
@@ -0,0 +1,211 @@
+use crate::{
+    camera::{Aperture, Camera, CameraSample, CameraSpace},
+    core::{
+        geometry::{
+            transform::{AnimatedTransform, Transform3, Transformation},
+            Box3, Point3, Ray, Vector3,
+        },
+        units::Angle,
+    },
+};
+use num_traits::{Float, FloatConst};
+use std::fmt;
+
+/// A perspective camera with a lens of finite size, which blurs everything away from the focal
+/// plane
+///
+/// With a `lens_radius` of zero it behaves like a pinhole camera.
+pub struct ThinLensCamera<T, U> {
+    camera_to_world: AnimatedTransform<T, CameraSpace, U>,
+    /// Half the extent of the film at a distance of one, horizontally and vertically
+    screen: (T, T),
+    pub lens_radius: T,
+    /// Distance along the view direction of the plane in focus
+    pub focal_distance: T,
+    pub aperture: Aperture<T>,
+}
+
+impl<T: fmt::Debug, U> fmt::Debug for ThinLensCamera<T, U>
+where
+    AnimatedTransform<T, CameraSpace, U>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ThinLensCamera")
+            .field("camera_to_world", &self.camera_to_world)
+            .field("screen", &self.screen)
+            .field("lens_radius", &self.lens_radius)
+            .field("focal_distance", &self.focal_distance)
+            .field("aperture", &self.aperture)
+            .finish()
+    }
+}
+
+impl<T: Copy, U> Clone for ThinLensCamera<T, U> {
+    fn clone(&self) -> Self {
+        Self {
+            camera_to_world: self.camera_to_world.clone(),
+            screen: self.screen,
+            lens_radius: self.lens_radius,
+            focal_distance: self.focal_distance,
+            aperture: self.aperture.clone(),
+        }
+    }
+}
+
+impl<T: Float, U> ThinLensCamera<T, U> {
+    /// Creates a pinhole camera with the given vertical field of view and width to height ratio
+    #[must_use]
+    pub fn new(
+        camera_to_world: Transform3<T, CameraSpace, U>,
+        fov_y: Angle<T>,
+        aspect_ratio: T,
+    ) -> Self {
+        let half = T::from(0.5).unwrap();
+        let y = (fov_y.radians() * half).tan();
+        Self {
+            camera_to_world: camera_to_world.into(),
+            screen: (y * aspect_ratio, y),
+            lens_radius: T::zero(),
+            focal_distance: T::one(),
+            aperture: Aperture::Circular,
+        }
+    }
+
+    /// Creates a pinhole camera at `eye` looking towards `look`
+    #[must_use]
+    pub fn look_at(
+        eye: Point3<T, U>,
+        look: Point3<T, U>,
+        up: Vector3<T, U>,
+        fov_y: Angle<T>,
+        aspect_ratio: T,
+    ) -> Self {
+        let world_to_camera = Transform3::<T, U, CameraSpace>::look_at_rh(eye, look, up);
+        Self::new(world_to_camera.inverse(), fov_y, aspect_ratio)
+    }
+
+    /// Moves the camera over the shutter interval, such as between two placements made with
+    /// [`look_at_rh`](Transform3::look_at_rh)
+    #[must_use]
+    pub fn with_motion(mut self, camera_to_world: AnimatedTransform<T, CameraSpace, U>) -> Self {
+        self.camera_to_world = camera_to_world;
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn camera_to_world(&self) -> &AnimatedTransform<T, CameraSpace, U> {
+        &self.camera_to_world
+    }
+
+    /// Returns about how many pixels across bounds appear on an image `height` pixels tall,
+    /// from the diameter of the sphere around them at the start of the shutter interval
+    ///
+    /// Bounds around the camera fill the image, and are infinitely large.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the transform of the camera is projective.
+    #[must_use]
+    pub fn projected_size(&self, bounds: Box3<T, U>, height: usize) -> T {
+        let eye: Point3<T, U> = self
+            .camera_to_world
+            .at(T::zero())
+            .transform(Point3::origin())
+            .try_into()
+            .expect("camera transforms must be affine");
+        let center = bounds.center();
+        let radius = (bounds.max - center).length();
+        let distance = (center - eye).length();
+        if distance <= radius {
+            return T::infinity();
+        }
+        radius / (distance * self.screen.1) * T::from(height).unwrap()
+    }
+}
+
+impl<T: Float + FloatConst, U> Camera<T, U> for ThinLensCamera<T, U> {
+    fn generate_ray(&self, sample: &CameraSample<T>) -> Ray<T, U> {
+        let two = T::one() + T::one();
+        let x = (sample.film.x * two - T::one()) * self.screen.0;
+        let y = (T::one() - sample.film.y * two) * self.screen.1;
+        let mut ray = Ray::with_time(Point3::origin(), Vector3::new(x, y, -T::one()), sample.time);
+
+        if self.lens_radius > T::zero() {
+            let lens = self.aperture.sample(sample.lens);
+            let origin = Point3::new(
+                lens.x * self.lens_radius,
+                lens.y * self.lens_radius,
+                T::zero(),
+            );
+            // All rays through the same film position converge on the focal plane
+            let focus = Point3::origin() + ray.dir * self.focal_distance;
+            ray = Ray::with_time(origin, focus - origin, sample.time);
+        }
+        self.camera_to_world
+            .at(sample.time)
+            .transform(ray)
+            .expect("camera transforms must be affine")
+            .normalize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        geometry::{Point2, UnknownUnit},
+        num::ApproxEq,
+        units::Time,
+    };
+
+    type P = Point3<f32, UnknownUnit>;
+    type V = Vector3<f32, UnknownUnit>;
+
+    #[test]
+    fn test_generate_ray() {
+        let eye = P::new(1., 2., 3.);
+        let look = P::new(1., 2., 13.);
+        let mut camera =
+            ThinLensCamera::look_at(eye, look, V::new(0., 1., 0.), Angle::from_degrees(90.), 2.);
+        let center = Point2::new(0.5, 0.5);
+        let ray = camera.generate_ray(&CameraSample {
+            film: center,
+            lens: center,
+            time: 0.,
+        });
+        assert!(ray.origin.approx_eq(&eye));
+        assert!(ray.dir.approx_eq(&V::new(0., 0., 1.)));
+
+        let ray = camera.generate_ray(&CameraSample {
+            film: Point2::new(0.5, 0.),
+            lens: center,
+            time: 0.,
+        });
+        assert!(ray.dir.approx_eq(&V::new(0., 1., 1.).normalize()));
+
+        camera.lens_radius = 0.5;
+        camera.focal_distance = 10.;
+        for aperture in [
+            Aperture::Circular,
+            Aperture::Polygonal {
+                blades: 6,
+                rotation: Angle::from_degrees(15.),
+            },
+            Aperture::textured(2, 2, &[0., 1., 1., 0.]),
+        ] {
+            camera.aperture = aperture;
+            for lens in [(0.1, 0.9), (0.7, 0.3), (0.99, 0.01)] {
+                let ray = camera.generate_ray(&CameraSample {
+                    film: center,
+                    lens: lens.into(),
+                    time: 0.,
+                });
+                assert!((ray.origin - eye).length() <= 0.5 * 2f32.sqrt() + 1e-5);
+                // Rays through the film center all converge on the focus point
+                let t = (look.z - ray.origin.z) / ray.dir.z;
+                assert!((ray.at(Time(t)) - look).length() < 1e-4);
+            }
+        }
+    }
+}
@@ -0,0 +1,6 @@
+mod rgb;
+mod xyz;
+
+pub use rgb::{linear_to_srgb, srgb_to_linear, Rgb, Rgba};
+pub use xyz::{chromatic_adaptation, white_point, Chromaticity, RgbColorSpace, XyY, Xyz};
+pub(crate) use xyz::{invert, mul, Matrix3};
@@ -0,0 +1,268 @@
+use num_traits::Float;
+use std::ops::*;
+
+/// A linear RGB color with sRGB (Rec. 709) primaries
+#[derive(Debug, Default, Copy, Clone, PartialEq, Hash)]
+pub struct Rgb<T> {
+    pub r: T,
+    pub g: T,
+    pub b: T,
+}
+
+/// A linear RGB color with straight (not premultiplied) alpha
+#[derive(Debug, Default, Copy, Clone, PartialEq, Hash)]
+pub struct Rgba<T> {
+    pub r: T,
+    pub g: T,
+    pub b: T,
+    pub a: T,
+}
+
+impl<T> Rgb<T> {
+    #[inline]
+    #[must_use]
+    pub const fn new(r: T, g: T, b: T) -> Self {
+        Self { r, g, b }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn splat(x: T) -> Self
+    where
+        T: Copy,
+    {
+        Self::new(x, x, x)
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn map<V>(self, mut f: impl FnMut(T) -> V) -> Rgb<V> {
+        Rgb::new(f(self.r), f(self.g), f(self.b))
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn to_array(self) -> [T; 3] {
+        [self.r, self.g, self.b]
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn with_alpha(self, a: T) -> Rgba<T> {
+        Rgba::new(self.r, self.g, self.b, a)
+    }
+}
+
+impl<T: Float> Rgb<T> {
+    #[inline]
+    #[must_use]
+    pub fn black() -> Self {
+        Self::splat(T::zero())
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn is_black(self) -> bool {
+        self.r == T::zero() && self.g == T::zero() && self.b == T::zero()
+    }
+
+    /// Returns the relative luminance, the Y component of the color in CIE XYZ
+    #[inline]
+    #[must_use]
+    pub fn luminance(self) -> T {
+        let [wr, wg, wb] = [0.2126, 0.7152, 0.0722].map(|w| T::from(w).unwrap());
+        wr * self.r + wg * self.g + wb * self.b
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn max_component(self) -> T {
+        self.r.max(self.g).max(self.b)
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn clamp(self, min: T, max: T) -> Self {
+        self.map(|c| c.max(min).min(max))
+    }
+
+    /// Scales the color down so that no channel exceeds `max`, keeping its hue
+    #[inline]
+    #[must_use]
+    pub fn clamp_max_component(self, max: T) -> Self {
+        let brightest = self.max_component();
+        if brightest > max {
+            self * (max / brightest)
+        } else {
+            self
+        }
+    }
+
+    /// Applies the sRGB transfer function to each channel, for display
+    #[inline]
+    #[must_use]
+    pub fn to_srgb(self) -> Self {
+        self.map(linear_to_srgb)
+    }
+
+    /// Undoes the sRGB transfer function of each channel, for colors picked on screen or read
+    /// from 8-bit images
+    #[inline]
+    #[must_use]
+    pub fn from_srgb(self) -> Self {
+        self.map(srgb_to_linear)
+    }
+}
+
+impl<T> Rgba<T> {
+    #[inline]
+    #[must_use]
+    pub const fn new(r: T, g: T, b: T, a: T) -> Self {
+        Self { r, g, b, a }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn rgb(self) -> Rgb<T> {
+        Rgb::new(self.r, self.g, self.b)
+    }
+}
+
+impl<T: Float> Rgba<T> {
+    /// Encodes the color channels with the sRGB transfer function, leaving alpha linear
+    #[inline]
+    #[must_use]
+    pub fn to_srgb(self) -> Self {
+        self.rgb().to_srgb().with_alpha(self.a)
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn from_srgb(self) -> Self {
+        self.rgb().from_srgb().with_alpha(self.a)
+    }
+
+    /// Returns the color multiplied by its alpha
+    #[inline]
+    #[must_use]
+    pub fn premultiplied(self) -> Rgb<T> {
+        self.rgb() * self.a
+    }
+}
+
+impl<T> From<[T; 3]> for Rgb<T> {
+    #[inline]
+    fn from([r, g, b]: [T; 3]) -> Self {
+        Self::new(r, g, b)
+    }
+}
+
+impl<T> From<Rgb<T>> for [T; 3] {
+    #[inline]
+    fn from(c: Rgb<T>) -> Self {
+        c.to_array()
+    }
+}
+
+/// Applies the sRGB transfer function to a linear value, clamped to `[0, 1]`
+#[must_use]
+pub fn linear_to_srgb<T: Float>(x: T) -> T {
+    let x = x.max(T::zero()).min(T::one());
+    if x <= T::from(0.003_130_8).unwrap() {
+        x * T::from(12.92).unwrap()
+    } else {
+        T::from(1.055).unwrap() * x.powf(T::from(1. / 2.4).unwrap()) - T::from(0.055).unwrap()
+    }
+}
+
+/// Undoes the sRGB transfer function of a value, clamped to `[0, 1]`
+#[must_use]
+pub fn srgb_to_linear<T: Float>(x: T) -> T {
+    let x = x.max(T::zero()).min(T::one());
+    if x <= T::from(0.040_45).unwrap() {
+        x / T::from(12.92).unwrap()
+    } else {
+        ((x + T::from(0.055).unwrap()) / T::from(1.055).unwrap()).powf(T::from(2.4).unwrap())
+    }
+}
+
+macro_rules! impl_ops {
+    ($Trait:ident { fn $func:ident }, $TraitAssign:ident { fn $func_assign:ident }) => {
+        impl<T: $Trait<Output = T>> $Trait for Rgb<T> {
+            type Output = Self;
+
+            #[inline]
+            fn $func(self, rhs: Self) -> Self {
+                Self::new(
+                    self.r.$func(rhs.r),
+                    self.g.$func(rhs.g),
+                    self.b.$func(rhs.b),
+                )
+            }
+        }
+
+        impl<T: Copy + $Trait<Output = T>> $Trait<T> for Rgb<T> {
+            type Output = Self;
+
+            #[inline]
+            fn $func(self, rhs: T) -> Self {
+                Self::new(self.r.$func(rhs), self.g.$func(rhs), self.b.$func(rhs))
+            }
+        }
+
+        // Only requiring the binary operator keeps these usable with `num_traits::Float`
+        impl<T: Copy + $Trait<Output = T>> $TraitAssign for Rgb<T> {
+            #[inline]
+            fn $func_assign(&mut self, rhs: Self) {
+                *self = (*self).$func(rhs);
+            }
+        }
+
+        impl<T: Copy + $Trait<Output = T>> $TraitAssign<T> for Rgb<T> {
+            #[inline]
+            fn $func_assign(&mut self, rhs: T) {
+                *self = (*self).$func(rhs);
+            }
+        }
+    };
+}
+
+impl_ops!(Add { fn add }, AddAssign { fn add_assign });
+impl_ops!(Sub { fn sub }, SubAssign { fn sub_assign });
+impl_ops!(Mul { fn mul }, MulAssign { fn mul_assign });
+impl_ops!(Div { fn div }, DivAssign { fn div_assign });
+
+impl<T: Neg<Output = T>> Neg for Rgb<T> {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self {
+        Self::new(-self.r, -self.g, -self.b)
+    }
+}
+
+impl<T: Float> std::iter::Sum for Rgb<T> {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::black(), Add::add)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_srgb() {
+        for x in [0., 0.002, 0.2, 0.5, 1.] {
+            let c = Rgb::splat(x as f32);
+            let round_trip = c.to_srgb().from_srgb();
+            assert!((round_trip.r - c.r).abs() < 1e-6);
+        }
+        assert!((linear_to_srgb(0.2_f32) - 0.484_529).abs() < 1e-5);
+        assert!((Rgb::splat(2_f32).luminance() - 2.).abs() < 1e-6);
+        assert_eq!(
+            Rgb::new(1., 2., 3.) * Rgb::splat(2.) - Rgb::new(1., 1., 1.) / 2.,
+            Rgb::new(1.5, 3.5, 5.5)
+        );
+    }
+}
@@ -0,0 +1,307 @@
+use crate::color::Rgb;
+use num_traits::Float;
+
+/// A color in the CIE 1931 XYZ space, where `y` is the luminance
+#[derive(Debug, Default, Copy, Clone, PartialEq, Hash)]
+pub struct Xyz<T> {
+    pub x: T,
+    pub y: T,
+    pub z: T,
+}
+
+/// A CIE 1931 xy chromaticity, the hue and saturation of a color regardless of its luminance
+#[derive(Debug, Default, Copy, Clone, PartialEq, Hash)]
+pub struct Chromaticity<T> {
+    pub x: T,
+    pub y: T,
+}
+
+/// A color as its chromaticity and luminance
+#[derive(Debug, Default, Copy, Clone, PartialEq, Hash)]
+pub struct XyY<T> {
+    pub chromaticity: Chromaticity<T>,
+    pub luminance: T,
+}
+
+impl<T> Xyz<T> {
+    #[inline]
+    #[must_use]
+    pub const fn new(x: T, y: T, z: T) -> Self {
+        Self { x, y, z }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn to_array(self) -> [T; 3] {
+        [self.x, self.y, self.z]
+    }
+}
+
+impl<T: Float> Xyz<T> {
+    /// Returns the chromaticity and luminance, with the chromaticity of black taken to be that
+    /// of equal energy
+    #[must_use]
+    pub fn to_xyy(self) -> XyY<T> {
+        let sum = self.x + self.y + self.z;
+        let chromaticity = if sum == T::zero() {
+            let third = T::one() / T::from(3).unwrap();
+            Chromaticity::new(third, third)
+        } else {
+            Chromaticity::new(self.x / sum, self.y / sum)
+        };
+        XyY {
+            chromaticity,
+            luminance: self.y,
+        }
+    }
+}
+
+impl<T> Chromaticity<T> {
+    #[inline]
+    #[must_use]
+    pub const fn new(x: T, y: T) -> Self {
+        Self { x, y }
+    }
+}
+
+impl<T: Float> Chromaticity<T> {
+    /// Returns the color of this chromaticity with the given luminance
+    #[inline]
+    #[must_use]
+    pub fn to_xyz(self, luminance: T) -> Xyz<T> {
+        if self.y == T::zero() {
+            return Xyz::new(T::zero(), T::zero(), T::zero());
+        }
+        let scale = luminance / self.y;
+        Xyz::new(
+            self.x * scale,
+            luminance,
+            (T::one() - self.x - self.y) * scale,
+        )
+    }
+}
+
+impl<T: Float> XyY<T> {
+    #[inline]
+    #[must_use]
+    pub fn to_xyz(self) -> Xyz<T> {
+        self.chromaticity.to_xyz(self.luminance)
+    }
+}
+
+/// Standard illuminants, as chromaticities of the CIE 1931 2° observer
+pub mod white_point {
+    use super::Chromaticity;
+
+    /// Average daylight, the white of sRGB and Rec. 2020
+    pub const D65: Chromaticity<f64> = Chromaticity::new(0.3127, 0.3290);
+    /// The white of the ACES color spaces
+    pub const D60: Chromaticity<f64> = Chromaticity::new(0.32168, 0.33767);
+    /// The white of print and ICC profile connection spaces
+    pub const D50: Chromaticity<f64> = Chromaticity::new(0.3457, 0.3585);
+    /// Equal energy
+    pub const E: Chromaticity<f64> = Chromaticity::new(1. / 3., 1. / 3.);
+}
+
+pub(crate) type Matrix3<T> = [[T; 3]; 3];
+
+/// A linear RGB space defined by the chromaticities of its primaries and white point
+#[derive(Debug, Clone, PartialEq)]
+pub struct RgbColorSpace<T> {
+    pub r: Chromaticity<T>,
+    pub g: Chromaticity<T>,
+    pub b: Chromaticity<T>,
+    pub white: Chromaticity<T>,
+    rgb_to_xyz: Matrix3<T>,
+    xyz_to_rgb: Matrix3<T>,
+}
+
+impl<T: Float> RgbColorSpace<T> {
+    /// Panics if the primaries are collinear
+    #[must_use]
+    pub fn new(
+        r: Chromaticity<T>,
+        g: Chromaticity<T>,
+        b: Chromaticity<T>,
+        white: Chromaticity<T>,
+    ) -> Self {
+        let [r_xyz, g_xyz, b_xyz] = [r, g, b].map(|c| c.to_xyz(T::one()).to_array());
+        let primaries = transpose([r_xyz, g_xyz, b_xyz]);
+        let inverse = invert(primaries).expect("primaries must not be collinear");
+        // Scale the primaries so that white has a luminance of one
+        let scale = mul(inverse, white.to_xyz(T::one()).to_array());
+        let rgb_to_xyz = primaries.map(|row| [0, 1, 2].map(|i| row[i] * scale[i]));
+        Self {
+            r,
+            g,
+            b,
+            white,
+            rgb_to_xyz,
+            xyz_to_rgb: invert(rgb_to_xyz).unwrap(),
+        }
+    }
+
+    /// The color space of sRGB and Rec. 709
+    #[must_use]
+    pub fn srgb() -> Self {
+        Self::from_f64([(0.64, 0.33), (0.30, 0.60), (0.15, 0.06)], white_point::D65)
+    }
+
+    /// The wide gamut of Rec. 2020 UHDTV
+    #[must_use]
+    pub fn rec2020() -> Self {
+        Self::from_f64(
+            [(0.708, 0.292), (0.170, 0.797), (0.131, 0.046)],
+            white_point::D65,
+        )
+    }
+
+    /// The ACES working space for rendering and compositing, with AP1 primaries
+    #[must_use]
+    pub fn aces_cg() -> Self {
+        Self::from_f64(
+            [(0.713, 0.293), (0.165, 0.830), (0.128, 0.044)],
+            white_point::D60,
+        )
+    }
+
+    fn from_f64(primaries: [(f64, f64); 3], white: Chromaticity<f64>) -> Self {
+        let cast = |(x, y)| Chromaticity::new(T::from(x).unwrap(), T::from(y).unwrap());
+        let [r, g, b] = primaries.map(cast);
+        Self::new(r, g, b, cast((white.x, white.y)))
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn to_xyz(&self, c: Rgb<T>) -> Xyz<T> {
+        let [x, y, z] = mul(self.rgb_to_xyz, c.to_array());
+        Xyz::new(x, y, z)
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn from_xyz(&self, c: Xyz<T>) -> Rgb<T> {
+        mul(self.xyz_to_rgb, c.to_array()).into()
+    }
+
+    /// Converts a color to another space, adapting it from this white point to the other's with
+    /// the Bradford transform, so that white maps to white
+    #[must_use]
+    pub fn convert(&self, c: Rgb<T>, to: &Self) -> Rgb<T> {
+        let xyz = self.to_xyz(c);
+        let xyz = if self.white == to.white {
+            xyz
+        } else {
+            chromatic_adaptation(self.white, to.white, xyz)
+        };
+        to.from_xyz(xyz)
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn rgb_to_xyz_matrix(&self) -> &[[T; 3]; 3] {
+        &self.rgb_to_xyz
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn xyz_to_rgb_matrix(&self) -> &[[T; 3]; 3] {
+        &self.xyz_to_rgb
+    }
+}
+
+/// Adapts a color seen under the white `from` to how it appears under `to`, with the Bradford
+/// transform
+#[must_use]
+pub fn chromatic_adaptation<T: Float>(
+    from: Chromaticity<T>,
+    to: Chromaticity<T>,
+    c: Xyz<T>,
+) -> Xyz<T> {
+    const BRADFORD: Matrix3<f64> = [
+        [0.8951, 0.2664, -0.1614],
+        [-0.7502, 1.7135, 0.0367],
+        [0.0389, -0.0685, 1.0296],
+    ];
+    let bradford = BRADFORD.map(|row| row.map(|x| T::from(x).unwrap()));
+    let cone = |white: Chromaticity<T>| mul(bradford, white.to_xyz(T::one()).to_array());
+    let (src, dst) = (cone(from), cone(to));
+    let lms = mul(bradford, c.to_array());
+    let lms = [0, 1, 2].map(|i| lms[i] * dst[i] / src[i]);
+    let [x, y, z] = mul(invert(bradford).unwrap(), lms);
+    Xyz::new(x, y, z)
+}
+
+#[inline]
+pub(crate) fn mul<T: Float>(m: Matrix3<T>, v: [T; 3]) -> [T; 3] {
+    m.map(|row| row[0] * v[0] + row[1] * v[1] + row[2] * v[2])
+}
+
+#[inline]
+fn transpose<T: Copy>(m: Matrix3<T>) -> Matrix3<T> {
+    [0, 1, 2].map(|i| [m[0][i], m[1][i], m[2][i]])
+}
+
+pub(crate) fn invert<T: Float>(m: Matrix3<T>) -> Option<Matrix3<T>> {
+    let cofactor =
+        |r0: usize, r1: usize, c0: usize, c1: usize| m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0];
+    // The adjugate, as the transposed cofactors
+    let adjugate = [
+        [
+            cofactor(1, 2, 1, 2),
+            -cofactor(0, 2, 1, 2),
+            cofactor(0, 1, 1, 2),
+        ],
+        [
+            -cofactor(1, 2, 0, 2),
+            cofactor(0, 2, 0, 2),
+            -cofactor(0, 1, 0, 2),
+        ],
+        [
+            cofactor(1, 2, 0, 1),
+            -cofactor(0, 2, 0, 1),
+            cofactor(0, 1, 0, 1),
+        ],
+    ];
+    let det = m[0][0] * adjugate[0][0] + m[0][1] * adjugate[1][0] + m[0][2] * adjugate[2][0];
+    (det != T::zero()).then(|| adjugate.map(|row| row.map(|x| x / det)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: [f64; 3], b: [f64; 3], eps: f64) {
+        assert!(
+            a.iter().zip(b).all(|(a, b)| (a - b).abs() < eps),
+            "{a:?} != {b:?}"
+        );
+    }
+
+    #[test]
+    fn test_color_spaces() {
+        let srgb = RgbColorSpace::<f64>::srgb();
+        assert_close(srgb.rgb_to_xyz_matrix()[0], [0.4124, 0.3576, 0.1805], 1e-4);
+        assert_close(srgb.rgb_to_xyz_matrix()[1], [0.2126, 0.7152, 0.0722], 1e-4);
+        let xyz = srgb.to_xyz(Rgb::new(0.2, 0.5, 0.9));
+        assert_close(srgb.from_xyz(xyz).to_array(), [0.2, 0.5, 0.9], 1e-12);
+
+        // Luminance is preserved, and white stays white when adapting to another white point
+        let aces = RgbColorSpace::aces_cg();
+        assert_close(
+            srgb.convert(Rgb::splat(1.), &aces).to_array(),
+            [1.; 3],
+            1e-4,
+        );
+        let c = srgb.convert(Rgb::new(1., 0., 0.), &aces);
+        assert_close(c.to_array(), [0.6131, 0.0701, 0.0206], 1e-3);
+
+        let rec2020 = RgbColorSpace::rec2020();
+        let c = srgb.convert(Rgb::new(0., 1., 0.), &rec2020);
+        assert_close(c.to_array(), [0.3293, 0.9195, 0.0880], 1e-3);
+
+        let xyy = Xyz::new(0.25, 0.5, 0.25).to_xyy();
+        assert_eq!(xyy.chromaticity, Chromaticity::new(0.25, 0.5));
+        assert_close(xyy.to_xyz().to_array(), [0.25, 0.5, 0.25], 1e-12);
+    }
+}
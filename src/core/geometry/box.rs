@@ -1,7 +1,7 @@
 use crate::core::{
     geometry::{transform::*, *},
     num::*,
-    units::Length,
+    units::{Length, Time},
 };
 use num_traits::NumCast;
 use std::{
@@ -510,6 +510,17 @@ impl<T: Copy, U> Box3<T, U> {
         size.x * size.y * size.z
     }
 
+    #[inline]
+    #[must_use]
+    pub fn surface_area(&self) -> T
+    where
+        T: Add<Output = T> + Sub<Output = T> + Mul<Output = T>,
+    {
+        let size = self.size();
+        let half = size.x * size.y + size.y * size.z + size.z * size.x;
+        half + half
+    }
+
     #[inline]
     #[must_use]
     pub fn union(&self, other: &Self) -> Self
@@ -528,6 +539,19 @@ impl<T: Copy, U> Box3<T, U> {
         }
     }
 
+    /// Unlike [`union`](Self::union), empty boxes still contribute their bounds
+    #[inline]
+    #[must_use]
+    pub fn union_unchecked(&self, other: &Self) -> Self
+    where
+        T: PartialOrd,
+    {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
     #[inline]
     #[must_use]
     pub fn intersection(&self, other: &Self) -> Option<Self>
@@ -623,3 +647,26 @@ impl<T: Copy, U> Box3<T, U> {
         Self::new(self.min.floor(), self.max.ceil())
     }
 }
+
+impl<T: num_traits::Float, U> Box3<T, U> {
+    /// Returns the range of ray parameters within `0..=t_max` for which the ray is inside the box
+    #[must_use]
+    pub fn intersect_ray<D>(&self, ray: &Ray<T, U, D>, t_max: Time<T>) -> Option<(T, T)> {
+        let (mut t0, mut t1) = (T::zero(), t_max.0);
+        for axis in Axis3::AXES {
+            let inv_dir = T::one() / ray.dir[axis];
+            let mut near = (self.min[axis] - ray.origin[axis]) * inv_dir;
+            let mut far = (self.max[axis] - ray.origin[axis]) * inv_dir;
+            if near > far {
+                std::mem::swap(&mut near, &mut far);
+            }
+            // Written so that NaNs, from a ray lying in one of the slab's planes, are ignored
+            t0 = if near > t0 { near } else { t0 };
+            t1 = if far < t1 { far } else { t1 };
+            if t0 > t1 {
+                return None;
+            }
+        }
+        Some((t0, t1))
+    }
+}
@@ -2,16 +2,22 @@ mod r#box;
 mod mask;
 mod point;
 mod ray;
+#[cfg(feature = "simd")]
+mod simd;
 mod size;
 pub mod transform;
 mod vector;
+mod wide;
 
 pub use mask::{Mask2, Mask3};
 pub use point::{Point2, Point3};
 pub use r#box::{Box2, Box3};
-pub use ray::Ray;
+pub use ray::{Ray, RayBundle, RayDifferential};
 pub use size::{Size2, Size3};
 pub use vector::{Vector2, Vector3};
+pub use wide::{
+    Mask3x4, Mask3x8, Mask3xN, Point3x4, Point3x8, Point3xN, Vector3x4, Vector3x8, Vector3xN, Wide3,
+};
 
 pub struct Normal<U>(std::marker::PhantomData<U>);
 
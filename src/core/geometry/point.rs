@@ -16,6 +16,9 @@ pub struct Point2<T, U> {
     _unit: PhantomData<U>,
 }
 
+/// With the `simd` feature, `Point3<f32, U>` is padded to fill a 4-lane SIMD register, through
+/// which its arithmetic is computed
+#[cfg_attr(feature = "simd", repr(C, align(16)))]
 pub struct Point3<T, U> {
     pub x: T,
     pub y: T,
@@ -23,6 +26,9 @@ pub struct Point3<T, U> {
     _unit: PhantomData<U>,
 }
 
+#[cfg(feature = "simd")]
+const _: () = assert!(std::mem::size_of::<Point3<f32, UnknownUnit>>() == 16);
+
 impl<T: Default, U> Default for Point2<T, U> {
     fn default() -> Self {
         Self::new(T::default(), T::default())
@@ -531,6 +537,10 @@ impl<T: Neg, U> Neg for Point3<T, U> {
 
     #[inline]
     fn neg(self) -> Self::Output {
+        #[cfg(feature = "simd")]
+        if let Some([x, y, z]) = simd::neg(&self) {
+            return Point3::new(x, y, z);
+        }
         Point3::new(-self.x, -self.y, -self.z)
     }
 }
@@ -549,6 +559,10 @@ impl<T: Copy + Mul, U> Mul<T> for Point3<T, U> {
 
     #[inline]
     fn mul(self, rhs: T) -> Self::Output {
+        #[cfg(feature = "simd")]
+        if let Some([x, y, z]) = simd::mul(&self, &rhs) {
+            return Point3::new(x, y, z);
+        }
         Point3::new(self.x * rhs, self.y * rhs, self.z * rhs)
     }
 }
@@ -584,6 +598,10 @@ impl<T: Copy + Div, U> Div<T> for Point3<T, U> {
 
     #[inline]
     fn div(self, rhs: T) -> Self::Output {
+        #[cfg(feature = "simd")]
+        if let Some([x, y, z]) = simd::div(&self, &rhs) {
+            return Point3::new(x, y, z);
+        }
         Point3::new(self.x / rhs, self.y / rhs, self.z / rhs)
     }
 }
@@ -619,6 +637,10 @@ impl<T: Add, U> Add<Vector3<T, U>> for Point3<T, U> {
 
     #[inline]
     fn add(self, rhs: Vector3<T, U>) -> Self::Output {
+        #[cfg(feature = "simd")]
+        if let Some([x, y, z]) = simd::add(&self, &rhs) {
+            return Point3::new(x, y, z);
+        }
         Point3::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z)
     }
 }
@@ -654,6 +676,10 @@ impl<T: Sub, U> Sub<Vector3<T, U>> for Point3<T, U> {
 
     #[inline]
     fn sub(self, rhs: Vector3<T, U>) -> Self::Output {
+        #[cfg(feature = "simd")]
+        if let Some([x, y, z]) = simd::sub(&self, &rhs) {
+            return Point3::new(x, y, z);
+        }
         Point3::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z)
     }
 }
@@ -689,6 +715,10 @@ impl<T: Sub, U> Sub<Point3<T, U>> for Point3<T, U> {
 
     #[inline]
     fn sub(self, rhs: Point3<T, U>) -> Self::Output {
+        #[cfg(feature = "simd")]
+        if let Some([x, y, z]) = simd::sub(&self, &rhs) {
+            return Vector3::new(x, y, z);
+        }
         Vector3::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z)
     }
 }
@@ -2,15 +2,19 @@ use crate::core::{
     geometry::{Point3, Vector3},
     units::Time,
 };
+use num_traits::ConstZero;
 use std::{
     fmt,
     hash::{Hash, Hasher},
+    marker::PhantomData,
     ops::{Add, Mul},
 };
 
 pub struct Ray<T, U, D = ()> {
     pub origin: Point3<T, U>,
     pub dir: Vector3<T, U>,
+    /// The moment the ray is traced at, from `0` when the shutter opens to `1` when it closes
+    pub time: T,
     pub data: D,
 }
 
@@ -19,6 +23,7 @@ impl<T: fmt::Debug, U, D: fmt::Debug> fmt::Debug for Ray<T, U, D> {
         f.debug_struct("Ray")
             .field("origin", &self.origin)
             .field("dir", &self.dir)
+            .field("time", &self.time)
             .field("data", &self.data)
             .finish()
     }
@@ -31,6 +36,7 @@ impl<T: Clone, U, D: Clone> Clone for Ray<T, U, D> {
         Self {
             origin: self.origin.clone(),
             dir: self.dir.clone(),
+            time: self.time.clone(),
             data: self.data.clone(),
         }
     }
@@ -40,7 +46,10 @@ impl<T: Eq, U, D: Eq> Eq for Ray<T, U, D> {}
 
 impl<T: PartialEq, U, D: PartialEq> PartialEq for Ray<T, U, D> {
     fn eq(&self, other: &Self) -> bool {
-        self.origin == other.origin && self.dir == other.dir && self.data == other.data
+        self.origin == other.origin
+            && self.dir == other.dir
+            && self.time == other.time
+            && self.data == other.data
     }
 }
 
@@ -48,11 +57,13 @@ impl<T: Hash, U, D: Hash> Hash for Ray<T, U, D> {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.origin.hash(state);
         self.dir.hash(state);
+        self.time.hash(state);
         self.data.hash(state);
     }
 }
 
-impl<T, U> Ray<T, U> {
+impl<T: ConstZero, U> Ray<T, U> {
+    /// Creates a ray at the opening of the shutter
     #[inline]
     #[must_use]
     pub const fn new(origin: Point3<T, U>, dir: Vector3<T, U>) -> Self {
@@ -60,13 +71,34 @@ impl<T, U> Ray<T, U> {
     }
 }
 
-impl<T, U, D> Ray<T, U, D> {
+impl<T: ConstZero, U, D> Ray<T, U, D> {
     #[inline]
     #[must_use]
     pub const fn with_data(origin: Point3<T, U>, dir: Vector3<T, U>, data: D) -> Self {
-        Self { origin, dir, data }
+        Self {
+            origin,
+            dir,
+            time: T::ZERO,
+            data,
+        }
+    }
+}
+
+impl<T, U> Ray<T, U> {
+    /// Creates a ray at a moment of the shutter interval
+    #[inline]
+    #[must_use]
+    pub const fn with_time(origin: Point3<T, U>, dir: Vector3<T, U>, time: T) -> Self {
+        Self {
+            origin,
+            dir,
+            time,
+            data: (),
+        }
     }
+}
 
+impl<T, U, D> Ray<T, U, D> {
     #[inline]
     #[must_use]
     pub fn normalize(self) -> Self
@@ -90,3 +122,133 @@ impl<T, U, D> Ray<T, U, D> {
         self.origin + self.dir * t.0
     }
 }
+
+/// The rays through the neighbouring pixels of a camera ray, one pixel further along each axis
+/// of the image
+///
+/// Where they hit a surface relative to the ray itself gives the footprint of the pixel on it,
+/// over which textures are filtered.
+pub struct RayDifferential<T, U> {
+    pub rx_origin: Point3<T, U>,
+    pub rx_dir: Vector3<T, U>,
+    pub ry_origin: Point3<T, U>,
+    pub ry_dir: Vector3<T, U>,
+}
+
+common_impls!(RayDifferential {
+    rx_origin,
+    rx_dir,
+    ry_origin,
+    ry_dir
+});
+
+impl<T: num_traits::Float, U> RayDifferential<T, U> {
+    /// Moves the offset rays towards `ray` by a factor of `s`, such as to make the footprint
+    /// match the spacing of several samples per pixel
+    #[must_use]
+    pub fn scaled(&self, ray: &Ray<T, U>, s: T) -> Self {
+        Self {
+            rx_origin: ray.origin + (self.rx_origin - ray.origin) * s,
+            rx_dir: ray.dir + (self.rx_dir - ray.dir) * s,
+            ry_origin: ray.origin + (self.ry_origin - ray.origin) * s,
+            ry_dir: ray.dir + (self.ry_dir - ray.dir) * s,
+        }
+    }
+}
+
+/// `N` rays stored as a structure of arrays, so that they can be tested against a box at once
+///
+/// Lanes that are not `active` carry no ray and are ignored.
+pub struct RayBundle<T, U, const N: usize> {
+    pub origin: [[T; N]; 3],
+    pub dir: [[T; N]; 3],
+    pub time: [T; N],
+    /// The furthest each ray extends
+    pub t_max: [T; N],
+    pub active: [bool; N],
+    _unit: PhantomData<U>,
+}
+
+impl<T: fmt::Debug, U, const N: usize> fmt::Debug for RayBundle<T, U, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RayBundle")
+            .field("origin", &self.origin)
+            .field("dir", &self.dir)
+            .field("time", &self.time)
+            .field("t_max", &self.t_max)
+            .field("active", &self.active)
+            .finish()
+    }
+}
+
+impl<T: Copy, U, const N: usize> Copy for RayBundle<T, U, N> {}
+
+impl<T: Clone, U, const N: usize> Clone for RayBundle<T, U, N> {
+    fn clone(&self) -> Self {
+        Self {
+            origin: self.origin.clone(),
+            dir: self.dir.clone(),
+            time: self.time.clone(),
+            t_max: self.t_max.clone(),
+            active: self.active,
+            _unit: PhantomData,
+        }
+    }
+}
+
+impl<T: PartialEq, U, const N: usize> PartialEq for RayBundle<T, U, N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.origin == other.origin
+            && self.dir == other.dir
+            && self.time == other.time
+            && self.t_max == other.t_max
+            && self.active == other.active
+    }
+}
+
+impl<T: num_traits::Zero + Copy, U, const N: usize> RayBundle<T, U, N> {
+    /// Packs up to `N` rays with the furthest they extend, leaving the remaining lanes inactive
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are more than `N` rays.
+    #[must_use]
+    pub fn new<D>(rays: impl IntoIterator<Item = (Ray<T, U, D>, Time<T>)>) -> Self {
+        let zero = T::zero();
+        let mut bundle = Self {
+            origin: [[zero; N]; 3],
+            dir: [[zero; N]; 3],
+            time: [zero; N],
+            t_max: [zero; N],
+            active: [false; N],
+            _unit: PhantomData,
+        };
+        for (lane, (ray, t_max)) in rays.into_iter().enumerate() {
+            assert!(lane < N, "too many rays for a bundle of {N}");
+            let (o, d) = (ray.origin, ray.dir);
+            for (axis, (o, d)) in [(o.x, d.x), (o.y, d.y), (o.z, d.z)].into_iter().enumerate() {
+                bundle.origin[axis][lane] = o;
+                bundle.dir[axis][lane] = d;
+            }
+            bundle.time[lane] = ray.time;
+            bundle.t_max[lane] = t_max.0;
+            bundle.active[lane] = true;
+        }
+        bundle
+    }
+}
+
+impl<T: Copy, U, const N: usize> RayBundle<T, U, N> {
+    /// Returns the ray in a lane, whether it is active or not
+    #[inline]
+    #[must_use]
+    pub fn ray(&self, lane: usize) -> Ray<T, U> {
+        let [o, d] = [self.origin, self.dir].map(|v| (v[0][lane], v[1][lane], v[2][lane]));
+        Ray {
+            origin: Point3::new(o.0, o.1, o.2),
+            dir: Vector3::new(d.0, d.1, d.2),
+            time: self.time[lane],
+            data: (),
+        }
+    }
+}
@@ -0,0 +1,372 @@
+//! SIMD kernels for the arithmetic of `Vector3<f32, U>` and `Point3<f32, U>`, with the `simd`
+//! feature
+//!
+//! The arithmetic of vectors and points is generic over their scalar, so the kernels are picked
+//! after monomorphization: each returns `None` unless the scalar is `f32`, which the compiler
+//! folds, leaving either the kernel or the scalar code in place. Every lane goes through the
+//! same operations in the same order as the scalar code, so that the results are identical.
+
+use crate::core::geometry::{Point3, Vector3};
+use std::mem::{size_of, transmute_copy};
+
+/// The coordinates of a vector or point
+pub(crate) trait Xyz<T> {
+    fn xyz(&self) -> [&T; 3];
+}
+
+impl<T, U> Xyz<T> for Vector3<T, U> {
+    #[inline(always)]
+    fn xyz(&self) -> [&T; 3] {
+        [&self.x, &self.y, &self.z]
+    }
+}
+
+impl<T, U> Xyz<T> for Point3<T, U> {
+    #[inline(always)]
+    fn xyz(&self) -> [&T; 3] {
+        [&self.x, &self.y, &self.z]
+    }
+}
+
+#[inline]
+#[must_use]
+pub(crate) fn add<T, O>(a: &impl Xyz<T>, b: &impl Xyz<T>) -> Option<[O; 3]> {
+    store(load(a)?.add(load(b)?))
+}
+
+#[inline]
+#[must_use]
+pub(crate) fn sub<T, O>(a: &impl Xyz<T>, b: &impl Xyz<T>) -> Option<[O; 3]> {
+    store(load(a)?.sub(load(b)?))
+}
+
+#[inline]
+#[must_use]
+pub(crate) fn mul<T, O>(a: &impl Xyz<T>, s: &T) -> Option<[O; 3]> {
+    store(load(a)?.mul(F32x4::splat(scalar(s)?)))
+}
+
+#[inline]
+#[must_use]
+pub(crate) fn div<T, O>(a: &impl Xyz<T>, s: &T) -> Option<[O; 3]> {
+    store(load(a)?.div(F32x4::splat(scalar(s)?)))
+}
+
+#[inline]
+#[must_use]
+pub(crate) fn neg<T, O>(a: &impl Xyz<T>) -> Option<[O; 3]> {
+    store(load(a)?.neg())
+}
+
+#[inline]
+#[must_use]
+pub(crate) fn dot<T>(a: &impl Xyz<T>, b: &impl Xyz<T>) -> Option<T> {
+    Some(cast(load(a)?.mul(load(b)?).sum()))
+}
+
+#[inline]
+#[must_use]
+pub(crate) fn cross<T>(a: &impl Xyz<T>, b: &impl Xyz<T>) -> Option<[T; 3]> {
+    let (a, b) = (load(a)?, load(b)?);
+    store(a.yzx().mul(b.zxy()).sub(a.zxy().mul(b.yzx())))
+}
+
+/// Divides the vector by its length, which is kept in a register throughout
+#[inline]
+#[must_use]
+pub(crate) fn normalize<T>(a: &impl Xyz<T>) -> Option<[T; 3]> {
+    let a = load(a)?;
+    store(a.div(F32x4::splat(a.mul(a).sum()).sqrt()))
+}
+
+/// Returns whether `T` is `f32`
+///
+/// This holds for no other type, and is known once `T` is, unlike a `TypeId` it does not need
+/// `T: 'static`, which the generic arithmetic does not require.
+#[inline(always)]
+fn is_f32<T>() -> bool {
+    size_of::<T>() == size_of::<f32>() && std::any::type_name::<T>() == "f32"
+}
+
+/// Reinterprets an `f32` as a `T` known to be `f32`
+#[inline(always)]
+fn cast<T>(x: f32) -> T {
+    debug_assert!(is_f32::<T>());
+    // SAFETY: `T` is `f32`
+    unsafe { transmute_copy(&x) }
+}
+
+#[inline(always)]
+fn scalar<T>(x: &T) -> Option<f32> {
+    // SAFETY: `T` is `f32`
+    is_f32::<T>().then(|| unsafe { *(x as *const T).cast::<f32>() })
+}
+
+#[inline(always)]
+fn load<T>(a: &impl Xyz<T>) -> Option<F32x4> {
+    let [x, y, z] = a.xyz();
+    Some(F32x4::new(scalar(x)?, scalar(y)?, scalar(z)?))
+}
+
+#[inline(always)]
+fn store<O>(a: F32x4) -> Option<[O; 3]> {
+    let [x, y, z, _] = a.to_array();
+    is_f32::<O>().then(|| [cast(x), cast(y), cast(z)])
+}
+
+/// Four `f32` lanes, the last of which is zero when loaded from a vector or point
+#[derive(Copy, Clone)]
+struct F32x4(imp::Lanes);
+
+/// SSE, which is part of the x86-64 baseline, so that its intrinsics are always safe to call
+#[cfg(target_arch = "x86_64")]
+mod imp {
+    use super::F32x4;
+    use std::arch::x86_64::*;
+
+    pub(super) type Lanes = __m128;
+
+    /// Lanes `[a, b, c, d]` of a shuffle, as the indices of the lanes they take
+    const fn lanes(a: i32, b: i32, c: i32, d: i32) -> i32 {
+        a | b << 2 | c << 4 | d << 6
+    }
+
+    impl F32x4 {
+        #[inline(always)]
+        pub(super) fn new(x: f32, y: f32, z: f32) -> Self {
+            Self(unsafe { _mm_set_ps(0., z, y, x) })
+        }
+
+        #[inline(always)]
+        pub(super) fn splat(x: f32) -> Self {
+            Self(unsafe { _mm_set1_ps(x) })
+        }
+
+        #[inline(always)]
+        pub(super) fn to_array(self) -> [f32; 4] {
+            let mut lanes = [0.; 4];
+            // SAFETY: the array holds four lanes, and SSE is part of the baseline
+            unsafe { _mm_storeu_ps(lanes.as_mut_ptr(), self.0) };
+            lanes
+        }
+
+        #[inline(always)]
+        pub(super) fn add(self, rhs: Self) -> Self {
+            Self(unsafe { _mm_add_ps(self.0, rhs.0) })
+        }
+
+        #[inline(always)]
+        pub(super) fn sub(self, rhs: Self) -> Self {
+            Self(unsafe { _mm_sub_ps(self.0, rhs.0) })
+        }
+
+        #[inline(always)]
+        pub(super) fn mul(self, rhs: Self) -> Self {
+            Self(unsafe { _mm_mul_ps(self.0, rhs.0) })
+        }
+
+        #[inline(always)]
+        pub(super) fn div(self, rhs: Self) -> Self {
+            Self(unsafe { _mm_div_ps(self.0, rhs.0) })
+        }
+
+        /// Flips the sign bits, as scalar negation does
+        #[inline(always)]
+        pub(super) fn neg(self) -> Self {
+            Self(unsafe { _mm_xor_ps(self.0, _mm_set1_ps(-0.)) })
+        }
+
+        #[inline(always)]
+        pub(super) fn sqrt(self) -> Self {
+            Self(unsafe { _mm_sqrt_ps(self.0) })
+        }
+
+        #[inline(always)]
+        pub(super) fn yzx(self) -> Self {
+            Self(unsafe { _mm_shuffle_ps::<{ lanes(1, 2, 0, 3) }>(self.0, self.0) })
+        }
+
+        #[inline(always)]
+        pub(super) fn zxy(self) -> Self {
+            Self(unsafe { _mm_shuffle_ps::<{ lanes(2, 0, 1, 3) }>(self.0, self.0) })
+        }
+
+        /// Returns `(x + y) + z`
+        #[inline(always)]
+        pub(super) fn sum(self) -> f32 {
+            unsafe {
+                let y = _mm_shuffle_ps::<{ lanes(1, 1, 1, 1) }>(self.0, self.0);
+                let z = _mm_shuffle_ps::<{ lanes(2, 2, 2, 2) }>(self.0, self.0);
+                _mm_cvtss_f32(_mm_add_ss(_mm_add_ss(self.0, y), z))
+            }
+        }
+    }
+}
+
+/// Plain arrays elsewhere, which the compiler vectorizes where the target allows
+#[cfg(not(target_arch = "x86_64"))]
+mod imp {
+    use super::F32x4;
+
+    pub(super) type Lanes = [f32; 4];
+
+    impl F32x4 {
+        #[inline(always)]
+        pub(super) fn new(x: f32, y: f32, z: f32) -> Self {
+            Self([x, y, z, 0.])
+        }
+
+        #[inline(always)]
+        pub(super) fn splat(x: f32) -> Self {
+            Self([x; 4])
+        }
+
+        #[inline(always)]
+        pub(super) fn to_array(self) -> [f32; 4] {
+            self.0
+        }
+
+        #[inline(always)]
+        fn zip(self, rhs: Self, f: impl Fn(f32, f32) -> f32) -> Self {
+            Self(std::array::from_fn(|i| f(self.0[i], rhs.0[i])))
+        }
+
+        #[inline(always)]
+        pub(super) fn add(self, rhs: Self) -> Self {
+            self.zip(rhs, |a, b| a + b)
+        }
+
+        #[inline(always)]
+        pub(super) fn sub(self, rhs: Self) -> Self {
+            self.zip(rhs, |a, b| a - b)
+        }
+
+        #[inline(always)]
+        pub(super) fn mul(self, rhs: Self) -> Self {
+            self.zip(rhs, |a, b| a * b)
+        }
+
+        #[inline(always)]
+        pub(super) fn div(self, rhs: Self) -> Self {
+            self.zip(rhs, |a, b| a / b)
+        }
+
+        #[inline(always)]
+        pub(super) fn neg(self) -> Self {
+            Self(self.0.map(|a| -a))
+        }
+
+        #[inline(always)]
+        pub(super) fn sqrt(self) -> Self {
+            Self(self.0.map(f32::sqrt))
+        }
+
+        #[inline(always)]
+        pub(super) fn yzx(self) -> Self {
+            let [x, y, z, w] = self.0;
+            Self([y, z, x, w])
+        }
+
+        #[inline(always)]
+        pub(super) fn zxy(self) -> Self {
+            let [x, y, z, w] = self.0;
+            Self([z, x, y, w])
+        }
+
+        /// Returns `(x + y) + z`
+        #[inline(always)]
+        pub(super) fn sum(self) -> f32 {
+            self.0[0] + self.0[1] + self.0[2]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::geometry::UnknownUnit;
+
+    type V = Vector3<f32, UnknownUnit>;
+    type P = Point3<f32, UnknownUnit>;
+
+    /// Asserts that the lanes hold the same bits, which also compares NaNs
+    fn assert_same(a: [f32; 3], b: [f32; 3]) {
+        assert_eq!(a.map(f32::to_bits), b.map(f32::to_bits), "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn test_matches_scalar() {
+        let mut state = 0x1234_5678_u32;
+        let mut random = || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            // Spans many magnitudes and both signs
+            let mantissa = (state >> 8) as f32 / (1 << 24) as f32 - 0.5;
+            mantissa * 2f32.powi((state & 31) as i32 - 16)
+        };
+        let xyz = |v: V| [v.x, v.y, v.z];
+        let p_xyz = |p: P| [p.x, p.y, p.z];
+
+        for _ in 0..10_000 {
+            let a = V::new(random(), random(), random());
+            let b = V::new(random(), random(), random());
+            let (p, s) = (P::new(random(), random(), random()), random());
+
+            // The operators and methods, against the scalar code they replace
+            assert_eq!(
+                a.dot(b).to_bits(),
+                (a.x * b.x + a.y * b.y + a.z * b.z).to_bits()
+            );
+            assert_eq!(a.length_squared().to_bits(), a.dot(a).to_bits());
+            assert_same(
+                xyz(a.cross(b)),
+                [
+                    a.y * b.z - a.z * b.y,
+                    a.z * b.x - a.x * b.z,
+                    a.x * b.y - a.y * b.x,
+                ],
+            );
+            let length = (a.x * a.x + a.y * a.y + a.z * a.z).sqrt();
+            assert_same(
+                xyz(a.normalize()),
+                [a.x / length, a.y / length, a.z / length],
+            );
+            assert_same(xyz(a + b), [a.x + b.x, a.y + b.y, a.z + b.z]);
+            assert_same(xyz(a - b), [a.x - b.x, a.y - b.y, a.z - b.z]);
+            assert_same(xyz(a * s), [a.x * s, a.y * s, a.z * s]);
+            assert_same(xyz(a / s), [a.x / s, a.y / s, a.z / s]);
+            assert_same(xyz(-a), [-a.x, -a.y, -a.z]);
+            assert_same(p_xyz(p + a), [p.x + a.x, p.y + a.y, p.z + a.z]);
+            assert_same(p_xyz(p - a), [p.x - a.x, p.y - a.y, p.z - a.z]);
+            assert_same(
+                xyz(p - P::new(b.x, b.y, b.z)),
+                [p.x - b.x, p.y - b.y, p.z - b.z],
+            );
+            assert_same(p_xyz(p * s), [p.x * s, p.y * s, p.z * s]);
+            assert_same(p_xyz(p / s), [p.x / s, p.y / s, p.z / s]);
+            assert_same(p_xyz(-p), [-p.x, -p.y, -p.z]);
+        }
+
+        // Zero vectors, infinities and NaNs propagate as they do in the scalar code
+        let special = [
+            V::zero(),
+            V::new(f32::INFINITY, 1., -0.),
+            V::new(f32::NAN, 2., 3.),
+        ];
+        for a in special {
+            for b in special {
+                let dot = a.x * b.x + a.y * b.y + a.z * b.z;
+                assert_eq!(a.dot(b).to_bits(), dot.to_bits());
+                assert_same(xyz(a / 0.), [a.x / 0., a.y / 0., a.z / 0.]);
+            }
+        }
+
+        // Only `f32` is routed to the kernels
+        let v = Vector3::<f64, UnknownUnit>::new(1., 2., 3.);
+        assert_eq!(dot(&v, &v), None);
+        assert_eq!(dot(&V::new(1., 2., 3.), &V::new(4., 5., 6.)), Some(32.));
+        let v = Vector3::<i32, UnknownUnit>::new(1, 2, 3);
+        assert_eq!(add::<_, i32>(&v, &v), None);
+    }
+}
@@ -0,0 +1,366 @@
+use crate::core::geometry::{
+    transform::{Transform3, Transformation},
+    Box3,
+};
+use num_traits::Float;
+use std::fmt;
+
+/// How many times each interval between keys is sampled to bound the motion of a box
+const MOTION_BOUNDS_STEPS: usize = 64;
+
+/// The parts of an affine transform that are interpolated separately, which are applied to
+/// row vectors as a scale with possible shear, then a rotation, then a translation
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Components<T> {
+    scale: [[T; 3]; 3],
+    /// A unit quaternion, with its real part first
+    rotation: [T; 4],
+    translation: [T; 3],
+}
+
+/// A transform that changes over the shutter interval, between keys at increasing moments of
+/// it
+///
+/// Between keys, translations and scales are interpolated linearly and rotations along the
+/// shortest arc, so that objects turn rather than shrink through the middle of a rotation.
+/// Before the first key and after the last, the transform stays at that key. Keys must be
+/// affine.
+pub struct AnimatedTransform<T, Src, Dst> {
+    keys: Vec<(T, Transform3<T, Src, Dst>)>,
+    components: Vec<Components<T>>,
+}
+
+impl<T: Float + fmt::Debug, Src, Dst> fmt::Debug for AnimatedTransform<T, Src, Dst> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AnimatedTransform")
+            .field("keys", &self.keys)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Copy, Src, Dst> Clone for AnimatedTransform<T, Src, Dst> {
+    fn clone(&self) -> Self {
+        Self {
+            keys: self.keys.clone(),
+            components: self.components.clone(),
+        }
+    }
+}
+
+impl<T: Float, Src, Dst> AnimatedTransform<T, Src, Dst> {
+    /// # Panics
+    ///
+    /// Panics if there are no keys or their moments are not increasing.
+    #[must_use]
+    pub fn new(keys: Vec<(T, Transform3<T, Src, Dst>)>) -> Self {
+        assert!(!keys.is_empty(), "animated transforms need a key");
+        assert!(
+            keys.windows(2).all(|pair| pair[0].0 < pair[1].0),
+            "the keys of animated transforms must be in increasing order"
+        );
+        let components = keys.iter().map(|(_, key)| decompose(key)).collect();
+        Self { keys, components }
+    }
+
+    /// Returns a transform that moves from `start` when the shutter opens to `end` when it
+    /// closes
+    #[inline]
+    #[must_use]
+    pub fn between(start: Transform3<T, Src, Dst>, end: Transform3<T, Src, Dst>) -> Self {
+        Self::new(vec![(T::zero(), start), (T::one(), end)])
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn keys(&self) -> &[(T, Transform3<T, Src, Dst>)] {
+        &self.keys
+    }
+
+    /// Whether the transform changes at all over the shutter interval
+    #[inline]
+    #[must_use]
+    pub fn is_animated(&self) -> bool {
+        self.keys.len() > 1
+    }
+
+    /// Returns the transform at the moment `time`
+    #[must_use]
+    pub fn at(&self, time: T) -> Transform3<T, Src, Dst> {
+        let next = self.keys.partition_point(|&(t, _)| t <= time);
+        if next == 0 {
+            return self.keys[0].1;
+        }
+        if next == self.keys.len() {
+            return self.keys[next - 1].1;
+        }
+        let (t0, t1) = (self.keys[next - 1].0, self.keys[next].0);
+        let s = (time - t0) / (t1 - t0);
+        let (c0, c1) = (&self.components[next - 1], &self.components[next]);
+        let lerp = |a: T, b: T| a + (b - a) * s;
+        compose(&Components {
+            scale: std::array::from_fn(|i| {
+                std::array::from_fn(|j| lerp(c0.scale[i][j], c1.scale[i][j]))
+            }),
+            rotation: slerp(c0.rotation, c1.rotation, s),
+            translation: std::array::from_fn(|i| lerp(c0.translation[i], c1.translation[i])),
+        })
+    }
+
+    /// Returns a box containing `bounds` wherever it is moved over the shutter interval
+    ///
+    /// The box is found by transforming `bounds` at many moments between each pair of keys,
+    /// which may miss slivers of the swept volume for fast rotations.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the transform is projective.
+    #[must_use]
+    pub fn motion_bounds(&self, bounds: Box3<T, Src>) -> Box3<T, Dst> {
+        let transformed = |transform: &Transform3<T, Src, Dst>| -> Box3<T, Dst> {
+            transform
+                .transform(bounds)
+                .expect("animated transforms must be affine")
+        };
+        let mut motion = transformed(&self.keys[0].1);
+        for pair in self.keys.windows(2) {
+            let (t0, t1) = (pair[0].0, pair[1].0);
+            for step in 1..=MOTION_BOUNDS_STEPS {
+                let s = T::from(step).unwrap() / T::from(MOTION_BOUNDS_STEPS).unwrap();
+                let moved = transformed(&self.at(t0 + (t1 - t0) * s));
+                motion = motion.union_unchecked(&moved);
+            }
+        }
+        motion
+    }
+}
+
+impl<T: Float, Src, Dst> From<Transform3<T, Src, Dst>> for AnimatedTransform<T, Src, Dst> {
+    /// Returns a transform which stays the same over the shutter interval
+    #[inline]
+    fn from(transform: Transform3<T, Src, Dst>) -> Self {
+        Self::new(vec![(T::zero(), transform)])
+    }
+}
+
+/// Splits an affine transform into a translation, a rotation and what remains of the linear
+/// part, by polar decomposition
+fn decompose<T: Float, Src, Dst>(transform: &Transform3<T, Src, Dst>) -> Components<T> {
+    let mat = transform.to_array();
+    let linear: [[T; 3]; 3] = std::array::from_fn(|i| std::array::from_fn(|j| mat[i][j]));
+
+    // Averaging with the inverse transpose converges to the nearest orthogonal matrix
+    let half = T::from(0.5).unwrap();
+    let mut rotation = linear;
+    for _ in 0..100 {
+        let Some(inverse) = inverse3(rotation) else {
+            break;
+        };
+        let next: [[T; 3]; 3] = std::array::from_fn(|i| {
+            std::array::from_fn(|j| (rotation[i][j] + inverse[j][i]) * half)
+        });
+        let change = (0..9).fold(T::zero(), |max, n| {
+            max.max((next[n / 3][n % 3] - rotation[n / 3][n % 3]).abs())
+        });
+        rotation = next;
+        if change <= T::epsilon() * T::from(16).unwrap() {
+            break;
+        }
+    }
+    // Mirroring is left to the scale, so that the rotation is a proper one
+    if determinant3(rotation) < T::zero() {
+        rotation = rotation.map(|row| row.map(|x| -x));
+    }
+
+    // `linear = scale * rotation`, and the inverse of a rotation is its transpose
+    let scale = std::array::from_fn(|i| {
+        std::array::from_fn(|j| {
+            (0..3).fold(T::zero(), |sum, k| sum + linear[i][k] * rotation[j][k])
+        })
+    });
+    Components {
+        scale,
+        rotation: to_quaternion(rotation),
+        translation: [mat[3][0], mat[3][1], mat[3][2]],
+    }
+}
+
+fn compose<T: Float, Src, Dst>(components: &Components<T>) -> Transform3<T, Src, Dst> {
+    let rotation = from_quaternion(components.rotation);
+    let linear: [[T; 3]; 3] = std::array::from_fn(|i| {
+        std::array::from_fn(|j| {
+            (0..3).fold(T::zero(), |sum, k| {
+                sum + components.scale[i][k] * rotation[k][j]
+            })
+        })
+    });
+    let [x, y, z] = components.translation;
+    let (o, l) = (T::zero(), T::one());
+    Transform3::new([
+        [linear[0][0], linear[0][1], linear[0][2], o],
+        [linear[1][0], linear[1][1], linear[1][2], o],
+        [linear[2][0], linear[2][1], linear[2][2], o],
+        [x, y, z, l],
+    ])
+}
+
+fn determinant3<T: Float>(m: [[T; 3]; 3]) -> T {
+    m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+        - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+        + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+}
+
+fn inverse3<T: Float>(m: [[T; 3]; 3]) -> Option<[[T; 3]; 3]> {
+    let det = determinant3(m);
+    if det == T::zero() {
+        return None;
+    }
+    let inv_det = det.recip();
+    // The transposed cofactors, over the determinant
+    Some(std::array::from_fn(|i| {
+        std::array::from_fn(|j| {
+            let (r0, r1) = ((j + 1) % 3, (j + 2) % 3);
+            let (c0, c1) = ((i + 1) % 3, (i + 2) % 3);
+            (m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]) * inv_det
+        })
+    }))
+}
+
+/// Returns the quaternion of a rotation of row vectors, laid out like the matrices of
+/// [`Rotation3`](super::Rotation3)
+fn to_quaternion<T: Float>(r: [[T; 3]; 3]) -> [T; 4] {
+    let half = T::from(0.5).unwrap();
+    let trace = r[0][0] + r[1][1] + r[2][2];
+    let q = if trace > T::zero() {
+        let s = (trace + T::one()).sqrt();
+        let f = half / s;
+        [
+            s * half,
+            (r[1][2] - r[2][1]) * f,
+            (r[2][0] - r[0][2]) * f,
+            (r[0][1] - r[1][0]) * f,
+        ]
+    } else if r[0][0] >= r[1][1] && r[0][0] >= r[2][2] {
+        let s = (T::one() + r[0][0] - r[1][1] - r[2][2]).sqrt();
+        let f = half / s;
+        [
+            (r[1][2] - r[2][1]) * f,
+            s * half,
+            (r[0][1] + r[1][0]) * f,
+            (r[2][0] + r[0][2]) * f,
+        ]
+    } else if r[1][1] >= r[2][2] {
+        let s = (T::one() + r[1][1] - r[0][0] - r[2][2]).sqrt();
+        let f = half / s;
+        [
+            (r[2][0] - r[0][2]) * f,
+            (r[0][1] + r[1][0]) * f,
+            s * half,
+            (r[1][2] + r[2][1]) * f,
+        ]
+    } else {
+        let s = (T::one() + r[2][2] - r[0][0] - r[1][1]).sqrt();
+        let f = half / s;
+        [
+            (r[0][1] - r[1][0]) * f,
+            (r[2][0] + r[0][2]) * f,
+            (r[1][2] + r[2][1]) * f,
+            s * half,
+        ]
+    };
+    normalize4(q)
+}
+
+fn from_quaternion<T: Float>([a, i, j, k]: [T; 4]) -> [[T; 3]; 3] {
+    let (l, two) = (T::one(), T::from(2).unwrap());
+    [
+        [
+            l - two * (j * j + k * k),
+            two * (i * j + a * k),
+            two * (i * k - a * j),
+        ],
+        [
+            two * (i * j - a * k),
+            l - two * (i * i + k * k),
+            two * (j * k + a * i),
+        ],
+        [
+            two * (i * k + a * j),
+            two * (j * k - a * i),
+            l - two * (i * i + j * j),
+        ],
+    ]
+}
+
+fn normalize4<T: Float>(q: [T; 4]) -> [T; 4] {
+    let norm = q.iter().fold(T::zero(), |sum, &x| sum + x * x).sqrt();
+    q.map(|x| x / norm)
+}
+
+/// Interpolates between two rotations along the shortest arc
+fn slerp<T: Float>(q0: [T; 4], mut q1: [T; 4], s: T) -> [T; 4] {
+    let mut dot = (0..4).fold(T::zero(), |sum, i| sum + q0[i] * q1[i]);
+    if dot < T::zero() {
+        q1 = q1.map(|x| -x);
+        dot = -dot;
+    }
+    // Nearly equal rotations are interpolated linearly, which is as accurate and stable
+    if dot > T::one() - T::from(1e-4).unwrap() {
+        return normalize4(std::array::from_fn(|i| q0[i] + (q1[i] - q0[i]) * s));
+    }
+    let theta = dot.acos() * s;
+    let ortho = normalize4(std::array::from_fn(|i| q1[i] - q0[i] * dot));
+    let (sin, cos) = theta.sin_cos();
+    std::array::from_fn(|i| q0[i] * cos + ortho[i] * sin)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        geometry::{transform::Scale, Point3, UnknownUnit, Vector3},
+        units::Angle,
+    };
+
+    type P = Point3<f64, UnknownUnit>;
+    type V = Vector3<f64, UnknownUnit>;
+    type M = Transform3<f64, UnknownUnit, UnknownUnit>;
+
+    #[test]
+    fn test_animated_transform() {
+        let placement = |scale: f64, degrees: f64, x: f64| {
+            let scale = Scale::new(scale);
+            M::scale(scale, scale, scale)
+                * M::rotation(V::new(0., 0., 1.), Angle::from_degrees(degrees))
+                * M::translation(V::new(x, 0., 0.))
+        };
+        let animated = AnimatedTransform::between(placement(1., 0., 0.), placement(3., 90., 2.));
+        assert!(animated.is_animated());
+
+        // Halfway, the object is turned halfway rather than squashed through the diagonal
+        let expected = placement(2., 45., 1.);
+        let middle = animated.at(0.5);
+        for p in [P::new(1., 0., 0.), P::new(0., 1., 2.), P::new(-3., 0.5, 1.)] {
+            let (a, b): (P, P) = (
+                middle.transform(p).try_into().unwrap(),
+                expected.transform(p).try_into().unwrap(),
+            );
+            assert!((a - b).length() < 1e-9, "{a:?} {b:?}");
+        }
+        assert_eq!(animated.at(-1.), animated.keys()[0].1);
+        assert_eq!(animated.at(2.), animated.keys()[1].1);
+
+        // The bounds of a moving box contain it all along the way, up to the arcs its corners
+        // take between samples
+        let bounds = Box3::new(P::new(-1., -1., -1.), P::new(1., 1., 1.));
+        let motion = animated.motion_bounds(bounds);
+        for i in 0..=10 {
+            let moved: Box3<f64, UnknownUnit> =
+                animated.at(f64::from(i) / 10.).transform(bounds).unwrap();
+            let outside = (motion.min - moved.min).max(moved.max - motion.max);
+            assert!(
+                outside.x.max(outside.y).max(outside.z) < 1e-2,
+                "{outside:?}"
+            );
+        }
+    }
+}
@@ -1,9 +1,12 @@
+mod animated;
 mod rotation;
 mod scale;
+#[allow(clippy::module_inception)]
 mod transform;
 mod translation;
 mod homogen;
 
+pub use animated::AnimatedTransform;
 pub use homogen::HomogeneousVector;
 pub use rotation::{Rotation2, Rotation3};
 pub use scale::Scale;
@@ -136,9 +136,29 @@ impl<T, Src, Dst> Transform3<T, Src, Dst> {
         self.mat == Self::identity().mat
     }
 
+    /// Returns the matrix, which transforms row vectors with the translation in its last row
+    #[inline]
+    #[must_use]
+    pub const fn to_array(&self) -> [[T; 4]; 4]
+    where
+        T: Copy,
+    {
+        self.mat
+    }
+
     #[inline]
     #[must_use]
     pub const fn erase_unit(&self) -> Transform3<T, UnknownUnit, UnknownUnit>
+    where
+        T: Copy,
+    {
+        self.cast_unit()
+    }
+
+    /// Returns the same transform between other spaces
+    #[inline]
+    #[must_use]
+    pub const fn cast_unit<Src2, Dst2>(&self) -> Transform3<T, Src2, Dst2>
     where
         T: Copy,
     {
@@ -383,7 +403,7 @@ impl<T: Copy + Zero + One + NumOps, Src, Dst> Transform3<T, Src, Dst> {
             [              s.x,               u.x,              -f.x, o],
             [              s.y,               u.y,              -f.y, o],
             [              s.z,               u.z,              -f.z, o],
-            [-eye.dot(s), -eye.dot(u), -eye.dot(f), l],
+            [-eye.dot(s), -eye.dot(u), eye.dot(f), l],
         ])
     }
 
@@ -809,6 +829,26 @@ where
     }
 }
 
+impl<T, Src, Dst, D> Transform<Ray<T, Src, D>> for Transform3<T, Src, Dst>
+where
+    T: Copy + PartialOrd + Zero + One + NumOps,
+{
+    type Output = Option<Ray<T, Dst, D>>;
+
+    /// The direction is not renormalized, so that ray parameters are the same in both spaces
+    #[inline]
+    fn transform(&self, ray: Ray<T, Src, D>) -> Self::Output {
+        let origin = self.transform_point3(ray.origin).ok()?;
+        Some(Ray {
+            origin,
+            dir: Transform::transform(self, ray.dir),
+            time: ray.time,
+            data: ray.data,
+        })
+    }
+}
+
+#[allow(clippy::needless_lifetimes)]
 impl<'a, T, A, B, C> Mul<Transform2<T, B, C>> for &'a Transform2<T, A, B>
 where
     T: Copy + NumOps,
@@ -833,6 +873,7 @@ where
     }
 }
 
+#[allow(clippy::needless_lifetimes)]
 impl<'a, 'b, T, A, B, C> Mul<&'b Transform2<T, B, C>> for &'a Transform2<T, A, B>
 where
     T: Copy + NumOps,
@@ -882,6 +923,7 @@ where
     }
 }
 
+#[allow(clippy::needless_lifetimes)]
 impl<'a, T, A, B, C> Mul<Transform3<T, B, C>> for &'a Transform3<T, A, B>
 where
     T: Copy + NumOps,
@@ -906,6 +948,7 @@ where
     }
 }
 
+#[allow(clippy::needless_lifetimes)]
 impl<'a, 'b, T, A, B, C> Mul<&'b Transform3<T, B, C>> for &'a Transform3<T, A, B>
 where
     T: Copy + NumOps,
@@ -1138,6 +1181,18 @@ mod tests {
         assert_eq!(t1 * t1, Mf32::translation(Vf32::new(2., 4., 6.)));
     }
 
+    #[test]
+    pub fn test_look_at() {
+        let eye = Point3::new(1., 2., 3.);
+        let target = Point3::new(4., 6., 3.);
+        let view = Mf32::look_at_rh(eye, target, Vf32::new(0., 0., 1.));
+        let eye = view.transform_point3(eye).unwrap();
+        assert!(eye.to_vector().length() < 1e-6);
+        // Right-handed views look down -z
+        let target = view.transform_point3(target).unwrap();
+        assert!((target - Point3::new(0., 0., -5.)).length() < 1e-6);
+    }
+
     #[test]
     pub fn test_rotation() {
 
@@ -17,6 +17,9 @@ pub struct Vector2<T, U> {
     _unit: PhantomData<U>,
 }
 
+/// With the `simd` feature, `Vector3<f32, U>` is padded to fill a 4-lane SIMD register, through
+/// which its arithmetic is computed
+#[cfg_attr(feature = "simd", repr(C, align(16)))]
 pub struct Vector3<T, U> {
     pub x: T,
     pub y: T,
@@ -24,6 +27,9 @@ pub struct Vector3<T, U> {
     _unit: PhantomData<U>,
 }
 
+#[cfg(feature = "simd")]
+const _: () = assert!(std::mem::size_of::<Vector3<f32, UnknownUnit>>() == 16);
+
 impl<T: Zero, U> Zero for Vector2<T, U> {
     fn zero() -> Self {
         Self::new(T::zero(), T::zero())
@@ -567,6 +573,10 @@ impl<T, U> Vector3<T, U> {
     where
         T: Copy + Add<Output = T> + Mul<Output = T>,
     {
+        #[cfg(feature = "simd")]
+        if let Some(length_squared) = simd::dot(&self, &self) {
+            return length_squared;
+        }
         self.x * self.x + self.y * self.y + self.z * self.z
     }
 
@@ -626,6 +636,10 @@ impl<T, U> Vector3<T, U> {
     where
         T: Add<Output = T> + Mul<Output = T>,
     {
+        #[cfg(feature = "simd")]
+        if let Some(dot) = simd::dot(&self, &other) {
+            return dot;
+        }
         self.x * other.x + self.y * other.y + self.z * other.z
     }
 
@@ -635,6 +649,10 @@ impl<T, U> Vector3<T, U> {
     where
         T: Copy + Sub<Output = T> + Mul<Output = T>,
     {
+        #[cfg(feature = "simd")]
+        if let Some([x, y, z]) = simd::cross(&self, &other) {
+            return Self::new(x, y, z);
+        }
         Self::new(
             self.y * other.z - self.z * other.y,
             self.z * other.x - self.x * other.z,
@@ -747,6 +765,10 @@ impl<T: num_traits::real::Real, U> Vector3<T, U> {
     #[inline]
     #[must_use]
     pub fn normalize(self) -> Self {
+        #[cfg(feature = "simd")]
+        if let Some([x, y, z]) = simd::normalize(&self) {
+            return Self::new(x, y, z);
+        }
         self / self.length()
     }
 
@@ -795,6 +817,26 @@ impl<T: num_traits::real::Real, U> Vector3<T, U> {
         debug_assert!(min <= max);
         self.with_min_length(min).with_max_length(max)
     }
+
+    /// Returns two unit vectors that together with `self` form an orthonormal basis
+    ///
+    /// `self` must be normalized.
+    #[inline]
+    #[must_use]
+    pub fn coordinate_system(self) -> (Self, Self) {
+        // See https://graphics.pixar.com/library/OrthonormalB/paper.pdf
+        let sign = self.z.signum();
+        let a = -T::one() / (sign + self.z);
+        let b = self.x * self.y * a;
+        (
+            Self::new(
+                T::one() + sign * self.x * self.x * a,
+                sign * b,
+                -sign * self.x,
+            ),
+            Self::new(b, sign + self.y * self.y * a, -self.y),
+        )
+    }
 }
 
 impl<T: PartialEq, U> Vector2<T, U> {
@@ -1052,6 +1094,10 @@ impl<T: Neg, U> Neg for Vector3<T, U> {
 
     #[inline]
     fn neg(self) -> Self::Output {
+        #[cfg(feature = "simd")]
+        if let Some([x, y, z]) = simd::neg(&self) {
+            return Vector3::new(x, y, z);
+        }
         Vector3::new(-self.x, -self.y, -self.z)
     }
 }
@@ -1070,6 +1116,10 @@ impl<T: Copy + Mul, U> Mul<T> for Vector3<T, U> {
 
     #[inline]
     fn mul(self, rhs: T) -> Self::Output {
+        #[cfg(feature = "simd")]
+        if let Some([x, y, z]) = simd::mul(&self, &rhs) {
+            return Vector3::new(x, y, z);
+        }
         Vector3::new(self.x * rhs, self.y * rhs, self.z * rhs)
     }
 }
@@ -1105,6 +1155,10 @@ impl<T: Copy + Div, U> Div<T> for Vector3<T, U> {
 
     #[inline]
     fn div(self, rhs: T) -> Self::Output {
+        #[cfg(feature = "simd")]
+        if let Some([x, y, z]) = simd::div(&self, &rhs) {
+            return Vector3::new(x, y, z);
+        }
         Vector3::new(self.x / rhs, self.y / rhs, self.z / rhs)
     }
 }
@@ -1140,6 +1194,10 @@ impl<T: Add, U> Add<Vector3<T, U>> for Vector3<T, U> {
 
     #[inline]
     fn add(self, rhs: Vector3<T, U>) -> Self::Output {
+        #[cfg(feature = "simd")]
+        if let Some([x, y, z]) = simd::add(&self, &rhs) {
+            return Vector3::new(x, y, z);
+        }
         Vector3::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z)
     }
 }
@@ -1207,6 +1265,10 @@ impl<T: Sub, U> Sub<Vector3<T, U>> for Vector3<T, U> {
 
     #[inline]
     fn sub(self, rhs: Vector3<T, U>) -> Self::Output {
+        #[cfg(feature = "simd")]
+        if let Some([x, y, z]) = simd::sub(&self, &rhs) {
+            return Vector3::new(x, y, z);
+        }
         Vector3::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z)
     }
 }
@@ -0,0 +1,572 @@
+//! Vectors and points of `f32` lanes in structure-of-arrays layout
+//!
+//! Every operation works lane by lane on fixed-size arrays, which the compiler turns into SIMD.
+
+use crate::core::geometry::{Mask3, Point3, Vector3};
+use std::{
+    fmt,
+    marker::PhantomData,
+    ops::{Add, AddAssign, Div, Mul, Neg, Not, Sub, SubAssign},
+};
+
+#[inline]
+fn map<const N: usize>(a: [f32; N], f: impl Fn(f32) -> f32) -> [f32; N] {
+    a.map(f)
+}
+
+#[inline]
+fn zip<const N: usize>(a: [f32; N], b: [f32; N], f: impl Fn(f32, f32) -> f32) -> [f32; N] {
+    std::array::from_fn(|i| f(a[i], b[i]))
+}
+
+#[inline]
+fn cmp<const N: usize>(a: [f32; N], b: [f32; N], f: impl Fn(f32, f32) -> bool) -> [bool; N] {
+    std::array::from_fn(|i| f(a[i], b[i]))
+}
+
+#[inline]
+fn blend<const N: usize>(mask: [bool; N], a: [f32; N], b: [f32; N]) -> [f32; N] {
+    std::array::from_fn(|i| if mask[i] { a[i] } else { b[i] })
+}
+
+/// A [`Mask3`] per lane
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Mask3xN<const N: usize> {
+    pub x: [bool; N],
+    pub y: [bool; N],
+    pub z: [bool; N],
+}
+
+pub type Mask3x4 = Mask3xN<4>;
+pub type Mask3x8 = Mask3xN<8>;
+
+impl<const N: usize> Mask3xN<N> {
+    #[inline]
+    #[must_use]
+    pub fn splat(mask: Mask3) -> Self {
+        Self {
+            x: [mask.x; N],
+            y: [mask.y; N],
+            z: [mask.z; N],
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn from_lanes(lanes: [Mask3; N]) -> Self {
+        Self {
+            x: lanes.map(|m| m.x),
+            y: lanes.map(|m| m.y),
+            z: lanes.map(|m| m.z),
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn lane(&self, i: usize) -> Mask3 {
+        Mask3 {
+            x: self.x[i],
+            y: self.y[i],
+            z: self.z[i],
+        }
+    }
+
+    /// Returns which lanes have all components set
+    #[inline]
+    #[must_use]
+    pub fn all(&self) -> [bool; N] {
+        std::array::from_fn(|i| self.lane(i).all())
+    }
+
+    /// Returns which lanes have any component set
+    #[inline]
+    #[must_use]
+    pub fn any(&self) -> [bool; N] {
+        std::array::from_fn(|i| self.lane(i).any())
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn and(self, rhs: Self) -> Self {
+        let and = |a: [bool; N], b: [bool; N]| std::array::from_fn(|i| a[i] && b[i]);
+        Self {
+            x: and(self.x, rhs.x),
+            y: and(self.y, rhs.y),
+            z: and(self.z, rhs.z),
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn or(self, rhs: Self) -> Self {
+        let or = |a: [bool; N], b: [bool; N]| std::array::from_fn(|i| a[i] || b[i]);
+        Self {
+            x: or(self.x, rhs.x),
+            y: or(self.y, rhs.y),
+            z: or(self.z, rhs.z),
+        }
+    }
+
+    /// Picks each component from `a` where the mask is set and from `b` elsewhere
+    #[inline]
+    #[must_use]
+    pub fn select<W: Wide3<N>>(self, a: W, b: W) -> W {
+        let ([ax, ay, az], [bx, by, bz]) = (a.to_arrays(), b.to_arrays());
+        W::from_arrays([
+            blend(self.x, ax, bx),
+            blend(self.y, ay, by),
+            blend(self.z, az, bz),
+        ])
+    }
+}
+
+impl<const N: usize> Not for Mask3xN<N> {
+    type Output = Self;
+
+    #[inline]
+    fn not(self) -> Self {
+        Self {
+            x: self.x.map(|b| !b),
+            y: self.y.map(|b| !b),
+            z: self.z.map(|b| !b),
+        }
+    }
+}
+
+/// Wide types with three components, which masks can select between
+pub trait Wide3<const N: usize> {
+    fn to_arrays(self) -> [[f32; N]; 3];
+    fn from_arrays(arrays: [[f32; N]; 3]) -> Self;
+
+    /// Picks whole lanes from `self` where `mask` is set and from `other` elsewhere
+    #[inline]
+    #[must_use]
+    fn select_lanes(self, mask: [bool; N], other: Self) -> Self
+    where
+        Self: Sized,
+    {
+        let (a, b) = (self.to_arrays(), other.to_arrays());
+        Self::from_arrays([0, 1, 2].map(|c| blend(mask, a[c], b[c])))
+    }
+}
+
+macro_rules! wide_type {
+    ($(#[$attr:meta])* $Wide:ident, $x4:ident, $x8:ident, $Scalar:ident) => {
+        $(#[$attr])*
+        pub struct $Wide<U, const N: usize> {
+            pub x: [f32; N],
+            pub y: [f32; N],
+            pub z: [f32; N],
+            _unit: PhantomData<U>,
+        }
+
+        pub type $x4<U> = $Wide<U, 4>;
+        pub type $x8<U> = $Wide<U, 8>;
+
+        impl<U, const N: usize> fmt::Debug for $Wide<U, N> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct(stringify!($Wide))
+                    .field("x", &self.x)
+                    .field("y", &self.y)
+                    .field("z", &self.z)
+                    .finish()
+            }
+        }
+
+        impl<U, const N: usize> Copy for $Wide<U, N> {}
+
+        impl<U, const N: usize> Clone for $Wide<U, N> {
+            #[inline]
+            fn clone(&self) -> Self {
+                *self
+            }
+        }
+
+        impl<U, const N: usize> PartialEq for $Wide<U, N> {
+            #[inline]
+            fn eq(&self, other: &Self) -> bool {
+                self.x == other.x && self.y == other.y && self.z == other.z
+            }
+        }
+
+        impl<U, const N: usize> Wide3<N> for $Wide<U, N> {
+            #[inline]
+            fn to_arrays(self) -> [[f32; N]; 3] {
+                [self.x, self.y, self.z]
+            }
+
+            #[inline]
+            fn from_arrays([x, y, z]: [[f32; N]; 3]) -> Self {
+                Self::new(x, y, z)
+            }
+        }
+
+        impl<U, const N: usize> $Wide<U, N> {
+            #[inline]
+            #[must_use]
+            pub const fn new(x: [f32; N], y: [f32; N], z: [f32; N]) -> Self {
+                Self {
+                    x,
+                    y,
+                    z,
+                    _unit: PhantomData,
+                }
+            }
+
+            #[inline]
+            #[must_use]
+            pub fn splat(v: $Scalar<f32, U>) -> Self {
+                Self::new([v.x; N], [v.y; N], [v.z; N])
+            }
+
+            #[inline]
+            #[must_use]
+            pub fn from_lanes(lanes: [$Scalar<f32, U>; N]) -> Self {
+                Self::new(lanes.map(|v| v.x), lanes.map(|v| v.y), lanes.map(|v| v.z))
+            }
+
+            #[inline]
+            #[must_use]
+            pub fn lane(&self, i: usize) -> $Scalar<f32, U> {
+                $Scalar::new(self.x[i], self.y[i], self.z[i])
+            }
+
+            #[inline]
+            #[must_use]
+            pub fn to_lanes(self) -> [$Scalar<f32, U>; N] {
+                std::array::from_fn(|i| self.lane(i))
+            }
+
+            #[inline]
+            #[must_use]
+            pub fn min(self, other: Self) -> Self {
+                Self::new(
+                    zip(self.x, other.x, f32::min),
+                    zip(self.y, other.y, f32::min),
+                    zip(self.z, other.z, f32::min),
+                )
+            }
+
+            #[inline]
+            #[must_use]
+            pub fn max(self, other: Self) -> Self {
+                Self::new(
+                    zip(self.x, other.x, f32::max),
+                    zip(self.y, other.y, f32::max),
+                    zip(self.z, other.z, f32::max),
+                )
+            }
+
+            #[inline]
+            #[must_use]
+            pub fn cmp_lt(self, other: Self) -> Mask3xN<N> {
+                Mask3xN {
+                    x: cmp(self.x, other.x, |a, b| a < b),
+                    y: cmp(self.y, other.y, |a, b| a < b),
+                    z: cmp(self.z, other.z, |a, b| a < b),
+                }
+            }
+
+            #[inline]
+            #[must_use]
+            pub fn cmp_le(self, other: Self) -> Mask3xN<N> {
+                Mask3xN {
+                    x: cmp(self.x, other.x, |a, b| a <= b),
+                    y: cmp(self.y, other.y, |a, b| a <= b),
+                    z: cmp(self.z, other.z, |a, b| a <= b),
+                }
+            }
+
+            #[inline]
+            #[must_use]
+            pub fn cmp_gt(self, other: Self) -> Mask3xN<N> {
+                other.cmp_lt(self)
+            }
+
+            #[inline]
+            #[must_use]
+            pub fn cmp_ge(self, other: Self) -> Mask3xN<N> {
+                other.cmp_le(self)
+            }
+
+            #[inline]
+            #[must_use]
+            pub fn cmp_eq(self, other: Self) -> Mask3xN<N> {
+                Mask3xN {
+                    x: cmp(self.x, other.x, |a, b| a == b),
+                    y: cmp(self.y, other.y, |a, b| a == b),
+                    z: cmp(self.z, other.z, |a, b| a == b),
+                }
+            }
+        }
+    };
+}
+
+wide_type!(
+    /// `N` [`Vector3`]s of `f32`
+    Vector3xN,
+    Vector3x4,
+    Vector3x8,
+    Vector3
+);
+
+wide_type!(
+    /// `N` [`Point3`]s of `f32`
+    Point3xN,
+    Point3x4,
+    Point3x8,
+    Point3
+);
+
+impl<U, const N: usize> Vector3xN<U, N> {
+    #[inline]
+    #[must_use]
+    pub fn zero() -> Self {
+        Self::new([0.; N], [0.; N], [0.; N])
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn dot(self, other: Self) -> [f32; N] {
+        std::array::from_fn(|i| {
+            self.x[i] * other.x[i] + self.y[i] * other.y[i] + self.z[i] * other.z[i]
+        })
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn cross(self, other: Self) -> Self {
+        let diff = |a: [f32; N], b: [f32; N], c: [f32; N], d: [f32; N]| {
+            std::array::from_fn(|i| a[i] * b[i] - c[i] * d[i])
+        };
+        Self::new(
+            diff(self.y, other.z, self.z, other.y),
+            diff(self.z, other.x, self.x, other.z),
+            diff(self.x, other.y, self.y, other.x),
+        )
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn length_squared(self) -> [f32; N] {
+        self.dot(self)
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn length(self) -> [f32; N] {
+        map(self.length_squared(), f32::sqrt)
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn normalize(self) -> Self {
+        let length = self.length();
+        Self::new(
+            zip(self.x, length, Div::div),
+            zip(self.y, length, Div::div),
+            zip(self.z, length, Div::div),
+        )
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn abs(self) -> Self {
+        Self::new(
+            map(self.x, f32::abs),
+            map(self.y, f32::abs),
+            map(self.z, f32::abs),
+        )
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn recip(self) -> Self {
+        Self::new(
+            map(self.x, f32::recip),
+            map(self.y, f32::recip),
+            map(self.z, f32::recip),
+        )
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn component_mul(self, other: Self) -> Self {
+        Self::new(
+            zip(self.x, other.x, Mul::mul),
+            zip(self.y, other.y, Mul::mul),
+            zip(self.z, other.z, Mul::mul),
+        )
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn to_point(self) -> Point3xN<U, N> {
+        Point3xN::new(self.x, self.y, self.z)
+    }
+}
+
+impl<U, const N: usize> Point3xN<U, N> {
+    #[inline]
+    #[must_use]
+    pub fn origin() -> Self {
+        Self::new([0.; N], [0.; N], [0.; N])
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn to_vector(self) -> Vector3xN<U, N> {
+        Vector3xN::new(self.x, self.y, self.z)
+    }
+}
+
+impl<U, const N: usize> Add for Vector3xN<U, N> {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self {
+        Self::new(
+            zip(self.x, rhs.x, Add::add),
+            zip(self.y, rhs.y, Add::add),
+            zip(self.z, rhs.z, Add::add),
+        )
+    }
+}
+
+impl<U, const N: usize> AddAssign for Vector3xN<U, N> {
+    #[inline]
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl<U, const N: usize> Sub for Vector3xN<U, N> {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(
+            zip(self.x, rhs.x, Sub::sub),
+            zip(self.y, rhs.y, Sub::sub),
+            zip(self.z, rhs.z, Sub::sub),
+        )
+    }
+}
+
+impl<U, const N: usize> SubAssign for Vector3xN<U, N> {
+    #[inline]
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl<U, const N: usize> Neg for Vector3xN<U, N> {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self {
+        Self::new(
+            map(self.x, Neg::neg),
+            map(self.y, Neg::neg),
+            map(self.z, Neg::neg),
+        )
+    }
+}
+
+impl<U, const N: usize> Mul<f32> for Vector3xN<U, N> {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: f32) -> Self {
+        self * [rhs; N]
+    }
+}
+
+/// Scales each lane by its own factor
+impl<U, const N: usize> Mul<[f32; N]> for Vector3xN<U, N> {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: [f32; N]) -> Self {
+        Self::new(
+            zip(self.x, rhs, Mul::mul),
+            zip(self.y, rhs, Mul::mul),
+            zip(self.z, rhs, Mul::mul),
+        )
+    }
+}
+
+impl<U, const N: usize> Add<Vector3xN<U, N>> for Point3xN<U, N> {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Vector3xN<U, N>) -> Self {
+        (self.to_vector() + rhs).to_point()
+    }
+}
+
+impl<U, const N: usize> AddAssign<Vector3xN<U, N>> for Point3xN<U, N> {
+    #[inline]
+    fn add_assign(&mut self, rhs: Vector3xN<U, N>) {
+        *self = *self + rhs;
+    }
+}
+
+impl<U, const N: usize> Sub<Vector3xN<U, N>> for Point3xN<U, N> {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Vector3xN<U, N>) -> Self {
+        (self.to_vector() - rhs).to_point()
+    }
+}
+
+impl<U, const N: usize> SubAssign<Vector3xN<U, N>> for Point3xN<U, N> {
+    #[inline]
+    fn sub_assign(&mut self, rhs: Vector3xN<U, N>) {
+        *self = *self - rhs;
+    }
+}
+
+impl<U, const N: usize> Sub for Point3xN<U, N> {
+    type Output = Vector3xN<U, N>;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Vector3xN<U, N> {
+        self.to_vector() - rhs.to_vector()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::geometry::UnknownUnit;
+
+    type V = Vector3<f32, UnknownUnit>;
+    type P = Point3<f32, UnknownUnit>;
+
+    #[test]
+    fn test_matches_scalar() {
+        let a: [V; 4] = std::array::from_fn(|i| V::new(i as f32, 1. - i as f32, 2.));
+        let b: [V; 4] = std::array::from_fn(|i| V::new(3., i as f32 * 0.5, -(i as f32)));
+        let (wa, wb) = (Vector3x4::from_lanes(a), Vector3x4::from_lanes(b));
+
+        let cross = wa.cross(wb);
+        let dot = wa.dot(wb);
+        let normalized = wa.normalize();
+        let p = Point3x4::splat(P::new(1., 2., 3.)) + wa;
+        let mask = wa.cmp_lt(wb);
+        let selected = mask.select(wa, wb);
+        for i in 0..4 {
+            assert_eq!(cross.lane(i), a[i].cross(b[i]));
+            assert_eq!(dot[i], a[i].dot(b[i]));
+            assert_eq!(normalized.lane(i), a[i].normalize());
+            assert_eq!(p.lane(i), P::new(1., 2., 3.) + a[i]);
+            assert_eq!(mask.lane(i), a[i].cmp_lt(b[i]));
+            assert_eq!(selected.lane(i), a[i].cmp_lt(b[i]).select(a[i], b[i]));
+        }
+
+        let lanes = wa.select_lanes([true, false, true, false], wb).to_lanes();
+        assert_eq!(lanes, [a[0], b[1], a[2], b[3]]);
+    }
+}
@@ -20,6 +20,38 @@ pub fn max<T: PartialOrd>(x: T, y: T) -> T {
     }
 }
 
+/// Returns the real roots of `a x^2 + b x + c`, smallest first
+#[must_use]
+pub fn solve_quadratic<T: num_traits::Float>(a: T, b: T, c: T) -> Option<(T, T)> {
+    if a == T::zero() {
+        if b == T::zero() {
+            return None;
+        }
+        let x = -c / b;
+        return Some((x, x));
+    }
+
+    let two = T::one() + T::one();
+    let discrim = b * b - two * two * a * c;
+    if discrim < T::zero() {
+        return None;
+    }
+
+    // Avoids the cancellation in `-b + sqrt(discrim)` when `b` dominates
+    let root = discrim.sqrt();
+    let q = if b < T::zero() {
+        (root - b) / two
+    } else {
+        -(b + root) / two
+    };
+    if q == T::zero() {
+        return Some((T::zero(), T::zero()));
+    }
+
+    let (x0, x1) = (q / a, c / q);
+    Some(if x0 <= x1 { (x0, x1) } else { (x1, x0) })
+}
+
 pub trait Trig {
     fn sin(self) -> Self;
 
@@ -53,6 +85,7 @@ macro_rules! impl_trig {
             }
 
             #[inline]
+            #[allow(clippy::excessive_precision)]
             fn fast_atan2(y: $ty, x: $ty) -> $ty {
                 // See https://math.stackexchange.com/questions/1098487/atan2-faster-approximation#1105038
                 use core::$ty::consts;
@@ -0,0 +1,236 @@
+use crate::{
+    color::Rgb,
+    film::{Film, Pixel, PixelStatistics},
+};
+use num_traits::Float;
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+const MAGIC: [u8; 4] = *b"RCKP";
+const VERSION: u32 = 1;
+
+/// Why a render checkpoint could not be loaded
+#[derive(Debug)]
+pub enum CheckpointError {
+    Io(io::Error),
+    /// The data is not a render checkpoint, or is corrupt
+    InvalidFormat,
+    /// The data was written by an incompatible version
+    UnsupportedVersion(u32),
+    /// The checkpoint was written from a film of a different size, overscan or crop window
+    FilmMismatch,
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "failed to read checkpoint: {e}"),
+            Self::InvalidFormat => f.write_str("invalid checkpoint data"),
+            Self::UnsupportedVersion(v) => write!(f, "unsupported checkpoint format version {v}"),
+            Self::FilmMismatch => f.write_str("checkpoint was written from a different film"),
+        }
+    }
+}
+
+impl std::error::Error for CheckpointError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CheckpointError {
+    #[inline]
+    fn from(e: io::Error) -> Self {
+        if e.kind() == io::ErrorKind::UnexpectedEof {
+            Self::InvalidFormat
+        } else {
+            Self::Io(e)
+        }
+    }
+}
+
+impl<T: Float> Film<T> {
+    /// The layout of the film, which a checkpoint must match to be loaded into it
+    fn layout(&self) -> [usize; 7] {
+        let crop = self.crop;
+        [
+            self.width,
+            self.height,
+            self.overscan,
+            crop.min.x,
+            crop.min.y,
+            crop.max.x,
+            crop.max.y,
+        ]
+    }
+
+    /// Writes the samples and splats accumulated so far and the number of samples per pixel
+    /// they make up to a compact binary format, from which a render can resume
+    pub fn write_checkpoint(&self, writer: impl Write, samples_taken: usize) -> io::Result<()> {
+        let mut w = BufWriter::new(writer);
+        w.write_all(&MAGIC)?;
+        w.write_all(&VERSION.to_le_bytes())?;
+        for x in self.layout() {
+            w.write_all(&(x as u64).to_le_bytes())?;
+        }
+        w.write_all(&(samples_taken as u64).to_le_bytes())?;
+        let pixels = self.pixels.lock().unwrap();
+        let rows = pixels.chunks_exact(self.width.max(1)).zip(&self.splats);
+        for (row, splats) in rows {
+            let splats = splats.lock().unwrap();
+            for (pixel, splat) in row.iter().zip(splats.iter()) {
+                let (c, stats) = (pixel.radiance, pixel.statistics);
+                for x in [
+                    c.r,
+                    c.g,
+                    c.b,
+                    pixel.weight,
+                    stats.mean,
+                    stats.variance,
+                    pixel.m2,
+                    splat.r,
+                    splat.g,
+                    splat.b,
+                ] {
+                    w.write_all(&x.to_f64().unwrap_or(f64::NAN).to_le_bytes())?;
+                }
+                w.write_all(&(stats.samples as u64).to_le_bytes())?;
+            }
+        }
+        w.flush()
+    }
+
+    /// Writes a checkpoint to `path`, replacing the previous one only once it is complete so
+    /// that an interruption while writing leaves it intact
+    pub fn save_checkpoint(&self, path: impl AsRef<Path>, samples_taken: usize) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".partial");
+        self.write_checkpoint(File::create(&partial)?, samples_taken)?;
+        fs::rename(partial, path)
+    }
+
+    /// Replaces the samples of the film with those of a checkpoint written by
+    /// [`write_checkpoint`](Self::write_checkpoint), returning the number of samples per pixel
+    /// they make up
+    ///
+    /// The film is left unchanged if the checkpoint cannot be read.
+    pub fn read_checkpoint(&self, reader: impl Read) -> Result<usize, CheckpointError> {
+        let mut r = Reader { reader };
+        if r.bytes()? != MAGIC {
+            return Err(CheckpointError::InvalidFormat);
+        }
+        let version = u32::from_le_bytes(r.bytes()?);
+        if version != VERSION {
+            return Err(CheckpointError::UnsupportedVersion(version));
+        }
+        for x in self.layout() {
+            if r.usize()? != x {
+                return Err(CheckpointError::FilmMismatch);
+            }
+        }
+        let samples_taken = r.usize()?;
+
+        let mut pixels = Vec::with_capacity(self.width * self.height);
+        let mut splats = Vec::with_capacity(self.width * self.height);
+        for _ in 0..self.width * self.height {
+            let radiance = Rgb::new(r.scalar()?, r.scalar()?, r.scalar()?);
+            let weight = r.scalar()?;
+            let (mean, variance, m2) = (r.scalar()?, r.scalar()?, r.scalar()?);
+            splats.push(Rgb::new(r.scalar()?, r.scalar()?, r.scalar()?));
+            pixels.push(Pixel {
+                radiance,
+                weight,
+                statistics: PixelStatistics {
+                    samples: r.usize()?,
+                    mean,
+                    variance,
+                },
+                m2,
+            });
+        }
+        let mut film_pixels = self.pixels.lock().unwrap();
+        for (row, splats) in self.splats.iter().zip(splats.chunks_exact(self.width.max(1))) {
+            row.lock().unwrap().copy_from_slice(splats);
+        }
+        *film_pixels = pixels;
+        Ok(samples_taken)
+    }
+
+    /// Loads the checkpoint at `path` like [`read_checkpoint`](Self::read_checkpoint)
+    pub fn load_checkpoint(&self, path: impl AsRef<Path>) -> Result<usize, CheckpointError> {
+        self.read_checkpoint(BufReader::new(File::open(path)?))
+    }
+}
+
+struct Reader<R> {
+    reader: R,
+}
+
+impl<R: Read> Reader<R> {
+    fn bytes<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        let mut bytes = [0; N];
+        self.reader.read_exact(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn usize(&mut self) -> Result<usize, CheckpointError> {
+        let x = u64::from_le_bytes(self.bytes()?);
+        usize::try_from(x).map_err(|_| CheckpointError::InvalidFormat)
+    }
+
+    fn scalar<T: Float>(&mut self) -> Result<T, CheckpointError> {
+        let x = f64::from_le_bytes(self.bytes()?);
+        T::from(x).ok_or(CheckpointError::InvalidFormat)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::geometry::{Box2, Point2};
+
+    #[test]
+    fn test_round_trip() {
+        let mut film = Film::<f32>::new(3, 2);
+        for (i, x) in [0.5, 1.5, 2.5, 0.5].into_iter().enumerate() {
+            let c = i as f32;
+            film.add_sample(Point2::new(x, 0.5), Rgb::new(c, 2. * c, 0.25), 0.5);
+        }
+        film.add_splat(Point2::new(2.5, 1.5), Rgb::new(1., 2., 3.));
+        let mut bytes = Vec::new();
+        film.write_checkpoint(&mut bytes, 7).unwrap();
+
+        let loaded = Film::new(3, 2);
+        assert_eq!(loaded.read_checkpoint(bytes.as_slice()).unwrap(), 7);
+        assert_eq!(loaded.statistics(), film.statistics());
+        assert_eq!(
+            loaded.resolve_with_splat_scale(0.5),
+            film.resolve_with_splat_scale(0.5)
+        );
+
+        let window = Box2::new(Point2::new(0., 0.), Point2::new(0.5, 1.));
+        let cropped = Film::new(3, 2).with_crop_window(window);
+        assert!(matches!(
+            cropped.read_checkpoint(bytes.as_slice()),
+            Err(CheckpointError::FilmMismatch)
+        ));
+        assert!(matches!(
+            loaded.read_checkpoint(&bytes[..bytes.len() - 1]),
+            Err(CheckpointError::InvalidFormat)
+        ));
+        assert!(matches!(
+            loaded.read_checkpoint(&b"RBVH"[..]),
+            Err(CheckpointError::InvalidFormat)
+        ));
+        // Failed reads leave the film as it was
+        assert_eq!(loaded.statistics(), film.statistics());
+    }
+}
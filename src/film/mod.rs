@@ -0,0 +1,593 @@
+mod checkpoint;
+
+pub use checkpoint::CheckpointError;
+
+use crate::{
+    color::Rgb,
+    core::geometry::{Box2, Point2, UnknownUnit},
+    image::Image,
+};
+use num_traits::Float;
+use std::sync::Mutex;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Pixel<T> {
+    radiance: Rgb<T>,
+    weight: T,
+    /// The luminance of the samples, unweighted
+    statistics: PixelStatistics<T>,
+    /// The sum of the squared deviations of the luminance from its mean
+    m2: T,
+}
+
+impl<T: Float> Pixel<T> {
+    #[inline]
+    fn zero() -> Self {
+        Self {
+            radiance: Rgb::black(),
+            weight: T::zero(),
+            statistics: PixelStatistics {
+                samples: 0,
+                mean: T::zero(),
+                variance: T::zero(),
+            },
+            m2: T::zero(),
+        }
+    }
+
+    #[inline]
+    fn add(&mut self, radiance: Rgb<T>, weight: T) {
+        self.radiance += radiance * weight;
+        self.weight = self.weight + weight;
+
+        // Welford's update, which avoids the cancellation of summing squares
+        let y = radiance.luminance();
+        let stats = &mut self.statistics;
+        stats.samples += 1;
+        let delta = y - stats.mean;
+        stats.mean = stats.mean + delta / T::from(stats.samples).unwrap();
+        self.m2 = self.m2 + delta * (y - stats.mean);
+        self.update_variance();
+    }
+
+    /// Adds the samples of another pixel
+    fn merge(&mut self, other: &Self) {
+        self.radiance += other.radiance;
+        self.weight = self.weight + other.weight;
+
+        let (a, b) = (self.statistics, other.statistics);
+        if b.samples == 0 {
+            return;
+        }
+        let samples = a.samples + b.samples;
+        let [na, nb, n] = [a.samples, b.samples, samples].map(|n| T::from(n).unwrap());
+        let delta = b.mean - a.mean;
+        self.statistics.samples = samples;
+        self.statistics.mean = a.mean + delta * nb / n;
+        self.m2 = self.m2 + other.m2 + delta * delta * na * nb / n;
+        self.update_variance();
+    }
+
+    #[inline]
+    fn update_variance(&mut self) {
+        let n = self.statistics.samples;
+        if n > 1 {
+            self.statistics.variance = self.m2 / T::from(n - 1).unwrap();
+        }
+    }
+}
+
+/// The number of samples of a pixel, and the mean and variance of their luminance
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PixelStatistics<T> {
+    pub samples: usize,
+    pub mean: T,
+    /// The unbiased sample variance, or zero with fewer than two samples
+    pub variance: T,
+}
+
+impl<T: Float> PixelStatistics<T> {
+    /// Returns the standard error of the mean luminance relative to the mean
+    ///
+    /// This is infinite with fewer than two samples, and for noisy pixels averaging to zero.
+    #[must_use]
+    pub fn relative_error(&self) -> T {
+        if self.samples < 2 {
+            return T::infinity();
+        }
+        let error = (self.variance / T::from(self.samples).unwrap()).sqrt();
+        if error == T::zero() {
+            T::zero()
+        } else {
+            error / self.mean.abs()
+        }
+    }
+}
+
+/// The render target, accumulating weighted radiance samples per pixel
+///
+/// Threads render into their own [`FilmTile`]s, which are merged back when done, so the film
+/// is only locked once per tile.
+///
+/// Light-tracing strategies, which reach pixels from the lights rather than from the camera,
+/// [splat](Self::add_splat) their contributions onto the film instead. Splats are unweighted
+/// sums, scaled when resolving the image, and any thread may add them at any time.
+///
+/// The film covers the frame seen by the camera, and may have an overscan of pixels around it
+/// which see past its edges, such as for reframing or filtering the image later. Renders may
+/// also be limited to a crop window of the frame, to iterate quickly on a part of it.
+#[derive(Debug)]
+pub struct Film<T> {
+    width: usize,
+    height: usize,
+    pixels: Mutex<Vec<Pixel<T>>>,
+    /// The sums of the splats of each row, behind a lock per row so that threads splatting
+    /// onto different rows don't wait on each other
+    splats: Vec<Mutex<Vec<Rgb<T>>>>,
+    max_sample_value: Option<T>,
+    outlier_factor: Option<T>,
+    overscan: usize,
+    /// The part of the frame rendered, in pixels of the frame
+    crop: Box2<usize, UnknownUnit>,
+}
+
+impl<T: Float> Film<T> {
+    /// Creates a film covering a frame of `width` by `height` pixels
+    #[must_use]
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: Mutex::new(vec![Pixel::zero(); width * height]),
+            splats: splat_rows(width, height),
+            max_sample_value: None,
+            outlier_factor: None,
+            overscan: 0,
+            crop: Box2::new(Point2::new(0, 0), Point2::new(width, height)),
+        }
+    }
+
+    /// Adds `pixels` pixels on every side of the frame, seeing past its edges, discarding the
+    /// samples of the film
+    #[must_use]
+    pub fn with_overscan(mut self, pixels: usize) -> Self {
+        let (width, height) = self.frame_size();
+        self.width = width + 2 * pixels;
+        self.height = height + 2 * pixels;
+        self.pixels = Mutex::new(vec![Pixel::zero(); self.width * self.height]);
+        self.splats = splat_rows(self.width, self.height);
+        self.overscan = pixels;
+        self
+    }
+
+    /// Only renders the part of the frame within `window`, from `(0, 0)` at its top left corner
+    /// to `(1, 1)` at its bottom right, and the overscan around that part
+    ///
+    /// Pixels are rendered if the window covers their top left corner.
+    #[must_use]
+    pub fn with_crop_window(mut self, window: Box2<T, UnknownUnit>) -> Self {
+        let (width, height) = self.frame_size();
+        let pixel = |x: T, size: usize| {
+            let x = x.max(T::zero()).min(T::one());
+            (x * T::from(size).unwrap()).ceil().to_usize().unwrap()
+        };
+        let min = Point2::new(pixel(window.min.x, width), pixel(window.min.y, height));
+        let max = Point2::new(pixel(window.max.x, width), pixel(window.max.y, height));
+        self.crop = Box2::new(min, max.max(min));
+        self
+    }
+
+    /// Scales down samples whose brightest channel exceeds `max`, keeping their hue
+    ///
+    /// This tames fireflies, the rare samples far brighter than the rest of their pixel, at the
+    /// cost of darkening highlights that are legitimately brighter than `max`.
+    #[must_use]
+    pub fn with_max_sample_value(mut self, max: T) -> Self {
+        self.max_sample_value = Some(max);
+        self
+    }
+
+    /// Replaces pixels brighter than `factor` times each of their neighbours with the average
+    /// of the neighbours when resolving, removing isolated fireflies
+    #[must_use]
+    pub fn with_outlier_rejection(mut self, factor: T) -> Self {
+        self.outlier_factor = Some(factor);
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// All the pixels of the film, including the overscan
+    #[inline]
+    #[must_use]
+    pub fn bounds(&self) -> Box2<usize, UnknownUnit> {
+        Box2::new(Point2::new(0, 0), Point2::new(self.width, self.height))
+    }
+
+    /// The pixels of the frame seen by the camera, within the overscan
+    #[inline]
+    #[must_use]
+    pub fn frame(&self) -> Box2<usize, UnknownUnit> {
+        let (width, height) = self.frame_size();
+        let min = Point2::new(self.overscan, self.overscan);
+        Box2::new(min, Point2::new(min.x + width, min.y + height))
+    }
+
+    #[inline]
+    fn frame_size(&self) -> (usize, usize) {
+        let overscan = 2 * self.overscan;
+        (self.width - overscan, self.height - overscan)
+    }
+
+    /// The pixels rendered, those of the crop window and of the overscan around it
+    #[must_use]
+    pub fn pixel_bounds(&self) -> Box2<usize, UnknownUnit> {
+        // The crop window moves into the film by the overscan, and grows by it again
+        let Box2 { min, max } = self.crop;
+        let overscan = 2 * self.overscan;
+        Box2::new(min, Point2::new(max.x + overscan, max.y + overscan))
+    }
+
+    /// Returns the position on the frame of a continuous raster position, within `[0, 1]^2`
+    /// on the frame and beyond it in the overscan
+    #[inline]
+    #[must_use]
+    pub fn frame_position(&self, p: Point2<T, UnknownUnit>) -> Point2<T, UnknownUnit> {
+        let (width, height) = self.frame_size();
+        let overscan = T::from(self.overscan).unwrap();
+        Point2::new(
+            (p.x - overscan) / T::from(width).unwrap(),
+            (p.y - overscan) / T::from(height).unwrap(),
+        )
+    }
+
+    /// Splits the [rendered pixels](Self::pixel_bounds) into tiles of at most `size` by `size`
+    /// pixels, row by row
+    #[must_use]
+    pub fn tiles(&self, size: usize) -> Vec<Box2<usize, UnknownUnit>> {
+        assert!(size > 0, "tiles must not be empty");
+        let Box2 { min, max } = self.pixel_bounds();
+        (min.y..max.y)
+            .step_by(size)
+            .flat_map(|y| {
+                (min.x..max.x).step_by(size).map(move |x| {
+                    Box2::new(
+                        Point2::new(x, y),
+                        Point2::new((x + size).min(max.x), (y + size).min(max.y)),
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// Returns an empty tile covering `bounds`, clipped to the film
+    #[must_use]
+    pub fn tile(&self, bounds: Box2<usize, UnknownUnit>) -> FilmTile<T> {
+        let bounds = bounds
+            .intersection(&self.bounds())
+            .unwrap_or_else(Box2::empty);
+        let area = (bounds.max.x - bounds.min.x) * (bounds.max.y - bounds.min.y);
+        FilmTile {
+            bounds,
+            pixels: vec![Pixel::zero(); area],
+            max_sample_value: self.max_sample_value,
+        }
+    }
+
+    /// Adds the samples of a tile to the film
+    pub fn merge_tile(&self, tile: FilmTile<T>) {
+        let mut pixels = self.pixels.lock().unwrap();
+        let tile_width = tile.width();
+        for (y, row) in tile.pixels.chunks_exact(tile_width.max(1)).enumerate() {
+            let start = (tile.bounds.min.y + y) * self.width + tile.bounds.min.x;
+            for (pixel, sample) in pixels[start..start + tile_width].iter_mut().zip(row) {
+                pixel.merge(sample);
+            }
+        }
+    }
+
+    /// Adds a sample at a continuous raster position directly, without going through a tile
+    pub fn add_sample(&mut self, p: Point2<T, UnknownUnit>, radiance: Rgb<T>, weight: T) {
+        let (width, height) = (self.width, self.height);
+        if let Some((x, y)) = pixel_at(p, width, height) {
+            let radiance = clamp_sample(radiance, self.max_sample_value);
+            self.pixels.get_mut().unwrap()[y * width + x].add(radiance, weight);
+        }
+    }
+
+    /// Adds a contribution at a continuous raster position to the sum of the splats of its
+    /// pixel, ignoring those outside the film
+    pub fn add_splat(&self, p: Point2<T, UnknownUnit>, value: Rgb<T>) {
+        if let Some((x, y)) = pixel_at(p, self.width, self.height) {
+            self.splats[y].lock().unwrap()[x] += value;
+        }
+    }
+
+    /// Returns the statistics of the samples of each pixel, row by row
+    #[must_use]
+    pub fn statistics(&self) -> Vec<PixelStatistics<T>> {
+        let pixels = self.pixels.lock().unwrap();
+        pixels.iter().map(|pixel| pixel.statistics).collect()
+    }
+
+    /// Divides the accumulated radiance of each pixel by its weight, leaving pixels without
+    /// samples black
+    ///
+    /// Splats are added unscaled.
+    #[must_use]
+    pub fn resolve(&self) -> Image<T> {
+        self.resolve_with_splat_scale(T::one())
+    }
+
+    /// Resolves the image like [`resolve`](Self::resolve), adding the sum of the splats of
+    /// each pixel times `splat_scale`
+    ///
+    /// The scale is usually one over the number of paths traced from the lights per pixel,
+    /// which may only be known once rendering is done.
+    #[must_use]
+    pub fn resolve_with_splat_scale(&self, splat_scale: T) -> Image<T> {
+        let pixels = self.pixels.lock().unwrap();
+        let splats = self
+            .splats
+            .iter()
+            .flat_map(|row| row.lock().unwrap().clone());
+        let pixels: Vec<_> = pixels
+            .iter()
+            .zip(splats)
+            .map(|(pixel, splat)| {
+                let splat = splat * splat_scale;
+                if pixel.weight == T::zero() {
+                    splat
+                } else {
+                    pixel.radiance / pixel.weight + splat
+                }
+            })
+            .collect();
+        let pixels = match self.outlier_factor {
+            Some(factor) => self.reject_outliers(&pixels, factor),
+            None => pixels,
+        };
+        Image::new(self.width, self.height, pixels)
+    }
+
+    /// Replaces the pixels whose luminance exceeds `factor` times that of all their neighbours
+    fn reject_outliers(&self, pixels: &[Rgb<T>], factor: T) -> Vec<Rgb<T>> {
+        let (width, height) = (self.width, self.height);
+        let mut resolved = pixels.to_vec();
+        for y in 0..height {
+            for x in 0..width {
+                let mut brightest = T::zero();
+                let mut sum = Rgb::black();
+                let mut count = 0;
+                for ny in y.saturating_sub(1)..(y + 2).min(height) {
+                    for nx in x.saturating_sub(1)..(x + 2).min(width) {
+                        if (nx, ny) != (x, y) {
+                            let neighbour = pixels[ny * width + nx];
+                            brightest = brightest.max(neighbour.luminance());
+                            sum += neighbour;
+                            count += 1;
+                        }
+                    }
+                }
+                if count > 0 && pixels[y * width + x].luminance() > factor * brightest {
+                    resolved[y * width + x] = sum / T::from(count).unwrap();
+                }
+            }
+        }
+        resolved
+    }
+}
+
+/// A rectangle of the film owned by a single thread while rendering
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilmTile<T> {
+    bounds: Box2<usize, UnknownUnit>,
+    pixels: Vec<Pixel<T>>,
+    max_sample_value: Option<T>,
+}
+
+impl<T: Float> FilmTile<T> {
+    /// The pixels covered, excluding `max`
+    #[inline]
+    #[must_use]
+    pub fn bounds(&self) -> Box2<usize, UnknownUnit> {
+        self.bounds
+    }
+
+    #[inline]
+    fn width(&self) -> usize {
+        self.bounds.max.x - self.bounds.min.x
+    }
+
+    /// Adds a sample at a continuous raster position of the film, ignoring those outside the
+    /// tile
+    pub fn add_sample(&mut self, p: Point2<T, UnknownUnit>, radiance: Rgb<T>, weight: T) {
+        let Some((x, y)) = pixel_at(p, usize::MAX, usize::MAX) else {
+            return;
+        };
+        let Box2 { min, max } = self.bounds;
+        if (min.x..max.x).contains(&x) && (min.y..max.y).contains(&y) {
+            let width = self.width();
+            let radiance = clamp_sample(radiance, self.max_sample_value);
+            self.pixels[(y - min.y) * width + x - min.x].add(radiance, weight);
+        }
+    }
+}
+
+fn splat_rows<T: Float>(width: usize, height: usize) -> Vec<Mutex<Vec<Rgb<T>>>> {
+    (0..height)
+        .map(|_| Mutex::new(vec![Rgb::black(); width]))
+        .collect()
+}
+
+#[inline]
+fn clamp_sample<T: Float>(radiance: Rgb<T>, max: Option<T>) -> Rgb<T> {
+    max.map_or(radiance, |max| radiance.clamp_max_component(max))
+}
+
+/// Returns the pixel containing a continuous raster position, if within the film
+#[inline]
+fn pixel_at<T: Float>(
+    p: Point2<T, UnknownUnit>,
+    width: usize,
+    height: usize,
+) -> Option<(usize, usize)> {
+    let x = p.x.floor().to_usize()?;
+    let y = p.y.floor().to_usize()?;
+    (x < width && y < height).then_some((x, y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type C = Rgb<f32>;
+
+    #[test]
+    fn test_tiles() {
+        let film = Film::<f32>::new(5, 3);
+        let tiles = film.tiles(2);
+        assert_eq!(tiles.len(), 6);
+        assert_eq!(tiles[5], Box2::new(Point2::new(4, 2), Point2::new(5, 3)));
+
+        std::thread::scope(|scope| {
+            for &bounds in &tiles {
+                let film = &film;
+                scope.spawn(move || {
+                    let mut tile = film.tile(bounds);
+                    for y in bounds.min.y..bounds.max.y {
+                        for x in bounds.min.x..bounds.max.x {
+                            let p = Point2::new(x as f32 + 0.5, y as f32 + 0.5);
+                            tile.add_sample(p, C::new(x as f32, y as f32, 1.), 1.);
+                            tile.add_sample(p, C::new(x as f32, y as f32, 3.), 3.);
+                        }
+                    }
+                    // Samples of other tiles are dropped
+                    tile.add_sample(Point2::new(-1., 0.), C::new(100., 0., 0.), 1.);
+                    film.merge_tile(tile);
+                });
+            }
+        });
+
+        let image = film.resolve();
+        assert_eq!(image.get(Point2::new(4, 2)), Some(&C::new(4., 2., 2.5)));
+        assert_eq!(image.get(Point2::new(0, 0)), Some(&C::new(0., 0., 2.5)));
+        assert_eq!(image.get(Point2::new(5, 0)), None);
+
+        // The statistics of the samples survive merging tiles
+        let statistics = film.statistics();
+        assert_eq!(statistics[0].samples, 2);
+        assert!((statistics[0].mean - 2. * 0.0722).abs() < 1e-6);
+        assert!((statistics[0].variance - 2. * 0.0722 * 0.0722).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_splats() {
+        let film = Film::<f32>::new(4, 3);
+        let mut tile = film.tile(film.bounds());
+        tile.add_sample(Point2::new(1.5, 1.5), C::splat(2.), 1.);
+        film.merge_tile(tile);
+
+        // Threads splat onto the same pixels at once, without losing any splat
+        std::thread::scope(|scope| {
+            for i in 0..8 {
+                let film = &film;
+                scope.spawn(move || {
+                    for j in 0..1000 {
+                        let x = (i + j) % 4;
+                        let p = Point2::new(x as f32 + 0.25, 1.75);
+                        film.add_splat(p, C::new(1., 0., x as f32));
+                    }
+                    // Splats outside the film are dropped
+                    film.add_splat(Point2::new(-0.5, 1.5), C::splat(100.));
+                    film.add_splat(Point2::new(1.5, 3.), C::splat(100.));
+                });
+            }
+        });
+
+        // The sums are scaled on their own, and added to the samples
+        let image = film.resolve_with_splat_scale(0.5);
+        assert_eq!(image.get(Point2::new(0, 1)), Some(&C::new(1000., 0., 0.)));
+        assert_eq!(
+            image.get(Point2::new(1, 1)),
+            Some(&C::new(1002., 2., 1002.))
+        );
+        assert_eq!(
+            image.get(Point2::new(3, 1)),
+            Some(&C::new(1000., 0., 3000.))
+        );
+        assert_eq!(image.get(Point2::new(1, 0)), Some(&C::black()));
+        assert_eq!(
+            film.resolve().get(Point2::new(2, 1)),
+            Some(&C::new(2000., 0., 4000.))
+        );
+        // Splats are not samples
+        assert_eq!(film.statistics()[4 + 2].samples, 0);
+    }
+
+    #[test]
+    fn test_crop_window_and_overscan() {
+        let window = Box2::new(Point2::new(0.5, 0.2), Point2::new(1., 0.5));
+        let film = Film::<f32>::new(10, 8)
+            .with_overscan(2)
+            .with_crop_window(window);
+        assert_eq!(
+            film.bounds(),
+            Box2::new(Point2::new(0, 0), Point2::new(14, 12))
+        );
+        assert_eq!(
+            film.frame(),
+            Box2::new(Point2::new(2, 2), Point2::new(12, 10))
+        );
+        // Pixels 5 to 9 and 2 to 3 of the frame, and the overscan around them
+        let bounds = Box2::new(Point2::new(5, 2), Point2::new(14, 8));
+        assert_eq!(film.pixel_bounds(), bounds);
+        let tiles = film.tiles(4);
+        assert_eq!(tiles.len(), 6);
+        assert_eq!(tiles[0].min, bounds.min);
+        assert_eq!(tiles[5].max, bounds.max);
+
+        assert_eq!(
+            film.frame_position(Point2::new(2., 2.)),
+            Point2::new(0., 0.)
+        );
+        assert_eq!(
+            film.frame_position(Point2::new(12., 10.)),
+            Point2::new(1., 1.)
+        );
+        assert_eq!(
+            film.frame_position(Point2::new(0., 0.)),
+            Point2::new(-0.2, -0.25)
+        );
+    }
+
+    #[test]
+    fn test_firefly_suppression() {
+        let mut film = Film::<f32>::new(3, 3).with_max_sample_value(4.);
+        for y in 0..3 {
+            for x in 0..3 {
+                let p = Point2::new(x as f32 + 0.5, y as f32 + 0.5);
+                film.add_sample(p, C::splat(1.), 1.);
+            }
+        }
+        // Samples are clamped keeping their hue
+        film.add_sample(Point2::new(0.5, 0.5), C::new(16., 8., 0.), 1.);
+        let image = film.resolve();
+        assert_eq!(image.get(Point2::new(0, 0)), Some(&C::new(2.5, 1.5, 0.5)));
+
+        let film = film.with_outlier_rejection(1.5);
+        let image = film.resolve();
+        assert_eq!(image.get(Point2::new(0, 0)), Some(&C::splat(1.)));
+        assert_eq!(image.get(Point2::new(1, 1)), Some(&C::splat(1.)));
+    }
+}
@@ -0,0 +1,61 @@
+use crate::{
+    color::Rgb,
+    core::geometry::{Point2, UnknownUnit},
+};
+
+/// A linear RGB image, stored row by row from the top-left corner
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image<T> {
+    width: usize,
+    height: usize,
+    pixels: Vec<Rgb<T>>,
+}
+
+impl<T> Image<T> {
+    /// Panics if the number of pixels does not match the size
+    #[must_use]
+    pub fn new(width: usize, height: usize, pixels: Vec<Rgb<T>>) -> Self {
+        assert_eq!(pixels.len(), width * height, "wrong number of pixels");
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn pixels(&self) -> &[Rgb<T>] {
+        &self.pixels
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn pixels_mut(&mut self) -> &mut [Rgb<T>] {
+        &mut self.pixels
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn get(&self, p: Point2<usize, UnknownUnit>) -> Option<&Rgb<T>> {
+        (p.x < self.width && p.y < self.height).then(|| &self.pixels[p.y * self.width + p.x])
+    }
+
+    /// Returns the rows of pixels from top to bottom
+    #[inline]
+    pub fn rows(&self) -> impl DoubleEndedIterator<Item = &[Rgb<T>]> + ExactSizeIterator {
+        self.pixels.chunks_exact(self.width.max(1))
+    }
+}
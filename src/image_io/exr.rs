@@ -0,0 +1,287 @@
+use crate::image::Image;
+use num_traits::Float;
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+pub(super) const MAGIC: [u8; 4] = [0x76, 0x2f, 0x31, 0x01];
+/// Version 2, single-part scanline file without long names
+const VERSION: [u8; 4] = [2, 0, 0, 0];
+
+/// The type of the values stored in an EXR file
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ExrPixelType {
+    /// 16-bit floats, which are enough for most color data
+    #[default]
+    Half,
+    Float,
+}
+
+impl ExrPixelType {
+    #[inline]
+    fn id(self) -> i32 {
+        match self {
+            Self::Half => 1,
+            Self::Float => 2,
+        }
+    }
+
+    #[inline]
+    fn size(self) -> usize {
+        match self {
+            Self::Half => 2,
+            Self::Float => 4,
+        }
+    }
+}
+
+/// A named image stored as the `R`, `G` and `B` channels of a layer in a multi-layer EXR file
+///
+/// The channels of a layer with an empty name are stored without prefix, as in single-layer
+/// files.
+#[derive(Debug, Copy, Clone)]
+pub struct ExrLayer<'a, T> {
+    pub name: &'a str,
+    pub image: &'a Image<T>,
+}
+
+impl<T: Float> Image<T> {
+    /// Writes the linear image to an uncompressed EXR file
+    pub fn write_exr(&self, writer: impl Write, pixel_type: ExrPixelType) -> io::Result<()> {
+        write_exr_layers(
+            writer,
+            &[ExrLayer {
+                name: "",
+                image: self,
+            }],
+            pixel_type,
+        )
+    }
+
+    /// Writes the image to an EXR file at `path`, see [`write_exr`](Self::write_exr)
+    pub fn save_exr(&self, path: impl AsRef<Path>, pixel_type: ExrPixelType) -> io::Result<()> {
+        self.write_exr(File::create(path)?, pixel_type)
+    }
+}
+
+/// Writes images of the same size as layers of one uncompressed EXR file, such as the beauty
+/// pass along with arbitrary output variables
+pub fn write_exr_layers<T: Float>(
+    writer: impl Write,
+    layers: &[ExrLayer<'_, T>],
+    pixel_type: ExrPixelType,
+) -> io::Result<()> {
+    let invalid = |msg| io::Error::new(io::ErrorKind::InvalidInput, msg);
+    let first = layers
+        .first()
+        .ok_or_else(|| invalid("no layers to write"))?;
+    let (width, height) = (first.image.width(), first.image.height());
+    if layers
+        .iter()
+        .any(|l| l.image.width() != width || l.image.height() != height)
+    {
+        return Err(invalid("layers differ in size"));
+    }
+    if width == 0 || height == 0 {
+        return Err(invalid("empty image"));
+    }
+    let max = |x: usize| i32::try_from(x - 1).map_err(|_| invalid("image too large"));
+    let (max_x, max_y) = (max(width)?, max(height)?);
+
+    // Channels are stored in alphabetical order
+    let mut channels: Vec<_> = layers
+        .iter()
+        .enumerate()
+        .flat_map(|(i, layer)| {
+            ["R", "G", "B"]
+                .into_iter()
+                .enumerate()
+                .map(move |(c, channel)| {
+                    let name = if layer.name.is_empty() {
+                        channel.to_owned()
+                    } else {
+                        format!("{}.{channel}", layer.name)
+                    };
+                    (name, i, c)
+                })
+        })
+        .collect();
+    channels.sort_by(|a, b| a.0.cmp(&b.0));
+    if channels.windows(2).any(|w| w[0].0 == w[1].0) {
+        return Err(invalid("duplicate layer names"));
+    }
+
+    // The header is assembled first, since the offsets of the scanlines depend on its size
+    let mut header = Vec::new();
+    header.extend_from_slice(&MAGIC);
+    header.extend_from_slice(&VERSION);
+
+    let mut chlist = Vec::new();
+    for (name, _, _) in &channels {
+        chlist.extend_from_slice(name.as_bytes());
+        chlist.push(0);
+        chlist.extend_from_slice(&pixel_type.id().to_le_bytes());
+        // Not perceptually linear, three reserved bytes, and no subsampling
+        chlist.extend_from_slice(&[0; 4]);
+        chlist.extend_from_slice(&1_i32.to_le_bytes());
+        chlist.extend_from_slice(&1_i32.to_le_bytes());
+    }
+    chlist.push(0);
+    write_attribute(&mut header, "channels", "chlist", &chlist);
+    // No compression
+    write_attribute(&mut header, "compression", "compression", &[0]);
+    let window: Vec<u8> = [0, 0, max_x, max_y]
+        .iter()
+        .flat_map(|x| x.to_le_bytes())
+        .collect();
+    write_attribute(&mut header, "dataWindow", "box2i", &window);
+    write_attribute(&mut header, "displayWindow", "box2i", &window);
+    // Increasing y
+    write_attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+    write_attribute(
+        &mut header,
+        "pixelAspectRatio",
+        "float",
+        &1_f32.to_le_bytes(),
+    );
+    write_attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+    write_attribute(
+        &mut header,
+        "screenWindowWidth",
+        "float",
+        &1_f32.to_le_bytes(),
+    );
+    header.push(0);
+
+    // Every scanline is its own block, preceded by its y coordinate and size
+    let line_size = width * channels.len() * pixel_type.size();
+    let header_size = header.len() + height * 8;
+    let mut w = BufWriter::new(writer);
+    w.write_all(&header)?;
+    for y in 0..height {
+        let offset = header_size + y * (8 + line_size);
+        w.write_all(&(offset as u64).to_le_bytes())?;
+    }
+    let line_size_i32 = i32::try_from(line_size).map_err(|_| invalid("image too large"))?;
+    for y in 0..height {
+        w.write_all(&(y as i32).to_le_bytes())?;
+        w.write_all(&line_size_i32.to_le_bytes())?;
+        for &(_, layer, c) in &channels {
+            let row = &layers[layer].image.pixels()[y * width..(y + 1) * width];
+            for pixel in row {
+                let x = pixel.to_array()[c].to_f32().unwrap_or(f32::NAN);
+                match pixel_type {
+                    ExrPixelType::Half => w.write_all(&f32_to_half(x).to_le_bytes())?,
+                    ExrPixelType::Float => w.write_all(&x.to_le_bytes())?,
+                }
+            }
+        }
+    }
+    w.flush()
+}
+
+/// Writes layers of the same size to an EXR file at `path`, see [`write_exr_layers`]
+pub fn save_exr_layers<T: Float>(
+    path: impl AsRef<Path>,
+    layers: &[ExrLayer<'_, T>],
+    pixel_type: ExrPixelType,
+) -> io::Result<()> {
+    write_exr_layers(File::create(path)?, layers, pixel_type)
+}
+
+fn write_attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(kind.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+/// Converts to the bits of the nearest half-precision float, rounding ties to even
+fn f32_to_half(x: f32) -> u16 {
+    let bits = x.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exp = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+    if exp == 0xff {
+        // Infinity, or NaN kept quiet
+        return sign | 0x7c00 | if mantissa == 0 { 0 } else { 0x200 };
+    }
+
+    let exp = exp - 127 + 15;
+    if exp >= 0x1f {
+        return sign | 0x7c00;
+    }
+    let (value, shift) = if exp <= 0 {
+        if exp < -10 {
+            return sign;
+        }
+        // Subnormal, with the implicit leading bit made explicit
+        (mantissa | 0x80_0000, (14 - exp) as u32)
+    } else {
+        (((exp as u32) << 23) | mantissa, 13)
+    };
+    let truncated = value >> shift;
+    let rest = value & ((1 << shift) - 1);
+    let halfway = 1 << (shift - 1);
+    // Carries into the exponent round up to the next power of two, or to infinity
+    let rounded = if rest > halfway || (rest == halfway && truncated & 1 == 1) {
+        truncated + 1
+    } else {
+        truncated
+    };
+    sign | rounded as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Rgb;
+
+    #[test]
+    fn test_write_exr() {
+        assert_eq!(f32_to_half(1.), 0x3c00);
+        assert_eq!(f32_to_half(-2.5), 0xc100);
+        assert_eq!(f32_to_half(65504.), 0x7bff);
+        assert_eq!(f32_to_half(65520.), 0x7c00);
+        assert_eq!(f32_to_half(2f32.powi(-24)), 1);
+        assert_eq!(f32_to_half(1. + 2f32.powi(-11)), 0x3c00);
+        assert_eq!(f32_to_half(f32::NAN) & 0x7e00, 0x7e00);
+
+        let beauty = Image::new(2, 2, vec![Rgb::new(1., 2., 3.); 4]);
+        let normals = Image::new(2, 2, vec![Rgb::new(0., 0., 1.); 4]);
+        let mut data = Vec::new();
+        let layers = [
+            ExrLayer {
+                name: "",
+                image: &beauty,
+            },
+            ExrLayer {
+                name: "normal",
+                image: &normals,
+            },
+        ];
+        write_exr_layers(&mut data, &layers, ExrPixelType::Float).unwrap();
+        assert_eq!(data[..8], [0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0]);
+
+        // Channels B, G, R, normal.B, normal.G, normal.R of 2 pixels each per line
+        let line_size = 8 + 6 * 2 * 4;
+        let table = data.len() - 2 * line_size - 2 * 8;
+        let offset = |y: usize| {
+            u64::from_le_bytes(data[table + y * 8..table + y * 8 + 8].try_into().unwrap()) as usize
+        };
+        assert_eq!(offset(0), table + 16);
+        assert_eq!(offset(1), table + 16 + line_size);
+
+        let line = &data[offset(1)..];
+        assert_eq!(line[..8], [1, 0, 0, 0, 48, 0, 0, 0]);
+        let values: Vec<f32> = line[8..line_size]
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        assert_eq!(values, [3., 3., 2., 2., 1., 1., 1., 1., 0., 0., 0., 0.]);
+    }
+}
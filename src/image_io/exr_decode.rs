@@ -0,0 +1,392 @@
+use crate::{
+    color::Rgb,
+    image::Image,
+    image_io::{exr::MAGIC, inflate::zlib_decompress, pixel_count, ImageError},
+};
+use num_traits::Float;
+use std::{fs::File, io::Read, path::Path};
+
+/// The flags of the version field marking tiled, deep and multi-part files
+const TILED: u32 = 0x200;
+const DEEP: u32 = 0x800;
+const MULTI_PART: u32 = 0x1000;
+
+impl<T: Float> Image<T> {
+    /// Reads the `R`, `G` and `B` channels of a scanline EXR image, or its `Y` channel if it is
+    /// grayscale
+    ///
+    /// Blocks may be uncompressed or compressed with RLE, ZIPS or ZIP, and channels may store
+    /// halves, floats or unsigned integers. Tiled, deep and multi-part files are not supported,
+    /// nor are subsampled channels. Missing channels are read as zero.
+    pub fn read_exr(mut reader: impl Read) -> Result<Self, ImageError> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut rest = &data[..];
+        if take(&mut rest, 4)? != MAGIC {
+            return Err(ImageError::InvalidFormat);
+        }
+        let flags = u32::from_le_bytes(take(&mut rest, 4)?.try_into().unwrap());
+        if flags & 0xff != 2 {
+            return Err(ImageError::InvalidFormat);
+        }
+        for (flag, kind) in [(TILED, "tiled"), (DEEP, "deep"), (MULTI_PART, "multi-part")] {
+            if flags & flag != 0 {
+                return Err(ImageError::Unsupported(format!("{kind} EXR")));
+            }
+        }
+
+        let mut channels = None;
+        let mut compression = None;
+        let mut window = None;
+        loop {
+            let name = take_string(&mut rest)?;
+            if name.is_empty() {
+                break;
+            }
+            let _kind = take_string(&mut rest)?;
+            let size = take_i32(&mut rest)?;
+            let mut value = take(&mut rest, usize::try_from(size).unwrap_or(usize::MAX))?;
+            match name {
+                b"channels" => channels = Some(parse_channels(value)?),
+                b"compression" => compression = value.first().copied(),
+                b"dataWindow" => window = Some([(); 4].map(|()| take_i32(&mut value))),
+                _ => {}
+            }
+        }
+        let channels = channels.ok_or(ImageError::InvalidFormat)?;
+        let Some([Ok(min_x), Ok(min_y), Ok(max_x), Ok(max_y)]) = window else {
+            return Err(ImageError::InvalidFormat);
+        };
+        let size = |min: i32, max: i32| {
+            usize::try_from(i64::from(max) - i64::from(min) + 1)
+                .map_err(|_| ImageError::InvalidFormat)
+        };
+        let (width, height) = (size(min_x, max_x)?, size(min_y, max_y)?);
+        let compression = compression.ok_or(ImageError::InvalidFormat)?;
+        let lines_per_block = match compression {
+            0..=2 => 1,
+            3 => 16,
+            c => {
+                let name = ["PIZ", "PXR24", "B44", "B44A", "DWAA", "DWAB"]
+                    .get(usize::from(c) - 4)
+                    .ok_or(ImageError::InvalidFormat)?;
+                return Err(ImageError::Unsupported(format!("EXR compression {name}")));
+            }
+        };
+
+        // The channel read into each color component, falling back to luminance
+        let find = |name: &[u8]| channels.iter().position(|c| c.name == name);
+        let mut sources = [find(b"R"), find(b"G"), find(b"B")];
+        if sources == [None; 3] {
+            sources = [find(b"Y"); 3];
+        }
+        if sources == [None; 3] {
+            return Err(ImageError::Unsupported(
+                "EXR without RGB or luminance channels".into(),
+            ));
+        }
+
+        // Each line stores all values of each channel in turn
+        let pixels = pixel_count(width, height)?;
+        let mut starts = Vec::with_capacity(channels.len());
+        let mut line_size = 0_usize;
+        for c in &channels {
+            starts.push(line_size);
+            line_size = width
+                .checked_mul(c.pixel_type.size())
+                .and_then(|size| line_size.checked_add(size))
+                .ok_or(ImageError::InvalidFormat)?;
+        }
+        // The offset of every block must follow the header
+        let blocks = height.div_ceil(lines_per_block);
+        if blocks.checked_mul(8).is_none_or(|len| len > rest.len()) {
+            return Err(ImageError::InvalidFormat);
+        }
+        let mut pixels = vec![Rgb::black(); pixels];
+        for _ in 0..blocks {
+            let offset = u64::from_le_bytes(take(&mut rest, 8)?.try_into().unwrap());
+            let mut block = usize::try_from(offset)
+                .ok()
+                .and_then(|offset| data.get(offset..))
+                .ok_or(ImageError::InvalidFormat)?;
+            let y = usize::try_from(i64::from(take_i32(&mut block)?) - i64::from(min_y))
+                .ok()
+                .filter(|&y| y < height)
+                .ok_or(ImageError::InvalidFormat)?;
+            let packed_size = take_i32(&mut block)?;
+            let packed = take(
+                &mut block,
+                usize::try_from(packed_size).unwrap_or(usize::MAX),
+            )?;
+            let lines = lines_per_block.min(height - y);
+            let unpacked_size = lines
+                .checked_mul(line_size)
+                .ok_or(ImageError::InvalidFormat)?;
+            // Blocks which compression would not shrink are stored uncompressed
+            let unpacked = if packed.len() == unpacked_size {
+                packed.to_vec()
+            } else {
+                let mut unpacked = match compression {
+                    1 => decode_rle(packed)?,
+                    2 | 3 => zlib_decompress(packed)?,
+                    _ => return Err(ImageError::InvalidFormat),
+                };
+                if unpacked.len() != unpacked_size {
+                    return Err(ImageError::InvalidFormat);
+                }
+                unpredict(&mut unpacked);
+                deinterleave(&unpacked)
+            };
+
+            for (line, data) in unpacked.chunks_exact(line_size).enumerate() {
+                let row = &mut pixels[(y + line) * width..][..width];
+                for (x, pixel) in row.iter_mut().enumerate() {
+                    let value = |source: Option<usize>| {
+                        source.map_or_else(T::zero, |i| {
+                            let pixel_type = channels[i].pixel_type;
+                            let size = pixel_type.size();
+                            let bytes = &data[starts[i] + x * size..][..size];
+                            T::from(pixel_type.decode(bytes)).unwrap()
+                        })
+                    };
+                    let [r, g, b] = sources.map(value);
+                    *pixel = Rgb::new(r, g, b);
+                }
+            }
+        }
+        Ok(Self::new(width, height, pixels))
+    }
+
+    /// Reads an EXR file at `path`, see [`read_exr`](Self::read_exr)
+    pub fn load_exr(path: impl AsRef<Path>) -> Result<Self, ImageError> {
+        Self::read_exr(File::open(path)?)
+    }
+}
+
+struct Channel<'a> {
+    name: &'a [u8],
+    pixel_type: PixelType,
+}
+
+#[derive(Copy, Clone)]
+enum PixelType {
+    Uint,
+    Half,
+    Float,
+}
+
+impl PixelType {
+    #[inline]
+    fn size(self) -> usize {
+        match self {
+            Self::Half => 2,
+            Self::Uint | Self::Float => 4,
+        }
+    }
+
+    #[inline]
+    fn decode(self, bytes: &[u8]) -> f32 {
+        match self {
+            Self::Uint => u32::from_le_bytes(bytes.try_into().unwrap()) as f32,
+            Self::Half => half_to_f32(u16::from_le_bytes(bytes.try_into().unwrap())),
+            Self::Float => f32::from_le_bytes(bytes.try_into().unwrap()),
+        }
+    }
+}
+
+fn parse_channels(mut data: &[u8]) -> Result<Vec<Channel<'_>>, ImageError> {
+    let mut channels = Vec::new();
+    loop {
+        let name = take_string(&mut data)?;
+        if name.is_empty() {
+            return Ok(channels);
+        }
+        let pixel_type = match take_i32(&mut data)? {
+            0 => PixelType::Uint,
+            1 => PixelType::Half,
+            2 => PixelType::Float,
+            _ => return Err(ImageError::InvalidFormat),
+        };
+        // Perceptual linearity and three reserved bytes
+        take(&mut data, 4)?;
+        if take_i32(&mut data)? != 1 || take_i32(&mut data)? != 1 {
+            return Err(ImageError::Unsupported("subsampled EXR channels".into()));
+        }
+        channels.push(Channel { name, pixel_type });
+    }
+}
+
+fn take<'a>(data: &mut &'a [u8], n: usize) -> Result<&'a [u8], ImageError> {
+    if data.len() < n {
+        return Err(ImageError::InvalidFormat);
+    }
+    let (head, tail) = data.split_at(n);
+    *data = tail;
+    Ok(head)
+}
+
+#[inline]
+fn take_i32(data: &mut &[u8]) -> Result<i32, ImageError> {
+    Ok(i32::from_le_bytes(take(data, 4)?.try_into().unwrap()))
+}
+
+/// Takes a null-terminated string, without its terminator
+fn take_string<'a>(data: &mut &'a [u8]) -> Result<&'a [u8], ImageError> {
+    let len = data
+        .iter()
+        .position(|&b| b == 0)
+        .ok_or(ImageError::InvalidFormat)?;
+    let string = take(data, len)?;
+    take(data, 1)?;
+    Ok(string)
+}
+
+/// Expands runs, each introduced by a signed count which is either the number of repeats of the
+/// next byte minus one, or the negated number of bytes copied as they are
+fn decode_rle(mut data: &[u8]) -> Result<Vec<u8>, ImageError> {
+    let mut out = Vec::new();
+    while let Some((&count, rest)) = data.split_first() {
+        data = rest;
+        let count = count as i8;
+        if count < 0 {
+            out.extend_from_slice(take(&mut data, count.unsigned_abs().into())?);
+        } else {
+            let byte = take(&mut data, 1)?[0];
+            out.resize(out.len() + count as usize + 1, byte);
+        }
+    }
+    Ok(out)
+}
+
+/// Undoes the prediction of each byte by the previous one, stored offset by 128
+fn unpredict(data: &mut [u8]) {
+    for i in 1..data.len() {
+        data[i] = data[i - 1].wrapping_add(data[i]).wrapping_sub(128);
+    }
+}
+
+/// Interleaves the first half of the bytes, holding the even ones, with the second half
+fn deinterleave(data: &[u8]) -> Vec<u8> {
+    let (even, odd) = data.split_at(data.len().div_ceil(2));
+    let mut out = Vec::with_capacity(data.len());
+    for (i, &byte) in even.iter().enumerate() {
+        out.push(byte);
+        if let Some(&byte) = odd.get(i) {
+            out.push(byte);
+        }
+    }
+    out
+}
+
+/// Converts the bits of a half-precision float to the float they represent
+fn half_to_f32(h: u16) -> f32 {
+    let sign = u32::from(h & 0x8000) << 16;
+    let exp = u32::from((h >> 10) & 0x1f);
+    let mantissa = u32::from(h & 0x3ff);
+    let bits = match exp {
+        0 => {
+            // Zero or subnormal, scaled from units of 2^-24
+            let magnitude = mantissa as f32 * 2_f32.powi(-24);
+            return if sign == 0 { magnitude } else { -magnitude };
+        }
+        0x1f => sign | 0x7f80_0000 | (mantissa << 13),
+        _ => sign | ((exp + 127 - 15) << 23) | (mantissa << 13),
+    };
+    f32::from_bits(bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image_io::{write_exr_layers, ExrLayer, ExrPixelType};
+
+    #[test]
+    fn test_read_exr() {
+        assert_eq!(half_to_f32(0x3c00), 1.);
+        assert_eq!(half_to_f32(0xc100), -2.5);
+        assert_eq!(half_to_f32(1), 2_f32.powi(-24));
+        assert_eq!(half_to_f32(0x7c00), f32::INFINITY);
+        assert!(half_to_f32(0x7e00).is_nan());
+
+        let beauty = Image::new(2, 1, vec![Rgb::new(1., 2., 3.), Rgb::new(-0.5, 0., 1e3)]);
+        let normals = Image::new(2, 1, vec![Rgb::new(0., 0., 1.); 2]);
+        for pixel_type in [ExrPixelType::Half, ExrPixelType::Float] {
+            let mut data = Vec::new();
+            beauty.write_exr(&mut data, pixel_type).unwrap();
+            assert_eq!(Image::<f64>::read_exr(&data[..]).unwrap(), beauty);
+        }
+        // Data windows larger than the file can hold are refused before allocating them
+        let mut data = Vec::new();
+        beauty.write_exr(&mut data, ExrPixelType::Half).unwrap();
+        let name = b"dataWindow\0box2i\0";
+        let window = data.windows(name.len()).position(|w| w == name).unwrap() + name.len() + 4;
+        for (max_x, max_y) in [(i32::MAX, i32::MAX), (1, 100_000)] {
+            let mut data = data.clone();
+            data[window + 8..window + 12].copy_from_slice(&max_x.to_le_bytes());
+            data[window + 12..window + 16].copy_from_slice(&max_y.to_le_bytes());
+            assert!(matches!(
+                Image::<f64>::read_exr(&data[..]),
+                Err(ImageError::InvalidFormat)
+            ));
+        }
+
+        // Layers other than the unprefixed one are ignored
+        let layers = [
+            ExrLayer {
+                name: "normal",
+                image: &normals,
+            },
+            ExrLayer {
+                name: "",
+                image: &beauty,
+            },
+        ];
+        let mut data = Vec::new();
+        write_exr_layers(&mut data, &layers, ExrPixelType::Float).unwrap();
+        assert_eq!(Image::<f64>::read_exr(&data[..]).unwrap(), beauty);
+
+        // A 3×20 ZIP-compressed image of halves in two blocks, with its data window at (10, 5)
+        // and only the attributes needed to read it, whose pixels are `(x, y, 0.5)`
+        let zip = [
+            0x76, 0x2f, 0x31, 0x01, 0x02, 0x00, 0x00, 0x00, 0x63, 0x68, 0x61, 0x6e, 0x6e, 0x65,
+            0x6c, 0x73, 0x00, 0x63, 0x68, 0x6c, 0x69, 0x73, 0x74, 0x00, 0x37, 0x00, 0x00, 0x00,
+            0x42, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+            0x01, 0x00, 0x00, 0x00, 0x47, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x52, 0x00, 0x01, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x63,
+            0x6f, 0x6d, 0x70, 0x72, 0x65, 0x73, 0x73, 0x69, 0x6f, 0x6e, 0x00, 0x63, 0x6f, 0x6d,
+            0x70, 0x72, 0x65, 0x73, 0x73, 0x69, 0x6f, 0x6e, 0x00, 0x01, 0x00, 0x00, 0x00, 0x03,
+            0x64, 0x61, 0x74, 0x61, 0x57, 0x69, 0x6e, 0x64, 0x6f, 0x77, 0x00, 0x62, 0x6f, 0x78,
+            0x32, 0x69, 0x00, 0x10, 0x00, 0x00, 0x00, 0x0a, 0x00, 0x00, 0x00, 0x05, 0x00, 0x00,
+            0x00, 0x0c, 0x00, 0x00, 0x00, 0x18, 0x00, 0x00, 0x00, 0x00, 0xa6, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x03, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05, 0x00,
+            0x00, 0x00, 0x55, 0x00, 0x00, 0x00, 0x78, 0xda, 0xad, 0xc8, 0x2b, 0x0e, 0x80, 0x40,
+            0x14, 0x43, 0xd1, 0xd9, 0xd4, 0xf0, 0x0f, 0x41, 0x20, 0x58, 0x12, 0xf2, 0x8a, 0x11,
+            0x84, 0xf0, 0x1b, 0xd8, 0x0c, 0x82, 0xc5, 0x41, 0xaa, 0x78, 0x96, 0xd0, 0x54, 0x9c,
+            0xd6, 0xf1, 0x7f, 0x9c, 0xfa, 0xed, 0x39, 0xa1, 0x7b, 0xc6, 0x15, 0x7a, 0x08, 0xd0,
+            0x0a, 0x03, 0x34, 0xc2, 0x08, 0xb5, 0x30, 0x41, 0x25, 0xcc, 0x50, 0x0a, 0x0b, 0x14,
+            0xc2, 0x0a, 0xb9, 0xb0, 0x41, 0x66, 0x11, 0x21, 0xb5, 0xd8, 0x21, 0xb1, 0x38, 0xc0,
+            0xbf, 0x70, 0x03, 0x1e, 0x52, 0x8b, 0xc1, 0x15, 0x00, 0x00, 0x00, 0x25, 0x00, 0x00,
+            0x00, 0x78, 0xda, 0x63, 0x68, 0x40, 0x80, 0x03, 0x0d, 0x0d, 0x0e, 0x60, 0x06, 0x03,
+            0x18, 0x35, 0x80, 0xb9, 0x40, 0xc1, 0x1d, 0x0d, 0x0d, 0x53, 0x1a, 0x1a, 0x4c, 0xf6,
+            0xb4, 0x54, 0xe0, 0x66, 0x00, 0x00, 0xd5, 0x24, 0x22, 0xc1,
+        ];
+        let image = Image::<f32>::read_exr(&zip[..]).unwrap();
+        assert_eq!((image.width(), image.height()), (3, 20));
+        for (i, p) in image.pixels().iter().enumerate() {
+            assert_eq!(*p, Rgb::new((i % 3) as f32, (i / 3) as f32, 0.5));
+        }
+
+        // The bytes 1, 2, 3, 3, 3, 3, 3, 4 after prediction and run-length encoding
+        let mut rle = decode_rle(&[248, 1, 130, 128, 128, 127, 129, 128, 129]).unwrap();
+        unpredict(&mut rle);
+        assert_eq!(deinterleave(&rle), [1, 2, 3, 3, 3, 3, 3, 4]);
+
+        let mut tiled = data;
+        tiled[5] |= 0x2;
+        assert!(matches!(
+            Image::<f32>::read_exr(&tiled[..]),
+            Err(ImageError::Unsupported(_))
+        ));
+    }
+}
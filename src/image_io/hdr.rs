@@ -0,0 +1,324 @@
+use crate::{
+    color::Rgb,
+    image::Image,
+    image_io::{pixel_count, ImageError},
+};
+use num_traits::Float;
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+/// Scanlines outside this range of widths can't be run-length encoded
+const RLE_WIDTHS: std::ops::RangeInclusive<usize> = 8..=0x7fff;
+const MIN_RUN: usize = 4;
+const MAX_RUN: usize = 127;
+const MAX_LITERAL: usize = 128;
+
+impl<T: Float> Image<T> {
+    /// Reads a Radiance RGBE image, such as an environment map
+    ///
+    /// Only the standard orientation and the RGB variant of the format are supported.
+    pub fn read_hdr(reader: impl Read) -> Result<Self, ImageError> {
+        let mut r = BufReader::new(reader);
+        let mut line = String::new();
+        r.read_line(&mut line)?;
+        if !line.starts_with("#?") {
+            return Err(ImageError::InvalidFormat);
+        }
+        loop {
+            line.clear();
+            if r.read_line(&mut line)? == 0 {
+                return Err(ImageError::InvalidFormat);
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some(format) = line.strip_prefix("FORMAT=") {
+                if format != "32-bit_rle_rgbe" {
+                    return Err(ImageError::Unsupported(format!("{format} pixels")));
+                }
+            }
+        }
+
+        line.clear();
+        r.read_line(&mut line)?;
+        let (height, width) = match line.split_whitespace().collect::<Vec<_>>()[..] {
+            ["-Y", height, "+X", width] => (parse(height)?, parse(width)?),
+            [_, _, _, _] => {
+                return Err(ImageError::Unsupported(format!(
+                    "orientation {}",
+                    line.trim_end()
+                )))
+            }
+            _ => return Err(ImageError::InvalidFormat),
+        };
+
+        pixel_count(width, height)?;
+
+        // Pixels are only stored as scanlines are read, so that truncated files fail early
+        let mut pixels = Vec::new();
+        let mut scanline = vec![[0; 4]; width];
+        for _ in 0..height {
+            read_scanline(&mut r, &mut scanline)?;
+            pixels.extend(scanline.iter().map(|&rgbe| from_rgbe(rgbe)));
+        }
+        Ok(Self::new(width, height, pixels))
+    }
+
+    /// Reads a Radiance RGBE image from the file at `path`, see [`read_hdr`](Self::read_hdr)
+    pub fn load_hdr(path: impl AsRef<Path>) -> Result<Self, ImageError> {
+        Self::read_hdr(File::open(path)?)
+    }
+
+    /// Writes the linear image as a run-length encoded Radiance RGBE image
+    ///
+    /// Negative values are clamped to zero.
+    pub fn write_hdr(&self, writer: impl Write) -> io::Result<()> {
+        let mut w = BufWriter::new(writer);
+        write!(
+            w,
+            "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+            self.height(),
+            self.width()
+        )?;
+        let mut channel = Vec::with_capacity(self.width());
+        for row in self.rows() {
+            let rgbe: Vec<_> = row.iter().map(|&p| to_rgbe(p)).collect();
+            if !RLE_WIDTHS.contains(&self.width()) {
+                w.write_all(rgbe.as_flattened())?;
+                continue;
+            }
+            let [hi, lo] = (self.width() as u16).to_be_bytes();
+            w.write_all(&[2, 2, hi, lo])?;
+            for c in 0..4 {
+                channel.clear();
+                channel.extend(rgbe.iter().map(|p| p[c]));
+                write_rle(&mut w, &channel)?;
+            }
+        }
+        w.flush()
+    }
+
+    /// Writes the image to a Radiance HDR file at `path`, see [`write_hdr`](Self::write_hdr)
+    pub fn save_hdr(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.write_hdr(File::create(path)?)
+    }
+}
+
+fn parse(s: &str) -> Result<usize, ImageError> {
+    s.parse().map_err(|_| ImageError::InvalidFormat)
+}
+
+fn read_byte(r: &mut impl Read) -> io::Result<u8> {
+    let mut byte = [0];
+    r.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+fn read_scanline(r: &mut impl Read, scanline: &mut [[u8; 4]]) -> Result<(), ImageError> {
+    let width = scanline.len();
+    if width == 0 {
+        return Ok(());
+    }
+    let mut first = [0; 4];
+    r.read_exact(&mut first)?;
+    let is_rle = first[0] == 2 && first[1] == 2 && first[2] & 0x80 == 0;
+    if !RLE_WIDTHS.contains(&width) || !is_rle {
+        return read_flat_scanline(r, first, scanline);
+    }
+    if usize::from(u16::from_be_bytes([first[2], first[3]])) != width {
+        return Err(ImageError::InvalidFormat);
+    }
+
+    // Each channel is encoded separately, in runs of a single value or literal bytes
+    for c in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let count = read_byte(r)?;
+            if count > 128 {
+                let count = usize::from(count - 128);
+                let value = read_byte(r)?;
+                let run = scanline
+                    .get_mut(x..x + count)
+                    .ok_or(ImageError::InvalidFormat)?;
+                run.iter_mut().for_each(|p| p[c] = value);
+                x += count;
+            } else {
+                let count = usize::from(count);
+                if count == 0 || x + count > width {
+                    return Err(ImageError::InvalidFormat);
+                }
+                for p in &mut scanline[x..x + count] {
+                    p[c] = read_byte(r)?;
+                }
+                x += count;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Reads a scanline of plain pixels, possibly with the original run-length encoding which
+/// repeats the previous pixel
+fn read_flat_scanline(
+    r: &mut impl Read,
+    first: [u8; 4],
+    scanline: &mut [[u8; 4]],
+) -> Result<(), ImageError> {
+    let mut x = 0_usize;
+    let mut shift = 0;
+    let mut pixel = first;
+    loop {
+        if pixel[..3] == [1, 1, 1] {
+            let prev = *x
+                .checked_sub(1)
+                .and_then(|i| scanline.get(i))
+                .ok_or(ImageError::InvalidFormat)?;
+            let count = usize::from(pixel[3]) << shift;
+            let run = scanline
+                .get_mut(x..x + count)
+                .ok_or(ImageError::InvalidFormat)?;
+            run.fill(prev);
+            x += count;
+            shift += 8;
+        } else {
+            scanline[x] = pixel;
+            x += 1;
+            shift = 0;
+        }
+        if x == scanline.len() {
+            return Ok(());
+        }
+        r.read_exact(&mut pixel)?;
+    }
+}
+
+/// Writes a channel of a scanline, encoding runs of at least [`MIN_RUN`] equal bytes
+fn write_rle(w: &mut impl Write, data: &[u8]) -> io::Result<()> {
+    let mut x = 0;
+    while x < data.len() {
+        // Literal bytes up to the next run worth encoding
+        let mut end = x;
+        let mut run = 0;
+        while end < data.len() {
+            run = run_length(&data[end..]);
+            if run >= MIN_RUN {
+                break;
+            }
+            end += run;
+        }
+        for literal in data[x..end].chunks(MAX_LITERAL) {
+            w.write_all(&[literal.len() as u8])?;
+            w.write_all(literal)?;
+        }
+        if end < data.len() {
+            w.write_all(&[128 + run as u8, data[end]])?;
+            end += run;
+        }
+        x = end;
+    }
+    Ok(())
+}
+
+#[inline]
+fn run_length(data: &[u8]) -> usize {
+    data.iter()
+        .take(MAX_RUN)
+        .take_while(|&&b| b == data[0])
+        .count()
+}
+
+/// Encodes a color as 8-bit mantissas sharing an exponent
+fn to_rgbe<T: Float>(c: Rgb<T>) -> [u8; 4] {
+    // NaNs become zero, and infinities the largest encodable value
+    let clamp = |c: T| c.to_f64().unwrap_or(0.).max(0.).min(f32::MAX.into());
+    let [r, g, b] = c.to_array().map(clamp);
+    let max = r.max(g).max(b);
+    if max < 1e-32 {
+        return [0; 4];
+    }
+    let (mantissa, exp) = frexp(max);
+    let scale = mantissa * 256. / max;
+    let exp = exp + 128;
+    if exp > 255 {
+        return [255, 255, 255, 255];
+    }
+    let [r, g, b] = [r, g, b].map(|c| (c * scale).min(255.) as u8);
+    [r, g, b, exp as u8]
+}
+
+fn from_rgbe<T: Float>([r, g, b, e]: [u8; 4]) -> Rgb<T> {
+    if e == 0 {
+        return Rgb::black();
+    }
+    // The mantissas are taken at the center of their quantization interval
+    let scale = 2_f64.powi(i32::from(e) - (128 + 8));
+    let [r, g, b] = [r, g, b].map(|c| T::from((f64::from(c) + 0.5) * scale).unwrap());
+    Rgb::new(r, g, b)
+}
+
+/// Splits a positive finite number into a mantissa in `[0.5, 1)` and a power of two
+fn frexp(x: f64) -> (f64, i32) {
+    let mut exp = x.log2().floor() as i32 + 1;
+    let mut mantissa = x * 2_f64.powi(-exp);
+    // Correct rounding errors of the logarithm
+    if mantissa >= 1. {
+        mantissa /= 2.;
+        exp += 1;
+    } else if mantissa < 0.5 {
+        mantissa *= 2.;
+        exp -= 1;
+    }
+    (mantissa, exp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let pixels = (0..40)
+            .map(|i| {
+                // Runs and literals of various lengths
+                let x = if i % 10 < 6 { 1. } else { i as f32 * 0.37 };
+                Rgb::new(x, 0.5 * x, 1000. * x)
+            })
+            .collect();
+        let image = Image::new(20, 2, pixels);
+        let mut data = Vec::new();
+        image.write_hdr(&mut data).unwrap();
+        let read = Image::<f32>::read_hdr(&data[..]).unwrap();
+        assert_eq!((read.width(), read.height()), (20, 2));
+        for (a, b) in image.pixels().iter().zip(read.pixels()) {
+            // The mantissas of the smaller channels keep fewer bits
+            let tolerance = a.b / 128.;
+            assert!((a.r - b.r).abs() <= tolerance && (a.b - b.b).abs() <= tolerance);
+        }
+
+        // Flat scanlines with the original run-length encoding
+        let mut data = b"#?RGBE\n\n-Y 1 +X 4\n".to_vec();
+        data.extend_from_slice(&[128, 64, 32, 129, 1, 1, 1, 3]);
+        let read = Image::<f32>::read_hdr(&data[..]).unwrap();
+        assert!(read
+            .pixels()
+            .iter()
+            .all(|p| *p == Rgb::new(128.5_f32, 64.5, 32.5) / 128.));
+
+        assert!(matches!(
+            Image::<f32>::read_hdr(&b"#?RGBE\n\n+Y 1 +X 4\n"[..]),
+            Err(ImageError::Unsupported(_))
+        ));
+        assert!(matches!(
+            Image::<f32>::read_hdr(&b"P6\n"[..]),
+            Err(ImageError::InvalidFormat)
+        ));
+        assert!(matches!(
+            Image::<f32>::read_hdr(&b"#?RGBE\n\n-Y 4000000000 +X 4000000000\n"[..]),
+            Err(ImageError::InvalidFormat)
+        ));
+    }
+}
@@ -0,0 +1,319 @@
+use crate::image_io::{png::adler32, ImageError};
+
+/// The longest code of deflate's Huffman codes
+const MAX_BITS: usize = 15;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+/// The order in which the lengths of the code length code are stored
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// Decompresses a zlib stream, checking its checksum
+pub(super) fn zlib_decompress(data: &[u8]) -> Result<Vec<u8>, ImageError> {
+    let [cmf, flg, ..] = *data else {
+        return Err(ImageError::InvalidFormat);
+    };
+    if cmf & 0x0f != 8 || (u16::from(cmf) << 8 | u16::from(flg)) % 31 != 0 {
+        return Err(ImageError::InvalidFormat);
+    }
+    if flg & 0x20 != 0 {
+        return Err(ImageError::Unsupported("zlib preset dictionary".into()));
+    }
+    let mut out = Vec::new();
+    let end = 2 + inflate(&data[2..], &mut out)?;
+    let checksum = data.get(end..end + 4).ok_or(ImageError::InvalidFormat)?;
+    if u32::from_be_bytes(checksum.try_into().unwrap()) != adler32(&out) {
+        return Err(ImageError::InvalidFormat);
+    }
+    Ok(out)
+}
+
+/// Decompresses a raw deflate stream into `out`, returning the number of bytes read
+pub(super) fn inflate(data: &[u8], out: &mut Vec<u8>) -> Result<usize, ImageError> {
+    let mut r = BitReader::new(data);
+    loop {
+        let last = r.bits(1)? == 1;
+        match r.bits(2)? {
+            0 => {
+                r.align();
+                let len = r.bits(16)?;
+                if r.bits(16)? != !len & 0xffff {
+                    return Err(ImageError::InvalidFormat);
+                }
+                for _ in 0..len {
+                    out.push(r.bits(8)? as u8);
+                }
+            }
+            1 => {
+                let mut lengths = [0; 288 + 30];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..288].fill(8);
+                lengths[288..].fill(5);
+                let literals = Huffman::new(&lengths[..288])?;
+                let distances = Huffman::new(&lengths[288..])?;
+                inflate_block(&mut r, out, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = read_dynamic_codes(&mut r)?;
+                inflate_block(&mut r, out, &literals, &distances)?;
+            }
+            _ => return Err(ImageError::InvalidFormat),
+        }
+        if last {
+            r.align();
+            return Ok(r.position());
+        }
+    }
+}
+
+fn read_dynamic_codes(r: &mut BitReader<'_>) -> Result<(Huffman, Huffman), ImageError> {
+    let literal_count = r.bits(5)? as usize + 257;
+    let distance_count = r.bits(5)? as usize + 1;
+    let code_length_count = r.bits(4)? as usize + 4;
+    let mut code_lengths = [0; 19];
+    for &i in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_lengths[i] = r.bits(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_lengths)?;
+
+    let mut lengths = vec![0; literal_count + distance_count];
+    let mut i = 0;
+    while i < lengths.len() {
+        let (value, repeat) = match code_lengths.decode(r)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => {
+                let prev = *i
+                    .checked_sub(1)
+                    .and_then(|i| lengths.get(i))
+                    .ok_or(ImageError::InvalidFormat)?;
+                (prev, 3 + r.bits(2)? as usize)
+            }
+            17 => (0, 3 + r.bits(3)? as usize),
+            _ => (0, 11 + r.bits(7)? as usize),
+        };
+        lengths
+            .get_mut(i..i + repeat)
+            .ok_or(ImageError::InvalidFormat)?
+            .fill(value);
+        i += repeat;
+    }
+    if lengths[256] == 0 {
+        return Err(ImageError::InvalidFormat);
+    }
+    Ok((
+        Huffman::new(&lengths[..literal_count])?,
+        Huffman::new(&lengths[literal_count..])?,
+    ))
+}
+
+fn inflate_block(
+    r: &mut BitReader<'_>,
+    out: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<(), ImageError> {
+    loop {
+        let symbol = literals.decode(r)?;
+        match symbol {
+            0..=255 => out.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let i = usize::from(symbol - 257);
+                let (&base, &extra) = LENGTH_BASE
+                    .get(i)
+                    .zip(LENGTH_EXTRA.get(i))
+                    .ok_or(ImageError::InvalidFormat)?;
+                let len = usize::from(base) + r.bits(extra.into())? as usize;
+                let i = usize::from(distances.decode(r)?);
+                let (&base, &extra) = DIST_BASE
+                    .get(i)
+                    .zip(DIST_EXTRA.get(i))
+                    .ok_or(ImageError::InvalidFormat)?;
+                let dist = usize::from(base) + r.bits(extra.into())? as usize;
+                let start = out
+                    .len()
+                    .checked_sub(dist)
+                    .ok_or(ImageError::InvalidFormat)?;
+                // The copy may overlap what it appends, repeating the last `dist` bytes
+                for j in start..start + len {
+                    out.push(out[j]);
+                }
+            }
+        }
+    }
+}
+
+/// A canonical Huffman code, decoded with a table indexed by the next bits of the input
+struct Huffman {
+    /// The symbol and the length of the code starting with each combination of `max_len` bits,
+    /// with a length of zero for combinations that start no code
+    table: Vec<(u16, u8)>,
+    max_len: u32,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self, ImageError> {
+        let mut counts = [0_u16; MAX_BITS + 1];
+        for &len in lengths {
+            counts[usize::from(len)] += 1;
+        }
+        counts[0] = 0;
+        let max_len = (1..=MAX_BITS).rev().find(|&l| counts[l] > 0).unwrap_or(1);
+
+        let mut next_code = [0_u32; MAX_BITS + 1];
+        let mut code = 0;
+        for len in 1..=MAX_BITS {
+            code = (code + u32::from(counts[len - 1])) << 1;
+            next_code[len] = code;
+        }
+
+        let mut table = vec![(0, 0); 1 << max_len];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len == 0 {
+                continue;
+            }
+            let len = usize::from(len);
+            let code = next_code[len];
+            next_code[len] += 1;
+            if code >= 1 << len {
+                return Err(ImageError::InvalidFormat);
+            }
+            // Codes are packed starting from their most significant bit
+            let reversed = code.reverse_bits() >> (32 - len);
+            for entry in table.iter_mut().skip(reversed as usize).step_by(1 << len) {
+                *entry = (symbol as u16, len as u8);
+            }
+        }
+        Ok(Self {
+            table,
+            max_len: max_len as u32,
+        })
+    }
+
+    fn decode(&self, r: &mut BitReader<'_>) -> Result<u16, ImageError> {
+        let (symbol, len) = self.table[r.peek(self.max_len) as usize];
+        if len == 0 {
+            return Err(ImageError::InvalidFormat);
+        }
+        r.consume(len.into())?;
+        Ok(symbol)
+    }
+}
+
+/// Reads bits starting from the least significant bit of each byte
+struct BitReader<'a> {
+    data: &'a [u8],
+    /// The next byte to load into `buffer`
+    next: usize,
+    buffer: u64,
+    count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            next: 0,
+            buffer: 0,
+            count: 0,
+        }
+    }
+
+    /// Returns the next `n` bits without consuming them, padded with zeros past the end
+    fn peek(&mut self, n: u32) -> u32 {
+        while self.count < n {
+            let byte = self.data.get(self.next).copied().unwrap_or(0);
+            self.buffer |= u64::from(byte) << self.count;
+            self.next += 1;
+            self.count += 8;
+        }
+        (self.buffer & ((1 << n) - 1)) as u32
+    }
+
+    fn consume(&mut self, n: u32) -> Result<(), ImageError> {
+        self.buffer >>= n;
+        self.count -= n;
+        if self.position() > self.data.len() {
+            return Err(ImageError::InvalidFormat);
+        }
+        Ok(())
+    }
+
+    fn bits(&mut self, n: u32) -> Result<u32, ImageError> {
+        let value = self.peek(n);
+        self.consume(n)?;
+        Ok(value)
+    }
+
+    /// Skips to the next byte boundary
+    fn align(&mut self) {
+        let n = self.count % 8;
+        self.buffer >>= n;
+        self.count -= n;
+    }
+
+    /// The number of bytes consumed, counting partially read ones
+    fn position(&self) -> usize {
+        self.next - (self.count / 8) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inflate() {
+        // zlib.compress(b"hello hello hello hello!", 9), with fixed codes and a repeat
+        let fixed = [
+            0x78, 0xda, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x27, 0x15, 0x01, 0x70,
+            0xd5, 0x08, 0xd2,
+        ];
+        assert_eq!(
+            zlib_decompress(&fixed).unwrap(),
+            b"hello hello hello hello!"
+        );
+        assert!(matches!(
+            zlib_decompress(&fixed[..fixed.len() - 1]),
+            Err(ImageError::InvalidFormat)
+        ));
+
+        // The squares of 0 to 199 modulo 97 as text, compressed the same way with dynamic codes
+        let dynamic = [
+            0x78, 0xda, 0xed, 0x90, 0x09, 0x8d, 0x45, 0x21, 0x0c, 0x45, 0xad, 0x1c, 0x09, 0xaf,
+            0x2d, 0x14, 0xf0, 0x6f, 0x6c, 0x0e, 0x18, 0xf8, 0x06, 0x26, 0x21, 0x84, 0xe5, 0xae,
+            0xfd, 0x08, 0x06, 0x87, 0x68, 0x72, 0x52, 0xcd, 0x38, 0xf4, 0x60, 0x07, 0x45, 0x0e,
+            0xc6, 0x62, 0x25, 0x49, 0x05, 0x9d, 0x1c, 0x11, 0xc5, 0xfa, 0x88, 0x64, 0x16, 0x47,
+            0xb4, 0x5c, 0x05, 0x3c, 0x0f, 0xe6, 0xc7, 0xa6, 0x27, 0xb9, 0xd8, 0x9b, 0x39, 0xc8,
+            0x64, 0xab, 0xa6, 0xd4, 0x24, 0x82, 0xdd, 0xb4, 0x8c, 0x4d, 0x25, 0x21, 0x92, 0x53,
+            0xec, 0xc9, 0x3a, 0x2c, 0xf7, 0x7a, 0xeb, 0x5d, 0x7d, 0xf4, 0xab, 0x2f, 0x48, 0xa8,
+            0x04, 0x69, 0x92, 0xe3, 0x09, 0x29, 0xa7, 0xa8, 0xd2, 0x1a, 0x68, 0xa3, 0x99, 0x96,
+            0xfb, 0x9a, 0x1b, 0xe1, 0x06, 0x89, 0x17, 0xaa, 0x6f, 0x40, 0x63, 0x1a, 0xd6, 0xc8,
+            0x06, 0xef, 0x57, 0x22, 0x6f, 0x1d, 0x4b, 0x59, 0xad, 0x6e, 0x49, 0xab, 0x5a, 0xb8,
+            0x5e, 0x79, 0x47, 0x70, 0x9c, 0x45, 0xf0, 0xfd, 0xcf, 0xe4, 0xc7, 0x4c, 0xfe, 0x00,
+            0x40, 0x31, 0x64, 0x4d,
+        ];
+        let expected: String = (0..200).map(|i| format!("{} ", i * i % 97)).collect();
+        assert_eq!(zlib_decompress(&dynamic).unwrap(), expected.as_bytes());
+        let mut corrupt = dynamic;
+        corrupt[40] ^= 0x10;
+        assert!(zlib_decompress(&corrupt).is_err());
+    }
+}
@@ -0,0 +1,631 @@
+use crate::{
+    color::Rgb,
+    image::Image,
+    image_io::{pixel_count, ImageError},
+};
+use num_traits::Float;
+use std::{f32::consts::PI, fs::File, io::Read, path::Path};
+
+/// The position in a block of each coefficient, in the order they are stored
+const ZIGZAG: [usize; 64] = [
+    0, 1, 8, 16, 9, 2, 3, 10, 17, 24, 32, 25, 18, 11, 4, 5, 12, 19, 26, 33, 40, 48, 41, 34, 27, 20,
+    13, 6, 7, 14, 21, 28, 35, 42, 49, 56, 57, 50, 43, 36, 29, 22, 15, 23, 30, 37, 44, 51, 58, 59,
+    52, 45, 38, 31, 39, 46, 53, 60, 61, 54, 47, 55, 62, 63,
+];
+
+impl<T: Float> Image<T> {
+    /// Reads a sequential JPEG image with Huffman coding, decoding its sRGB values to linear ones
+    ///
+    /// Grayscale, YCbCr and RGB images with any chroma subsampling are supported, but not
+    /// progressive, lossless, arithmetic-coded or CMYK ones. Subsampled channels are upsampled by
+    /// repeating their samples.
+    pub fn read_jpeg(mut reader: impl Read) -> Result<Self, ImageError> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let (width, height, rgb) = Decoder::new().decode(&data)?;
+        let values: Vec<T> = (0..=u8::MAX)
+            .map(|v| T::from(f32::from(v) / 255.).unwrap())
+            .collect();
+        let pixels = rgb
+            .chunks_exact(3)
+            .map(|p| Rgb::new(p[0], p[1], p[2]).map(|c| values[usize::from(c)]))
+            .map(Rgb::from_srgb)
+            .collect();
+        Ok(Self::new(width, height, pixels))
+    }
+
+    /// Reads a JPEG file at `path`, see [`read_jpeg`](Self::read_jpeg)
+    pub fn load_jpeg(path: impl AsRef<Path>) -> Result<Self, ImageError> {
+        Self::read_jpeg(File::open(path)?)
+    }
+}
+
+struct Decoder {
+    /// The quantization tables, in the natural order of the coefficients
+    quantization: [[u16; 64]; 4],
+    dc_tables: [Option<HuffmanTable>; 4],
+    ac_tables: [Option<HuffmanTable>; 4],
+    /// The number of MCUs between restart markers, or zero if there are none
+    restart_interval: usize,
+    frame: Option<Frame>,
+    /// The color transform of an Adobe segment, where zero marks RGB rather than YCbCr
+    adobe_transform: Option<u8>,
+}
+
+struct Frame {
+    width: usize,
+    height: usize,
+    components: Vec<Component>,
+    max_h: usize,
+    max_v: usize,
+    mcus_x: usize,
+    mcus_y: usize,
+}
+
+struct Component {
+    id: u8,
+    /// The horizontal and vertical sampling factors
+    h: usize,
+    v: usize,
+    quantization: usize,
+    dc_table: usize,
+    ac_table: usize,
+    /// The decoded samples, covering whole MCUs
+    samples: Vec<u8>,
+    stride: usize,
+}
+
+impl Decoder {
+    fn new() -> Self {
+        Self {
+            quantization: [[0; 64]; 4],
+            dc_tables: Default::default(),
+            ac_tables: Default::default(),
+            restart_interval: 0,
+            frame: None,
+            adobe_transform: None,
+        }
+    }
+
+    /// Decodes the image into its width, height and 8-bit sRGB values
+    fn decode(mut self, data: &[u8]) -> Result<(usize, usize, Vec<u8>), ImageError> {
+        if data.get(..2) != Some(&[0xff, 0xd8]) {
+            return Err(ImageError::InvalidFormat);
+        }
+        let mut pos = 2;
+        loop {
+            // Markers may be preceded by any number of fill bytes
+            while data.get(pos) == Some(&0xff) && data.get(pos + 1) == Some(&0xff) {
+                pos += 1;
+            }
+            let marker = match data.get(pos..pos + 2) {
+                Some(&[0xff, marker]) => marker,
+                _ => return Err(ImageError::InvalidFormat),
+            };
+            pos += 2;
+            if marker == 0xd9 {
+                break;
+            }
+            let len = data
+                .get(pos..pos + 2)
+                .map(|len| usize::from(u16::from_be_bytes([len[0], len[1]])))
+                .filter(|&len| len >= 2)
+                .ok_or(ImageError::InvalidFormat)?;
+            let segment = data
+                .get(pos + 2..pos + len)
+                .ok_or(ImageError::InvalidFormat)?;
+            pos += len;
+            match marker {
+                0xc0 | 0xc1 => self.read_frame(segment)?,
+                0xc2 | 0xc6 | 0xca | 0xce => {
+                    return Err(ImageError::Unsupported("progressive JPEG".into()))
+                }
+                0xc3 | 0xc5 | 0xc7 | 0xc9 | 0xcb | 0xcd | 0xcf => {
+                    return Err(ImageError::Unsupported(
+                        "lossless, hierarchical or arithmetic-coded JPEG".into(),
+                    ))
+                }
+                0xc4 => self.read_huffman_tables(segment)?,
+                0xdb => self.read_quantization_tables(segment)?,
+                0xdd => {
+                    let &[hi, lo] = segment else {
+                        return Err(ImageError::InvalidFormat);
+                    };
+                    self.restart_interval = u16::from_be_bytes([hi, lo]).into();
+                }
+                0xee if segment.starts_with(b"Adobe") => {
+                    self.adobe_transform = segment.get(11).copied();
+                }
+                0xda => pos = self.decode_scan(segment, data, pos)?,
+                _ => {}
+            }
+        }
+
+        let frame = self.frame.ok_or(ImageError::InvalidFormat)?;
+        let mut rgb = Vec::with_capacity(frame.width * frame.height * 3);
+        let rgb_transform =
+            self.adobe_transform == Some(0) || frame.components.iter().map(|c| c.id).eq(*b"RGB");
+        for y in 0..frame.height {
+            for x in 0..frame.width {
+                let mut values = frame.components.iter().map(|c| {
+                    let (sx, sy) = (x * c.h / frame.max_h, y * c.v / frame.max_v);
+                    c.samples[sy * c.stride + sx]
+                });
+                match frame.components.len() {
+                    1 => rgb.extend([values.next().unwrap(); 3]),
+                    _ => {
+                        let [a, b, c] = [0; 3].map(|_| values.next().unwrap());
+                        if rgb_transform {
+                            rgb.extend([a, b, c]);
+                        } else {
+                            rgb.extend(ycbcr_to_rgb(a, b, c));
+                        }
+                    }
+                }
+            }
+        }
+        Ok((frame.width, frame.height, rgb))
+    }
+
+    fn read_frame(&mut self, segment: &[u8]) -> Result<(), ImageError> {
+        let &[precision, h0, h1, w0, w1, count, ref components @ ..] = segment else {
+            return Err(ImageError::InvalidFormat);
+        };
+        if precision != 8 {
+            return Err(ImageError::Unsupported(format!("{precision}-bit JPEG")));
+        }
+        let height = usize::from(u16::from_be_bytes([h0, h1]));
+        let width = usize::from(u16::from_be_bytes([w0, w1]));
+        if height == 0 {
+            return Err(ImageError::Unsupported("JPEG with a DNL segment".into()));
+        }
+        pixel_count(width, height)?;
+        if count == 4 {
+            return Err(ImageError::Unsupported("CMYK JPEG".into()));
+        }
+        if !matches!(count, 1 | 3) || components.len() != 3 * usize::from(count) {
+            return Err(ImageError::InvalidFormat);
+        }
+        let mut components = components
+            .chunks_exact(3)
+            .map(|c| {
+                let (h, v) = (usize::from(c[1] >> 4), usize::from(c[1] & 0xf));
+                if !(1..=4).contains(&h) || !(1..=4).contains(&v) || c[2] > 3 {
+                    return Err(ImageError::InvalidFormat);
+                }
+                Ok(Component {
+                    id: c[0],
+                    h,
+                    v,
+                    quantization: c[2].into(),
+                    dc_table: 0,
+                    ac_table: 0,
+                    samples: Vec::new(),
+                    stride: 0,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let max_h = components.iter().map(|c| c.h).max().unwrap();
+        let max_v = components.iter().map(|c| c.v).max().unwrap();
+        let (mcus_x, mcus_y) = (width.div_ceil(8 * max_h), height.div_ceil(8 * max_v));
+        for c in &mut components {
+            c.stride = mcus_x * c.h * 8;
+            c.samples = vec![0; c.stride * mcus_y * c.v * 8];
+        }
+        self.frame = Some(Frame {
+            width,
+            height,
+            components,
+            max_h,
+            max_v,
+            mcus_x,
+            mcus_y,
+        });
+        Ok(())
+    }
+
+    fn read_huffman_tables(&mut self, mut segment: &[u8]) -> Result<(), ImageError> {
+        while let Some((&class_and_id, rest)) = segment.split_first() {
+            let counts = rest.get(..16).ok_or(ImageError::InvalidFormat)?;
+            let total = counts.iter().map(|&c| usize::from(c)).sum::<usize>();
+            let symbols = rest.get(16..16 + total).ok_or(ImageError::InvalidFormat)?;
+            let table = Some(HuffmanTable::new(counts, symbols)?);
+            let id = usize::from(class_and_id & 0xf);
+            match (class_and_id >> 4, id) {
+                (0, 0..=3) => self.dc_tables[id] = table,
+                (1, 0..=3) => self.ac_tables[id] = table,
+                _ => return Err(ImageError::InvalidFormat),
+            }
+            segment = &rest[16 + total..];
+        }
+        Ok(())
+    }
+
+    fn read_quantization_tables(&mut self, mut segment: &[u8]) -> Result<(), ImageError> {
+        while let Some((&precision_and_id, rest)) = segment.split_first() {
+            let id = usize::from(precision_and_id & 0xf);
+            let wide = precision_and_id >> 4 == 1;
+            let size = if wide { 128 } else { 64 };
+            let values = rest.get(..size).ok_or(ImageError::InvalidFormat)?;
+            let table = self
+                .quantization
+                .get_mut(id)
+                .ok_or(ImageError::InvalidFormat)?;
+            for (k, &i) in ZIGZAG.iter().enumerate() {
+                table[i] = if wide {
+                    u16::from_be_bytes([values[2 * k], values[2 * k + 1]])
+                } else {
+                    values[k].into()
+                };
+            }
+            segment = &rest[size..];
+        }
+        Ok(())
+    }
+
+    /// Decodes the entropy-coded data of a scan starting at `pos`, returning the position of the
+    /// marker that ends it
+    fn decode_scan(&mut self, header: &[u8], data: &[u8], pos: usize) -> Result<usize, ImageError> {
+        let frame = self.frame.as_mut().ok_or(ImageError::InvalidFormat)?;
+        let (&count, rest) = header.split_first().ok_or(ImageError::InvalidFormat)?;
+        let count = usize::from(count);
+        let (selectors, spectral) = rest
+            .split_at_checked(2 * count)
+            .ok_or(ImageError::InvalidFormat)?;
+        if spectral != [0, 63, 0] {
+            return Err(ImageError::InvalidFormat);
+        }
+        let mut scan = Vec::with_capacity(count);
+        for selector in selectors.chunks_exact(2) {
+            let i = frame
+                .components
+                .iter()
+                .position(|c| c.id == selector[0])
+                .ok_or(ImageError::InvalidFormat)?;
+            let c = &mut frame.components[i];
+            (c.dc_table, c.ac_table) = (
+                usize::from(selector[1] >> 4),
+                usize::from(selector[1] & 0xf),
+            );
+            let tables = (
+                self.dc_tables.get(c.dc_table),
+                self.ac_tables.get(c.ac_table),
+            );
+            if !matches!(tables, (Some(Some(_)), Some(Some(_)))) {
+                return Err(ImageError::InvalidFormat);
+            }
+            scan.push(i);
+        }
+        if scan.is_empty() {
+            return Err(ImageError::InvalidFormat);
+        }
+
+        // A scan of a single component codes its blocks in row order, covering only the image,
+        // while interleaved scans code each component's blocks of each MCU
+        let units = if let [i] = scan[..] {
+            let c = &frame.components[i];
+            let width = (frame.width * c.h).div_ceil(frame.max_h).div_ceil(8);
+            let height = (frame.height * c.v).div_ceil(frame.max_v).div_ceil(8);
+            (0..height)
+                .flat_map(|y| (0..width).map(move |x| vec![(i, x, y)]))
+                .collect::<Vec<_>>()
+        } else {
+            let components = &frame.components;
+            let scan = &scan;
+            (0..frame.mcus_y)
+                .flat_map(|my| (0..frame.mcus_x).map(move |mx| (mx, my)))
+                .map(|(mx, my)| {
+                    scan.iter()
+                        .flat_map(|&i| {
+                            let c = &components[i];
+                            (0..c.v).flat_map(move |v| {
+                                (0..c.h).map(move |h| (i, mx * c.h + h, my * c.v + v))
+                            })
+                        })
+                        .collect()
+                })
+                .collect()
+        };
+
+        let idct = IdctTable::new();
+        let mut r = EntropyReader::new(data, pos);
+        let mut predictions = vec![0; frame.components.len()];
+        for (n, unit) in units.iter().enumerate() {
+            if n > 0 && self.restart_interval > 0 && n % self.restart_interval == 0 {
+                r.restart()?;
+                predictions.fill(0);
+            }
+            for &(i, bx, by) in unit {
+                let c = &mut frame.components[i];
+                let dc = self.dc_tables[c.dc_table].as_ref().unwrap();
+                let ac = self.ac_tables[c.ac_table].as_ref().unwrap();
+                let quantization = &self.quantization[c.quantization];
+                let mut coefficients = [0_f32; 64];
+                predictions[i] += dc.decode_value(&mut r)?;
+                coefficients[0] = (predictions[i] * i32::from(quantization[0])) as f32;
+                let mut k = 1;
+                while k < 64 {
+                    let symbol = ac.decode(&mut r)?;
+                    let (run, size) = (usize::from(symbol >> 4), symbol & 0xf);
+                    if size == 0 {
+                        if run != 15 {
+                            break;
+                        }
+                        k += 16;
+                        continue;
+                    }
+                    k += run;
+                    let z = *ZIGZAG.get(k).ok_or(ImageError::InvalidFormat)?;
+                    let value = r.receive_extend(size)?;
+                    coefficients[z] = (value * i32::from(quantization[z])) as f32;
+                    k += 1;
+                }
+                let start = by * 8 * c.stride + bx * 8;
+                idct.apply(&coefficients, &mut c.samples[start..], c.stride);
+            }
+        }
+        Ok(r.end())
+    }
+}
+
+#[inline]
+fn ycbcr_to_rgb(y: u8, cb: u8, cr: u8) -> [u8; 3] {
+    let (y, cb, cr) = (f32::from(y), f32::from(cb) - 128., f32::from(cr) - 128.);
+    [
+        y + 1.402 * cr,
+        y - 0.344_136 * cb - 0.714_136 * cr,
+        y + 1.772 * cb,
+    ]
+    .map(|c| c.round().clamp(0., 255.) as u8)
+}
+
+/// The cosines of the inverse DCT, scaled for the normalization of its frequencies
+struct IdctTable([[f32; 8]; 8]);
+
+impl IdctTable {
+    fn new() -> Self {
+        let mut table = [[0.; 8]; 8];
+        for (x, row) in table.iter_mut().enumerate() {
+            for (u, c) in row.iter_mut().enumerate() {
+                let scale = if u == 0 { 0.5 * 0.5_f32.sqrt() } else { 0.5 };
+                *c = scale * ((2 * x + 1) as f32 * u as f32 * PI / 16.).cos();
+            }
+        }
+        Self(table)
+    }
+
+    /// Transforms the coefficients of a block into samples, level-shifted and clamped to 8 bits
+    fn apply(&self, coefficients: &[f32; 64], out: &mut [u8], stride: usize) {
+        let mut rows = [0_f32; 64];
+        for v in 0..8 {
+            for x in 0..8 {
+                rows[v * 8 + x] = (0..8).map(|u| self.0[x][u] * coefficients[v * 8 + u]).sum();
+            }
+        }
+        for y in 0..8 {
+            for x in 0..8 {
+                let value: f32 = (0..8).map(|v| self.0[y][v] * rows[v * 8 + x]).sum();
+                out[y * stride + x] = (value + 128.).round().clamp(0., 255.) as u8;
+            }
+        }
+    }
+}
+
+/// A Huffman table, decoded with a table indexed by the next 16 bits of the input
+struct HuffmanTable {
+    /// The symbol and the length of the code starting each combination of 16 bits, with a
+    /// length of zero for combinations that start no code
+    lookup: Vec<(u8, u8)>,
+}
+
+impl HuffmanTable {
+    /// Builds the table from the number of codes of each length from 1 to 16 and their symbols
+    fn new(counts: &[u8], symbols: &[u8]) -> Result<Self, ImageError> {
+        let mut lookup = vec![(0, 0); 1 << 16];
+        let mut symbols = symbols.iter();
+        let mut code = 0_usize;
+        for (len, &count) in (1..=16).zip(counts) {
+            for _ in 0..count {
+                let span = 1 << (16 - len);
+                let entries = lookup
+                    .get_mut(code * span..(code + 1) * span)
+                    .ok_or(ImageError::InvalidFormat)?;
+                entries.fill((*symbols.next().unwrap(), len as u8));
+                code += 1;
+            }
+            code <<= 1;
+        }
+        Ok(Self { lookup })
+    }
+
+    fn decode(&self, r: &mut EntropyReader<'_>) -> Result<u8, ImageError> {
+        let (symbol, len) = self.lookup[r.peek16() as usize];
+        if len == 0 {
+            return Err(ImageError::InvalidFormat);
+        }
+        r.consume(len.into());
+        Ok(symbol)
+    }
+
+    /// Decodes the size of a value and then the value itself
+    fn decode_value(&self, r: &mut EntropyReader<'_>) -> Result<i32, ImageError> {
+        let size = self.decode(r)?;
+        if size > 16 {
+            return Err(ImageError::InvalidFormat);
+        }
+        r.receive_extend(size)
+    }
+}
+
+/// Reads bits starting from the most significant bit of each byte, skipping the zero byte
+/// stuffed after each `0xff` data byte
+struct EntropyReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    buffer: u32,
+    count: u32,
+    /// Whether a marker was reached, after which zeros are read
+    at_marker: bool,
+}
+
+impl<'a> EntropyReader<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        Self {
+            data,
+            pos,
+            buffer: 0,
+            count: 0,
+            at_marker: false,
+        }
+    }
+
+    fn fill(&mut self) {
+        while self.count <= 24 {
+            let byte = if self.at_marker {
+                0
+            } else {
+                match self.data.get(self.pos..) {
+                    Some([0xff, 0, ..]) => {
+                        self.pos += 2;
+                        0xff
+                    }
+                    Some([0xff, ..] | []) | None => {
+                        self.at_marker = true;
+                        0
+                    }
+                    Some([byte, ..]) => {
+                        self.pos += 1;
+                        *byte
+                    }
+                }
+            };
+            self.buffer |= u32::from(byte) << (24 - self.count);
+            self.count += 8;
+        }
+    }
+
+    #[inline]
+    fn peek16(&mut self) -> u32 {
+        self.fill();
+        self.buffer >> 16
+    }
+
+    #[inline]
+    fn consume(&mut self, n: u32) {
+        self.buffer <<= n;
+        self.count -= n;
+    }
+
+    /// Reads a value of `size` bits, whose leading zero marks it as negative
+    fn receive_extend(&mut self, size: u8) -> Result<i32, ImageError> {
+        if size == 0 {
+            return Ok(0);
+        }
+        if size > 16 {
+            return Err(ImageError::InvalidFormat);
+        }
+        self.fill();
+        let size = u32::from(size);
+        let value = (self.buffer >> (32 - size)) as i32;
+        self.consume(size);
+        Ok(if value < 1 << (size - 1) {
+            value - (1 << size) + 1
+        } else {
+            value
+        })
+    }
+
+    /// Skips past the next restart marker, discarding the bits left before it
+    fn restart(&mut self) -> Result<(), ImageError> {
+        self.pos = self.end();
+        match self.data.get(self.pos..self.pos + 2) {
+            Some(&[0xff, 0xd0..=0xd7]) => self.pos += 2,
+            _ => return Err(ImageError::InvalidFormat),
+        }
+        self.buffer = 0;
+        self.count = 0;
+        self.at_marker = false;
+        Ok(())
+    }
+
+    /// Returns the position of the next marker
+    fn end(&self) -> usize {
+        let mut pos = self.pos;
+        while let Some(&[a, b]) = self.data.get(pos..pos + 2) {
+            if a == 0xff && b != 0 {
+                break;
+            }
+            pos += 1;
+        }
+        pos
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_jpeg() {
+        // A 20×12 baseline JPEG with 4:2:0 subsampling, unit quantization and a restart marker
+        // between its two MCUs, whose left 16 columns are the gray `10 y + 5 x` and whose right
+        // ones are `(200, 100, 50)`
+        let data = [
+            0xff, 0xd8, 0xff, 0xe0, 0x00, 0x10, 0x4a, 0x46, 0x49, 0x46, 0x00, 0x01, 0x01, 0x00,
+            0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0xff, 0xdb, 0x00, 0x84, 0x00, 0x01, 0x01, 0x01,
+            0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
+            0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
+            0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
+            0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
+            0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
+            0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
+            0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
+            0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
+            0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
+            0xff, 0xc0, 0x00, 0x11, 0x08, 0x00, 0x0c, 0x00, 0x14, 0x03, 0x01, 0x22, 0x00, 0x02,
+            0x11, 0x01, 0x03, 0x11, 0x01, 0xff, 0xc4, 0x00, 0x18, 0x00, 0x00, 0x02, 0x03, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x09,
+            0x05, 0x08, 0x0a, 0xff, 0xc4, 0x00, 0x15, 0x01, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x09, 0x00, 0xff, 0xc4,
+            0x00, 0x21, 0x10, 0x00, 0x00, 0x02, 0x0b, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x07, 0x02, 0x06, 0x24, 0x25, 0x34, 0x41, 0x43,
+            0x52, 0x61, 0x63, 0x71, 0x08, 0xff, 0xc4, 0x00, 0x14, 0x11, 0x01, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff,
+            0xdd, 0x00, 0x04, 0x00, 0x01, 0xff, 0xda, 0x00, 0x0c, 0x03, 0x01, 0x00, 0x02, 0x11,
+            0x03, 0x11, 0x00, 0x3f, 0x00, 0xcd, 0x19, 0x49, 0xe4, 0x98, 0x57, 0x65, 0x94, 0x79,
+            0x80, 0xd0, 0x0a, 0x4f, 0x24, 0xc2, 0xbb, 0x2c, 0xa3, 0xcc, 0x0b, 0xb8, 0x52, 0x16,
+            0xca, 0x93, 0x2b, 0xbe, 0xc9, 0xa1, 0x8d, 0x61, 0xa0, 0x14, 0x85, 0xb2, 0xa4, 0xca,
+            0xef, 0xb2, 0x68, 0x63, 0x58, 0x93, 0xff, 0xd0, 0x81, 0x00, 0x00, 0x0b, 0x03, 0x62,
+            0xff, 0xd9,
+        ];
+        let image = Image::<f64>::read_jpeg(&data[..]).unwrap();
+        assert_eq!((image.width(), image.height()), (20, 12));
+        for (i, p) in image.pixels().iter().enumerate() {
+            let (x, y) = (i % 20, i / 20);
+            let expected = if x < 16 {
+                Rgb::splat((10 * y + 5 * x) as f64)
+            } else {
+                Rgb::new(200., 100., 50.)
+            };
+            let error = (p.to_srgb() * 255. - expected)
+                .map(f64::abs)
+                .max_component();
+            assert!(error < 2.5, "{x} {y} {p:?}");
+        }
+
+        let mut progressive = data;
+        let sof = data.windows(2).position(|w| w == [0xff, 0xc0]).unwrap();
+        progressive[sof + 1] = 0xc2;
+        assert!(matches!(
+            Image::<f32>::read_jpeg(&progressive[..]),
+            Err(ImageError::Unsupported(_))
+        ));
+
+        let mut huge = data;
+        huge[sof + 5..sof + 9].fill(0xff);
+        assert!(matches!(
+            Image::<f32>::read_jpeg(&huge[..]),
+            Err(ImageError::InvalidFormat)
+        ));
+    }
+}
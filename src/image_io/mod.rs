@@ -0,0 +1,132 @@
+//! Reading and writing [`Image`](crate::image::Image)s in common file formats
+//!
+//! All formats are implemented without external dependencies. Reading PNG, JPEG and EXR files
+//! needs the features of the same names.
+
+mod exr;
+#[cfg(feature = "exr")]
+mod exr_decode;
+mod hdr;
+#[cfg(any(feature = "png", feature = "exr"))]
+mod inflate;
+#[cfg(feature = "jpeg")]
+mod jpeg;
+mod netpbm;
+mod png;
+#[cfg(feature = "png")]
+mod png_decode;
+
+pub use exr::{save_exr_layers, write_exr_layers, ExrLayer, ExrPixelType};
+pub use png::PngBitDepth;
+
+use crate::image::Image;
+use num_traits::Float;
+use std::{fmt, io, path::Path};
+
+/// Why an image could not be read
+#[derive(Debug)]
+pub enum ImageError {
+    Io(io::Error),
+    /// The data is not an image of the expected format, or is corrupt
+    InvalidFormat,
+    /// The image uses a feature of the format that is not supported
+    Unsupported(String),
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "failed to read image: {e}"),
+            Self::InvalidFormat => f.write_str("invalid image data"),
+            Self::Unsupported(feature) => write!(f, "unsupported image feature: {feature}"),
+        }
+    }
+}
+
+impl std::error::Error for ImageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ImageError {
+    #[inline]
+    fn from(e: io::Error) -> Self {
+        if e.kind() == io::ErrorKind::UnexpectedEof {
+            Self::InvalidFormat
+        } else {
+            Self::Io(e)
+        }
+    }
+}
+
+impl<T: Float> Image<T> {
+    /// Reads an image file of any supported format by its extension, decoding it to linear values
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ImageError> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("hdr") => Self::load_hdr(path),
+            Some("ppm") => Self::load_ppm(path),
+            #[cfg(feature = "png")]
+            Some("png") => Self::load_png(path),
+            #[cfg(feature = "jpeg")]
+            Some("jpg" | "jpeg") => Self::load_jpeg(path),
+            #[cfg(feature = "exr")]
+            Some("exr") => Self::load_exr(path),
+            other => {
+                let feature = match other {
+                    Some("png") => " without the `png` feature",
+                    Some("jpg" | "jpeg") => " without the `jpeg` feature",
+                    Some("exr") => " without the `exr` feature",
+                    _ => "",
+                };
+                Err(ImageError::Unsupported(format!(
+                    "image file {}{feature}",
+                    path.display()
+                )))
+            }
+        }
+    }
+
+    /// Encodes the image as 8-bit sRGB clamped to `[0, 1]`, with opaque alpha, in the layout
+    /// of the pixels of an HTML canvas
+    #[must_use]
+    pub fn to_rgba8(&self) -> Vec<u8> {
+        self.pixels()
+            .iter()
+            .flat_map(|pixel| {
+                let [r, g, b] = pixel.to_srgb().to_array().map(|c| quantize(c, 255) as u8);
+                [r, g, b, u8::MAX]
+            })
+            .collect()
+    }
+}
+
+/// Refuses images with more pixels than this, whose headers are likely corrupt
+const MAX_PIXELS: usize = 1 << 28;
+
+/// Checks the size of an image read from its header before anything is allocated for it,
+/// returning its number of pixels
+fn pixel_count(width: usize, height: usize) -> Result<usize, ImageError> {
+    width
+        .checked_mul(height)
+        .filter(|&n| n <= MAX_PIXELS)
+        .ok_or(ImageError::InvalidFormat)
+}
+
+/// Quantizes a value in `[0, 1]` to the integer range `[0, max]`, rounding to nearest
+#[inline]
+fn quantize<T: Float>(x: T, max: u16) -> u16 {
+    let max_f = T::from(max).unwrap();
+    (x * max_f + T::from(0.5).unwrap())
+        .min(max_f)
+        .to_u16()
+        .unwrap_or(0)
+}
@@ -0,0 +1,169 @@
+use crate::{
+    color::Rgb,
+    image::Image,
+    image_io::{pixel_count, quantize, ImageError},
+};
+use num_traits::Float;
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+impl<T: Float> Image<T> {
+    /// Reads a binary PPM, decoding its sRGB values to linear ones
+    pub fn read_ppm(reader: impl Read) -> Result<Self, ImageError> {
+        let mut r = BufReader::new(reader);
+        let mut magic = [0; 2];
+        r.read_exact(&mut magic)?;
+        if &magic != b"P6" {
+            return Err(ImageError::InvalidFormat);
+        }
+        let width = read_header_value(&mut r)?;
+        let height = read_header_value(&mut r)?;
+        let max = read_header_value(&mut r)?;
+        if max == 0 || max > usize::from(u16::MAX) {
+            return Err(ImageError::InvalidFormat);
+        }
+        let bytes = if max < 256 { 1 } else { 2 };
+        let len = pixel_count(width, height)? * 3 * bytes;
+        // Read into a growing buffer, so that truncated files fail without allocating for the
+        // size their header claims
+        let mut data = Vec::new();
+        if r.take(len as u64).read_to_end(&mut data)? < len {
+            return Err(ImageError::InvalidFormat);
+        }
+        let scale = T::from(max).unwrap().recip();
+        let value = |c: &[u8]| {
+            let c = if bytes == 1 {
+                u16::from(c[0])
+            } else {
+                u16::from_be_bytes([c[0], c[1]])
+            };
+            T::from(c).unwrap() * scale
+        };
+        let pixels = data
+            .chunks_exact(3 * bytes)
+            .map(|p| {
+                let [r, g, b] = [0, 1, 2].map(|c| value(&p[c * bytes..]));
+                Rgb::new(r, g, b).from_srgb()
+            })
+            .collect();
+        Ok(Self::new(width, height, pixels))
+    }
+
+    /// Reads a PPM file at `path`, see [`read_ppm`](Self::read_ppm)
+    pub fn load_ppm(path: impl AsRef<Path>) -> Result<Self, ImageError> {
+        Self::read_ppm(File::open(path)?)
+    }
+
+    /// Writes the image as an 8-bit binary PPM, sRGB-encoded and clamped to `[0, 1]`
+    pub fn write_ppm(&self, writer: impl Write) -> io::Result<()> {
+        let mut w = BufWriter::new(writer);
+        write!(w, "P6\n{} {}\n255\n", self.width(), self.height())?;
+        for pixel in self.pixels() {
+            let rgb = pixel.to_srgb().to_array().map(|c| quantize(c, 255) as u8);
+            w.write_all(&rgb)?;
+        }
+        w.flush()
+    }
+
+    /// Writes the image to a PPM file at `path`, see [`write_ppm`](Self::write_ppm)
+    pub fn save_ppm(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.write_ppm(File::create(path)?)
+    }
+
+    /// Writes the linear image as a little-endian PFM, with 32-bit floats stored bottom row first
+    pub fn write_pfm(&self, writer: impl Write) -> io::Result<()> {
+        let mut w = BufWriter::new(writer);
+        // A negative scale marks little-endian data
+        write!(w, "PF\n{} {}\n-1.0\n", self.width(), self.height())?;
+        for row in self.rows().rev() {
+            for pixel in row {
+                for c in pixel.to_array() {
+                    w.write_all(&c.to_f32().unwrap_or(f32::NAN).to_le_bytes())?;
+                }
+            }
+        }
+        w.flush()
+    }
+
+    /// Writes the image to a PFM file at `path`, see [`write_pfm`](Self::write_pfm)
+    pub fn save_pfm(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.write_pfm(File::create(path)?)
+    }
+}
+
+/// Reads a decimal number of the header, skipping whitespace and comments before it and the
+/// single whitespace character after it
+fn read_header_value(r: &mut impl BufRead) -> Result<usize, ImageError> {
+    let mut byte = [0];
+    loop {
+        r.read_exact(&mut byte)?;
+        match byte[0] {
+            b'#' => {
+                r.read_until(b'\n', &mut Vec::new())?;
+            }
+            c if c.is_ascii_whitespace() => {}
+            _ => break,
+        }
+    }
+    let mut value = 0_usize;
+    while byte[0].is_ascii_digit() {
+        value = value
+            .checked_mul(10)
+            .and_then(|v| v.checked_add(usize::from(byte[0] - b'0')))
+            .ok_or(ImageError::InvalidFormat)?;
+        r.read_exact(&mut byte)?;
+    }
+    if !byte[0].is_ascii_whitespace() {
+        return Err(ImageError::InvalidFormat);
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Rgb;
+
+    #[test]
+    fn test_write_netpbm() {
+        let image = Image::new(1, 2, vec![Rgb::new(0., 1., 0.5), Rgb::new(-1., 2., 0.25)]);
+        let mut data = Vec::new();
+        image.write_ppm(&mut data).unwrap();
+        assert_eq!(data, b"P6\n1 2\n255\n\x00\xff\xbc\x00\xff\x89");
+
+        let mut data = Vec::new();
+        image.write_pfm(&mut data).unwrap();
+        let mut expected = b"PF\n1 2\n-1.0\n".to_vec();
+        for x in [-1_f32, 2., 0.25, 0., 1., 0.5] {
+            expected.extend_from_slice(&x.to_le_bytes());
+        }
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn test_read_ppm() {
+        let image = Image::new(2, 1, vec![Rgb::new(0., 1., 0.5), Rgb::new(0.2, 0.04, 0.9)]);
+        let mut data = Vec::new();
+        image.write_ppm(&mut data).unwrap();
+        let read = Image::<f32>::read_ppm(&data[..]).unwrap();
+        for (a, b) in image.pixels().iter().zip(read.pixels()) {
+            assert!((a.r - b.r).abs() < 5e-3 && (a.g - b.g).abs() < 5e-3);
+        }
+
+        // 16-bit values and comments
+        let data = b"P6 # comment\n1 1\n65535\n\xff\xff\x00\x00\x80\x00";
+        let read = Image::<f64>::read_ppm(&data[..]).unwrap();
+        assert!((read.pixels()[0].b - 0.214).abs() < 1e-3);
+        assert!(matches!(
+            Image::<f32>::read_ppm(&b"P6\n2 2\n255\n\x00"[..]),
+            Err(ImageError::InvalidFormat)
+        ));
+        assert!(matches!(
+            Image::<f32>::read_ppm(&b"P6 200000 200000 255\n\x00"[..]),
+            Err(ImageError::InvalidFormat)
+        ));
+    }
+}
@@ -0,0 +1,169 @@
+use crate::{image::Image, image_io::quantize};
+use num_traits::Float;
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+pub(super) const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+/// The largest block of uncompressed data allowed by deflate
+const MAX_STORED_BLOCK: usize = 0xffff;
+
+/// The number of bits per channel of a PNG file
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum PngBitDepth {
+    #[default]
+    Eight,
+    Sixteen,
+}
+
+impl<T: Float> Image<T> {
+    /// Writes the image as an sRGB-encoded PNG, clamping values outside `[0, 1]`
+    ///
+    /// The image data is stored without compression.
+    pub fn write_png(&self, writer: impl Write, depth: PngBitDepth) -> io::Result<()> {
+        let mut w = BufWriter::new(writer);
+        w.write_all(&SIGNATURE)?;
+
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&to_u32(self.width())?.to_be_bytes());
+        header.extend_from_slice(&to_u32(self.height())?.to_be_bytes());
+        let bits = match depth {
+            PngBitDepth::Eight => 8,
+            PngBitDepth::Sixteen => 16,
+        };
+        // Truecolor without alpha, default compression, filtering and no interlacing
+        header.extend_from_slice(&[bits, 2, 0, 0, 0]);
+        write_chunk(&mut w, b"IHDR", &header)?;
+        // Perceptual rendering intent
+        write_chunk(&mut w, b"sRGB", &[0])?;
+
+        let mut raw =
+            Vec::with_capacity(self.height() * (1 + self.width() * 3 * bits as usize / 8));
+        for row in self.rows() {
+            // No filter
+            raw.push(0);
+            for pixel in row {
+                for c in pixel.to_srgb().to_array() {
+                    match depth {
+                        PngBitDepth::Eight => raw.push(quantize(c, u8::MAX.into()) as u8),
+                        PngBitDepth::Sixteen => {
+                            raw.extend_from_slice(&quantize(c, u16::MAX).to_be_bytes());
+                        }
+                    }
+                }
+            }
+        }
+        write_chunk(&mut w, b"IDAT", &zlib_stored(&raw))?;
+        write_chunk(&mut w, b"IEND", &[])?;
+        w.flush()
+    }
+
+    /// Writes the image to a PNG file at `path`, see [`write_png`](Self::write_png)
+    pub fn save_png(&self, path: impl AsRef<Path>, depth: PngBitDepth) -> io::Result<()> {
+        self.write_png(File::create(path)?, depth)
+    }
+}
+
+fn to_u32(x: usize) -> io::Result<u32> {
+    u32::try_from(x).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "image too large"))
+}
+
+fn write_chunk(w: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    w.write_all(&to_u32(data.len())?.to_be_bytes())?;
+    w.write_all(kind)?;
+    w.write_all(data)?;
+    let crc = !crc32(crc32(!0, kind), data);
+    w.write_all(&crc.to_be_bytes())
+}
+
+/// Wraps the data in a zlib stream of uncompressed deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let blocks = data.len().div_ceil(MAX_STORED_BLOCK).max(1);
+    let mut out = Vec::with_capacity(data.len() + blocks * 5 + 6);
+    // Deflate with a 32K window, no preset dictionary and the fastest level
+    out.extend_from_slice(&[0x78, 0x01]);
+    let mut chunks = data.chunks(MAX_STORED_BLOCK).peekable();
+    if chunks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(chunk) = chunks.next() {
+        let last = chunks.peek().is_none();
+        out.push(u8::from(last));
+        let len = chunk.len() as u16;
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(chunk);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut c = n as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 == 1 {
+                0xedb8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+    table
+};
+
+/// Updates a running CRC-32 without the final inversion
+pub(super) fn crc32(mut crc: u32, data: &[u8]) -> u32 {
+    for &byte in data {
+        crc = CRC_TABLE[((crc ^ u32::from(byte)) & 0xff) as usize] ^ (crc >> 8);
+    }
+    crc
+}
+
+pub(super) fn adler32(data: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    let (mut a, mut b) = (1, 0);
+    // The sums can't overflow within a chunk of this size
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += u32::from(byte);
+            b += a;
+        }
+        a %= MOD;
+        b %= MOD;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Rgb;
+
+    #[test]
+    fn test_write_png() {
+        assert_eq!(!crc32(!0, b"IEND"), 0xae42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+
+        let image = Image::new(2, 1, vec![Rgb::new(0., 0.5, 1.), Rgb::new(2., -1., 0.2)]);
+        let mut data = Vec::new();
+        image.write_png(&mut data, PngBitDepth::Eight).unwrap();
+        assert_eq!(data[..8], SIGNATURE);
+        // The image data is the last stored block before the checksum and the IEND chunk
+        let end = data.len() - 12 - 4 - 4;
+        assert_eq!(data[end - 7..end], [0, 0, 188, 255, 255, 0, 124]);
+
+        let mut data = Vec::new();
+        image.write_png(&mut data, PngBitDepth::Sixteen).unwrap();
+        let end = data.len() - 12 - 4 - 4;
+        assert_eq!(data[end - 6..end], [255, 255, 0, 0, 124, 10]);
+    }
+}
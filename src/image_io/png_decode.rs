@@ -0,0 +1,378 @@
+use crate::{
+    color::{srgb_to_linear, Rgb},
+    image::Image,
+    image_io::{
+        inflate::zlib_decompress,
+        pixel_count,
+        png::{crc32, SIGNATURE},
+        ImageError,
+    },
+};
+use num_traits::Float;
+use std::{fs::File, io::Read, path::Path};
+
+/// The offsets and spacings of the pixels of each pass of Adam7 interlacing
+const ADAM7: [(usize, usize, usize, usize); 7] = [
+    (0, 0, 8, 8),
+    (4, 0, 8, 8),
+    (0, 4, 4, 8),
+    (2, 0, 4, 4),
+    (0, 2, 2, 4),
+    (1, 0, 2, 2),
+    (0, 1, 1, 2),
+];
+
+impl<T: Float> Image<T> {
+    /// Reads a PNG image of any bit depth and color type, decoding its values to linear ones
+    ///
+    /// Values are decoded from sRGB, unless a `gAMA` chunk gives another gamma without an `sRGB`
+    /// chunk. Alpha is ignored.
+    pub fn read_png(mut reader: impl Read) -> Result<Self, ImageError> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut rest = data
+            .strip_prefix(&SIGNATURE)
+            .ok_or(ImageError::InvalidFormat)?;
+
+        let mut header = None;
+        let mut palette = Vec::new();
+        let mut gamma = None;
+        let mut srgb = false;
+        let mut compressed = Vec::new();
+        loop {
+            let (kind, chunk) = next_chunk(&mut rest)?;
+            match &kind {
+                b"IHDR" => header = Some(Header::parse(chunk)?),
+                b"PLTE" => palette = chunk.chunks_exact(3).map(|c| [c[0], c[1], c[2]]).collect(),
+                b"gAMA" => {
+                    let bytes = chunk.try_into().map_err(|_| ImageError::InvalidFormat)?;
+                    gamma = Some(u32::from_be_bytes(bytes));
+                }
+                b"sRGB" => srgb = true,
+                b"IDAT" => compressed.extend_from_slice(chunk),
+                b"IEND" => break,
+                // Ancillary chunks, whose names start with a lowercase letter, may be ignored
+                _ if kind[0].is_ascii_lowercase() => {}
+                _ => {
+                    return Err(ImageError::Unsupported(format!(
+                        "PNG chunk {}",
+                        String::from_utf8_lossy(&kind)
+                    )))
+                }
+            }
+        }
+        let header = header.ok_or(ImageError::InvalidFormat)?;
+        let samples = header.samples(&zlib_decompress(&compressed)?)?;
+
+        // The file's gamma relates its values to linear ones as `value = linear^gamma`
+        let exponent = match gamma {
+            Some(0) => return Err(ImageError::InvalidFormat),
+            Some(gamma) if !srgb => Some(T::from(100_000. / f64::from(gamma)).unwrap()),
+            _ => None,
+        };
+        let decode = |max: u16| -> Vec<T> {
+            let max_f = T::from(max).unwrap();
+            (0..=max)
+                .map(|v| {
+                    let v = T::from(v).unwrap() / max_f;
+                    exponent.map_or_else(|| srgb_to_linear(v), |e| v.powf(e))
+                })
+                .collect()
+        };
+        let pixels = if header.color_type == 3 {
+            let values = decode(u8::MAX.into());
+            let colors = palette
+                .iter()
+                .map(|c| {
+                    let [r, g, b] = c.map(|c| values[usize::from(c)]);
+                    Rgb::new(r, g, b)
+                })
+                .collect::<Vec<_>>();
+            samples
+                .iter()
+                .map(|&i| colors.get(usize::from(i)).copied())
+                .collect::<Option<_>>()
+                .ok_or(ImageError::InvalidFormat)?
+        } else {
+            let values = decode(((1_u32 << header.depth) - 1) as u16);
+            let value = |s: u16| values[usize::from(s)];
+            samples
+                .chunks_exact(header.channels())
+                .map(|p| match *p {
+                    [y] | [y, _] => Rgb::splat(value(y)),
+                    _ => Rgb::new(value(p[0]), value(p[1]), value(p[2])),
+                })
+                .collect()
+        };
+        Ok(Self::new(header.width, header.height, pixels))
+    }
+
+    /// Reads a PNG file at `path`, see [`read_png`](Self::read_png)
+    pub fn load_png(path: impl AsRef<Path>) -> Result<Self, ImageError> {
+        Self::read_png(File::open(path)?)
+    }
+}
+
+/// Splits the next chunk off the data, returning its type and contents after checking its CRC
+fn next_chunk<'a>(data: &mut &'a [u8]) -> Result<([u8; 4], &'a [u8]), ImageError> {
+    let len = data
+        .get(..4)
+        .map(|len| u32::from_be_bytes(len.try_into().unwrap()) as usize)
+        .ok_or(ImageError::InvalidFormat)?;
+    let (chunk, rest) = data
+        .get(4..)
+        .filter(|rest| rest.len() >= len + 8)
+        .ok_or(ImageError::InvalidFormat)?
+        .split_at(len + 8);
+    let (kind_and_contents, crc) = chunk.split_at(len + 4);
+    if !crc32(!0, kind_and_contents) != u32::from_be_bytes(crc.try_into().unwrap()) {
+        return Err(ImageError::InvalidFormat);
+    }
+    let (kind, contents) = kind_and_contents.split_at(4);
+    *data = rest;
+    Ok((kind.try_into().unwrap(), contents))
+}
+
+struct Header {
+    width: usize,
+    height: usize,
+    /// The number of bits per sample, or per palette index
+    depth: usize,
+    color_type: u8,
+    interlaced: bool,
+}
+
+impl Header {
+    fn parse(data: &[u8]) -> Result<Self, ImageError> {
+        let &[w0, w1, w2, w3, h0, h1, h2, h3, depth, color_type, compression, filter, interlace] =
+            data
+        else {
+            return Err(ImageError::InvalidFormat);
+        };
+        let depths: &[u8] = match color_type {
+            0 => &[1, 2, 4, 8, 16],
+            3 => &[1, 2, 4, 8],
+            2 | 4 | 6 => &[8, 16],
+            _ => &[],
+        };
+        if !depths.contains(&depth) || compression != 0 || filter != 0 || interlace > 1 {
+            return Err(ImageError::InvalidFormat);
+        }
+        // Sizes are positive and fit in 31 bits
+        let size = |bytes| match u32::from_be_bytes(bytes) {
+            0 | 0x8000_0000.. => Err(ImageError::InvalidFormat),
+            size => Ok(size as usize),
+        };
+        let (width, height) = (size([w0, w1, w2, w3])?, size([h0, h1, h2, h3])?);
+        pixel_count(width, height)?;
+        Ok(Self {
+            width,
+            height,
+            depth: depth.into(),
+            color_type,
+            interlaced: interlace == 1,
+        })
+    }
+
+    #[inline]
+    fn channels(&self) -> usize {
+        match self.color_type {
+            2 => 3,
+            4 => 2,
+            6 => 4,
+            _ => 1,
+        }
+    }
+
+    /// Unfilters the decompressed image data, returning the samples of each pixel in row order
+    fn samples(&self, mut data: &[u8]) -> Result<Vec<u16>, ImageError> {
+        let channels = self.channels();
+        let passes: &[_] = if self.interlaced {
+            &ADAM7
+        } else {
+            &[(0, 0, 1, 1)]
+        };
+        // The size in bytes of the filtered lines of a pass, and the number of them
+        let pass_size = |(x0, y0, dx, dy): (usize, usize, usize, usize)| {
+            let width = self.width.saturating_sub(x0).div_ceil(dx) as u64;
+            let height = self.height.saturating_sub(y0).div_ceil(dy);
+            ((width * (channels * self.depth) as u64).div_ceil(8), height)
+        };
+        // The data is checked to hold every line before the samples are allocated
+        let len: u64 = passes
+            .iter()
+            .map(|&pass| match pass_size(pass) {
+                (0, _) => 0,
+                (stride, height) => (stride + 1) * height as u64,
+            })
+            .sum();
+        if len > data.len() as u64 {
+            return Err(ImageError::InvalidFormat);
+        }
+
+        let mut samples = vec![0; self.width * self.height * channels];
+        for &(x0, y0, dx, dy) in passes {
+            let (stride, height) = pass_size((x0, y0, dx, dy));
+            let stride = stride as usize;
+            let width = self.width.saturating_sub(x0).div_ceil(dx);
+            if width == 0 || height == 0 {
+                continue;
+            }
+            // Filters predict each byte from the one a pixel to the left, or a byte if smaller
+            let bpp = (channels * self.depth).div_ceil(8);
+            let mut prev = vec![0; stride];
+            let mut row = vec![0; stride];
+            for y in 0..height {
+                let (&filter, rest) = data.split_first().ok_or(ImageError::InvalidFormat)?;
+                let line = rest.get(..stride).ok_or(ImageError::InvalidFormat)?;
+                data = &rest[stride..];
+                unfilter(filter, line, &prev, &mut row, bpp)?;
+                let start = ((y0 + y * dy) * self.width + x0) * channels;
+                for x in 0..width {
+                    for c in 0..channels {
+                        let i = start + x * dx * channels + c;
+                        samples[i] = sample(&row, x * channels + c, self.depth);
+                    }
+                }
+                std::mem::swap(&mut prev, &mut row);
+            }
+        }
+        Ok(samples)
+    }
+}
+
+fn unfilter(
+    filter: u8,
+    line: &[u8],
+    prev: &[u8],
+    out: &mut [u8],
+    bpp: usize,
+) -> Result<(), ImageError> {
+    if filter > 4 {
+        return Err(ImageError::InvalidFormat);
+    }
+    for i in 0..line.len() {
+        let a = if i >= bpp { out[i - bpp] } else { 0 };
+        let b = prev[i];
+        let c = if i >= bpp { prev[i - bpp] } else { 0 };
+        let prediction = match filter {
+            0 => 0,
+            1 => a,
+            2 => b,
+            3 => ((u16::from(a) + u16::from(b)) / 2) as u8,
+            _ => paeth(a, b, c),
+        };
+        out[i] = line[i].wrapping_add(prediction);
+    }
+    Ok(())
+}
+
+/// Predicts a byte with whichever of its left, upper and upper left neighbors is closest to
+/// `a + b - c`
+#[inline]
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let (ia, ib, ic) = (i16::from(a), i16::from(b), i16::from(c));
+    let p = ia + ib - ic;
+    let (pa, pb, pc) = ((p - ia).abs(), (p - ib).abs(), (p - ic).abs());
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// Returns the `i`th sample of a row, packed from the most significant bit for depths below 8
+#[inline]
+fn sample(row: &[u8], i: usize, depth: usize) -> u16 {
+    match depth {
+        16 => u16::from_be_bytes([row[2 * i], row[2 * i + 1]]),
+        8 => row[i].into(),
+        _ => {
+            let bit = i * depth;
+            u16::from(row[bit / 8] >> (8 - depth - bit % 8)) & ((1 << depth) - 1)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image_io::PngBitDepth;
+
+    #[test]
+    fn test_read_png() {
+        let image = Image::new(2, 1, vec![Rgb::new(0., 1., 0.5), Rgb::new(0.2, 0.04, 0.9)]);
+        for depth in [PngBitDepth::Eight, PngBitDepth::Sixteen] {
+            let mut data = Vec::new();
+            image.write_png(&mut data, depth).unwrap();
+            let read = Image::<f64>::read_png(&data[..]).unwrap();
+            for (a, b) in image.pixels().iter().zip(read.pixels()) {
+                assert!((a.r - b.r).abs() < 5e-3 && (a.b - b.b).abs() < 5e-3);
+            }
+        }
+
+        // A 5×5 interlaced image using every filter, compressed by zlib and with a linear gamma,
+        // whose pixels are `(50 x, 50 y, 20 (x + y)) / 255`
+        let interlaced = [
+            0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48,
+            0x44, 0x52, 0x00, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 0x05, 0x08, 0x02, 0x00, 0x00,
+            0x01, 0x75, 0x0a, 0x81, 0x24, 0x00, 0x00, 0x00, 0x04, 0x67, 0x41, 0x4d, 0x41, 0x00,
+            0x01, 0x86, 0xa0, 0x31, 0xe8, 0x96, 0x5f, 0x00, 0x00, 0x00, 0x4e, 0x49, 0x44, 0x41,
+            0x54, 0x78, 0xda, 0x3d, 0xca, 0xa1, 0x0d, 0xc0, 0x30, 0x10, 0x43, 0x51, 0x27, 0xe9,
+            0x08, 0x21, 0xdd, 0x20, 0xf8, 0x70, 0x71, 0xf1, 0x0d, 0xe1, 0x21, 0x32, 0x44, 0xb1,
+            0x71, 0xc7, 0xf1, 0x58, 0x3d, 0x54, 0xe9, 0x03, 0xeb, 0xc9, 0x00, 0xd0, 0x8c, 0xec,
+            0x70, 0xda, 0xef, 0x20, 0xd6, 0x51, 0x13, 0xe0, 0x22, 0xd3, 0xdc, 0x2d, 0x30, 0x0b,
+            0x7b, 0x41, 0x35, 0x4e, 0xdd, 0x11, 0x75, 0x89, 0x59, 0xfe, 0x07, 0xe8, 0x0a, 0x25,
+            0x45, 0x69, 0x5b, 0xcf, 0x07, 0x53, 0xc2, 0x15, 0x00, 0xb5, 0xdf, 0x5b, 0xa1, 0x00,
+            0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+        ];
+        // Headers claiming more pixels than the file holds are refused before allocating them
+        let mut data = Vec::new();
+        image.write_png(&mut data, PngBitDepth::Eight).unwrap();
+        for size in [0x4000_0000_u32, 10_000] {
+            let mut data = data.clone();
+            data[16..20].copy_from_slice(&size.to_be_bytes());
+            data[20..24].copy_from_slice(&size.to_be_bytes());
+            let crc = !crc32(!0, &data[12..29]);
+            data[29..33].copy_from_slice(&crc.to_be_bytes());
+            assert!(matches!(
+                Image::<f64>::read_png(&data[..]),
+                Err(ImageError::InvalidFormat)
+            ));
+        }
+
+        let read = Image::<f64>::read_png(&interlaced[..]).unwrap();
+        assert_eq!((read.width(), read.height()), (5, 5));
+        for (i, p) in read.pixels().iter().enumerate() {
+            let (x, y) = ((i % 5) as f64, (i / 5) as f64);
+            let expected = Rgb::new(50. * x, 50. * y, 20. * (x + y)) / 255.;
+            assert!((*p - expected).max_component().abs() < 1e-12, "{i}");
+        }
+
+        // A 3×2 image of 2-bit palette indices `(x + y) % 4`, into black, white, red and blue
+        let palette = [
+            0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48,
+            0x44, 0x52, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x02, 0x02, 0x03, 0x00, 0x00,
+            0x00, 0xe0, 0x1a, 0x8e, 0x89, 0x00, 0x00, 0x00, 0x0c, 0x50, 0x4c, 0x54, 0x45, 0x00,
+            0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00, 0xff, 0x01, 0x1d, 0x33,
+            0x4a, 0x00, 0x00, 0x00, 0x0a, 0x74, 0x45, 0x58, 0x74, 0x43, 0x6f, 0x6d, 0x6d, 0x65,
+            0x6e, 0x74, 0x00, 0x68, 0x69, 0xa2, 0xa2, 0x58, 0x66, 0x00, 0x00, 0x00, 0x0c, 0x49,
+            0x44, 0x41, 0x54, 0x78, 0xda, 0x63, 0x90, 0x60, 0xcc, 0x01, 0x00, 0x00, 0xba, 0x00,
+            0x86, 0xa9, 0x57, 0x87, 0x45, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae,
+            0x42, 0x60, 0x82,
+        ];
+        let read = Image::<f32>::read_png(&palette[..]).unwrap();
+        let (black, white) = (Rgb::splat(0.), Rgb::splat(1.));
+        let (red, blue) = (Rgb::new(1., 0., 0.), Rgb::new(0., 0., 1.));
+        assert_eq!(read.pixels(), &[black, white, red, white, red, blue]);
+
+        let mut corrupt = palette;
+        corrupt[20] ^= 1;
+        assert!(matches!(
+            Image::<f32>::read_png(&corrupt[..]),
+            Err(ImageError::InvalidFormat)
+        ));
+    }
+}
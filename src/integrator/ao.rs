@@ -0,0 +1,94 @@
+use crate::{
+    bsdf::Frame,
+    color::Rgb,
+    core::{geometry::Ray, units::Time},
+    integrator::{spawn_ray, Integrator},
+    sampler::Sampler,
+    sampling::sample_cosine_hemisphere,
+    scene::Scene,
+};
+use num_traits::{Float, FloatConst};
+
+/// Ambient occlusion, the fraction of the hemisphere above the first surface hit which is not
+/// blocked by nearby geometry, weighted by cosine
+///
+/// Materials and lights are ignored, which makes it useful for inspecting geometry, normals and
+/// accelerators on their own.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AmbientOcclusionIntegrator<T> {
+    /// The number of occlusion rays traced per camera ray
+    pub samples: usize,
+    /// The distance beyond which geometry doesn't occlude
+    pub max_distance: T,
+}
+
+impl<T> AmbientOcclusionIntegrator<T> {
+    #[inline]
+    #[must_use]
+    pub const fn new(samples: usize, max_distance: T) -> Self {
+        Self {
+            samples,
+            max_distance,
+        }
+    }
+}
+
+impl<T: Float + FloatConst + Send + Sync, U> Integrator<T, U> for AmbientOcclusionIntegrator<T> {
+    fn li(&self, ray: &Ray<T, U>, scene: &Scene<T, U>, sampler: &mut dyn Sampler<T>) -> Rgb<T> {
+        let Some(si) = scene.intersect(ray, Time(T::infinity())) else {
+            return Rgb::black();
+        };
+        if self.samples == 0 {
+            return Rgb::black();
+        }
+        let frame = Frame::from_interaction(&si);
+        // Occlusion is measured on the side of the surface facing the camera
+        let side = if frame.n.dot(si.wo) < T::zero() {
+            -T::one()
+        } else {
+            T::one()
+        };
+        let mut unoccluded = 0_usize;
+        for _ in 0..self.samples {
+            let mut wi = sample_cosine_hemisphere(sampler.next_2d());
+            wi.z = wi.z * side;
+            let ray = spawn_ray(&si, frame.from_local(wi));
+            if !scene.intersect_any(&ray, Time(self.max_distance)) {
+                unoccluded += 1;
+            }
+        }
+        Rgb::splat(T::from(unoccluded).unwrap() / T::from(self.samples).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        accel::Bvh,
+        core::geometry::{Point2, Point3, UnknownUnit, Vector3},
+        sampler::IndependentSampler,
+        scene::Primitive,
+        shape::{Disk, Shape, Sphere},
+    };
+    use std::sync::Arc;
+
+    #[test]
+    fn test_enclosed() {
+        // A disk inside a sphere is fully occluded, unless the sphere is out of reach
+        let shapes: [Arc<dyn Shape<f64, UnknownUnit> + Send + Sync>; 2] = [
+            Arc::new(Disk::new(Point3::origin(), 1., 0.)),
+            Arc::new(Sphere::new(Point3::origin(), 10.)),
+        ];
+        let primitives = shapes.map(|s| Primitive::new(s, None)).into();
+        let scene = Scene::new(Bvh::new(primitives), Vec::new());
+        let ray = Ray::new(Point3::new(0.2, 0.1, 5.), Vector3::new(0., 0., -1.));
+        let mut sampler = IndependentSampler::new(1, 0);
+        sampler.start_pixel_sample(Point2::new(0, 0), 0, 0);
+
+        let ao = AmbientOcclusionIntegrator::new(64, f64::INFINITY);
+        assert_eq!(ao.li(&ray, &scene, &mut sampler), Rgb::black());
+        let ao = AmbientOcclusionIntegrator::new(64, 5.);
+        assert_eq!(ao.li(&ray, &scene, &mut sampler), Rgb::splat(1.));
+    }
+}
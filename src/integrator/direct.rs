@@ -0,0 +1,153 @@
+use crate::{
+    bsdf::Frame,
+    color::Rgb,
+    core::{
+        geometry::{Ray, RayDifferential},
+        units::Time,
+    },
+    integrator::{sample_light, spawn_ray, Integrator},
+    light::Light,
+    sampler::Sampler,
+    sampling::power_heuristic,
+    scene::Scene,
+};
+use num_traits::Float;
+
+/// Direct illumination only, the light arriving at the first surface hit straight from the
+/// lights
+///
+/// A light and a BSDF direction are sampled at each hit, and combined with the power heuristic,
+/// so that this isolates the light sampling code of the [`PathIntegrator`](super::PathIntegrator).
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct DirectLightingIntegrator;
+
+impl DirectLightingIntegrator {
+    /// Estimates the light leaving the first hit, filtering textures with the footprint of
+    /// the differential if there is one
+    fn shade<T: Float + Send + Sync, U>(
+        ray: &Ray<T, U>,
+        differential: Option<&RayDifferential<T, U>>,
+        scene: &Scene<T, U>,
+        sampler: &mut dyn Sampler<T>,
+    ) -> Rgb<T> {
+        let Some(mut si) = scene.intersect(ray, Time(T::infinity())) else {
+            return scene.lights().iter().map(|light| light.le(ray)).sum();
+        };
+        if let Some(differential) = differential {
+            si.compute_differentials(differential);
+        }
+        let primitive = scene.primitive(&si);
+        let mut radiance = primitive
+            .area_light()
+            .map_or_else(Rgb::black, |light| light.l(si.n, si.wo));
+        let Some(material) = primitive.material() else {
+            return radiance;
+        };
+        let bsdf = material.bsdf(&si);
+        let frame = Frame::from_interaction(&si);
+        let wo = frame.to_local(si.wo);
+        if bsdf.flags().is_non_specular() {
+            radiance += sample_light(scene, &si, &frame, &*bsdf, sampler);
+        }
+
+        // The lights found in a direction sampled from the BSDF
+        let uc = sampler.next_1d();
+        let u = sampler.next_2d();
+        let Some(sample) = bsdf.sample_f(wo, uc, u) else {
+            return radiance;
+        };
+        let beta = sample.f * (sample.wi.z.abs() / sample.pdf);
+        if beta.is_black() {
+            return radiance;
+        }
+        let ray = spawn_ray(&si, frame.from_local(sample.wi));
+        let weight = |index: usize, light: &dyn Light<T, U>| {
+            if sample.flags.is_specular() {
+                T::one()
+            } else {
+                let pmf = scene.light_sampler().pmf(si.p, Some(si.n), index);
+                power_heuristic(sample.pdf, pmf * light.pdf_li(ray.origin, ray.dir))
+            }
+        };
+        match scene.intersect(&ray, Time(T::infinity())) {
+            Some(hit) => {
+                let light = scene.primitive(&hit).area_light();
+                if let (Some(light), Some(index)) = (light, scene.area_light_index(&hit)) {
+                    let le = light.l(hit.n, hit.wo);
+                    if !le.is_black() {
+                        radiance += beta * le * weight(index, &**light);
+                    }
+                }
+            }
+            None => {
+                for (index, light) in scene.lights().iter().enumerate() {
+                    let le = light.le(&ray);
+                    if !le.is_black() {
+                        radiance += beta * le * weight(index, &**light);
+                    }
+                }
+            }
+        }
+        radiance
+    }
+}
+
+impl<T: Float + Send + Sync, U> Integrator<T, U> for DirectLightingIntegrator {
+    #[inline]
+    fn li(&self, ray: &Ray<T, U>, scene: &Scene<T, U>, sampler: &mut dyn Sampler<T>) -> Rgb<T> {
+        Self::shade(ray, None, scene, sampler)
+    }
+
+    #[inline]
+    fn li_differential(
+        &self,
+        ray: &Ray<T, U>,
+        differential: &RayDifferential<T, U>,
+        scene: &Scene<T, U>,
+        sampler: &mut dyn Sampler<T>,
+    ) -> Rgb<T> {
+        Self::shade(ray, Some(differential), scene, sampler)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        accel::Bvh,
+        core::geometry::{Point2, Point3, UnknownUnit, Vector3},
+        light::DiffuseAreaLight,
+        material::DiffuseMaterial,
+        sampler::IndependentSampler,
+        scene::Primitive,
+        shape::{Disk, SampleShape, Sphere},
+    };
+    use std::sync::Arc;
+
+    #[test]
+    fn test_sphere_light() {
+        // A white floor under a sphere of radius 1 at a height of 2 reflects a quarter of its
+        // radiance straight below it
+        let sphere: Arc<dyn SampleShape<f64, UnknownUnit> + Send + Sync> =
+            Arc::new(Sphere::new(Point3::new(0., 0., 2.), 1.));
+        let light = DiffuseAreaLight::new(Arc::clone(&sphere), Rgb::splat(1.));
+        let floor = Arc::new(Disk::new(Point3::origin(), 100., 0.));
+        let material = Arc::new(DiffuseMaterial::new(Rgb::splat(1.)));
+        let primitives = vec![
+            Primitive::emissive(sphere, None, light),
+            Primitive::new(floor, Some(material)),
+        ];
+        let scene = Scene::new(Bvh::new(primitives), Vec::new());
+
+        let mut sampler = IndependentSampler::new(1, 3);
+        let ray = Ray::new(Point3::new(3., 0., 1.), Vector3::new(-3., 0., -1.));
+        let n = 4000;
+        let mut sum = Rgb::black();
+        for i in 0..n {
+            sampler.start_pixel_sample(Point2::new(0, 0), i, 0);
+            sum += DirectLightingIntegrator.li(&ray, &scene, &mut sampler);
+        }
+        let mean = sum / n as f64;
+        assert!((mean.g - 0.25).abs() < 0.01, "{mean:?}");
+    }
+}
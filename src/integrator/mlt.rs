@@ -0,0 +1,286 @@
+use crate::{
+    camera::{Camera, CameraSample},
+    color::Rgb,
+    core::geometry::{Point2, Ray, UnknownUnit},
+    film::Film,
+    integrator::{Integrator, PathIntegrator, RenderSettings},
+    sampler::{hash, MltSampler, Pcg32, Sampler},
+    sampling::PiecewiseConstant1D,
+    scene::Scene,
+};
+use num_traits::{Float, FloatConst};
+use rayon::prelude::*;
+
+/// The number of Markov chains whose splats are summed together before being added to the
+/// others
+const CHAINS_PER_BATCH: usize = 16;
+
+/// Primary sample space Metropolis light transport, of Kelemen et al.
+///
+/// Markov chains explore the sample vectors of the path tracer, mutating them and keeping the
+/// mutations in proportion to the luminance they carry, so that paths that are hard to find
+/// are explored locally once found. The brightness of the image is estimated by a bootstrap
+/// phase of independent paths, which also picks the starting states of the chains.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MltIntegrator<T> {
+    /// The path tracer sampling the paths
+    pub path: PathIntegrator,
+    /// The number of paths traced to estimate the brightness and start the chains
+    pub bootstrap_samples: usize,
+    /// The number of independent Markov chains, run in parallel
+    pub chains: usize,
+    /// The number of mutations per pixel, over all chains
+    pub mutations_per_pixel: usize,
+    /// The standard deviation of small steps, in primary sample space
+    pub sigma: T,
+    /// The probability of a mutation being a large step, replacing the sample vector
+    pub large_step_probability: T,
+    /// The seed of the chains, unless the render settings have one
+    pub seed: u64,
+}
+
+impl<T: Float> MltIntegrator<T> {
+    #[must_use]
+    pub fn new(max_depth: usize) -> Self {
+        Self {
+            path: PathIntegrator::new(max_depth),
+            bootstrap_samples: 100_000,
+            chains: 1000,
+            mutations_per_pixel: 100,
+            sigma: T::from(0.01).unwrap(),
+            large_step_probability: T::from(0.3).unwrap(),
+            seed: 0,
+        }
+    }
+
+    fn sampler(&self, seed: u64, index: usize) -> MltSampler<T> {
+        MltSampler::new(
+            self.mutations_per_pixel,
+            hash(&[seed, index as u64]),
+            self.sigma,
+            self.large_step_probability,
+        )
+    }
+
+    /// Traces the path of the current sample vector, returning its position within the
+    /// rendered pixels of the film in `[0, 1)^2` and its radiance
+    fn trace<U>(
+        &self,
+        scene: &Scene<T, U>,
+        camera: &impl Camera<T, U>,
+        film: &Film<T>,
+        sampler: &mut MltSampler<T>,
+    ) -> (Point2<T, UnknownUnit>, Rgb<T>)
+    where
+        T: FloatConst + Send + Sync,
+    {
+        let u = sampler.next_2d();
+        let bounds = film.pixel_bounds();
+        let raster =
+            |u: T, min: usize, max: usize| T::from(min).unwrap() + u * T::from(max - min).unwrap();
+        let p = Point2::new(
+            raster(u.x, bounds.min.x, bounds.max.x),
+            raster(u.y, bounds.min.y, bounds.max.y),
+        );
+        let sample = CameraSample {
+            film: film.frame_position(p),
+            lens: sampler.next_2d(),
+            time: sampler.next_1d(),
+        };
+        let ray = camera.generate_ray(&sample);
+        let radiance = self.path.li(&ray, scene, sampler);
+        if radiance.to_array().iter().all(|c| c.is_finite()) {
+            (u, radiance)
+        } else {
+            (u, Rgb::black())
+        }
+    }
+}
+
+impl<T: Float + FloatConst + Send + Sync, U> Integrator<T, U> for MltIntegrator<T> {
+    /// Estimates radiance with the path tracer alone
+    #[inline]
+    fn li(&self, ray: &Ray<T, U>, scene: &Scene<T, U>, sampler: &mut dyn Sampler<T>) -> Rgb<T> {
+        self.path.li(ray, scene, sampler)
+    }
+
+    /// Renders the scene with Markov chains, using their own samplers in place of `sampler`
+    /// seeded by the settings, or else by the integrator
+    ///
+    /// The chains only explore the rendered pixels of the film.
+    fn render<C, S>(
+        &self,
+        scene: &Scene<T, U>,
+        camera: &C,
+        _: &S,
+        film: &Film<T>,
+        settings: &RenderSettings,
+    ) where
+        Self: Sized,
+        T: Float + Send + Sync,
+        C: Camera<T, U> + Sync,
+        S: Sampler<T> + Clone + Send + Sync,
+    {
+        let seed = settings.seed.unwrap_or(self.seed);
+        settings.install(|| self.render_chains(scene, camera, film, seed));
+    }
+}
+
+impl<T: Float + FloatConst + Send + Sync> MltIntegrator<T> {
+    /// Runs the Markov chains and splats their radiance into the film
+    fn render_chains<U>(
+        &self,
+        scene: &Scene<T, U>,
+        camera: &(impl Camera<T, U> + Sync),
+        film: &Film<T>,
+        seed: u64,
+    ) {
+        let bounds = film.pixel_bounds();
+        let (width, height) = (bounds.max.x - bounds.min.x, bounds.max.y - bounds.min.y);
+        if width * height == 0 || self.bootstrap_samples == 0 || self.chains == 0 {
+            return;
+        }
+        let luminance = |c: Rgb<T>| c.luminance().max(T::zero());
+
+        let weights = (0..self.bootstrap_samples)
+            .into_par_iter()
+            .map(|i| {
+                let mut sampler = self.sampler(seed, i);
+                luminance(self.trace(scene, camera, film, &mut sampler).1)
+            })
+            .collect();
+        let bootstrap = PiecewiseConstant1D::new(weights);
+        let brightness = bootstrap.integral();
+        if brightness == T::zero() {
+            return;
+        }
+
+        // Radiance is splatted so that every mutation adds a luminance of one
+        let mutations = self.mutations_per_pixel * width * height;
+        let per_chain = mutations.div_ceil(self.chains);
+        let size = (T::from(width).unwrap(), T::from(height).unwrap());
+        // Chains are summed in batches of the same chains every time, then in order, so that
+        // the image does not depend on how they were spread over threads
+        let batches: Vec<_> = (0..self.chains)
+            .into_par_iter()
+            .fold_chunks(
+                CHAINS_PER_BATCH,
+                || vec![Rgb::black(); width * height],
+                |mut splats, chain| {
+                    let count = mutations.saturating_sub(chain * per_chain).min(per_chain);
+                    if count == 0 {
+                        return splats;
+                    }
+                    let mut splat = |p: Point2<T, UnknownUnit>, c: Rgb<T>| {
+                        let x = (p.x * size.0).to_usize().unwrap_or(0).min(width - 1);
+                        let y = (p.y * size.1).to_usize().unwrap_or(0).min(height - 1);
+                        splats[y * width + x] += c;
+                    };
+
+                    let mut rng = Pcg32::new(hash(&[seed, chain as u64, 1]));
+                    let (_, _, index) = bootstrap.sample(rng.uniform());
+                    let mut sampler = self.sampler(seed, index);
+                    let mut current = self.trace(scene, camera, film, &mut sampler);
+                    for _ in 0..count {
+                        sampler.start_iteration();
+                        let proposed = self.trace(scene, camera, film, &mut sampler);
+                        let (y_current, y_proposed) = (luminance(current.1), luminance(proposed.1));
+                        let accept = if y_current > T::zero() {
+                            (y_proposed / y_current).min(T::one())
+                        } else {
+                            T::one()
+                        };
+                        if accept > T::zero() {
+                            splat(proposed.0, proposed.1 * (accept / y_proposed));
+                        }
+                        if accept < T::one() {
+                            splat(current.0, current.1 * ((T::one() - accept) / y_current));
+                        }
+                        if rng.uniform::<T>() < accept {
+                            current = proposed;
+                            sampler.accept();
+                        } else {
+                            sampler.reject();
+                        }
+                    }
+                    splats
+                },
+            )
+            .collect();
+        let splats = batches
+            .into_iter()
+            .reduce(|mut a, b| {
+                a.iter_mut().zip(b).for_each(|(a, b)| *a += b);
+                a
+            })
+            .unwrap();
+
+        let scale = brightness / T::from(self.mutations_per_pixel).unwrap();
+        let mut tile = film.tile(bounds);
+        let half = T::from(0.5).unwrap();
+        for (i, splat) in splats.into_iter().enumerate() {
+            let p = Point2::new(
+                T::from(bounds.min.x + i % width).unwrap() + half,
+                T::from(bounds.min.y + i / width).unwrap() + half,
+            );
+            tile.add_sample(p, splat * scale, T::one());
+        }
+        film.merge_tile(tile);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        accel::Bvh,
+        camera::ThinLensCamera,
+        core::{
+            geometry::{Point3, Vector3},
+            units::Angle,
+        },
+        light::DiffuseAreaLight,
+        material::DiffuseMaterial,
+        sampler::IndependentSampler,
+        scene::Primitive,
+        shape::{SampleShape, Sphere},
+    };
+    use std::sync::Arc;
+
+    #[test]
+    fn test_furnace() {
+        // Inside a sphere that emits and reflects half of the light, every pixel converges to 2
+        let sphere: Arc<dyn SampleShape<f64, UnknownUnit> + Send + Sync> =
+            Arc::new(Sphere::new(Point3::origin(), 1.));
+        let mut light = DiffuseAreaLight::new(Arc::clone(&sphere), Rgb::splat(1.));
+        light.two_sided = true;
+        let material = Arc::new(DiffuseMaterial::new(Rgb::splat(0.5)));
+        let primitive = Primitive::emissive(sphere, Some(material), light);
+        let scene = Scene::new(Bvh::new(vec![primitive]), Vec::new());
+        let camera = ThinLensCamera::look_at(
+            Point3::origin(),
+            Point3::new(0., 0., -1.),
+            Vector3::new(0., 1., 0.),
+            Angle::from_degrees(60.),
+            1.,
+        );
+
+        let mut integrator = MltIntegrator::new(100);
+        integrator.bootstrap_samples = 10_000;
+        integrator.chains = 256;
+        integrator.mutations_per_pixel = 2000;
+        let film = Film::new(4, 4);
+        let sampler = IndependentSampler::new(1, 0);
+        integrator.render(&scene, &camera, &sampler, &film, &RenderSettings::new());
+        let image = film.resolve();
+        let mean = image.pixels().iter().copied().sum::<Rgb<f64>>() / 16.;
+        assert!((mean.g - 2.).abs() < 0.05, "{mean:?}");
+        assert!(image.pixels().iter().all(|p| (p.g - 2.).abs() < 0.3));
+
+        // The chains do not depend on the threads running them
+        let again = Film::new(4, 4);
+        let settings = RenderSettings::new().with_threads(3);
+        integrator.render(&scene, &camera, &sampler, &again, &settings);
+        assert_eq!(again.resolve(), image);
+    }
+}
@@ -0,0 +1,913 @@
+//! Light transport algorithms, estimating the radiance reaching the camera
+
+mod ao;
+mod direct;
+mod mlt;
+mod path;
+mod scheduler;
+mod volpath;
+mod wavefront;
+mod whitted;
+
+pub use ao::AmbientOcclusionIntegrator;
+pub use direct::DirectLightingIntegrator;
+pub use mlt::MltIntegrator;
+pub use path::PathIntegrator;
+pub use scheduler::RenderSettings;
+pub use volpath::VolPathIntegrator;
+pub use wavefront::WavefrontPathIntegrator;
+pub use whitted::WhittedIntegrator;
+
+use crate::{
+    bsdf::{Bsdf, Frame},
+    camera::{Camera, CameraSample},
+    color::Rgb,
+    core::{
+        geometry::{Point2, Point3, Ray, RayDifferential, UnknownUnit, Vector3},
+        units::Time,
+    },
+    film::{CheckpointError, Film, FilmTile},
+    image::Image,
+    sampler::Sampler,
+    sampling::power_heuristic,
+    scene::Scene,
+    shape::SurfaceInteraction,
+};
+use num_traits::Float;
+use std::{
+    io,
+    ops::{ControlFlow, Range},
+    path::Path,
+};
+
+/// The default size of the square tiles rendered by each thread, in pixels
+pub const TILE_SIZE: usize = 16;
+
+/// Estimates the radiance carried along camera rays, and renders images from it
+pub trait Integrator<T, U>: Sync {
+    /// Returns an estimate of the radiance arriving at the origin of the ray from the opposite of
+    /// its direction
+    #[must_use]
+    fn li(&self, ray: &Ray<T, U>, scene: &Scene<T, U>, sampler: &mut dyn Sampler<T>) -> Rgb<T>;
+
+    /// Estimates like [`li`](Self::li) for a camera ray with the offset rays through the
+    /// neighbouring pixels, which integrators following it through specular bounces use to
+    /// filter the textures they find
+    ///
+    /// By default, the differentials are ignored.
+    #[inline]
+    #[must_use]
+    fn li_differential(
+        &self,
+        ray: &Ray<T, U>,
+        differential: &RayDifferential<T, U>,
+        scene: &Scene<T, U>,
+        sampler: &mut dyn Sampler<T>,
+    ) -> Rgb<T> {
+        let _ = differential;
+        self.li(ray, scene, sampler)
+    }
+
+    /// Renders the scene as seen by the camera into the film, in parallel over its tiles as
+    /// the settings say
+    ///
+    /// The sampler is cloned for each tile, with the seed of the settings, and provides the
+    /// first two dimensions of every sample to the position on the film, the next two to the
+    /// position on the lens and the fifth to the moment within the shutter interval.
+    /// Non-finite estimates are discarded.
+    fn render<C, S>(
+        &self,
+        scene: &Scene<T, U>,
+        camera: &C,
+        sampler: &S,
+        film: &Film<T>,
+        settings: &RenderSettings,
+    ) where
+        Self: Sized,
+        T: Float + Send + Sync,
+        C: Camera<T, U> + Sync,
+        S: Sampler<T> + Clone + Send + Sync,
+    {
+        let samples = 0..sampler.samples_per_pixel();
+        self.render_samples(scene, camera, sampler, film, settings, samples);
+    }
+
+    /// Renders like [`render`](Self::render), but only takes the samples of every pixel with
+    /// indices in `samples`, so that a render can be spread over several calls, such as
+    /// between the frames of an event loop
+    ///
+    /// Indices past the samples of the sampler are skipped.
+    fn render_samples<C, S>(
+        &self,
+        scene: &Scene<T, U>,
+        camera: &C,
+        sampler: &S,
+        film: &Film<T>,
+        settings: &RenderSettings,
+        samples: Range<usize>,
+    ) where
+        Self: Sized,
+        T: Float + Send + Sync,
+        C: Camera<T, U> + Sync,
+        S: Sampler<T> + Clone + Send + Sync,
+    {
+        let render = Render {
+            integrator: self,
+            scene,
+            camera,
+            film,
+        };
+        let samples = samples.start..samples.end.min(sampler.samples_per_pixel());
+        settings.render_tiles(film, |tile| {
+            let mut sampler = settings.tile_sampler(sampler);
+            render.tile(&mut sampler, tile, samples.clone());
+        });
+    }
+
+    /// Renders like [`render`](Self::render) in passes of `samples_per_pass` samples per
+    /// pixel, calling `on_pass` after each with the number of samples per pixel taken so far
+    /// and the image resolved from the film, such as to show a preview
+    ///
+    /// The passes take the samples of the sampler in order, until it has none left or
+    /// `on_pass` breaks, and the number of samples per pixel taken is returned.
+    ///
+    /// Panics if `samples_per_pass` is zero.
+    #[allow(clippy::too_many_arguments)]
+    fn render_progressive<C, S>(
+        &self,
+        scene: &Scene<T, U>,
+        camera: &C,
+        sampler: &S,
+        film: &Film<T>,
+        settings: &RenderSettings,
+        samples_per_pass: usize,
+        mut on_pass: impl FnMut(usize, &Image<T>) -> ControlFlow<()>,
+    ) -> usize
+    where
+        Self: Sized,
+        T: Float + Send + Sync,
+        C: Camera<T, U> + Sync,
+        S: Sampler<T> + Clone + Send + Sync,
+    {
+        assert!(samples_per_pass > 0, "passes must take samples");
+        let render = Render {
+            integrator: self,
+            scene,
+            camera,
+            film,
+        };
+        render.passes(sampler, settings, 0, samples_per_pass, |taken| {
+            on_pass(taken, &film.resolve())
+        })
+    }
+
+    /// Renders like [`render_progressive`](Self::render_progressive), saving a
+    /// [checkpoint](Film::save_checkpoint) of the film to `checkpoint` after each pass, and
+    /// first resuming from the checkpoint there if there is one
+    ///
+    /// A render interrupted at any point thus only loses the samples of its last pass when run
+    /// again. Resumed renders take the same samples as uninterrupted ones, provided the sampler
+    /// and settings are the same. The number of samples per pixel taken is returned.
+    ///
+    /// Panics if `samples_per_pass` is zero.
+    #[allow(clippy::too_many_arguments)]
+    fn render_resumable<C, S>(
+        &self,
+        scene: &Scene<T, U>,
+        camera: &C,
+        sampler: &S,
+        film: &Film<T>,
+        settings: &RenderSettings,
+        samples_per_pass: usize,
+        checkpoint: impl AsRef<Path>,
+    ) -> Result<usize, CheckpointError>
+    where
+        Self: Sized,
+        T: Float + Send + Sync,
+        C: Camera<T, U> + Sync,
+        S: Sampler<T> + Clone + Send + Sync,
+    {
+        assert!(samples_per_pass > 0, "passes must take samples");
+        let checkpoint = checkpoint.as_ref();
+        let start = match film.load_checkpoint(checkpoint) {
+            Ok(taken) => taken,
+            Err(CheckpointError::Io(e)) if e.kind() == io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(e),
+        };
+        let render = Render {
+            integrator: self,
+            scene,
+            camera,
+            film,
+        };
+        let mut result = Ok(());
+        let taken = render.passes(sampler, settings, start, samples_per_pass, |taken| {
+            result = film.save_checkpoint(checkpoint, taken);
+            if result.is_ok() {
+                ControlFlow::Continue(())
+            } else {
+                ControlFlow::Break(())
+            }
+        });
+        result?;
+        Ok(taken)
+    }
+
+    /// Renders like [`render`](Self::render), then keeps adding as many samples again to the
+    /// pixels whose [relative error](crate::film::PixelStatistics::relative_error) is above
+    /// `error_target`, until they reach it or have taken `max_samples`
+    ///
+    /// Samples are concentrated where the image is noisy, such as in soft shadows and caustics,
+    /// rather than spread evenly over pixels that have converged.
+    #[allow(clippy::too_many_arguments)]
+    fn render_adaptive<C, S>(
+        &self,
+        scene: &Scene<T, U>,
+        camera: &C,
+        sampler: &S,
+        film: &Film<T>,
+        settings: &RenderSettings,
+        error_target: T,
+        max_samples: usize,
+    ) where
+        Self: Sized,
+        T: Float + Send + Sync,
+        C: Camera<T, U> + Sync,
+        S: Sampler<T> + Clone + Send + Sync,
+    {
+        let render = Render {
+            integrator: self,
+            scene,
+            camera,
+            film,
+        };
+        let batch = sampler.samples_per_pixel().max(1);
+        // Counted here rather than by the film, which drops non-finite samples
+        let mut taken = vec![0; film.width() * film.height()];
+        let bounds = film.pixel_bounds();
+        let rendered = |i: usize| bounds.contains(Point2::new(i % film.width(), i / film.width()));
+        loop {
+            let active: Vec<_> = (taken.iter().zip(film.statistics()).enumerate())
+                .map(|(i, (&taken, stats))| {
+                    rendered(i) && taken < max_samples && stats.relative_error() > error_target
+                })
+                .collect();
+            if !active.contains(&true) {
+                break;
+            }
+            settings.render_tiles(film, |tile| {
+                let mut sampler = settings.tile_sampler(sampler);
+                let bounds = tile.bounds();
+                for y in bounds.min.y..bounds.max.y {
+                    for x in bounds.min.x..bounds.max.x {
+                        let i = y * film.width() + x;
+                        if !active[i] {
+                            continue;
+                        }
+                        for index in taken[i]..(taken[i] + batch).min(max_samples) {
+                            render.sample(&mut sampler, tile, Point2::new(x, y), index);
+                        }
+                    }
+                }
+            });
+            for (taken, _) in taken.iter_mut().zip(&active).filter(|(_, &active)| active) {
+                *taken = (*taken + batch).min(max_samples);
+            }
+        }
+    }
+}
+
+/// What the samples of a render are taken of
+struct Render<'a, T, U, I, C> {
+    integrator: &'a I,
+    scene: &'a Scene<T, U>,
+    camera: &'a C,
+    film: &'a Film<T>,
+}
+
+impl<T: Float + Send + Sync, U, I: Integrator<T, U>, C: Camera<T, U> + Sync>
+    Render<'_, T, U, I, C>
+{
+    /// Renders passes of `samples_per_pass` samples per pixel from sample `start` on, until the
+    /// sampler has none left or `on_pass` breaks, returning the number of samples per pixel
+    /// then taken
+    fn passes<S: Sampler<T> + Clone + Send + Sync>(
+        &self,
+        sampler: &S,
+        settings: &RenderSettings,
+        start: usize,
+        samples_per_pass: usize,
+        mut on_pass: impl FnMut(usize) -> ControlFlow<()>,
+    ) -> usize {
+        let mut taken = start;
+        while taken < sampler.samples_per_pixel() {
+            let end = (taken + samples_per_pass).min(sampler.samples_per_pixel());
+            settings.render_tiles(self.film, |tile| {
+                let mut sampler = settings.tile_sampler(sampler);
+                self.tile(&mut sampler, tile, taken..end);
+            });
+            taken = end;
+            if on_pass(taken).is_break() {
+                break;
+            }
+        }
+        taken
+    }
+}
+
+impl<T: Float, U, I: Integrator<T, U>, C: Camera<T, U>> Render<'_, T, U, I, C> {
+    /// Takes the samples with the given indices of every pixel of a tile
+    fn tile(&self, sampler: &mut dyn Sampler<T>, tile: &mut FilmTile<T>, samples: Range<usize>) {
+        let bounds = tile.bounds();
+        for y in bounds.min.y..bounds.max.y {
+            for x in bounds.min.x..bounds.max.x {
+                for index in samples.clone() {
+                    self.sample(sampler, tile, Point2::new(x, y), index);
+                }
+            }
+        }
+    }
+
+    /// Traces the camera ray of a sample of a pixel, and adds its estimate to the tile unless
+    /// it is not finite
+    fn sample(
+        &self,
+        sampler: &mut dyn Sampler<T>,
+        tile: &mut FilmTile<T>,
+        pixel: Point2<usize, UnknownUnit>,
+        index: usize,
+    ) {
+        let (p, ray, differential) = camera_ray(self.camera, self.film, sampler, pixel, index);
+        let radiance = self
+            .integrator
+            .li_differential(&ray, &differential, self.scene, sampler);
+        if radiance.to_array().iter().all(|c| c.is_finite()) {
+            tile.add_sample(p, radiance, T::one());
+        }
+    }
+}
+
+/// Starts a sample of a pixel and generates its camera ray, returning the position of the
+/// sample on the film, the ray and its differential
+fn camera_ray<T: Float, U>(
+    camera: &impl Camera<T, U>,
+    film: &Film<T>,
+    sampler: &mut dyn Sampler<T>,
+    pixel: Point2<usize, UnknownUnit>,
+    index: usize,
+) -> (Point2<T, UnknownUnit>, Ray<T, U>, RayDifferential<T, U>) {
+    sampler.start_pixel_sample(pixel, index, 0);
+    let offset = sampler.next_2d();
+    let p: Point2<T, UnknownUnit> = Point2::new(
+        T::from(pixel.x).unwrap() + offset.x,
+        T::from(pixel.y).unwrap() + offset.y,
+    );
+    let frame = film.frame();
+    let width = T::from(frame.max.x - frame.min.x).unwrap();
+    let height = T::from(frame.max.y - frame.min.y).unwrap();
+    let sample = CameraSample {
+        film: film.frame_position(p),
+        lens: sampler.next_2d(),
+        time: sampler.next_1d(),
+    };
+    // The footprint of a sample shrinks as more of them share the pixel
+    let spp = T::from(sampler.samples_per_pixel()).unwrap();
+    let scale = spp.sqrt().recip().max(T::from(0.125).unwrap());
+    let (ray, differential) =
+        camera.generate_ray_differential(&sample, width.recip(), height.recip());
+    (p, ray, differential.scaled(&ray, scale))
+}
+
+impl<T, U, I: Integrator<T, U> + ?Sized> Integrator<T, U> for &I {
+    #[inline]
+    fn li(&self, ray: &Ray<T, U>, scene: &Scene<T, U>, sampler: &mut dyn Sampler<T>) -> Rgb<T> {
+        (**self).li(ray, scene, sampler)
+    }
+
+    #[inline]
+    fn li_differential(
+        &self,
+        ray: &Ray<T, U>,
+        differential: &RayDifferential<T, U>,
+        scene: &Scene<T, U>,
+        sampler: &mut dyn Sampler<T>,
+    ) -> Rgb<T> {
+        (**self).li_differential(ray, differential, scene, sampler)
+    }
+}
+
+/// When the paths of integrators following several bounces are ended
+///
+/// Paths end after `max_depth` scattering events. From `rr_depth` on, paths carrying less than
+/// `rr_threshold` in their brightest channel are also ended by Russian roulette, surviving with
+/// a probability proportional to their throughput and weighted up to stay unbiased.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PathTermination {
+    /// The maximum number of scattering events
+    pub max_depth: usize,
+    /// The number of scattering events before paths may be ended by Russian roulette
+    pub rr_depth: usize,
+    /// The throughput below which paths may be ended, and above which they always survive
+    pub rr_threshold: f32,
+    /// The lowest probability of surviving Russian roulette, which bounds how much the
+    /// survivors are weighted up and so the noise it adds
+    pub min_survival: f32,
+}
+
+impl Default for PathTermination {
+    #[inline]
+    fn default() -> Self {
+        Self::new(5)
+    }
+}
+
+impl PathTermination {
+    #[inline]
+    #[must_use]
+    pub const fn new(max_depth: usize) -> Self {
+        Self {
+            max_depth,
+            rr_depth: 3,
+            rr_threshold: 1.,
+            min_survival: 0.,
+        }
+    }
+
+    /// Never ends paths by Russian roulette, only at the maximum depth
+    #[inline]
+    #[must_use]
+    pub const fn without_roulette(mut self) -> Self {
+        self.rr_depth = usize::MAX;
+        self
+    }
+
+    /// Whether a path has scattered as many times as it may
+    #[inline]
+    #[must_use]
+    pub const fn reached_max_depth(&self, depth: usize) -> bool {
+        depth >= self.max_depth
+    }
+
+    /// Returns the probability of a path surviving Russian roulette after `depth` scattering
+    /// events, with the throughput `beta`
+    #[must_use]
+    pub fn survival_probability<T: Float>(&self, depth: usize, beta: Rgb<T>) -> T {
+        let threshold = T::from(self.rr_threshold).unwrap();
+        let brightest = beta.max_component();
+        if depth < self.rr_depth || brightest >= threshold {
+            return T::one();
+        }
+        (brightest / threshold)
+            .max(T::from(self.min_survival).unwrap())
+            .min(T::one())
+    }
+
+    /// Decides whether a path continues by Russian roulette, weighting it up if so
+    ///
+    /// A sample is only drawn from the sampler if the path may be ended.
+    pub fn roulette<T: Float>(
+        &self,
+        depth: usize,
+        beta: &mut Rgb<T>,
+        sampler: &mut dyn Sampler<T>,
+    ) -> bool {
+        let survival = self.survival_probability(depth, *beta);
+        if survival >= T::one() {
+            return true;
+        }
+        if sampler.next_1d() >= survival {
+            return false;
+        }
+        *beta /= survival;
+        true
+    }
+}
+
+/// The fraction of the way to their target that shadow rays stop short of, as the surface
+/// there may not be offset
+const SHADOW_EPSILON: f64 = 1e-4;
+
+/// Returns the ray leaving the surface in the direction `dir`
+#[inline]
+fn spawn_ray<T: Float, U>(si: &SurfaceInteraction<T, U>, dir: Vector3<T, U>) -> Ray<T, U> {
+    Ray::with_time(si.offset_origin(dir), dir, si.time)
+}
+
+/// Returns the ray leaving the surface towards `p`, and the parameter just before reaching it
+#[inline]
+fn spawn_ray_to<T: Float, U>(
+    si: &SurfaceInteraction<T, U>,
+    p: Point3<T, U>,
+) -> (Ray<T, U>, Time<T>) {
+    let origin = si.offset_origin(p - si.p);
+    let t_max = T::one() - T::from(SHADOW_EPSILON).unwrap();
+    (Ray::with_time(origin, p - origin, si.time), Time(t_max))
+}
+
+/// Returns the differential of the ray leaving the surface in the direction `wi` by specular
+/// reflection or transmission, given that of the ray which hit it
+///
+/// The offset rays leave from the footprint of the pixel, bent by the shading normal as if it
+/// were the same over the footprint. The relative index of refraction is recovered from how
+/// much the direction bent, which is unknown at normal incidence, so `None` is returned then
+/// and on total internal reflection.
+fn specular_differential<T: Float, U>(
+    si: &SurfaceInteraction<T, U>,
+    differential: &RayDifferential<T, U>,
+    wi: Vector3<T, U>,
+    transmission: bool,
+) -> Option<RayDifferential<T, U>> {
+    let mut n = si.shading.n.to_vector().normalize();
+    let (rx_origin, ry_origin) = (si.p + si.dpdx, si.p + si.dpdy);
+    if !transmission {
+        let reflect = |d: Vector3<T, U>| d - n * (d.dot(n) * (T::one() + T::one()));
+        return Some(RayDifferential {
+            rx_origin,
+            rx_dir: reflect(differential.rx_dir),
+            ry_origin,
+            ry_dir: reflect(differential.ry_dir),
+        });
+    }
+
+    if n.dot(si.wo) < T::zero() {
+        n = -n;
+    }
+    let wo_tangent = (si.wo - n * n.dot(si.wo)).length();
+    let wi_tangent = (wi - n * n.dot(wi)).length();
+    if wo_tangent < T::epsilon().sqrt() || wi_tangent == T::zero() {
+        return None;
+    }
+    let eta = wo_tangent * wi.length() / (wi_tangent * si.wo.length());
+    let refract = |d: Vector3<T, U>| {
+        let d = d.try_normalize()?;
+        let cos_theta_i = -d.dot(n);
+        let sin2_theta_t = (T::one() - cos_theta_i * cos_theta_i).max(T::zero()) / (eta * eta);
+        if sin2_theta_t >= T::one() {
+            return None;
+        }
+        let cos_theta_t = (T::one() - sin2_theta_t).sqrt();
+        Some(d / eta + n * (cos_theta_i / eta - cos_theta_t))
+    };
+    Some(RayDifferential {
+        rx_origin,
+        rx_dir: refract(differential.rx_dir)?,
+        ry_origin,
+        ry_dir: refract(differential.ry_dir)?,
+    })
+}
+
+/// Clamps the light gathered by a path after `bounces` bounces if it is indirect
+#[inline]
+fn clamp_indirect<T: Float>(radiance: Rgb<T>, bounces: usize, max: Option<f32>) -> Rgb<T> {
+    match max {
+        Some(max) if bounces > 1 => radiance.clamp_max_component(T::from(max).unwrap()),
+        _ => radiance,
+    }
+}
+
+/// Estimates the light arriving directly at the surface point from a sampled light, and
+/// scattered towards `si.wo`
+fn sample_light<T: Float, U>(
+    scene: &Scene<T, U>,
+    si: &SurfaceInteraction<T, U>,
+    frame: &Frame<T, U>,
+    bsdf: &dyn Bsdf<T>,
+    sampler: &mut dyn Sampler<T>,
+) -> Rgb<T> {
+    match light_sample(scene, si, frame, bsdf, sampler) {
+        Some(shadow) if !scene.intersect_any(&shadow.ray, shadow.t_max) => shadow.radiance,
+        _ => Rgb::black(),
+    }
+}
+
+/// The light sampled at a vertex of a path, which reaches it unless the shadow ray is blocked
+struct ShadowRay<T, U> {
+    ray: Ray<T, U>,
+    t_max: Time<T>,
+    radiance: Rgb<T>,
+}
+
+/// Samples a light like [`sample_light`], returning the shadow ray towards the sampled point
+/// and the light scattered towards `si.wo` unless the ray is blocked
+fn light_sample<T: Float, U>(
+    scene: &Scene<T, U>,
+    si: &SurfaceInteraction<T, U>,
+    frame: &Frame<T, U>,
+    bsdf: &dyn Bsdf<T>,
+    sampler: &mut dyn Sampler<T>,
+) -> Option<ShadowRay<T, U>> {
+    let ul = sampler.next_1d();
+    let u = sampler.next_2d();
+    let sampled = scene.light_sampler().sample(si.p, Some(si.n), ul)?;
+    let light = &scene.lights()[sampled.index];
+    let sample = light.sample_li(si.p, u)?;
+    let (wo, wi) = (frame.to_local(si.wo), frame.to_local(sample.wi));
+    let f = bsdf.f(wo, wi) * wi.z.abs();
+    if f.is_black() {
+        return None;
+    }
+    let (ray, t_max) = spawn_ray_to(si, sample.p);
+
+    let pdf = sampled.pmf * sample.pdf;
+    let weight = if light.is_delta() {
+        T::one()
+    } else {
+        power_heuristic(pdf, bsdf.pdf(wo, wi))
+    };
+    Some(ShadowRay {
+        ray,
+        t_max,
+        radiance: f * sample.radiance * (weight / pdf),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        accel::Bvh,
+        camera::ThinLensCamera,
+        core::{geometry::Box2, units::Angle},
+        sampler::{IndependentSampler, StratifiedSampler},
+        scene::Primitive,
+        shape::Sphere,
+    };
+    use std::sync::Arc;
+
+    /// Sees hit surfaces as white, and the sampled lens position as the background
+    struct Visibility;
+
+    impl Integrator<f32, UnknownUnit> for Visibility {
+        fn li(
+            &self,
+            ray: &Ray<f32, UnknownUnit>,
+            scene: &Scene<f32, UnknownUnit>,
+            sampler: &mut dyn Sampler<f32>,
+        ) -> Rgb<f32> {
+            assert_eq!(sampler.dimension(), 5);
+            if scene.intersect(ray, Time(f32::INFINITY)).is_some() {
+                Rgb::splat(1.)
+            } else {
+                Rgb::new(0., 0., f32::NAN)
+            }
+        }
+    }
+
+    /// Sees noise averaging to one in the left half of the image, and a constant one elsewhere
+    struct HalfNoise;
+
+    impl Integrator<f32, UnknownUnit> for HalfNoise {
+        fn li(
+            &self,
+            ray: &Ray<f32, UnknownUnit>,
+            _: &Scene<f32, UnknownUnit>,
+            sampler: &mut dyn Sampler<f32>,
+        ) -> Rgb<f32> {
+            let u = sampler.next_1d();
+            Rgb::splat(if ray.dir.x < 0. { 2. * u } else { 1. })
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let sphere = Arc::new(Sphere::new(Point3::new(0., 0., -5.), 1.));
+        let scene = Scene::new(Bvh::new(vec![Primitive::new(sphere, None)]), Vec::new());
+        let camera = ThinLensCamera::look_at(
+            Point3::origin(),
+            Point3::new(0., 0., -1.),
+            Vector3::new(0., 1., 0.),
+            Angle::from_degrees(40.),
+            1.,
+        );
+        let film = Film::new(20, 20);
+        Visibility.render(
+            &scene,
+            &camera,
+            &StratifiedSampler::new(2, 2, true, 0),
+            &film,
+            &RenderSettings::new().with_tile_size(7),
+        );
+        let image = film.resolve();
+        assert_eq!(image.get(Point2::new(10, 10)), Some(&Rgb::splat(1.)));
+        // Background samples are all discarded
+        assert_eq!(image.get(Point2::new(0, 0)), Some(&Rgb::black()));
+
+        // Only a pixel in the middle of the frame and the overscan around it are rendered,
+        // which all see the sphere
+        let window = Box2::new(Point2::new(0.5, 0.5), Point2::new(0.55, 0.55));
+        let film = Film::new(20, 20).with_overscan(2).with_crop_window(window);
+        let sampler = StratifiedSampler::new(2, 2, true, 0);
+        Visibility.render(&scene, &camera, &sampler, &film, &RenderSettings::new());
+        let statistics = film.statistics();
+        let samples = |x: usize, y: usize| statistics[y * 24 + x].samples;
+        assert_eq!(
+            (samples(12, 12), samples(10, 12), samples(16, 12)),
+            (4, 4, 0)
+        );
+        assert_eq!(
+            film.resolve().get(Point2::new(10, 12)),
+            Some(&Rgb::splat(1.))
+        );
+    }
+
+    #[test]
+    fn test_deterministic_render() {
+        let scene = Scene::new(Bvh::new(Vec::new()), Vec::new());
+        let camera = ThinLensCamera::look_at(
+            Point3::origin(),
+            Point3::new(0., 0., -1.),
+            Vector3::new(0., 1., 0.),
+            Angle::from_degrees(40.),
+            1.,
+        );
+        let sampler = IndependentSampler::new(4, 0);
+        let render = |settings: RenderSettings| {
+            let film = Film::new(23, 9);
+            HalfNoise.render(&scene, &camera, &sampler, &film, &settings);
+            film.resolve()
+        };
+        let image = render(RenderSettings::new().with_seed(7));
+        for settings in [
+            RenderSettings::new().with_threads(1),
+            RenderSettings::new().with_threads(5).with_tile_size(3),
+            RenderSettings::new().with_tile_size(100),
+        ] {
+            assert_eq!(render(settings.with_seed(7)), image);
+        }
+        assert_ne!(render(RenderSettings::new().with_seed(8)), image);
+        // Without a seed in the settings, that of the sampler is kept
+        let sampler = IndependentSampler::new(4, 7);
+        let film = Film::new(23, 9);
+        HalfNoise.render(&scene, &camera, &sampler, &film, &RenderSettings::new());
+        assert_eq!(film.resolve(), image);
+    }
+
+    #[test]
+    fn test_render_progressive() {
+        let scene = Scene::new(Bvh::new(Vec::new()), Vec::new());
+        let camera = ThinLensCamera::look_at(
+            Point3::origin(),
+            Point3::new(0., 0., -1.),
+            Vector3::new(0., 1., 0.),
+            Angle::from_degrees(40.),
+            1.,
+        );
+        let sampler = IndependentSampler::new(16, 0);
+        let settings = RenderSettings::new();
+        let film = Film::new(6, 4);
+        let mut passes = Vec::new();
+        let taken = HalfNoise.render_progressive(
+            &scene,
+            &camera,
+            &sampler,
+            &film,
+            &settings,
+            5,
+            |taken, image| {
+                assert_eq!(film.statistics()[0].samples, taken);
+                passes.push((taken, image.clone()));
+                ControlFlow::Continue(())
+            },
+        );
+        assert_eq!(taken, 16);
+        assert_eq!(
+            passes.iter().map(|p| p.0).collect::<Vec<_>>(),
+            [5, 10, 15, 16]
+        );
+        assert_ne!(passes[0].1, passes[3].1);
+
+        // The passes take the same samples as a single render
+        let single = Film::new(6, 4);
+        HalfNoise.render(&scene, &camera, &sampler, &single, &settings);
+        for (a, b) in single.resolve().pixels().iter().zip(passes[3].1.pixels()) {
+            assert!((a.r - b.r).abs() < 1e-5);
+        }
+
+        // As do renders split over calls
+        let split = Film::new(6, 4);
+        for samples in [0..3, 3..11, 11..20] {
+            HalfNoise.render_samples(&scene, &camera, &sampler, &split, &settings, samples);
+        }
+        assert_eq!(split.statistics()[0].samples, 16);
+        for (a, b) in single
+            .resolve()
+            .pixels()
+            .iter()
+            .zip(split.resolve().pixels())
+        {
+            assert!((a.r - b.r).abs() < 1e-5);
+        }
+
+        // Rendering stops when the callback breaks
+        let film = Film::new(6, 4);
+        let stop = |_, _: &Image<f32>| ControlFlow::Break(());
+        let taken =
+            HalfNoise.render_progressive(&scene, &camera, &sampler, &film, &settings, 5, stop);
+        assert_eq!(taken, 5);
+        assert!(film.statistics().iter().all(|stats| stats.samples == 5));
+    }
+
+    #[test]
+    fn test_render_resumable() {
+        let scene = Scene::new(Bvh::new(Vec::new()), Vec::new());
+        let camera = ThinLensCamera::look_at(
+            Point3::origin(),
+            Point3::new(0., 0., -1.),
+            Vector3::new(0., 1., 0.),
+            Angle::from_degrees(40.),
+            1.,
+        );
+        let sampler = IndependentSampler::new(12, 0);
+        let settings = RenderSettings::new().with_seed(3);
+        let path = std::env::temp_dir().join(format!("rt3-checkpoint-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        // A render interrupted after its first pass, by rendering fewer samples
+        let film = Film::new(6, 4);
+        let interrupted = IndependentSampler::new(5, 0);
+        let taken =
+            HalfNoise.render_resumable(&scene, &camera, &interrupted, &film, &settings, 5, &path);
+        assert_eq!(taken.unwrap(), 5);
+
+        // Resuming takes the remaining samples only, the same as an uninterrupted render
+        let resumed = Film::new(6, 4);
+        let taken =
+            HalfNoise.render_resumable(&scene, &camera, &sampler, &resumed, &settings, 5, &path);
+        assert_eq!(taken.unwrap(), 12);
+        assert!(resumed.statistics().iter().all(|stats| stats.samples == 12));
+        let single = Film::new(6, 4);
+        HalfNoise.render(&scene, &camera, &sampler, &single, &settings);
+        for (a, b) in single
+            .resolve()
+            .pixels()
+            .iter()
+            .zip(resumed.resolve().pixels())
+        {
+            assert!((a.r - b.r).abs() < 1e-5);
+        }
+
+        // Checkpoints of another film are not resumed from
+        let other = Film::new(4, 4);
+        let result =
+            HalfNoise.render_resumable(&scene, &camera, &sampler, &other, &settings, 5, &path);
+        assert!(matches!(result, Err(CheckpointError::FilmMismatch)));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_path_termination() {
+        let termination = PathTermination {
+            rr_threshold: 0.5,
+            min_survival: 0.125,
+            ..PathTermination::new(8)
+        };
+        assert!(!termination.reached_max_depth(7));
+        assert!(termination.reached_max_depth(8));
+        let survival = |depth, beta| termination.survival_probability(depth, Rgb::splat(beta));
+        assert_eq!(survival(2, 0.01), 1.);
+        assert_eq!(survival(3, 0.6), 1.);
+        assert_eq!(survival(3, 0.25), 0.5);
+        assert_eq!(survival(3, 0.01), 0.125);
+        assert_eq!(
+            termination
+                .without_roulette()
+                .survival_probability(100, Rgb::<f64>::black()),
+            1.
+        );
+
+        // Survivors are weighted up so that the expected throughput is unchanged
+        let mut sampler = IndependentSampler::new(1, 0);
+        let n = 100_000;
+        let mut sum = 0.;
+        for _ in 0..n {
+            let mut beta = Rgb::splat(0.2);
+            if termination.roulette(3, &mut beta, &mut sampler) {
+                sum += beta.r;
+            }
+        }
+        assert!((sum / f64::from(n) - 0.2).abs() < 0.005);
+    }
+
+    #[test]
+    fn test_render_adaptive() {
+        let scene = Scene::new(Bvh::new(Vec::new()), Vec::new());
+        let camera = ThinLensCamera::look_at(
+            Point3::origin(),
+            Point3::new(0., 0., -1.),
+            Vector3::new(0., 1., 0.),
+            Angle::from_degrees(40.),
+            1.,
+        );
+        let film = Film::new(4, 2);
+        let sampler = StratifiedSampler::new(2, 2, true, 0);
+        let settings = RenderSettings::default();
+        HalfNoise.render_adaptive(&scene, &camera, &sampler, &film, &settings, 0.05, 64);
+
+        // Noisy pixels take every sample allowed, and the others stop after the first batch
+        let statistics = film.statistics();
+        assert_eq!(statistics[4].samples, 64);
+        assert_eq!(statistics[3].samples, 4);
+        assert_eq!(statistics[3].relative_error(), 0.);
+        assert!((statistics[4].mean - 1.).abs() < 0.2);
+    }
+}
@@ -0,0 +1,258 @@
+use crate::{
+    bsdf::{BsdfFlags, Frame},
+    color::Rgb,
+    core::{
+        geometry::{Point3, Ray, RayDifferential},
+        prelude::Normal3,
+        units::Time,
+    },
+    integrator::{
+        clamp_indirect, light_sample, spawn_ray, specular_differential, Integrator,
+        PathTermination, ShadowRay,
+    },
+    light::Light,
+    sampler::Sampler,
+    sampling::power_heuristic,
+    scene::Scene,
+    shape::SurfaceInteraction,
+};
+use num_traits::Float;
+
+/// Unidirectional path tracing, with next event estimation
+///
+/// At every bounce, a light is sampled and its contribution combined with that of the direction
+/// sampled from the BSDF by multiple importance sampling. Paths are ended by Russian roulette
+/// once they have carried little energy for a few bounces.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct PathIntegrator {
+    /// When paths are ended, counting bounces
+    pub termination: PathTermination,
+    /// The largest value of any channel of the light gathered at each bounce after the first,
+    /// which suppresses fireflies of indirect light at the cost of losing some of it
+    pub max_indirect: Option<f32>,
+}
+
+impl PathIntegrator {
+    #[inline]
+    #[must_use]
+    pub const fn new(max_depth: usize) -> Self {
+        Self {
+            termination: PathTermination::new(max_depth),
+            max_indirect: None,
+        }
+    }
+
+    /// Follows a path from the camera, with the differential of the camera ray as long as the
+    /// path only bounces specularly
+    fn trace<T: Float + Send + Sync, U>(
+        &self,
+        ray: &Ray<T, U>,
+        differential: Option<RayDifferential<T, U>>,
+        scene: &Scene<T, U>,
+        sampler: &mut dyn Sampler<T>,
+    ) -> Rgb<T> {
+        let mut path = PathState::new(*ray, differential);
+        while !path.finished {
+            let hit = scene.intersect(&path.ray, Time(T::infinity()));
+            if let Some(shadow) = path.bounce(self, scene, hit, sampler) {
+                if !scene.intersect_any(&shadow.ray, shadow.t_max) {
+                    path.radiance += shadow.radiance;
+                }
+            }
+        }
+        path.radiance
+    }
+}
+
+/// A path traced from the camera one bounce at a time, by [`PathIntegrator`] as well as by
+/// drivers tracing the rays of many paths together
+pub(super) struct PathState<T, U> {
+    /// The ray leaving the last vertex of the path
+    pub ray: Ray<T, U>,
+    /// The differential of the ray, as long as the path only bounced specularly
+    differential: Option<RayDifferential<T, U>>,
+    pub radiance: Rgb<T>,
+    beta: Rgb<T>,
+    depth: usize,
+    /// The BSDF density of the previous bounce and where it happened, for weighting emission
+    bsdf_pdf: Option<T>,
+    scattered_from: (Point3<T, U>, Option<Normal3<T, U>>),
+    specular_bounce: bool,
+    pub finished: bool,
+}
+
+impl<T: Float + Send + Sync, U> PathState<T, U> {
+    #[must_use]
+    pub fn new(ray: Ray<T, U>, differential: Option<RayDifferential<T, U>>) -> Self {
+        Self {
+            ray,
+            differential,
+            radiance: Rgb::black(),
+            beta: Rgb::splat(T::one()),
+            depth: 0,
+            bsdf_pdf: None,
+            scattered_from: (ray.origin, None),
+            specular_bounce: false,
+            finished: false,
+        }
+    }
+
+    /// Gathers the light emitted where the ray of the path hits, if anything, and samples a
+    /// light and the next direction there, returning the shadow ray towards the light
+    ///
+    /// The path is finished once it has escaped or been ended.
+    pub fn bounce(
+        &mut self,
+        integrator: &PathIntegrator,
+        scene: &Scene<T, U>,
+        hit: Option<SurfaceInteraction<T, U>>,
+        sampler: &mut dyn Sampler<T>,
+    ) -> Option<ShadowRay<T, U>> {
+        let (ray, depth, max_indirect) = (self.ray, self.depth, integrator.max_indirect);
+        // Emission found by sampling the BSDF was also sampled from the light at the previous
+        // bounce, unless it was specular
+        let (bsdf_pdf, scattered_from) = (self.bsdf_pdf, self.scattered_from);
+        let specular_bounce = self.specular_bounce;
+        let emission_weight = |index: usize, light: &dyn Light<T, U>| match bsdf_pdf {
+            Some(pdf) if !specular_bounce => {
+                let (p, n) = scattered_from;
+                let pmf = scene.light_sampler().pmf(p, n, index);
+                power_heuristic(pdf, pmf * light.pdf_li(ray.origin, ray.dir))
+            }
+            _ => T::one(),
+        };
+        self.finished = true;
+        let Some(mut si) = hit else {
+            for (index, light) in scene.lights().iter().enumerate() {
+                let le = light.le(&ray);
+                if le.is_black() {
+                    continue;
+                }
+                let le = self.beta * le * emission_weight(index, &**light);
+                self.radiance += clamp_indirect(le, depth, max_indirect);
+            }
+            return None;
+        };
+
+        if let Some(differential) = &self.differential {
+            si.compute_differentials(differential);
+        }
+        let primitive = scene.primitive(&si);
+        if let (Some(light), Some(index)) = (primitive.area_light(), scene.area_light_index(&si)) {
+            let le = light.l(si.n, si.wo);
+            if !le.is_black() {
+                let le = self.beta * le * emission_weight(index, &**light);
+                self.radiance += clamp_indirect(le, depth, max_indirect);
+            }
+        }
+
+        let material = primitive.material()?;
+        if integrator.termination.reached_max_depth(depth) {
+            return None;
+        }
+        let bsdf = material.bsdf(&si);
+        let frame = Frame::from_interaction(&si);
+        let wo = frame.to_local(si.wo);
+
+        let shadow = if bsdf.flags().is_non_specular() {
+            light_sample(scene, &si, &frame, &*bsdf, sampler).map(|shadow| ShadowRay {
+                radiance: clamp_indirect(self.beta * shadow.radiance, depth + 1, max_indirect),
+                ..shadow
+            })
+        } else {
+            None
+        };
+
+        let uc = sampler.next_1d();
+        let u = sampler.next_2d();
+        let Some(sample) = bsdf.sample_f(wo, uc, u) else {
+            return shadow;
+        };
+        self.beta *= sample.f * (sample.wi.z.abs() / sample.pdf);
+        self.specular_bounce = sample.flags.is_specular();
+        let wi = frame.from_local(sample.wi);
+        self.differential = self
+            .differential
+            .filter(|_| self.specular_bounce)
+            .and_then(|d| {
+                let transmission = sample.flags.contains(BsdfFlags::TRANSMISSION);
+                specular_differential(&si, &d, wi, transmission)
+            });
+        self.ray = spawn_ray(&si, wi);
+        self.bsdf_pdf = Some(sample.pdf);
+        self.scattered_from = (si.p, Some(si.n));
+
+        self.finished = !integrator
+            .termination
+            .roulette(depth, &mut self.beta, sampler);
+        self.depth += 1;
+        shadow
+    }
+}
+
+impl<T: Float + Send + Sync, U> Integrator<T, U> for PathIntegrator {
+    #[inline]
+    fn li(&self, ray: &Ray<T, U>, scene: &Scene<T, U>, sampler: &mut dyn Sampler<T>) -> Rgb<T> {
+        self.trace(ray, None, scene, sampler)
+    }
+
+    #[inline]
+    fn li_differential(
+        &self,
+        ray: &Ray<T, U>,
+        differential: &RayDifferential<T, U>,
+        scene: &Scene<T, U>,
+        sampler: &mut dyn Sampler<T>,
+    ) -> Rgb<T> {
+        self.trace(ray, Some(*differential), scene, sampler)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        accel::Bvh,
+        core::geometry::{Point2, Point3, UnknownUnit, Vector3},
+        light::DiffuseAreaLight,
+        material::DiffuseMaterial,
+        sampler::IndependentSampler,
+        scene::Primitive,
+        shape::{SampleShape, Sphere},
+    };
+    use std::sync::Arc;
+
+    #[test]
+    fn test_furnace() {
+        // Inside a sphere that emits and reflects half of the light, radiance converges to
+        // 1 / (1 - 0.5) everywhere
+        let sphere: Arc<dyn SampleShape<f64, UnknownUnit> + Send + Sync> =
+            Arc::new(Sphere::new(Point3::new(0.2, 0., 0.), 1.));
+        let mut light = DiffuseAreaLight::new(Arc::clone(&sphere), Rgb::splat(1.));
+        light.two_sided = true;
+        let material = Arc::new(DiffuseMaterial::new(Rgb::splat(0.5)));
+        let primitive = Primitive::emissive(sphere, Some(material), light);
+        let scene = Scene::new(Bvh::new(vec![primitive]), Vec::new());
+
+        let integrator = PathIntegrator::new(100);
+        // Clamping all indirect light leaves the emission and a single bounce, 1 + 0.5
+        let direct = PathIntegrator {
+            max_indirect: Some(0.),
+            ..integrator
+        };
+        let mut sampler = IndependentSampler::new(1, 7);
+        let n = 4000;
+        let (mut sum, mut direct_sum) = (Rgb::black(), Rgb::black());
+        for i in 0..n {
+            sampler.start_pixel_sample(Point2::new(0, 0), i, 0);
+            let dir = Vector3::new(1., (i % 7) as f64 - 3., 0.5);
+            let ray = Ray::new(Point3::origin(), dir.normalize());
+            sum += integrator.li(&ray, &scene, &mut sampler);
+            direct_sum += direct.li(&ray, &scene, &mut sampler);
+        }
+        let mean = sum / n as f64;
+        assert!((mean.g - 2.).abs() < 0.05, "{mean:?}");
+        let mean = direct_sum / n as f64;
+        assert!((mean.g - 1.5).abs() < 0.05, "{mean:?}");
+    }
+}
@@ -0,0 +1,161 @@
+use crate::{
+    film::{Film, FilmTile},
+    integrator::TILE_SIZE,
+    sampler::Sampler,
+};
+use num_traits::Float;
+use rayon::prelude::*;
+
+/// How a render is split into tiles and spread over threads
+///
+/// Tiles are handed out by rayon, whose threads each take the next tile as they finish one and
+/// steal from the others when they run out, so that threads stay busy when some parts of the
+/// image cost more than others. Each tile is rendered into its own [`FilmTile`] and merged into
+/// the film when done.
+///
+/// Renders are the same to the bit for the same seed, whatever the number of threads and the
+/// order in which they take the tiles, since samplers start every pixel afresh and pixels
+/// belong to a single tile.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RenderSettings {
+    /// The size of the square tiles, in pixels
+    pub tile_size: usize,
+    /// The number of threads rendering, or none for those of rayon's global pool
+    pub threads: Option<usize>,
+    /// The seed replacing that of the sampler, or none to keep it
+    pub seed: Option<u64>,
+}
+
+impl Default for RenderSettings {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RenderSettings {
+    /// Renders tiles of [`TILE_SIZE`] on rayon's global pool
+    #[inline]
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            tile_size: TILE_SIZE,
+            threads: None,
+            seed: None,
+        }
+    }
+
+    /// Panics if `tile_size` is zero.
+    #[inline]
+    #[must_use]
+    pub const fn with_tile_size(mut self, tile_size: usize) -> Self {
+        assert!(tile_size > 0, "tiles must not be empty");
+        self.tile_size = tile_size;
+        self
+    }
+
+    /// Renders on a pool of its own with as many threads
+    ///
+    /// Panics if `threads` is zero.
+    #[inline]
+    #[must_use]
+    pub const fn with_threads(mut self, threads: usize) -> Self {
+        assert!(threads > 0, "renders need a thread");
+        self.threads = Some(threads);
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Returns the sampler of a tile, a clone of `sampler` with the seed of the settings
+    #[must_use]
+    pub fn tile_sampler<T, S: Sampler<T> + Clone>(&self, sampler: &S) -> S {
+        let mut sampler = sampler.clone();
+        if let Some(seed) = self.seed {
+            sampler.reseed(seed);
+        }
+        sampler
+    }
+
+    /// Runs `op` on the threads of the render, so that the parallel iterators within it use
+    /// them
+    ///
+    /// On WebAssembly, where threads can only be started as web workers handed to rayon's
+    /// global pool, if at all, renders that cannot start threads of their own run on that pool,
+    /// which is the calling thread alone without workers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the threads cannot be started on other targets.
+    pub fn install<R: Send>(&self, op: impl FnOnce() -> R + Send) -> R {
+        let Some(threads) = self.threads else {
+            return op();
+        };
+        match rayon::ThreadPoolBuilder::new().num_threads(threads).build() {
+            Ok(pool) => pool.install(op),
+            Err(_) if cfg!(target_family = "wasm") => op(),
+            Err(e) => panic!("failed to start the render threads: {e}"),
+        }
+    }
+
+    /// Renders every tile of the film in parallel with `render_tile`, merging each into the
+    /// film once rendered
+    pub fn render_tiles<T: Float + Send + Sync>(
+        &self,
+        film: &Film<T>,
+        render_tile: impl Fn(&mut FilmTile<T>) + Sync,
+    ) {
+        let tiles = film.tiles(self.tile_size);
+        self.install(|| {
+            tiles.into_par_iter().for_each(|bounds| {
+                let mut tile = film.tile(bounds);
+                render_tile(&mut tile);
+                film.merge_tile(tile);
+            });
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        color::Rgb,
+        core::geometry::{Point2, UnknownUnit},
+    };
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_render_tiles() {
+        let film = Film::<f64>::new(37, 21);
+        let tiles = AtomicUsize::new(0);
+        for settings in [
+            RenderSettings::new(),
+            RenderSettings::new().with_tile_size(1).with_threads(1),
+            RenderSettings::new().with_tile_size(7).with_threads(3),
+            RenderSettings::new().with_tile_size(64),
+        ] {
+            settings.render_tiles(&film, |tile| {
+                let bounds = tile.bounds();
+                for y in bounds.min.y..bounds.max.y {
+                    for x in bounds.min.x..bounds.max.x {
+                        let p = Point2::<_, UnknownUnit>::new(x as f64 + 0.5, y as f64 + 0.5);
+                        tile.add_sample(p, Rgb::splat(1.), 1.);
+                    }
+                }
+                if let Some(n) = settings.threads {
+                    assert_eq!(rayon::current_num_threads(), n);
+                }
+                tiles.fetch_add(1, Ordering::Relaxed);
+            });
+        }
+        // Every pixel is covered by exactly one tile of each render
+        assert!(film.statistics().iter().all(|stats| stats.samples == 4));
+        assert_eq!(tiles.load(Ordering::Relaxed), 3 * 2 + 37 * 21 + 6 * 3 + 1);
+    }
+}
@@ -0,0 +1,413 @@
+use crate::{
+    bsdf::Frame,
+    color::Rgb,
+    core::{
+        geometry::{Point3, Ray, Vector3},
+        units::Time,
+    },
+    integrator::{clamp_indirect, spawn_ray, Integrator, PathTermination, SHADOW_EPSILON},
+    light::Light,
+    medium::{Medium, MediumInterface},
+    sampler::{hash, Pcg32, Sampler},
+    sampling::power_heuristic,
+    scene::Scene,
+    shape::SurfaceInteraction,
+};
+use num_traits::{Float, FloatConst};
+use std::sync::Arc;
+
+/// Unidirectional path tracing through participating media
+///
+/// Distances to collisions in media are sampled by delta tracking against their majorants, so
+/// that paths are absorbed, scattered or pass on in proportion to the medium's coefficients,
+/// and the transmittance of shadow rays is estimated by ratio tracking. Surfaces without a
+/// material which separate media are passed through.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct VolPathIntegrator {
+    /// When paths are ended, counting scattering events at surfaces and in media
+    pub termination: PathTermination,
+    /// The largest value of any channel of the light gathered at each bounce after the first,
+    /// which suppresses fireflies of indirect light at the cost of losing some of it
+    pub max_indirect: Option<f32>,
+}
+
+impl VolPathIntegrator {
+    #[inline]
+    #[must_use]
+    pub const fn new(max_depth: usize) -> Self {
+        Self {
+            termination: PathTermination::new(max_depth),
+            max_indirect: None,
+        }
+    }
+}
+
+/// What happens to a path travelling through a medium
+enum Tracking<T, U> {
+    Absorbed,
+    /// The path scattered at a point, with the weight of the throughput of each channel
+    Scattered(Point3<T, U>, Rgb<T>),
+    /// The path reached the end of the ray, with the weight of each channel
+    Passed(Rgb<T>),
+}
+
+impl<T: Float + FloatConst + Send + Sync, U> Integrator<T, U> for VolPathIntegrator {
+    fn li(&self, ray: &Ray<T, U>, scene: &Scene<T, U>, sampler: &mut dyn Sampler<T>) -> Rgb<T> {
+        let mut radiance = Rgb::black();
+        let mut beta = Rgb::splat(T::one());
+        let mut ray = *ray;
+        let mut medium = scene.medium().cloned();
+        // The density of the direction sampled at the previous scattering event, and where it
+        // happened, for weighting emission
+        let mut bsdf_pdf = None;
+        let mut scattered_from = (ray.origin, None);
+        let mut specular_bounce = false;
+        // Collisions in media take an unbounded number of random values, which are drawn from a
+        // generator seeded by the sampler
+        let seed = sampler.next_1d().to_f64().unwrap_or(0.) * 2_f64.powi(32);
+        let mut rng = Pcg32::new(hash(&[seed as u64]));
+        let mut depth = 0;
+
+        loop {
+            let emission_weight = |index: usize, light: &dyn Light<T, U>| match bsdf_pdf {
+                Some(pdf) if !specular_bounce => {
+                    let (p, n) = scattered_from;
+                    let pmf = scene.light_sampler().pmf(p, n, index);
+                    power_heuristic(pdf, pmf * light.pdf_li(p, ray.dir))
+                }
+                _ => T::one(),
+            };
+            let hit = scene.intersect(&ray, Time(T::infinity()));
+            if let Some(medium) = &medium {
+                let t_max = hit.map_or(T::infinity(), |si| si.t.0);
+                match track(&**medium, &ray, t_max, &mut rng) {
+                    Tracking::Absorbed => break,
+                    Tracking::Passed(weight) => beta *= weight,
+                    Tracking::Scattered(p, weight) => {
+                        beta *= weight;
+                        if self.termination.reached_max_depth(depth) {
+                            break;
+                        }
+                        depth += 1;
+                        let phase = medium.phase();
+                        let wo = -ray.dir.normalize();
+                        let f = |wi| {
+                            let p = phase.p(wo, wi);
+                            (Rgb::splat(p), phase.pdf(wo, wi))
+                        };
+                        let media = |_| Some(medium);
+                        let ld =
+                            sample_light(scene, p, None, ray.time, media, f, sampler, &mut rng);
+                        radiance += clamp_indirect(beta * ld, depth, self.max_indirect);
+
+                        let Some(sample) = phase.sample_p(wo, sampler.next_2d()) else {
+                            break;
+                        };
+                        beta *= sample.p / sample.pdf;
+                        ray = Ray::with_time(p, sample.wi, ray.time);
+                        scattered_from = (p, None);
+                        bsdf_pdf = Some(sample.pdf);
+                        specular_bounce = false;
+                        if !self.termination.roulette(depth, &mut beta, sampler) {
+                            break;
+                        }
+                        continue;
+                    }
+                }
+            }
+
+            let Some(si) = hit else {
+                for (index, light) in scene.lights().iter().enumerate() {
+                    let le = light.le(&ray);
+                    if !le.is_black() {
+                        let le = beta * le * emission_weight(index, &**light);
+                        radiance += clamp_indirect(le, depth, self.max_indirect);
+                    }
+                }
+                break;
+            };
+            let primitive = scene.primitive(&si);
+            if let (Some(light), Some(index)) =
+                (primitive.area_light(), scene.area_light_index(&si))
+            {
+                let le = light.l(si.n, si.wo);
+                if !le.is_black() {
+                    let le = beta * le * emission_weight(index, &**light);
+                    radiance += clamp_indirect(le, depth, self.max_indirect);
+                }
+            }
+
+            let interface = primitive.medium_interface();
+            let Some(material) = primitive.material() else {
+                // Boundaries between media are crossed without scattering
+                let Some(interface) = interface else {
+                    break;
+                };
+                medium = interface.medium(si.n, ray.dir).cloned();
+                ray = spawn_ray(&si, ray.dir);
+                continue;
+            };
+            if self.termination.reached_max_depth(depth) {
+                break;
+            }
+            depth += 1;
+            let bsdf = material.bsdf(&si);
+            let frame = Frame::from_interaction(&si);
+            let wo = frame.to_local(si.wo);
+
+            if bsdf.flags().is_non_specular() {
+                let f = |wi| {
+                    let wi = frame.to_local(wi);
+                    (bsdf.f(wo, wi) * wi.z.abs(), bsdf.pdf(wo, wi))
+                };
+                let media = |wi| entered(interface, &si, wi, medium.as_ref());
+                let ld = sample_light(scene, si.p, Some(&si), si.time, media, f, sampler, &mut rng);
+                radiance += clamp_indirect(beta * ld, depth, self.max_indirect);
+            }
+
+            let uc = sampler.next_1d();
+            let u = sampler.next_2d();
+            let Some(sample) = bsdf.sample_f(wo, uc, u) else {
+                break;
+            };
+            beta *= sample.f * (sample.wi.z.abs() / sample.pdf);
+            specular_bounce = sample.flags.is_specular();
+            ray = spawn_ray(&si, frame.from_local(sample.wi));
+            scattered_from = (ray.origin, Some(si.n));
+            bsdf_pdf = Some(sample.pdf);
+            medium = entered(interface, &si, ray.dir, medium.as_ref()).cloned();
+            if !self.termination.roulette(depth, &mut beta, sampler) {
+                break;
+            }
+        }
+        radiance
+    }
+}
+
+/// Returns the medium a direction leaving a surface enters, which is that of the ray unless
+/// the surface separates media
+#[inline]
+fn entered<'a, T: Float, U>(
+    interface: Option<&'a MediumInterface<T, U>>,
+    si: &SurfaceInteraction<T, U>,
+    w: Vector3<T, U>,
+    medium: Option<&'a Arc<dyn Medium<T, U>>>,
+) -> Option<&'a Arc<dyn Medium<T, U>>> {
+    match interface {
+        Some(interface) => interface.medium(si.n, w),
+        None => medium,
+    }
+}
+
+/// Samples where a path collides with the medium along a ray, up to the ray parameter `t_max`,
+/// by delta tracking
+///
+/// The kind of each collision is chosen from the average coefficients over the channels, and
+/// the channels weighted by how their own coefficients differ.
+fn track<T: Float, U>(
+    medium: &dyn Medium<T, U>,
+    ray: &Ray<T, U>,
+    t_max: T,
+    rng: &mut Pcg32,
+) -> Tracking<T, U> {
+    let length = ray.dir.length();
+    let ray = Ray {
+        dir: ray.dir / length,
+        ..*ray
+    };
+    let average = |c: Rgb<T>| (c.r + c.g + c.b) / T::from(3).unwrap();
+    let mut beta = Rgb::splat(T::one());
+    for segment in medium.majorants(&ray, t_max * length) {
+        let sigma_maj = segment.sigma_maj;
+        if sigma_maj <= T::zero() {
+            continue;
+        }
+        let mut t = segment.t_min;
+        loop {
+            t = t - (T::one() - rng.uniform::<T>()).ln() / sigma_maj;
+            if t >= segment.t_max {
+                break;
+            }
+            let p = ray.at(Time(t));
+            let properties = medium.properties(p);
+            let p_absorb = average(properties.sigma_a) / sigma_maj;
+            let p_scatter = average(properties.sigma_s) / sigma_maj;
+            let u = rng.uniform::<T>();
+            if u < p_absorb {
+                return Tracking::Absorbed;
+            }
+            if u < p_absorb + p_scatter {
+                beta *= properties.sigma_s / average(properties.sigma_s);
+                return Tracking::Scattered(p, beta);
+            }
+            let p_null = T::one() - p_absorb - p_scatter;
+            let sigma_n = Rgb::splat(sigma_maj) - properties.sigma_t();
+            beta *= sigma_n / (sigma_maj * p_null);
+        }
+    }
+    Tracking::Passed(beta)
+}
+
+/// Estimates the light arriving at `p` directly from a sampled light, through media and their
+/// boundaries, and scattered by `f`, which returns the scattered fraction of the light from a
+/// direction and the density with which that direction is otherwise sampled
+#[allow(clippy::too_many_arguments)]
+fn sample_light<'a, T: Float + 'a, U: 'a>(
+    scene: &Scene<T, U>,
+    p: Point3<T, U>,
+    si: Option<&SurfaceInteraction<T, U>>,
+    time: T,
+    medium: impl Fn(Vector3<T, U>) -> Option<&'a Arc<dyn Medium<T, U>>>,
+    f: impl Fn(Vector3<T, U>) -> (Rgb<T>, T),
+    sampler: &mut dyn Sampler<T>,
+    rng: &mut Pcg32,
+) -> Rgb<T> {
+    let ul = sampler.next_1d();
+    let u = sampler.next_2d();
+    let Some(sampled) = scene.light_sampler().sample(p, si.map(|si| si.n), ul) else {
+        return Rgb::black();
+    };
+    let light = &scene.lights()[sampled.index];
+    let Some(sample) = light.sample_li(p, u) else {
+        return Rgb::black();
+    };
+    let (f, scatter_pdf) = f(sample.wi);
+    if f.is_black() {
+        return Rgb::black();
+    }
+    let origin = si.map_or(p, |si| si.offset_origin(sample.p - si.p));
+    let medium = medium(sample.wi).cloned();
+    let tr = transmittance_to(scene, origin, sample.p, time, medium, rng);
+    if tr.is_black() {
+        return Rgb::black();
+    }
+
+    let pdf = sampled.pmf * sample.pdf;
+    let weight = if light.is_delta() {
+        T::one()
+    } else {
+        power_heuristic(pdf, scatter_pdf)
+    };
+    f * tr * sample.radiance * (weight / pdf)
+}
+
+/// Estimates the transmittance between two points, through the boundaries of media but not
+/// other surfaces, at the moment `time`
+fn transmittance_to<T: Float, U>(
+    scene: &Scene<T, U>,
+    mut origin: Point3<T, U>,
+    target: Point3<T, U>,
+    time: T,
+    mut medium: Option<Arc<dyn Medium<T, U>>>,
+    rng: &mut Pcg32,
+) -> Rgb<T> {
+    let t_max = Time(T::one() - T::from(SHADOW_EPSILON).unwrap());
+    let mut tr = Rgb::splat(T::one());
+    loop {
+        let ray = Ray::with_time(origin, target - origin, time);
+        let hit = scene.intersect(&ray, t_max);
+        if let Some(medium) = &medium {
+            tr *= medium.transmittance(&ray, hit.map_or(t_max, |si| si.t), rng);
+            if tr.is_black() {
+                return tr;
+            }
+        }
+        let Some(si) = hit else {
+            return tr;
+        };
+        let primitive = scene.primitive(&si);
+        let (None, Some(interface)) = (primitive.material(), primitive.medium_interface()) else {
+            return Rgb::black();
+        };
+        medium = interface.medium(si.n, ray.dir).cloned();
+        origin = si.offset_origin(ray.dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        accel::Bvh,
+        core::geometry::{Point2, UnknownUnit},
+        light::DiffuseAreaLight,
+        medium::{
+            HomogeneousMedium, IsotropicPhase, MajorantSegment, MediumProperties, PhaseFunction,
+        },
+        sampler::IndependentSampler,
+        scene::Primitive,
+        shape::{SampleShape, Sphere},
+    };
+
+    /// A medium filling all of space, with a majorant twice its attenuation
+    struct Fog(MediumProperties<f64>);
+
+    impl Medium<f64, UnknownUnit> for Fog {
+        fn properties(&self, _: Point3<f64, UnknownUnit>) -> MediumProperties<f64> {
+            self.0
+        }
+
+        fn majorants(&self, _: &Ray<f64, UnknownUnit>, t_max: f64) -> Vec<MajorantSegment<f64>> {
+            let sigma_maj = 2. * self.0.sigma_t().max_component();
+            vec![MajorantSegment {
+                t_min: 0.,
+                t_max,
+                sigma_maj,
+            }]
+        }
+
+        fn phase(&self) -> &dyn PhaseFunction<f64, UnknownUnit> {
+            &IsotropicPhase
+        }
+    }
+
+    fn mean_radiance(scene: &Scene<f64, UnknownUnit>, ray: Ray<f64, UnknownUnit>) -> Rgb<f64> {
+        let integrator = VolPathIntegrator::new(100);
+        let mut sampler = IndependentSampler::new(1, 5);
+        let n = 10_000;
+        let mut sum = Rgb::black();
+        for i in 0..n {
+            sampler.start_pixel_sample(Point2::new(0, 0), i, 0);
+            sum += integrator.li(&ray, scene, &mut sampler);
+        }
+        sum / n as f64
+    }
+
+    #[test]
+    fn test_media() {
+        // Inside an emitting sphere filled with a medium that only scatters, radiance is the
+        // emitted one everywhere
+        let sphere: Arc<dyn SampleShape<f64, UnknownUnit> + Send + Sync> =
+            Arc::new(Sphere::new(Point3::origin(), 1.));
+        let mut light = DiffuseAreaLight::new(Arc::clone(&sphere), Rgb::splat(1.));
+        light.two_sided = true;
+        let scene = Scene::new(
+            Bvh::new(vec![Primitive::emissive(sphere, None, light)]),
+            Vec::new(),
+        )
+        .with_medium(Arc::new(Fog(MediumProperties {
+            sigma_a: Rgb::black(),
+            sigma_s: Rgb::splat(2.),
+        })));
+        let ray = Ray::new(Point3::new(0.3, 0., 0.), Vector3::new(0., 0.6, 0.8));
+        let mean = mean_radiance(&scene, ray);
+        assert!((mean.g - 1.).abs() < 0.02, "{mean:?}");
+
+        // A ball of absorbing medium in front of a light lets through the transmittance
+        // along its diameter
+        let emitter: Arc<dyn SampleShape<f64, UnknownUnit> + Send + Sync> =
+            Arc::new(Sphere::new(Point3::new(0., 0., -10.), 3.));
+        let light = DiffuseAreaLight::new(Arc::clone(&emitter), Rgb::splat(1.));
+        let fog: Arc<dyn Medium<f64, UnknownUnit>> =
+            Arc::new(HomogeneousMedium::new(Rgb::splat(1.), Rgb::black()));
+        let ball = Primitive::new(Arc::new(Sphere::new(Point3::new(0., 0., -4.), 1.)), None)
+            .with_medium_interface(MediumInterface::new(Some(fog), None));
+        let scene = Scene::new(
+            Bvh::new(vec![Primitive::emissive(emitter, None, light), ball]),
+            Vec::new(),
+        );
+        let ray = Ray::new(Point3::origin(), Vector3::new(0., 0., -1.));
+        let mean = mean_radiance(&scene, ray);
+        assert!((mean.g - (-2_f64).exp()).abs() < 0.015, "{mean:?}");
+    }
+}
@@ -0,0 +1,303 @@
+use crate::{
+    camera::Camera,
+    color::Rgb,
+    core::{
+        geometry::{Box2, Point2, Ray, RayDifferential, UnknownUnit},
+        units::Time,
+    },
+    film::Film,
+    integrator::{
+        camera_ray, path::PathState, Integrator, PathIntegrator, RenderSettings, ShadowRay,
+    },
+    sampler::Sampler,
+    scene::Scene,
+};
+use num_traits::Float;
+use rayon::prelude::*;
+
+/// Path tracing in wavefronts, where each stage of the paths runs over all of them before the
+/// next: generating the camera rays, intersecting them, shading the hits and tracing the
+/// shadow rays towards the lights sampled there
+///
+/// Rays are intersected in large batches through
+/// [`intersect_batch`](crate::accel::Accelerator::intersect_batch), which accelerators tracing
+/// many rays at once, such as on a GPU, are made for, and each stage keeps its code and data
+/// hot over many paths. Paths only carry the position of their pixel sample in the sample
+/// vector, from which the sampler resumes them at every stage.
+///
+/// The paths are those of [`PathIntegrator`], so images are the same as with it, to the bit
+/// for the same seed.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct WavefrontPathIntegrator {
+    /// The path tracer whose paths are traced
+    pub path: PathIntegrator,
+    /// The number of paths traced together, at least those of a tile
+    pub wavefront_size: usize,
+}
+
+impl WavefrontPathIntegrator {
+    #[inline]
+    #[must_use]
+    pub const fn new(max_depth: usize) -> Self {
+        Self {
+            path: PathIntegrator::new(max_depth),
+            wavefront_size: 1 << 16,
+        }
+    }
+}
+
+impl<T: Float + Send + Sync, U: Send + Sync> Integrator<T, U> for WavefrontPathIntegrator {
+    /// Estimates radiance with the path tracer alone
+    #[inline]
+    fn li(&self, ray: &Ray<T, U>, scene: &Scene<T, U>, sampler: &mut dyn Sampler<T>) -> Rgb<T> {
+        self.path.li(ray, scene, sampler)
+    }
+
+    #[inline]
+    fn li_differential(
+        &self,
+        ray: &Ray<T, U>,
+        differential: &RayDifferential<T, U>,
+        scene: &Scene<T, U>,
+        sampler: &mut dyn Sampler<T>,
+    ) -> Rgb<T> {
+        self.path.li_differential(ray, differential, scene, sampler)
+    }
+
+    /// Renders the tiles of the film in wavefronts of about `wavefront_size` paths
+    ///
+    /// Progressive and adaptive renders trace their paths one by one.
+    fn render<C, S>(
+        &self,
+        scene: &Scene<T, U>,
+        camera: &C,
+        sampler: &S,
+        film: &Film<T>,
+        settings: &RenderSettings,
+    ) where
+        Self: Sized,
+        T: Float + Send + Sync,
+        C: Camera<T, U> + Sync,
+        S: Sampler<T> + Clone + Send + Sync,
+    {
+        let tiles = film.tiles(settings.tile_size);
+        let samples = sampler.samples_per_pixel();
+        let paths = |bounds: &Box2<usize, UnknownUnit>| {
+            (bounds.max.x - bounds.min.x) * (bounds.max.y - bounds.min.y) * samples
+        };
+        settings.install(|| {
+            let mut start = 0;
+            while start < tiles.len() {
+                let mut end = start + 1;
+                let mut count = paths(&tiles[start]);
+                while end < tiles.len() && count + paths(&tiles[end]) <= self.wavefront_size {
+                    count += paths(&tiles[end]);
+                    end += 1;
+                }
+                let wavefront = Wavefront {
+                    integrator: &self.path,
+                    scene,
+                    film,
+                    sampler,
+                    settings,
+                };
+                wavefront.render(camera, &tiles[start..end]);
+                start = end;
+            }
+        });
+    }
+}
+
+/// A path of a wavefront, with the pixel sample it estimates
+struct WavefrontPath<T, U> {
+    tile: usize,
+    pixel: Point2<usize, UnknownUnit>,
+    index: usize,
+    /// The next dimension of the sample vector the path uses
+    dimension: usize,
+    /// The position of the sample on the film
+    p: Point2<T, UnknownUnit>,
+    state: PathState<T, U>,
+}
+
+/// What the paths of wavefronts are traced through
+struct Wavefront<'a, T, U, S> {
+    integrator: &'a PathIntegrator,
+    scene: &'a Scene<T, U>,
+    film: &'a Film<T>,
+    sampler: &'a S,
+    settings: &'a RenderSettings,
+}
+
+impl<T, U, S> Wavefront<'_, T, U, S>
+where
+    T: Float + Send + Sync,
+    U: Send + Sync,
+    S: Sampler<T> + Clone + Send + Sync,
+{
+    /// Traces every sample of the tiles, and merges them into the film
+    fn render(&self, camera: &(impl Camera<T, U> + Sync), tiles: &[Box2<usize, UnknownUnit>]) {
+        let samples = self.sampler.samples_per_pixel();
+        let pixel_samples: Vec<_> = tiles
+            .iter()
+            .enumerate()
+            .flat_map(|(tile, bounds)| {
+                (bounds.min.y..bounds.max.y).flat_map(move |y| {
+                    (bounds.min.x..bounds.max.x).flat_map(move |x| {
+                        (0..samples).map(move |index| (tile, Point2::new(x, y), index))
+                    })
+                })
+            })
+            .collect();
+        let mut paths: Vec<_> = pixel_samples
+            .into_par_iter()
+            .map_init(
+                || self.settings.tile_sampler(self.sampler),
+                |sampler, (tile, pixel, index)| {
+                    let (p, ray, differential) =
+                        camera_ray(camera, self.film, sampler, pixel, index);
+                    WavefrontPath {
+                        tile,
+                        pixel,
+                        index,
+                        dimension: sampler.dimension(),
+                        p,
+                        state: PathState::new(ray, Some(differential)),
+                    }
+                },
+            )
+            .collect();
+
+        let mut finished = Vec::with_capacity(paths.len());
+        while !paths.is_empty() {
+            let shadows = self.bounce(&mut paths);
+            self.trace_shadows(&mut paths, shadows);
+            let (done, active) = paths.into_iter().partition(|path| path.state.finished);
+            finished.extend::<Vec<_>>(done);
+            paths = active;
+        }
+
+        // Samples are added in the order tiles take them, so that pixels sum them alike
+        finished.sort_by_key(|path| (path.tile, path.pixel.y, path.pixel.x, path.index));
+        let mut film_tiles: Vec<_> = tiles.iter().map(|&bounds| self.film.tile(bounds)).collect();
+        for path in finished {
+            let radiance = path.state.radiance;
+            if radiance.to_array().iter().all(|c| c.is_finite()) {
+                film_tiles[path.tile].add_sample(path.p, radiance, T::one());
+            }
+        }
+        for tile in film_tiles {
+            self.film.merge_tile(tile);
+        }
+    }
+
+    /// Intersects the rays of the paths and shades their hits, returning the shadow ray of
+    /// each path
+    fn bounce(&self, paths: &mut [WavefrontPath<T, U>]) -> Vec<Option<ShadowRay<T, U>>> {
+        let rays: Vec<_> = paths
+            .iter()
+            .map(|path| (path.state.ray, Time(T::infinity())))
+            .collect();
+        let hits = self.scene.intersect_batch(&rays);
+        paths
+            .par_iter_mut()
+            .zip(hits)
+            .map_init(
+                || self.settings.tile_sampler(self.sampler),
+                |sampler, (path, hit)| {
+                    sampler.start_pixel_sample(path.pixel, path.index, path.dimension);
+                    let shadow = path.state.bounce(self.integrator, self.scene, hit, sampler);
+                    path.dimension = sampler.dimension();
+                    shadow
+                },
+            )
+            .collect()
+    }
+
+    /// Traces the shadow rays of the paths, adding the light of those that are not blocked
+    fn trace_shadows(
+        &self,
+        paths: &mut [WavefrontPath<T, U>],
+        shadows: Vec<Option<ShadowRay<T, U>>>,
+    ) {
+        let shadows: Vec<_> = shadows
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, shadow)| Some((i, shadow?)))
+            .collect();
+        let rays: Vec<_> = shadows
+            .iter()
+            .map(|(_, shadow)| (shadow.ray, shadow.t_max))
+            .collect();
+        let occluded = self.scene.intersect_any_batch(&rays);
+        for ((i, shadow), occluded) in shadows.into_iter().zip(occluded) {
+            if !occluded {
+                paths[i].state.radiance += shadow.radiance;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        accel::Bvh,
+        camera::ThinLensCamera,
+        core::{
+            geometry::{Point3, Vector3},
+            units::Angle,
+        },
+        light::{DiffuseAreaLight, PointLight},
+        material::DiffuseMaterial,
+        sampler::StratifiedSampler,
+        scene::Primitive,
+        shape::{SampleShape, Sphere},
+    };
+    use std::sync::Arc;
+
+    #[test]
+    fn test_wavefront() {
+        // Two spheres lit by a point light and one of them, over an environment of nothing
+        let emitter: Arc<dyn SampleShape<f64, UnknownUnit> + Send + Sync> =
+            Arc::new(Sphere::new(Point3::new(1., 1., -4.), 0.5));
+        let light = DiffuseAreaLight::new(Arc::clone(&emitter), Rgb::splat(2.));
+        let diffuse = Arc::new(DiffuseMaterial::new(Rgb::new(0.7, 0.5, 0.3)));
+        let primitives = vec![
+            Primitive::emissive(emitter, Some(diffuse.clone()), light),
+            Primitive::new(
+                Arc::new(Sphere::new(Point3::new(-0.5, -0.5, -5.), 1.)),
+                Some(diffuse),
+            ),
+        ];
+        let point = PointLight::new(Point3::new(0., 3., -3.), Rgb::splat(5.));
+        let scene = Scene::new(Bvh::new(primitives), vec![Arc::new(point)]);
+        let camera = ThinLensCamera::look_at(
+            Point3::origin(),
+            Point3::new(0., 0., -1.),
+            Vector3::new(0., 1., 0.),
+            Angle::from_degrees(50.),
+            1.,
+        );
+        let sampler = StratifiedSampler::new(2, 2, true, 0);
+
+        // Wavefronts smaller than a tile take one tile each
+        let mut integrator = WavefrontPathIntegrator::new(5);
+        integrator.wavefront_size = 100;
+        for settings in [
+            RenderSettings::new(),
+            RenderSettings::new().with_tile_size(4).with_threads(3),
+        ] {
+            let film = Film::new(18, 14);
+            integrator.render(&scene, &camera, &sampler, &film, &settings);
+            let expected = Film::new(18, 14);
+            integrator
+                .path
+                .render(&scene, &camera, &sampler, &expected, &settings);
+            let image = film.resolve();
+            assert_eq!(image, expected.resolve());
+            assert!(image.pixels().iter().any(|p| p.r > 0.));
+            assert_eq!(film.statistics(), expected.statistics());
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use crate::{
+    bsdf::{BsdfFlags, Frame},
+    color::Rgb,
+    core::{
+        geometry::{Point2, Ray, RayDifferential},
+        units::Time,
+    },
+    integrator::{spawn_ray, spawn_ray_to, specular_differential, Integrator},
+    sampler::Sampler,
+    scene::Scene,
+};
+use num_traits::Float;
+
+/// Classic recursive ray tracing, following perfect reflection and refraction and only lighting
+/// other surfaces directly
+///
+/// Both specular lobes are traced at every bounce rather than sampled, and each light is
+/// sampled once per hit, so images converge with few samples. Light reflected off other
+/// non-specular surfaces is missing.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct WhittedIntegrator {
+    /// The maximum number of specular bounces
+    pub max_depth: usize,
+}
+
+impl Default for WhittedIntegrator {
+    #[inline]
+    fn default() -> Self {
+        Self { max_depth: 5 }
+    }
+}
+
+impl WhittedIntegrator {
+    #[inline]
+    #[must_use]
+    pub const fn new(max_depth: usize) -> Self {
+        Self { max_depth }
+    }
+
+    /// Traces a ray along with its differential, if still known after the specular bounces
+    /// so far
+    fn trace<T: Float + Send + Sync, U>(
+        &self,
+        ray: &Ray<T, U>,
+        differential: Option<&RayDifferential<T, U>>,
+        scene: &Scene<T, U>,
+        sampler: &mut dyn Sampler<T>,
+        depth: usize,
+    ) -> Rgb<T> {
+        let Some(mut si) = scene.intersect(ray, Time(T::infinity())) else {
+            return scene.lights().iter().map(|light| light.le(ray)).sum();
+        };
+        if let Some(differential) = differential {
+            si.compute_differentials(differential);
+        }
+        let primitive = scene.primitive(&si);
+        let mut radiance = primitive
+            .area_light()
+            .map_or_else(Rgb::black, |light| light.l(si.n, si.wo));
+        let Some(material) = primitive.material() else {
+            return radiance;
+        };
+        let bsdf = material.bsdf(&si);
+        let frame = Frame::from_interaction(&si);
+        let wo = frame.to_local(si.wo);
+        let flags = bsdf.flags();
+
+        if flags.is_non_specular() {
+            for light in scene.lights() {
+                let u = sampler.next_2d();
+                let Some(sample) = light.sample_li(si.p, u) else {
+                    continue;
+                };
+                let wi = frame.to_local(sample.wi);
+                let f = bsdf.f(wo, wi) * wi.z.abs();
+                if f.is_black() {
+                    continue;
+                }
+                let (shadow_ray, t_max) = spawn_ray_to(&si, sample.p);
+                if !scene.intersect_any(&shadow_ray, t_max) {
+                    radiance += f * sample.radiance / sample.pdf;
+                }
+            }
+        }
+
+        if flags.is_specular() && depth < self.max_depth {
+            // The lowest and highest lobe choices select reflection and transmission
+            let half = T::from(0.5).unwrap();
+            let lobes = [
+                (T::zero(), BsdfFlags::REFLECTION),
+                (T::one() - T::epsilon(), BsdfFlags::TRANSMISSION),
+            ];
+            for (uc, lobe) in lobes {
+                let Some(sample) = bsdf.sample_f(wo, uc, Point2::new(half, half)) else {
+                    continue;
+                };
+                if !sample.flags.is_specular() || !sample.flags.contains(lobe) {
+                    continue;
+                }
+                let wi = frame.from_local(sample.wi);
+                let ray = spawn_ray(&si, wi);
+                let differential = differential.and_then(|d| {
+                    specular_differential(&si, d, wi, lobe == BsdfFlags::TRANSMISSION)
+                });
+                let li = self.trace(&ray, differential.as_ref(), scene, sampler, depth + 1);
+                radiance += sample.f * li * sample.wi.z.abs();
+            }
+        }
+        radiance
+    }
+}
+
+impl<T: Float + Send + Sync, U> Integrator<T, U> for WhittedIntegrator {
+    #[inline]
+    fn li(&self, ray: &Ray<T, U>, scene: &Scene<T, U>, sampler: &mut dyn Sampler<T>) -> Rgb<T> {
+        self.trace(ray, None, scene, sampler, 0)
+    }
+
+    #[inline]
+    fn li_differential(
+        &self,
+        ray: &Ray<T, U>,
+        differential: &RayDifferential<T, U>,
+        scene: &Scene<T, U>,
+        sampler: &mut dyn Sampler<T>,
+    ) -> Rgb<T> {
+        self.trace(ray, Some(differential), scene, sampler, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        accel::Bvh,
+        core::geometry::{Point3, UnknownUnit, Vector3},
+        light::DiffuseAreaLight,
+        material::DielectricMaterial,
+        sampler::IndependentSampler,
+        scene::Primitive,
+        shape::{SampleShape, Sphere},
+    };
+    use std::sync::Arc;
+
+    #[test]
+    fn test_glass() {
+        let emitter: Arc<dyn SampleShape<f64, UnknownUnit> + Send + Sync> =
+            Arc::new(Sphere::new(Point3::new(0., 0., -10.), 3.));
+        let light = DiffuseAreaLight::new(Arc::clone(&emitter), Rgb::splat(1.));
+        let glass = Arc::new(Sphere::new(Point3::new(0., 0., -4.), 1.));
+        let primitives = vec![
+            Primitive::emissive(emitter, None, light),
+            Primitive::new(glass, Some(Arc::new(DielectricMaterial::new(1.5, 0.)))),
+        ];
+        let scene = Scene::new(Bvh::new(primitives), Vec::new());
+
+        // Straight through the glass, losing some light to reflection at both surfaces
+        let mut sampler = IndependentSampler::new(1, 0);
+        sampler.start_pixel_sample(Point2::new(0, 0), 0, 0);
+        let ray = Ray::new(Point3::origin(), Vector3::new(0., 0., -1.));
+        let radiance = WhittedIntegrator::default().li(&ray, &scene, &mut sampler);
+        let (r, t) = (0.04, 0.96);
+        let expected = t * t / (1. - r * r);
+        assert!((radiance.g - expected).abs() < 1e-4, "{radiance:?}");
+    }
+}
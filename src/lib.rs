@@ -1 +1,23 @@
+#[macro_use]
+mod macros;
+
+pub mod accel;
+pub mod bsdf;
+pub mod camera;
+pub mod color;
 pub mod core;
+pub mod film;
+pub mod image;
+pub mod image_io;
+pub mod integrator;
+pub mod light;
+pub mod material;
+pub mod medium;
+pub mod sampler;
+pub mod sampling;
+pub mod scene;
+pub mod scene_io;
+pub mod shape;
+pub mod spectrum;
+pub mod texture;
+pub mod wasm;
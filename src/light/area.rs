@@ -0,0 +1,97 @@
+use crate::{
+    color::Rgb,
+    core::{
+        geometry::{Point2, Point3, UnknownUnit, Vector3},
+        prelude::Normal3,
+    },
+    light::{DirectionCone, Light, LightBounds, LightSample},
+    shape::SampleShape,
+};
+use num_traits::Float;
+use std::{f64::consts::PI, fmt, sync::Arc};
+
+/// Emission of the same radiance in all directions from the surface of a shape
+pub struct DiffuseAreaLight<T, U> {
+    shape: Arc<dyn SampleShape<T, U> + Send + Sync>,
+    pub radiance: Rgb<T>,
+    /// Whether both sides of the surface emit, rather than only the side the normal faces
+    pub two_sided: bool,
+}
+
+impl<T: fmt::Debug, U> fmt::Debug for DiffuseAreaLight<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DiffuseAreaLight")
+            .field("radiance", &self.radiance)
+            .field("two_sided", &self.two_sided)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Float, U> DiffuseAreaLight<T, U> {
+    #[must_use]
+    pub fn new(shape: Arc<dyn SampleShape<T, U> + Send + Sync>, radiance: Rgb<T>) -> Self {
+        Self {
+            shape,
+            radiance,
+            two_sided: false,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn shape(&self) -> &Arc<dyn SampleShape<T, U> + Send + Sync> {
+        &self.shape
+    }
+
+    /// Returns the radiance leaving a point of the surface with normal `n` in the direction `w`
+    #[inline]
+    #[must_use]
+    pub fn l(&self, n: Normal3<T, U>, w: Vector3<T, U>) -> Rgb<T> {
+        if !self.two_sided && n.to_vector().dot(w) <= T::zero() {
+            Rgb::black()
+        } else {
+            self.radiance
+        }
+    }
+}
+
+impl<T: Float + Send + Sync, U> Light<T, U> for DiffuseAreaLight<T, U> {
+    fn sample_li(&self, p: Point3<T, U>, u: Point2<T, UnknownUnit>) -> Option<LightSample<T, U>> {
+        let sample = self.shape.sample_from(p, u)?;
+        let wi = (sample.p - p).try_normalize()?;
+        let radiance = self.l(sample.n, -wi);
+        (!radiance.is_black()).then_some(LightSample {
+            radiance,
+            wi,
+            pdf: sample.pdf,
+            p: sample.p,
+        })
+    }
+
+    #[inline]
+    fn pdf_li(&self, p: Point3<T, U>, wi: Vector3<T, U>) -> T {
+        self.shape.pdf_from(p, wi)
+    }
+
+    fn power(&self) -> Rgb<T> {
+        let sides = if self.two_sided { 2. } else { 1. };
+        self.radiance * (self.shape.area() * T::from(sides * PI).unwrap())
+    }
+
+    fn bounds(&self) -> Option<LightBounds<T, U>> {
+        // Flat emitters only light the side they face, and others any direction
+        let normals = self
+            .shape
+            .normal()
+            .map_or_else(DirectionCone::entire_sphere, |n| {
+                DirectionCone::new(n.to_vector(), T::one())
+            });
+        Some(LightBounds {
+            bounds: self.shape.bounds(),
+            intensity: self.radiance.max_component() * self.shape.area(),
+            normals,
+            cos_theta_e: T::zero(),
+            two_sided: self.two_sided,
+        })
+    }
+}
@@ -0,0 +1,190 @@
+use crate::core::{
+    geometry::{Box3, Point3, Vector3},
+    prelude::Normal3,
+};
+use num_traits::Float;
+
+/// A cone of directions around a normalized axis
+pub struct DirectionCone<T, U> {
+    pub w: Vector3<T, U>,
+    /// The cosine of the half angle of the cone
+    pub cos_theta: T,
+}
+
+common_impls!(DirectionCone { w, cos_theta });
+
+impl<T: Float, U> DirectionCone<T, U> {
+    #[inline]
+    #[must_use]
+    pub fn new(w: Vector3<T, U>, cos_theta: T) -> Self {
+        Self { w, cos_theta }
+    }
+
+    /// Returns the cone of all directions
+    #[inline]
+    #[must_use]
+    pub fn entire_sphere() -> Self {
+        Self::new(Vector3::new(T::zero(), T::zero(), T::one()), -T::one())
+    }
+
+    /// Returns a cone bounding the directions from `p` towards any point of `bounds`
+    #[must_use]
+    pub fn bound_subtended(bounds: &Box3<T, U>, p: Point3<T, U>) -> Self {
+        let two = T::one() + T::one();
+        let center = bounds.min + (bounds.max - bounds.min) / two;
+        let radius_squared = (bounds.max - bounds.min).length_squared() / (two * two);
+        let d = center - p;
+        let distance_squared = d.length_squared();
+        if distance_squared < radius_squared {
+            return Self::entire_sphere();
+        }
+        // The cone around the bounding sphere of the box
+        let sin2_theta_max = radius_squared / distance_squared;
+        let cos_theta_max = (T::one() - sin2_theta_max).max(T::zero()).sqrt();
+        Self::new(d / distance_squared.sqrt(), cos_theta_max)
+    }
+
+    /// Returns the smallest cone, around an axis between the axes of both cones, which
+    /// contains them both
+    #[must_use]
+    pub fn union(&self, other: &Self) -> Self {
+        let theta_a = clamped_acos(self.cos_theta);
+        let theta_b = clamped_acos(other.cos_theta);
+        let theta_d = clamped_acos(self.w.dot(other.w));
+        let pi = T::from(std::f64::consts::PI).unwrap();
+        if (theta_d + theta_b).min(pi) <= theta_a {
+            return *self;
+        }
+        if (theta_d + theta_a).min(pi) <= theta_b {
+            return *other;
+        }
+
+        let theta_o = (theta_a + theta_d + theta_b) / (T::one() + T::one());
+        if theta_o >= pi {
+            return Self::entire_sphere();
+        }
+        // Rotates the first axis towards the second until the cone reaches past both
+        let Some(axis) = self.w.cross(other.w).try_normalize() else {
+            return Self::entire_sphere();
+        };
+        let (sin, cos) = (theta_o - theta_a).sin_cos();
+        let w =
+            self.w * cos + axis.cross(self.w) * sin + axis * (axis.dot(self.w) * (T::one() - cos));
+        Self::new(w, theta_o.cos())
+    }
+}
+
+/// The spatial and directional extent of the emission of one or more lights, for estimating
+/// how much of it may reach a point
+pub struct LightBounds<T, U> {
+    pub bounds: Box3<T, U>,
+    /// The largest radiant intensity of the emission, such as for a point light
+    pub intensity: T,
+    /// The directions of the surface normals of emitters, or of their emission otherwise
+    pub normals: DirectionCone<T, U>,
+    /// The cosine of how far from the normals emission spreads, such as zero for surfaces
+    /// emitting into their hemisphere
+    pub cos_theta_e: T,
+    /// Whether emitters also emit on the back side of their surfaces
+    pub two_sided: bool,
+}
+
+common_impls!(LightBounds {
+    bounds,
+    intensity,
+    normals,
+    cos_theta_e,
+    two_sided
+});
+
+impl<T: Float, U> LightBounds<T, U> {
+    #[inline]
+    #[must_use]
+    pub fn centroid(&self) -> Point3<T, U> {
+        let two = T::one() + T::one();
+        self.bounds.min + (self.bounds.max - self.bounds.min) / two
+    }
+
+    /// Returns the bounds of the emission of both
+    #[must_use]
+    pub fn union(&self, other: &Self) -> Self {
+        Self {
+            bounds: self.bounds.union_unchecked(&other.bounds),
+            intensity: self.intensity + other.intensity,
+            normals: self.normals.union(&other.normals),
+            cos_theta_e: self.cos_theta_e.min(other.cos_theta_e),
+            two_sided: self.two_sided || other.two_sided,
+        }
+    }
+
+    /// Returns a conservative estimate of the light reaching `p`, on a surface with normal `n`
+    /// unless it is in a medium
+    ///
+    /// This is the estimate of Conty Estevez and Kulla, which is zero only where no light can
+    /// arrive at all.
+    #[must_use]
+    pub fn importance(&self, p: Point3<T, U>, n: Option<Normal3<T, U>>) -> T {
+        let center = self.centroid();
+        let two = T::one() + T::one();
+        // Points within the bounds are not considered any closer than half their diagonal
+        let distance_squared = (p - center)
+            .length_squared()
+            .max((self.bounds.max - self.bounds.min).length() / two);
+
+        let Some(wi) = (p - center).try_normalize() else {
+            return self.intensity / distance_squared;
+        };
+        let mut cos_theta_w = self.normals.w.dot(wi);
+        if self.two_sided {
+            cos_theta_w = cos_theta_w.abs();
+        }
+        let sin_theta_w = sin_from_cos(cos_theta_w);
+
+        // The smallest angle between a normal and any direction towards `p` from the bounds
+        let subtended = DirectionCone::bound_subtended(&self.bounds, p);
+        let (cos_theta_b, sin_theta_b) = (subtended.cos_theta, sin_from_cos(subtended.cos_theta));
+        let (cos_theta_o, sin_theta_o) =
+            (self.normals.cos_theta, sin_from_cos(self.normals.cos_theta));
+        let (cos_theta_x, sin_theta_x) =
+            sub_clamped(cos_theta_w, sin_theta_w, cos_theta_o, sin_theta_o);
+        let (cos_theta_p, _) = sub_clamped(cos_theta_x, sin_theta_x, cos_theta_b, sin_theta_b);
+        if cos_theta_p <= self.cos_theta_e {
+            return T::zero();
+        }
+        let mut importance = self.intensity * cos_theta_p / distance_squared;
+
+        // The largest cosine at the receiving surface
+        if let Some(n) = n {
+            let cos_theta_i = n.to_vector().normalize().dot(wi).abs();
+            let (cos_theta_pi, _) = sub_clamped(
+                cos_theta_i,
+                sin_from_cos(cos_theta_i),
+                cos_theta_b,
+                sin_theta_b,
+            );
+            importance = importance * cos_theta_pi;
+        }
+        importance.max(T::zero())
+    }
+}
+
+#[inline]
+fn clamped_acos<T: Float>(cos: T) -> T {
+    cos.max(-T::one()).min(T::one()).acos()
+}
+
+#[inline]
+fn sin_from_cos<T: Float>(cos: T) -> T {
+    (T::one() - cos * cos).max(T::zero()).sqrt()
+}
+
+/// Returns the cosine and sine of the difference of two angles given by theirs, clamped to
+/// zero
+#[inline]
+fn sub_clamped<T: Float>(cos_a: T, sin_a: T, cos_b: T, sin_b: T) -> (T, T) {
+    if cos_a > cos_b {
+        (T::one(), T::zero())
+    } else {
+        (cos_a * cos_b + sin_a * sin_b, sin_a * cos_b - cos_a * sin_b)
+    }
+}
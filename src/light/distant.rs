@@ -0,0 +1,185 @@
+use crate::{
+    color::Rgb,
+    core::{
+        geometry::{Box3, Point2, Point3, Ray, UnknownUnit, Vector3},
+        units::Angle,
+    },
+    light::{Light, LightSample},
+    sampling::{sample_uniform_cone, uniform_cone_pdf},
+};
+use num_traits::{Float, FloatConst};
+
+/// Light arriving from a direction at infinity, such as sunlight
+///
+/// A light with an angular radius arrives from a cone of directions around its direction and
+/// casts soft shadows, and is seen in that cone by rays leaving the scene. Without one, it
+/// casts perfectly sharp shadows and can only be reached by sampling it.
+pub struct DistantLight<T, U> {
+    /// The normalized direction towards the light
+    pub direction: Vector3<T, U>,
+    /// The irradiance on a surface facing the light
+    pub irradiance: Rgb<T>,
+    /// The half angle of the cone the light arrives from
+    pub angular_radius: Angle<T>,
+    scene_center: Point3<T, U>,
+    scene_radius: T,
+}
+
+common_impls!(DistantLight {
+    direction,
+    irradiance,
+    angular_radius,
+    scene_center,
+    scene_radius
+});
+
+impl<T: Float + FloatConst, U> DistantLight<T, U> {
+    /// Creates a light for a scene within `scene_bounds`, which shadow rays must leave to
+    /// reach the light
+    #[must_use]
+    pub fn new(
+        direction: Vector3<T, U>,
+        irradiance: Rgb<T>,
+        angular_radius: Angle<T>,
+        scene_bounds: Box3<T, U>,
+    ) -> Self {
+        let two = T::one() + T::one();
+        Self {
+            direction: direction.normalize(),
+            irradiance,
+            angular_radius,
+            scene_center: scene_bounds.center(),
+            scene_radius: (scene_bounds.max - scene_bounds.min).length() / two,
+        }
+    }
+
+    /// Returns the radiance arriving from each direction of the cone, which adds up to the
+    /// irradiance on a surface facing the light
+    #[inline]
+    #[must_use]
+    pub fn radiance(&self) -> Rgb<T> {
+        let sin = self.angular_radius.radians().sin();
+        self.irradiance / (T::PI() * sin * sin)
+    }
+
+    /// Returns whether the normalized direction `w` is within the cone the light arrives from
+    #[inline]
+    fn in_cone(&self, w: Vector3<T, U>) -> bool {
+        w.dot(self.direction) >= self.angular_radius.radians().cos()
+    }
+}
+
+impl<T: Float + FloatConst + Send + Sync, U: Send + Sync> Light<T, U> for DistantLight<T, U> {
+    fn sample_li(&self, p: Point3<T, U>, u: Point2<T, UnknownUnit>) -> Option<LightSample<T, U>> {
+        let (wi, radiance, pdf) = if self.is_delta() {
+            (self.direction, self.irradiance, T::one())
+        } else {
+            let w: Vector3<T, U> = sample_uniform_cone(u, self.angular_radius);
+            let (x, y) = self.direction.coordinate_system();
+            let wi = x * w.x + y * w.y + self.direction * w.z;
+            (wi, self.radiance(), uniform_cone_pdf(self.angular_radius))
+        };
+        // Far enough to leave the bounding sphere of the scene from anywhere in it
+        let distance = (p - self.scene_center).length() + self.scene_radius;
+        Some(LightSample {
+            radiance,
+            wi,
+            pdf,
+            p: p + wi * distance,
+        })
+    }
+
+    fn pdf_li(&self, _p: Point3<T, U>, wi: Vector3<T, U>) -> T {
+        if !self.is_delta() && self.in_cone(wi.normalize()) {
+            uniform_cone_pdf(self.angular_radius)
+        } else {
+            T::zero()
+        }
+    }
+
+    #[inline]
+    fn power(&self) -> Rgb<T> {
+        // The light crossing a disk as large as the scene
+        self.irradiance * (T::PI() * self.scene_radius * self.scene_radius)
+    }
+
+    fn le(&self, ray: &Ray<T, U>) -> Rgb<T> {
+        if !self.is_delta() && self.in_cone(ray.dir.normalize()) {
+            self.radiance()
+        } else {
+            Rgb::black()
+        }
+    }
+
+    #[inline]
+    fn is_delta(&self) -> bool {
+        self.angular_radius.radians() == T::zero()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        accel::Bvh,
+        core::num::ApproxEq,
+        integrator::{DirectLightingIntegrator, Integrator},
+        material::DiffuseMaterial,
+        sampler::{IndependentSampler, Sampler},
+        scene::{Primitive, Scene},
+        shape::{Disk, Shape},
+    };
+    use std::{f64::consts::PI, sync::Arc};
+
+    #[test]
+    fn test_distant_light() {
+        let floor = Arc::new(Disk::new(Point3::origin(), 10., 0.));
+        let bounds = floor.bounds();
+        let material = Arc::new(DiffuseMaterial::new(Rgb::splat(1.)));
+        let scene_with = |light: DistantLight<f64, UnknownUnit>| {
+            let floor = Primitive::new(floor.clone(), Some(material.clone()));
+            Scene::new(
+                Bvh::new(vec![floor]),
+                vec![Arc::new(light) as Arc<dyn Light<_, _>>],
+            )
+        };
+        let direction = Vector3::new(1., 0., 1.);
+        let ray = Ray::new(Point3::new(0., 0., 1.), Vector3::new(0., 0., -1.));
+
+        // A sharp light reflects `E cos / pi` off a white floor
+        let sharp = DistantLight::new(direction, Rgb::splat(3.), Angle::from_radians(0.), bounds);
+        let sample = sharp
+            .sample_li(Point3::origin(), Point2::new(0.5, 0.5))
+            .unwrap();
+        assert!(sample.wi.approx_eq(&direction.normalize()));
+        assert!((sample.p - Point3::origin()).length() > 10.);
+        let mut sampler = IndependentSampler::new(1, 3);
+        sampler.start_pixel_sample(Point2::new(0, 0), 0, 0);
+        let radiance = DirectLightingIntegrator.li(&ray, &scene_with(sharp), &mut sampler);
+        let expected = 3. * 0.5_f64.sqrt() / PI;
+        assert!((radiance.r - expected).abs() < 1e-9, "{radiance:?}");
+
+        // A soft light arrives with the same irradiance from within its cone
+        let soft = DistantLight::new(direction, Rgb::splat(3.), Angle::from_radians(0.2), bounds);
+        for u in [(0., 0.), (0.3, 0.6), (0.999, 0.9)] {
+            let sample = soft
+                .sample_li(Point3::origin(), Point2::new(u.0, u.1))
+                .unwrap();
+            assert!(sample.wi.dot(soft.direction) >= 0.2_f64.cos() - 1e-12);
+            assert!((soft.pdf_li(Point3::origin(), sample.wi) - sample.pdf).abs() < 1e-9);
+            let escaping = Ray::new(Point3::origin(), sample.wi);
+            assert_eq!(soft.le(&escaping), soft.radiance());
+        }
+        assert_eq!(soft.pdf_li(Point3::origin(), Vector3::new(0., 0., 1.)), 0.);
+
+        let scene = scene_with(soft);
+        let n = 2000;
+        let mut sum = 0.;
+        for i in 0..n {
+            sampler.start_pixel_sample(Point2::new(0, 0), i, 0);
+            sum += DirectLightingIntegrator.li(&ray, &scene, &mut sampler).r;
+        }
+        let mean = sum / n as f64;
+        assert!((mean - expected).abs() < 0.01, "{mean}");
+    }
+}
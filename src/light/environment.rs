@@ -0,0 +1,382 @@
+use crate::{
+    color::Rgb,
+    core::geometry::{Box3, Point2, Point3, Ray, UnknownUnit, Vector3},
+    image::Image,
+    image_io::ImageError,
+    light::{Light, LightSample},
+    sampling::PiecewiseConstant2D,
+    shape::{Rectangle, SampleShape},
+};
+use num_traits::{Float, FloatConst};
+use std::{fmt, path::Path, sync::Arc};
+
+/// Light arriving from all directions at infinity, given by an equirectangular image such as
+/// a captured HDR panorama
+///
+/// The image wraps around the +z axis, with its top row at +z, its bottom row at -z, and its
+/// left and right edges at +x. Directions are sampled in proportion to their brightness, so
+/// that small bright features such as the sun are found without noise.
+///
+/// Interiors lit through windows are better sampled through [portals](Self::with_portals).
+pub struct EnvironmentLight<T, U> {
+    image: Arc<Image<T>>,
+    /// The factor by which the radiance of the image is scaled
+    pub scale: T,
+    distribution: Arc<PiecewiseConstant2D<T>>,
+    scene_center: Point3<T, U>,
+    scene_radius: T,
+    portals: Vec<Rectangle<T, U>>,
+}
+
+impl<T: fmt::Debug, U> fmt::Debug for EnvironmentLight<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EnvironmentLight")
+            .field("width", &self.image.width())
+            .field("height", &self.image.height())
+            .field("scale", &self.scale)
+            .field("scene_center", &self.scene_center)
+            .field("scene_radius", &self.scene_radius)
+            .field("portals", &self.portals)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Clone, U> Clone for EnvironmentLight<T, U> {
+    fn clone(&self) -> Self {
+        Self {
+            image: Arc::clone(&self.image),
+            scale: self.scale.clone(),
+            distribution: Arc::clone(&self.distribution),
+            scene_center: self.scene_center.clone(),
+            scene_radius: self.scene_radius.clone(),
+            portals: self.portals.clone(),
+        }
+    }
+}
+
+impl<T: Float + FloatConst, U> EnvironmentLight<T, U> {
+    /// Creates a light for a scene within `scene_bounds`, which shadow rays must leave to
+    /// reach the light
+    ///
+    /// Panics if the image is empty.
+    #[must_use]
+    pub fn new(image: Image<T>, scale: T, scene_bounds: Box3<T, U>) -> Self {
+        let (width, height) = (image.width(), image.height());
+        assert!(
+            width > 0 && height > 0,
+            "environment images cannot be empty"
+        );
+
+        // Every pixel weighs as much as the largest of its neighbors, since those contribute to
+        // its interpolated radiance, and as much as the solid angle it covers
+        let brightness: Vec<T> = image.pixels().iter().map(|p| p.max_component()).collect();
+        let mut func = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let theta = (T::from(y).unwrap() + T::from(0.5).unwrap()) / T::from(height).unwrap();
+            let sin_theta = (theta * T::PI()).sin();
+            for x in 0..width {
+                let mut max = T::zero();
+                for ny in y.saturating_sub(1)..(y + 2).min(height) {
+                    for nx in [x + width - 1, x, x + 1] {
+                        max = max.max(brightness[ny * width + nx % width]);
+                    }
+                }
+                func.push(max * sin_theta);
+            }
+        }
+
+        let two = T::one() + T::one();
+        Self {
+            image: Arc::new(image),
+            scale,
+            distribution: Arc::new(PiecewiseConstant2D::new(&func, width, height)),
+            scene_center: scene_bounds.center(),
+            scene_radius: (scene_bounds.max - scene_bounds.min).length() / two,
+            portals: Vec::new(),
+        }
+    }
+
+    /// Samples only the directions through the given openings, such as the windows and doors
+    /// of an interior, instead of the whole sky, most of which is hidden by walls
+    ///
+    /// Light then only arrives through the portals, and the scene must be closed apart from
+    /// them for it to be lit correctly.
+    #[must_use]
+    pub fn with_portals(self, portals: Vec<Rectangle<T, U>>) -> Self {
+        Self { portals, ..self }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn portals(&self) -> &[Rectangle<T, U>] {
+        &self.portals
+    }
+
+    /// Loads the image from a file by its extension, see [`Image::load`]
+    pub fn load(
+        path: impl AsRef<Path>,
+        scale: T,
+        scene_bounds: Box3<T, U>,
+    ) -> Result<Self, ImageError> {
+        let image = Image::load(path)?;
+        if image.width() == 0 || image.height() == 0 {
+            return Err(ImageError::InvalidFormat);
+        }
+        Ok(Self::new(image, scale, scene_bounds))
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn image(&self) -> &Image<T> {
+        &self.image
+    }
+
+    /// Returns the radiance arriving from the normalized direction `w`
+    #[must_use]
+    pub fn radiance(&self, w: Vector3<T, U>) -> Rgb<T> {
+        self.lookup(direction_to_uv(w)) * self.scale
+    }
+
+    /// Interpolates the four pixels nearest to `uv`, wrapping around horizontally
+    fn lookup(&self, uv: Point2<T, UnknownUnit>) -> Rgb<T> {
+        let (width, height) = (self.image.width(), self.image.height());
+        let half = T::from(0.5).unwrap();
+        let x = uv.x * T::from(width).unwrap() - half;
+        let y = uv.y * T::from(height).unwrap() - half;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (dx, dy) = (x - x0, y - y0);
+        let column = |i: T| {
+            let i = i.to_i64().unwrap_or(0);
+            i.rem_euclid(width as i64) as usize
+        };
+        let row = |i: T| i.max(T::zero()).to_usize().unwrap_or(0).min(height - 1);
+        let pixel = |x: T, y: T| self.image.pixels()[row(y) * width + column(x)];
+        let x1 = x0 + T::one();
+        let y1 = y0 + T::one();
+        (pixel(x0, y0) * (T::one() - dx) + pixel(x1, y0) * dx) * (T::one() - dy)
+            + (pixel(x0, y1) * (T::one() - dx) + pixel(x1, y1) * dx) * dy
+    }
+
+    /// Returns the density of sampling `wi` at `p` through any of the portals, which may
+    /// overlap as seen from `p`
+    fn portal_pdf(&self, p: Point3<T, U>, wi: Vector3<T, U>) -> T {
+        let sum = self
+            .portals
+            .iter()
+            .fold(T::zero(), |sum, portal| sum + portal.pdf_from(p, wi));
+        sum / T::from(self.portals.len()).unwrap()
+    }
+}
+
+/// Returns the position in the image of the normalized direction `w`
+#[inline]
+fn direction_to_uv<T: Float + FloatConst, U>(w: Vector3<T, U>) -> Point2<T, UnknownUnit> {
+    let theta = w.z.max(-T::one()).min(T::one()).acos();
+    let phi = w.y.atan2(w.x);
+    let phi = if phi < T::zero() { phi + T::TAU() } else { phi };
+    Point2::new(phi / T::TAU(), theta / T::PI())
+}
+
+/// Returns the direction through a position in the image, and the sine of its angle to +z
+#[inline]
+fn uv_to_direction<T: Float + FloatConst, U>(uv: Point2<T, UnknownUnit>) -> (Vector3<T, U>, T) {
+    let (sin_theta, cos_theta) = (uv.y * T::PI()).sin_cos();
+    let (sin_phi, cos_phi) = (uv.x * T::TAU()).sin_cos();
+    let w = Vector3::new(sin_theta * cos_phi, sin_theta * sin_phi, cos_theta);
+    (w, sin_theta)
+}
+
+impl<T: Float + FloatConst + Send + Sync, U: Send + Sync> Light<T, U> for EnvironmentLight<T, U> {
+    fn sample_li(&self, p: Point3<T, U>, u: Point2<T, UnknownUnit>) -> Option<LightSample<T, U>> {
+        let (wi, pdf, radiance) = if self.portals.is_empty() {
+            let (uv, map_pdf) = self.distribution.sample(u);
+            let (wi, sin_theta) = uv_to_direction(uv);
+            if map_pdf == T::zero() || sin_theta == T::zero() {
+                return None;
+            }
+            // The image spans 2 pi by pi radians, squeezed towards the poles by the sine
+            let pdf = map_pdf / (T::TAU() * T::PI() * sin_theta);
+            (wi, pdf, self.lookup(uv) * self.scale)
+        } else {
+            // The first coordinate picks a portal and is then reused within it
+            let n = T::from(self.portals.len()).unwrap();
+            let scaled = u.x * n;
+            let index = scaled.floor().min(n - T::one());
+            let u = Point2::new(scaled - index, u.y);
+            let portal = &self.portals[index.to_usize().unwrap_or(0)];
+            let wi = (portal.sample_from(p, u)?.p - p).try_normalize()?;
+            (wi, self.portal_pdf(p, wi), self.radiance(wi))
+        };
+        // Far enough to leave the bounding sphere of the scene from anywhere in it
+        let distance = (p - self.scene_center).length() + self.scene_radius;
+        Some(LightSample {
+            radiance,
+            wi,
+            pdf,
+            p: p + wi * distance,
+        })
+    }
+
+    fn pdf_li(&self, p: Point3<T, U>, wi: Vector3<T, U>) -> T {
+        if !self.portals.is_empty() {
+            return self.portal_pdf(p, wi);
+        }
+        let uv = direction_to_uv(wi.normalize());
+        let sin_theta = (uv.y * T::PI()).sin();
+        if sin_theta == T::zero() {
+            return T::zero();
+        }
+        self.distribution.pdf(uv) / (T::TAU() * T::PI() * sin_theta)
+    }
+
+    fn power(&self) -> Rgb<T> {
+        // The light crossing a disk as large as the scene, from every pixel's solid angle
+        let (width, height) = (self.image.width(), self.image.height());
+        let mut sum = Rgb::black();
+        for (y, row) in self.image.rows().enumerate() {
+            let theta = (T::from(y).unwrap() + T::from(0.5).unwrap()) / T::from(height).unwrap();
+            let sin_theta = (theta * T::PI()).sin();
+            sum += row.iter().copied().sum::<Rgb<T>>() * sin_theta;
+        }
+        let pixel_solid_angle = T::TAU() * T::PI() / T::from(width * height).unwrap();
+        let area = T::PI() * self.scene_radius * self.scene_radius;
+        sum * (pixel_solid_angle * area * self.scale)
+    }
+
+    #[inline]
+    fn le(&self, ray: &Ray<T, U>) -> Rgb<T> {
+        self.radiance(ray.dir.normalize())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        accel::Bvh,
+        core::units::Time,
+        integrator::{DirectLightingIntegrator, Integrator},
+        material::DiffuseMaterial,
+        sampler::{IndependentSampler, Pcg32, Sampler},
+        sampling::sample_uniform_sphere,
+        scene::{Primitive, Scene},
+        shape::{Disk, Shape},
+    };
+
+    type P = Point3<f64, UnknownUnit>;
+    type V = Vector3<f64, UnknownUnit>;
+
+    #[test]
+    fn test_environment_light() {
+        let floor = Arc::new(Disk::new(P::origin(), 1e3, 0.));
+        let bounds = floor.bounds();
+
+        // A dark sky with a bright spot towards +x, near the horizon
+        let (width, height) = (32, 16);
+        let mut pixels = vec![Rgb::splat(0.1); width * height];
+        pixels[7 * width] = Rgb::splat(1000.);
+        let sky = EnvironmentLight::new(Image::new(width, height, pixels), 1., bounds);
+        let p = P::origin();
+        let mut sampler = IndependentSampler::new(1, 3);
+        let mut towards_spot = 0;
+        for i in 0..200 {
+            sampler.start_pixel_sample(Point2::new(0, 0), i, 0);
+            let sample = sky.sample_li(p, sampler.next_2d()).unwrap();
+            let pdf = sky.pdf_li(p, sample.wi);
+            assert!(
+                (pdf - sample.pdf).abs() <= 1e-9 * pdf,
+                "{pdf} != {}",
+                sample.pdf
+            );
+            let le = sky.le(&Ray::new(p, sample.wi));
+            assert!((le.r - sample.radiance.r).abs() <= 1e-9 * le.r);
+            if sample.wi.x > 0.9 {
+                towards_spot += 1;
+            }
+        }
+        assert!(towards_spot > 150, "{towards_spot}");
+        assert_eq!(sky.radiance(V::new(0., 0., 1.)), Rgb::splat(0.1));
+
+        // A white floor under a uniform sky reflects the radiance of the sky, also when the
+        // sky is only sampled through a portal and found elsewhere by sampling the BSDF
+        let uniform = Image::new(width, height, vec![Rgb::splat(2.); width * height]);
+        let light = EnvironmentLight::new(uniform, 0.5, bounds);
+        let portal = |x| Rectangle::new(P::new(x, -1., 2.), V::new(1., 0., 0.), V::new(0., 2., 0.));
+        let material = Arc::new(DiffuseMaterial::new(Rgb::splat(1.)));
+        for light in [light.clone(), light.with_portals(vec![portal(-0.5)])] {
+            let scene = Scene::new(
+                Bvh::new(vec![Primitive::new(floor.clone(), Some(material.clone()))]),
+                vec![Arc::new(light) as Arc<dyn Light<_, _>>],
+            );
+            let ray = Ray::new(P::new(0., 0., 1.), V::new(0.3, 0., -1.));
+            let n = 4000;
+            let mut sum = 0.;
+            for i in 0..n {
+                sampler.start_pixel_sample(Point2::new(0, 0), i, 0);
+                sum += DirectLightingIntegrator.li(&ray, &scene, &mut sampler).r;
+            }
+            let mean = sum / n as f64;
+            assert!((mean - 1.).abs() < 0.03, "{mean}");
+        }
+
+        // Portals only pick directions through them, with the density of either one
+        let through = sky.clone().with_portals(vec![portal(-1.5), portal(0.5)]);
+        let p = P::new(-1., 0., 0.);
+        for i in 0..100 {
+            sampler.start_pixel_sample(Point2::new(0, 0), i, 0);
+            let sample = through.sample_li(p, sampler.next_2d()).unwrap();
+            let pdf = through.pdf_li(p, sample.wi);
+            assert!(
+                (pdf - sample.pdf).abs() <= 1e-9 * pdf,
+                "{pdf} != {}",
+                sample.pdf
+            );
+            let hits = [portal(-1.5), portal(0.5)]
+                .iter()
+                .filter(|portal| portal.intersect_any(&Ray::new(p, sample.wi), Time(f64::INFINITY)))
+                .count();
+            assert_eq!(hits, 1);
+        }
+        assert_eq!(through.pdf_li(p, V::new(0., 0., -1.)), 0.);
+    }
+
+    #[test]
+    fn test_portal_pdf() {
+        let bounds = Box3::new(P::new(-10., -10., -10.), P::new(10., 10., 10.));
+        let sky = Image::new(4, 2, vec![Rgb::splat(1.); 8]);
+        let portal =
+            |z| Rectangle::new(P::new(-0.5, -1., z), V::new(1., 0., 0.), V::new(0., 2., 0.));
+        let p = P::new(-1., 0.5, 0.);
+        let mut rng = Pcg32::new(4);
+        // The second pair of portals overlap as seen from `p`
+        for portals in [vec![portal(2.)], vec![portal(2.), portal(3.)]] {
+            let light =
+                EnvironmentLight::new(sky.clone(), 1., bounds).with_portals(portals.clone());
+            let through = |wi: V| {
+                let ray = Ray::new(p, wi);
+                portals
+                    .iter()
+                    .any(|portal| portal.intersect_any(&ray, Time(f64::INFINITY)))
+            };
+
+            // The density is zero exactly outside the portals, and integrates to one over the
+            // sphere of directions
+            let n = 200_000;
+            let mut sum = 0.;
+            for _ in 0..n {
+                let wi: V = sample_uniform_sphere(Point2::new(rng.uniform(), rng.uniform()));
+                let pdf = light.pdf_li(p, wi);
+                assert_eq!(pdf > 0., through(wi), "{wi:?}");
+                sum += pdf;
+            }
+            let integral = sum * 4. * std::f64::consts::PI / f64::from(n);
+            assert!((integral - 1.).abs() < 0.03, "{integral}");
+
+            for _ in 0..100 {
+                let u = Point2::new(rng.uniform(), rng.uniform());
+                let sample = light.sample_li(p, u).unwrap();
+                assert!(through(sample.wi));
+            }
+        }
+    }
+}
@@ -0,0 +1,86 @@
+//! Sources of light, and how to sample the directions they illuminate a point from
+
+mod area;
+mod bounds;
+mod distant;
+mod environment;
+mod point;
+mod sampler;
+mod spot;
+
+pub use area::DiffuseAreaLight;
+pub use bounds::{DirectionCone, LightBounds};
+pub use distant::DistantLight;
+pub use environment::EnvironmentLight;
+pub use point::PointLight;
+pub use sampler::{BvhLightSampler, LightSampler, SampledLight, UniformLightSampler};
+pub use spot::SpotLight;
+
+use crate::{
+    color::Rgb,
+    core::geometry::{Point2, Point3, Ray, UnknownUnit, Vector3},
+};
+use num_traits::Float;
+
+/// Light arriving at a point from a sampled position on a light
+pub struct LightSample<T, U> {
+    /// The incident radiance
+    pub radiance: Rgb<T>,
+    /// The normalized direction towards the light
+    pub wi: Vector3<T, U>,
+    /// The density of `wi` with respect to solid angle, or one for lights at a single point
+    pub pdf: T,
+    /// The sampled position on the light, which must be visible for the light to arrive
+    pub p: Point3<T, U>,
+}
+
+common_impls!(LightSample {
+    radiance,
+    wi,
+    pdf,
+    p
+});
+
+pub trait Light<T, U>: Send + Sync {
+    /// Samples a direction from which the light reaches `p`
+    #[must_use]
+    fn sample_li(&self, p: Point3<T, U>, u: Point2<T, UnknownUnit>) -> Option<LightSample<T, U>>;
+
+    /// Returns the density with which [`sample_li`](Self::sample_li) picks the direction `wi`
+    /// at `p`
+    #[must_use]
+    fn pdf_li(&self, p: Point3<T, U>, wi: Vector3<T, U>) -> T;
+
+    /// Returns the total power emitted, such as for picking lights in proportion to it
+    #[must_use]
+    fn power(&self) -> Rgb<T>;
+
+    /// Returns the extent of the emission, for picking among many lights by how much of it
+    /// reaches a point, or `None` for lights at infinity
+    #[inline]
+    #[must_use]
+    fn bounds(&self) -> Option<LightBounds<T, U>>
+    where
+        T: Float,
+    {
+        None
+    }
+
+    /// Returns the radiance reaching a ray that leaves the scene, for lights at infinity
+    #[inline]
+    #[must_use]
+    fn le(&self, _ray: &Ray<T, U>) -> Rgb<T>
+    where
+        T: Float,
+    {
+        Rgb::black()
+    }
+
+    /// Whether the light is described by a delta distribution, such as a point light, which
+    /// can only be reached by sampling it
+    #[inline]
+    #[must_use]
+    fn is_delta(&self) -> bool {
+        false
+    }
+}
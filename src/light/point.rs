@@ -0,0 +1,109 @@
+use crate::{
+    color::Rgb,
+    core::geometry::{Box3, Point2, Point3, UnknownUnit, Vector3},
+    light::{DirectionCone, Light, LightBounds, LightSample},
+};
+use num_traits::Float;
+use std::f64::consts::PI;
+
+/// Emission of the same intensity in all directions from a single point, falling off with the
+/// square of the distance
+pub struct PointLight<T, U> {
+    pub position: Point3<T, U>,
+    /// The radiant intensity, the power emitted per unit solid angle
+    pub intensity: Rgb<T>,
+}
+
+common_impls!(PointLight {
+    position,
+    intensity
+});
+
+impl<T, U> PointLight<T, U> {
+    #[inline]
+    #[must_use]
+    pub fn new(position: Point3<T, U>, intensity: Rgb<T>) -> Self {
+        Self {
+            position,
+            intensity,
+        }
+    }
+}
+
+impl<T: Float + Send + Sync, U: Send + Sync> Light<T, U> for PointLight<T, U> {
+    fn sample_li(&self, p: Point3<T, U>, _u: Point2<T, UnknownUnit>) -> Option<LightSample<T, U>> {
+        let d = self.position - p;
+        let wi = d.try_normalize()?;
+        Some(LightSample {
+            radiance: self.intensity / d.length_squared(),
+            wi,
+            pdf: T::one(),
+            p: self.position,
+        })
+    }
+
+    #[inline]
+    fn pdf_li(&self, _p: Point3<T, U>, _wi: Vector3<T, U>) -> T {
+        T::zero()
+    }
+
+    #[inline]
+    fn power(&self) -> Rgb<T> {
+        self.intensity * T::from(4. * PI).unwrap()
+    }
+
+    fn bounds(&self) -> Option<LightBounds<T, U>> {
+        Some(LightBounds {
+            bounds: Box3::new(self.position, self.position),
+            intensity: self.intensity.max_component(),
+            normals: DirectionCone::entire_sphere(),
+            cos_theta_e: T::zero(),
+            two_sided: false,
+        })
+    }
+
+    #[inline]
+    fn is_delta(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        accel::Bvh,
+        core::geometry::Ray,
+        integrator::{DirectLightingIntegrator, Integrator},
+        material::DiffuseMaterial,
+        sampler::{IndependentSampler, Sampler},
+        scene::{Primitive, Scene},
+        shape::Disk,
+    };
+    use std::sync::Arc;
+
+    #[test]
+    fn test_point_light() {
+        let light = PointLight::new(Point3::new(0., 0., 2.), Rgb::splat(8.));
+        let sample = light
+            .sample_li(Point3::<f64, UnknownUnit>::origin(), Point2::new(0.5, 0.5))
+            .unwrap();
+        assert_eq!(sample.radiance, Rgb::splat(2.));
+        assert_eq!(sample.wi, Vector3::new(0., 0., 1.));
+        assert_eq!(light.power(), Rgb::splat(32. * PI));
+
+        // A white floor reflects `I cos / (pi d²)` of the light
+        let floor = Arc::new(Disk::new(Point3::origin(), 100., 0.));
+        let material = Arc::new(DiffuseMaterial::new(Rgb::splat(1.)));
+        let scene = Scene::new(
+            Bvh::new(vec![Primitive::new(floor, Some(material))]),
+            vec![Arc::new(light) as Arc<dyn Light<_, _>>],
+        );
+        let mut sampler = IndependentSampler::new(1, 3);
+        sampler.start_pixel_sample(Point2::new(0, 0), 0, 0);
+        let ray = Ray::new(Point3::new(2., 0., 1.), Vector3::new(0., 0., -1.));
+        let radiance = DirectLightingIntegrator.li(&ray, &scene, &mut sampler);
+        let expected = 8. * (2. / 8_f64.sqrt()) / (PI * 8.);
+        assert!((radiance.r - expected).abs() < 1e-9, "{radiance:?}");
+    }
+}
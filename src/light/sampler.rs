@@ -0,0 +1,315 @@
+use crate::{
+    core::{
+        geometry::{Axis3, Box3, Point3},
+        prelude::Normal3,
+    },
+    light::{Light, LightBounds},
+};
+use num_traits::Float;
+use std::sync::Arc;
+
+/// A light picked to estimate the light arriving at a point
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct SampledLight<T> {
+    /// The index of the light in the scene
+    pub index: usize,
+    /// The probability of picking it
+    pub pmf: T,
+}
+
+/// Chooses among the lights of a scene which one to sample at a point
+pub trait LightSampler<T, U>: Send + Sync {
+    /// Picks a light for `p`, on a surface with normal `n` unless it is in a medium
+    #[must_use]
+    fn sample(&self, p: Point3<T, U>, n: Option<Normal3<T, U>>, u: T) -> Option<SampledLight<T>>;
+
+    /// Returns the probability with which [`sample`](Self::sample) picks the light at `index`
+    #[must_use]
+    fn pmf(&self, p: Point3<T, U>, n: Option<Normal3<T, U>>, index: usize) -> T;
+}
+
+/// Picks every light with the same probability, which is cheap but wastes most samples in
+/// scenes with many lights far apart
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct UniformLightSampler {
+    count: usize,
+}
+
+impl UniformLightSampler {
+    #[inline]
+    #[must_use]
+    pub fn new<T, U>(lights: &[Arc<dyn Light<T, U>>]) -> Self {
+        Self {
+            count: lights.len(),
+        }
+    }
+}
+
+impl<T: Float, U> LightSampler<T, U> for UniformLightSampler {
+    fn sample(&self, _p: Point3<T, U>, _n: Option<Normal3<T, U>>, u: T) -> Option<SampledLight<T>> {
+        if self.count == 0 {
+            return None;
+        }
+        let index = (u * T::from(self.count).unwrap())
+            .to_usize()
+            .unwrap_or(0)
+            .min(self.count - 1);
+        Some(SampledLight {
+            index,
+            pmf: T::from(self.count).unwrap().recip(),
+        })
+    }
+
+    #[inline]
+    fn pmf(&self, _p: Point3<T, U>, _n: Option<Normal3<T, U>>, _index: usize) -> T {
+        if self.count == 0 {
+            T::zero()
+        } else {
+            T::from(self.count).unwrap().recip()
+        }
+    }
+}
+
+/// A node of a light hierarchy, stored in depth-first order
+///
+/// The first child of an interior node immediately follows it.
+struct LightNode<T, U> {
+    bounds: LightBounds<T, U>,
+    /// The light of a leaf or the second child of an interior node
+    offset: usize,
+    is_leaf: bool,
+}
+
+/// Picks lights in proportion to an estimate of how much of their light reaches a point,
+/// found by descending a bounding volume hierarchy over the lights
+///
+/// Sampling a light takes time logarithmic in the number of lights, so that scenes with
+/// thousands of them are rendered with about as little noise as if only the nearby ones
+/// existed. Lights at infinity are picked uniformly, with the same probability as the
+/// hierarchy as a whole.
+pub struct BvhLightSampler<T, U> {
+    nodes: Vec<LightNode<T, U>>,
+    infinite: Vec<usize>,
+    /// The branches taken from the root to the leaf of each light, as bits from the lowest,
+    /// or `None` for lights which are never picked
+    trails: Vec<Option<u64>>,
+}
+
+impl<T, U> std::fmt::Debug for BvhLightSampler<T, U> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BvhLightSampler")
+            .field("nodes", &self.nodes.len())
+            .field("infinite", &self.infinite)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Float, U> BvhLightSampler<T, U> {
+    #[must_use]
+    pub fn new(lights: &[Arc<dyn Light<T, U>>]) -> Self {
+        let mut infinite = Vec::new();
+        let mut bounded = Vec::new();
+        for (index, light) in lights.iter().enumerate() {
+            match light.bounds() {
+                Some(bounds) if bounds.intensity > T::zero() => bounded.push((index, bounds)),
+                // Lights that emit nothing are never picked
+                Some(_) => {}
+                None => infinite.push(index),
+            }
+        }
+
+        let mut sampler = Self {
+            nodes: Vec::with_capacity(2 * bounded.len()),
+            infinite,
+            trails: vec![None; lights.len()],
+        };
+        if !bounded.is_empty() {
+            sampler.build(&mut bounded, 0, 0);
+        }
+        sampler
+    }
+
+    /// Builds the subtree over `lights`, splitting them in halves around the median centroid
+    /// along the largest axis, and returns the bounds of its root
+    fn build(
+        &mut self,
+        lights: &mut [(usize, LightBounds<T, U>)],
+        trail: u64,
+        depth: u32,
+    ) -> LightBounds<T, U> {
+        let index = self.nodes.len();
+        if let [(light, bounds)] = lights {
+            self.trails[*light] = Some(trail);
+            self.nodes.push(LightNode {
+                bounds: *bounds,
+                offset: *light,
+                is_leaf: true,
+            });
+            return *bounds;
+        }
+
+        let (first, rest) = lights.split_first().unwrap();
+        let centroids = rest.iter().fold(
+            Box3::new(first.1.centroid(), first.1.centroid()),
+            |b, (_, l)| Box3::new(b.min.min(l.centroid()), b.max.max(l.centroid())),
+        );
+        let extent = centroids.max - centroids.min;
+        let axis = if extent.x >= extent.y && extent.x >= extent.z {
+            Axis3::X
+        } else if extent.y >= extent.z {
+            Axis3::Y
+        } else {
+            Axis3::Z
+        };
+        let mid = lights.len() / 2;
+        lights.select_nth_unstable_by(mid, |a, b| {
+            a.1.centroid()[axis]
+                .partial_cmp(&b.1.centroid()[axis])
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        // The bounds are filled in once both children are built
+        self.nodes.push(LightNode {
+            bounds: lights[0].1,
+            offset: 0,
+            is_leaf: false,
+        });
+        let (left, right) = lights.split_at_mut(mid);
+        let left_bounds = self.build(left, trail, depth + 1);
+        let second = self.nodes.len();
+        let right_bounds = self.build(right, trail | (1 << depth), depth + 1);
+        let bounds = left_bounds.union(&right_bounds);
+        self.nodes[index].bounds = bounds;
+        self.nodes[index].offset = second;
+        bounds
+    }
+
+    /// The probability of picking a light at infinity rather than from the hierarchy
+    #[inline]
+    fn infinite_probability(&self) -> T {
+        let tree = usize::from(!self.nodes.is_empty());
+        let count = self.infinite.len() + tree;
+        if count == 0 {
+            T::zero()
+        } else {
+            T::from(self.infinite.len()).unwrap() / T::from(count).unwrap()
+        }
+    }
+}
+
+impl<T: Float + Send + Sync, U: Send + Sync> LightSampler<T, U> for BvhLightSampler<T, U> {
+    fn sample(&self, p: Point3<T, U>, n: Option<Normal3<T, U>>, u: T) -> Option<SampledLight<T>> {
+        let p_infinite = self.infinite_probability();
+        if u < p_infinite {
+            let count = T::from(self.infinite.len()).unwrap();
+            let i = (u / p_infinite * count).to_usize().unwrap_or(0);
+            return Some(SampledLight {
+                index: self.infinite[i.min(self.infinite.len() - 1)],
+                pmf: p_infinite / count,
+            });
+        }
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        // Descends towards the children in proportion to their importance, reusing `u`
+        let mut u = ((u - p_infinite) / (T::one() - p_infinite)).min(T::one() - T::epsilon());
+        let mut pmf = T::one() - p_infinite;
+        let mut index = 0;
+        loop {
+            let node = &self.nodes[index];
+            if node.is_leaf {
+                return (node.bounds.importance(p, n) > T::zero()).then_some(SampledLight {
+                    index: node.offset,
+                    pmf,
+                });
+            }
+            let children = [index + 1, node.offset];
+            let [i0, i1] = children.map(|child| self.nodes[child].bounds.importance(p, n));
+            if i0 == T::zero() && i1 == T::zero() {
+                return None;
+            }
+            let p0 = i0 / (i0 + i1);
+            if u < p0 {
+                u = (u / p0).min(T::one() - T::epsilon());
+                pmf = pmf * p0;
+                index = children[0];
+            } else {
+                u = ((u - p0) / (T::one() - p0)).min(T::one() - T::epsilon());
+                pmf = pmf * (T::one() - p0);
+                index = children[1];
+            }
+        }
+    }
+
+    fn pmf(&self, p: Point3<T, U>, n: Option<Normal3<T, U>>, index: usize) -> T {
+        let p_infinite = self.infinite_probability();
+        if self.infinite.contains(&index) {
+            return p_infinite / T::from(self.infinite.len()).unwrap();
+        }
+        let Some(Some(mut trail)) = self.trails.get(index).copied() else {
+            return T::zero();
+        };
+
+        // Follows the branches to the light, with the probabilities of taking them
+        let mut pmf = T::one() - p_infinite;
+        let mut node = 0;
+        while !self.nodes[node].is_leaf {
+            let children = [node + 1, self.nodes[node].offset];
+            let [i0, i1] = children.map(|child| self.nodes[child].bounds.importance(p, n));
+            if i0 == T::zero() && i1 == T::zero() {
+                return T::zero();
+            }
+            let branch = (trail & 1) as usize;
+            pmf = pmf * [i0, i1][branch] / (i0 + i1);
+            node = children[branch];
+            trail >>= 1;
+        }
+        pmf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        color::Rgb,
+        core::{
+            geometry::{UnknownUnit, Vector3},
+            units::Angle,
+        },
+        light::{DistantLight, PointLight},
+    };
+
+    #[test]
+    fn test_bvh_light_sampler() {
+        let scene_bounds = Box3::new(Point3::splat(-10.), Point3::splat(10.));
+        let mut lights: Vec<Arc<dyn Light<f64, UnknownUnit>>> = (0..7)
+            .map(|i| {
+                let position = Point3::new(f64::from(i) * 2., 0., 0.);
+                Arc::new(PointLight::new(position, Rgb::splat(1.))) as _
+            })
+            .collect();
+        lights.push(Arc::new(DistantLight::new(
+            Vector3::new(0., 0., -1.),
+            Rgb::splat(1.),
+            Angle::from_radians(0.),
+            scene_bounds,
+        )));
+        let sampler = BvhLightSampler::new(&lights);
+
+        // The probabilities of picking each light sum to one, and agree with sampling
+        let p = Point3::new(0.5, 1., 0.);
+        let pmfs: Vec<_> = (0..lights.len()).map(|i| sampler.pmf(p, None, i)).collect();
+        assert!((pmfs.iter().sum::<f64>() - 1.).abs() < 1e-12);
+        for i in 0..100 {
+            let u = (f64::from(i) + 0.5) / 100.;
+            let sampled = sampler.sample(p, None, u).unwrap();
+            assert!((sampled.pmf - pmfs[sampled.index]).abs() < 1e-12);
+        }
+
+        // The light at infinity is picked as often as the hierarchy, which prefers nearby lights
+        assert!((pmfs[7] - 0.5).abs() < 1e-12);
+        assert!(pmfs[0] > pmfs[3] && pmfs[3] > pmfs[6]);
+    }
+}
@@ -0,0 +1,272 @@
+use crate::{
+    color::Rgb,
+    core::{
+        geometry::{Box3, Point2, Point3, UnknownUnit, Vector3},
+        units::Angle,
+    },
+    light::{DirectionCone, Light, LightBounds, LightSample},
+    texture::ImageTexture,
+};
+use num_traits::{Float, FloatConst};
+use std::fmt;
+
+/// Emission from a single point into a cone, like a point light behind a shade
+///
+/// The intensity falls off smoothly to zero over the outermost `penumbra` of the cone, and can
+/// be shaped further by projecting an image through the cone, like a gobo in a stage light.
+pub struct SpotLight<T, U> {
+    pub position: Point3<T, U>,
+    /// The normalized axis of the cone
+    pub direction: Vector3<T, U>,
+    /// The radiant intensity along the axis
+    pub intensity: Rgb<T>,
+    /// The half angle of the cone, outside of which nothing is emitted
+    pub cone_angle: Angle<T>,
+    /// The angular width of the edge of the cone where the intensity falls off
+    pub penumbra: Angle<T>,
+    gobo: Option<Gobo<T, U>>,
+}
+
+/// An image projected through the cone of a spot light
+struct Gobo<T, U> {
+    texture: ImageTexture<T>,
+    /// The normalized directions of the image's `u` and `v` across the cone
+    right: Vector3<T, U>,
+    up: Vector3<T, U>,
+    average: Rgb<T>,
+}
+
+impl<T: fmt::Debug, U> fmt::Debug for SpotLight<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpotLight")
+            .field("position", &self.position)
+            .field("direction", &self.direction)
+            .field("intensity", &self.intensity)
+            .field("cone_angle", &self.cone_angle)
+            .field("penumbra", &self.penumbra)
+            .field("gobo", &self.gobo.as_ref().map(|gobo| &gobo.texture))
+            .finish()
+    }
+}
+
+impl<T: Clone, U> Clone for SpotLight<T, U> {
+    fn clone(&self) -> Self {
+        Self {
+            position: self.position.clone(),
+            direction: self.direction.clone(),
+            intensity: self.intensity.clone(),
+            cone_angle: self.cone_angle.clone(),
+            penumbra: self.penumbra.clone(),
+            gobo: self.gobo.as_ref().map(|gobo| Gobo {
+                texture: gobo.texture.clone(),
+                right: gobo.right.clone(),
+                up: gobo.up.clone(),
+                average: gobo.average.clone(),
+            }),
+        }
+    }
+}
+
+impl<T: Float, U> SpotLight<T, U> {
+    /// Creates a spot light at `position` shining towards `target`
+    #[must_use]
+    pub fn new(
+        position: Point3<T, U>,
+        target: Point3<T, U>,
+        intensity: Rgb<T>,
+        cone_angle: Angle<T>,
+        penumbra: Angle<T>,
+    ) -> Self {
+        Self {
+            position,
+            direction: (target - position).normalize(),
+            intensity,
+            cone_angle,
+            penumbra,
+            gobo: None,
+        }
+    }
+
+    /// Projects an image through the cone, which it covers up to the cone's edge with the top
+    /// of the image towards `up`
+    #[must_use]
+    pub fn with_gobo(self, texture: ImageTexture<T>, up: Vector3<T, U>) -> Self {
+        let right = self.direction.cross(up).normalize();
+        let up = right.cross(self.direction);
+        let pixels = texture.image().pixels();
+        let average = pixels.iter().copied().sum::<Rgb<T>>() / T::from(pixels.len()).unwrap();
+        Self {
+            gobo: Some(Gobo {
+                texture,
+                right,
+                up,
+                average,
+            }),
+            ..self
+        }
+    }
+
+    /// Returns the intensity emitted in the normalized direction `w`
+    #[must_use]
+    pub fn i(&self, w: Vector3<T, U>) -> Rgb<T> {
+        let cos_theta = w.dot(self.direction);
+        let cos_end = self.cone_angle.radians().cos();
+        let start = (self.cone_angle.radians() - self.penumbra.radians()).max(T::zero());
+        let falloff = smoothstep(cos_theta, cos_end, start.cos());
+        if falloff == T::zero() {
+            return Rgb::black();
+        }
+        let Some(gobo) = &self.gobo else {
+            return self.intensity * falloff;
+        };
+        // Where the direction crosses the image, at a distance of one along the axis
+        let extent = self.cone_angle.radians().tan();
+        let half = T::from(0.5).unwrap();
+        let u = (w.dot(gobo.right) / cos_theta / extent + T::one()) * half;
+        let v = (w.dot(gobo.up) / cos_theta / extent + T::one()) * half;
+        self.intensity * gobo.texture.bilinear(Point2::new(u, v)) * falloff
+    }
+}
+
+/// Rises smoothly from zero at `start` to one at `end`, or jumps there if they are equal
+#[inline]
+fn smoothstep<T: Float>(x: T, start: T, end: T) -> T {
+    if start == end {
+        return if x < start { T::zero() } else { T::one() };
+    }
+    let t = ((x - start) / (end - start)).max(T::zero()).min(T::one());
+    t * t * (T::from(3).unwrap() - (t + t))
+}
+
+impl<T: Float + FloatConst + Send + Sync, U: Send + Sync> Light<T, U> for SpotLight<T, U> {
+    fn sample_li(&self, p: Point3<T, U>, _u: Point2<T, UnknownUnit>) -> Option<LightSample<T, U>> {
+        let d = self.position - p;
+        let wi = d.try_normalize()?;
+        let radiance = self.i(-wi) / d.length_squared();
+        (!radiance.is_black()).then_some(LightSample {
+            radiance,
+            wi,
+            pdf: T::one(),
+            p: self.position,
+        })
+    }
+
+    #[inline]
+    fn pdf_li(&self, _p: Point3<T, U>, _wi: Vector3<T, U>) -> T {
+        T::zero()
+    }
+
+    fn power(&self) -> Rgb<T> {
+        // The full intensity inside of the penumbra, and half of it on average across it
+        let cos_end = self.cone_angle.radians().cos();
+        let start = (self.cone_angle.radians() - self.penumbra.radians()).max(T::zero());
+        let cos_start = start.cos();
+        let half = T::from(0.5).unwrap();
+        let solid_angle = T::TAU() * ((T::one() - cos_start) + (cos_start - cos_end) * half);
+        let average = self
+            .gobo
+            .as_ref()
+            .map_or(Rgb::splat(T::one()), |g| g.average);
+        self.intensity * average * solid_angle
+    }
+
+    fn bounds(&self) -> Option<LightBounds<T, U>> {
+        let start = (self.cone_angle.radians() - self.penumbra.radians()).max(T::zero());
+        let brightest = self.gobo.as_ref().map_or(T::one(), |gobo| {
+            let pixels = gobo.texture.image().pixels();
+            pixels
+                .iter()
+                .fold(T::zero(), |max, p| max.max(p.max_component()))
+        });
+        Some(LightBounds {
+            bounds: Box3::new(self.position, self.position),
+            intensity: self.intensity.max_component() * brightest,
+            normals: DirectionCone::new(self.direction, start.cos()),
+            // The intensity falls off from the edge of the penumbra
+            cos_theta_e: (self.cone_angle.radians() - start).cos(),
+            two_sided: false,
+        })
+    }
+
+    #[inline]
+    fn is_delta(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        accel::Bvh,
+        core::geometry::Ray,
+        image::Image,
+        integrator::{DirectLightingIntegrator, Integrator},
+        material::DiffuseMaterial,
+        sampler::{IndependentSampler, Sampler},
+        scene::{Primitive, Scene},
+        shape::Disk,
+        texture::WrapMode,
+    };
+    use std::{f64::consts::PI, sync::Arc};
+
+    type P = Point3<f64, UnknownUnit>;
+    type V = Vector3<f64, UnknownUnit>;
+
+    #[test]
+    fn test_spot_light() {
+        let angle = |degrees| Angle::from_radians(f64::to_radians(degrees));
+        let spot = SpotLight::new(
+            P::new(0., 0., 2.),
+            P::origin(),
+            Rgb::splat(8.),
+            angle(30.),
+            angle(10.),
+        );
+        let at = |light: &SpotLight<f64, UnknownUnit>, degrees: f64| {
+            let (sin, cos) = degrees.to_radians().sin_cos();
+            light.i(V::new(sin, 0., -cos)).r
+        };
+        assert_eq!(at(&spot, 0.), 8.);
+        assert_eq!(at(&spot, 19.), 8.);
+        // Halfway across the penumbra in cosine
+        let middle = f64::midpoint(20_f64.to_radians().cos(), 30_f64.to_radians().cos());
+        assert!((at(&spot, middle.acos().to_degrees()) - 4.).abs() < 1e-9);
+        assert_eq!(at(&spot, 31.), 0.);
+        let sharp = SpotLight {
+            penumbra: angle(0.),
+            ..spot.clone()
+        };
+        assert_eq!(at(&sharp, 29.9), 8.);
+        assert_eq!(at(&sharp, 30.1), 0.);
+        let expected = 8. * 2. * PI * (1. - middle);
+        assert!((spot.power().r - expected).abs() < 1e-9);
+
+        // A white floor reflects `I cos / (pi d²)` of the light within the cone only
+        let floor = Arc::new(Disk::new(P::origin(), 100., 0.));
+        let material = Arc::new(DiffuseMaterial::new(Rgb::splat(1.)));
+        let scene = Scene::new(
+            Bvh::new(vec![Primitive::new(floor, Some(material))]),
+            vec![Arc::new(spot.clone()) as Arc<dyn Light<_, _>>],
+        );
+        let mut sampler = IndependentSampler::new(1, 3);
+        sampler.start_pixel_sample(Point2::new(0, 0), 0, 0);
+        let down = |x: f64| Ray::new(P::new(x, 0., 1.), V::new(0., 0., -1.));
+        let radiance = DirectLightingIntegrator.li(&down(0.5), &scene, &mut sampler);
+        let d_squared = 4.25_f64;
+        let expected = 8. * (2. / d_squared.sqrt()) / (PI * d_squared);
+        assert!((radiance.r - expected).abs() < 1e-9, "{radiance:?}");
+        let radiance = DirectLightingIntegrator.li(&down(2.), &scene, &mut sampler);
+        assert_eq!(radiance, Rgb::black());
+
+        // A gobo dark on its right half, which the light projects towards -y
+        let image = Image::new(2, 1, vec![Rgb::splat(1.), Rgb::splat(0.)]);
+        let texture = ImageTexture::new(image, WrapMode::Clamp);
+        let gobo = spot.clone().with_gobo(texture, V::new(1., 0., 0.));
+        assert!((at(&gobo, 15.) - 4.).abs() < 1e-9);
+        let (sin, cos) = 19_f64.to_radians().sin_cos();
+        assert_eq!(gobo.i(V::new(0., sin, -cos)), Rgb::splat(8.));
+        assert_eq!(gobo.i(V::new(0., -sin, -cos)), Rgb::black());
+        assert!((gobo.power().r - spot.power().r / 2.).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,29 @@
+/// Implements `Debug`, `Copy`, `Clone` and `PartialEq` for a struct generic over a scalar `T` and
+/// a unit `U`, without requiring the unit to implement them
+macro_rules! common_impls {
+    ($ty:ident { $($field:ident),+ $(,)? }) => {
+        impl<T: std::fmt::Debug, U> std::fmt::Debug for $ty<T, U> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.debug_struct(stringify!($ty))
+                    $(.field(stringify!($field), &self.$field))+
+                    .finish()
+            }
+        }
+
+        impl<T: Copy, U> Copy for $ty<T, U> {}
+
+        impl<T: Clone, U> Clone for $ty<T, U> {
+            fn clone(&self) -> Self {
+                Self {
+                    $($field: self.$field.clone()),+
+                }
+            }
+        }
+
+        impl<T: PartialEq, U> PartialEq for $ty<T, U> {
+            fn eq(&self, other: &Self) -> bool {
+                true $(&& self.$field == other.$field)+
+            }
+        }
+    };
+}
@@ -0,0 +1,345 @@
+//! Descriptions of surface appearance, which produce the BSDF at each point of a surface
+
+use crate::{
+    bsdf::{
+        Bsdf, ConductorBsdf, DielectricBsdf, DiffuseBsdf, LayeredBsdf, Metal, MirrorBsdf,
+        OrenNayarBsdf, PrincipledBsdf, PrincipledParameters, RoughDielectricBsdf, SheenBsdf,
+        TrowbridgeReitz,
+    },
+    color::Rgb,
+    shape::SurfaceInteraction,
+    texture::Texture,
+};
+use num_traits::{Float, FloatConst};
+use std::sync::Arc;
+
+pub trait Material<T, U>: Send + Sync {
+    /// Returns the scattering at a surface point, in its shading frame
+    #[must_use]
+    fn bsdf(&self, si: &SurfaceInteraction<T, U>) -> Box<dyn Bsdf<T>>;
+
+    /// Returns the opacity of the surface if it has cutouts, which rays pass through with a
+    /// probability of one minus the opacity
+    ///
+    /// The opacity is evaluated while intersecting rays, shadow rays included, so that the
+    /// surface is only hit on its opaque parts.
+    #[inline]
+    fn alpha(&self) -> Option<&dyn Texture<T, U>> {
+        None
+    }
+}
+
+/// A matte surface with Lambertian reflection
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct DiffuseMaterial<R> {
+    /// The reflectance, an [`Rgb`] color or a texture of them
+    pub reflectance: R,
+}
+
+impl<R> DiffuseMaterial<R> {
+    #[inline]
+    #[must_use]
+    pub const fn new(reflectance: R) -> Self {
+        Self { reflectance }
+    }
+}
+
+impl<T, U, R> Material<T, U> for DiffuseMaterial<R>
+where
+    T: Float + FloatConst + Send + Sync + 'static,
+    R: Texture<T, U, Rgb<T>>,
+{
+    fn bsdf(&self, si: &SurfaceInteraction<T, U>) -> Box<dyn Bsdf<T>> {
+        Box::new(DiffuseBsdf::new(self.reflectance.evaluate(si)))
+    }
+}
+
+/// A rough matte surface with Oren–Nayar reflection, flatter looking than a Lambertian one
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct OrenNayarMaterial<R, S> {
+    pub reflectance: R,
+    /// The roughness in `[0, 1]`, with zero for a Lambertian surface
+    pub roughness: S,
+}
+
+impl<R, S> OrenNayarMaterial<R, S> {
+    #[inline]
+    #[must_use]
+    pub const fn new(reflectance: R, roughness: S) -> Self {
+        Self {
+            reflectance,
+            roughness,
+        }
+    }
+}
+
+impl<T, U, R, S> Material<T, U> for OrenNayarMaterial<R, S>
+where
+    T: Float + FloatConst + Send + Sync + 'static,
+    R: Texture<T, U, Rgb<T>>,
+    S: Texture<T, U>,
+{
+    fn bsdf(&self, si: &SurfaceInteraction<T, U>) -> Box<dyn Bsdf<T>> {
+        Box::new(OrenNayarBsdf::new(
+            self.reflectance.evaluate(si),
+            self.roughness.evaluate(si),
+        ))
+    }
+}
+
+/// The sheen of velvet and other fabrics alone, reflecting light mostly at grazing angles
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct SheenMaterial<R, T> {
+    pub color: R,
+    /// The roughness in `[0, 1]`, from a narrow rim to a broad haze
+    pub roughness: T,
+}
+
+impl<R, T> SheenMaterial<R, T> {
+    #[inline]
+    #[must_use]
+    pub const fn new(color: R, roughness: T) -> Self {
+        Self { color, roughness }
+    }
+}
+
+impl<T, U, R> Material<T, U> for SheenMaterial<R, T>
+where
+    T: Float + FloatConst + Send + Sync + 'static,
+    R: Texture<T, U, Rgb<T>>,
+{
+    fn bsdf(&self, si: &SurfaceInteraction<T, U>) -> Box<dyn Bsdf<T>> {
+        Box::new(SheenBsdf::new(self.color.evaluate(si), self.roughness))
+    }
+}
+
+/// A perfect mirror
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct MirrorMaterial<R> {
+    pub reflectance: R,
+}
+
+impl<R> MirrorMaterial<R> {
+    #[inline]
+    #[must_use]
+    pub const fn new(reflectance: R) -> Self {
+        Self { reflectance }
+    }
+}
+
+impl<T, U, R> Material<T, U> for MirrorMaterial<R>
+where
+    T: Float + Send + Sync + 'static,
+    R: Texture<T, U, Rgb<T>>,
+{
+    fn bsdf(&self, si: &SurfaceInteraction<T, U>) -> Box<dyn Bsdf<T>> {
+        Box::new(MirrorBsdf::new(self.reflectance.evaluate(si)))
+    }
+}
+
+/// Glass, water and other clear dielectrics, smooth or frosted
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct DielectricMaterial<T> {
+    /// The index of refraction of the inside of the surface, relative to the outside
+    pub eta: T,
+    /// The perceptual roughness in `[0, 1]`, with zero for a polished surface
+    pub roughness: T,
+}
+
+impl<T> DielectricMaterial<T> {
+    #[inline]
+    #[must_use]
+    pub const fn new(eta: T, roughness: T) -> Self {
+        Self { eta, roughness }
+    }
+}
+
+impl<T: Float + FloatConst + Send + Sync + 'static, U> Material<T, U> for DielectricMaterial<T> {
+    fn bsdf(&self, _si: &SurfaceInteraction<T, U>) -> Box<dyn Bsdf<T>> {
+        let alpha = TrowbridgeReitz::roughness_to_alpha(self.roughness);
+        let distribution = TrowbridgeReitz::new(alpha, alpha);
+        if distribution.effectively_smooth() {
+            Box::new(DielectricBsdf::new(self.eta))
+        } else {
+            Box::new(RoughDielectricBsdf::new(distribution, self.eta))
+        }
+    }
+}
+
+/// A metal, smooth or rough, given by its complex index of refraction
+///
+/// Different roughnesses along the tangent and bitangent of the surface, the directions of
+/// increasing `u` and `v`, give the stretched highlights of brushed metal.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct ConductorMaterial<T> {
+    /// The index of refraction relative to the outside
+    pub eta: Rgb<T>,
+    /// The absorption coefficient relative to the outside
+    pub k: Rgb<T>,
+    /// The perceptual roughness in `[0, 1]` along the tangent, with zero for a polished surface
+    pub roughness_u: T,
+    /// The perceptual roughness in `[0, 1]` along the bitangent
+    pub roughness_v: T,
+    /// The counterclockwise angle in radians from the tangent to the direction of
+    /// `roughness_u`
+    pub rotation: T,
+}
+
+impl<T: Float> ConductorMaterial<T> {
+    /// Returns an isotropic metal
+    #[inline]
+    #[must_use]
+    pub fn new(eta: Rgb<T>, k: Rgb<T>, roughness: T) -> Self {
+        Self {
+            eta,
+            k,
+            roughness_u: roughness,
+            roughness_v: roughness,
+            rotation: T::zero(),
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn from_metal(metal: Metal, roughness: T) -> Self {
+        Self::new(metal.eta(), metal.k(), roughness)
+    }
+
+    /// Returns the material with separate roughnesses along the tangent and bitangent
+    #[inline]
+    #[must_use]
+    pub fn with_anisotropic_roughness(self, roughness_u: T, roughness_v: T) -> Self {
+        Self {
+            roughness_u,
+            roughness_v,
+            ..self
+        }
+    }
+
+    /// Returns the material with the directions of its roughnesses rotated by `angle` radians
+    #[inline]
+    #[must_use]
+    pub fn with_rotation(self, angle: T) -> Self {
+        Self {
+            rotation: angle,
+            ..self
+        }
+    }
+}
+
+impl<T: Float + FloatConst + Send + Sync + 'static, U> Material<T, U> for ConductorMaterial<T> {
+    fn bsdf(&self, _si: &SurfaceInteraction<T, U>) -> Box<dyn Bsdf<T>> {
+        let distribution = TrowbridgeReitz::new(
+            TrowbridgeReitz::roughness_to_alpha(self.roughness_u),
+            TrowbridgeReitz::roughness_to_alpha(self.roughness_v),
+        )
+        .with_rotation(self.rotation);
+        Box::new(ConductorBsdf::new(distribution, self.eta, self.k))
+    }
+}
+
+/// The principled material, combining the lobes of most real materials from a few intuitive
+/// parameters
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct PrincipledMaterial<T> {
+    pub params: PrincipledParameters<T>,
+}
+
+impl<T> PrincipledMaterial<T> {
+    #[inline]
+    #[must_use]
+    pub const fn new(params: PrincipledParameters<T>) -> Self {
+        Self { params }
+    }
+}
+
+impl<T: Float + FloatConst + Send + Sync + 'static, U> Material<T, U> for PrincipledMaterial<T> {
+    fn bsdf(&self, _si: &SurfaceInteraction<T, U>) -> Box<dyn Bsdf<T>> {
+        Box::new(PrincipledBsdf::new(&self.params))
+    }
+}
+
+/// A coating over an opaque base, such as varnish over wood or a clear coat over metal, given
+/// by the materials of each layer
+pub struct LayeredMaterial<T, U> {
+    /// The coating, usually a [`DielectricMaterial`]
+    pub top: Arc<dyn Material<T, U>>,
+    pub bottom: Arc<dyn Material<T, U>>,
+    /// The thickness of the medium between the layers, in units of its mean free path
+    pub thickness: T,
+    /// The single-scattering albedo of the medium, with black for a clear coating
+    pub albedo: Rgb<T>,
+    /// The asymmetry of the phase function of the medium
+    pub g: T,
+}
+
+impl<T: Float, U> LayeredMaterial<T, U> {
+    /// Returns a clear, thin coating
+    #[must_use]
+    pub fn new(top: Arc<dyn Material<T, U>>, bottom: Arc<dyn Material<T, U>>) -> Self {
+        Self {
+            top,
+            bottom,
+            thickness: T::from(0.01).unwrap(),
+            albedo: Rgb::black(),
+            g: T::zero(),
+        }
+    }
+
+    /// Returns the coating with a medium of the given thickness and scattering between the
+    /// layers
+    #[must_use]
+    pub fn with_medium(self, thickness: T, albedo: Rgb<T>, g: T) -> Self {
+        Self {
+            thickness,
+            albedo,
+            g,
+            ..self
+        }
+    }
+}
+
+impl<T: Float + FloatConst + Send + Sync + 'static, U> Material<T, U> for LayeredMaterial<T, U> {
+    fn bsdf(&self, si: &SurfaceInteraction<T, U>) -> Box<dyn Bsdf<T>> {
+        Box::new(
+            LayeredBsdf::new(self.top.bsdf(si), self.bottom.bsdf(si)).with_medium(
+                self.thickness,
+                self.albedo,
+                self.g,
+            ),
+        )
+    }
+}
+
+/// A material with cutouts given by an opacity texture, such as a leaf or a fence drawn on a
+/// single quad
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct MaskedMaterial<M, A> {
+    pub material: M,
+    /// The opacity, usually zero or one, or a texture of it
+    pub alpha: A,
+}
+
+impl<M, A> MaskedMaterial<M, A> {
+    #[inline]
+    #[must_use]
+    pub const fn new(material: M, alpha: A) -> Self {
+        Self { material, alpha }
+    }
+}
+
+impl<T, U, M, A> Material<T, U> for MaskedMaterial<M, A>
+where
+    M: Material<T, U>,
+    A: Texture<T, U>,
+{
+    #[inline]
+    fn bsdf(&self, si: &SurfaceInteraction<T, U>) -> Box<dyn Bsdf<T>> {
+        self.material.bsdf(si)
+    }
+
+    #[inline]
+    fn alpha(&self) -> Option<&dyn Texture<T, U>> {
+        Some(&self.alpha)
+    }
+}
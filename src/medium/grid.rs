@@ -0,0 +1,292 @@
+use crate::{
+    color::Rgb,
+    core::{
+        geometry::{Box3, Point3, Ray},
+        units::Time,
+    },
+    medium::{HenyeyGreenstein, MajorantSegment, Medium, MediumProperties, PhaseFunction},
+};
+use num_traits::{Float, FloatConst};
+
+/// The number of cells of the majorant grid along each axis
+const MAJORANT_RESOLUTION: usize = 16;
+
+/// Values sampled at the centers of the cells of a regular grid over the unit cube, and
+/// interpolated trilinearly between them
+#[derive(Debug, Clone, PartialEq)]
+pub struct DensityGrid<T> {
+    size: [usize; 3],
+    values: Vec<T>,
+}
+
+impl<T: Float> DensityGrid<T> {
+    /// Creates a grid from its values in x-major order, then y, then z
+    ///
+    /// Panics if the number of values does not match the size or the grid is empty.
+    #[must_use]
+    pub fn new(size: [usize; 3], values: Vec<T>) -> Self {
+        assert_eq!(
+            values.len(),
+            size.iter().product(),
+            "wrong number of values"
+        );
+        assert!(!values.is_empty(), "grids need at least one value");
+        Self { size, values }
+    }
+
+    /// The number of values along each axis
+    #[inline]
+    #[must_use]
+    pub fn size(&self) -> [usize; 3] {
+        self.size
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    /// Returns the value at an index, clamped to the grid
+    #[inline]
+    #[must_use]
+    pub fn get(&self, index: [i64; 3]) -> T {
+        let [x, y, z] = [0, 1, 2].map(|i| index[i].clamp(0, self.size[i] as i64 - 1) as usize);
+        self.values[(z * self.size[1] + y) * self.size[0] + x]
+    }
+
+    /// Interpolates the values at a point of the unit cube
+    #[must_use]
+    pub fn lookup(&self, p: [T; 3]) -> T {
+        let half = T::from(0.5).unwrap();
+        let mut index = [0; 3];
+        let mut frac = [T::zero(); 3];
+        for i in 0..3 {
+            let x = p[i] * T::from(self.size[i]).unwrap() - half;
+            let floor = x.floor();
+            index[i] = floor.to_i64().unwrap_or(0);
+            frac[i] = x - floor;
+        }
+        let lerp = |t: T, a: T, b: T| a + (b - a) * t;
+        let [x, y, z] = index;
+        let corner = |dx, dy, dz| self.get([x + dx, y + dy, z + dz]);
+        let along_x = |dy, dz| lerp(frac[0], corner(0, dy, dz), corner(1, dy, dz));
+        let along_y = |dz| lerp(frac[1], along_x(0, dz), along_x(1, dz));
+        lerp(frac[2], along_y(0), along_y(1))
+    }
+
+    /// Returns the largest value interpolated within a box of the unit cube
+    #[must_use]
+    pub fn max_value(&self, min: [T; 3], max: [T; 3]) -> T {
+        // The values whose cells can contribute to points of the box
+        let half = T::from(0.5).unwrap();
+        let index = |p: T, i: usize| {
+            let x = p * T::from(self.size[i]).unwrap() - half;
+            x.floor().to_i64().unwrap_or(0)
+        };
+        let clamp = |x: i64, i: usize| x.clamp(0, self.size[i] as i64 - 1);
+        let lo = [0, 1, 2].map(|i| clamp(index(min[i], i), i));
+        let hi = [0, 1, 2].map(|i| clamp(index(max[i], i) + 1, i));
+        let mut value = T::neg_infinity();
+        for z in lo[2]..=hi[2] {
+            for y in lo[1]..=hi[1] {
+                for x in lo[0]..=hi[0] {
+                    value = value.max(self.get([x, y, z]));
+                }
+            }
+        }
+        value
+    }
+}
+
+/// A medium whose coefficients are scaled by a density varying over a box, such as smoke or
+/// clouds
+///
+/// Collisions are tracked against a coarse grid of the largest density in each of its cells,
+/// so that empty and thin regions are crossed in few steps.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridMedium<T, U> {
+    bounds: Box3<T, U>,
+    sigma_a: Rgb<T>,
+    sigma_s: Rgb<T>,
+    density: DensityGrid<T>,
+    majorants: DensityGrid<T>,
+    phase: HenyeyGreenstein<T>,
+}
+
+impl<T: Float, U> GridMedium<T, U> {
+    /// Stretches the density grid over `bounds`, outside of which the medium is empty, and
+    /// scatters equally in all directions
+    #[must_use]
+    pub fn new(
+        bounds: Box3<T, U>,
+        sigma_a: Rgb<T>,
+        sigma_s: Rgb<T>,
+        density: DensityGrid<T>,
+    ) -> Self {
+        let n = MAJORANT_RESOLUTION;
+        let cell = |i: usize| T::from(i).unwrap() / T::from(n).unwrap();
+        let mut majorants = Vec::with_capacity(n * n * n);
+        for z in 0..n {
+            for y in 0..n {
+                for x in 0..n {
+                    let min = [cell(x), cell(y), cell(z)];
+                    let max = [cell(x + 1), cell(y + 1), cell(z + 1)];
+                    majorants.push(density.max_value(min, max).max(T::zero()));
+                }
+            }
+        }
+        Self {
+            bounds,
+            sigma_a,
+            sigma_s,
+            density,
+            majorants: DensityGrid::new([n; 3], majorants),
+            phase: HenyeyGreenstein { g: T::zero() },
+        }
+    }
+
+    #[must_use]
+    pub fn with_phase(mut self, phase: HenyeyGreenstein<T>) -> Self {
+        self.phase = phase;
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn bounds(&self) -> Box3<T, U> {
+        self.bounds
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn density(&self) -> &DensityGrid<T> {
+        &self.density
+    }
+
+    /// Maps a point to the unit cube over the bounds
+    #[inline]
+    fn to_grid(&self, p: Point3<T, U>) -> [T; 3] {
+        let (min, max) = (self.bounds.min, self.bounds.max);
+        [
+            (p.x - min.x) / (max.x - min.x),
+            (p.y - min.y) / (max.y - min.y),
+            (p.z - min.z) / (max.z - min.z),
+        ]
+    }
+}
+
+impl<T: Float + FloatConst + Send + Sync, U: Send + Sync> Medium<T, U> for GridMedium<T, U> {
+    fn properties(&self, p: Point3<T, U>) -> MediumProperties<T> {
+        let density = if self.bounds.contains(p) {
+            self.density.lookup(self.to_grid(p)).max(T::zero())
+        } else {
+            T::zero()
+        };
+        MediumProperties {
+            sigma_a: self.sigma_a * density,
+            sigma_s: self.sigma_s * density,
+        }
+    }
+
+    #[inline]
+    fn phase(&self) -> &dyn PhaseFunction<T, U> {
+        &self.phase
+    }
+
+    fn majorants(&self, ray: &Ray<T, U>, t_max: T) -> Vec<MajorantSegment<T>> {
+        let Some((t_min, t_max)) = self.bounds.intersect_ray(ray, Time(t_max)) else {
+            return Vec::new();
+        };
+        let sigma_t = (self.sigma_a + self.sigma_s).max_component();
+
+        // Steps through the cells of the majorant grid with a 3D DDA, in grid space where the
+        // ray parameter is unchanged
+        let n = MAJORANT_RESOLUTION as i64;
+        let res = T::from(n).unwrap();
+        let p = self.to_grid(ray.at(Time(t_min)));
+        let extent = self.bounds.max - self.bounds.min;
+        let dir = [
+            ray.dir.x / extent.x,
+            ray.dir.y / extent.y,
+            ray.dir.z / extent.z,
+        ];
+        let mut voxel = [0; 3];
+        let mut step = [0; 3];
+        let mut next_crossing = [T::infinity(); 3];
+        let mut delta_t = [T::infinity(); 3];
+        for i in 0..3 {
+            voxel[i] = (p[i] * res).floor().to_i64().unwrap_or(0).clamp(0, n - 1);
+            let boundary = if dir[i] > T::zero() {
+                step[i] = 1;
+                T::from(voxel[i] + 1).unwrap() / res
+            } else if dir[i] < T::zero() {
+                step[i] = -1;
+                T::from(voxel[i]).unwrap() / res
+            } else {
+                continue;
+            };
+            next_crossing[i] = t_min + (boundary - p[i]) / dir[i];
+            delta_t[i] = (res * dir[i]).abs().recip();
+        }
+
+        let mut segments = Vec::new();
+        let mut t = t_min;
+        loop {
+            let axis = (0..3)
+                .min_by(|&a, &b| next_crossing[a].partial_cmp(&next_crossing[b]).unwrap())
+                .unwrap();
+            let t_exit = next_crossing[axis].min(t_max);
+            segments.push(MajorantSegment {
+                t_min: t,
+                t_max: t_exit,
+                sigma_maj: self.majorants.get(voxel) * sigma_t,
+            });
+            t = t_exit;
+            voxel[axis] += step[axis];
+            if t >= t_max || !(0..n).contains(&voxel[axis]) {
+                return segments;
+            }
+            next_crossing[axis] = next_crossing[axis] + delta_t[axis];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::geometry::{UnknownUnit, Vector3},
+        medium::ratio_tracking,
+        sampler::Pcg32,
+    };
+
+    #[test]
+    fn test_grid() {
+        // Density rising linearly along x, from 0 at x = 0.25 to 1 at x = 0.75
+        let grid = DensityGrid::new([2, 1, 1], vec![0., 1.]);
+        assert_eq!(grid.lookup([0.5, 0.3, 0.9]), 0.5);
+        assert_eq!(grid.lookup([0.1, 0.5, 0.5]), 0.);
+        assert_eq!(grid.max_value([0., 0., 0.], [0.2, 1., 1.]), 0.);
+        assert_eq!(grid.max_value([0., 0., 0.], [0.3, 1., 1.]), 1.);
+
+        let bounds = Box3::new(Point3::new(0., 0., 0.), Point3::new(2., 1., 1.));
+        let medium: GridMedium<f64, UnknownUnit> =
+            GridMedium::new(bounds, Rgb::splat(1.), Rgb::black(), grid);
+        let ray = Ray::new(Point3::new(-1., 0.5, 0.5), Vector3::new(1., 0., 0.));
+        let segments = medium.majorants(&ray, 10.);
+        assert_eq!(segments.len(), MAJORANT_RESOLUTION);
+        assert_eq!((segments[0].t_min, segments[15].t_max), (1., 3.));
+        assert!(segments.windows(2).all(|s| s[0].t_max == s[1].t_min));
+        assert_eq!(segments[0].sigma_maj, 0.);
+
+        // The optical depth across is that of a density of 1/2 over 2 units
+        let mut rng = Pcg32::new(0);
+        let n = 20_000;
+        let mean = (0..n)
+            .map(|_| ratio_tracking(&medium, &ray, Time(10.), &mut rng).g)
+            .sum::<f64>()
+            / f64::from(n);
+        assert!((mean - (-1_f64).exp()).abs() < 0.01, "{mean}");
+    }
+}
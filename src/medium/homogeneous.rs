@@ -0,0 +1,107 @@
+use crate::{
+    color::Rgb,
+    core::{
+        geometry::{Point3, Ray},
+        units::Time,
+    },
+    medium::{HenyeyGreenstein, MajorantSegment, Medium, MediumProperties, PhaseFunction},
+    sampler::Pcg32,
+};
+use num_traits::{Float, FloatConst};
+
+/// A medium with the same coefficients everywhere, such as clear water or uniform fog
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct HomogeneousMedium<T> {
+    pub sigma_a: Rgb<T>,
+    pub sigma_s: Rgb<T>,
+    pub phase: HenyeyGreenstein<T>,
+}
+
+impl<T: Float> HomogeneousMedium<T> {
+    /// Creates a medium scattering equally in all directions
+    #[inline]
+    #[must_use]
+    pub fn new(sigma_a: Rgb<T>, sigma_s: Rgb<T>) -> Self {
+        Self {
+            sigma_a,
+            sigma_s,
+            phase: HenyeyGreenstein { g: T::zero() },
+        }
+    }
+
+    /// Returns the fraction of light of each channel travelling the given distance without
+    /// being absorbed or scattered, by the Beer–Lambert law
+    #[inline]
+    #[must_use]
+    pub fn transmittance_over(&self, distance: T) -> Rgb<T> {
+        (self.sigma_a + self.sigma_s).map(|sigma_t| (-sigma_t * distance).exp())
+    }
+}
+
+impl<T: Float + FloatConst + Send + Sync, U> Medium<T, U> for HomogeneousMedium<T> {
+    #[inline]
+    fn properties(&self, _: Point3<T, U>) -> MediumProperties<T> {
+        MediumProperties {
+            sigma_a: self.sigma_a,
+            sigma_s: self.sigma_s,
+        }
+    }
+
+    #[inline]
+    fn majorants(&self, _: &Ray<T, U>, t_max: T) -> Vec<MajorantSegment<T>> {
+        vec![MajorantSegment {
+            t_min: T::zero(),
+            t_max,
+            sigma_maj: (self.sigma_a + self.sigma_s).max_component(),
+        }]
+    }
+
+    #[inline]
+    fn phase(&self) -> &dyn PhaseFunction<T, U> {
+        &self.phase
+    }
+
+    /// The exact transmittance, without random sampling
+    #[inline]
+    fn transmittance(&self, ray: &Ray<T, U>, t_max: Time<T>, _: &mut Pcg32) -> Rgb<T> {
+        if t_max.0.is_infinite() {
+            return (self.sigma_a + self.sigma_s).map(|sigma_t| {
+                if sigma_t > T::zero() {
+                    T::zero()
+                } else {
+                    T::one()
+                }
+            });
+        }
+        self.transmittance_over(ray.dir.length() * t_max.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::geometry::{UnknownUnit, Vector3},
+        medium::ratio_tracking,
+    };
+
+    #[test]
+    fn test_transmittance() {
+        let medium = HomogeneousMedium::new(Rgb::new(0.5, 0., 1.), Rgb::new(0., 0.25, 1.));
+        let ray = Ray::<f64, UnknownUnit>::new(Point3::origin(), Vector3::new(0., 0., 2.));
+        let mut rng = Pcg32::new(0);
+        let tr = medium.transmittance(&ray, Time(0.5), &mut rng);
+        assert_eq!(tr, Rgb::new(-0.5_f64, -0.25, -2.).map(f64::exp));
+
+        // Ratio tracking converges to the same
+        let n = 20_000;
+        let mut sum = Rgb::black();
+        for _ in 0..n {
+            sum += ratio_tracking(&medium, &ray, Time(0.5), &mut rng);
+        }
+        let mean = sum / f64::from(n);
+        for (a, b) in mean.to_array().into_iter().zip(tr.to_array()) {
+            assert!((a - b).abs() < 0.01, "{mean:?}");
+        }
+    }
+}
@@ -0,0 +1,215 @@
+//! Participating media, which absorb and scatter light along rays instead of only at surfaces
+
+mod grid;
+mod homogeneous;
+#[cfg(feature = "nanovdb")]
+mod nanovdb;
+mod phase;
+
+pub use grid::{DensityGrid, GridMedium};
+pub use homogeneous::HomogeneousMedium;
+#[cfg(feature = "nanovdb")]
+pub use nanovdb::VolumeError;
+pub use phase::{HenyeyGreenstein, IsotropicPhase, PhaseFunction, PhaseSample};
+
+use crate::{
+    color::Rgb,
+    core::{
+        geometry::{Point3, Ray, Vector3},
+        prelude::Normal3,
+        units::Time,
+    },
+    sampler::Pcg32,
+};
+use num_traits::Float;
+use std::{fmt, sync::Arc};
+
+/// The coefficients of a medium at a point, per unit of distance
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct MediumProperties<T> {
+    /// The absorption coefficient
+    pub sigma_a: Rgb<T>,
+    /// The scattering coefficient
+    pub sigma_s: Rgb<T>,
+}
+
+impl<T: Float> MediumProperties<T> {
+    /// The attenuation coefficient, the sum of absorption and scattering
+    #[inline]
+    #[must_use]
+    pub fn sigma_t(&self) -> Rgb<T> {
+        self.sigma_a + self.sigma_s
+    }
+}
+
+/// A bound of the attenuation coefficient of every channel over a segment of a ray
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct MajorantSegment<T> {
+    pub t_min: T,
+    pub t_max: T,
+    pub sigma_maj: T,
+}
+
+/// A volume of material light travels through
+///
+/// Media are sampled by tracking collisions with a majorant, a bound of their attenuation,
+/// and resolving each collision as absorption, scattering or a null collision.
+pub trait Medium<T, U>: Send + Sync {
+    /// Returns the coefficients at a point
+    #[must_use]
+    fn properties(&self, p: Point3<T, U>) -> MediumProperties<T>;
+
+    /// Returns the segments of a ray with a normalized direction which cross the medium, in
+    /// order, up to the distance `t_max`
+    #[must_use]
+    fn majorants(&self, ray: &Ray<T, U>, t_max: T) -> Vec<MajorantSegment<T>>;
+
+    /// The distribution of directions light is scattered into
+    #[must_use]
+    fn phase(&self) -> &dyn PhaseFunction<T, U>;
+
+    /// Estimates the fraction of light of each channel that travels along the ray up to
+    /// `t_max` without being absorbed or scattered, by [`ratio_tracking`] unless known exactly
+    #[must_use]
+    fn transmittance(&self, ray: &Ray<T, U>, t_max: Time<T>, rng: &mut Pcg32) -> Rgb<T>
+    where
+        T: Float,
+    {
+        ratio_tracking(self, ray, t_max, rng)
+    }
+}
+
+/// The media on either side of a surface, where absent media are vacuum
+pub struct MediumInterface<T, U> {
+    /// The medium on the side opposite to the geometric normal
+    pub inside: Option<Arc<dyn Medium<T, U>>>,
+    /// The medium on the side of the geometric normal
+    pub outside: Option<Arc<dyn Medium<T, U>>>,
+}
+
+impl<T, U> fmt::Debug for MediumInterface<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MediumInterface")
+            .field("inside", &self.inside.is_some())
+            .field("outside", &self.outside.is_some())
+            .finish()
+    }
+}
+
+impl<T, U> Clone for MediumInterface<T, U> {
+    fn clone(&self) -> Self {
+        Self {
+            inside: self.inside.clone(),
+            outside: self.outside.clone(),
+        }
+    }
+}
+
+impl<T: Float, U> MediumInterface<T, U> {
+    #[inline]
+    #[must_use]
+    pub fn new(
+        inside: Option<Arc<dyn Medium<T, U>>>,
+        outside: Option<Arc<dyn Medium<T, U>>>,
+    ) -> Self {
+        Self { inside, outside }
+    }
+
+    /// Returns the medium a direction leaving the surface enters
+    #[inline]
+    #[must_use]
+    pub fn medium(&self, n: Normal3<T, U>, w: Vector3<T, U>) -> Option<&Arc<dyn Medium<T, U>>> {
+        if n.to_vector().dot(w) > T::zero() {
+            self.outside.as_ref()
+        } else {
+            self.inside.as_ref()
+        }
+    }
+}
+
+/// Estimates the fraction of light of each channel that travels along a ray up to `t_max`
+/// without being absorbed or scattered, by ratio tracking
+#[must_use]
+pub fn ratio_tracking<T: Float, U, M: Medium<T, U> + ?Sized>(
+    medium: &M,
+    ray: &Ray<T, U>,
+    t_max: Time<T>,
+    rng: &mut Pcg32,
+) -> Rgb<T> {
+    let length = ray.dir.length();
+    if length == T::zero() {
+        return Rgb::splat(T::one());
+    }
+    let ray = Ray {
+        dir: ray.dir / length,
+        ..*ray
+    };
+    let mut tr = Rgb::splat(T::one());
+    for segment in medium.majorants(&ray, t_max.0 * length) {
+        if segment.sigma_maj <= T::zero() {
+            continue;
+        }
+        let mut t = segment.t_min;
+        loop {
+            t = t - (T::one() - rng.uniform::<T>()).ln() / segment.sigma_maj;
+            if t >= segment.t_max {
+                break;
+            }
+            // Each collision keeps the fraction of the majorant that is null
+            let sigma_t = medium.properties(ray.at(Time(t))).sigma_t();
+            tr *= (Rgb::splat(segment.sigma_maj) - sigma_t) / segment.sigma_maj;
+            if tr.is_black() {
+                return tr;
+            }
+        }
+    }
+    tr
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::geometry::UnknownUnit;
+
+    /// A medium filling all of space, with a majorant twice its attenuation
+    struct Fog(MediumProperties<f64>);
+
+    impl Medium<f64, UnknownUnit> for Fog {
+        fn properties(&self, _: Point3<f64, UnknownUnit>) -> MediumProperties<f64> {
+            self.0
+        }
+
+        fn majorants(&self, _: &Ray<f64, UnknownUnit>, t_max: f64) -> Vec<MajorantSegment<f64>> {
+            let sigma_maj = 2. * self.0.sigma_t().max_component();
+            vec![MajorantSegment {
+                t_min: 0.,
+                t_max,
+                sigma_maj,
+            }]
+        }
+
+        fn phase(&self) -> &dyn PhaseFunction<f64, UnknownUnit> {
+            &IsotropicPhase
+        }
+    }
+
+    #[test]
+    fn test_ratio_tracking() {
+        let fog = Fog(MediumProperties {
+            sigma_a: Rgb::new(0.5, 1., 0.),
+            sigma_s: Rgb::new(0., 0.5, 0.25),
+        });
+        let ray = Ray::new(Point3::origin(), Vector3::new(0., 2., 0.));
+        let mut rng = Pcg32::new(0);
+        let n = 20_000;
+        let mut sum = Rgb::black();
+        for _ in 0..n {
+            sum += ratio_tracking(&fog, &ray, Time(1.), &mut rng);
+        }
+        let mean = sum / f64::from(n);
+        let expected = Rgb::new(-1., -3., -0.5).map(f64::exp);
+        for (a, b) in mean.to_array().into_iter().zip(expected.to_array()) {
+            assert!((a - b).abs() < 0.01, "{mean:?}");
+        }
+    }
+}
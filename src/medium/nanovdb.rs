@@ -0,0 +1,299 @@
+//! Reading the density grids of NanoVDB files
+
+use crate::{
+    color::Rgb,
+    core::geometry::{Box3, Point3},
+    medium::{DensityGrid, GridMedium},
+};
+use num_traits::Float;
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufReader, Read},
+    path::Path,
+};
+
+/// The magic numbers of files, "NanoVDB0" and "NanoVDB1" in little endian
+const FILE_MAGICS: [u64; 2] = [0x3042_4456_6f6e_614e, 0x3142_4456_6f6e_614e];
+/// The magic number of grids, "NanoVDB0"
+const GRID_MAGIC: u64 = 0x3042_4456_6f6e_614e;
+const GRID_TYPE_FLOAT: u32 = 1;
+const CODEC_NONE: u16 = 0;
+
+const FILE_HEADER_SIZE: usize = 16;
+const FILE_METADATA_SIZE: usize = 176;
+/// The size of the grid header, followed by that of the tree
+const GRID_DATA_SIZE: usize = 672;
+const LEAF_DIM: i64 = 8;
+/// The size of a leaf of `f32` values, with their offset within it
+const LEAF_SIZE: usize = 2144;
+const LEAF_VALUES_OFFSET: usize = 96;
+/// Refuses grids larger than this many voxels, which are likely corrupt
+const MAX_VOXELS: usize = 1 << 30;
+
+/// Why a volume could not be read
+#[derive(Debug)]
+pub enum VolumeError {
+    Io(io::Error),
+    /// The data is not a volume of the expected format, or is corrupt
+    InvalidFormat,
+    /// The volume uses a feature of the format that is not supported
+    Unsupported(String),
+}
+
+impl fmt::Display for VolumeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "failed to read volume: {e}"),
+            Self::InvalidFormat => f.write_str("invalid volume data"),
+            Self::Unsupported(feature) => write!(f, "unsupported volume feature: {feature}"),
+        }
+    }
+}
+
+impl std::error::Error for VolumeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for VolumeError {
+    #[inline]
+    fn from(e: io::Error) -> Self {
+        if e.kind() == io::ErrorKind::UnexpectedEof {
+            Self::InvalidFormat
+        } else {
+            Self::Io(e)
+        }
+    }
+}
+
+impl<T: Float, U> GridMedium<T, U> {
+    /// Reads the first grid of `f32` values of an uncompressed NanoVDB file, scaling the
+    /// coefficients by its values
+    ///
+    /// The voxels stored in leaves are resampled to a dense grid over their bounds, while
+    /// constant tiles of the upper levels of the tree are ignored, as are rotations of the
+    /// grid.
+    pub fn read_nanovdb(
+        reader: impl Read,
+        sigma_a: Rgb<T>,
+        sigma_s: Rgb<T>,
+    ) -> Result<Self, VolumeError> {
+        let mut r = BufReader::new(reader);
+        let mut header = [0; FILE_HEADER_SIZE];
+        r.read_exact(&mut header)?;
+        if !FILE_MAGICS.contains(&u64_at(&header, 0)) {
+            return Err(VolumeError::InvalidFormat);
+        }
+        let grid_count = u16_at(&header, 12);
+
+        for _ in 0..grid_count {
+            let mut metadata = [0; FILE_METADATA_SIZE];
+            r.read_exact(&mut metadata)?;
+            let grid_size =
+                usize::try_from(u64_at(&metadata, 0)).map_err(|_| VolumeError::InvalidFormat)?;
+            let file_size = u64_at(&metadata, 8);
+            let name_size = u32_at(&metadata, 136);
+            let codec = u16_at(&metadata, 168);
+            io::copy(&mut (&mut r).take(name_size.into()), &mut io::sink())?;
+
+            let stored_size = if codec == CODEC_NONE {
+                grid_size as u64
+            } else {
+                file_size
+            };
+            if u32_at(&metadata, 32) != GRID_TYPE_FLOAT {
+                io::copy(&mut (&mut r).take(stored_size), &mut io::sink())?;
+                continue;
+            }
+            if codec != CODEC_NONE {
+                return Err(VolumeError::Unsupported(format!("codec {codec}")));
+            }
+            let mut grid = Vec::new();
+            (&mut r).take(stored_size).read_to_end(&mut grid)?;
+            if grid.len() != grid_size {
+                return Err(VolumeError::InvalidFormat);
+            }
+            return parse_grid(&grid, sigma_a, sigma_s);
+        }
+        Err(VolumeError::Unsupported("grids without f32 values".into()))
+    }
+
+    /// Reads a NanoVDB file at `path`, see [`read_nanovdb`](Self::read_nanovdb)
+    pub fn load_nanovdb(
+        path: impl AsRef<Path>,
+        sigma_a: Rgb<T>,
+        sigma_s: Rgb<T>,
+    ) -> Result<Self, VolumeError> {
+        Self::read_nanovdb(File::open(path)?, sigma_a, sigma_s)
+    }
+}
+
+/// Rasterizes the leaves of an in-memory grid
+fn parse_grid<T: Float, U>(
+    grid: &[u8],
+    sigma_a: Rgb<T>,
+    sigma_s: Rgb<T>,
+) -> Result<GridMedium<T, U>, VolumeError> {
+    if grid.len() < GRID_DATA_SIZE + 64 || u64_at(grid, 0) != GRID_MAGIC {
+        return Err(VolumeError::InvalidFormat);
+    }
+    // The translation of the index to world map, and the scale of its diagonal
+    let translation = [0, 1, 2].map(|i| f64_at(grid, 528 + 8 * i));
+    let voxel_size = [0, 1, 2].map(|i| f64_at(grid, 608 + 8 * i));
+    let tree = GRID_DATA_SIZE;
+    let leaf_offset = usize::try_from(u64_at(grid, tree))
+        .ok()
+        .and_then(|offset| offset.checked_add(tree))
+        .ok_or(VolumeError::InvalidFormat)?;
+    let leaf_count = u32_at(grid, tree + 32) as usize;
+    let leaves = leaf_count
+        .checked_mul(LEAF_SIZE)
+        .and_then(|size| grid.get(leaf_offset..leaf_offset.checked_add(size)?))
+        .ok_or(VolumeError::InvalidFormat)?;
+    if leaves.is_empty() {
+        return Err(VolumeError::Unsupported("grids without leaves".into()));
+    }
+
+    // Leaves are aligned to multiples of their size in index space
+    let origin = |leaf: &[u8]| [0, 1, 2].map(|i| i64::from(i32_at(leaf, 4 * i)) & !(LEAF_DIM - 1));
+    let (mut min, mut max) = ([i64::MAX; 3], [i64::MIN; 3]);
+    for leaf in leaves.chunks_exact(LEAF_SIZE) {
+        let origin = origin(leaf);
+        for i in 0..3 {
+            min[i] = min[i].min(origin[i]);
+            max[i] = max[i].max(origin[i] + LEAF_DIM);
+        }
+    }
+    let size = [0, 1, 2].map(|i| (max[i] - min[i]) as usize);
+    if size
+        .iter()
+        .try_fold(1_usize, |n, &s| n.checked_mul(s))
+        .is_none_or(|n| n > MAX_VOXELS)
+    {
+        return Err(VolumeError::Unsupported("grids this large".into()));
+    }
+
+    let mut values = vec![T::zero(); size.iter().product()];
+    for leaf in leaves.chunks_exact(LEAF_SIZE) {
+        let origin = origin(leaf);
+        // Voxels are stored with z varying fastest, the opposite of the dense grid
+        for (n, value) in leaf[LEAF_VALUES_OFFSET..].chunks_exact(4).enumerate() {
+            let n = n as i64;
+            let [x, y, z] = [n >> 6, (n >> 3) & 7, n & 7];
+            let [x, y, z] = [0, 1, 2].map(|i| (origin[i] + [x, y, z][i] - min[i]) as usize);
+            let value = f32::from_le_bytes(value.try_into().unwrap());
+            values[(z * size[1] + y) * size[0] + x] = T::from(value).unwrap_or(T::zero());
+        }
+    }
+
+    // Voxels are centered on their integer coordinates
+    let world = |index: i64, i: usize| {
+        let x = (index as f64 - 0.5) * voxel_size[i] + translation[i];
+        T::from(x).unwrap()
+    };
+    let bounds = Box3::new(
+        Point3::new(world(min[0], 0), world(min[1], 1), world(min[2], 2)),
+        Point3::new(world(max[0], 0), world(max[1], 1), world(max[2], 2)),
+    );
+    Ok(GridMedium::new(
+        bounds,
+        sigma_a,
+        sigma_s,
+        DensityGrid::new(size, values),
+    ))
+}
+
+#[inline]
+fn u16_at(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(data[offset..offset + 2].try_into().unwrap())
+}
+
+#[inline]
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+#[inline]
+fn i32_at(data: &[u8], offset: usize) -> i32 {
+    i32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+#[inline]
+fn u64_at(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+#[inline]
+fn f64_at(data: &[u8], offset: usize) -> f64 {
+    f64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::geometry::UnknownUnit;
+
+    /// Builds a file with a single leaf at the origin, whose voxels are their x coordinate
+    fn file() -> Vec<u8> {
+        let tree_size = 64;
+        let grid_size = GRID_DATA_SIZE + tree_size + LEAF_SIZE;
+        let mut grid = vec![0; grid_size];
+        grid[..8].copy_from_slice(&GRID_MAGIC.to_le_bytes());
+        for i in 0..3 {
+            grid[528 + 8 * i..536 + 8 * i].copy_from_slice(&1_f64.to_le_bytes());
+            grid[608 + 8 * i..616 + 8 * i].copy_from_slice(&0.5_f64.to_le_bytes());
+        }
+        let tree = GRID_DATA_SIZE;
+        grid[tree..tree + 8].copy_from_slice(&(tree_size as u64).to_le_bytes());
+        grid[tree + 32..tree + 36].copy_from_slice(&1_u32.to_le_bytes());
+        let leaf = tree + tree_size;
+        for n in 0..512 {
+            let offset = leaf + LEAF_VALUES_OFFSET + 4 * n;
+            let x = (n >> 6) as f32;
+            grid[offset..offset + 4].copy_from_slice(&x.to_le_bytes());
+        }
+
+        let mut file = FILE_MAGICS[1].to_le_bytes().to_vec();
+        file.extend_from_slice(&[0; 4]);
+        file.extend_from_slice(&1_u16.to_le_bytes());
+        file.extend_from_slice(&[0; 2]);
+        let mut metadata = [0; FILE_METADATA_SIZE];
+        metadata[..8].copy_from_slice(&(grid_size as u64).to_le_bytes());
+        metadata[32..36].copy_from_slice(&GRID_TYPE_FLOAT.to_le_bytes());
+        metadata[136..140].copy_from_slice(&8_u32.to_le_bytes());
+        file.extend_from_slice(&metadata);
+        file.extend_from_slice(b"density\0");
+        file.extend_from_slice(&grid);
+        file
+    }
+
+    #[test]
+    fn test_read() {
+        let medium: GridMedium<f64, UnknownUnit> =
+            GridMedium::read_nanovdb(&file()[..], Rgb::splat(1.), Rgb::black()).unwrap();
+        assert_eq!(medium.density().size(), [8, 8, 8]);
+        assert_eq!(
+            medium.bounds(),
+            Box3::new(Point3::splat(0.75), Point3::splat(4.75))
+        );
+        let p = [0.5 / 8., 0.5, 0.5];
+        assert_eq!(medium.density().lookup(p), 0.);
+        assert_eq!(medium.density().lookup([7.5 / 8., 0.5, 0.5]), 7.);
+
+        let mut truncated = file();
+        truncated.truncate(1000);
+        assert!(matches!(
+            GridMedium::<f64, UnknownUnit>::read_nanovdb(
+                &truncated[..],
+                Rgb::black(),
+                Rgb::black()
+            ),
+            Err(VolumeError::InvalidFormat)
+        ));
+    }
+}
@@ -0,0 +1,148 @@
+use crate::core::geometry::{Point2, UnknownUnit, Vector3};
+use num_traits::{Float, FloatConst};
+
+/// A direction sampled from a phase function
+pub struct PhaseSample<T, U> {
+    /// The value of the phase function
+    pub p: T,
+    pub wi: Vector3<T, U>,
+    pub pdf: T,
+}
+
+common_impls!(PhaseSample { p, wi, pdf });
+
+/// The distribution of directions light is scattered into within a medium
+///
+/// Both directions point away from the scattering point, `wo` towards where the light goes
+/// and `wi` towards where it comes from, and phase functions are normalized over the sphere.
+pub trait PhaseFunction<T, U>: Send + Sync {
+    /// Returns the density of light from `wi` scattered towards `wo`
+    #[must_use]
+    fn p(&self, wo: Vector3<T, U>, wi: Vector3<T, U>) -> T;
+
+    #[must_use]
+    fn sample_p(&self, wo: Vector3<T, U>, u: Point2<T, UnknownUnit>) -> Option<PhaseSample<T, U>>;
+
+    /// Returns the density with which [`sample_p`](Self::sample_p) picks `wi`
+    #[must_use]
+    fn pdf(&self, wo: Vector3<T, U>, wi: Vector3<T, U>) -> T;
+}
+
+/// Scattering equally in all directions
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct IsotropicPhase;
+
+impl<T: Float + FloatConst, U> PhaseFunction<T, U> for IsotropicPhase {
+    #[inline]
+    fn p(&self, _: Vector3<T, U>, _: Vector3<T, U>) -> T {
+        T::FRAC_1_PI() / T::from(4).unwrap()
+    }
+
+    #[inline]
+    fn sample_p(&self, wo: Vector3<T, U>, u: Point2<T, UnknownUnit>) -> Option<PhaseSample<T, U>> {
+        let p = PhaseFunction::<T, U>::p(self, wo, wo);
+        Some(PhaseSample {
+            p,
+            wi: crate::sampling::sample_uniform_sphere(u),
+            pdf: p,
+        })
+    }
+
+    #[inline]
+    fn pdf(&self, wo: Vector3<T, U>, wi: Vector3<T, U>) -> T {
+        self.p(wo, wi)
+    }
+}
+
+/// The phase function of Henyey and Greenstein, with a single parameter for how much light
+/// is scattered forward or back
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct HenyeyGreenstein<T> {
+    /// The average cosine of the angle light is deflected by, in `(-1, 1)`, positive for
+    /// forward scattering and zero for isotropic scattering
+    pub g: T,
+}
+
+impl<T: Float + FloatConst> HenyeyGreenstein<T> {
+    #[inline]
+    #[must_use]
+    pub fn new(g: T) -> Self {
+        let limit = T::one() - T::from(1e-3).unwrap();
+        Self {
+            g: g.max(-limit).min(limit),
+        }
+    }
+
+    /// Evaluates the phase function for the cosine between `wo` and `wi`
+    #[inline]
+    fn evaluate(&self, cos_theta: T) -> T {
+        let g = self.g;
+        let denom = T::one() + g * g + (g + g) * cos_theta;
+        T::FRAC_1_PI() / T::from(4).unwrap() * (T::one() - g * g)
+            / (denom * denom.max(T::zero()).sqrt())
+    }
+}
+
+impl<T: Float + FloatConst + Send + Sync, U> PhaseFunction<T, U> for HenyeyGreenstein<T> {
+    #[inline]
+    fn p(&self, wo: Vector3<T, U>, wi: Vector3<T, U>) -> T {
+        self.evaluate(wo.dot(wi))
+    }
+
+    fn sample_p(&self, wo: Vector3<T, U>, u: Point2<T, UnknownUnit>) -> Option<PhaseSample<T, U>> {
+        let g = self.g;
+        let two = T::one() + T::one();
+        // Inverts the cumulative distribution of the cosine with `wo`
+        let cos_theta = if g.abs() < T::from(1e-3).unwrap() {
+            T::one() - two * u.x
+        } else {
+            let s = (T::one() - g * g) / (T::one() + g - two * g * u.x);
+            -(T::one() + g * g - s * s) / (two * g)
+        };
+        let cos_theta = cos_theta.max(-T::one()).min(T::one());
+        let sin_theta = (T::one() - cos_theta * cos_theta).max(T::zero()).sqrt();
+        let (sin_phi, cos_phi) = (T::TAU() * u.y).sin_cos();
+        let (s, t) = wo.coordinate_system();
+        let wi = s * (sin_theta * cos_phi) + t * (sin_theta * sin_phi) + wo * cos_theta;
+        let p = self.evaluate(cos_theta);
+        Some(PhaseSample { p, wi, pdf: p })
+    }
+
+    #[inline]
+    fn pdf(&self, wo: Vector3<T, U>, wi: Vector3<T, U>) -> T {
+        self.p(wo, wi)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::Pcg32;
+
+    #[test]
+    fn test_henyey_greenstein() {
+        let wo = Vector3::<f64, UnknownUnit>::new(0., 0.6, 0.8);
+        let mut rng = Pcg32::new(0);
+        for g in [-0.7, 0., 0.3, 0.6] {
+            let hg = HenyeyGreenstein::new(g);
+            let n = 20_000;
+            let (mut integral, mut mean_cos) = (0., 0.);
+            for _ in 0..n {
+                let u = Point2::new(rng.uniform(), rng.uniform());
+                // Normalized over the sphere
+                let w = crate::sampling::sample_uniform_sphere(u);
+                integral += hg.p(wo, w) * 4. * std::f64::consts::PI;
+
+                let sample = hg.sample_p(wo, u).unwrap();
+                assert!((sample.wi.length() - 1.).abs() < 1e-9);
+                assert!((sample.pdf - hg.pdf(wo, sample.wi)).abs() < 1e-9 * sample.pdf);
+                mean_cos += -wo.dot(sample.wi);
+            }
+            let (integral, mean_cos) = (integral / f64::from(n), mean_cos / f64::from(n));
+            assert!((integral - 1.).abs() < 0.05, "{g}: {integral}");
+            assert!((mean_cos - g).abs() < 0.02, "{g}: {mean_cos}");
+        }
+        let p: f64 = IsotropicPhase.p(wo, -wo);
+        assert_eq!(p, HenyeyGreenstein::new(0.).p(wo, -wo));
+    }
+}
@@ -0,0 +1,294 @@
+use crate::{
+    core::geometry::{Point2, UnknownUnit},
+    image::Image,
+    sampler::{hash, Pcg32, Sampler},
+};
+use num_traits::Float;
+use std::sync::Arc;
+
+/// The standard deviation of the filter measuring clusters and voids, in pixels
+const SIGMA: f64 = 1.5;
+/// The fraction of pixels set in the initial pattern of the void-and-cluster method
+const INITIAL_DENSITY: f64 = 0.1;
+
+/// A tileable dither mask whose thresholds are distributed as blue noise, so that neighboring
+/// pixels have very different values
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlueNoiseMask {
+    width: usize,
+    height: usize,
+    /// A permutation of `0..width * height`
+    ranks: Vec<u32>,
+}
+
+impl BlueNoiseMask {
+    /// Generates a `size * size` mask with the void-and-cluster method of Ulichney (1993)
+    ///
+    /// This takes time quadratic in the number of pixels, so masks of more than 128 pixels
+    /// square are better generated once and loaded with [`from_image`](Self::from_image).
+    ///
+    /// Panics if `size` is zero.
+    #[must_use]
+    pub fn generate(size: usize, seed: u64) -> Self {
+        assert!(size > 0, "blue noise masks need at least one pixel");
+        let n = size * size;
+        // The filter for every toroidal offset, indexed like the pixels
+        let kernel: Vec<f64> = (0..n)
+            .map(|i| {
+                let wrap = |d: usize| d.min(size - d) as f64;
+                let (dx, dy) = (wrap(i % size), wrap(i / size));
+                (-(dx * dx + dy * dy) / (2. * SIGMA * SIGMA)).exp()
+            })
+            .collect();
+        let mut pattern = vec![false; n];
+        let mut energy = vec![0.; n];
+        let toggle = |pattern: &mut [bool], energy: &mut [f64], p: usize| {
+            pattern[p] = !pattern[p];
+            let sign = if pattern[p] { 1. } else { -1. };
+            let (px, py) = (p % size, p / size);
+            for (i, e) in energy.iter_mut().enumerate() {
+                let dx = (i % size + size - px) % size;
+                let dy = (i / size + size - py) % size;
+                *e += sign * kernel[dy * size + dx];
+            }
+        };
+        // The set pixel with the most set neighbors, or the unset one with the fewest
+        let tightest_cluster = |pattern: &[bool], energy: &[f64]| {
+            (0..n)
+                .filter(|&i| pattern[i])
+                .max_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+        };
+        let largest_void = |pattern: &[bool], energy: &[f64]| {
+            (0..n)
+                .filter(|&i| !pattern[i])
+                .min_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+        };
+
+        // Start from white noise, and move points from clusters to voids until they are even
+        let mut rng = Pcg32::new(seed);
+        let initial = ((n as f64 * INITIAL_DENSITY) as usize).clamp(1, n);
+        let mut count = 0;
+        while count < initial {
+            let p = rng.next_u32() as usize % n;
+            if !pattern[p] {
+                toggle(&mut pattern, &mut energy, p);
+                count += 1;
+            }
+        }
+        if initial < n {
+            loop {
+                let cluster = tightest_cluster(&pattern, &energy).unwrap();
+                toggle(&mut pattern, &mut energy, cluster);
+                let void = largest_void(&pattern, &energy).unwrap();
+                toggle(&mut pattern, &mut energy, void);
+                if void == cluster {
+                    break;
+                }
+            }
+        }
+
+        // Rank the initial points by removing clusters, then fill voids with the rest
+        let mut ranks = vec![0; n];
+        let (mut initial_pattern, mut initial_energy) = (pattern.clone(), energy.clone());
+        for rank in (0..initial).rev() {
+            let cluster = tightest_cluster(&initial_pattern, &initial_energy).unwrap();
+            toggle(&mut initial_pattern, &mut initial_energy, cluster);
+            ranks[cluster] = rank as u32;
+        }
+        for rank in initial..n {
+            let void = largest_void(&pattern, &energy).unwrap();
+            toggle(&mut pattern, &mut energy, void);
+            ranks[void] = rank as u32;
+        }
+        Self {
+            width: size,
+            height: size,
+            ranks,
+        }
+    }
+
+    /// Uses the luminance of an image as the thresholds of the mask, such as one of the
+    /// published blue noise textures
+    ///
+    /// Panics if the image is empty.
+    #[must_use]
+    pub fn from_image<T: Float>(image: &Image<T>) -> Self {
+        assert!(
+            !image.pixels().is_empty(),
+            "blue noise masks need at least one pixel"
+        );
+        let luminance: Vec<T> = image.pixels().iter().map(|p| p.luminance()).collect();
+        let mut order: Vec<usize> = (0..luminance.len()).collect();
+        order.sort_by(|&a, &b| {
+            luminance[a]
+                .partial_cmp(&luminance[b])
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        let mut ranks = vec![0; order.len()];
+        for (rank, &i) in order.iter().enumerate() {
+            ranks[i] = rank as u32;
+        }
+        Self {
+            width: image.width(),
+            height: image.height(),
+            ranks,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the threshold at a pixel in `[0, 1)`, tiling the mask over the plane
+    #[inline]
+    #[must_use]
+    pub fn value<T: Float>(&self, pixel: Point2<usize, UnknownUnit>) -> T {
+        let i = (pixel.y % self.height) * self.width + pixel.x % self.width;
+        let n = self.ranks.len();
+        T::from((f64::from(self.ranks[i]) + 0.5) / n as f64).unwrap()
+    }
+}
+
+/// Offsets the samples of another sampler by a blue noise mask, so that the errors of
+/// neighboring pixels are negatively correlated
+///
+/// Every dimension is shifted toroidally by the mask, tiled with a different offset for each
+/// dimension. This keeps the distribution of the samples within a pixel, but makes the error of
+/// low sample count renders appear as high frequency noise, which is less objectionable and
+/// easier to filter than white noise.
+#[derive(Debug, Clone)]
+pub struct BlueNoiseSampler<S> {
+    sampler: S,
+    mask: Arc<BlueNoiseMask>,
+    pixel: Point2<usize, UnknownUnit>,
+}
+
+impl<S> BlueNoiseSampler<S> {
+    #[must_use]
+    pub fn new(sampler: S, mask: Arc<BlueNoiseMask>) -> Self {
+        Self {
+            sampler,
+            mask,
+            pixel: Point2::new(0, 0),
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn mask(&self) -> &Arc<BlueNoiseMask> {
+        &self.mask
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn into_inner(self) -> S {
+        self.sampler
+    }
+
+    fn shift<T: Float>(&self, u: T, dimension: usize) -> T {
+        let h = hash(&[dimension as u64]);
+        let offset = Point2::new(
+            self.pixel.x.wrapping_add(h as u32 as usize),
+            self.pixel.y.wrapping_add((h >> 32) as usize),
+        );
+        let u = u + self.mask.value(offset);
+        if u >= T::one() {
+            (u - T::one()).min(T::one() - T::epsilon())
+        } else {
+            u
+        }
+    }
+}
+
+impl<T: Float, S: Sampler<T>> Sampler<T> for BlueNoiseSampler<S> {
+    #[inline]
+    fn samples_per_pixel(&self) -> usize {
+        self.sampler.samples_per_pixel()
+    }
+
+    /// Reseeds the offset sampler, keeping the mask
+    #[inline]
+    fn reseed(&mut self, seed: u64) {
+        self.sampler.reseed(seed);
+    }
+
+    fn start_pixel_sample(
+        &mut self,
+        pixel: Point2<usize, UnknownUnit>,
+        sample_index: usize,
+        dimension: usize,
+    ) {
+        self.pixel = pixel;
+        self.sampler
+            .start_pixel_sample(pixel, sample_index, dimension);
+    }
+
+    #[inline]
+    fn dimension(&self) -> usize {
+        self.sampler.dimension()
+    }
+
+    fn next_1d(&mut self) -> T {
+        let dimension = self.sampler.dimension();
+        let u = self.sampler.next_1d();
+        self.shift(u, dimension)
+    }
+
+    fn next_2d(&mut self) -> Point2<T, UnknownUnit> {
+        let dimension = self.sampler.dimension();
+        let u = self.sampler.next_2d();
+        Point2::new(self.shift(u.x, dimension), self.shift(u.y, dimension + 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::IndependentSampler;
+
+    #[test]
+    fn test_blue_noise() {
+        let size = 16;
+        let mask = BlueNoiseMask::generate(size, 3);
+        let mut ranks = mask.ranks.clone();
+        ranks.sort_unstable();
+        assert_eq!(ranks, (0..256).collect::<Vec<_>>());
+
+        // The darkest eighth of the thresholds are spread out, unlike white noise
+        let points: Vec<_> = (0..size * size)
+            .filter(|&i| mask.ranks[i] < 32)
+            .map(|i| (i % size, i / size))
+            .collect();
+        let min_distance = points
+            .iter()
+            .flat_map(|a| points.iter().filter(move |b| *b != a).map(move |b| (a, b)))
+            .map(|(a, b)| {
+                let wrap = |d: usize| d.min(size - d);
+                let (dx, dy) = (wrap(a.0.abs_diff(b.0)), wrap(a.1.abs_diff(b.1)));
+                dx * dx + dy * dy
+            })
+            .min()
+            .unwrap();
+        assert!(min_distance >= 4, "points {min_distance} apart");
+
+        // Neighboring pixels get different offsets for the same sample
+        let mut sampler = BlueNoiseSampler::new(IndependentSampler::new(1, 0), Arc::new(mask));
+        let mut values = Vec::new();
+        for x in 0..2 {
+            sampler.start_pixel_sample(Point2::new(x, 0), 0, 0);
+            let u: f64 = sampler.next_1d();
+            assert!((0. ..1.).contains(&u));
+            values.push(u);
+        }
+        assert_ne!(values[0], values[1]);
+        assert_eq!(sampler.dimension(), 1);
+    }
+}
@@ -0,0 +1,74 @@
+use crate::{
+    core::geometry::{Point2, UnknownUnit},
+    sampler::{hash, Pcg32, Sampler},
+};
+use num_traits::Float;
+use std::marker::PhantomData;
+
+/// Samples every dimension uniformly at random, with no correlation between samples
+///
+/// This converges slowest, but is a baseline free of structured artifacts.
+#[derive(Debug, Clone)]
+pub struct IndependentSampler<T> {
+    samples_per_pixel: usize,
+    seed: u64,
+    rng: Pcg32,
+    dimension: usize,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> IndependentSampler<T> {
+    #[must_use]
+    pub fn new(samples_per_pixel: usize, seed: u64) -> Self {
+        Self {
+            samples_per_pixel,
+            seed,
+            rng: Pcg32::default(),
+            dimension: 0,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Float> Sampler<T> for IndependentSampler<T> {
+    #[inline]
+    fn samples_per_pixel(&self) -> usize {
+        self.samples_per_pixel
+    }
+
+    #[inline]
+    fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    fn start_pixel_sample(
+        &mut self,
+        pixel: Point2<usize, UnknownUnit>,
+        sample_index: usize,
+        dimension: usize,
+    ) {
+        self.rng
+            .set_sequence(hash(&[pixel.x as u64, pixel.y as u64, self.seed]), 0);
+        // Sample vectors get disjoint parts of the sequence of their pixel
+        let offset = (sample_index as u64).wrapping_mul(1 << 16) + dimension as u64;
+        self.rng.advance(offset as i64);
+        self.dimension = dimension;
+    }
+
+    #[inline]
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    #[inline]
+    fn next_1d(&mut self) -> T {
+        self.dimension += 1;
+        self.rng.uniform()
+    }
+
+    #[inline]
+    fn next_2d(&mut self) -> Point2<T, UnknownUnit> {
+        self.dimension += 2;
+        Point2::new(self.rng.uniform(), self.rng.uniform())
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{
+    core::geometry::{Point2, UnknownUnit},
+    sampler::{Pcg32, Sampler},
+};
+use num_traits::{Float, FloatConst};
+
+/// A dimension of the current state of a Markov chain, and its value before the last mutation
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct PrimarySample<T> {
+    value: T,
+    last_modified: u64,
+    backup: T,
+    backup_modified: u64,
+}
+
+/// Samples the primary sample space of Metropolis light transport, where each sample vector
+/// is a mutation of the previously accepted one
+///
+/// Dimensions are mutated lazily when consumed, either by a large step replacing them with
+/// uniform values, or by a small step perturbing them with a normal distribution. Each
+/// iteration is started by [`start_iteration`](Self::start_iteration), and its mutation then
+/// kept by [`accept`](Self::accept) or undone by [`reject`](Self::reject).
+#[derive(Debug, Clone)]
+pub struct MltSampler<T> {
+    samples_per_pixel: usize,
+    rng: Pcg32,
+    sigma: T,
+    large_step_probability: T,
+    samples: Vec<PrimarySample<T>>,
+    iteration: u64,
+    large_step: bool,
+    last_large_step: u64,
+    dimension: usize,
+}
+
+impl<T: Float> MltSampler<T> {
+    /// Creates a chain whose first sample vector is uniform, drawing from the random sequence
+    /// with the given index
+    ///
+    /// Small steps have a standard deviation of `sigma`, and each mutation is a large step with
+    /// probability `large_step_probability`.
+    #[must_use]
+    pub fn new(
+        samples_per_pixel: usize,
+        sequence: u64,
+        sigma: T,
+        large_step_probability: T,
+    ) -> Self {
+        Self {
+            samples_per_pixel,
+            rng: Pcg32::new(sequence),
+            sigma,
+            large_step_probability,
+            samples: Vec::new(),
+            iteration: 0,
+            large_step: true,
+            last_large_step: 0,
+            dimension: 0,
+        }
+    }
+
+    /// Whether the current mutation is a large step
+    #[inline]
+    #[must_use]
+    pub fn is_large_step(&self) -> bool {
+        self.large_step
+    }
+
+    /// Starts a mutation of the sample vector, consumed from its first dimension
+    pub fn start_iteration(&mut self) {
+        self.iteration += 1;
+        self.large_step = self.rng.uniform::<T>() < self.large_step_probability;
+        self.dimension = 0;
+    }
+
+    /// Keeps the mutation as the current state of the chain
+    pub fn accept(&mut self) {
+        if self.large_step {
+            self.last_large_step = self.iteration;
+        }
+    }
+
+    /// Restores the state of the chain from before the mutation
+    pub fn reject(&mut self) {
+        for sample in &mut self.samples {
+            if sample.last_modified == self.iteration {
+                sample.value = sample.backup;
+                sample.last_modified = sample.backup_modified;
+            }
+        }
+        self.iteration -= 1;
+    }
+}
+
+impl<T: Float + FloatConst> MltSampler<T> {
+    fn next(&mut self) -> T {
+        let index = self.dimension;
+        self.dimension += 1;
+        if index >= self.samples.len() {
+            self.samples.resize(
+                index + 1,
+                PrimarySample {
+                    value: T::zero(),
+                    last_modified: 0,
+                    backup: T::zero(),
+                    backup_modified: 0,
+                },
+            );
+        }
+        let sample = &mut self.samples[index];
+
+        // Dimensions not consumed since the last large step are still due for it
+        if sample.last_modified < self.last_large_step {
+            sample.value = self.rng.uniform();
+            sample.last_modified = self.last_large_step;
+        }
+        sample.backup = sample.value;
+        sample.backup_modified = sample.last_modified;
+        if self.large_step {
+            sample.value = self.rng.uniform();
+        } else {
+            // The small steps missed since the dimension was last consumed add up to one
+            // with a wider distribution
+            let steps = T::from(self.iteration - sample.last_modified).unwrap();
+            let sigma = self.sigma * steps.sqrt();
+            let (u1, u2) = (self.rng.uniform::<T>(), self.rng.uniform::<T>());
+            let normal =
+                (-(T::one() + T::one()) * (T::one() - u1).ln()).sqrt() * (T::TAU() * u2).cos();
+            sample.value = sample.value + normal * sigma;
+            sample.value = sample.value - sample.value.floor();
+        }
+        sample.last_modified = self.iteration;
+        // Wrapping around can round up to one
+        sample.value.min(T::one() - T::epsilon())
+    }
+}
+
+impl<T: Float + FloatConst> Sampler<T> for MltSampler<T> {
+    #[inline]
+    fn samples_per_pixel(&self) -> usize {
+        self.samples_per_pixel
+    }
+
+    /// Draws the rest of the chain from the random sequence with the index `seed`
+    #[inline]
+    fn reseed(&mut self, seed: u64) {
+        self.rng = Pcg32::new(seed);
+    }
+
+    /// The sample vector is that of the current iteration whatever the pixel, only the
+    /// dimension is moved to
+    #[inline]
+    fn start_pixel_sample(&mut self, _: Point2<usize, UnknownUnit>, _: usize, dimension: usize) {
+        self.dimension = dimension;
+    }
+
+    #[inline]
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    #[inline]
+    fn next_1d(&mut self) -> T {
+        self.next()
+    }
+
+    #[inline]
+    fn next_2d(&mut self) -> Point2<T, UnknownUnit> {
+        let x = self.next();
+        Point2::new(x, self.next())
+    }
+}
@@ -0,0 +1,88 @@
+//! Sources of the sample values driving Monte Carlo estimates, one dimension at a time
+
+mod blue_noise;
+mod independent;
+mod mlt;
+mod rng;
+mod stratified;
+
+pub use blue_noise::{BlueNoiseMask, BlueNoiseSampler};
+pub use independent::IndependentSampler;
+pub use mlt::MltSampler;
+pub use rng::{hash, mix_bits, Pcg32};
+pub use stratified::StratifiedSampler;
+
+use crate::core::geometry::{Point2, UnknownUnit};
+
+/// Generates the sample vectors of the pixels of an image
+///
+/// A sample vector is consumed one or two dimensions at a time, in the same order for every
+/// sample, so that samplers can distribute each dimension well over the samples of a pixel.
+/// Samplers are deterministic given their seed, and cloned for each tile of a render. Their
+/// sample vectors only depend on the pixel and the index of the sample in it, so renders are
+/// the same whichever thread renders each tile.
+pub trait Sampler<T> {
+    #[must_use]
+    fn samples_per_pixel(&self) -> usize;
+
+    /// Replaces the seed from which sample vectors are made, so that renders with different
+    /// seeds have independent noise
+    fn reseed(&mut self, seed: u64);
+
+    /// Starts the sample vector with the given index in a pixel, at `dimension`
+    fn start_pixel_sample(
+        &mut self,
+        pixel: Point2<usize, UnknownUnit>,
+        sample_index: usize,
+        dimension: usize,
+    );
+
+    /// The index of the next dimension of the sample vector
+    #[must_use]
+    fn dimension(&self) -> usize;
+
+    /// Returns the next dimension of the sample vector, in `[0, 1)`
+    #[must_use]
+    fn next_1d(&mut self) -> T;
+
+    /// Returns the next two dimensions of the sample vector, in `[0, 1)^2`
+    #[must_use]
+    fn next_2d(&mut self) -> Point2<T, UnknownUnit>;
+}
+
+impl<T, S: Sampler<T> + ?Sized> Sampler<T> for &mut S {
+    #[inline]
+    fn samples_per_pixel(&self) -> usize {
+        (**self).samples_per_pixel()
+    }
+
+    #[inline]
+    fn reseed(&mut self, seed: u64) {
+        (**self).reseed(seed);
+    }
+
+    #[inline]
+    fn start_pixel_sample(
+        &mut self,
+        pixel: Point2<usize, UnknownUnit>,
+        sample_index: usize,
+        dimension: usize,
+    ) {
+        (**self).start_pixel_sample(pixel, sample_index, dimension);
+    }
+
+    #[inline]
+    fn dimension(&self) -> usize {
+        (**self).dimension()
+    }
+
+    #[inline]
+    fn next_1d(&mut self) -> T {
+        (**self).next_1d()
+    }
+
+    #[inline]
+    fn next_2d(&mut self) -> Point2<T, UnknownUnit> {
+        (**self).next_2d()
+    }
+}
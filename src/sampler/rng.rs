@@ -0,0 +1,161 @@
+use num_traits::Float;
+
+const MULTIPLIER: u64 = 0x5851_f42d_4c95_7f2d;
+const DEFAULT_STATE: u64 = 0x853c_49e6_748f_ea9b;
+const DEFAULT_STREAM: u64 = 0xda3e_39cb_94b9_5bdb;
+
+/// The PCG32 random number generator of O'Neill, which can select among 2^63 independent
+/// sequences and skip ahead in them
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Pcg32 {
+    state: u64,
+    increment: u64,
+}
+
+impl Default for Pcg32 {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            state: DEFAULT_STATE,
+            increment: DEFAULT_STREAM,
+        }
+    }
+}
+
+impl Pcg32 {
+    /// Starts the sequence with the given index at an offset derived from it
+    #[inline]
+    #[must_use]
+    pub fn new(sequence: u64) -> Self {
+        let mut rng = Self::default();
+        rng.set_sequence(sequence, mix_bits(sequence));
+        rng
+    }
+
+    /// Restarts the generator at `offset` in the sequence with the given index
+    pub fn set_sequence(&mut self, sequence: u64, offset: u64) {
+        self.state = 0;
+        self.increment = (sequence << 1) | 1;
+        self.next_u32();
+        self.state = self.state.wrapping_add(offset);
+        self.next_u32();
+    }
+
+    #[inline]
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(MULTIPLIER).wrapping_add(self.increment);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+
+    /// Returns a uniform value in `[0, 1)`
+    #[inline]
+    pub fn uniform<T: Float>(&mut self) -> T {
+        let x = T::from(f64::from(self.next_u32()) * 2_f64.powi(-32)).unwrap();
+        // Rounding to a narrower type can reach one
+        x.min(T::one() - T::epsilon() / (T::one() + T::one()))
+    }
+
+    /// Skips ahead `delta` steps in logarithmic time, or back if it is negative
+    pub fn advance(&mut self, delta: i64) {
+        let (mut acc_mult, mut acc_plus) = (1_u64, 0_u64);
+        let (mut cur_mult, mut cur_plus) = (MULTIPLIER, self.increment);
+        // Steps wrap around the period of 2^64
+        let mut delta = delta as u64;
+        while delta > 0 {
+            if delta & 1 == 1 {
+                acc_mult = acc_mult.wrapping_mul(cur_mult);
+                acc_plus = acc_plus.wrapping_mul(cur_mult).wrapping_add(cur_plus);
+            }
+            cur_plus = cur_mult.wrapping_add(1).wrapping_mul(cur_plus);
+            cur_mult = cur_mult.wrapping_mul(cur_mult);
+            delta >>= 1;
+        }
+        self.state = acc_mult.wrapping_mul(self.state).wrapping_add(acc_plus);
+    }
+}
+
+/// Scrambles the bits of a value, so that nearby values give unrelated results
+#[inline]
+#[must_use]
+pub fn mix_bits(mut v: u64) -> u64 {
+    v ^= v >> 31;
+    v = v.wrapping_mul(0x7fb5_d329_728e_a185);
+    v ^= v >> 27;
+    v = v.wrapping_mul(0x81da_def4_bc2d_d44d);
+    v ^= v >> 33;
+    v
+}
+
+/// Combines values into a hash, such as a pixel, a dimension and a seed into the seed of a
+/// random sequence
+#[must_use]
+pub fn hash(values: &[u64]) -> u64 {
+    values
+        .iter()
+        .fold(0x9e37_79b9_7f4a_7c15, |h, &v| mix_bits(h ^ mix_bits(v)))
+}
+
+/// Returns the element at `index` of a pseudorandom permutation of `0..len`, following Kensler,
+/// "Correlated Multi-Jittered Sampling" (2013)
+#[must_use]
+pub(crate) fn permutation_element(mut index: u32, len: u32, seed: u32) -> u32 {
+    debug_assert!(index < len);
+    let mut mask = len.wrapping_sub(1);
+    mask |= mask >> 1;
+    mask |= mask >> 2;
+    mask |= mask >> 4;
+    mask |= mask >> 8;
+    mask |= mask >> 16;
+    // Permute within the enclosing power of two until the index falls inside the range
+    loop {
+        let mut i = index;
+        i ^= seed;
+        i = i.wrapping_mul(0xe170_893d);
+        i ^= seed >> 16;
+        i ^= (i & mask) >> 4;
+        i ^= seed >> 8;
+        i = i.wrapping_mul(0x0929_eb3f);
+        i ^= seed >> 23;
+        i ^= (i & mask) >> 1;
+        i = i.wrapping_mul(1 | seed >> 27);
+        i = i.wrapping_mul(0x6935_fa69);
+        i ^= (i & mask) >> 11;
+        i = i.wrapping_mul(0x74dc_b303);
+        i ^= (i & mask) >> 2;
+        i = i.wrapping_mul(0x9e50_1cc3);
+        i ^= (i & mask) >> 2;
+        i = i.wrapping_mul(0xc860_a3df);
+        i &= mask;
+        i ^= i >> 5;
+        if i < len {
+            return i.wrapping_add(seed) % len;
+        }
+        index = i;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pcg32() {
+        let mut a = Pcg32::new(7);
+        let mut b = a;
+        let values: Vec<u32> = (0..10).map(|_| a.next_u32()).collect();
+        b.advance(10);
+        assert_eq!(a, b);
+        b.advance(-10);
+        assert_eq!(b.next_u32(), values[0]);
+        assert_ne!(Pcg32::new(8).next_u32(), values[0]);
+
+        let mut rng = Pcg32::default();
+        assert!((0..1000).all(|_| (0. ..1.).contains(&rng.uniform::<f32>())));
+
+        let mut seen: Vec<u32> = (0..10).map(|i| permutation_element(i, 10, 1234)).collect();
+        seen.sort_unstable();
+        assert_eq!(seen, (0..10).collect::<Vec<_>>());
+    }
+}
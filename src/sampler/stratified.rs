@@ -0,0 +1,154 @@
+use crate::{
+    core::geometry::{Point2, UnknownUnit},
+    sampler::{hash, rng::permutation_element, Pcg32, Sampler},
+};
+use num_traits::Float;
+use std::marker::PhantomData;
+
+/// Divides each dimension into as many strata as there are samples per pixel, and places one
+/// sample in each
+///
+/// Pairs of dimensions are stratified over a grid of `x_samples * y_samples` cells. The strata
+/// are shuffled differently for each dimension, so that dimensions are not correlated.
+#[derive(Debug, Clone)]
+pub struct StratifiedSampler<T> {
+    x_samples: usize,
+    y_samples: usize,
+    /// Whether samples are placed randomly in their stratum, rather than at its center
+    pub jitter: bool,
+    seed: u64,
+    rng: Pcg32,
+    pixel: Point2<usize, UnknownUnit>,
+    sample_index: usize,
+    dimension: usize,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> StratifiedSampler<T> {
+    /// Panics if either number of samples is zero
+    #[must_use]
+    pub fn new(x_samples: usize, y_samples: usize, jitter: bool, seed: u64) -> Self {
+        assert!(
+            x_samples > 0 && y_samples > 0,
+            "stratified samplers need at least one sample"
+        );
+        Self {
+            x_samples,
+            y_samples,
+            jitter,
+            seed,
+            rng: Pcg32::default(),
+            pixel: Point2::new(0, 0),
+            sample_index: 0,
+            dimension: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the index of the stratum of the current sample in the next dimension
+    fn stratum(&self) -> usize {
+        let seed = hash(&[
+            self.pixel.x as u64,
+            self.pixel.y as u64,
+            self.dimension as u64,
+            self.seed,
+        ]);
+        let spp = (self.x_samples * self.y_samples) as u32;
+        permutation_element(self.sample_index as u32 % spp, spp, seed as u32) as usize
+    }
+
+    #[inline]
+    fn offset(&mut self) -> T
+    where
+        T: Float,
+    {
+        if self.jitter {
+            self.rng.uniform()
+        } else {
+            T::from(0.5).unwrap()
+        }
+    }
+}
+
+impl<T: Float> Sampler<T> for StratifiedSampler<T> {
+    #[inline]
+    fn samples_per_pixel(&self) -> usize {
+        self.x_samples * self.y_samples
+    }
+
+    #[inline]
+    fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    fn start_pixel_sample(
+        &mut self,
+        pixel: Point2<usize, UnknownUnit>,
+        sample_index: usize,
+        dimension: usize,
+    ) {
+        self.pixel = pixel;
+        self.sample_index = sample_index;
+        self.dimension = dimension;
+        self.rng
+            .set_sequence(hash(&[pixel.x as u64, pixel.y as u64, self.seed]), 0);
+        let offset = (sample_index as u64).wrapping_mul(1 << 16) + dimension as u64;
+        self.rng.advance(offset as i64);
+    }
+
+    #[inline]
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn next_1d(&mut self) -> T {
+        let stratum = self.stratum();
+        self.dimension += 1;
+        let spp = T::from(self.samples_per_pixel()).unwrap();
+        let offset: T = self.offset();
+        ((T::from(stratum).unwrap() + offset) / spp).min(T::one() - T::epsilon())
+    }
+
+    fn next_2d(&mut self) -> Point2<T, UnknownUnit> {
+        let stratum = self.stratum();
+        self.dimension += 2;
+        let (x, y) = (stratum % self.x_samples, stratum / self.x_samples);
+        let (dx, dy): (T, T) = (self.offset(), self.offset());
+        let coord = |i: usize, offset: T, n: usize| {
+            ((T::from(i).unwrap() + offset) / T::from(n).unwrap()).min(T::one() - T::epsilon())
+        };
+        Point2::new(coord(x, dx, self.x_samples), coord(y, dy, self.y_samples))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strata() {
+        let mut sampler = StratifiedSampler::new(4, 2, true, 1);
+        let mut strata_1d = Vec::new();
+        let mut strata_2d = Vec::new();
+        for i in 0..8 {
+            sampler.start_pixel_sample(Point2::new(3, 5), i, 0);
+            let u: f64 = sampler.next_1d();
+            let p: Point2<f64, UnknownUnit> = sampler.next_2d();
+            assert_eq!(sampler.dimension(), 3);
+            strata_1d.push((u * 8.) as usize);
+            strata_2d.push(((p.x * 4.) as usize, (p.y * 2.) as usize));
+        }
+        // Every stratum gets exactly one sample
+        strata_1d.sort_unstable();
+        assert_eq!(strata_1d, (0..8).collect::<Vec<_>>());
+        strata_2d.sort_unstable();
+        let cells: Vec<_> = (0..4).flat_map(|x| (0..2).map(move |y| (x, y))).collect();
+        assert_eq!(strata_2d, cells);
+
+        // Samples are reproducible
+        sampler.start_pixel_sample(Point2::new(3, 5), 2, 1);
+        let a: Point2<f64, UnknownUnit> = sampler.next_2d();
+        sampler.start_pixel_sample(Point2::new(3, 5), 2, 1);
+        assert_eq!(a, sampler.next_2d());
+    }
+}
@@ -0,0 +1,359 @@
+//! Warps of uniform samples in `[0, 1)^2` to other distributions, and the weighting of samples
+//! drawn from several of them
+
+use crate::core::{
+    geometry::{Point2, UnknownUnit, Vector3},
+    units::Angle,
+};
+use num_traits::{Float, FloatConst};
+
+/// Maps the unit square to the unit disk, keeping nearby samples close together
+#[must_use]
+pub fn sample_uniform_disk_concentric<T: Float + FloatConst>(
+    u: Point2<T, UnknownUnit>,
+) -> Point2<T, UnknownUnit> {
+    let two = T::one() + T::one();
+    let (x, y) = (u.x * two - T::one(), u.y * two - T::one());
+    if x == T::zero() && y == T::zero() {
+        return Point2::new(x, y);
+    }
+    let (r, theta) = if x.abs() > y.abs() {
+        (x, T::FRAC_PI_4() * (y / x))
+    } else {
+        (y, T::FRAC_PI_2() - T::FRAC_PI_4() * (x / y))
+    };
+    let (sin, cos) = theta.sin_cos();
+    Point2::new(r * cos, r * sin)
+}
+
+/// Returns uniformly distributed barycentric coordinates
+#[must_use]
+pub fn sample_uniform_triangle<T: Float>(u: Point2<T, UnknownUnit>) -> [T; 3] {
+    let half = T::from(0.5).unwrap();
+    let (b0, b1) = if u.x < u.y {
+        let b0 = u.x * half;
+        (b0, u.y - b0)
+    } else {
+        let b1 = u.y * half;
+        (u.x - b1, b1)
+    };
+    [b0, b1, T::one() - b0 - b1]
+}
+
+/// Samples a regular polygon inscribed in the unit circle, with a vertex at `rotation`
+#[must_use]
+pub fn sample_regular_polygon<T: Float + FloatConst>(
+    u: Point2<T, UnknownUnit>,
+    sides: u32,
+    rotation: Angle<T>,
+) -> Point2<T, UnknownUnit> {
+    assert!(sides >= 3, "polygons need at least 3 sides");
+    let n = T::from(sides).unwrap();
+    // The first coordinate picks a side and is then reused within its triangle
+    let scaled = u.x * n;
+    let side = scaled.floor().min(n - T::one());
+    let u = Point2::new(scaled - side, u.y);
+
+    let step = T::TAU() / n;
+    let (sin0, cos0) = (rotation.radians() + side * step).sin_cos();
+    let (sin1, cos1) = (rotation.radians() + (side + T::one()) * step).sin_cos();
+    let [_, b1, b2] = sample_uniform_triangle(u);
+    Point2::new(b1 * cos0 + b2 * cos1, b1 * sin0 + b2 * sin1)
+}
+
+/// Samples a direction on the unit sphere uniformly
+#[must_use]
+pub fn sample_uniform_sphere<T: Float + FloatConst, U>(u: Point2<T, UnknownUnit>) -> Vector3<T, U> {
+    let z = T::one() - (u.x + u.x);
+    let r = (T::one() - z * z).max(T::zero()).sqrt();
+    let (sin, cos) = (T::TAU() * u.y).sin_cos();
+    Vector3::new(r * cos, r * sin, z)
+}
+
+/// Samples a direction uniformly within the cone of half angle `theta_max` around +z
+#[must_use]
+pub fn sample_uniform_cone<T: Float + FloatConst, U>(
+    u: Point2<T, UnknownUnit>,
+    theta_max: Angle<T>,
+) -> Vector3<T, U> {
+    // Going through `1 - cos` keeps precision for narrow cones, such as of the sun
+    let one_minus_cos = u.x * one_minus_cos(theta_max);
+    let z = T::one() - one_minus_cos;
+    let r = (one_minus_cos * (T::one() + z)).max(T::zero()).sqrt();
+    let (sin, cos) = (T::TAU() * u.y).sin_cos();
+    Vector3::new(r * cos, r * sin, z)
+}
+
+/// The density of [`sample_uniform_cone`] within the cone
+#[inline]
+#[must_use]
+pub fn uniform_cone_pdf<T: Float + FloatConst>(theta_max: Angle<T>) -> T {
+    (T::TAU() * one_minus_cos(theta_max)).recip()
+}
+
+/// Returns `1 - cos(theta)` without cancellation for small angles
+#[inline]
+fn one_minus_cos<T: Float>(theta: Angle<T>) -> T {
+    let half = (theta.radians() / (T::one() + T::one())).sin();
+    (half + half) * half
+}
+
+/// Samples a direction in the hemisphere around +z proportionally to its cosine with +z
+#[must_use]
+pub fn sample_cosine_hemisphere<T: Float + FloatConst, U>(
+    u: Point2<T, UnknownUnit>,
+) -> Vector3<T, U> {
+    // Projecting points of the disk up to the hemisphere gives the cosine distribution
+    let d = sample_uniform_disk_concentric(u);
+    let z = (T::one() - d.x * d.x - d.y * d.y).max(T::zero()).sqrt();
+    Vector3::new(d.x, d.y, z)
+}
+
+/// The density of [`sample_cosine_hemisphere`] for a direction with the given cosine
+#[inline]
+#[must_use]
+pub fn cosine_hemisphere_pdf<T: Float + FloatConst>(cos_theta: T) -> T {
+    cos_theta * T::FRAC_1_PI()
+}
+
+/// How multiple importance sampling weights the samples of several strategies estimating the
+/// same integral
+///
+/// Each sample is weighted by how likely its own strategy was to produce it relative to all of
+/// them, so that the weights of a sample under every strategy sum to one and the combined
+/// estimate stays unbiased.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum MisHeuristic {
+    /// Weights in proportion to the densities, which is never much worse than the best
+    /// strategy
+    Balance,
+    /// Weights in proportion to the squared densities, which sharpens the weights where one
+    /// strategy is much better than the others
+    #[default]
+    Power,
+}
+
+impl MisHeuristic {
+    /// Returns the weight of a sample of the strategy at `index` among `strategies`, each
+    /// given as its number of samples and the density with which it would draw the sample
+    ///
+    /// A strategy drawing the sample with infinite density, such as from a delta
+    /// distribution, gets the whole weight.
+    #[must_use]
+    pub fn weight<T: Float>(self, index: usize, strategies: &[(usize, T)]) -> T {
+        let term = |(n, pdf): (usize, T)| {
+            let f = T::from(n).unwrap() * pdf;
+            match self {
+                Self::Balance => f,
+                Self::Power => f * f,
+            }
+        };
+        let f = term(strategies[index]);
+        if f.is_infinite() {
+            return T::one();
+        }
+        let sum = strategies
+            .iter()
+            .fold(T::zero(), |sum, &strategy| sum + term(strategy));
+        if sum == T::zero() {
+            T::zero()
+        } else {
+            f / sum
+        }
+    }
+}
+
+/// Weights a sample with density `pdf` against one other strategy, each taking one sample, by
+/// the balance heuristic
+#[inline]
+#[must_use]
+pub fn balance_heuristic<T: Float>(pdf: T, other_pdf: T) -> T {
+    MisHeuristic::Balance.weight(0, &[(1, pdf), (1, other_pdf)])
+}
+
+/// Weights a sample with density `pdf` against one other strategy, each taking one sample, by
+/// the power heuristic of Veach with an exponent of two
+#[inline]
+#[must_use]
+pub fn power_heuristic<T: Float>(pdf: T, other_pdf: T) -> T {
+    MisHeuristic::Power.weight(0, &[(1, pdf), (1, other_pdf)])
+}
+
+/// A piecewise-constant distribution over `[0, 1)` proportional to a non-negative function
+#[derive(Debug, Clone, PartialEq)]
+pub struct PiecewiseConstant1D<T> {
+    func: Vec<T>,
+    cdf: Vec<T>,
+    integral: T,
+}
+
+impl<T: Float> PiecewiseConstant1D<T> {
+    /// Functions that are zero everywhere are sampled uniformly
+    ///
+    /// Panics if `func` is empty.
+    #[must_use]
+    pub fn new(func: Vec<T>) -> Self {
+        assert!(!func.is_empty(), "distributions need at least one value");
+        let func: Vec<T> = func.into_iter().map(T::abs).collect();
+        let n = T::from(func.len()).unwrap();
+
+        let mut cdf = Vec::with_capacity(func.len() + 1);
+        cdf.push(T::zero());
+        for (i, &f) in func.iter().enumerate() {
+            cdf.push(cdf[i] + f / n);
+        }
+        let integral = cdf[func.len()];
+        if integral == T::zero() {
+            for (i, c) in cdf.iter_mut().enumerate() {
+                *c = T::from(i).unwrap() / n;
+            }
+        } else {
+            for c in &mut cdf {
+                *c = *c / integral;
+            }
+        }
+        Self {
+            func,
+            cdf,
+            integral,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.func.len()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.func.is_empty()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn integral(&self) -> T {
+        self.integral
+    }
+
+    /// Returns the sampled position, its density and the index of its segment
+    #[must_use]
+    pub fn sample(&self, u: T) -> (T, T, usize) {
+        // The last segment whose cdf starts at or before `u`
+        let offset = self.cdf.partition_point(|&c| c <= u).clamp(1, self.len()) - 1;
+        let width = self.cdf[offset + 1] - self.cdf[offset];
+        let du = if width > T::zero() {
+            (u - self.cdf[offset]) / width
+        } else {
+            T::zero()
+        };
+        let n = T::from(self.len()).unwrap();
+        let x = (T::from(offset).unwrap() + du) / n;
+        (x.min(T::one() - T::epsilon()), self.pdf_at(offset), offset)
+    }
+
+    /// Returns the density of the segment containing `x`
+    #[inline]
+    #[must_use]
+    pub fn pdf(&self, x: T) -> T {
+        let n = T::from(self.len()).unwrap();
+        let offset = (x * n).to_usize().unwrap_or(0).min(self.len() - 1);
+        self.pdf_at(offset)
+    }
+
+    #[inline]
+    fn pdf_at(&self, offset: usize) -> T {
+        if self.integral > T::zero() {
+            self.func[offset] / self.integral
+        } else {
+            T::one()
+        }
+    }
+}
+
+/// A piecewise-constant distribution over `[0, 1)^2`, sampled by rows and then within the row
+#[derive(Debug, Clone, PartialEq)]
+pub struct PiecewiseConstant2D<T> {
+    conditional: Vec<PiecewiseConstant1D<T>>,
+    marginal: PiecewiseConstant1D<T>,
+}
+
+impl<T: Float> PiecewiseConstant2D<T> {
+    /// Creates the distribution from `width * height` values in row-major order, with the first
+    /// row at `y = 0`
+    ///
+    /// Panics if the number of values does not match or either dimension is zero.
+    #[must_use]
+    pub fn new(func: &[T], width: usize, height: usize) -> Self {
+        assert_eq!(func.len(), width * height, "wrong number of values");
+        let conditional: Vec<_> = func
+            .chunks_exact(width)
+            .map(|row| PiecewiseConstant1D::new(row.to_vec()))
+            .collect();
+        let marginal = PiecewiseConstant1D::new(conditional.iter().map(|c| c.integral()).collect());
+        Self {
+            conditional,
+            marginal,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn integral(&self) -> T {
+        self.marginal.integral()
+    }
+
+    /// Returns the sampled position and its density
+    #[must_use]
+    pub fn sample(&self, u: Point2<T, UnknownUnit>) -> (Point2<T, UnknownUnit>, T) {
+        let (y, pdf_y, row) = self.marginal.sample(u.y);
+        let (x, pdf_x, _) = self.conditional[row].sample(u.x);
+        (Point2::new(x, y), pdf_x * pdf_y)
+    }
+
+    #[must_use]
+    pub fn pdf(&self, p: Point2<T, UnknownUnit>) -> T {
+        let n = T::from(self.conditional.len()).unwrap();
+        let row = (p.y * n)
+            .to_usize()
+            .unwrap_or(0)
+            .min(self.conditional.len() - 1);
+        self.marginal.pdf(p.y) * self.conditional[row].pdf(p.x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mis_heuristics() {
+        assert_eq!(balance_heuristic(3., 1.), 0.75);
+        assert_eq!(power_heuristic(3., 1.), 0.9);
+        assert_eq!(power_heuristic(1., 3.), 0.1);
+        // Delta distributions take the whole weight, and samples no strategy draws none
+        assert_eq!(power_heuristic(f64::INFINITY, 2.), 1.);
+        assert_eq!(balance_heuristic(0., 0.), 0.);
+
+        // Sample counts scale the densities, and the weights of a sample sum to one
+        let strategies = [(2, 1.), (1, 2.), (4, 0.5)];
+        assert_eq!(MisHeuristic::Balance.weight(0, &strategies), 1. / 3.);
+        for heuristic in [MisHeuristic::Balance, MisHeuristic::Power] {
+            let sum: f64 = (0..3).map(|i| heuristic.weight(i, &strategies)).sum();
+            assert!((sum - 1.).abs() < 1e-12);
+        }
+
+        // Combining a uniform and a linear estimate of the integral of x^2 over [0, 1]
+        let n = 1000;
+        let mut estimate = 0.;
+        for i in 0..n {
+            let u = (f64::from(i) + 0.5) / f64::from(n);
+            let linear = |x: f64| 2. * x;
+            estimate += balance_heuristic(1., linear(u)) * u * u;
+            let x = u.sqrt();
+            estimate += balance_heuristic(linear(x), 1.) * x * x / linear(x);
+        }
+        assert!((estimate / f64::from(n) - 1. / 3.).abs() < 1e-6);
+    }
+}
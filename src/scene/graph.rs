@@ -0,0 +1,244 @@
+use crate::{
+    core::geometry::transform::{AnimatedTransform, Transform3},
+    scene::Instance,
+};
+use num_traits::Float;
+use std::fmt;
+
+/// A handle to a node of a [`SceneGraph`]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub struct NodeId(usize);
+
+struct Node<T, U, O> {
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    /// From the space of the node to that of its parent, or to the world for roots
+    local: Transform3<T, U, U>,
+    /// From the space of the node to the world, which is stale while the node is dirty
+    world: Transform3<T, U, U>,
+    /// Whether an ancestor or the node itself has moved since `world` was found, which is
+    /// then also true of all of its descendants
+    dirty: bool,
+    /// Placed relative to the node
+    instances: Vec<Instance<T, U, O>>,
+}
+
+/// A hierarchy of nodes, each placed relative to its parent, which carry instances along
+/// with them, such as the wheels of a car
+///
+/// Moving a node marks its subtree dirty, and the transforms to the world are only found
+/// again for dirty nodes when they are next needed. The graph is rendered by flattening it
+/// into the instances it carries, placed in the world, which go into an accelerator.
+pub struct SceneGraph<T, U, O> {
+    nodes: Vec<Node<T, U, O>>,
+}
+
+impl<T, U, O> fmt::Debug for SceneGraph<T, U, O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SceneGraph")
+            .field("nodes", &self.nodes.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, U, O> Default for SceneGraph<T, U, O> {
+    #[inline]
+    fn default() -> Self {
+        Self { nodes: Vec::new() }
+    }
+}
+
+impl<T: Float, U, O> SceneGraph<T, U, O> {
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a node placed by `local` relative to `parent`, or to the world without one
+    pub fn add_node(&mut self, parent: Option<NodeId>, local: Transform3<T, U, U>) -> NodeId {
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(Node {
+            parent,
+            children: Vec::new(),
+            local,
+            world: local,
+            dirty: true,
+            instances: Vec::new(),
+        });
+        if let Some(parent) = parent {
+            self.nodes[parent.0].children.push(id);
+        }
+        id
+    }
+
+    /// Attaches an instance to `node`, whose transform is then relative to the node
+    #[inline]
+    pub fn attach(&mut self, node: NodeId, instance: Instance<T, U, O>) {
+        self.nodes[node.0].instances.push(instance);
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn parent(&self, node: NodeId) -> Option<NodeId> {
+        self.nodes[node.0].parent
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn children(&self, node: NodeId) -> &[NodeId] {
+        &self.nodes[node.0].children
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn instances(&self, node: NodeId) -> &[Instance<T, U, O>] {
+        &self.nodes[node.0].instances
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn local_transform(&self, node: NodeId) -> &Transform3<T, U, U> {
+        &self.nodes[node.0].local
+    }
+
+    /// Moves `node` relative to its parent, along with all of its descendants
+    pub fn set_local_transform(&mut self, node: NodeId, local: Transform3<T, U, U>) {
+        self.nodes[node.0].local = local;
+        self.mark_dirty(node);
+    }
+
+    /// Moves `node` with its subtree under another parent, or to the root without one
+    ///
+    /// # Panics
+    ///
+    /// Panics if `parent` is `node` or one of its descendants.
+    pub fn set_parent(&mut self, node: NodeId, parent: Option<NodeId>) {
+        let mut ancestor = parent;
+        while let Some(id) = ancestor {
+            assert_ne!(id, node, "a node cannot descend from itself");
+            ancestor = self.nodes[id.0].parent;
+        }
+
+        if let Some(old) = self.nodes[node.0].parent {
+            self.nodes[old.0].children.retain(|&child| child != node);
+        }
+        if let Some(parent) = parent {
+            self.nodes[parent.0].children.push(node);
+        }
+        self.nodes[node.0].parent = parent;
+        self.mark_dirty(node);
+    }
+
+    /// Marks `node` and its descendants dirty, skipping subtrees which already are
+    fn mark_dirty(&mut self, node: NodeId) {
+        let mut stack = vec![node];
+        while let Some(id) = stack.pop() {
+            let node = &mut self.nodes[id.0];
+            if !node.dirty {
+                node.dirty = true;
+                stack.extend_from_slice(&node.children);
+            }
+        }
+    }
+
+    /// Returns the transform from the space of `node` to the world, finding it again for the
+    /// node and its dirty ancestors
+    pub fn world_transform(&mut self, node: NodeId) -> Transform3<T, U, U> {
+        // The dirty ancestors, from the node up
+        let mut path = Vec::new();
+        let mut next = Some(node);
+        while let Some(id) = next {
+            if !self.nodes[id.0].dirty {
+                break;
+            }
+            path.push(id);
+            next = self.nodes[id.0].parent;
+        }
+
+        for id in path.into_iter().rev() {
+            let local = self.nodes[id.0].local;
+            self.nodes[id.0].world = match self.nodes[id.0].parent {
+                Some(parent) => local * self.nodes[parent.0].world,
+                None => local,
+            };
+            self.nodes[id.0].dirty = false;
+        }
+        self.nodes[node.0].world
+    }
+
+    /// Returns all instances of the graph placed in the world, to become primitives with
+    /// [`Primitive::try_from`](crate::scene::Primitive::try_from)
+    #[must_use]
+    pub fn flatten(&mut self) -> Vec<Instance<T, U, O>> {
+        let mut flattened = Vec::new();
+        for index in 0..self.nodes.len() {
+            if self.nodes[index].instances.is_empty() {
+                continue;
+            }
+            let node_to_world = self.world_transform(NodeId(index));
+            flattened.extend(self.nodes[index].instances.iter().map(|instance| {
+                let keys = instance.object_to_world.keys();
+                let keys = keys.iter().map(|&(time, key)| (time, key * node_to_world));
+                Instance {
+                    object_to_world: AnimatedTransform::new(keys.collect()),
+                    ..instance.clone()
+                }
+            }));
+        }
+        flattened
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::geometry::{transform::Transformation, Point3, UnknownUnit, Vector3},
+        scene::Prototype,
+        shape::Sphere,
+    };
+    use std::sync::Arc;
+
+    #[test]
+    fn test_scene_graph() {
+        enum Object {}
+        let sphere = Arc::new(Sphere::<f64, Object>::new(Point3::origin(), 1.));
+        let prototype = Arc::new(Prototype::new(sphere, None));
+        fn translation<A, B>(x: f64, y: f64) -> Transform3<f64, A, B> {
+            Transform3::translation(Vector3::new(x, y, 0.))
+        }
+
+        // A car with a wheel, which sits on a table after being reparented
+        let mut graph = SceneGraph::<f64, UnknownUnit, Object>::new();
+        let car = graph.add_node(None, translation(10., 0.));
+        let wheel = graph.add_node(Some(car), translation(0., 2.));
+        let table = graph.add_node(None, translation(0., 5.));
+        graph.attach(
+            wheel,
+            Instance::new(Arc::clone(&prototype), translation(1., 0.)),
+        );
+        let center = |graph: &mut SceneGraph<_, _, _>| {
+            let flattened = graph.flatten();
+            assert_eq!(flattened.len(), 1);
+            let object_to_world = flattened[0].object_to_world().at(0.);
+            let center: Point3<_, _> = object_to_world
+                .transform(Point3::origin())
+                .try_into()
+                .unwrap();
+            center
+        };
+        assert_eq!(center(&mut graph), Point3::new(11., 2., 0.));
+
+        // Moving the car carries the wheel along, which is found again when needed
+        graph.set_local_transform(car, translation(20., 0.));
+        assert!(graph.nodes[wheel.0].dirty);
+        assert_eq!(center(&mut graph), Point3::new(21., 2., 0.));
+        assert!(!graph.nodes[wheel.0].dirty);
+
+        graph.set_parent(wheel, Some(table));
+        assert_eq!(graph.children(car), &[]);
+        assert_eq!(graph.world_transform(wheel), translation(0., 7.));
+        assert_eq!(center(&mut graph), Point3::new(1., 7., 0.));
+    }
+}
@@ -0,0 +1,143 @@
+use crate::{
+    camera::ThinLensCamera,
+    core::geometry::transform::AnimatedTransform,
+    scene::{Instance, Prototype},
+};
+use num_traits::Float;
+use std::{fmt, sync::Arc};
+
+/// The size in pixels from which a level is used, and its geometry
+type Level<T, U, O> = (T, Arc<Prototype<T, U, O>>);
+
+/// Versions of the same geometry in decreasing detail, such as meshes made with
+/// [`simplify`](crate::shape::TriangleMesh::simplify), of which instances get the one suited to
+/// how large they appear on the image
+///
+/// Each level is used for instances appearing at least as many pixels across as its threshold,
+/// and the last one for all smaller instances.
+pub struct Lod<T, U, O> {
+    levels: Vec<Level<T, U, O>>,
+}
+
+impl<T: fmt::Debug, U, O> fmt::Debug for Lod<T, U, O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let thresholds: Vec<_> = self.levels.iter().map(|(pixels, _)| pixels).collect();
+        f.debug_struct("Lod")
+            .field("thresholds", &thresholds)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Copy, U, O> Clone for Lod<T, U, O> {
+    fn clone(&self) -> Self {
+        Self {
+            levels: self.levels.clone(),
+        }
+    }
+}
+
+impl<T: Float, U, O> Lod<T, U, O> {
+    /// Creates levels from the finest to the coarsest, with the size in pixels from which each
+    /// is used
+    ///
+    /// Panics if there are no levels or their thresholds do not decrease.
+    #[must_use]
+    pub fn new(levels: Vec<Level<T, U, O>>) -> Self {
+        assert!(!levels.is_empty(), "a level of detail needs a level");
+        assert!(
+            levels.windows(2).all(|w| w[0].0 > w[1].0),
+            "the thresholds of levels of detail must decrease"
+        );
+        Self { levels }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn levels(&self) -> &[Level<T, U, O>] {
+        &self.levels
+    }
+
+    /// Returns the level for geometry appearing `pixels` across
+    #[must_use]
+    pub fn select(&self, pixels: T) -> &Arc<Prototype<T, U, O>> {
+        let level = self
+            .levels
+            .iter()
+            .find(|(threshold, _)| pixels >= *threshold)
+            .unwrap_or_else(|| self.levels.last().unwrap());
+        &level.1
+    }
+
+    /// Places the level suited to the size of the instance seen by `camera` on an image
+    /// `height` pixels tall, measured by the bounds of the finest level over the shutter
+    /// interval
+    ///
+    /// # Panics
+    ///
+    /// Panics if the transform of the camera is projective.
+    #[must_use]
+    pub fn instance(
+        &self,
+        object_to_world: impl Into<AnimatedTransform<T, O, U>>,
+        camera: &ThinLensCamera<T, U>,
+        height: usize,
+    ) -> Instance<T, U, O> {
+        let object_to_world = object_to_world.into();
+        let bounds = object_to_world.motion_bounds(self.levels[0].1.shape().bounds());
+        let pixels = camera.projected_size(bounds, height);
+        Instance::new(Arc::clone(self.select(pixels)), object_to_world)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::{
+            geometry::{transform::Transform3, Box3, Point3, UnknownUnit, Vector3},
+            units::Angle,
+        },
+        shape::{Shape, Sphere},
+    };
+
+    #[test]
+    fn test_lod() {
+        enum Object {}
+        let level = |r: f64| {
+            let sphere = Arc::new(Sphere::<f64, Object>::new(Point3::origin(), r));
+            Arc::new(Prototype::<f64, UnknownUnit, Object>::new(sphere, None))
+        };
+        let lod = Lod::new(vec![
+            (100., level(1.)),
+            (10., level(0.99)),
+            (0., level(0.9)),
+        ]);
+        assert_eq!(lod.levels().len(), 3);
+        assert!(Arc::ptr_eq(lod.select(1e3), &lod.levels()[0].1));
+        assert!(Arc::ptr_eq(lod.select(50.), &lod.levels()[1].1));
+        assert!(Arc::ptr_eq(lod.select(-1.), &lod.levels()[2].1));
+
+        // The sphere around a box of size 2 which is 100 units away spans 17 pixels of a 90
+        // degree view 1000 pixels tall
+        let camera = ThinLensCamera::look_at(
+            Point3::origin(),
+            Point3::new(0., 0., -1.),
+            Vector3::new(0., 1., 0.),
+            Angle::from_degrees(90.),
+            1.,
+        );
+        let place = |z: f64| Transform3::translation(Vector3::new(0., 0., z));
+        let bounds = Box3::new(Point3::new(-1., -1., -101.), Point3::new(1., 1., -99.));
+        let pixels = camera.projected_size(bounds, 1000);
+        assert!((pixels - 3f64.sqrt() / 100. * 1000.).abs() < 1e-9);
+        let radius = |instance: Instance<f64, UnknownUnit, Object>| {
+            instance.prototype().shape().bounds().max.x
+        };
+        assert_eq!(radius(lod.instance(place(-5.), &camera, 1000)), 1.);
+        assert_eq!(radius(lod.instance(place(-100.), &camera, 1000)), 0.99);
+        assert_eq!(radius(lod.instance(place(-1e4), &camera, 1000)), 0.9);
+        // Bounds around the camera are as large as can be
+        let around = bounds.inflate(1., 1., 100.);
+        assert!(camera.projected_size(around, 1000).is_infinite());
+    }
+}
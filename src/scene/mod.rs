@@ -0,0 +1,665 @@
+//! Everything a light transport algorithm needs to know about what is being rendered
+
+mod graph;
+mod lod;
+
+pub use graph::{NodeId, SceneGraph};
+pub use lod::Lod;
+
+use crate::{
+    accel::{Accelerator, BlasInstance, InstanceTransformError},
+    core::{
+        geometry::{
+            transform::{AnimatedTransform, Transformation},
+            Box3, Point3, Ray,
+        },
+        units::Time,
+    },
+    light::{BvhLightSampler, DiffuseAreaLight, Light, LightSampler},
+    material::Material,
+    medium::{Medium, MediumInterface},
+    sampler::hash,
+    shape::{SampleShape, Shape, SurfaceInteraction},
+    texture::Texture,
+};
+use num_traits::Float;
+use std::{collections::HashMap, fmt, sync::Arc};
+
+/// A shape with the appearance of its surface
+pub struct Primitive<T, U> {
+    shape: Arc<dyn Shape<T, U> + Send + Sync>,
+    material: Option<Arc<dyn Material<T, U>>>,
+    area_light: Option<Arc<DiffuseAreaLight<T, U>>>,
+    medium_interface: Option<MediumInterface<T, U>>,
+}
+
+impl<T, U> fmt::Debug for Primitive<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Primitive")
+            .field("emissive", &self.area_light.is_some())
+            .field("medium_interface", &self.medium_interface)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, U> Clone for Primitive<T, U> {
+    fn clone(&self) -> Self {
+        Self {
+            shape: Arc::clone(&self.shape),
+            material: self.material.clone(),
+            area_light: self.area_light.clone(),
+            medium_interface: self.medium_interface.clone(),
+        }
+    }
+}
+
+impl<T: Float, U> Primitive<T, U> {
+    /// Surfaces without a material absorb all light
+    #[must_use]
+    pub fn new(
+        shape: Arc<dyn Shape<T, U> + Send + Sync>,
+        material: Option<Arc<dyn Material<T, U>>>,
+    ) -> Self {
+        Self {
+            shape,
+            material,
+            area_light: None,
+            medium_interface: None,
+        }
+    }
+
+    /// Creates a primitive whose surface emits light, see [`DiffuseAreaLight`]
+    #[must_use]
+    pub fn emissive(
+        shape: Arc<dyn SampleShape<T, U> + Send + Sync>,
+        material: Option<Arc<dyn Material<T, U>>>,
+        light: DiffuseAreaLight<T, U>,
+    ) -> Self {
+        assert!(
+            Arc::ptr_eq(&shape, light.shape()),
+            "area lights must emit from the shape of their primitive"
+        );
+        Self {
+            shape,
+            material,
+            area_light: Some(Arc::new(light)),
+            medium_interface: None,
+        }
+    }
+
+    /// Separates different media with the surface
+    ///
+    /// Surfaces without a material are then only boundaries between the media, which light
+    /// passes through unchanged.
+    #[must_use]
+    pub fn with_medium_interface(mut self, medium_interface: MediumInterface<T, U>) -> Self {
+        self.medium_interface = Some(medium_interface);
+        self
+    }
+}
+
+impl<T, U> Primitive<T, U> {
+    #[inline]
+    #[must_use]
+    pub fn shape(&self) -> &Arc<dyn Shape<T, U> + Send + Sync> {
+        &self.shape
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn material(&self) -> Option<&Arc<dyn Material<T, U>>> {
+        self.material.as_ref()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn area_light(&self) -> Option<&Arc<DiffuseAreaLight<T, U>>> {
+        self.area_light.as_ref()
+    }
+
+    /// The media on either side of the surface, if it separates different ones
+    #[inline]
+    #[must_use]
+    pub fn medium_interface(&self) -> Option<&MediumInterface<T, U>> {
+        self.medium_interface.as_ref()
+    }
+}
+
+impl<T: Float, U> Primitive<T, U> {
+    #[inline]
+    fn alpha(&self) -> Option<&dyn Texture<T, U>> {
+        self.material.as_ref()?.alpha()
+    }
+
+    /// Returns the closest hit on an opaque part of the surface, continuing the ray through
+    /// the cutouts of its material
+    fn intersect_masked(
+        &self,
+        ray: &Ray<T, U>,
+        t_max: Time<T>,
+        alpha: &dyn Texture<T, U>,
+    ) -> Option<SurfaceInteraction<T, U>> {
+        let mut t_start = T::zero();
+        let mut origin = ray.origin;
+        loop {
+            let continued = Ray { origin, ..*ray };
+            let mut hit = self.shape.intersect(&continued, Time(t_max.0 - t_start))?;
+            let t = t_start + hit.t.0;
+            if is_opaque(alpha.evaluate(&hit), ray, t) {
+                hit.t = Time(t);
+                return Some(hit);
+            }
+            origin = hit.offset_origin(ray.dir);
+            t_start = (origin - ray.origin).dot(ray.dir) / ray.dir.length_squared();
+        }
+    }
+}
+
+/// Decides whether a hit stops the ray, with a probability of `alpha`
+///
+/// The decision hashes the ray instead of drawing a random number, so that it is the same
+/// whenever the ray is traced.
+fn is_opaque<T: Float, U>(alpha: T, ray: &Ray<T, U>, t: T) -> bool {
+    if alpha >= T::one() {
+        return true;
+    }
+    if alpha <= T::zero() {
+        return false;
+    }
+    let bits = |x: T| x.to_f64().unwrap_or(0.).to_bits();
+    let (o, d) = (ray.origin, ray.dir);
+    let h = hash(&[o.x, o.y, o.z, d.x, d.y, d.z, t].map(bits));
+    let u = (h >> 11) as f64 / (1_u64 << 53) as f64;
+    T::from(u).unwrap() < alpha
+}
+
+impl<T: Float, U> Shape<T, U> for Primitive<T, U> {
+    #[inline]
+    fn bounds(&self) -> Box3<T, U> {
+        self.shape.bounds()
+    }
+
+    #[inline]
+    fn intersect(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<SurfaceInteraction<T, U>> {
+        match self.alpha() {
+            Some(alpha) => self.intersect_masked(ray, t_max, alpha),
+            None => self.shape.intersect(ray, t_max),
+        }
+    }
+
+    #[inline]
+    fn intersect_any(&self, ray: &Ray<T, U>, t_max: Time<T>) -> bool {
+        match self.alpha() {
+            Some(alpha) => self.intersect_masked(ray, t_max, alpha).is_some(),
+            None => self.shape.intersect_any(ray, t_max),
+        }
+    }
+
+    /// Returns the triangle of the shape, unless the material cuts parts of it out
+    #[inline]
+    fn triangle(&self) -> Option<[Point3<T, U>; 3]> {
+        match self.alpha() {
+            Some(_) => None,
+            None => self.shape.triangle(),
+        }
+    }
+}
+
+/// Geometry shared by all of its instances, in its own space `O`, with the material they have
+/// unless they override it
+pub struct Prototype<T, U, O> {
+    shape: Arc<dyn Shape<T, O> + Send + Sync>,
+    material: Option<Arc<dyn Material<T, U>>>,
+}
+
+impl<T, U, O> fmt::Debug for Prototype<T, U, O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Prototype")
+            .field("material", &self.material.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, U, O> Prototype<T, U, O> {
+    #[inline]
+    #[must_use]
+    pub fn new(
+        shape: Arc<dyn Shape<T, O> + Send + Sync>,
+        material: Option<Arc<dyn Material<T, U>>>,
+    ) -> Self {
+        Self { shape, material }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn shape(&self) -> &Arc<dyn Shape<T, O> + Send + Sync> {
+        &self.shape
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn material(&self) -> Option<&Arc<dyn Material<T, U>>> {
+        self.material.as_ref()
+    }
+}
+
+/// A copy of a prototype placed in the scene by a transform, which costs the memory of the
+/// transform rather than of the geometry, such as for the trees of a forest
+///
+/// The transform may change over the shutter interval, which blurs the instance along its
+/// motion.
+///
+/// Instances become primitives, which may go into any accelerator alongside others. They do
+/// not emit light.
+pub struct Instance<T, U, O> {
+    prototype: Arc<Prototype<T, U, O>>,
+    object_to_world: AnimatedTransform<T, O, U>,
+    material: Option<Arc<dyn Material<T, U>>>,
+}
+
+impl<T: Float + fmt::Debug, U, O> fmt::Debug for Instance<T, U, O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Instance")
+            .field("prototype", &self.prototype)
+            .field("object_to_world", &self.object_to_world)
+            .field("material", &self.material.is_some())
+            .finish()
+    }
+}
+
+impl<T: Copy, U, O> Clone for Instance<T, U, O> {
+    fn clone(&self) -> Self {
+        Self {
+            prototype: Arc::clone(&self.prototype),
+            object_to_world: self.object_to_world.clone(),
+            material: self.material.clone(),
+        }
+    }
+}
+
+impl<T: Float, U, O> Instance<T, U, O> {
+    /// Places the prototype with its own material
+    #[inline]
+    #[must_use]
+    pub fn new(
+        prototype: Arc<Prototype<T, U, O>>,
+        object_to_world: impl Into<AnimatedTransform<T, O, U>>,
+    ) -> Self {
+        Self {
+            prototype,
+            object_to_world: object_to_world.into(),
+            material: None,
+        }
+    }
+
+    /// Gives this instance another material than that of its prototype
+    #[inline]
+    #[must_use]
+    pub fn with_material(mut self, material: Arc<dyn Material<T, U>>) -> Self {
+        self.material = Some(material);
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn prototype(&self) -> &Arc<Prototype<T, U, O>> {
+        &self.prototype
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn object_to_world(&self) -> &AnimatedTransform<T, O, U> {
+        &self.object_to_world
+    }
+
+    /// The material of the instance, or else of its prototype
+    #[inline]
+    #[must_use]
+    pub fn material(&self) -> Option<&Arc<dyn Material<T, U>>> {
+        self.material.as_ref().or(self.prototype.material.as_ref())
+    }
+}
+
+impl<T, U, O> TryFrom<Instance<T, U, O>> for Primitive<T, U>
+where
+    T: Float + Send + Sync + 'static,
+    U: Send + Sync + 'static,
+    O: Send + Sync + 'static,
+{
+    type Error = InstanceTransformError;
+
+    /// Fails if a transform of the instance is projective
+    fn try_from(instance: Instance<T, U, O>) -> Result<Self, Self::Error> {
+        let material = instance.material().cloned();
+        let shape = Arc::clone(&instance.prototype.shape);
+        if instance.object_to_world.is_animated() {
+            let bounds = shape.bounds();
+            let keys = instance.object_to_world.keys();
+            if keys.iter().any(|(_, key)| key.transform(bounds).is_none()) {
+                return Err(InstanceTransformError);
+            }
+            let bounds = instance.object_to_world.motion_bounds(bounds);
+            let moving = MovingInstance {
+                shape,
+                object_to_world: instance.object_to_world,
+                bounds,
+            };
+            Ok(Self::new(Arc::new(moving), material))
+        } else {
+            let object_to_world = instance.object_to_world.keys()[0].1;
+            let instance = BlasInstance::new(shape, object_to_world)?;
+            Ok(Self::new(Arc::new(instance), material))
+        }
+    }
+}
+
+/// The shape of an instance whose transform changes over the shutter interval, which is found
+/// for the moment of each ray
+struct MovingInstance<T, U, O> {
+    shape: Arc<dyn Shape<T, O> + Send + Sync>,
+    object_to_world: AnimatedTransform<T, O, U>,
+    bounds: Box3<T, U>,
+}
+
+impl<T: Float, U, O> Shape<T, U> for MovingInstance<T, U, O> {
+    #[inline]
+    fn bounds(&self) -> Box3<T, U> {
+        self.bounds
+    }
+
+    fn intersect(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<SurfaceInteraction<T, U>> {
+        let object_to_world = self.object_to_world.at(ray.time);
+        let ray = object_to_world.inverse().transform(*ray)?;
+        self.shape
+            .intersect(&ray, t_max)?
+            .transform(&object_to_world)
+    }
+
+    fn intersect_any(&self, ray: &Ray<T, U>, t_max: Time<T>) -> bool {
+        let object_to_world = self.object_to_world.at(ray.time);
+        object_to_world
+            .inverse()
+            .transform(*ray)
+            .is_some_and(|ray| self.shape.intersect_any(&ray, t_max))
+    }
+}
+
+type Aggregate<T, U> = dyn Accelerator<T, U, Primitive = Primitive<T, U>> + Send + Sync;
+
+/// The primitives of a scene behind an accelerator, and the lights illuminating them
+pub struct Scene<T, U> {
+    aggregate: Box<Aggregate<T, U>>,
+    lights: Vec<Arc<dyn Light<T, U>>>,
+    /// The index in `lights` of the area light of each emissive primitive
+    area_lights: HashMap<usize, usize>,
+    light_sampler: Box<dyn LightSampler<T, U>>,
+    medium: Option<Arc<dyn Medium<T, U>>>,
+}
+
+impl<T, U> fmt::Debug for Scene<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scene")
+            .field("primitives", &self.aggregate.primitives().len())
+            .field("lights", &self.lights.len())
+            .field("medium", &self.medium.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Float + Send + Sync + 'static, U: Send + Sync + 'static> Scene<T, U> {
+    /// The area lights of emissive primitives are added to `lights`, which are then picked
+    /// with a [`BvhLightSampler`]
+    #[must_use]
+    pub fn new(
+        aggregate: impl Accelerator<T, U, Primitive = Primitive<T, U>> + Send + Sync + 'static,
+        mut lights: Vec<Arc<dyn Light<T, U>>>,
+    ) -> Self {
+        let mut area_lights = HashMap::new();
+        for (i, primitive) in aggregate.primitives().iter().enumerate() {
+            if let Some(light) = &primitive.area_light {
+                area_lights.insert(i, lights.len());
+                lights.push(Arc::clone(light) as Arc<dyn Light<T, U>>);
+            }
+        }
+        let light_sampler = Box::new(BvhLightSampler::new(&lights));
+        Self {
+            aggregate: Box::new(aggregate),
+            lights,
+            area_lights,
+            light_sampler,
+            medium: None,
+        }
+    }
+
+    /// Replaces how lights are picked, such as by a sampler built over [`lights`](Self::lights)
+    #[must_use]
+    pub fn with_light_sampler(mut self, light_sampler: impl LightSampler<T, U> + 'static) -> Self {
+        self.light_sampler = Box::new(light_sampler);
+        self
+    }
+
+    /// Fills the space around the camera with a medium, up to the surfaces bounding other media
+    #[must_use]
+    pub fn with_medium(mut self, medium: Arc<dyn Medium<T, U>>) -> Self {
+        self.medium = Some(medium);
+        self
+    }
+}
+
+impl<T, U> Scene<T, U> {
+    #[inline]
+    #[must_use]
+    pub fn aggregate(&self) -> &Aggregate<T, U> {
+        &*self.aggregate
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn lights(&self) -> &[Arc<dyn Light<T, U>>] {
+        &self.lights
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn light_sampler(&self) -> &dyn LightSampler<T, U> {
+        &*self.light_sampler
+    }
+
+    /// Returns the index in [`lights`](Self::lights) of the area light of the primitive that
+    /// was hit, if it emits
+    #[inline]
+    #[must_use]
+    pub fn area_light_index(&self, hit: &SurfaceInteraction<T, U>) -> Option<usize> {
+        self.area_lights.get(&hit.primitive).copied()
+    }
+
+    /// The medium the camera is in
+    #[inline]
+    #[must_use]
+    pub fn medium(&self) -> Option<&Arc<dyn Medium<T, U>>> {
+        self.medium.as_ref()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn bounds(&self) -> Box3<T, U> {
+        self.aggregate.bounds()
+    }
+
+    /// Returns the closest hit along the ray
+    #[inline]
+    #[must_use]
+    pub fn intersect(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<SurfaceInteraction<T, U>>
+    where
+        T: Copy,
+    {
+        let mut hit = self.aggregate.intersect(ray, t_max)?;
+        hit.time = ray.time;
+        Some(hit)
+    }
+
+    /// Returns whether anything blocks the ray before `t_max`
+    #[inline]
+    #[must_use]
+    pub fn intersect_any(&self, ray: &Ray<T, U>, t_max: Time<T>) -> bool {
+        self.aggregate.intersect_any(ray, t_max)
+    }
+
+    /// Returns the closest hit along each ray like [`intersect`](Self::intersect), tracing
+    /// them together where the accelerator can
+    #[must_use]
+    pub fn intersect_batch(
+        &self,
+        rays: &[(Ray<T, U>, Time<T>)],
+    ) -> Vec<Option<SurfaceInteraction<T, U>>>
+    where
+        T: Copy,
+    {
+        let mut hits = self.aggregate.intersect_batch(rays);
+        for (hit, (ray, _)) in hits.iter_mut().zip(rays) {
+            if let Some(hit) = hit {
+                hit.time = ray.time;
+            }
+        }
+        hits
+    }
+
+    /// Returns whether anything blocks each ray before its `t_max`, tracing them together
+    /// where the accelerator can
+    #[inline]
+    #[must_use]
+    pub fn intersect_any_batch(&self, rays: &[(Ray<T, U>, Time<T>)]) -> Vec<bool>
+    where
+        T: Copy,
+    {
+        self.aggregate.intersect_any_batch(rays)
+    }
+
+    /// Returns the primitive that was hit
+    #[inline]
+    #[must_use]
+    pub fn primitive(&self, hit: &SurfaceInteraction<T, U>) -> &Primitive<T, U> {
+        &self.aggregate.primitives()[hit.primitive]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        accel::Bvh,
+        color::Rgb,
+        core::geometry::{transform::Transform3, UnknownUnit, Vector3},
+        material::{DiffuseMaterial, MaskedMaterial},
+        shape::{Disk, Sphere},
+        texture::CheckerTexture,
+    };
+
+    type P = Point3<f64, UnknownUnit>;
+    type V = Vector3<f64, UnknownUnit>;
+
+    #[test]
+    fn test_alpha_cutouts() {
+        let diffuse = DiffuseMaterial::new(Rgb::splat(0.5));
+        // Opaque and transparent in alternating quarter turns of a disk in front of a sphere
+        let mask = CheckerTexture {
+            even: 1.,
+            odd: 0.,
+            scale_u: 4.,
+            scale_v: 1.,
+        };
+        let disk = Primitive::new(
+            Arc::new(Disk::new(P::new(0., 0., 1.), 2., 0.)),
+            Some(Arc::new(MaskedMaterial::new(diffuse, mask))),
+        );
+        let sphere = Primitive::new(
+            Arc::new(Sphere::new(P::new(0., 0., -2.), 1.)),
+            Some(Arc::new(diffuse)),
+        );
+        let scene = Scene::new(Bvh::new(vec![disk, sphere]), Vec::new());
+        let down = |x: f64, y: f64| Ray::new(P::new(x, y, 5.), V::new(0., 0., -1.));
+        let inf = Time(f64::INFINITY);
+
+        let hit = scene.intersect(&down(0.5, 0.2), inf).unwrap();
+        assert_eq!(hit.primitive, 0);
+        assert!((hit.t.0 - 4.).abs() < 1e-9);
+        // Through the cutout onto the sphere, with the distance along the original ray
+        let hit = scene.intersect(&down(-0.5, 0.2), inf).unwrap();
+        assert_eq!(hit.primitive, 1);
+        assert!((hit.t.0 - (7. - 0.71_f64.sqrt())).abs() < 1e-9);
+        assert!(scene.intersect(&down(-1.2, 1.2), inf).is_none());
+        assert!(!scene.intersect_any(&down(-1.2, 1.2), inf));
+        assert!(scene.intersect_any(&down(1.2, 1.2), inf));
+
+        // Partial opacity stops a matching fraction of rays, the same way every time
+        let veil = Primitive::new(
+            Arc::new(Disk::new(P::new(0., 0., 1.), 2., 0.)),
+            Some(Arc::new(MaskedMaterial::new(diffuse, 0.3))),
+        );
+        let scene = Scene::new(Bvh::new(vec![veil]), Vec::new());
+        let n = 10_000;
+        let rays = (0..n).map(|i| down(f64::from(i) / f64::from(n) - 0.5, 0.1));
+        let blocked = rays.filter(|ray| scene.intersect_any(ray, inf)).count();
+        assert!(
+            (blocked as f64 / f64::from(n) - 0.3).abs() < 0.02,
+            "{blocked}"
+        );
+        assert_eq!(
+            scene.intersect_any(&down(0.25, 0.1), inf),
+            scene.intersect(&down(0.25, 0.1), inf).is_some()
+        );
+    }
+
+    #[test]
+    fn test_instances() {
+        enum Object {}
+        let material: Arc<dyn Material<f64, UnknownUnit>> =
+            Arc::new(DiffuseMaterial::new(Rgb::splat(0.5)));
+        let red: Arc<dyn Material<f64, UnknownUnit>> =
+            Arc::new(DiffuseMaterial::new(Rgb::new(1., 0., 0.)));
+        let sphere = Arc::new(Sphere::<f64, Object>::new(Point3::origin(), 1.));
+        let prototype = Arc::new(Prototype::new(sphere, Some(Arc::clone(&material))));
+
+        // Two copies of the same sphere side by side, the second one painted red
+        let place = |x: f64| {
+            let translation = Transform3::translation(Vector3::new(x, 0., 0.));
+            Instance::new(Arc::clone(&prototype), translation)
+        };
+        // And a third one rising over the shutter interval
+        let rising = AnimatedTransform::between(
+            Transform3::translation(Vector3::new(6., 0., 0.)),
+            Transform3::translation(Vector3::new(6., 4., 0.)),
+        );
+        let primitives = vec![
+            Primitive::try_from(place(-2.)).unwrap(),
+            place(2.)
+                .with_material(Arc::clone(&red))
+                .try_into()
+                .unwrap(),
+            Instance::new(Arc::clone(&prototype), rising)
+                .try_into()
+                .unwrap(),
+        ];
+        let scene = Scene::new(Bvh::new(primitives), Vec::new());
+        assert_eq!(Arc::strong_count(prototype.shape()), 4);
+
+        let inf = Time(f64::INFINITY);
+        for (x, expected) in [(-2., &material), (2., &red)] {
+            let ray = Ray::new(P::new(x, 0., 5.), V::new(0., 0., -1.));
+            let hit = scene.intersect(&ray, inf).unwrap();
+            assert!((hit.p - P::new(x, 0., 1.)).length() < 1e-9);
+            let found = scene.primitive(&hit).material().unwrap();
+            assert!(Arc::ptr_eq(found, expected));
+        }
+        let between = Ray::new(P::new(0., 0., 5.), V::new(0., 0., -1.));
+        assert!(!scene.intersect_any(&between, inf));
+
+        // The moving instance is found where it is at the moment of each ray
+        let down = |y: f64, time: f64| Ray::with_time(P::new(6., y, 5.), V::new(0., 0., -1.), time);
+        assert!(scene.intersect_any(&down(0., 0.), inf));
+        assert!(!scene.intersect_any(&down(0., 1.), inf));
+        let hit = scene.intersect(&down(2., 0.5), inf).unwrap();
+        assert!((hit.p - P::new(6., 2., 1.)).length() < 1e-9);
+        assert_eq!(hit.time, 0.5);
+    }
+}
@@ -0,0 +1,1114 @@
+use crate::{
+    accel::Bvh,
+    core::geometry::{
+        transform::{AnimatedTransform, Transform3},
+        Point2, Point3, Vector3,
+    },
+    material::Material,
+    scene::{Instance, Primitive, Prototype},
+    scene_io::SceneError,
+    shape::{MovingMesh, MovingTriangle, Shape, Triangle, TriangleMesh},
+};
+use num_traits::Float;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    fs::File,
+    io::Read,
+    path::Path,
+    sync::Arc,
+};
+
+/// The flag of the children of Ogawa groups which are data rather than groups
+const DATA: u64 = 1 << 63;
+
+/// The time per cycle of acyclic time samplings, whose samples are at arbitrary times
+const ACYCLIC: f64 = f64::MAX / 32.;
+
+/// The meshes of an Alembic archive, with their baked animation
+///
+/// Polygon meshes, and the control meshes of subdivision surfaces, are read at every sample
+/// with their normals and texture coordinates, and placed by the transforms of the objects
+/// above them. Other objects, such as cameras, curves, points and face sets, are skipped, as
+/// are velocities and user properties. Only archives in the Ogawa layout are supported, not
+/// the older ones in HDF5.
+pub struct Alembic<T, U> {
+    pub meshes: Vec<AlembicMesh<T, U>>,
+}
+
+impl<T: Float + fmt::Debug, U> fmt::Debug for Alembic<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Alembic")
+            .field("meshes", &self.meshes)
+            .finish()
+    }
+}
+
+/// A mesh of an Alembic archive, at the moments it was sampled
+pub struct AlembicMesh<T, U> {
+    /// The full name of the object, such as `/body/bodyShape`
+    pub name: String,
+    /// The mesh at increasing moments in seconds, whose triangles may differ between them
+    pub samples: Vec<(T, TriangleMesh<T, U>)>,
+    /// The transform from the mesh to the world, with keys in seconds
+    pub object_to_world: AnimatedTransform<T, U, U>,
+}
+
+impl<T: Float + fmt::Debug, U> fmt::Debug for AlembicMesh<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AlembicMesh")
+            .field("name", &self.name)
+            .field("samples", &self.samples.len())
+            .field("object_to_world", &self.object_to_world)
+            .finish()
+    }
+}
+
+impl<T: Float + Send + Sync + 'static, U: Send + Sync + 'static> Alembic<T, U> {
+    /// Reads an archive in the Ogawa layout
+    pub fn read(mut reader: impl Read) -> Result<Self, SceneError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        if bytes.starts_with(b"\x89HDF") {
+            return Err(SceneError::Unsupported("HDF5 Alembic archives".into()));
+        }
+        let (archive, top) = Archive::new(&bytes)?;
+
+        let mut meshes = Vec::new();
+        let mut stack = vec![(top, String::new(), Vec::new())];
+        // Groups reached twice may contain themselves, and would be walked forever
+        let mut visited = HashSet::from([top]);
+        while let Some((group, parent, chain)) = stack.pop() {
+            for object in archive.objects(group)?.into_iter().rev() {
+                if !visited.insert(object.group) {
+                    return Err(SceneError::InvalidFormat);
+                }
+                let name = format!("{parent}/{}", object.name);
+                let properties = archive.properties(object.group)?;
+                let schema = metadata_value(&object.metadata, "schema").unwrap_or_default();
+                let mut chain: Vec<Arc<AnimatedTransform<T, U, U>>> = chain.clone();
+                if schema.starts_with("AbcGeom_Xform") {
+                    let (local, inherits) = xform(&archive, &properties)?;
+                    if !inherits {
+                        chain.clear();
+                    }
+                    chain.push(Arc::new(local));
+                } else if schema.starts_with("AbcGeom_PolyMesh")
+                    || schema.starts_with("AbcGeom_SubD")
+                {
+                    let samples = mesh_samples(&archive, &properties)?;
+                    if !samples.is_empty() {
+                        meshes.push(AlembicMesh {
+                            name: name.clone(),
+                            samples,
+                            object_to_world: world(&chain),
+                        });
+                    }
+                }
+                stack.push((object.group, name, chain));
+            }
+        }
+        Ok(Self { meshes })
+    }
+
+    /// Reads the archive at `path`, see [`read`](Self::read)
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SceneError> {
+        Self::read(File::open(path)?)
+    }
+
+    /// Returns the first and last moments, in seconds, at which any mesh or transform was
+    /// sampled, or none if the archive has no meshes
+    #[must_use]
+    pub fn time_range(&self) -> Option<(T, T)> {
+        let times = self.meshes.iter().flat_map(|mesh| {
+            let samples = mesh.samples.iter().map(|(time, _)| *time);
+            samples.chain(mesh.object_to_world.keys().iter().map(|(time, _)| *time))
+        });
+        times.fold(None, |range, time| match range {
+            Some((start, end)) => Some((time.min(start), time.max(end))),
+            None => Some((time, time)),
+        })
+    }
+
+    /// Returns the meshes as they move while the shutter is open, see
+    /// [`AlembicMesh::primitive`]
+    pub fn primitives(
+        &self,
+        shutter_open: T,
+        shutter_close: T,
+        material: Option<Arc<dyn Material<T, U>>>,
+    ) -> Result<Vec<Primitive<T, U>>, SceneError> {
+        self.meshes
+            .iter()
+            .map(|mesh| mesh.primitive(shutter_open, shutter_close, material.clone()))
+            .collect()
+    }
+}
+
+impl<T: Float + Send + Sync + 'static, U: Send + Sync + 'static> AlembicMesh<T, U> {
+    /// Returns the mesh at a moment in seconds, interpolating the positions and normals of the
+    /// samples around it if they have the same triangles
+    #[must_use]
+    pub fn mesh_at(&self, time: T) -> TriangleMesh<T, U> {
+        let next = self.samples.partition_point(|(t, _)| *t <= time);
+        if next == 0 {
+            return self.samples[0].1.clone();
+        }
+        let (t0, a) = &self.samples[next - 1];
+        let Some((t1, b)) = self.samples.get(next) else {
+            return a.clone();
+        };
+        if !same_triangles(a, b) {
+            return a.clone();
+        }
+        let w = (time - *t0) / (*t1 - *t0);
+        let mut mesh = a.clone();
+        for (p, q) in mesh.positions.iter_mut().zip(&b.positions) {
+            *p = *p + (*q - *p) * w;
+        }
+        if let (Some(normals), Some(next)) = (&mut mesh.normals, &b.normals) {
+            for (n, m) in normals.iter_mut().zip(next) {
+                let v = n.to_vector();
+                *n = (v + (m.to_vector() - v) * w).to_normal();
+            }
+        }
+        mesh
+    }
+
+    /// Returns the mesh as it moves while the shutter is open, between moments in seconds
+    ///
+    /// Both the vertices and the transform of the mesh are blurred along their motion, with
+    /// keys at the moments the shutter opens and closes and at the samples in between. Where
+    /// the triangles of the mesh change while the shutter is open, it stays as it was when it
+    /// opened.
+    pub fn primitive(
+        &self,
+        shutter_open: T,
+        shutter_close: T,
+        material: Option<Arc<dyn Material<T, U>>>,
+    ) -> Result<Primitive<T, U>, SceneError> {
+        let duration = shutter_close - shutter_open;
+        let moments = |samples: &mut dyn Iterator<Item = T>| {
+            let mut moments = vec![shutter_open];
+            moments.extend(samples.filter(|&t| t > shutter_open && t < shutter_close));
+            if duration > T::zero() {
+                moments.push(shutter_close);
+            }
+            moments
+        };
+        // Keys over the shutter interval, normalized to `[0, 1]`
+        let key = |time: T| {
+            if duration > T::zero() {
+                (time - shutter_open) / duration
+            } else {
+                T::zero()
+            }
+        };
+
+        let mut samples = self.samples.iter().map(|(time, _)| *time);
+        let mut keys: Vec<_> = moments(&mut samples)
+            .into_iter()
+            .map(|time| (key(time), self.mesh_at(time)))
+            .collect();
+        let first = &keys[0].1;
+        let moving = keys.iter().all(|(_, mesh)| same_triangles(mesh, first))
+            && keys
+                .iter()
+                .any(|(_, mesh)| mesh.positions != first.positions);
+        let shape: Arc<dyn Shape<T, U> + Send + Sync> = if moving {
+            let mesh = Arc::new(MovingMesh::new(keys));
+            Arc::new(Bvh::new(MovingTriangle::from_mesh(&mesh).collect()))
+        } else {
+            let mesh = Arc::new(keys.swap_remove(0).1);
+            Arc::new(Bvh::new(Triangle::from_mesh(&mesh).collect()))
+        };
+
+        let mut times = self.object_to_world.keys().iter().map(|(time, _)| *time);
+        let mut transforms: Vec<_> = moments(&mut times)
+            .into_iter()
+            .map(|time| (key(time), self.object_to_world.at(time)))
+            .collect();
+        if transforms.iter().all(|(_, t)| *t == transforms[0].1) {
+            transforms.truncate(1);
+        }
+        let prototype = Arc::new(Prototype::new(shape, material));
+        let instance = Instance::new(prototype, AnimatedTransform::new(transforms));
+        Ok(instance.try_into()?)
+    }
+}
+
+/// Whether two samples of a mesh have the same triangles, so that they can be interpolated
+fn same_triangles<T, U>(a: &TriangleMesh<T, U>, b: &TriangleMesh<T, U>) -> bool {
+    a.indices == b.indices
+        && a.positions.len() == b.positions.len()
+        && a.normals.as_ref().map(Vec::len) == b.normals.as_ref().map(Vec::len)
+}
+
+/// Reads little-endian values from a slice, reporting reads past its end as corruption
+struct Bytes<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Bytes<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SceneError> {
+        let end = self.pos.checked_add(len).ok_or(SceneError::InvalidFormat)?;
+        let bytes = self
+            .data
+            .get(self.pos..end)
+            .ok_or(SceneError::InvalidFormat)?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], SceneError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, SceneError> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, SceneError> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Result<u64, SceneError> {
+        self.array().map(u64::from_le_bytes)
+    }
+
+    fn f64(&mut self) -> Result<f64, SceneError> {
+        self.array().map(f64::from_le_bytes)
+    }
+
+    /// Reads an unsigned integer of one, two or four bytes, as the size hint of a property
+    /// header tells
+    fn var(&mut self, hint: u32) -> Result<usize, SceneError> {
+        Ok(match hint {
+            0 => usize::from(self.u8()?),
+            1 => usize::from(u16::from_le_bytes(self.array()?)),
+            _ => self.u32()? as usize,
+        })
+    }
+
+    fn string(&mut self, len: usize) -> Result<String, SceneError> {
+        Ok(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+}
+
+/// The groups and data of an Ogawa file, which refer to their children by their offsets
+struct Ogawa<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Ogawa<'a> {
+    fn at(&self, offset: u64) -> Result<Bytes<'a>, SceneError> {
+        let offset = usize::try_from(offset)
+            .ok()
+            .filter(|&offset| offset <= self.bytes.len())
+            .ok_or(SceneError::InvalidFormat)?;
+        Ok(Bytes::new(&self.bytes[offset..]))
+    }
+
+    /// Returns the children of a group, with the flag of those which are data
+    fn group(&self, offset: u64) -> Result<Vec<u64>, SceneError> {
+        if offset & DATA != 0 {
+            return Err(SceneError::InvalidFormat);
+        }
+        // Empty groups have no place in the file
+        if offset == 0 {
+            return Ok(Vec::new());
+        }
+        let mut bytes = self.at(offset)?;
+        let count = bytes.u64()?;
+        if count > (self.bytes.len() / 8) as u64 {
+            return Err(SceneError::InvalidFormat);
+        }
+        (0..count).map(|_| bytes.u64()).collect()
+    }
+
+    fn data(&self, offset: u64) -> Result<&'a [u8], SceneError> {
+        if offset & DATA == 0 {
+            return Err(SceneError::InvalidFormat);
+        }
+        let offset = offset & !DATA;
+        if offset == 0 {
+            return Ok(&[]);
+        }
+        let mut bytes = self.at(offset)?;
+        let size = bytes.u64()?;
+        bytes.take(usize::try_from(size).map_err(|_| SceneError::InvalidFormat)?)
+    }
+}
+
+/// When the samples of properties were taken, in seconds
+struct TimeSampling {
+    time_per_cycle: f64,
+    /// The moments of the samples of the first cycle, or of all samples if acyclic
+    times: Vec<f64>,
+}
+
+impl TimeSampling {
+    fn time(&self, index: usize) -> f64 {
+        let Some(&last) = self.times.last() else {
+            return index as f64 * self.time_per_cycle;
+        };
+        let cycle = self.times.len();
+        if self.time_per_cycle >= ACYCLIC {
+            self.times.get(index).copied().unwrap_or(last)
+        } else {
+            self.times[index % cycle] + (index / cycle) as f64 * self.time_per_cycle
+        }
+    }
+}
+
+/// An object of an archive, with the group holding its properties and children
+struct Object {
+    name: String,
+    metadata: String,
+    group: u64,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Kind {
+    Compound,
+    /// One value per sample, of `extent` numbers
+    Scalar,
+    /// An array of values per sample
+    Array,
+}
+
+/// A property of an object, with the group holding its samples or properties
+struct Property {
+    name: String,
+    kind: Kind,
+    pod: u32,
+    sample_count: usize,
+    /// The samples from the first changed one to the last changed one are stored, after the
+    /// first, and those outside repeat the one next to them
+    first_changed: usize,
+    last_changed: usize,
+    time_sampling: usize,
+    metadata: String,
+    group: u64,
+}
+
+impl Property {
+    /// Returns which of the stored samples holds a sample
+    fn stored(&self, index: usize) -> usize {
+        let index = index.min(self.sample_count.saturating_sub(1));
+        if index < self.first_changed || self.last_changed == 0 {
+            0
+        } else {
+            index.min(self.last_changed) - self.first_changed + 1
+        }
+    }
+}
+
+/// The objects, properties and samples of an Alembic archive in an Ogawa file
+struct Archive<'a> {
+    ogawa: Ogawa<'a>,
+    time_samplings: Vec<TimeSampling>,
+    /// The metadata shared by objects and properties, which refer to it by index
+    metadata: Vec<String>,
+}
+
+impl<'a> Archive<'a> {
+    /// Reads the header of an archive, returning it with the group of its top object
+    fn new(bytes: &'a [u8]) -> Result<(Self, u64), SceneError> {
+        if !bytes.starts_with(b"Ogawa") {
+            return Err(SceneError::InvalidFormat);
+        }
+        let ogawa = Ogawa { bytes };
+        let root = ogawa.at(8)?.u64()?;
+        // The versions, the top object, the metadata of the archive, the time samplings and
+        // the shared metadata
+        let children = ogawa.group(root)?;
+        let &[_, _, top, _, samplings, metadata, ..] = &*children else {
+            return Err(SceneError::InvalidFormat);
+        };
+
+        let mut time_samplings = Vec::new();
+        let mut bytes = Bytes::new(ogawa.data(samplings)?);
+        while !bytes.is_empty() {
+            let _max_samples = bytes.u32()?;
+            let time_per_cycle = bytes.f64()?;
+            let count = bytes.u32()?;
+            let times = (0..count).map(|_| bytes.f64()).collect::<Result<_, _>>()?;
+            time_samplings.push(TimeSampling {
+                time_per_cycle,
+                times,
+            });
+        }
+
+        let mut shared = vec![String::new()];
+        let mut bytes = Bytes::new(ogawa.data(metadata)?);
+        while !bytes.is_empty() {
+            let len = usize::from(bytes.u8()?);
+            shared.push(bytes.string(len)?);
+        }
+
+        let archive = Self {
+            ogawa,
+            time_samplings,
+            metadata: shared,
+        };
+        Ok((archive, top))
+    }
+
+    /// Returns the metadata at an index, or else reads it from the header
+    fn metadata(
+        &self,
+        index: usize,
+        bytes: &mut Bytes<'_>,
+        len: impl FnOnce(&mut Bytes<'_>) -> Result<usize, SceneError>,
+    ) -> Result<String, SceneError> {
+        if index == 0xff {
+            let len = len(bytes)?;
+            bytes.string(len)
+        } else {
+            let metadata = self.metadata.get(index);
+            metadata.cloned().ok_or(SceneError::InvalidFormat)
+        }
+    }
+
+    /// Returns the children of the object whose group is at `group`
+    ///
+    /// The first child of the group holds the properties of the object, the others its
+    /// children, and the last their headers, followed by hashes.
+    fn objects(&self, group: u64) -> Result<Vec<Object>, SceneError> {
+        let children = self.ogawa.group(group)?;
+        let Some(&headers) = children.last().filter(|&&child| child & DATA != 0) else {
+            return Ok(Vec::new());
+        };
+        let data = self.ogawa.data(headers)?;
+        let mut bytes = Bytes::new(&data[..data.len().saturating_sub(32)]);
+        let mut objects = Vec::new();
+        while !bytes.is_empty() {
+            let len = bytes.u32()? as usize;
+            let name = bytes.string(len)?;
+            let index = usize::from(bytes.u8()?);
+            let metadata = self.metadata(index, &mut bytes, |b| Ok(b.u32()? as usize))?;
+            let group = children.get(objects.len() + 1).copied();
+            let group = group.ok_or(SceneError::InvalidFormat)?;
+            objects.push(Object {
+                name,
+                metadata,
+                group,
+            });
+        }
+        Ok(objects)
+    }
+
+    /// Returns the properties of the object whose group is at `group`
+    fn properties(&self, group: u64) -> Result<Vec<Property>, SceneError> {
+        match self.ogawa.group(group)?.first() {
+            Some(&properties) if properties & DATA == 0 => self.compound(properties),
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// Returns the properties of a compound property, whose headers are packed into the last
+    /// child of its group
+    fn compound(&self, group: u64) -> Result<Vec<Property>, SceneError> {
+        let children = self.ogawa.group(group)?;
+        let Some(&headers) = children.last().filter(|&&child| child & DATA != 0) else {
+            return Ok(Vec::new());
+        };
+        let mut bytes = Bytes::new(self.ogawa.data(headers)?);
+        let mut properties = Vec::new();
+        while !bytes.is_empty() {
+            let info = bytes.u32()?;
+            let kind = match info & 3 {
+                0 => Kind::Compound,
+                1 => Kind::Scalar,
+                _ => Kind::Array,
+            };
+            let hint = (info >> 2) & 3;
+            let mut property = Property {
+                name: String::new(),
+                kind,
+                pod: (info >> 4) & 0xf,
+                sample_count: 0,
+                first_changed: 0,
+                last_changed: 0,
+                time_sampling: 0,
+                metadata: String::new(),
+                group: *children
+                    .get(properties.len())
+                    .ok_or(SceneError::InvalidFormat)?,
+            };
+            if kind != Kind::Compound {
+                property.sample_count = bytes.var(hint)?;
+                if info & 0x200 != 0 {
+                    property.first_changed = bytes.var(hint)?;
+                    property.last_changed = bytes.var(hint)?;
+                } else if info & 0x800 == 0 {
+                    property.first_changed = 1;
+                    property.last_changed = property.sample_count.saturating_sub(1);
+                }
+                if info & 0x100 != 0 {
+                    property.time_sampling = bytes.var(hint)?;
+                }
+            }
+            let len = bytes.var(hint)?;
+            property.name = bytes.string(len)?;
+            let index = ((info >> 20) & 0xff) as usize;
+            property.metadata = self.metadata(index, &mut bytes, |b| b.var(hint))?;
+            properties.push(property);
+        }
+        Ok(properties)
+    }
+
+    /// Returns the moment of a sample of a property, in seconds
+    fn time(&self, property: &Property, index: usize) -> f64 {
+        let sampling = self.time_samplings.get(property.time_sampling);
+        sampling.map_or(index as f64, |sampling| sampling.time(index))
+    }
+
+    /// Returns the numbers of a sample of a scalar or array property
+    ///
+    /// The data of each sample starts with a hash of it, and those of arrays are followed by
+    /// their dimensions, which are left out.
+    fn numbers(&self, property: &Property, index: usize) -> Result<Vec<f64>, SceneError> {
+        let stored = property.stored(index);
+        let child = match property.kind {
+            Kind::Compound => return Err(SceneError::InvalidFormat),
+            Kind::Scalar => stored,
+            Kind::Array => 2 * stored,
+        };
+        let children = self.ogawa.group(property.group)?;
+        let child = *children.get(child).ok_or(SceneError::InvalidFormat)?;
+        let data = self.ogawa.data(child)?;
+        if data.is_empty() {
+            return Ok(Vec::new());
+        }
+        let data = data.get(16..).ok_or(SceneError::InvalidFormat)?;
+
+        let size = match property.pod {
+            0..=2 => 1,
+            3 | 4 => 2,
+            5 | 6 | 10 => 4,
+            7 | 8 | 11 => 8,
+            pod => {
+                let message = format!("properties of plain old data type {pod}");
+                return Err(SceneError::Unsupported(message));
+            }
+        };
+        let numbers = data.chunks_exact(size).map(|b| {
+            let array = |bytes: &[u8]| {
+                let mut array = [0; 8];
+                array[..bytes.len()].copy_from_slice(bytes);
+                array
+            };
+            let [b0, b1, b2, b3, ..] = array(b);
+            match property.pod {
+                0 | 1 => f64::from(b0),
+                2 => f64::from(b0 as i8),
+                3 => f64::from(u16::from_le_bytes([b0, b1])),
+                4 => f64::from(i16::from_le_bytes([b0, b1])),
+                5 => f64::from(u32::from_le_bytes([b0, b1, b2, b3])),
+                6 => f64::from(i32::from_le_bytes([b0, b1, b2, b3])),
+                7 => u64::from_le_bytes(array(b)) as f64,
+                8 => i64::from_le_bytes(array(b)) as f64,
+                10 => f64::from(f32::from_le_bytes([b0, b1, b2, b3])),
+                _ => f64::from_le_bytes(array(b)),
+            }
+        });
+        Ok(numbers.collect())
+    }
+}
+
+/// Returns the value of a key in metadata, which is written as `key=value;key=value`
+fn metadata_value<'a>(metadata: &'a str, key: &str) -> Option<&'a str> {
+    metadata
+        .split(';')
+        .filter_map(|entry| entry.split_once('='))
+        .find(|(k, _)| *k == key)
+        .map(|(_, value)| value)
+}
+
+/// Reads the local transform of an xform object at the moments it was sampled, and whether
+/// it is relative to its parent rather than the world
+fn xform<T: Float, U>(
+    archive: &Archive<'_>,
+    properties: &[Property],
+) -> Result<(AnimatedTransform<T, U, U>, bool), SceneError> {
+    let identity = || AnimatedTransform::new(vec![(T::zero(), Transform3::identity())]);
+    let Some(xform) = properties.iter().find(|p| p.name == ".xform") else {
+        return Ok((identity(), true));
+    };
+    let properties = archive.compound(xform.group)?;
+    let find = |name| properties.iter().find(|p| p.name == name);
+    let inherits = match find(".inherits") {
+        Some(inherits) => archive.numbers(inherits, 0)?.first() != Some(&0.),
+        None => true,
+    };
+    // Transforms without operations are the identity
+    let (Some(ops), Some(channels)) = (find(".ops"), find(".vals")) else {
+        return Ok((identity(), inherits));
+    };
+
+    let mut keys = Vec::new();
+    for index in 0..channels.sample_count.max(1) {
+        let ops = archive.numbers(ops, index)?;
+        let mut vals = archive.numbers(channels, index)?.into_iter();
+        let mut next = || vals.next().ok_or(SceneError::InvalidFormat);
+        let mut mat = identity_matrix();
+        for op in ops {
+            let mut op_matrix = identity_matrix();
+            match op as u8 >> 4 {
+                0 => {
+                    for (i, row) in op_matrix.iter_mut().take(3).enumerate() {
+                        row[i] = next()?;
+                    }
+                }
+                1 => {
+                    for x in &mut op_matrix[3][..3] {
+                        *x = next()?;
+                    }
+                }
+                2 => {
+                    let axis = [next()?, next()?, next()?];
+                    op_matrix = rotation(axis, next()?);
+                }
+                3 => {
+                    for x in op_matrix.iter_mut().flatten() {
+                        *x = next()?;
+                    }
+                }
+                axis @ 4..=6 => {
+                    let mut unit = [0.; 3];
+                    unit[usize::from(axis - 4)] = 1.;
+                    op_matrix = rotation(unit, next()?);
+                }
+                _ => return Err(SceneError::Unsupported("transform operation".into())),
+            }
+            // Later operations are applied first
+            mat = mul(op_matrix, mat);
+        }
+        let transform = Transform3::try_new(mat.map(|row| row.map(|x| T::from(x).unwrap())))
+            .ok_or_else(|| SceneError::Unsupported("objects with singular transforms".into()))?;
+        keys.push((T::from(archive.time(channels, index)).unwrap(), transform));
+    }
+    if !keys.windows(2).all(|pair| pair[0].0 < pair[1].0) {
+        return Err(SceneError::InvalidFormat);
+    }
+    Ok((AnimatedTransform::new(keys), inherits))
+}
+
+/// Returns the transform to the world from the local transforms of the objects above a mesh,
+/// from the outermost to the innermost, with keys at the moments of all of their samples
+fn world<T: Float, U>(chain: &[Arc<AnimatedTransform<T, U, U>>]) -> AnimatedTransform<T, U, U> {
+    let mut times: Vec<T> = chain
+        .iter()
+        .flat_map(|local| local.keys().iter().map(|(time, _)| *time))
+        .collect();
+    times.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    times.dedup();
+    if times.is_empty() || chain.iter().all(|local| !local.is_animated()) {
+        times.truncate(1);
+    }
+    let keys = times.into_iter().map(|time| {
+        let world = chain
+            .iter()
+            .rev()
+            .fold(Transform3::identity(), |world, local| {
+                world * local.at(time)
+            });
+        (time, world)
+    });
+    let keys: Vec<_> = keys.collect();
+    if keys.is_empty() {
+        AnimatedTransform::new(vec![(T::zero(), Transform3::identity())])
+    } else {
+        AnimatedTransform::new(keys)
+    }
+}
+
+fn identity_matrix() -> [[f64; 4]; 4] {
+    let mut mat = [[0.; 4]; 4];
+    for (i, row) in mat.iter_mut().enumerate() {
+        row[i] = 1.;
+    }
+    mat
+}
+
+/// Returns the product of two matrices, which applies `a` first
+fn mul(a: [[f64; 4]; 4], b: [[f64; 4]; 4]) -> [[f64; 4]; 4] {
+    let mut mat = [[0.; 4]; 4];
+    for (i, row) in mat.iter_mut().enumerate() {
+        for (j, x) in row.iter_mut().enumerate() {
+            *x = (0..4).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    mat
+}
+
+/// Returns a rotation by `degrees` counterclockwise about an axis
+fn rotation(axis: [f64; 3], degrees: f64) -> [[f64; 4]; 4] {
+    let length = axis.iter().map(|a| a * a).sum::<f64>().sqrt();
+    let mut mat = identity_matrix();
+    if length == 0. {
+        return mat;
+    }
+    let [x, y, z] = axis.map(|a| a / length);
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let k = 1. - cos;
+    // Transposed from the usual form, for row vectors
+    let rotation = [
+        [cos + x * x * k, x * y * k + z * sin, x * z * k - y * sin],
+        [x * y * k - z * sin, cos + y * y * k, y * z * k + x * sin],
+        [x * z * k + y * sin, y * z * k - x * sin, cos + z * z * k],
+    ];
+    for (row, rotation) in mat.iter_mut().zip(rotation) {
+        row[..3].copy_from_slice(&rotation);
+    }
+    mat
+}
+
+/// The values of a geometry parameter of a mesh at a sample, such as its normals or texture
+/// coordinates
+struct GeomParam {
+    values: Vec<f64>,
+    /// The indices of the values, if they are shared
+    indices: Vec<f64>,
+    /// Whether the values are constant, per face, per point or per corner of a face
+    scope: String,
+    /// The number of components of each value
+    size: usize,
+}
+
+impl GeomParam {
+    /// Reads a parameter of the mesh, which is either an array property or a compound one of
+    /// values and indices
+    fn read(
+        archive: &Archive<'_>,
+        property: Option<&Property>,
+        index: usize,
+        size: usize,
+    ) -> Result<Option<Self>, SceneError> {
+        let Some(property) = property else {
+            return Ok(None);
+        };
+        let scope = metadata_value(&property.metadata, "geoScope").unwrap_or("fvr");
+        let (values, indices) = match property.kind {
+            Kind::Array => (archive.numbers(property, index)?, Vec::new()),
+            Kind::Compound => {
+                let properties = archive.compound(property.group)?;
+                let find = |name| properties.iter().find(|p| p.name == name);
+                let Some(values) = find(".vals") else {
+                    return Ok(None);
+                };
+                let indices = match find(".indices") {
+                    Some(indices) => archive.numbers(indices, index)?,
+                    None => Vec::new(),
+                };
+                (archive.numbers(values, index)?, indices)
+            }
+            Kind::Scalar => return Ok(None),
+        };
+        Ok((values.len() >= size).then(|| Self {
+            values,
+            indices,
+            scope: scope.to_owned(),
+            size,
+        }))
+    }
+
+    /// Returns the index of the value at a corner of a face, whose point has the index `point`
+    fn index(&self, face: usize, corner: usize, point: usize) -> Option<usize> {
+        let index = match &*self.scope {
+            "con" => 0,
+            "uni" => face,
+            "fvr" => corner,
+            _ => point,
+        };
+        let index = match self.indices.get(index) {
+            Some(&i) => i as usize,
+            None if self.indices.is_empty() => index,
+            None => return None,
+        };
+        (index < self.values.len() / self.size).then_some(index)
+    }
+
+    fn value(&self, index: Option<usize>) -> &[f64] {
+        let index = index.unwrap_or(0);
+        &self.values[self.size * index..self.size * (index + 1)]
+    }
+}
+
+/// The samples of a mesh, at increasing moments
+type Samples<T, U> = Vec<(T, TriangleMesh<T, U>)>;
+
+/// Reads every sample of a polygon mesh, splitting its faces into fans of triangles
+fn mesh_samples<T: Float, U>(
+    archive: &Archive<'_>,
+    properties: &[Property],
+) -> Result<Samples<T, U>, SceneError> {
+    let Some(geom) = properties.iter().find(|p| p.name == ".geom") else {
+        return Ok(Vec::new());
+    };
+    let properties = archive.compound(geom.group)?;
+    let find = |name| properties.iter().find(|p| p.name == name);
+    let (Some(points), Some(indices), Some(counts)) =
+        (find("P"), find(".faceIndices"), find(".faceCounts"))
+    else {
+        return Ok(Vec::new());
+    };
+
+    let mut samples: Samples<T, U> = Vec::with_capacity(points.sample_count);
+    for index in 0..points.sample_count {
+        let time = T::from(archive.time(points, index)).unwrap();
+        if samples.last().is_some_and(|(last, _)| *last >= time) {
+            return Err(SceneError::InvalidFormat);
+        }
+        let normals = GeomParam::read(archive, find("N"), index, 3)?;
+        let uvs = GeomParam::read(archive, find("uv"), index, 2)?;
+        let mesh = polygons(
+            &archive.numbers(points, index)?,
+            &archive.numbers(counts, index)?,
+            &archive.numbers(indices, index)?,
+            normals.as_ref(),
+            uvs.as_ref(),
+        )?;
+        samples.push((time, mesh));
+    }
+    Ok(samples)
+}
+
+/// Returns the triangles of a sample of a mesh
+///
+/// Vertices are split where normals or texture coordinates vary between the faces they are
+/// on. Faces are wound clockwise in Alembic, and are reversed.
+fn polygons<T: Float, U>(
+    points: &[f64],
+    counts: &[f64],
+    indices: &[f64],
+    normals: Option<&GeomParam>,
+    uvs: Option<&GeomParam>,
+) -> Result<TriangleMesh<T, U>, SceneError> {
+    if !points.len().is_multiple_of(3) || counts.iter().sum::<f64>() != indices.len() as f64 {
+        return Err(SceneError::InvalidFormat);
+    }
+    let point_count = points.len() / 3;
+    let cast = |x: f64| T::from(x).unwrap();
+    let mut mesh = TriangleMesh::new(Vec::new(), Vec::new());
+    let (mut mesh_normals, mut mesh_uvs) = (Vec::new(), Vec::new());
+    let mut vertices = HashMap::new();
+    let mut corner = 0;
+    for (face, &count) in counts.iter().enumerate() {
+        let mut face_vertices = Vec::with_capacity(count as usize);
+        for _ in 0..count as usize {
+            let point = indices[corner];
+            if point < 0. || point as usize >= point_count {
+                return Err(SceneError::InvalidFormat);
+            }
+            let point = point as usize;
+            let n = normals.and_then(|n| n.index(face, corner, point));
+            let uv = uvs.and_then(|uv| uv.index(face, corner, point));
+            let next = vertices.len();
+            let vertex = *vertices.entry((point, n, uv)).or_insert_with(|| {
+                let p = &points[3 * point..3 * point + 3];
+                mesh.positions
+                    .push(Point3::new(cast(p[0]), cast(p[1]), cast(p[2])));
+                if let Some(normals) = normals {
+                    let n = normals.value(n);
+                    mesh_normals.push(Vector3::new(cast(n[0]), cast(n[1]), cast(n[2])).to_normal());
+                }
+                if let Some(uvs) = uvs {
+                    let uv = uvs.value(uv);
+                    mesh_uvs.push(Point2::new(cast(uv[0]), cast(uv[1])));
+                }
+                next as u32
+            });
+            face_vertices.push(vertex);
+            corner += 1;
+        }
+        for i in 1..face_vertices.len().saturating_sub(1) {
+            let [a, b, c] = [face_vertices[0], face_vertices[i], face_vertices[i + 1]];
+            mesh.indices.push([a, c, b]);
+        }
+    }
+    mesh.normals = normals.map(|_| mesh_normals);
+    mesh.uvs = uvs.map(|_| mesh_uvs);
+    Ok(mesh)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        geometry::{Box3, Ray, UnknownUnit},
+        units::Time,
+    };
+
+    /// Appends Ogawa groups and data to a file, returning their offsets
+    struct Writer(Vec<u8>);
+
+    impl Writer {
+        fn data(&mut self, bytes: &[u8]) -> u64 {
+            let offset = self.0.len() as u64;
+            self.0
+                .extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+            self.0.extend_from_slice(bytes);
+            offset | DATA
+        }
+
+        fn group(&mut self, children: &[u64]) -> u64 {
+            let offset = self.0.len() as u64;
+            self.0
+                .extend_from_slice(&(children.len() as u64).to_le_bytes());
+            for child in children {
+                self.0.extend_from_slice(&child.to_le_bytes());
+            }
+            offset
+        }
+
+        /// Writes a sample after its hash, which is not checked
+        fn sample(&mut self, bytes: &[u8]) -> u64 {
+            self.data(&[&[0; 16], bytes].concat())
+        }
+
+        /// Writes a compound property of the properties with the given groups and headers
+        fn compound(&mut self, properties: &[(u64, Vec<u8>)]) -> u64 {
+            let headers: Vec<u8> = properties
+                .iter()
+                .flat_map(|(_, header)| header.clone())
+                .collect();
+            let headers = self.data(&headers);
+            let mut children: Vec<_> = properties.iter().map(|(group, _)| *group).collect();
+            children.push(headers);
+            self.group(&children)
+        }
+    }
+
+    /// Returns the header of a property of the given kind, plain old data type and extent,
+    /// with its number of samples and time sampling
+    fn header(kind: u32, pod: u32, extent: u32, samples: u8, sampling: u8, name: &str) -> Vec<u8> {
+        let mut info = kind | pod << 4 | extent << 12;
+        if sampling != 0 {
+            info |= 0x100;
+        }
+        let mut header = info.to_le_bytes().to_vec();
+        if kind != 0 {
+            header.push(samples);
+            if sampling != 0 {
+                header.push(sampling);
+            }
+        }
+        header.push(name.len() as u8);
+        header.extend_from_slice(name.as_bytes());
+        header
+    }
+
+    fn bytes<const N: usize>(values: impl IntoIterator<Item = [u8; N]>) -> Vec<u8> {
+        values.into_iter().flatten().collect()
+    }
+
+    #[test]
+    fn test_read_alembic() {
+        let mut w = Writer(b"Ogawa\xff\0\x01\0\0\0\0\0\0\0\0".to_vec());
+
+        // A quad, wound clockwise, which doubles in size over a second while its parent
+        // moves two units along x
+        let quad = |s: f32| {
+            let points = [0., 0., 0., s, 0., 0., s, s, 0., 0., s, 0.];
+            bytes(points.map(f32::to_le_bytes))
+        };
+        let (p0, p1) = (w.sample(&quad(1.)), w.sample(&quad(2.)));
+        let p = w.group(&[p0, DATA, p1, DATA]);
+        let indices = w.sample(&bytes([0i32, 3, 2, 1].map(i32::to_le_bytes)));
+        let indices = w.group(&[indices, DATA]);
+        let counts = w.sample(&4i32.to_le_bytes());
+        let counts = w.group(&[counts, DATA]);
+        let geom = w.compound(&[
+            (p, header(2, 10, 3, 2, 1, "P")),
+            (indices, header(2, 6, 1, 1, 0, ".faceIndices")),
+            (counts, header(2, 6, 1, 1, 0, ".faceCounts")),
+        ]);
+        let mesh_properties = w.compound(&[(geom, header(0, 0, 0, 0, 0, ".geom"))]);
+        let mesh = w.group(&[mesh_properties]);
+
+        let ops = w.sample(&[0x10]);
+        let ops = w.group(&[ops]);
+        let translate = |x: f64| bytes([x, 0., -5.].map(f64::to_le_bytes));
+        let (v0, v1) = (w.sample(&translate(0.)), w.sample(&translate(2.)));
+        let vals = w.group(&[v0, v1]);
+        let xform = w.compound(&[
+            (ops, header(1, 1, 1, 1, 0, ".ops")),
+            (vals, header(1, 11, 3, 2, 1, ".vals")),
+        ]);
+        let xform_properties = w.compound(&[(xform, header(0, 0, 0, 0, 0, ".xform"))]);
+        let object_header = |name: &str, schema: &str| {
+            let metadata = format!("schema={schema}");
+            let mut header = (name.len() as u32).to_le_bytes().to_vec();
+            header.extend_from_slice(name.as_bytes());
+            header.push(0xff);
+            header.extend_from_slice(&(metadata.len() as u32).to_le_bytes());
+            header.extend_from_slice(metadata.as_bytes());
+            // The hashes of the objects
+            header.extend_from_slice(&[0; 32]);
+            header
+        };
+        let headers = w.data(&object_header("quad", "AbcGeom_PolyMesh_v1"));
+        let xform = w.group(&[xform_properties, mesh, headers]);
+        let headers = w.data(&object_header("xf", "AbcGeom_Xform_v3"));
+        let top = w.group(&[0, xform, headers]);
+
+        // The default time sampling and one at zero and one second
+        let mut samplings = Vec::new();
+        for (max, time_per_cycle, times) in [(1u32, 1., &[0f64][..]), (2, ACYCLIC, &[0., 1.])] {
+            samplings.extend_from_slice(&max.to_le_bytes());
+            samplings.extend_from_slice(&f64::to_le_bytes(time_per_cycle));
+            samplings.extend_from_slice(&(times.len() as u32).to_le_bytes());
+            samplings.extend(bytes(times.iter().map(|t: &f64| t.to_le_bytes())));
+        }
+        let version = w.data(&0u32.to_le_bytes());
+        let library = w.data(&10709u32.to_le_bytes());
+        let samplings = w.data(&samplings);
+        let root = w.group(&[version, library, top, DATA, samplings, DATA]);
+        w.0[8..16].copy_from_slice(&root.to_le_bytes());
+
+        let alembic = Alembic::<f64, UnknownUnit>::read(&w.0[..]).unwrap();
+        assert_eq!(alembic.meshes.len(), 1);
+        let mesh = &alembic.meshes[0];
+        assert_eq!(mesh.name, "/xf/quad");
+        assert_eq!(alembic.time_range(), Some((0., 1.)));
+        let halfway = mesh.mesh_at(0.5);
+        assert_eq!(halfway.positions[2], Point3::new(1.5, 1.5, 0.));
+        let [a, b, c] = halfway.vertices(0);
+        assert!((b - a).cross(c - a).z > 0.);
+
+        // Blurred over the second, the quad spans its motion, and is only where it was
+        // when the shutter opens
+        let primitive = mesh.primitive(0., 1., None).unwrap();
+        let bounds = primitive.shape().bounds();
+        let expected = Box3::new(Point3::new(0., 0., -5.), Point3::new(4., 2., -5.));
+        assert!((bounds.min - expected.min).length() < 1e-9);
+        assert!((bounds.max - expected.max).length() < 1e-9);
+        let ray =
+            |time: f64| Ray::with_time(Point3::new(0.5, 0.5, 0.), Vector3::new(0., 0., -1.), time);
+        let t_max = Time(f64::INFINITY);
+        assert!(primitive.shape().intersect_any(&ray(0.), t_max));
+        assert!(!primitive.shape().intersect_any(&ray(1.), t_max));
+
+        // An object which contains itself is refused
+        let mut cycle = w.0.clone();
+        let child = xform as usize + 16;
+        cycle[child..child + 8].copy_from_slice(&xform.to_le_bytes());
+        let error = Alembic::<f64, UnknownUnit>::read(&cycle[..]);
+        assert!(matches!(error, Err(SceneError::InvalidFormat)));
+
+        let error = Alembic::<f64, UnknownUnit>::read(&b"\x89HDF\r\n\x1a\n"[..]);
+        assert!(matches!(error, Err(SceneError::Unsupported(_))));
+    }
+}
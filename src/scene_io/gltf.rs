@@ -0,0 +1,735 @@
+use crate::{
+    accel::Bvh,
+    bsdf::{Bsdf, PrincipledBsdf, PrincipledParameters},
+    camera::{CameraSpace, ThinLensCamera},
+    color::Rgb,
+    core::{
+        geometry::{transform::Transform3, Point2, Point3, Vector3},
+        units::Angle,
+    },
+    image::Image,
+    material::Material,
+    scene::{Instance, Primitive, Prototype, SceneGraph},
+    scene_io::{json::Json, SceneError},
+    shape::{SurfaceInteraction, Triangle, TriangleMesh},
+    texture::{ImageTexture, Texture, WrapMode},
+};
+use num_traits::{Float, FloatConst};
+use std::{collections::HashMap, fmt, fs::File, io::Read, path::Path, sync::Arc};
+
+/// Refuses accessors with more values than this, whose counts are likely corrupt
+const MAX_VALUES: usize = 1 << 28;
+
+/// A perspective camera placed by a node of a glTF scene, looking down its -z axis
+pub struct GltfCamera<T, U> {
+    pub camera_to_world: Transform3<T, CameraSpace, U>,
+    pub fov_y: Angle<T>,
+    /// The ratio of width to height, which is left to the viewer if not given
+    pub aspect_ratio: Option<T>,
+}
+
+impl<T: fmt::Debug, U> fmt::Debug for GltfCamera<T, U>
+where
+    Transform3<T, CameraSpace, U>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GltfCamera")
+            .field("camera_to_world", &self.camera_to_world)
+            .field("fov_y", &self.fov_y)
+            .field("aspect_ratio", &self.aspect_ratio)
+            .finish()
+    }
+}
+
+impl<T: Float, U> GltfCamera<T, U> {
+    /// Returns a pinhole camera, with `aspect_ratio` if the camera does not give its own
+    #[must_use]
+    pub fn to_camera(&self, aspect_ratio: T) -> ThinLensCamera<T, U> {
+        let aspect_ratio = self.aspect_ratio.unwrap_or(aspect_ratio);
+        ThinLensCamera::new(self.camera_to_world, self.fov_y, aspect_ratio)
+    }
+}
+
+/// The default scene of a glTF 2.0 asset
+///
+/// The nodes of the scene become a scene graph, carrying an instance of each primitive of
+/// their meshes. Materials follow the metallic-roughness model and become principled
+/// materials, with their base color and metallic-roughness textures. Emission, normal maps,
+/// alpha modes, skins, morph targets, animations and extensions are ignored, and so are
+/// primitives other than triangle lists and orthographic cameras.
+pub struct Gltf<T, U> {
+    pub graph: SceneGraph<T, U, U>,
+    pub cameras: Vec<GltfCamera<T, U>>,
+}
+
+impl<T: fmt::Debug, U> fmt::Debug for Gltf<T, U>
+where
+    GltfCamera<T, U>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Gltf")
+            .field("graph", &self.graph)
+            .field("cameras", &self.cameras)
+            .finish()
+    }
+}
+
+impl<T, U> Gltf<T, U>
+where
+    T: Float + FloatConst + Send + Sync + 'static,
+    U: Send + Sync + 'static,
+{
+    /// Reads a glTF asset in JSON, looking up the files it refers to in `dir`
+    ///
+    /// PNG and JPEG textures need the features of the same names.
+    pub fn read_gltf(mut reader: impl Read, dir: impl AsRef<Path>) -> Result<Self, SceneError> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        Self::from_json(&Json::parse(&text)?, dir.as_ref(), None)
+    }
+
+    /// Reads a binary glTF asset, looking up the files it refers to in `dir`, see
+    /// [`read_gltf`](Self::read_gltf)
+    pub fn read_glb(mut reader: impl Read, dir: impl AsRef<Path>) -> Result<Self, SceneError> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let word = |at: usize| {
+            data.get(at..at + 4)
+                .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()) as usize)
+                .ok_or(SceneError::InvalidFormat)
+        };
+        if data.get(..4) != Some(b"glTF") || word(4)? != 2 {
+            return Err(SceneError::InvalidFormat);
+        }
+
+        // A JSON chunk, optionally followed by a binary one
+        let (mut json, mut bin) = (None, None);
+        let mut at = 12;
+        while at + 8 <= data.len().min(word(8)?) {
+            let (length, kind) = (word(at)?, word(at + 4)?);
+            let chunk = data
+                .get(at + 8..at + 8 + length)
+                .ok_or(SceneError::InvalidFormat)?;
+            match kind {
+                0x4e4f_534a => json = Some(chunk),
+                0x004e_4942 => bin = Some(chunk.to_vec()),
+                _ => {}
+            }
+            at += 8 + length;
+        }
+        let json = json.ok_or(SceneError::InvalidFormat)?;
+        let text = std::str::from_utf8(json).map_err(|_| SceneError::InvalidFormat)?;
+        Self::from_json(&Json::parse(text)?, dir.as_ref(), bin)
+    }
+
+    /// Reads the glTF asset at `path`, binary if its extension is `glb`
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SceneError> {
+        let path = path.as_ref();
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let file = File::open(path)?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(e) if e.eq_ignore_ascii_case("glb") => Self::read_glb(file, dir),
+            _ => Self::read_gltf(file, dir),
+        }
+    }
+
+    /// Returns the instances of the scene placed in the world, as primitives
+    pub fn primitives(&mut self) -> Result<Vec<Primitive<T, U>>, SceneError> {
+        self.graph
+            .flatten()
+            .into_iter()
+            .map(|instance| Ok(instance.try_into()?))
+            .collect()
+    }
+
+    fn from_json(json: &Json, dir: &Path, mut bin: Option<Vec<u8>>) -> Result<Self, SceneError> {
+        let version = json.get("asset").and_then(|asset| asset.get("version"));
+        if !version
+            .and_then(Json::as_str)
+            .is_some_and(|v| v.starts_with("2."))
+        {
+            return Err(SceneError::Unsupported("glTF versions other than 2".into()));
+        }
+        let buffers = elements(json, "buffers")
+            .iter()
+            .map(|buffer| match buffer.get("uri").and_then(Json::as_str) {
+                Some(uri) => read_uri(uri, dir).map(|(data, _)| data),
+                None => bin.take().ok_or(SceneError::InvalidFormat),
+            })
+            .collect::<Result<_, _>>()?;
+        let mut asset = Asset {
+            json,
+            dir,
+            buffers,
+            textures: HashMap::new(),
+        };
+
+        let materials = elements(json, "materials")
+            .iter()
+            .map(|material| asset.material(material))
+            .collect::<Result<Vec<_>, _>>()?;
+        let default_material: Arc<dyn Material<T, U>> = Arc::new(GltfMaterial {
+            params: PrincipledParameters {
+                metallic: T::one(),
+                roughness: T::one(),
+                ..PrincipledParameters::new(Rgb::splat(T::one()))
+            },
+            base_color: None,
+            metallic_roughness: None,
+        });
+        let mut meshes = Vec::new();
+        for mesh in elements(json, "meshes") {
+            let mut prototypes = Vec::new();
+            for primitive in elements(mesh, "primitives") {
+                let mode = primitive.get("mode").map_or(Some(4), Json::as_usize);
+                if mode != Some(4) {
+                    continue;
+                }
+                let mesh = Arc::new(asset.mesh(primitive)?);
+                if mesh.triangle_count() == 0 {
+                    continue;
+                }
+                let material = match primitive.get("material") {
+                    Some(index) => index
+                        .as_usize()
+                        .and_then(|index| materials.get(index))
+                        .ok_or(SceneError::InvalidFormat)?,
+                    None => &default_material,
+                };
+                let bvh = Bvh::new(Triangle::from_mesh(&mesh).collect());
+                let prototype = Prototype::new(Arc::new(bvh), Some(Arc::clone(material)));
+                prototypes.push(Arc::new(prototype));
+            }
+            meshes.push(prototypes);
+        }
+
+        // The roots of the default scene, or else of all nodes
+        let nodes = elements(json, "nodes");
+        let scene = json.get("scene").and_then(Json::as_usize).unwrap_or(0);
+        let roots: Vec<_> = match elements(json, "scenes").get(scene) {
+            Some(scene) => elements(scene, "nodes").to_vec(),
+            None => {
+                let children: Vec<_> = nodes
+                    .iter()
+                    .flat_map(|node| elements(node, "children"))
+                    .filter_map(Json::as_usize)
+                    .collect();
+                (0..nodes.len())
+                    .filter(|i| !children.contains(i))
+                    .map(|i| Json::Number(i as f64))
+                    .collect()
+            }
+        };
+
+        let mut graph = SceneGraph::new();
+        let mut camera_nodes = Vec::new();
+        let mut visited = vec![false; nodes.len()];
+        let mut stack: Vec<_> = roots.iter().rev().map(|root| (root, None)).collect();
+        while let Some((index, parent)) = stack.pop() {
+            let index = index.as_usize().filter(|&i| i < nodes.len());
+            let index = index.ok_or(SceneError::InvalidFormat)?;
+            // Nodes may only have one parent
+            if std::mem::replace(&mut visited[index], true) {
+                return Err(SceneError::InvalidFormat);
+            }
+            let node = &nodes[index];
+            let id = graph.add_node(parent, node_transform(node)?);
+            if let Some(mesh) = node.get("mesh") {
+                let prototypes = mesh.as_usize().and_then(|mesh| meshes.get(mesh));
+                for prototype in prototypes.ok_or(SceneError::InvalidFormat)? {
+                    let instance = Instance::new(Arc::clone(prototype), Transform3::identity());
+                    graph.attach(id, instance);
+                }
+            }
+            if let Some(camera) = node.get("camera") {
+                camera_nodes.push((id, camera));
+            }
+            let children = elements(node, "children").iter().rev();
+            stack.extend(children.map(|child| (child, Some(id))));
+        }
+
+        let mut cameras = Vec::new();
+        for (id, camera) in camera_nodes {
+            let camera = camera
+                .as_usize()
+                .and_then(|camera| elements(json, "cameras").get(camera))
+                .ok_or(SceneError::InvalidFormat)?;
+            let Some(perspective) = camera.get("perspective") else {
+                continue;
+            };
+            let number = |key| {
+                perspective
+                    .get(key)
+                    .and_then(Json::as_f64)
+                    .and_then(T::from)
+            };
+            cameras.push(GltfCamera {
+                camera_to_world: graph.world_transform(id).cast_unit(),
+                fov_y: Angle::from_radians(number("yfov").ok_or(SceneError::InvalidFormat)?),
+                aspect_ratio: number("aspectRatio"),
+            });
+        }
+        Ok(Self { graph, cameras })
+    }
+}
+
+/// The parts of an asset being read, with the textures read so far by their index and
+/// whether they hold colors
+struct Asset<'a, T> {
+    json: &'a Json,
+    dir: &'a Path,
+    buffers: Vec<Vec<u8>>,
+    textures: HashMap<(usize, bool), ImageTexture<T>>,
+}
+
+impl<T: Float + FloatConst + Send + Sync + 'static> Asset<'_, T> {
+    /// Returns the values of an accessor, with all components of an element in a row
+    fn accessor(&self, index: &Json) -> Result<Vec<f64>, SceneError> {
+        let accessor = index
+            .as_usize()
+            .and_then(|index| elements(self.json, "accessors").get(index))
+            .ok_or(SceneError::InvalidFormat)?;
+        if accessor.get("sparse").is_some() {
+            return Err(SceneError::Unsupported("sparse accessors".into()));
+        }
+        let count = accessor.get("count").and_then(Json::as_usize);
+        let components = match accessor.get("type").and_then(Json::as_str) {
+            Some("SCALAR") => 1,
+            Some("VEC2") => 2,
+            Some("VEC3") => 3,
+            Some("VEC4") => 4,
+            _ => return Err(SceneError::Unsupported("matrix accessors".into())),
+        };
+        let component_type = accessor.get("componentType").and_then(Json::as_usize);
+        let (size, max) = match component_type {
+            Some(5120) => (1, f64::from(i8::MAX)),
+            Some(5121) => (1, f64::from(u8::MAX)),
+            Some(5122) => (2, f64::from(i16::MAX)),
+            Some(5123) => (2, f64::from(u16::MAX)),
+            Some(5125) => (4, f64::from(u32::MAX)),
+            Some(5126) => (4, 1.),
+            _ => return Err(SceneError::InvalidFormat),
+        };
+        let count = count.ok_or(SceneError::InvalidFormat)?;
+        let len = count
+            .checked_mul(components)
+            .filter(|&len| len <= MAX_VALUES)
+            .ok_or(SceneError::InvalidFormat)?;
+        let normalized = accessor.get("normalized").and_then(Json::as_bool) == Some(true);
+
+        // Accessors without a buffer view are all zeros
+        let Some(view) = accessor.get("bufferView") else {
+            return Ok(vec![0.; len]);
+        };
+        let view = view
+            .as_usize()
+            .and_then(|view| elements(self.json, "bufferViews").get(view))
+            .ok_or(SceneError::InvalidFormat)?;
+        let data = self.buffer_view(view)?;
+        let stride = view.get("byteStride").and_then(Json::as_usize);
+        let stride = stride.unwrap_or(size * components);
+        let offset = accessor.get("byteOffset").and_then(Json::as_usize);
+        let offset = offset.unwrap_or(0);
+        // The last element must end within the view
+        if let Some(last) = count.checked_sub(1) {
+            let end = last
+                .checked_mul(stride)
+                .and_then(|start| start.checked_add(offset))
+                .and_then(|start| start.checked_add(components * size));
+            if end.is_none_or(|end| end > data.len()) {
+                return Err(SceneError::InvalidFormat);
+            }
+        }
+
+        let mut values = Vec::with_capacity(len);
+        for i in 0..count {
+            for c in 0..components {
+                let at = offset + i * stride + c * size;
+                let bytes = &data[at..at + size];
+                let value = match component_type {
+                    Some(5120) => f64::from(bytes[0] as i8),
+                    Some(5121) => f64::from(bytes[0]),
+                    Some(5122) => f64::from(i16::from_le_bytes([bytes[0], bytes[1]])),
+                    Some(5123) => f64::from(u16::from_le_bytes([bytes[0], bytes[1]])),
+                    Some(5125) => f64::from(u32::from_le_bytes(bytes.try_into().unwrap())),
+                    _ => f64::from(f32::from_le_bytes(bytes.try_into().unwrap())),
+                };
+                values.push(if normalized {
+                    (value / max).max(-1.)
+                } else {
+                    value
+                });
+            }
+        }
+        Ok(values)
+    }
+
+    fn buffer_view(&self, view: &Json) -> Result<&[u8], SceneError> {
+        let buffer = view.get("buffer").and_then(Json::as_usize);
+        let buffer = buffer.and_then(|buffer| self.buffers.get(buffer));
+        let offset = view.get("byteOffset").and_then(Json::as_usize).unwrap_or(0);
+        let length = view.get("byteLength").and_then(Json::as_usize);
+        buffer
+            .zip(length)
+            .and_then(|(buffer, length)| buffer.get(offset..offset.checked_add(length)?))
+            .ok_or(SceneError::InvalidFormat)
+    }
+
+    /// Returns the triangles of a primitive, with `v` going up rather than down the image
+    fn mesh<U>(&self, primitive: &Json) -> Result<TriangleMesh<T, U>, SceneError> {
+        let attributes = primitive.get("attributes");
+        let attribute = |name| attributes.and_then(|attributes| attributes.get(name));
+        let number = |x: f64| T::from(x).unwrap();
+
+        let position = attribute("POSITION").ok_or(SceneError::InvalidFormat)?;
+        let positions: Vec<_> = self
+            .accessor(position)?
+            .chunks_exact(3)
+            .map(|p| Point3::new(number(p[0]), number(p[1]), number(p[2])))
+            .collect();
+        let indices: Vec<_> = match primitive.get("indices") {
+            Some(indices) => self.accessor(indices)?,
+            None => (0..positions.len()).map(|i| i as f64).collect(),
+        };
+        if indices.iter().any(|&i| i as usize >= positions.len()) {
+            return Err(SceneError::InvalidFormat);
+        }
+        let indices = indices
+            .chunks_exact(3)
+            .map(|triangle| [0, 1, 2].map(|i| triangle[i] as u32))
+            .collect();
+
+        let mut mesh = TriangleMesh::new(positions, indices);
+        if let Some(normal) = attribute("NORMAL") {
+            let normals = self.accessor(normal)?;
+            let normals = normals
+                .chunks_exact(3)
+                .map(|n| Vector3::new(number(n[0]), number(n[1]), number(n[2])).to_normal());
+            mesh.normals = Some(normals.collect());
+        }
+        if let Some(uv) = attribute("TEXCOORD_0") {
+            let uvs = self.accessor(uv)?;
+            let uvs = uvs
+                .chunks_exact(2)
+                .map(|uv| Point2::new(number(uv[0]), T::one() - number(uv[1])));
+            mesh.uvs = Some(uvs.collect());
+        }
+        let vertex_count = mesh.positions.len();
+        let lengths = [
+            mesh.normals.as_ref().map(Vec::len),
+            mesh.uvs.as_ref().map(Vec::len),
+        ];
+        if lengths.into_iter().flatten().any(|len| len != vertex_count) {
+            return Err(SceneError::InvalidFormat);
+        }
+        Ok(mesh)
+    }
+
+    fn material<U>(&mut self, material: &Json) -> Result<Arc<dyn Material<T, U>>, SceneError> {
+        let pbr = material.get("pbrMetallicRoughness");
+        let get = |key| pbr.and_then(|pbr| pbr.get(key));
+        let factor = |key| get(key).and_then(Json::as_f64).and_then(T::from);
+        let base_color = match get("baseColorFactor").map(Json::elements) {
+            Some([r, g, b, _]) => [r, g, b].map(|c| c.as_f64().and_then(T::from)),
+            None => [Some(T::one()); 3],
+            Some(_) => return Err(SceneError::InvalidFormat),
+        };
+        let [Some(r), Some(g), Some(b)] = base_color else {
+            return Err(SceneError::InvalidFormat);
+        };
+        let params = PrincipledParameters {
+            metallic: factor("metallicFactor").unwrap_or_else(T::one),
+            roughness: factor("roughnessFactor").unwrap_or_else(T::one),
+            ..PrincipledParameters::new(Rgb::new(r, g, b))
+        };
+
+        let mut texture = |key, color| match get(key).and_then(|info| info.get("index")) {
+            Some(index) => self.texture(index, color).map(Some),
+            None => Ok(None),
+        };
+        Ok(Arc::new(GltfMaterial {
+            params,
+            base_color: texture("baseColorTexture", true)?,
+            metallic_roughness: texture("metallicRoughnessTexture", false)?,
+        }))
+    }
+
+    /// Returns a texture, whose values are encoded with the sRGB transfer function if it holds
+    /// colors, and linearly otherwise
+    fn texture(&mut self, index: &Json, color: bool) -> Result<ImageTexture<T>, SceneError> {
+        let index = index.as_usize().ok_or(SceneError::InvalidFormat)?;
+        if let Some(texture) = self.textures.get(&(index, color)) {
+            return Ok(texture.clone());
+        }
+        let texture = elements(self.json, "textures")
+            .get(index)
+            .ok_or(SceneError::InvalidFormat)?;
+        let image = texture
+            .get("source")
+            .and_then(Json::as_usize)
+            .and_then(|source| elements(self.json, "images").get(source))
+            .ok_or(SceneError::InvalidFormat)?;
+
+        let mime = image.get("mimeType").and_then(Json::as_str);
+        let mut image: Image<T> = match (image.get("uri").and_then(Json::as_str), mime) {
+            (Some(uri), _) if !uri.starts_with("data:") => Image::load(self.dir.join(uri))?,
+            (Some(uri), _) => {
+                let (data, mime) = read_uri(uri, self.dir)?;
+                decode_image(&data, &mime)?
+            }
+            (None, Some(mime)) => {
+                let view = image
+                    .get("bufferView")
+                    .and_then(Json::as_usize)
+                    .and_then(|view| elements(self.json, "bufferViews").get(view))
+                    .ok_or(SceneError::InvalidFormat)?;
+                decode_image(self.buffer_view(view)?, mime)?
+            }
+            (None, None) => return Err(SceneError::InvalidFormat),
+        };
+        // Images are decoded as sRGB colors, which data is not
+        if !color {
+            for p in image.pixels_mut() {
+                *p = p.to_srgb();
+            }
+        }
+
+        let sampler = texture
+            .get("sampler")
+            .and_then(Json::as_usize)
+            .and_then(|sampler| elements(self.json, "samplers").get(sampler));
+        let wrap = match sampler.and_then(|sampler| sampler.get("wrapS")?.as_usize()) {
+            Some(33071) => WrapMode::Clamp,
+            Some(33648) => WrapMode::Mirror,
+            _ => WrapMode::Repeat,
+        };
+        let texture = ImageTexture::new(image, wrap);
+        self.textures.insert((index, color), texture.clone());
+        Ok(texture)
+    }
+}
+
+/// The metallic-roughness material of glTF, whose factors are scaled by its textures
+struct GltfMaterial<T> {
+    params: PrincipledParameters<T>,
+    base_color: Option<ImageTexture<T>>,
+    /// The roughness in the green channel and the metallic factor in the blue one
+    metallic_roughness: Option<ImageTexture<T>>,
+}
+
+impl<T: Float + FloatConst + Send + Sync + 'static, U> Material<T, U> for GltfMaterial<T> {
+    fn bsdf(&self, si: &SurfaceInteraction<T, U>) -> Box<dyn Bsdf<T>> {
+        let mut params = self.params;
+        if let Some(texture) = &self.base_color {
+            params.base_color *= texture.evaluate(si);
+        }
+        if let Some(texture) = &self.metallic_roughness {
+            let value = texture.evaluate(si);
+            params.roughness = params.roughness * value.g;
+            params.metallic = params.metallic * value.b;
+        }
+        Box::new(PrincipledBsdf::new(&params))
+    }
+}
+
+/// Returns the elements of the array `key` of an object, or none if it is missing
+fn elements<'a>(json: &'a Json, key: &str) -> &'a [Json] {
+    json.get(key).map_or(&[], Json::elements)
+}
+
+/// Returns the transform of a node from its matrix, or else its translation, rotation and
+/// scale, which are applied in reverse
+fn node_transform<T: Float, U>(node: &Json) -> Result<Transform3<T, U, U>, SceneError> {
+    let numbers = |key, default: &[f64]| match node.get(key) {
+        Some(value) => {
+            let numbers = value.elements().iter().map(Json::as_f64);
+            let numbers = numbers.collect::<Option<Vec<_>>>();
+            numbers
+                .filter(|numbers| numbers.len() == default.len())
+                .ok_or(SceneError::InvalidFormat)
+        }
+        None => Ok(default.to_vec()),
+    };
+
+    let mut mat = [[0.; 4]; 4];
+    if node.get("matrix").is_some() {
+        let m = numbers("matrix", &[0.; 16])?;
+        // Columns of the matrix for column vectors are rows of the one for row vectors
+        for (i, row) in mat.iter_mut().enumerate() {
+            row.copy_from_slice(&m[4 * i..4 * i + 4]);
+        }
+    } else {
+        let t = numbers("translation", &[0.; 3])?;
+        let [x, y, z, w] = numbers("rotation", &[0., 0., 0., 1.])?[..] else {
+            unreachable!()
+        };
+        let s = numbers("scale", &[1.; 3])?;
+        let rotation = [
+            [
+                1. - 2. * (y * y + z * z),
+                2. * (x * y - z * w),
+                2. * (x * z + y * w),
+            ],
+            [
+                2. * (x * y + z * w),
+                1. - 2. * (x * x + z * z),
+                2. * (y * z - x * w),
+            ],
+            [
+                2. * (x * z - y * w),
+                2. * (y * z + x * w),
+                1. - 2. * (x * x + y * y),
+            ],
+        ];
+        for i in 0..3 {
+            for j in 0..3 {
+                mat[i][j] = s[i] * rotation[j][i];
+            }
+            mat[3][i] = t[i];
+        }
+        mat[3][3] = 1.;
+    }
+    Transform3::try_new(mat.map(|row| row.map(|x| T::from(x).unwrap())))
+        .ok_or_else(|| SceneError::Unsupported("nodes with singular transforms".into()))
+}
+
+/// Returns the contents of a URI with their media type, from a base64 data URI or a file
+/// relative to `dir`
+fn read_uri(uri: &str, dir: &Path) -> Result<(Vec<u8>, String), SceneError> {
+    let Some(data) = uri.strip_prefix("data:") else {
+        let mut data = Vec::new();
+        File::open(dir.join(uri))?.read_to_end(&mut data)?;
+        return Ok((data, String::new()));
+    };
+    let (header, data) = data.split_once(',').ok_or(SceneError::InvalidFormat)?;
+    let Some(mime) = header.strip_suffix(";base64") else {
+        return Err(SceneError::Unsupported("data URIs not in base64".into()));
+    };
+    Ok((decode_base64(data)?, mime.to_owned()))
+}
+
+fn decode_base64(data: &str) -> Result<Vec<u8>, SceneError> {
+    let mut decoded = Vec::with_capacity(data.len() / 4 * 3);
+    let (mut bits, mut count) = (0u32, 0);
+    for c in data.bytes().take_while(|&c| c != b'=') {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return Err(SceneError::InvalidFormat),
+        };
+        bits = bits << 6 | u32::from(value);
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            decoded.push((bits >> count) as u8);
+        }
+    }
+    Ok(decoded)
+}
+
+fn decode_image<T: Float>(data: &[u8], mime: &str) -> Result<Image<T>, SceneError> {
+    match mime {
+        #[cfg(feature = "png")]
+        "image/png" => Ok(Image::read_png(data)?),
+        #[cfg(feature = "jpeg")]
+        "image/jpeg" => Ok(Image::read_jpeg(data)?),
+        _ => {
+            let _ = data;
+            Err(SceneError::Unsupported(format!("`{mime}` textures")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        camera::{Camera, CameraSample},
+        core::geometry::{Box3, UnknownUnit},
+    };
+
+    #[test]
+    fn test_read_gltf() {
+        // A triangle and its indices, padded to four bytes
+        let mut buffer = Vec::new();
+        for x in [0f32, 0., 0., 1., 0., 0., 0., 1., 0.] {
+            buffer.extend_from_slice(&x.to_le_bytes());
+        }
+        for i in [0u16, 1, 2, 0] {
+            buffer.extend_from_slice(&i.to_le_bytes());
+        }
+        let alphabet = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let base64: String = buffer
+            .chunks(3)
+            .flat_map(|chunk| {
+                let bits = chunk.iter().fold(0u32, |bits, &b| bits << 8 | u32::from(b))
+                    << (8 * (3 - chunk.len()));
+                (0..=chunk.len())
+                    .map(move |i| char::from(alphabet[(bits >> (18 - 6 * i)) as usize & 63]))
+            })
+            .collect();
+
+        // The triangle is scaled by the child and moved by the parent, whose sibling is a
+        // camera looking down -x
+        let json = format!(
+            r#"{{
+                "asset": {{ "version": "2.0" }},
+                "scene": 0,
+                "scenes": [{{ "nodes": [0, 2] }}],
+                "nodes": [
+                    {{ "translation": [0, 0, -5], "children": [1] }},
+                    {{ "scale": [2, 2, 2], "mesh": 0 }},
+                    {{ "rotation": [0, 0.7071067811865476, 0, 0.7071067811865476], "camera": 0 }}
+                ],
+                "cameras": [{{ "type": "perspective", "perspective": {{ "yfov": 1.0, "znear": 0.1 }} }}],
+                "meshes": [{{ "primitives": [{{ "attributes": {{ "POSITION": 0 }}, "indices": 1, "material": 0 }}] }}],
+                "materials": [{{ "pbrMetallicRoughness": {{ "baseColorFactor": [1, 0, 0, 1], "metallicFactor": 0 }} }}],
+                "accessors": [
+                    {{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" }},
+                    {{ "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }}
+                ],
+                "bufferViews": [
+                    {{ "buffer": 0, "byteLength": 36 }},
+                    {{ "buffer": 0, "byteOffset": 36, "byteLength": 6 }}
+                ],
+                "buffers": [{{ "byteLength": 44, "uri": "data:application/octet-stream;base64,{base64}" }}]
+            }}"#
+        );
+        let mut gltf = Gltf::<f64, UnknownUnit>::read_gltf(json.as_bytes(), "").unwrap();
+        let primitives = gltf.primitives().unwrap();
+        assert_eq!(primitives.len(), 1);
+        let bounds = primitives[0].shape().bounds();
+        let expected = Box3::new(Point3::new(0., 0., -5.), Point3::new(2., 2., -5.));
+        assert!((bounds.min - expected.min).length() < 1e-9);
+        assert!((bounds.max - expected.max).length() < 1e-9);
+
+        assert_eq!(gltf.cameras.len(), 1);
+        assert_eq!(gltf.cameras[0].aspect_ratio, None);
+        let camera = gltf.cameras[0].to_camera(1.5);
+        let sample = CameraSample {
+            film: Point2::new(0.5, 0.5),
+            lens: Point2::new(0.5, 0.5),
+            time: 0.,
+        };
+        let ray = camera.generate_ray(&sample);
+        assert!((ray.dir - Vector3::new(-1., 0., 0.)).length() < 1e-9);
+
+        let error = Gltf::<f64, UnknownUnit>::read_gltf(&b"{\"asset\": {}"[..], "");
+        assert!(matches!(error, Err(SceneError::Parse { line: 1, .. })));
+
+        // Accessors reaching past their buffer view, or too large to allocate, are refused
+        let positions = r#""bufferView": 0, "componentType": 5126, "count": 3"#;
+        for accessor in [
+            r#""bufferView": 0, "componentType": 5126, "count": 4"#,
+            r#""bufferView": 0, "componentType": 5126, "count": 1e19"#,
+            r#""componentType": 5126, "count": 1e12"#,
+        ] {
+            let json = json.replace(positions, accessor);
+            let gltf = Gltf::<f64, UnknownUnit>::read_gltf(json.as_bytes(), "");
+            assert!(matches!(gltf, Err(SceneError::InvalidFormat)));
+        }
+    }
+}
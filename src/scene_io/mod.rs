@@ -0,0 +1,119 @@
+//! Reading meshes and scenes from common file formats
+//!
+//! All formats are implemented without external dependencies.
+
+mod abc;
+mod gltf;
+mod json;
+mod obj;
+mod pbrt;
+mod ply;
+mod usd;
+
+pub use abc::{Alembic, AlembicMesh};
+pub use gltf::{Gltf, GltfCamera};
+pub use obj::{read_mtl, Obj, ObjMaterial, ObjMesh};
+pub use pbrt::Pbrt;
+pub use usd::Usd;
+
+use crate::{accel::InstanceTransformError, image_io::ImageError};
+use std::{fmt, io};
+
+/// Why a mesh or scene could not be read
+#[derive(Debug)]
+pub enum SceneError {
+    Io(io::Error),
+    /// A line of a text file could not be parsed, counting from one
+    Parse {
+        line: usize,
+        message: String,
+    },
+    /// The binary data of the file is corrupt, or its elements are inconsistent
+    InvalidFormat,
+    /// An image referenced by the file, such as a texture, could not be read
+    Image(ImageError),
+    /// The file uses a feature of the format that is not supported
+    Unsupported(String),
+}
+
+impl SceneError {
+    #[inline]
+    #[must_use]
+    pub(crate) fn parse(line: usize, message: impl Into<String>) -> Self {
+        Self::Parse {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "failed to read scene: {e}"),
+            Self::Parse { line, message } => write!(f, "line {line}: {message}"),
+            Self::InvalidFormat => f.write_str("invalid scene data"),
+            Self::Image(e) => write!(f, "failed to read texture: {e}"),
+            Self::Unsupported(feature) => write!(f, "unsupported scene feature: {feature}"),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Image(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SceneError {
+    #[inline]
+    fn from(e: io::Error) -> Self {
+        if e.kind() == io::ErrorKind::UnexpectedEof {
+            Self::InvalidFormat
+        } else {
+            Self::Io(e)
+        }
+    }
+}
+
+impl From<ImageError> for SceneError {
+    #[inline]
+    fn from(e: ImageError) -> Self {
+        Self::Image(e)
+    }
+}
+
+impl From<InstanceTransformError> for SceneError {
+    #[inline]
+    fn from(_: InstanceTransformError) -> Self {
+        Self::Unsupported("instances with projective transforms".into())
+    }
+}
+
+/// Joins the lines of a text file which end in a backslash with the next, and yields them with
+/// their numbers, counting from one
+fn logical_lines(
+    reader: impl io::BufRead,
+) -> impl Iterator<Item = Result<(usize, String), SceneError>> {
+    let mut lines = reader.lines().enumerate();
+    std::iter::from_fn(move || {
+        let (index, line) = lines.next()?;
+        let mut line = match line {
+            Ok(line) => line,
+            Err(e) => return Some(Err(e.into())),
+        };
+        while line.ends_with('\\') {
+            line.pop();
+            match lines.next() {
+                Some((_, Ok(next))) => line.push_str(&next),
+                Some((_, Err(e))) => return Some(Err(e.into())),
+                None => break,
+            }
+        }
+        Some(Ok((index + 1, line)))
+    })
+}
@@ -0,0 +1,553 @@
+use crate::{
+    bsdf::PrincipledParameters,
+    color::Rgb,
+    core::{
+        geometry::{Point2, Point3, UnknownUnit, Vector3},
+        prelude::Normal3,
+    },
+    image::Image,
+    light::DiffuseAreaLight,
+    material::{
+        DielectricMaterial, DiffuseMaterial, MaskedMaterial, Material, MirrorMaterial,
+        PrincipledMaterial,
+    },
+    scene::Primitive,
+    scene_io::{logical_lines, SceneError},
+    shape::{SampleShape, Triangle, TriangleMesh},
+    texture::{ConstantTexture, ImageTexture, WrapMode},
+};
+use num_traits::{Float, FloatConst};
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    str::SplitWhitespace,
+    sync::Arc,
+};
+
+/// A material of an MTL file, with the parameters that are translated to rt3 materials
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjMaterial<T> {
+    pub name: String,
+    /// `Kd`
+    pub diffuse: Rgb<T>,
+    /// `Ks`
+    pub specular: Rgb<T>,
+    /// `Ke`, the radiance emitted by surfaces with the material
+    pub emission: Rgb<T>,
+    /// `Ns`, the Phong exponent of the specular highlight
+    pub shininess: T,
+    /// `Ni`, the index of refraction
+    pub eta: T,
+    /// `d`, the opacity, or one minus `Tr`
+    pub dissolve: T,
+    /// `illum`, the illumination model
+    pub illum: u32,
+    /// `map_Kd`, joined to the directory the MTL file was read from
+    pub diffuse_map: Option<PathBuf>,
+}
+
+impl<T: Float> ObjMaterial<T> {
+    /// Returns the material an MTL file gives when no parameters are set, a light grey
+    /// diffuse surface
+    #[must_use]
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            diffuse: Rgb::splat(T::from(0.8).unwrap()),
+            specular: Rgb::splat(T::zero()),
+            emission: Rgb::splat(T::zero()),
+            shininess: T::zero(),
+            eta: T::one(),
+            dissolve: T::one(),
+            illum: 2,
+            diffuse_map: None,
+        }
+    }
+}
+
+impl<T: Float + FloatConst + Send + Sync + 'static> ObjMaterial<T> {
+    /// Translates the material to the closest rt3 material, reading its diffuse map
+    ///
+    /// The illumination models with refraction become glass, those with ray traced reflection
+    /// a mirror, and the others a diffuse surface, glossy if it has a specular color. The
+    /// Phong exponent sets the roughness of the microfacets, and the diffuse map replaces the
+    /// diffuse color. Partly dissolved materials get cutouts with the dissolve as opacity.
+    pub fn to_material<U>(&self) -> Result<Arc<dyn Material<T, U>>, SceneError> {
+        let two = T::one() + T::one();
+        // Matches the width of Phong and Beckmann lobes
+        let roughness = two / (self.shininess.max(T::zero()) + two);
+        let material = match self.illum {
+            4 | 6 | 7 => {
+                let eta = if self.eta > T::one() {
+                    self.eta
+                } else {
+                    T::from(1.5).unwrap()
+                };
+                self.with_dissolve(DielectricMaterial::new(eta, roughness))
+            }
+            3 | 5 => self.with_dissolve(MirrorMaterial::new(self.specular)),
+            _ => match &self.diffuse_map {
+                Some(path) => {
+                    let texture = ImageTexture::new(Image::load(path)?, WrapMode::Repeat);
+                    self.with_dissolve(DiffuseMaterial::new(texture))
+                }
+                None if self.specular.max_component() > T::zero() => {
+                    let params = PrincipledParameters {
+                        roughness,
+                        specular: self.specular.max_component().min(T::one()) / two,
+                        ..PrincipledParameters::new(self.diffuse)
+                    };
+                    self.with_dissolve(PrincipledMaterial::new(params))
+                }
+                None => self.with_dissolve(DiffuseMaterial::new(self.diffuse)),
+            },
+        };
+        Ok(material)
+    }
+
+    fn with_dissolve<U, M: Material<T, U> + 'static>(
+        &self,
+        material: M,
+    ) -> Arc<dyn Material<T, U>> {
+        if self.dissolve < T::one() {
+            Arc::new(MaskedMaterial::new(
+                material,
+                ConstantTexture(self.dissolve.max(T::zero())),
+            ))
+        } else {
+            Arc::new(material)
+        }
+    }
+}
+
+/// Reads the materials of a Wavefront MTL file, joining the paths of their maps to `dir`
+///
+/// Maps other than the diffuse one, and the options of maps, are ignored.
+pub fn read_mtl<T: Float>(
+    reader: impl BufRead,
+    dir: impl AsRef<Path>,
+) -> Result<Vec<ObjMaterial<T>>, SceneError> {
+    let mut materials: Vec<ObjMaterial<T>> = Vec::new();
+    for line in logical_lines(reader) {
+        let (number, line) = line?;
+        let line = line.split('#').next().unwrap_or_default();
+        let mut tokens = line.split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+        if keyword == "newmtl" {
+            materials.push(ObjMaterial::new(tokens.collect::<Vec<_>>().join(" ")));
+            continue;
+        }
+        let Some(material) = materials.last_mut() else {
+            return Err(SceneError::parse(
+                number,
+                "material parameter before `newmtl`",
+            ));
+        };
+        match keyword {
+            "Kd" => material.diffuse = parse_color(&mut tokens, number)?,
+            "Ks" => material.specular = parse_color(&mut tokens, number)?,
+            "Ke" => material.emission = parse_color(&mut tokens, number)?,
+            "Ns" => material.shininess = parse_float(tokens.next(), number)?,
+            "Ni" => material.eta = parse_float(tokens.next(), number)?,
+            "d" => material.dissolve = parse_float(tokens.next(), number)?,
+            "Tr" => material.dissolve = T::one() - parse_float(tokens.next(), number)?,
+            "illum" => {
+                material.illum = tokens
+                    .next()
+                    .and_then(|token| token.parse().ok())
+                    .ok_or_else(|| SceneError::parse(number, "invalid illumination model"))?;
+            }
+            // The file name follows any options
+            "map_Kd" => match tokens.last() {
+                Some(file) => material.diffuse_map = Some(dir.as_ref().join(file)),
+                None => return Err(SceneError::parse(number, "missing file name")),
+            },
+            _ => {}
+        }
+    }
+    Ok(materials)
+}
+
+/// The faces of a group or object of an OBJ file with the same material
+pub struct ObjMesh<T, U> {
+    /// The name given by `g` or `o`, empty before the first
+    pub name: String,
+    pub mesh: TriangleMesh<T, U>,
+    /// The name given by `usemtl`
+    pub material: Option<String>,
+}
+
+impl<T: fmt::Debug, U> fmt::Debug for ObjMesh<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ObjMesh")
+            .field("name", &self.name)
+            .field("mesh", &self.mesh)
+            .field("material", &self.material)
+            .finish()
+    }
+}
+
+/// The meshes and materials of a Wavefront OBJ file
+pub struct Obj<T, U> {
+    pub meshes: Vec<ObjMesh<T, U>>,
+    pub materials: Vec<ObjMaterial<T>>,
+    /// The MTL files named by `mtllib`, which [`load`](Self::load) reads the materials from
+    pub material_libraries: Vec<String>,
+}
+
+impl<T: fmt::Debug, U> fmt::Debug for Obj<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Obj")
+            .field("meshes", &self.meshes)
+            .field("materials", &self.materials)
+            .field("material_libraries", &self.material_libraries)
+            .finish()
+    }
+}
+
+/// A mesh being read, with its vertices for each combination of OBJ indices
+struct MeshBuilder<T, U> {
+    mesh: ObjMesh<T, U>,
+    normals: Vec<Normal3<T, U>>,
+    uvs: Vec<Point2<T, UnknownUnit>>,
+    /// Whether every vertex so far has a normal, and a texture coordinate
+    complete: (bool, bool),
+    vertices: HashMap<(usize, Option<usize>, Option<usize>), u32>,
+}
+
+impl<T: Float, U> Obj<T, U> {
+    /// Reads the meshes of a Wavefront OBJ file, without its materials
+    ///
+    /// Each combination of a group or object with a material becomes a mesh of the vertices
+    /// its faces use, and polygons are split into fans of triangles. Normals and texture
+    /// coordinates are kept for meshes which give them at every corner. Lines, points, curves
+    /// and smoothing groups are ignored.
+    pub fn read(reader: impl BufRead) -> Result<Self, SceneError> {
+        let mut positions = Vec::new();
+        let mut normals = Vec::new();
+        let mut uvs = Vec::new();
+        let mut builders: Vec<MeshBuilder<T, U>> = Vec::new();
+        let mut lookup = HashMap::new();
+        let mut group = String::new();
+        let mut material = None;
+        let mut current = None;
+        let mut material_libraries = Vec::new();
+
+        for line in logical_lines(reader) {
+            let (number, line) = line?;
+            let line = line.split('#').next().unwrap_or_default();
+            let mut tokens = line.split_whitespace();
+            let Some(keyword) = tokens.next() else {
+                continue;
+            };
+            match keyword {
+                "v" => {
+                    let [x, y, z] = parse_floats(&mut tokens, number)?;
+                    positions.push(Point3::new(x, y, z));
+                }
+                "vn" => {
+                    let [x, y, z] = parse_floats(&mut tokens, number)?;
+                    normals.push(Vector3::new(x, y, z).to_normal());
+                }
+                "vt" => {
+                    let u = parse_float(tokens.next(), number)?;
+                    let v = tokens
+                        .next()
+                        .map_or(Ok(T::zero()), |v| parse_float(Some(v), number))?;
+                    uvs.push(Point2::new(u, v));
+                }
+                "f" => {
+                    let index = *current.get_or_insert_with(|| {
+                        *lookup
+                            .entry((group.clone(), material.clone()))
+                            .or_insert_with(|| {
+                                builders.push(MeshBuilder {
+                                    mesh: ObjMesh {
+                                        name: group.clone(),
+                                        mesh: TriangleMesh::new(Vec::new(), Vec::new()),
+                                        material: material.clone(),
+                                    },
+                                    normals: Vec::new(),
+                                    uvs: Vec::new(),
+                                    complete: (true, true),
+                                    vertices: HashMap::new(),
+                                });
+                                builders.len() - 1
+                            })
+                    });
+                    let builder = &mut builders[index];
+                    let corners = tokens
+                        .map(|corner| {
+                            let key = parse_corner(corner, &positions, &normals, &uvs, number)?;
+                            builder.vertex(key, &positions, &normals, &uvs, number)
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if corners.len() < 3 {
+                        return Err(SceneError::parse(number, "face with fewer than 3 vertices"));
+                    }
+                    for i in 1..corners.len() - 1 {
+                        let triangle = [corners[0], corners[i], corners[i + 1]];
+                        builder.mesh.mesh.indices.push(triangle);
+                    }
+                }
+                "g" | "o" => {
+                    group = tokens.collect::<Vec<_>>().join(" ");
+                    current = None;
+                }
+                "usemtl" => {
+                    material = Some(tokens.collect::<Vec<_>>().join(" "));
+                    current = None;
+                }
+                "mtllib" => material_libraries.extend(tokens.map(String::from)),
+                _ => {}
+            }
+        }
+
+        let meshes = builders
+            .into_iter()
+            .map(|builder| {
+                let (has_normals, has_uvs) = builder.complete;
+                let mut mesh = builder.mesh;
+                mesh.mesh.normals = has_normals.then_some(builder.normals);
+                mesh.mesh.uvs = has_uvs.then_some(builder.uvs);
+                mesh
+            })
+            .collect();
+        Ok(Self {
+            meshes,
+            materials: Vec::new(),
+            material_libraries,
+        })
+    }
+
+    /// Reads an OBJ file with the materials of the MTL files it names, which are looked up
+    /// next to it, see [`read`](Self::read)
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SceneError> {
+        let path = path.as_ref();
+        let mut obj = Self::read(BufReader::new(File::open(path)?))?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        for library in &obj.material_libraries {
+            let file = BufReader::new(File::open(dir.join(library))?);
+            obj.materials.extend(read_mtl(file, dir)?);
+        }
+        Ok(obj)
+    }
+
+    #[must_use]
+    pub fn material(&self, name: &str) -> Option<&ObjMaterial<T>> {
+        self.materials.iter().find(|material| material.name == name)
+    }
+}
+
+impl<T, U> Obj<T, U>
+where
+    T: Float + FloatConst + Send + Sync + 'static,
+    U: Send + Sync + 'static,
+{
+    /// Returns a primitive for every triangle, with the translated materials, see
+    /// [`ObjMaterial::to_material`]
+    ///
+    /// Triangles whose material has an emission become area lights. Meshes without a
+    /// material, or with one missing from the MTL files, get the default one of
+    /// [`ObjMaterial::new`].
+    pub fn primitives(&self) -> Result<Vec<Primitive<T, U>>, SceneError> {
+        let fallback = ObjMaterial::new("");
+        let mut translated = HashMap::new();
+        let mut primitives = Vec::new();
+        for mesh in &self.meshes {
+            let name = mesh.material.as_deref().unwrap_or_default();
+            let material = self.material(name).unwrap_or(&fallback);
+            let translated = match translated.get(name) {
+                Some(translated) => Arc::clone(translated),
+                None => {
+                    let new = material.to_material()?;
+                    translated.insert(name, Arc::clone(&new));
+                    new
+                }
+            };
+
+            let triangles = Triangle::from_mesh(&Arc::new(mesh.mesh.clone())).collect::<Vec<_>>();
+            for triangle in triangles {
+                let shape: Arc<dyn SampleShape<T, U> + Send + Sync> = Arc::new(triangle);
+                let shape_material = Some(Arc::clone(&translated));
+                primitives.push(if material.emission.max_component() > T::zero() {
+                    let light = DiffuseAreaLight::new(Arc::clone(&shape), material.emission);
+                    Primitive::emissive(shape, shape_material, light)
+                } else {
+                    Primitive::new(shape, shape_material)
+                });
+            }
+        }
+        Ok(primitives)
+    }
+}
+
+impl<T: Float, U> MeshBuilder<T, U> {
+    /// Returns the index of the vertex with the given OBJ indices, adding it if it is new
+    fn vertex(
+        &mut self,
+        key: (usize, Option<usize>, Option<usize>),
+        positions: &[Point3<T, U>],
+        normals: &[Normal3<T, U>],
+        uvs: &[Point2<T, UnknownUnit>],
+        line: usize,
+    ) -> Result<u32, SceneError> {
+        if let Some(&index) = self.vertices.get(&key) {
+            return Ok(index);
+        }
+        let index = u32::try_from(self.mesh.mesh.positions.len()).map_err(|_| {
+            SceneError::Unsupported(format!("more than 2^32 vertices on line {line}"))
+        })?;
+        let (p, uv, n) = key;
+        self.mesh.mesh.positions.push(positions[p]);
+        self.complete.0 &= n.is_some();
+        self.normals
+            .push(n.map_or(Vector3::zero().to_normal(), |n| normals[n]));
+        self.complete.1 &= uv.is_some();
+        self.uvs.push(uv.map_or(Point2::origin(), |uv| uvs[uv]));
+        self.vertices.insert(key, index);
+        Ok(index)
+    }
+}
+
+/// Parses a corner of a face, `p`, `p/t`, `p//n` or `p/t/n`, into indices from zero
+fn parse_corner<T, U>(
+    corner: &str,
+    positions: &[Point3<T, U>],
+    normals: &[Normal3<T, U>],
+    uvs: &[Point2<T, UnknownUnit>],
+    line: usize,
+) -> Result<(usize, Option<usize>, Option<usize>), SceneError> {
+    // Counts from one, or back from the end of the elements so far if negative
+    let resolve = |index: &str, len: usize| {
+        let index: isize = index
+            .parse()
+            .map_err(|_| SceneError::parse(line, format!("invalid index `{index}`")))?;
+        let resolved = match index {
+            1.. => index.unsigned_abs() - 1,
+            ..=-1 => len.wrapping_sub(index.unsigned_abs()),
+            0 => usize::MAX,
+        };
+        if resolved < len {
+            Ok(resolved)
+        } else {
+            Err(SceneError::parse(
+                line,
+                format!("index {index} out of range"),
+            ))
+        }
+    };
+    let optional = |index: Option<&str>, len| match index {
+        Some(index) if !index.is_empty() => resolve(index, len).map(Some),
+        _ => Ok(None),
+    };
+
+    let mut indices = corner.split('/');
+    let p = resolve(indices.next().unwrap_or_default(), positions.len())?;
+    let uv = optional(indices.next(), uvs.len())?;
+    let n = optional(indices.next(), normals.len())?;
+    Ok((p, uv, n))
+}
+
+fn parse_float<T: Float>(token: Option<&str>, line: usize) -> Result<T, SceneError> {
+    let token = token.ok_or_else(|| SceneError::parse(line, "missing number"))?;
+    token
+        .parse::<f64>()
+        .ok()
+        .and_then(T::from)
+        .ok_or_else(|| SceneError::parse(line, format!("invalid number `{token}`")))
+}
+
+fn parse_floats<T: Float, const N: usize>(
+    tokens: &mut SplitWhitespace<'_>,
+    line: usize,
+) -> Result<[T; N], SceneError> {
+    let mut values = [T::zero(); N];
+    for value in &mut values {
+        *value = parse_float(tokens.next(), line)?;
+    }
+    Ok(values)
+}
+
+/// Parses a color of one or three components, with a single one standing for grey
+fn parse_color<T: Float>(
+    tokens: &mut SplitWhitespace<'_>,
+    line: usize,
+) -> Result<Rgb<T>, SceneError> {
+    let r = parse_float(tokens.next(), line)?;
+    let g = tokens
+        .next()
+        .map_or(Ok(r), |g| parse_float(Some(g), line))?;
+    let b = tokens
+        .next()
+        .map_or(Ok(g), |b| parse_float(Some(b), line))?;
+    Ok(Rgb::new(r, g, b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_obj() {
+        let obj = "mtllib scene.mtl
+# A textured quad
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+o quad
+usemtl red
+f 1/1/1 2/2/1 3/3/1 4/4/1
+
+# And a lamp above it, with relative indices
+o lamp
+usemtl light
+v 0 0 2
+v 1 0 2
+v 0 1 \\
+  2
+f -3 -1 -2
+";
+        let mut obj = Obj::<f64, UnknownUnit>::read(obj.as_bytes()).unwrap();
+        assert_eq!(obj.material_libraries, ["scene.mtl"]);
+        assert_eq!(obj.meshes.len(), 2);
+        let quad = &obj.meshes[0];
+        assert_eq!(
+            (quad.name.as_str(), quad.material.as_deref()),
+            ("quad", Some("red"))
+        );
+        assert_eq!(quad.mesh.indices, [[0, 1, 2], [0, 2, 3]]);
+        assert_eq!(quad.mesh.uvs.as_ref().unwrap()[2], Point2::new(1., 1.));
+        assert!(quad.mesh.normals.is_some());
+        let lamp = &obj.meshes[1].mesh;
+        assert_eq!(lamp.indices, [[0, 1, 2]]);
+        assert_eq!(lamp.positions[1], Point3::new(0., 1., 2.));
+        assert!(lamp.normals.is_none() && lamp.uvs.is_none());
+
+        let mtl = "newmtl red\nKd 0.8 0.1 0.1\nNs 10\n\nnewmtl light\nKd 0\nKe 4 4 4\nillum 1\n";
+        obj.materials = read_mtl(mtl.as_bytes(), "").unwrap();
+        assert_eq!(
+            obj.material("red").unwrap().diffuse,
+            Rgb::new(0.8, 0.1, 0.1)
+        );
+        assert_eq!(obj.material("light").unwrap().diffuse, Rgb::splat(0.));
+        let primitives = obj.primitives().unwrap();
+        assert_eq!(primitives.len(), 3);
+        let emissive = primitives.iter().filter(|p| p.area_light().is_some());
+        assert_eq!(emissive.count(), 1);
+
+        let error = Obj::<f64, UnknownUnit>::read("v 0 0 0\nf 1 2 3\n".as_bytes()).unwrap_err();
+        assert!(matches!(error, SceneError::Parse { line: 2, .. }));
+    }
+}
@@ -0,0 +1,1250 @@
+use crate::{
+    accel::Bvh,
+    bsdf::Metal,
+    camera::{CameraSpace, ThinLensCamera},
+    color::{Rgb, RgbColorSpace, Xyz},
+    core::{
+        geometry::{
+            transform::{Transform3, Transformation},
+            Box3, Point2, Point3, Vector3,
+        },
+        units::Angle,
+    },
+    image::Image,
+    light::{DiffuseAreaLight, DistantLight, EnvironmentLight, Light, PointLight, SpotLight},
+    material::{ConductorMaterial, DielectricMaterial, DiffuseMaterial, LayeredMaterial, Material},
+    scene::{Instance, Primitive, Prototype},
+    scene_io::SceneError,
+    shape::{Disk, SampleShape, Shape, Sphere, Triangle, TriangleMesh},
+    spectrum::{spectrum_to_xyz, BlackbodySpectrum, PiecewiseLinearSpectrum},
+    texture::{ImageTexture, Texture, WrapMode},
+};
+use num_traits::{Float, FloatConst};
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    io::Read,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// A scene in the format of pbrt-v4
+///
+/// Triangle meshes, bilinear patch meshes, PLY meshes, spheres and disks are read with their
+/// materials and area lights. Diffuse, conductor, dielectric and coated materials are
+/// translated to their rt3 counterparts, with image textures for diffuse reflectances; other
+/// materials become diffuse. Point, spot, distant and infinite lights are read, and so are the
+/// perspective camera and the film, sampler and integrator settings rt3 has equivalents for.
+///
+/// Other shapes, such as curves, cylinders and subdivision surfaces, are skipped, as are
+/// media, motion, the other cameras and lights, and the remaining textures. Disks, and spheres
+/// under transforms other than similarities, are placed as instances and do not emit light.
+pub struct Pbrt<T, U> {
+    pub primitives: Vec<Primitive<T, U>>,
+    /// The lights of the scene other than the area lights of its primitives
+    pub lights: Vec<Arc<dyn Light<T, U>>>,
+    pub camera: Option<ThinLensCamera<T, U>>,
+    /// The width and height of the film in pixels
+    pub resolution: (usize, usize),
+    /// The name of the image the scene is rendered to
+    pub filename: Option<String>,
+    pub samples_per_pixel: usize,
+    /// The number of bounces of the paths traced through the scene
+    pub max_depth: usize,
+}
+
+impl<T: fmt::Debug, U> fmt::Debug for Pbrt<T, U>
+where
+    ThinLensCamera<T, U>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pbrt")
+            .field("primitives", &self.primitives)
+            .field("camera", &self.camera)
+            .field("resolution", &self.resolution)
+            .field("filename", &self.filename)
+            .field("samples_per_pixel", &self.samples_per_pixel)
+            .field("max_depth", &self.max_depth)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, U> Pbrt<T, U>
+where
+    T: Float + FloatConst + Send + Sync + 'static,
+    U: Send + Sync + 'static,
+{
+    /// Reads a scene, looking up the files it includes and refers to in `dir`
+    pub fn read(mut reader: impl Read, dir: impl AsRef<Path>) -> Result<Self, SceneError> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        let mut builder = Builder::new(dir.as_ref());
+        builder.run(&text)?;
+        builder.finish()
+    }
+
+    /// Reads the scene at `path`, see [`read`](Self::read)
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SceneError> {
+        let path = path.as_ref();
+        let dir = path.parent().unwrap_or(Path::new(""));
+        Self::read(fs::File::open(path)?, dir)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    String(String),
+    Number(f64),
+    Open,
+    Close,
+}
+
+/// Splits a scene file into tokens, with the numbers of their lines
+fn tokenize(text: &str) -> Result<Vec<(usize, Token)>, SceneError> {
+    let mut tokens = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let number = index + 1;
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '#' => break,
+                '[' => tokens.push((number, Token::Open)),
+                ']' => tokens.push((number, Token::Close)),
+                '"' => {
+                    let mut string = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('"') => break,
+                            Some('\\') => string.extend(chars.next()),
+                            Some(c) => string.push(c),
+                            None => return Err(SceneError::parse(number, "unterminated string")),
+                        }
+                    }
+                    tokens.push((number, Token::String(string)));
+                }
+                c if c.is_whitespace() => {}
+                c => {
+                    let mut word = String::from(c);
+                    while let Some(&c) = chars.peek() {
+                        if c.is_whitespace() || matches!(c, '[' | ']' | '"' | '#') {
+                            break;
+                        }
+                        word.push(c);
+                        chars.next();
+                    }
+                    tokens.push((
+                        number,
+                        if c.is_ascii_digit() || matches!(c, '-' | '+' | '.') {
+                            let value = word.parse().map_err(|_| {
+                                SceneError::parse(number, format!("invalid number `{word}`"))
+                            })?;
+                            Token::Number(value)
+                        } else {
+                            Token::Word(word)
+                        },
+                    ));
+                }
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Number(f64),
+    String(String),
+    Bool(bool),
+}
+
+/// An argument of a directive, a single value or a bracketed list of them
+#[derive(Debug, Clone, PartialEq)]
+enum Arg {
+    Value(Value),
+    Array(Vec<Value>),
+}
+
+impl Arg {
+    fn values(&self) -> &[Value] {
+        match self {
+            Self::Value(value) => std::slice::from_ref(value),
+            Self::Array(values) => values,
+        }
+    }
+}
+
+/// A parameter such as `"float radius" 2`, with its type and values
+#[derive(Debug)]
+struct Param {
+    kind: String,
+    name: String,
+    values: Vec<Value>,
+}
+
+impl Param {
+    fn numbers(&self) -> Vec<f64> {
+        self.values
+            .iter()
+            .filter_map(|value| match *value {
+                Value::Number(n) => Some(n),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn string(&self) -> Option<&str> {
+        match self.values.first() {
+            Some(Value::String(s)) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// The parameters of a directive, with its line for errors
+#[derive(Debug, Default)]
+struct Params {
+    line: usize,
+    params: Vec<Param>,
+}
+
+impl Params {
+    fn get(&self, name: &str) -> Option<&Param> {
+        self.params.iter().find(|param| param.name == name)
+    }
+
+    fn number(&self, name: &str) -> Option<f64> {
+        self.get(name)
+            .and_then(|param| param.numbers().first().copied())
+    }
+
+    fn float<T: Float>(&self, name: &str, default: f64) -> T {
+        T::from(self.number(name).unwrap_or(default)).unwrap()
+    }
+
+    fn string(&self, name: &str) -> Option<&str> {
+        self.get(name).and_then(Param::string)
+    }
+
+    fn bool(&self, name: &str) -> Option<bool> {
+        match self.get(name)?.values.first()? {
+            Value::Bool(b) => Some(*b),
+            Value::String(s) => Some(s == "true"),
+            Value::Number(_) => None,
+        }
+    }
+
+    fn point<T: Float, U>(&self, name: &str, default: [f64; 3]) -> Point3<T, U> {
+        let numbers = self.get(name).map(Param::numbers);
+        let [x, y, z] = match numbers.as_deref() {
+            Some(&[x, y, z]) => [x, y, z],
+            _ => default,
+        };
+        let cast = |c: f64| T::from(c).unwrap();
+        Point3::new(cast(x), cast(y), cast(z))
+    }
+
+    /// Returns the color of a spectrum parameter
+    ///
+    /// Like pbrt-v4, illuminants given other than in RGB are normalized to a luminance of one,
+    /// which leaves their brightness to the scale of the light. Named spectra other than those
+    /// of the metals rt3 knows are left out.
+    fn spectrum<T: Float>(
+        &self,
+        name: &str,
+        illuminant: bool,
+    ) -> Result<Option<Rgb<T>>, SceneError> {
+        let Some(param) = self.get(name) else {
+            return Ok(None);
+        };
+        let invalid = || SceneError::parse(self.line, format!("invalid spectrum `{name}`"));
+        let numbers: Vec<T> = param
+            .numbers()
+            .into_iter()
+            .map(|n| T::from(n).unwrap())
+            .collect();
+        let xyz = match (&*param.kind, &*numbers) {
+            ("rgb" | "color", &[r, g, b]) => return Ok(Some(Rgb::new(r, g, b))),
+            ("blackbody", &[temperature]) => spectrum_to_xyz(&BlackbodySpectrum::new(temperature)),
+            ("spectrum", []) => return Ok(param.string().and_then(named_spectrum)),
+            ("spectrum", numbers) if numbers.len() % 2 == 0 => {
+                let lambdas = numbers.iter().step_by(2).copied().collect();
+                let values = numbers.iter().skip(1).step_by(2).copied().collect();
+                let spectrum = PiecewiseLinearSpectrum::new(lambdas, values).ok_or_else(invalid)?;
+                let xyz = spectrum_to_xyz(&spectrum);
+                if !illuminant {
+                    return Ok(Some(RgbColorSpace::srgb().from_xyz(xyz)));
+                }
+                xyz
+            }
+            _ => return Err(invalid()),
+        };
+        if xyz.y <= T::zero() {
+            return Ok(Some(Rgb::black()));
+        }
+        let xyz = Xyz::new(xyz.x / xyz.y, T::one(), xyz.z / xyz.y);
+        Ok(Some(RgbColorSpace::srgb().from_xyz(xyz)))
+    }
+}
+
+/// Returns the optical constants of a metal named as in pbrt-v4, such as `metal-Au-eta`
+fn named_spectrum<T: Float>(name: &str) -> Option<Rgb<T>> {
+    let (metal, constant) = name.strip_prefix("metal-")?.rsplit_once('-')?;
+    let metal = match metal {
+        "Au" => Metal::Gold,
+        "Ag" => Metal::Silver,
+        "Cu" => Metal::Copper,
+        "Al" => Metal::Aluminum,
+        _ => return None,
+    };
+    match constant {
+        "eta" => Some(metal.eta()),
+        "k" => Some(metal.k()),
+        _ => None,
+    }
+}
+
+/// Splits the arguments of a directive into its leading strings and its parameters
+fn split_args(
+    args: &[Arg],
+    positional: usize,
+    line: usize,
+) -> Result<(Vec<&str>, Params), SceneError> {
+    let invalid = |message: &str| SceneError::parse(line, message);
+    if args.len() < positional {
+        return Err(invalid("missing arguments"));
+    }
+    let strings = args[..positional]
+        .iter()
+        .map(|arg| match arg.values() {
+            [Value::String(s)] => Ok(&**s),
+            _ => Err(invalid("expected a string")),
+        })
+        .collect::<Result<_, _>>()?;
+
+    let mut params = Params {
+        line,
+        params: Vec::new(),
+    };
+    for pair in args[positional..].chunks(2) {
+        let [Arg::Value(Value::String(declaration)), value] = pair else {
+            return Err(invalid("expected a parameter"));
+        };
+        let mut words = declaration.split_whitespace();
+        let (Some(kind), Some(name), None) = (words.next(), words.next(), words.next()) else {
+            return Err(invalid("invalid parameter declaration"));
+        };
+        params.params.push(Param {
+            kind: kind.to_owned(),
+            name: name.to_owned(),
+            values: value.values().to_vec(),
+        });
+    }
+    Ok((strings, params))
+}
+
+/// Returns the numbers of a directive such as `Translate`, of which it takes `count`
+fn numbers(args: &[Arg], count: usize, line: usize) -> Result<Vec<f64>, SceneError> {
+    let numbers: Vec<_> = args
+        .iter()
+        .flat_map(Arg::values)
+        .map(|value| match *value {
+            Value::Number(n) => Some(n),
+            _ => None,
+        })
+        .collect::<Option<_>>()
+        .ok_or_else(|| SceneError::parse(line, "expected numbers"))?;
+    if numbers.len() != count {
+        return Err(SceneError::parse(line, format!("expected {count} numbers")));
+    }
+    Ok(numbers)
+}
+
+/// Returns a transform from a matrix in row-vector form
+fn matrix<T: Float, U>(mat: [[f64; 4]; 4], line: usize) -> Result<Transform3<T, U, U>, SceneError> {
+    Transform3::try_new(mat.map(|row| row.map(|x| T::from(x).unwrap())))
+        .ok_or_else(|| SceneError::parse(line, "singular transform"))
+}
+
+/// Returns the transform pbrt's `LookAt` applies, from world space to that of the camera
+fn look_at<T: Float, U>(v: &[f64], line: usize) -> Result<Transform3<T, U, U>, SceneError> {
+    let eye = [v[0], v[1], v[2]];
+    let dir = normalize([v[3] - v[0], v[4] - v[1], v[5] - v[2]]);
+    let right = normalize(cross(normalize([v[6], v[7], v[8]]), dir));
+    let up = cross(dir, right);
+    let camera_to_world = [
+        [right[0], right[1], right[2], 0.],
+        [up[0], up[1], up[2], 0.],
+        [dir[0], dir[1], dir[2], 0.],
+        [eye[0], eye[1], eye[2], 1.],
+    ];
+    if camera_to_world.iter().flatten().any(|x| !x.is_finite()) {
+        return Err(SceneError::parse(line, "degenerate `LookAt`"));
+    }
+    Ok(matrix(camera_to_world, line)?.inverse())
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normalize(a: [f64; 3]) -> [f64; 3] {
+    let length = a.iter().map(|x| x * x).sum::<f64>().sqrt();
+    a.map(|x| x / length)
+}
+
+/// The state that `AttributeBegin` saves and `AttributeEnd` restores
+struct Attributes<T, U> {
+    material: Option<Arc<dyn Material<T, U>>>,
+    /// The radiance of the area light of the shapes, and whether both of their sides emit
+    area_light: Option<(Rgb<T>, bool)>,
+    reverse_orientation: bool,
+}
+
+impl<T: Copy, U> Clone for Attributes<T, U> {
+    fn clone(&self) -> Self {
+        Self {
+            material: self.material.clone(),
+            area_light: self.area_light,
+            reverse_orientation: self.reverse_orientation,
+        }
+    }
+}
+
+/// A shape ready to be placed, either already in world space or in its object space
+enum Geometry<T, U> {
+    Mesh(TriangleMesh<T, U>),
+    Sphere(Sphere<T, U>),
+    Object(Arc<dyn Shape<T, U> + Send + Sync>),
+}
+
+/// A transform saved by `AttributeBegin` or `TransformBegin`, with the attributes unless
+/// it was the latter
+type Saved<T, U> = (Transform3<T, U, U>, Option<Attributes<T, U>>);
+
+/// A prototype placed in the space of an object
+type Placement<T, U> = (Arc<Prototype<T, U, U>>, Transform3<T, U, U>);
+
+/// A light waiting for the bounds of the scene
+type PendingLight<T, U> = Box<dyn FnOnce(Box3<T, U>) -> Arc<dyn Light<T, U>>>;
+
+struct Builder<T, U> {
+    dir: PathBuf,
+    ctm: Transform3<T, U, U>,
+    attributes: Attributes<T, U>,
+    stack: Vec<Saved<T, U>>,
+    coordinate_systems: HashMap<String, Transform3<T, U, U>>,
+    named_materials: HashMap<String, Option<Arc<dyn Material<T, U>>>>,
+    textures: HashMap<String, Arc<ImageTexture<T>>>,
+    objects: HashMap<String, Vec<Placement<T, U>>>,
+    object: Option<String>,
+    primitives: Vec<Primitive<T, U>>,
+    lights: Vec<PendingLight<T, U>>,
+    camera: Option<(Transform3<T, U, U>, Params)>,
+    film: Params,
+    samples_per_pixel: usize,
+    max_depth: usize,
+}
+
+impl<T, U> Builder<T, U>
+where
+    T: Float + FloatConst + Send + Sync + 'static,
+    U: Send + Sync + 'static,
+{
+    fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_owned(),
+            ctm: Transform3::identity(),
+            attributes: Attributes {
+                material: Some(Arc::new(DiffuseMaterial::new(Rgb::splat(
+                    T::from(0.5).unwrap(),
+                )))),
+                area_light: None,
+                reverse_orientation: false,
+            },
+            stack: Vec::new(),
+            coordinate_systems: HashMap::new(),
+            named_materials: HashMap::new(),
+            textures: HashMap::new(),
+            objects: HashMap::new(),
+            object: None,
+            primitives: Vec::new(),
+            lights: Vec::new(),
+            camera: None,
+            film: Params::default(),
+            samples_per_pixel: 16,
+            max_depth: 5,
+        }
+    }
+
+    /// Runs the directives of a file, or of one it includes
+    fn run(&mut self, text: &str) -> Result<(), SceneError> {
+        let tokens = tokenize(text)?;
+        let mut tokens = tokens.into_iter().peekable();
+        while let Some((line, token)) = tokens.next() {
+            let Token::Word(directive) = token else {
+                return Err(SceneError::parse(line, "expected a directive"));
+            };
+            let mut args = Vec::new();
+            let value = |token: Token, line| match token {
+                Token::Number(n) => Ok(Value::Number(n)),
+                Token::String(s) => Ok(Value::String(s)),
+                Token::Word(w) if w == "true" || w == "false" => Ok(Value::Bool(w == "true")),
+                _ => Err(SceneError::parse(line, "expected a value")),
+            };
+            while let Some((line, token)) = tokens.next_if(|(_, token)| match token {
+                Token::Word(w) => w == "true" || w == "false",
+                _ => true,
+            }) {
+                args.push(if token == Token::Open {
+                    let mut values = Vec::new();
+                    loop {
+                        match tokens.next() {
+                            Some((_, Token::Close)) => break,
+                            Some((line, token)) => values.push(value(token, line)?),
+                            None => return Err(SceneError::parse(line, "unterminated list")),
+                        }
+                    }
+                    Arg::Array(values)
+                } else {
+                    Arg::Value(value(token, line)?)
+                });
+            }
+            self.directive(&directive, &args, line)?;
+        }
+        Ok(())
+    }
+
+    fn directive(&mut self, directive: &str, args: &[Arg], line: usize) -> Result<(), SceneError> {
+        match directive {
+            "Identity" => self.ctm = Transform3::identity(),
+            "Translate" => {
+                let v = numbers(args, 3, line)?;
+                let mut mat = identity();
+                mat[3][..3].copy_from_slice(&v);
+                self.concat(mat, line)?;
+            }
+            "Scale" => {
+                let v = numbers(args, 3, line)?;
+                let mut mat = identity();
+                for i in 0..3 {
+                    mat[i][i] = v[i];
+                }
+                self.concat(mat, line)?;
+            }
+            "Rotate" => {
+                let v = numbers(args, 4, line)?;
+                let a = normalize([v[1], v[2], v[3]]);
+                let (sin, cos) = v[0].to_radians().sin_cos();
+                // Rodrigues' formula, transposed for row vectors
+                let mut mat = identity();
+                for i in 0..3 {
+                    for j in 0..3 {
+                        let skew = match (i + 3 - j) % 3 {
+                            1 => -a[3 - i - j],
+                            2 => a[3 - i - j],
+                            _ => 0.,
+                        };
+                        mat[i][j] = a[i] * a[j] * (1. - cos) + sin * skew;
+                        if i == j {
+                            mat[i][j] += cos;
+                        }
+                    }
+                }
+                self.concat(mat, line)?;
+            }
+            "LookAt" => {
+                let v = numbers(args, 9, line)?;
+                self.ctm = look_at(&v, line)? * self.ctm;
+            }
+            "Transform" | "ConcatTransform" => {
+                let v = numbers(args, 16, line)?;
+                let mut mat = identity();
+                for (i, row) in mat.iter_mut().enumerate() {
+                    row.copy_from_slice(&v[4 * i..4 * i + 4]);
+                }
+                if directive == "Transform" {
+                    self.ctm = Transform3::identity();
+                }
+                self.concat(mat, line)?;
+            }
+            "CoordinateSystem" | "CoordSysTransform" => {
+                let (name, _) = split_args(args, 1, line)?;
+                if directive == "CoordinateSystem" {
+                    self.coordinate_systems.insert(name[0].to_owned(), self.ctm);
+                } else {
+                    self.ctm = *self.coordinate_systems.get(name[0]).ok_or_else(|| {
+                        SceneError::parse(line, format!("unknown coordinate system `{}`", name[0]))
+                    })?;
+                }
+            }
+            "AttributeBegin" => self.stack.push((self.ctm, Some(self.attributes.clone()))),
+            "TransformBegin" => self.stack.push((self.ctm, None)),
+            "AttributeEnd" | "TransformEnd" => {
+                let (ctm, attributes) = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| SceneError::parse(line, format!("unmatched `{directive}`")))?;
+                self.ctm = ctm;
+                if let Some(attributes) = attributes {
+                    self.attributes = attributes;
+                }
+            }
+            "WorldBegin" => {
+                self.ctm = Transform3::identity();
+                self.coordinate_systems.insert("world".into(), self.ctm);
+            }
+            "Camera" => {
+                let (kind, params) = split_args(args, 1, line)?;
+                self.coordinate_systems
+                    .insert("camera".into(), self.ctm.inverse());
+                self.camera = (kind[0] == "perspective").then_some((self.ctm, params));
+            }
+            "Film" => self.film = split_args(args, 1, line)?.1,
+            "Sampler" => {
+                let (_, params) = split_args(args, 1, line)?;
+                if let Some(n) = params.number("pixelsamples") {
+                    self.samples_per_pixel = n.max(1.) as usize;
+                }
+            }
+            "Integrator" => {
+                let (_, params) = split_args(args, 1, line)?;
+                if let Some(n) = params.number("maxdepth") {
+                    self.max_depth = n.max(0.) as usize;
+                }
+            }
+            "Material" => {
+                let (kind, params) = split_args(args, 1, line)?;
+                self.attributes.material = self.material(kind[0], &params)?;
+            }
+            "MakeNamedMaterial" => {
+                let (name, params) = split_args(args, 1, line)?;
+                let kind = params.string("type").unwrap_or("diffuse");
+                let material = self.material(kind, &params)?;
+                self.named_materials.insert(name[0].to_owned(), material);
+            }
+            "NamedMaterial" => {
+                let (name, _) = split_args(args, 1, line)?;
+                let material = self.named_materials.get(name[0]).ok_or_else(|| {
+                    SceneError::parse(line, format!("unknown material `{}`", name[0]))
+                })?;
+                self.attributes.material = material.clone();
+            }
+            "Texture" => {
+                let (strings, params) = split_args(args, 3, line)?;
+                if let ["spectrum" | "color", "imagemap"] = strings[1..] {
+                    let texture = self.image_texture(&params)?;
+                    self.textures
+                        .insert(strings[0].to_owned(), Arc::new(texture));
+                }
+            }
+            "LightSource" => {
+                let (kind, params) = split_args(args, 1, line)?;
+                self.light(kind[0], &params)?;
+            }
+            "AreaLightSource" => {
+                let (_, params) = split_args(args, 1, line)?;
+                let radiance = params.spectrum("L", true)?.unwrap_or(Rgb::splat(T::one()));
+                let two_sided = params.bool("twosided").unwrap_or(false);
+                let scale = params.float::<T>("scale", 1.);
+                self.attributes.area_light = Some((radiance * scale, two_sided));
+            }
+            "ReverseOrientation" => {
+                self.attributes.reverse_orientation = !self.attributes.reverse_orientation;
+            }
+            "Shape" => {
+                let (kind, params) = split_args(args, 1, line)?;
+                if let Some(geometry) = self.geometry(kind[0], &params)? {
+                    self.place(geometry)?;
+                }
+            }
+            "ObjectBegin" => {
+                let (name, _) = split_args(args, 1, line)?;
+                self.stack.push((self.ctm, Some(self.attributes.clone())));
+                self.objects.insert(name[0].to_owned(), Vec::new());
+                self.object = Some(name[0].to_owned());
+            }
+            "ObjectEnd" => {
+                let outside = || SceneError::parse(line, "`ObjectEnd` outside an object");
+                self.object.take().ok_or_else(outside)?;
+                let (ctm, attributes) = self.stack.pop().ok_or_else(outside)?;
+                self.ctm = ctm;
+                if let Some(attributes) = attributes {
+                    self.attributes = attributes;
+                }
+            }
+            "ObjectInstance" => {
+                let (name, _) = split_args(args, 1, line)?;
+                let object = self.objects.get(name[0]).ok_or_else(|| {
+                    SceneError::parse(line, format!("unknown object `{}`", name[0]))
+                })?;
+                for (prototype, transform) in object {
+                    let instance = Instance::new(Arc::clone(prototype), *transform * self.ctm);
+                    self.primitives.push(instance.try_into()?);
+                }
+            }
+            "Include" | "Import" => {
+                let (path, _) = split_args(args, 1, line)?;
+                let text = fs::read_to_string(self.dir.join(path[0]))?;
+                self.run(&text)?;
+            }
+            "Attribute" | "ColorSpace" | "Option" | "PixelFilter" | "Accelerator"
+            | "MakeNamedMedium" | "MediumInterface" | "TransformTimes" | "ActiveTransform"
+            | "WorldEnd" => {}
+            _ => {
+                return Err(SceneError::parse(
+                    line,
+                    format!("unknown directive `{directive}`"),
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies a transform to what follows, before the current one
+    fn concat(&mut self, mat: [[f64; 4]; 4], line: usize) -> Result<(), SceneError> {
+        self.ctm = matrix(mat, line)? * self.ctm;
+        Ok(())
+    }
+
+    /// Returns a diffuse reflectance, from a color or the name of a texture
+    fn reflectance(
+        &self,
+        params: &Params,
+        name: &str,
+        default: f64,
+    ) -> Result<Arc<dyn Texture<T, U, Rgb<T>>>, SceneError> {
+        let param = params.get(name);
+        if let Some(param) = param.filter(|param| param.kind == "texture") {
+            let texture = param.string().and_then(|name| self.textures.get(name));
+            if let Some(texture) = texture {
+                return Ok(Arc::clone(texture) as _);
+            }
+        }
+        let color = params.spectrum(name, false)?;
+        Ok(Arc::new(
+            color.unwrap_or(Rgb::splat(T::from(default).unwrap())),
+        ))
+    }
+
+    /// Returns a conductor from the parameters with the given prefix, such as `conductor.`
+    fn conductor(&self, params: &Params, prefix: &str) -> Result<ConductorMaterial<T>, SceneError> {
+        let spectrum = |name: &str| params.spectrum(&format!("{prefix}{name}"), false);
+        let (eta, k) = match spectrum("reflectance")? {
+            // The absorption of a conductor with an index of refraction of one which reflects
+            // as much at normal incidence
+            Some(r) => {
+                let r = r.map(|r: T| r.max(T::zero()).min(T::from(0.9999).unwrap()));
+                let two = T::one() + T::one();
+                (
+                    Rgb::splat(T::one()),
+                    r.map(|r| two * r.sqrt() / (T::one() - r).sqrt()),
+                )
+            }
+            None => (
+                spectrum("eta")?.unwrap_or(Metal::Copper.eta()),
+                spectrum("k")?.unwrap_or(Metal::Copper.k()),
+            ),
+        };
+        let (u, v) = roughness(params, prefix);
+        Ok(ConductorMaterial::new(eta, k, u).with_anisotropic_roughness(u, v))
+    }
+
+    /// Translates a material, with none for `interface` materials
+    fn material(
+        &self,
+        kind: &str,
+        params: &Params,
+    ) -> Result<Option<Arc<dyn Material<T, U>>>, SceneError> {
+        let dielectric = |prefix: &str| {
+            let eta = params.number(&format!("{prefix}eta")).unwrap_or(1.5);
+            let (roughness, _) = roughness(params, prefix);
+            DielectricMaterial::new(T::from(eta).unwrap(), roughness)
+        };
+        let material: Arc<dyn Material<T, U>> = match kind {
+            "interface" => return Ok(None),
+            "conductor" => Arc::new(self.conductor(params, "")?),
+            "dielectric" | "thindielectric" => Arc::new(dielectric("")),
+            "coateddiffuse" => {
+                let base = DiffuseMaterial::new(self.reflectance(params, "reflectance", 0.5)?);
+                let albedo = params.spectrum("albedo", false)?;
+                Arc::new(
+                    LayeredMaterial::new(Arc::new(dielectric("")), Arc::new(base)).with_medium(
+                        params.float("thickness", 0.01),
+                        albedo.unwrap_or(Rgb::black()),
+                        params.float("g", 0.),
+                    ),
+                )
+            }
+            "coatedconductor" => {
+                let base = self.conductor(params, "conductor.")?;
+                let albedo = params.spectrum("albedo", false)?;
+                Arc::new(
+                    LayeredMaterial::new(Arc::new(dielectric("interface.")), Arc::new(base))
+                        .with_medium(
+                            params.float("thickness", 0.01),
+                            albedo.unwrap_or(Rgb::black()),
+                            params.float("g", 0.),
+                        ),
+                )
+            }
+            // A mix is replaced by the material it is mostly made of
+            "mix" => {
+                let names = params.get("materials").map_or(&[][..], |p| &p.values[..]);
+                let index = usize::from(params.number("amount").unwrap_or(0.5) > 0.5);
+                let material = match names.get(index) {
+                    Some(Value::String(name)) => self.named_materials.get(name),
+                    _ => None,
+                };
+                return material
+                    .cloned()
+                    .ok_or_else(|| SceneError::parse(params.line, "mix of unknown materials"));
+            }
+            _ => Arc::new(DiffuseMaterial::new(self.reflectance(
+                params,
+                "reflectance",
+                0.5,
+            )?)),
+        };
+        Ok(Some(material))
+    }
+
+    fn image_texture(&self, params: &Params) -> Result<ImageTexture<T>, SceneError> {
+        let filename = params
+            .string("filename")
+            .ok_or_else(|| SceneError::parse(params.line, "image texture without a file"))?;
+        let mut image: Image<T> = Image::load(self.dir.join(filename))?;
+        let linear = params.string("encoding") == Some("linear");
+        let scale = params.float::<T>("scale", 1.);
+        let invert = params.bool("invert") == Some(true);
+        for p in image.pixels_mut() {
+            // 8-bit images are decoded from sRGB when they are read
+            if linear && !filename.ends_with(".exr") && !filename.ends_with(".hdr") {
+                *p = p.to_srgb();
+            }
+            *p *= scale;
+            if invert {
+                *p = Rgb::splat(T::one()) - *p;
+            }
+        }
+        let wrap = match params.string("wrap") {
+            Some("clamp" | "black") => WrapMode::Clamp,
+            _ => WrapMode::Repeat,
+        };
+        Ok(ImageTexture::new(image, wrap))
+    }
+
+    fn light(&mut self, kind: &str, params: &Params) -> Result<(), SceneError> {
+        let scale = params.float::<T>("scale", 1.);
+        let white = Rgb::splat(T::one());
+        let ctm = self.ctm;
+        let point = |name, default| {
+            ctm.transform(params.point(name, default))
+                .try_into()
+                .map_err(|()| SceneError::parse(params.line, "point at infinity"))
+        };
+        match kind {
+            "point" => {
+                let intensity = params.spectrum("I", true)?.unwrap_or(white) * scale;
+                let light = PointLight::new(point("from", [0.; 3])?, intensity);
+                self.lights.push(Box::new(move |_| Arc::new(light)));
+            }
+            "spot" => {
+                let intensity = params.spectrum("I", true)?.unwrap_or(white) * scale;
+                let cone = Angle::from_radians(params.float::<T>("coneangle", 30.).to_radians());
+                let delta = Angle::from_radians(params.float::<T>("conedelta", 5.).to_radians());
+                let (from, to) = (point("from", [0.; 3])?, point("to", [0., 0., 1.])?);
+                let light = SpotLight::new(from, to, intensity, cone, delta);
+                self.lights.push(Box::new(move |_| Arc::new(light)));
+            }
+            "distant" => {
+                let irradiance = params.spectrum("L", true)?.unwrap_or(white) * scale;
+                let from: Point3<T, U> = params.point("from", [0.; 3]);
+                let direction = ctm.transform(from - params.point("to", [0., 0., 1.]));
+                self.lights.push(Box::new(move |bounds| {
+                    let radius = Angle::from_radians(T::zero());
+                    Arc::new(DistantLight::new(direction, irradiance, radius, bounds))
+                }));
+            }
+            "infinite" => {
+                let image = match params.string("filename") {
+                    Some(filename) => {
+                        let image = Image::load(self.dir.join(filename))?;
+                        equal_area_to_equirectangular(&image, &ctm.inverse()).ok_or_else(|| {
+                            SceneError::Unsupported("infinite lights with non-square images".into())
+                        })?
+                    }
+                    None => {
+                        let radiance = params.spectrum("L", true)?.unwrap_or(white);
+                        Image::new(1, 1, vec![radiance])
+                    }
+                };
+                self.lights.push(Box::new(move |bounds| {
+                    Arc::new(EnvironmentLight::new(image, scale, bounds))
+                }));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Reads a shape, with none for those that are skipped
+    fn geometry(&self, kind: &str, params: &Params) -> Result<Option<Geometry<T, U>>, SceneError> {
+        let invalid = || SceneError::parse(params.line, format!("invalid `{kind}`"));
+        let numbers = |name| params.get(name).map(Param::numbers).unwrap_or_default();
+        let mut mesh = match kind {
+            "trianglemesh" | "bilinearmesh" => {
+                let positions = numbers("P");
+                let vertex_count = positions.len() / 3;
+                let corners = if kind == "trianglemesh" { 3 } else { 4 };
+                let mut indices = numbers("indices");
+                if indices.is_empty() && vertex_count == corners {
+                    indices = (0..corners).map(|i| i as f64).collect();
+                }
+                if positions.len() % 3 != 0 || indices.len() % corners != 0 {
+                    return Err(invalid());
+                }
+                let indices: Vec<u32> = indices
+                    .into_iter()
+                    .map(|i| (i >= 0. && i < vertex_count as f64).then_some(i as u32))
+                    .collect::<Option<_>>()
+                    .ok_or_else(invalid)?;
+                let triangles = if corners == 3 {
+                    indices.chunks(3).map(|t| [t[0], t[1], t[2]]).collect()
+                } else {
+                    // Bilinear patches have their corners in the order (0, 0), (1, 0), (0, 1)
+                    // and (1, 1)
+                    let quads = indices.chunks(4);
+                    quads
+                        .flat_map(|q| [[q[0], q[1], q[3]], [q[0], q[3], q[2]]])
+                        .collect()
+                };
+                let cast = |x: f64| T::from(x).unwrap();
+                let positions = positions
+                    .chunks(3)
+                    .map(|p| Point3::new(cast(p[0]), cast(p[1]), cast(p[2])))
+                    .collect();
+                let mut mesh = TriangleMesh::new(positions, triangles);
+                let normals = numbers("N");
+                if normals.len() == 3 * vertex_count && vertex_count > 0 {
+                    let normals = normals.chunks(3);
+                    let normals = normals.map(|n| Vector3::new(cast(n[0]), cast(n[1]), cast(n[2])));
+                    mesh.normals = Some(normals.map(Vector3::to_normal).collect());
+                }
+                let uvs = numbers("uv");
+                if uvs.len() == 2 * vertex_count && vertex_count > 0 {
+                    let uvs = uvs
+                        .chunks(2)
+                        .map(|uv| Point2::new(cast(uv[0]), cast(uv[1])));
+                    mesh.uvs = Some(uvs.collect());
+                }
+                mesh
+            }
+            "plymesh" => {
+                let filename = params.string("filename").ok_or_else(invalid)?;
+                if filename.ends_with(".gz") {
+                    return Err(SceneError::Unsupported("compressed PLY meshes".into()));
+                }
+                TriangleMesh::load_ply(self.dir.join(filename))?
+            }
+            "sphere" => {
+                let radius = params.float::<T>("radius", 1.);
+                let sphere = Sphere::new(Point3::origin(), radius);
+                let Some(scale) = similarity_scale(&self.ctm) else {
+                    return Ok(Some(Geometry::Object(Arc::new(sphere))));
+                };
+                let center = self.ctm.transform(Point3::origin()).try_into();
+                let center = center.map_err(|()| invalid())?;
+                return Ok(Some(Geometry::Sphere(Sphere::new(center, radius * scale))));
+            }
+            "disk" => {
+                let center = Point3::new(T::zero(), T::zero(), params.float("height", 0.));
+                let disk = Disk::new(
+                    center,
+                    params.float("radius", 1.),
+                    params.float("innerradius", 0.),
+                );
+                return Ok(Some(Geometry::Object(Arc::new(disk))));
+            }
+            _ => return Ok(None),
+        };
+
+        // Meshes are moved to world space, keeping the side their normals are on, and reversed
+        // if the orientation of the shape is
+        for p in &mut mesh.positions {
+            *p = self.ctm.transform(*p).try_into().map_err(|()| invalid())?;
+        }
+        for n in mesh.normals.iter_mut().flatten() {
+            *n = self.ctm.transform(*n);
+        }
+        let swaps_handedness = self.ctm.determinant() < T::zero();
+        if self.attributes.reverse_orientation != swaps_handedness {
+            for triangle in &mut mesh.indices {
+                triangle.swap(1, 2);
+            }
+        }
+        Ok((mesh.triangle_count() > 0).then_some(Geometry::Mesh(mesh)))
+    }
+
+    /// Adds a shape to the scene, or to the object being defined
+    fn place(&mut self, geometry: Geometry<T, U>) -> Result<(), SceneError> {
+        let material = self.attributes.material.clone();
+        if let Some(name) = &self.object {
+            let (shape, transform): (Arc<dyn Shape<T, U> + Send + Sync>, _) = match geometry {
+                Geometry::Mesh(mesh) => {
+                    let triangles = Triangle::from_mesh(&Arc::new(mesh)).collect();
+                    (Arc::new(Bvh::new(triangles)), Transform3::identity())
+                }
+                Geometry::Sphere(sphere) => (Arc::new(sphere), Transform3::identity()),
+                Geometry::Object(shape) => (shape, self.ctm),
+            };
+            let prototype = Arc::new(Prototype::new(shape, material));
+            let object = self.objects.get_mut(name).unwrap();
+            object.push((prototype, transform));
+            return Ok(());
+        }
+
+        let shapes: Vec<Arc<dyn SampleShape<T, U> + Send + Sync>> = match geometry {
+            Geometry::Mesh(mesh) => Triangle::from_mesh(&Arc::new(mesh))
+                .map(|triangle| Arc::new(triangle) as _)
+                .collect(),
+            Geometry::Sphere(sphere) => vec![Arc::new(sphere)],
+            Geometry::Object(shape) => {
+                let prototype = Arc::new(Prototype::new(shape, material));
+                let instance = Instance::new(prototype, self.ctm);
+                self.primitives.push(instance.try_into()?);
+                return Ok(());
+            }
+        };
+        for shape in shapes {
+            self.primitives.push(match self.attributes.area_light {
+                Some((radiance, two_sided)) => {
+                    let mut light = DiffuseAreaLight::new(Arc::clone(&shape), radiance);
+                    light.two_sided = two_sided;
+                    Primitive::emissive(shape, material.clone(), light)
+                }
+                None => Primitive::new(shape, material.clone()),
+            });
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Pbrt<T, U>, SceneError> {
+        let width = self.film.number("xresolution").unwrap_or(1280.).max(1.) as usize;
+        let height = self.film.number("yresolution").unwrap_or(720.).max(1.) as usize;
+        let aspect_ratio = T::from(width).unwrap() / T::from(height).unwrap();
+
+        let camera = self.camera.map(|(world_to_camera, params)| {
+            // The field of view spans the shorter side of the image
+            let half = T::from(0.5).unwrap();
+            let fov = params.float::<T>("fov", 90.).to_radians();
+            let fov_y = if aspect_ratio >= T::one() {
+                fov
+            } else {
+                ((fov * half).tan() / aspect_ratio).atan() / half
+            };
+            // pbrt's cameras look down +z, and rt3's down -z
+            let mut flip = identity();
+            flip[2][2] = -1.;
+            let flip: Transform3<T, CameraSpace, U> = matrix::<T, U>(flip, 0).unwrap().cast_unit();
+            let camera_to_world = flip * world_to_camera.inverse();
+            let mut camera =
+                ThinLensCamera::new(camera_to_world, Angle::from_radians(fov_y), aspect_ratio);
+            camera.lens_radius = params.float("lensradius", 0.);
+            camera.focal_distance = params.float("focaldistance", 1e6);
+            camera
+        });
+
+        let bounds = self
+            .primitives
+            .iter()
+            .fold(Box3::empty(), |bounds, primitive| {
+                bounds.union(&primitive.bounds())
+            });
+        Ok(Pbrt {
+            primitives: self.primitives,
+            lights: self.lights.into_iter().map(|light| light(bounds)).collect(),
+            camera,
+            resolution: (width, height),
+            filename: self.film.string("filename").map(String::from),
+            samples_per_pixel: self.samples_per_pixel,
+            max_depth: self.max_depth,
+        })
+    }
+}
+
+/// Returns the roughnesses of the parameters with the given prefix along the tangent and
+/// bitangent
+///
+/// pbrt-v4 maps roughnesses to the alpha of its microfacets as rt3 does, unless
+/// `remaproughness` is false and they are alpha itself.
+fn roughness<T: Float>(params: &Params, prefix: &str) -> (T, T) {
+    let number = |name: &str| params.number(&format!("{prefix}{name}"));
+    let roughness = number("roughness").unwrap_or(0.);
+    let [u, v] = [number("uroughness"), number("vroughness")].map(|r| {
+        let r = r.unwrap_or(roughness);
+        let r = if params.bool("remaproughness") == Some(false) {
+            r * r
+        } else {
+            r
+        };
+        T::from(r).unwrap()
+    });
+    (u, v)
+}
+
+fn identity() -> [[f64; 4]; 4] {
+    let mut mat = [[0.; 4]; 4];
+    for (i, row) in mat.iter_mut().enumerate() {
+        row[i] = 1.;
+    }
+    mat
+}
+
+/// Returns the factor by which a transform scales lengths, if it is a similarity
+fn similarity_scale<T: Float, U>(transform: &Transform3<T, U, U>) -> Option<T> {
+    let mat = transform.to_array();
+    let rows = [0, 1, 2].map(|i| Vector3::<T, U>::new(mat[i][0], mat[i][1], mat[i][2]));
+    let scale = rows[0].length();
+    let tolerance = T::from(1e-6).unwrap() * scale * scale;
+    let similar = rows
+        .iter()
+        .all(|row| (row.length_squared() - scale * scale).abs() <= tolerance)
+        && (0..3).all(|i| rows[i].dot(rows[(i + 1) % 3]).abs() <= tolerance);
+    (similar && scale > T::zero()).then_some(scale)
+}
+
+/// Resamples an environment image in pbrt-v4's equal-area octahedral mapping, with the
+/// directions of its light space, to the equirectangular images of [`EnvironmentLight`]
+///
+/// Returns none unless the image is square.
+fn equal_area_to_equirectangular<T: Float + FloatConst, U>(
+    image: &Image<T>,
+    world_to_light: &Transform3<T, U, U>,
+) -> Option<Image<T>> {
+    let size = image.width();
+    if size == 0 || image.height() != size {
+        return None;
+    }
+    let (width, height) = (2 * size, size);
+    let mut pixels = Vec::with_capacity(width * height);
+    for y in 0..height {
+        let theta = (y as f64 + 0.5) / height as f64 * std::f64::consts::PI;
+        for x in 0..width {
+            let phi = (x as f64 + 0.5) / width as f64 * std::f64::consts::TAU;
+            let cast = |c: f64| T::from(c).unwrap();
+            let (sin_theta, cos_theta) = theta.sin_cos();
+            let w: Vector3<T, U> = Vector3::new(
+                cast(sin_theta * phi.cos()),
+                cast(sin_theta * phi.sin()),
+                cast(cos_theta),
+            );
+            let d = world_to_light.transform(w).normalize();
+            let d = [d.x, d.y, d.z].map(|c| c.to_f64().unwrap_or(0.));
+            let d = Vector3::<f64, U>::new(d[0], d[1], d[2]);
+
+            // Clarberg, "Fast Equal-Area Mapping of the (Hemi)Sphere using SIMD" (2008)
+            let (ax, ay) = (d.x.abs(), d.y.abs());
+            let r = (1. - d.z.abs()).max(0.).sqrt();
+            let (a, b) = (ax.max(ay), ax.min(ay));
+            let b = if a == 0. { 0. } else { b / a };
+            let mut phi = b.atan() * std::f64::consts::FRAC_2_PI;
+            if ax < ay {
+                phi = 1. - phi;
+            }
+            let mut v = phi * r;
+            let mut u = r - v;
+            if d.z < 0. {
+                (u, v) = (1. - v, 1. - u);
+            }
+            let (u, v) = (u.copysign(d.x), v.copysign(d.y));
+            let texel = |t: f64| (((t + 1.) * 0.5 * size as f64) as usize).min(size - 1);
+            pixels.push(*image.get(Point2::new(texel(u), texel(v)))?);
+        }
+    }
+    Some(Image::new(width, height, pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        camera::{Camera, CameraSample},
+        core::geometry::UnknownUnit,
+    };
+
+    #[test]
+    fn test_read_pbrt() {
+        let scene = r#"
+            # A quad light over a ground plane, with two instances of a sphere
+            LookAt 0 1 5  0 1 0  0 1 0
+            Camera "perspective" "float fov" [ 45 ]
+            Film "rgb" "integer xresolution" 400 "integer yresolution" 200
+                "string filename" "quad.exr"
+            Sampler "halton" "integer pixelsamples" 64
+            WorldBegin
+            MakeNamedMaterial "gold" "string type" "conductor"
+                "spectrum eta" "metal-Au-eta" "spectrum k" "metal-Au-k"
+            AttributeBegin
+                AreaLightSource "diffuse" "blackbody L" 6500 "float scale" 10
+                Translate 0 3 0
+                Shape "bilinearmesh" "point3 P" [ -1 0 -1  1 0 -1  -1 0 1  1 0 1 ]
+            AttributeEnd
+            Material "diffuse" "rgb reflectance" [ 0.2 0.4 0.6 ]
+            Shape "trianglemesh" "point3 P" [ -5 0 -5  5 0 -5  5 0 5 ] "integer indices" [ 0 2 1 ]
+            ObjectBegin "ball"
+                NamedMaterial "gold"
+                Scale 2 1 1
+                Shape "sphere" "float radius" 0.5
+            ObjectEnd
+            Translate 0 1 0
+            ObjectInstance "ball"
+            Rotate 90 0 0 1
+            Translate 3 0 0
+            ObjectInstance "ball"
+            LightSource "distant" "point3 to" [ 0 -1 0 ]
+        "#;
+        let pbrt = Pbrt::<f64, UnknownUnit>::read(scene.as_bytes(), "").unwrap();
+        assert_eq!(pbrt.resolution, (400, 200));
+        assert_eq!(pbrt.filename.as_deref(), Some("quad.exr"));
+        assert_eq!(pbrt.samples_per_pixel, 64);
+        assert_eq!(pbrt.lights.len(), 1);
+
+        // Two triangles of the light, one of the ground and two instances
+        assert_eq!(pbrt.primitives.len(), 5);
+        let lights: Vec<_> = pbrt
+            .primitives
+            .iter()
+            .filter_map(Primitive::area_light)
+            .collect();
+        assert_eq!(lights.len(), 2);
+        let radiance = lights[0].radiance;
+        assert!((radiance.luminance() - 10.).abs() < 0.1);
+        let bounds = pbrt.primitives[4].bounds();
+        assert!((bounds.min - Point3::new(-0.5, 3., -0.5)).length() < 1e-9);
+        assert!((bounds.max - Point3::new(0.5, 5., 0.5)).length() < 1e-9);
+
+        // pbrt's cameras have +x to the right of the image, which is -x in the world here
+        let camera = pbrt.camera.unwrap();
+        let ray = |x| {
+            let sample = CameraSample {
+                film: Point2::new(x, 0.5),
+                lens: Point2::new(0.5, 0.5),
+                time: 0.,
+            };
+            camera.generate_ray(&sample)
+        };
+        assert!((ray(0.5).origin - Point3::new(0., 1., 5.)).length() < 1e-9);
+        assert!((ray(0.5).dir - Vector3::new(0., 0., -1.)).length() < 1e-9);
+        // The field of view of 45° is vertical, across the shorter side
+        let tan = ray(1.).dir.x / ray(1.).dir.z;
+        assert!((tan - 2. * 22.5f64.to_radians().tan()).abs() < 1e-9);
+
+        let error = Pbrt::<f64, UnknownUnit>::read(&b"WorldBegin\nShap \"sphere\""[..], "");
+        assert!(matches!(error, Err(SceneError::Parse { line: 2, .. })));
+    }
+}
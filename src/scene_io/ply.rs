@@ -0,0 +1,341 @@
+use crate::{
+    core::geometry::{Point2, Point3, Vector3},
+    scene_io::SceneError,
+    shape::TriangleMesh,
+};
+use num_traits::Float;
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Format {
+    Ascii,
+    LittleEndian,
+    BigEndian,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "char" | "int8" => Self::I8,
+            "uchar" | "uint8" => Self::U8,
+            "short" | "int16" => Self::I16,
+            "ushort" | "uint16" => Self::U16,
+            "int" | "int32" => Self::I32,
+            "uint" | "uint32" => Self::U32,
+            "float" | "float32" => Self::F32,
+            "double" | "float64" => Self::F64,
+            _ => return None,
+        })
+    }
+
+    #[inline]
+    fn size(self) -> usize {
+        match self {
+            Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::F64 => 8,
+        }
+    }
+}
+
+/// A property of an element, with the type of its length if it is a list
+#[derive(Debug)]
+struct Property {
+    name: String,
+    scalar: Scalar,
+    list: Option<Scalar>,
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl Element {
+    fn property(&self, names: &[&str]) -> Option<usize> {
+        self.properties
+            .iter()
+            .position(|property| property.list.is_none() && names.contains(&&*property.name))
+    }
+}
+
+/// Reads the values of the body of a PLY file one at a time
+struct Values<R> {
+    reader: R,
+    format: Format,
+    /// The tokens left on the current line of an ASCII file, and its number
+    tokens: std::vec::IntoIter<String>,
+    line: usize,
+}
+
+impl<R: BufRead> Values<R> {
+    fn next(&mut self, scalar: Scalar) -> Result<f64, SceneError> {
+        if self.format == Format::Ascii {
+            let token = loop {
+                if let Some(token) = self.tokens.next() {
+                    break token;
+                }
+                let mut line = String::new();
+                if self.reader.read_line(&mut line)? == 0 {
+                    return Err(SceneError::InvalidFormat);
+                }
+                self.line += 1;
+                let tokens: Vec<_> = line.split_whitespace().map(String::from).collect();
+                self.tokens = tokens.into_iter();
+            };
+            return token
+                .parse()
+                .map_err(|_| SceneError::parse(self.line, format!("invalid number `{token}`")));
+        }
+
+        let mut bytes = [0; 8];
+        let bytes = &mut bytes[..scalar.size()];
+        self.reader.read_exact(bytes)?;
+        if self.format == Format::BigEndian {
+            bytes.reverse();
+        }
+        let array = |bytes: &[u8]| {
+            let mut array = [0; 8];
+            array[..bytes.len()].copy_from_slice(bytes);
+            array
+        };
+        let [b0, b1, b2, b3, ..] = array(bytes);
+        Ok(match scalar {
+            Scalar::I8 => f64::from(b0 as i8),
+            Scalar::U8 => f64::from(b0),
+            Scalar::I16 => f64::from(i16::from_le_bytes([b0, b1])),
+            Scalar::U16 => f64::from(u16::from_le_bytes([b0, b1])),
+            Scalar::I32 => f64::from(i32::from_le_bytes([b0, b1, b2, b3])),
+            Scalar::U32 => f64::from(u32::from_le_bytes([b0, b1, b2, b3])),
+            Scalar::F32 => f64::from(f32::from_le_bytes([b0, b1, b2, b3])),
+            Scalar::F64 => f64::from_le_bytes(array(bytes)),
+        })
+    }
+}
+
+impl<T: Float, U> TriangleMesh<T, U> {
+    /// Reads a mesh from a PLY file, in ASCII or binary
+    ///
+    /// Vertices keep their normals, from `nx`, `ny` and `nz`, and their texture coordinates,
+    /// from `u` and `v` or `s` and `t`, if they have them. Faces are split into fans of
+    /// triangles. Other elements and properties are skipped.
+    pub fn read_ply(mut reader: impl BufRead) -> Result<Self, SceneError> {
+        let mut line = String::new();
+        let mut number = 0;
+        let mut next_line = |line: &mut String| {
+            line.clear();
+            number += 1;
+            match reader.read_line(line) {
+                Ok(0) => Err(SceneError::parse(number, "missing `end_header`")),
+                Ok(_) => Ok(number),
+                Err(e) => Err(e.into()),
+            }
+        };
+
+        next_line(&mut line)?;
+        if line.trim_end() != "ply" {
+            return Err(SceneError::InvalidFormat);
+        }
+        let mut format = None;
+        let mut elements: Vec<Element> = Vec::new();
+        loop {
+            let number = next_line(&mut line)?;
+            let mut tokens = line.split_whitespace();
+            let invalid = || SceneError::parse(number, "invalid header line");
+            match tokens.next() {
+                Some("format") => {
+                    format = Some(match tokens.next() {
+                        Some("ascii") => Format::Ascii,
+                        Some("binary_little_endian") => Format::LittleEndian,
+                        Some("binary_big_endian") => Format::BigEndian,
+                        _ => return Err(invalid()),
+                    });
+                }
+                Some("element") => {
+                    let name = tokens.next().ok_or_else(invalid)?;
+                    let count = tokens.next().and_then(|count| count.parse().ok());
+                    elements.push(Element {
+                        name: name.to_owned(),
+                        count: count.ok_or_else(invalid)?,
+                        properties: Vec::new(),
+                    });
+                }
+                Some("property") => {
+                    let element = elements.last_mut().ok_or_else(invalid)?;
+                    let scalar = |token: Option<&str>| token.and_then(Scalar::parse);
+                    let (list, scalar) = match tokens.next() {
+                        Some("list") => {
+                            let list = scalar(tokens.next()).ok_or_else(invalid)?;
+                            (Some(list), scalar(tokens.next()))
+                        }
+                        token => (None, scalar(token)),
+                    };
+                    element.properties.push(Property {
+                        scalar: scalar.ok_or_else(invalid)?,
+                        list,
+                        name: tokens.next().ok_or_else(invalid)?.to_owned(),
+                    });
+                }
+                Some("end_header") => break,
+                Some("comment" | "obj_info") | None => {}
+                Some(_) => return Err(invalid()),
+            }
+        }
+        let format = format.ok_or_else(|| SceneError::parse(number, "missing `format`"))?;
+
+        let mut values = Values {
+            reader,
+            format,
+            tokens: Vec::new().into_iter(),
+            line: number,
+        };
+        let mut mesh = Self::new(Vec::new(), Vec::new());
+        let mut normals = Vec::new();
+        let mut uvs = Vec::new();
+        let mut row = Vec::new();
+        for element in &elements {
+            let is_vertex = element.name == "vertex";
+            let position = [&["x"][..], &["y"], &["z"]].map(|names| element.property(names));
+            let normal = [&["nx"][..], &["ny"], &["nz"]].map(|names| element.property(names));
+            let uv = [&["u", "s", "texture_u"][..], &["v", "t", "texture_v"]]
+                .map(|names| element.property(names));
+            let indices = element.properties.iter().position(|property| {
+                property.list.is_some()
+                    && ["vertex_indices", "vertex_index"].contains(&&*property.name)
+            });
+            if is_vertex && position.contains(&None) {
+                return Err(SceneError::Unsupported("vertices without positions".into()));
+            }
+
+            for _ in 0..element.count {
+                row.clear();
+                let mut face = Vec::new();
+                for (i, property) in element.properties.iter().enumerate() {
+                    let Some(count) = property.list else {
+                        row.push(values.next(property.scalar)?);
+                        continue;
+                    };
+                    row.push(f64::NAN);
+                    let count = values.next(count)?;
+                    for _ in 0..count as usize {
+                        let value = values.next(property.scalar)?;
+                        if element.name == "face" && Some(i) == indices {
+                            face.push(value);
+                        }
+                    }
+                }
+
+                let get = |index: Option<usize>| T::from(row[index.unwrap()]).unwrap();
+                if is_vertex {
+                    let [x, y, z] = position.map(get);
+                    mesh.positions.push(Point3::new(x, y, z));
+                    if !normal.contains(&None) {
+                        let [x, y, z] = normal.map(get);
+                        normals.push(Vector3::new(x, y, z).to_normal());
+                    }
+                    if !uv.contains(&None) {
+                        let [u, v] = uv.map(get);
+                        uvs.push(Point2::new(u, v));
+                    }
+                } else if element.name == "face" && face.len() >= 3 {
+                    let index = |i: f64| {
+                        (i >= 0. && i.fract() == 0. && i <= f64::from(u32::MAX))
+                            .then_some(i as u32)
+                            .ok_or(SceneError::InvalidFormat)
+                    };
+                    for i in 1..face.len() - 1 {
+                        let triangle = [index(face[0])?, index(face[i])?, index(face[i + 1])?];
+                        mesh.indices.push(triangle);
+                    }
+                }
+            }
+        }
+
+        let vertex_count = mesh.positions.len();
+        if mesh
+            .indices
+            .iter()
+            .flatten()
+            .any(|&i| i as usize >= vertex_count)
+        {
+            return Err(SceneError::InvalidFormat);
+        }
+        mesh.normals = (!normals.is_empty()).then_some(normals);
+        mesh.uvs = (!uvs.is_empty()).then_some(uvs);
+        Ok(mesh)
+    }
+
+    /// Reads a mesh from the PLY file at `path`, see [`read_ply`](Self::read_ply)
+    pub fn load_ply(path: impl AsRef<Path>) -> Result<Self, SceneError> {
+        Self::read_ply(BufReader::new(File::open(path)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::geometry::UnknownUnit;
+
+    #[test]
+    fn test_read_ply() {
+        let header = |format: &str| {
+            format!(
+                "ply\nformat {format} 1.0\ncomment a unit square\nelement vertex 4\n\
+                 property float x\nproperty float y\nproperty float z\nproperty float nx\n\
+                 property float ny\nproperty float nz\nproperty uchar red\n\
+                 element face 1\nproperty list uchar int vertex_indices\nend_header\n"
+            )
+        };
+        let vertices = [[0., 0.], [1., 0.], [1., 1.], [0., 1.]];
+
+        let mut ascii = header("ascii");
+        for [x, y] in vertices {
+            ascii.push_str(&format!("{x} {y} 0 0 0 1 255\n"));
+        }
+        ascii.push_str("4 0 1 2 3\n");
+        let mut binary = header("binary_big_endian").into_bytes();
+        for [x, y] in vertices {
+            for value in [x, y, 0., 0., 0., 1.] {
+                binary.extend_from_slice(&f32::to_be_bytes(value));
+            }
+            binary.push(255);
+        }
+        binary.push(4);
+        for i in 0..4 {
+            binary.extend_from_slice(&i32::to_be_bytes(i));
+        }
+
+        for data in [ascii.as_bytes(), &binary] {
+            let mesh = TriangleMesh::<f64, UnknownUnit>::read_ply(data).unwrap();
+            assert_eq!(mesh.positions[2], Point3::new(1., 1., 0.));
+            assert_eq!(mesh.indices, [[0, 1, 2], [0, 2, 3]]);
+            let n = Vector3::new(0., 0., 1.).to_normal();
+            assert_eq!(mesh.normals.as_deref(), Some(&[n; 4][..]));
+            assert!(mesh.uvs.is_none());
+        }
+
+        // Faces must index existing vertices
+        let truncated = ascii.replace("4 0 1 2 3", "3 0 1 4");
+        let error = TriangleMesh::<f64, UnknownUnit>::read_ply(truncated.as_bytes());
+        assert!(matches!(error, Err(SceneError::InvalidFormat)));
+    }
+}
@@ -0,0 +1,1198 @@
+use crate::{
+    accel::Bvh,
+    bsdf::{Bsdf, PrincipledBsdf, PrincipledParameters},
+    color::Rgb,
+    core::geometry::{
+        transform::{Transform3, Transformation},
+        Box3, Point2, Point3, Vector3,
+    },
+    image::Image,
+    material::Material,
+    scene::{Instance, Primitive, Prototype, SceneGraph},
+    scene_io::SceneError,
+    shape::{BoxShape, Capsule, Shape, Sphere, SurfaceInteraction, Triangle, TriangleMesh},
+    texture::{ImageTexture, Texture, WrapMode},
+};
+use num_traits::{Float, FloatConst};
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::File,
+    io::Read,
+    iter::Peekable,
+    path::{Path, PathBuf},
+    str::Chars,
+    sync::Arc,
+};
+
+/// A USD stage in the text format, usda
+///
+/// The defined prims of the layer become a scene graph, placed by their transform operations.
+/// Meshes, spheres, cubes and capsules carry instances with the `UsdPreviewSurface` materials
+/// bound to them or their ancestors, whose diffuse color, roughness and metallic inputs may be
+/// read from image textures; meshes without a material keep their display color.
+///
+/// Only the layer itself is read: references, payloads, sublayers, variants and inherits are
+/// not composed, and classes and overs are skipped. Attributes with time samples take their
+/// earliest one, subdivision surfaces are rendered as their control meshes, and points keep the
+/// coordinates they are authored in whatever the up axis of the stage. Binary crate files and
+/// USDZ packages are not supported.
+pub struct Usd<T, U> {
+    pub graph: SceneGraph<T, U, U>,
+}
+
+impl<T: fmt::Debug, U> fmt::Debug for Usd<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Usd").field("graph", &self.graph).finish()
+    }
+}
+
+impl<T, U> Usd<T, U>
+where
+    T: Float + FloatConst + Send + Sync + 'static,
+    U: Send + Sync + 'static,
+{
+    /// Reads a usda layer, looking up the textures it refers to in `dir`
+    ///
+    /// PNG and JPEG textures need the features of the same names.
+    pub fn read(mut reader: impl Read, dir: impl AsRef<Path>) -> Result<Self, SceneError> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        if data.starts_with(b"PXR-USDC") {
+            return Err(SceneError::Unsupported("binary USD crate files".into()));
+        }
+        if data.starts_with(b"PK") {
+            return Err(SceneError::Unsupported("USDZ packages".into()));
+        }
+        if !data.starts_with(b"#usda") {
+            return Err(SceneError::InvalidFormat);
+        }
+        let text = String::from_utf8(data).map_err(|_| SceneError::InvalidFormat)?;
+        let prims = Parser::new(tokenize(&text)?).layer()?;
+        Stage::new(&prims, dir.as_ref()).build()
+    }
+
+    /// Reads the usda layer at `path`, see [`read`](Self::read)
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SceneError> {
+        let path = path.as_ref();
+        let dir = path.parent().unwrap_or(Path::new(""));
+        Self::read(File::open(path)?, dir)
+    }
+
+    /// Returns the instances of the stage placed in the world, as primitives
+    pub fn primitives(&mut self) -> Result<Vec<Primitive<T, U>>, SceneError> {
+        self.graph
+            .flatten()
+            .into_iter()
+            .map(|instance| Ok(instance.try_into()?))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// An identifier or keyword, with the namespaces and suffixes of property names
+    Word(String),
+    String(String),
+    Number(f64),
+    /// An asset path between `@`s
+    Asset(String),
+    /// A path to a prim or property between `<` and `>`
+    Path(String),
+    Punct(char),
+}
+
+/// Splits a layer into tokens, with the numbers of their lines
+fn tokenize(text: &str) -> Result<Vec<(usize, Token)>, SceneError> {
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let start = line;
+        let token = match c {
+            '\n' => {
+                line += 1;
+                continue;
+            }
+            '#' => {
+                while chars.next_if(|&c| c != '\n').is_some() {}
+                continue;
+            }
+            c if c.is_whitespace() => continue,
+            '"' | '\'' => {
+                let mut s = literal(&mut chars, c, &mut line)?;
+                // Triple quotes start with an empty string, and may span lines
+                if s.is_empty() && chars.next_if_eq(&c).is_some() {
+                    s = loop {
+                        let part = literal(&mut chars, c, &mut line)?;
+                        if chars.next_if_eq(&c).is_some() && chars.next_if_eq(&c).is_some() {
+                            s.push_str(&part);
+                            break s;
+                        }
+                        s.push_str(&part);
+                        s.push(c);
+                    };
+                }
+                Token::String(s)
+            }
+            '@' => Token::Asset(literal(&mut chars, '@', &mut line)?),
+            '<' => Token::Path(literal(&mut chars, '>', &mut line)?),
+            c if c.is_alphabetic() || c == '_' => {
+                let mut word = String::from(c);
+                while let Some(c) =
+                    chars.next_if(|&c| c.is_alphanumeric() || matches!(c, '_' | ':' | '.'))
+                {
+                    word.push(c);
+                }
+                Token::Word(word)
+            }
+            c if c.is_ascii_digit() || matches!(c, '-' | '+' | '.') => {
+                let mut word = String::from(c);
+                while let Some(c) =
+                    chars.next_if(|&c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+'))
+                {
+                    word.push(c);
+                }
+                let number = word
+                    .parse()
+                    .map_err(|_| SceneError::parse(line, format!("invalid number `{word}`")))?;
+                Token::Number(number)
+            }
+            c => Token::Punct(c),
+        };
+        tokens.push((start, token));
+    }
+    Ok(tokens)
+}
+
+/// Reads the rest of a literal up to the character `end`, counting the lines it spans
+fn literal(
+    chars: &mut Peekable<Chars<'_>>,
+    end: char,
+    line: &mut usize,
+) -> Result<String, SceneError> {
+    let start = *line;
+    let mut s = String::new();
+    loop {
+        match chars.next() {
+            Some(c) if c == end => return Ok(s),
+            Some('\\') if end == '"' || end == '\'' => s.extend(chars.next()),
+            Some(c) => {
+                *line += usize::from(c == '\n');
+                s.push(c);
+            }
+            None => return Err(SceneError::parse(start, "unterminated literal")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Number(f64),
+    /// A string or a token
+    String(String),
+    Asset(String),
+    Path(String),
+    /// A tuple or an array
+    List(Vec<Value>),
+    /// A dictionary, `None` or another value that is not read
+    Other,
+}
+
+impl Value {
+    fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Self::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) | Self::Asset(s) | Self::Path(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn elements(&self) -> &[Self] {
+        match self {
+            Self::List(elements) => elements,
+            _ => std::slice::from_ref(self),
+        }
+    }
+
+    /// Returns all numbers of the value, with those of tuples in a row
+    fn numbers(&self) -> Vec<f64> {
+        match self {
+            Self::Number(n) => vec![*n],
+            Self::List(elements) => elements.iter().flat_map(Self::numbers).collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Attribute {
+    name: String,
+    value: Option<Value>,
+    /// The time samples, of which the earliest is used if there is no default value
+    samples: Vec<(f64, Value)>,
+    connection: Option<String>,
+    interpolation: Option<String>,
+}
+
+impl Attribute {
+    fn value(&self) -> Option<&Value> {
+        let earliest = self
+            .samples
+            .iter()
+            .min_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, value)| value);
+        self.value.as_ref().or(earliest)
+    }
+}
+
+#[derive(Debug)]
+struct Prim {
+    specifier: String,
+    type_name: String,
+    path: String,
+    /// The line of the name of the prim, which errors in its values are reported at
+    line: usize,
+    active: bool,
+    attributes: Vec<Attribute>,
+    relationships: Vec<(String, Vec<String>)>,
+    children: Vec<Prim>,
+}
+
+impl Prim {
+    fn attribute(&self, name: &str) -> Option<&Attribute> {
+        self.attributes.iter().find(|a| a.name == name)
+    }
+
+    fn value(&self, name: &str) -> Option<&Value> {
+        self.attribute(name).and_then(Attribute::value)
+    }
+
+    fn number(&self, name: &str) -> Option<f64> {
+        self.value(name).and_then(Value::as_f64)
+    }
+
+    fn attribute_mut(&mut self, name: &str) -> &mut Attribute {
+        let index = match self.attributes.iter().position(|a| a.name == name) {
+            Some(index) => index,
+            None => {
+                self.attributes.push(Attribute {
+                    name: name.to_owned(),
+                    ..Attribute::default()
+                });
+                self.attributes.len() - 1
+            }
+        };
+        &mut self.attributes[index]
+    }
+
+    /// Returns the targets of a relationship, resolved against the path of the prim
+    fn targets<'a>(&'a self, name: &'a str) -> impl Iterator<Item = String> + 'a {
+        self.relationships
+            .iter()
+            .filter(move |(n, _)| n == name)
+            .flat_map(|(_, targets)| targets)
+            .map(|target| resolve(&self.path, target))
+    }
+}
+
+/// Resolves a path relative to that of a prim, such as `../Looks/Wood`, to an absolute one
+fn resolve(prim: &str, path: &str) -> String {
+    if path.starts_with('/') {
+        return path.to_owned();
+    }
+    let mut components: Vec<&str> = prim.split('/').filter(|c| !c.is_empty()).collect();
+    for component in path.split('/') {
+        match component {
+            ".." => {
+                components.pop();
+            }
+            "." | "" => {}
+            component => components.push(component),
+        }
+    }
+    format!("/{}", components.join("/"))
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn new(tokens: Vec<(usize, Token)>) -> Self {
+        Self { tokens, pos: 0 }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, token)| token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        self.pos += 1;
+        token
+    }
+
+    /// Returns the line of the next token, or of the last one at the end of the layer
+    fn line(&self) -> usize {
+        match self.tokens.get(self.pos.min(self.tokens.len().max(1) - 1)) {
+            Some((line, _)) => *line,
+            None => 1,
+        }
+    }
+
+    fn error(&self, message: &str) -> SceneError {
+        SceneError::parse(self.line(), message)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(&Token::Punct(c));
+        self.pos += usize::from(found);
+        found
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), SceneError> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{c}`")))
+        }
+    }
+
+    fn word(&mut self) -> Result<String, SceneError> {
+        match self.peek() {
+            Some(Token::Word(word)) => {
+                let word = word.clone();
+                self.pos += 1;
+                Ok(word)
+            }
+            _ => Err(self.error("expected a name")),
+        }
+    }
+
+    /// Skips the rest of a bracketed block, whose opening bracket has been read
+    fn skip_block(&mut self, close: char) -> Result<(), SceneError> {
+        let open = match close {
+            '}' => '{',
+            ')' => '(',
+            _ => '[',
+        };
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next() {
+                Some(Token::Punct(c)) if c == open => depth += 1,
+                Some(Token::Punct(c)) if c == close => depth -= 1,
+                Some(_) => {}
+                None => return Err(self.error("unterminated block")),
+            }
+        }
+        Ok(())
+    }
+
+    fn layer(&mut self) -> Result<Vec<Prim>, SceneError> {
+        if self.eat('(') {
+            self.metadata()?;
+        }
+        let mut prims = Vec::new();
+        while self.peek().is_some() {
+            let specifier = self.word()?;
+            prims.push(self.prim(specifier, "")?);
+        }
+        Ok(prims)
+    }
+
+    /// Reads metadata between parentheses, whose opening one has been read
+    fn metadata(&mut self) -> Result<Vec<(String, Value)>, SceneError> {
+        let mut entries = Vec::new();
+        loop {
+            match self.next() {
+                Some(Token::Punct(')')) => return Ok(entries),
+                Some(Token::Punct(';')) | Some(Token::String(_)) => {}
+                Some(Token::Word(word)) => {
+                    let key = match &*word {
+                        "prepend" | "append" | "add" | "delete" | "reorder" => self.word()?,
+                        _ => word,
+                    };
+                    if self.eat('=') {
+                        entries.push((key, self.value()?));
+                    }
+                }
+                _ => return Err(self.error("invalid metadata")),
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, SceneError> {
+        let value = match self.next() {
+            Some(Token::Number(n)) => Value::Number(n),
+            Some(Token::String(s)) => Value::String(s),
+            Some(Token::Path(p)) => Value::Path(p),
+            Some(Token::Asset(a)) => {
+                // References follow the asset with the path of a prim in it
+                if let Some(Token::Path(_)) = self.peek() {
+                    self.pos += 1;
+                }
+                Value::Asset(a)
+            }
+            Some(Token::Word(w)) => match &*w {
+                "inf" => Value::Number(f64::INFINITY),
+                "-inf" => Value::Number(f64::NEG_INFINITY),
+                "nan" => Value::Number(f64::NAN),
+                "true" => Value::Number(1.),
+                "false" => Value::Number(0.),
+                "None" => Value::Other,
+                _ => Value::String(w),
+            },
+            Some(Token::Punct(open @ ('(' | '['))) => {
+                let close = if open == '(' { ')' } else { ']' };
+                let mut elements = Vec::new();
+                while !self.eat(close) {
+                    elements.push(self.value()?);
+                    if !self.eat(',') {
+                        self.expect(close)?;
+                        break;
+                    }
+                }
+                Value::List(elements)
+            }
+            Some(Token::Punct('{')) => {
+                self.skip_block('}')?;
+                Value::Other
+            }
+            _ => {
+                self.pos -= 1;
+                return Err(self.error("expected a value"));
+            }
+        };
+        Ok(value)
+    }
+
+    /// Reads a prim, whose specifier has been read, under the prim at `parent`
+    fn prim(&mut self, specifier: String, parent: &str) -> Result<Prim, SceneError> {
+        if !matches!(&*specifier, "def" | "over" | "class") {
+            return Err(self.error("expected a prim"));
+        }
+        let type_name = match self.peek() {
+            Some(Token::Word(_)) => self.word()?,
+            _ => String::new(),
+        };
+        let line = self.line();
+        let Some(Token::String(name)) = self.next() else {
+            return Err(self.error("expected the name of a prim"));
+        };
+        let mut prim = Prim {
+            specifier,
+            type_name,
+            path: format!("{parent}/{name}"),
+            line,
+            active: true,
+            attributes: Vec::new(),
+            relationships: Vec::new(),
+            children: Vec::new(),
+        };
+        if self.eat('(') {
+            let metadata = self.metadata()?;
+            prim.active = !metadata
+                .iter()
+                .any(|(key, value)| key == "active" && *value == Value::Number(0.));
+        }
+        self.expect('{')?;
+        while !self.eat('}') {
+            let word = match self.next() {
+                Some(Token::Word(word)) => word,
+                Some(Token::Punct(';')) => continue,
+                _ => {
+                    self.pos -= 1;
+                    return Err(self.error("expected a prim or property"));
+                }
+            };
+            match &*word {
+                "def" | "over" | "class" => {
+                    let child = self.prim(word, &prim.path.clone())?;
+                    prim.children.push(child);
+                }
+                "variantSet" => {
+                    self.next();
+                    self.expect('=')?;
+                    self.expect('{')?;
+                    self.skip_block('}')?;
+                }
+                _ => self.property(&mut prim, word)?,
+            }
+        }
+        Ok(prim)
+    }
+
+    /// Reads a property, whose first word has been read
+    fn property(&mut self, prim: &mut Prim, mut word: String) -> Result<(), SceneError> {
+        if matches!(&*word, "prepend" | "append" | "add" | "delete" | "reorder") {
+            word = self.word()?;
+        }
+        if word == "rel" {
+            let name = self.word()?;
+            let mut targets = Vec::new();
+            if self.eat('=') {
+                let value = self.value()?;
+                let paths = value.elements().iter().filter_map(|v| match v {
+                    Value::Path(path) => Some(path.clone()),
+                    _ => None,
+                });
+                targets.extend(paths);
+            }
+            if self.eat('(') {
+                self.metadata()?;
+            }
+            prim.relationships.push((name, targets));
+            return Ok(());
+        }
+
+        while matches!(&*word, "custom" | "uniform" | "varying" | "config") {
+            word = self.word()?;
+        }
+        // The type, and whether it is an array
+        if self.eat('[') {
+            self.expect(']')?;
+        }
+        let name = self.word()?;
+        if let Some(name) = name.strip_suffix(".timeSamples") {
+            self.expect('=')?;
+            self.expect('{')?;
+            let mut samples = Vec::new();
+            while !self.eat('}') {
+                let Some(Token::Number(time)) = self.next() else {
+                    return Err(self.error("expected a time"));
+                };
+                self.expect(':')?;
+                samples.push((time, self.value()?));
+                self.eat(',');
+            }
+            prim.attribute_mut(name).samples = samples;
+        } else if let Some(name) = name.strip_suffix(".connect") {
+            self.expect('=')?;
+            let value = self.value()?;
+            let target = value.elements().iter().find_map(|v| match v {
+                Value::Path(path) => Some(resolve(&prim.path, path)),
+                _ => None,
+            });
+            prim.attribute_mut(name).connection = target;
+        } else {
+            let value = if self.eat('=') {
+                Some(self.value()?)
+            } else {
+                None
+            };
+            let attribute = prim.attribute_mut(&name);
+            attribute.value = value.or(attribute.value.take());
+        }
+        if self.eat('(') {
+            let metadata = self.metadata()?;
+            let interpolation = metadata
+                .into_iter()
+                .find(|(key, _)| key == "interpolation")
+                .and_then(|(_, value)| value.as_str().map(String::from));
+            let base = name.split(".connect").next().unwrap_or(&name);
+            let base = base.split(".timeSamples").next().unwrap_or(base);
+            prim.attribute_mut(base).interpolation = interpolation;
+        }
+        Ok(())
+    }
+}
+
+/// The prims of a layer by their paths, with the materials translated so far
+struct Stage<'a, T, U> {
+    roots: &'a [Prim],
+    prims: HashMap<&'a str, &'a Prim>,
+    dir: &'a Path,
+    materials: HashMap<String, Arc<dyn Material<T, U>>>,
+}
+
+impl<'a, T, U> Stage<'a, T, U>
+where
+    T: Float + FloatConst + Send + Sync + 'static,
+    U: Send + Sync + 'static,
+{
+    fn new(roots: &'a [Prim], dir: &'a Path) -> Self {
+        let mut prims = HashMap::new();
+        let mut stack: Vec<&Prim> = roots.iter().collect();
+        while let Some(prim) = stack.pop() {
+            prims.insert(&*prim.path, prim);
+            stack.extend(&prim.children);
+        }
+        Self {
+            roots,
+            prims,
+            dir,
+            materials: HashMap::new(),
+        }
+    }
+
+    fn build(mut self) -> Result<Usd<T, U>, SceneError> {
+        let mut graph = SceneGraph::new();
+        let mut stack: Vec<_> = self.roots.iter().rev().map(|p| (p, None, None)).collect();
+        while let Some((prim, parent, binding)) = stack.pop() {
+            if prim.specifier != "def" || !prim.active {
+                continue;
+            }
+            let transform = xform_ops(prim)?;
+            let reset = prim
+                .value("xformOpOrder")
+                .map(Value::elements)
+                .and_then(|ops| ops.first())
+                .is_some_and(|op| op.as_str() == Some("!resetXformStack!"));
+            let id = graph.add_node(if reset { None } else { parent }, transform);
+            let binding = prim.targets("material:binding").next().or(binding);
+
+            if let Some(shape) = self.shape(prim)? {
+                let material = match &binding {
+                    Some(path) => Some(self.material(path)?),
+                    None => None,
+                };
+                let material = material.unwrap_or_else(|| {
+                    let color = prim.value("primvars:displayColor").map(Value::numbers);
+                    let color = match color.as_deref() {
+                        Some([r, g, b, ..]) => Rgb::new(*r, *g, *b),
+                        _ => Rgb::splat(0.18),
+                    };
+                    let color = color.map(|c| T::from(c).unwrap());
+                    Arc::new(PreviewSurface::new(PrincipledParameters::new(color)))
+                });
+                let prototype = Arc::new(Prototype::new(shape, Some(material)));
+                graph.attach(id, Instance::new(prototype, Transform3::identity()));
+            }
+            // Materials are only reached through their bindings
+            if prim.type_name != "Material" {
+                let children = prim.children.iter().rev();
+                stack.extend(children.map(|child| (child, Some(id), binding.clone())));
+            }
+        }
+        Ok(Usd { graph })
+    }
+
+    /// Returns the shape of a geometric prim in its own space, or none for other prims
+    fn shape(&self, prim: &Prim) -> Result<Option<Arc<dyn Shape<T, U> + Send + Sync>>, SceneError> {
+        let number = |name, default: f64| T::from(prim.number(name).unwrap_or(default)).unwrap();
+        let shape: Arc<dyn Shape<T, U> + Send + Sync> = match &*prim.type_name {
+            "Mesh" => {
+                let mesh = mesh(prim)?;
+                if mesh.triangle_count() == 0 {
+                    return Ok(None);
+                }
+                Arc::new(Bvh::new(Triangle::from_mesh(&Arc::new(mesh)).collect()))
+            }
+            "Sphere" => Arc::new(Sphere::new(Point3::origin(), number("radius", 1.))),
+            "Cube" => {
+                let half = number("size", 2.) / (T::one() + T::one());
+                let corner = Point3::new(half, half, half);
+                Arc::new(BoxShape::new(Box3::new(-corner, corner)))
+            }
+            "Capsule" => {
+                let half = number("height", 1.) / (T::one() + T::one());
+                let mut end = [T::zero(); 3];
+                let axis = match prim.value("axis").and_then(Value::as_str) {
+                    Some("X") => 0,
+                    Some("Y") => 1,
+                    _ => 2,
+                };
+                end[axis] = half;
+                let end = Point3::new(end[0], end[1], end[2]);
+                Arc::new(Capsule::new(-end, end, number("radius", 0.5)))
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(shape))
+    }
+
+    /// Translates the `UsdPreviewSurface` of the material at `path`, with the default one for
+    /// other shaders
+    fn material(&mut self, path: &str) -> Result<Arc<dyn Material<T, U>>, SceneError> {
+        if let Some(material) = self.materials.get(path) {
+            return Ok(Arc::clone(material));
+        }
+        let surface = self
+            .prims
+            .get(path)
+            .and_then(|material| material.attribute("outputs:surface"))
+            .and_then(|output| output.connection.as_deref())
+            .and_then(|target| self.shader(target))
+            .filter(|(shader, _)| {
+                shader.value("info:id").and_then(Value::as_str) == Some("UsdPreviewSurface")
+            });
+
+        let mut material = PreviewSurface::new(PrincipledParameters::new(Rgb::splat(
+            T::from(0.18).unwrap(),
+        )));
+        if let Some((shader, _)) = surface {
+            let input = |name: &str| shader.attribute(&format!("inputs:{name}"));
+            let number = |name, default: f64| {
+                let value = input(name).and_then(Attribute::value);
+                T::from(value.and_then(Value::as_f64).unwrap_or(default)).unwrap()
+            };
+            let params = &mut material.params;
+            if let Some(&[r, g, b]) = input("diffuseColor")
+                .and_then(Attribute::value)
+                .map(Value::numbers)
+                .as_deref()
+            {
+                params.base_color = Rgb::new(r, g, b).map(|c| T::from(c).unwrap());
+            }
+            if number("useSpecularWorkflow", 0.) == T::zero() {
+                params.metallic = number("metallic", 0.);
+            }
+            params.roughness = number("roughness", 0.5);
+            params.clearcoat = number("clearcoat", 0.);
+            params.clearcoat_gloss = T::one() - number("clearcoatRoughness", 0.01);
+            params.transmission = T::one() - number("opacity", 1.);
+            params.eta = number("ior", 1.5);
+
+            let connection = |name| input(name).and_then(|i| i.connection.as_deref());
+            material.base_color = match connection("diffuseColor") {
+                Some(target) => self.texture(target, true)?.map(|(texture, _)| texture),
+                None => None,
+            };
+            material.roughness = match connection("roughness") {
+                Some(target) => self.texture(target, false)?,
+                None => None,
+            };
+            material.metallic = match connection("metallic") {
+                Some(target) => self.texture(target, false)?,
+                None => None,
+            };
+        }
+        let material: Arc<dyn Material<T, U>> = Arc::new(material);
+        self.materials
+            .insert(path.to_owned(), Arc::clone(&material));
+        Ok(material)
+    }
+
+    /// Returns the shader prim of a connection to one of its outputs, with the output's name
+    fn shader(&self, target: &'a str) -> Option<(&'a Prim, &'a str)> {
+        let (path, output) = target.rsplit_once(".outputs:")?;
+        let (prim, output) = self
+            .prims
+            .get_key_value(path)
+            .map(|(_, prim)| (*prim, output))?;
+        let name = prim
+            .attribute(&format!("outputs:{output}"))
+            .map_or(output, |attribute| &attribute.name["outputs:".len()..]);
+        Some((prim, name))
+    }
+
+    /// Reads the `UsdUVTexture` a connection leads to, with the channel of its output, as colors
+    /// or as data
+    fn texture(
+        &self,
+        target: &'a str,
+        color: bool,
+    ) -> Result<Option<(ImageTexture<T>, usize)>, SceneError> {
+        let Some((shader, output)) = self.shader(target) else {
+            return Ok(None);
+        };
+        if shader.value("info:id").and_then(Value::as_str) != Some("UsdUVTexture") {
+            return Ok(None);
+        }
+        let file = shader.value("inputs:file").and_then(Value::as_str);
+        let Some(file) = file.filter(|file| !file.is_empty()) else {
+            return Ok(None);
+        };
+        let path: PathBuf = self.dir.join(file);
+        let mut image: Image<T> = Image::load(&path)?;
+        // Images are decoded as sRGB, which raw data and floating point images are not
+        let space = shader
+            .value("inputs:sourceColorSpace")
+            .and_then(Value::as_str);
+        let raw = space == Some("raw") || (space != Some("sRGB") && !color);
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default();
+        if raw && !["exr", "hdr"].contains(&&*extension.to_ascii_lowercase()) {
+            for p in image.pixels_mut() {
+                *p = p.to_srgb();
+            }
+        }
+        let wrap = match shader.value("inputs:wrapS").and_then(Value::as_str) {
+            Some("clamp" | "black") => WrapMode::Clamp,
+            Some("mirror") => WrapMode::Mirror,
+            _ => WrapMode::Repeat,
+        };
+        let channel = match output {
+            "g" => 1,
+            "b" => 2,
+            _ => 0,
+        };
+        Ok(Some((ImageTexture::new(image, wrap), channel)))
+    }
+}
+
+/// Returns the local transform of a prim from the transform operations in its
+/// `xformOpOrder`, of which the last is applied first
+fn xform_ops<T: Float, U>(prim: &Prim) -> Result<Transform3<T, U, U>, SceneError> {
+    let mut mat = identity();
+    let order = prim.value("xformOpOrder").map_or(&[][..], Value::elements);
+    for op in order.iter().rev() {
+        let Some(op) = op.as_str() else {
+            return Err(SceneError::InvalidFormat);
+        };
+        if op == "!resetXformStack!" {
+            continue;
+        }
+        let (invert, name) = match op.strip_prefix("!invert!") {
+            Some(name) => (true, name),
+            None => (false, op),
+        };
+        let numbers = prim.value(name).map(Value::numbers).unwrap_or_default();
+        let kind = name.strip_prefix("xformOp:").unwrap_or(name);
+        let kind = kind.split(':').next().unwrap_or(kind);
+        let mut local = identity();
+        match (kind, &*numbers) {
+            ("translate", &[x, y, z]) => local[3][..3].copy_from_slice(&[x, y, z]),
+            ("scale", &[x, y, z]) => {
+                for (i, s) in [x, y, z].into_iter().enumerate() {
+                    local[i][i] = s;
+                }
+            }
+            ("rotateX" | "rotateY" | "rotateZ", &[angle]) => {
+                local = rotation(usize::from(kind.as_bytes()[6] - b'X'), angle);
+            }
+            (_, &[x, y, z]) if kind.len() == 9 && kind.starts_with("rotate") => {
+                // The axes in the order their rotations are applied
+                let angles = [x, y, z];
+                for axis in kind[6..].bytes() {
+                    let axis = usize::from(axis.wrapping_sub(b'X'));
+                    let angle = *angles.get(axis).ok_or(SceneError::InvalidFormat)?;
+                    local = mul(local, rotation(axis, angle));
+                }
+            }
+            ("orient", &[w, x, y, z]) => {
+                let rotation = [
+                    [
+                        1. - 2. * (y * y + z * z),
+                        2. * (x * y - z * w),
+                        2. * (x * z + y * w),
+                    ],
+                    [
+                        2. * (x * y + z * w),
+                        1. - 2. * (x * x + z * z),
+                        2. * (y * z - x * w),
+                    ],
+                    [
+                        2. * (x * z - y * w),
+                        2. * (y * z + x * w),
+                        1. - 2. * (x * x + y * y),
+                    ],
+                ];
+                for i in 0..3 {
+                    for j in 0..3 {
+                        local[i][j] = rotation[j][i];
+                    }
+                }
+            }
+            // Matrices are already in the form for row vectors
+            ("transform", m) if m.len() == 16 => {
+                for (i, row) in local.iter_mut().enumerate() {
+                    row.copy_from_slice(&m[4 * i..4 * i + 4]);
+                }
+            }
+            _ => {
+                return Err(SceneError::Unsupported(format!(
+                    "transform operation `{op}`"
+                )))
+            }
+        }
+        if invert {
+            local = matrix::<T, U>(local, prim.line)?
+                .inverse()
+                .to_array()
+                .map(|row| row.map(|x| x.to_f64().unwrap_or(0.)));
+        }
+        mat = mul(mat, local);
+    }
+    matrix(mat, prim.line)
+}
+
+fn matrix<T: Float, U>(mat: [[f64; 4]; 4], line: usize) -> Result<Transform3<T, U, U>, SceneError> {
+    let mat = mat.map(|row| row.map(|x| T::from(x).unwrap()));
+    if mat.iter().flatten().any(|x| !x.is_finite()) {
+        return Err(SceneError::parse(line, "non-finite transform"));
+    }
+    Transform3::try_new(mat)
+        .ok_or_else(|| SceneError::Unsupported("prims with singular transforms".into()))
+}
+
+fn identity() -> [[f64; 4]; 4] {
+    let mut mat = [[0.; 4]; 4];
+    for (i, row) in mat.iter_mut().enumerate() {
+        row[i] = 1.;
+    }
+    mat
+}
+
+/// Returns the product of two matrices, which applies `a` first
+fn mul(a: [[f64; 4]; 4], b: [[f64; 4]; 4]) -> [[f64; 4]; 4] {
+    let mut mat = [[0.; 4]; 4];
+    for (i, row) in mat.iter_mut().enumerate() {
+        for (j, x) in row.iter_mut().enumerate() {
+            *x = (0..4).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    mat
+}
+
+/// Returns a rotation by `degrees` counterclockwise about the x, y or z axis
+fn rotation(axis: usize, degrees: f64) -> [[f64; 4]; 4] {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let (b, c) = ((axis + 1) % 3, (axis + 2) % 3);
+    let mut mat = identity();
+    mat[b][b] = cos;
+    mat[b][c] = sin;
+    mat[c][b] = -sin;
+    mat[c][c] = cos;
+    mat
+}
+
+/// Reads the mesh of a prim, splitting its faces into fans of triangles
+///
+/// Vertices are split where normals or texture coordinates vary between the faces they are
+/// on, and faces are reversed for the left-handed orientation.
+fn mesh<T: Float, U>(prim: &Prim) -> Result<TriangleMesh<T, U>, SceneError> {
+    let numbers = |name: &str| prim.value(name).map(Value::numbers).unwrap_or_default();
+    let points = numbers("points");
+    let counts = numbers("faceVertexCounts");
+    let indices = numbers("faceVertexIndices");
+    if points.len() % 3 != 0 || counts.iter().sum::<f64>() != indices.len() as f64 {
+        return Err(SceneError::InvalidFormat);
+    }
+    let point_count = points.len() / 3;
+
+    // Normals and texture coordinates, with their interpolations and indices
+    let is_uv = |a: &&Attribute| match a.value() {
+        Some(Value::List(values)) => {
+            matches!(values.first(), Some(Value::List(uv)) if uv.len() == 2)
+        }
+        _ => false,
+    };
+    let uv_name = match prim.attribute("primvars:st") {
+        Some(attribute) => Some(&attribute.name),
+        None => prim
+            .attributes
+            .iter()
+            .filter(|a| a.name.starts_with("primvars:") && !a.name.ends_with(":indices"))
+            .find(is_uv)
+            .map(|a| &a.name),
+    };
+    let primvar = |name: &str, size: usize| {
+        let attribute = prim.attribute(name)?;
+        let values = attribute.value().map(Value::numbers).unwrap_or_default();
+        (values.len() >= size).then(|| Primvar {
+            values,
+            interpolation: attribute.interpolation.as_deref().unwrap_or("vertex"),
+            indices: numbers(&format!("{name}:indices")),
+            size,
+        })
+    };
+    let normals = primvar("normals", 3).or_else(|| primvar("primvars:normals", 3));
+    let uvs = uv_name.and_then(|name| primvar(name, 2));
+
+    let left_handed = prim.value("orientation").and_then(Value::as_str) == Some("leftHanded");
+    let cast = |x: f64| T::from(x).unwrap();
+    if points.iter().any(|&x| !cast(x).is_finite()) {
+        return Err(SceneError::parse(prim.line, "non-finite points"));
+    }
+    let mut mesh = TriangleMesh::new(Vec::new(), Vec::new());
+    let (mut mesh_normals, mut mesh_uvs) = (Vec::new(), Vec::new());
+    let mut vertices = HashMap::new();
+    let mut corner = 0;
+    for (face, &count) in counts.iter().enumerate() {
+        let mut face_vertices = Vec::with_capacity(count as usize);
+        for _ in 0..count as usize {
+            let point = indices[corner];
+            if point < 0. || point as usize >= point_count {
+                return Err(SceneError::InvalidFormat);
+            }
+            let point = point as usize;
+            let n = normals.as_ref().and_then(|n| n.index(face, corner, point));
+            let uv = uvs.as_ref().and_then(|uv| uv.index(face, corner, point));
+            let key = (point, n, uv);
+            let next = vertices.len();
+            let vertex = *vertices.entry(key).or_insert_with(|| {
+                let p = &points[3 * point..3 * point + 3];
+                mesh.positions
+                    .push(Point3::new(cast(p[0]), cast(p[1]), cast(p[2])));
+                if let Some(Primvar { values, .. }) = &normals {
+                    let n = &values[3 * n.unwrap_or(0)..3 * n.unwrap_or(0) + 3];
+                    mesh_normals.push(Vector3::new(cast(n[0]), cast(n[1]), cast(n[2])).to_normal());
+                }
+                if let Some(Primvar { values, .. }) = &uvs {
+                    let uv = &values[2 * uv.unwrap_or(0)..2 * uv.unwrap_or(0) + 2];
+                    mesh_uvs.push(Point2::new(cast(uv[0]), cast(uv[1])));
+                }
+                next as u32
+            });
+            face_vertices.push(vertex);
+            corner += 1;
+        }
+        for i in 1..face_vertices.len().saturating_sub(1) {
+            let [a, b, c] = [face_vertices[0], face_vertices[i], face_vertices[i + 1]];
+            mesh.indices
+                .push(if left_handed { [a, c, b] } else { [a, b, c] });
+        }
+    }
+    mesh.normals = normals.map(|_| mesh_normals);
+    mesh.uvs = uvs.map(|_| mesh_uvs);
+    Ok(mesh)
+}
+
+/// The values of a primvar, such as the normals or texture coordinates of a mesh
+struct Primvar<'a> {
+    values: Vec<f64>,
+    interpolation: &'a str,
+    /// The indices of the values, if they are shared
+    indices: Vec<f64>,
+    /// The number of components of each value
+    size: usize,
+}
+
+impl Primvar<'_> {
+    /// Returns the index of the value at a corner of a face, whose point has the index `point`
+    fn index(&self, face: usize, corner: usize, point: usize) -> Option<usize> {
+        let index = match self.interpolation {
+            "constant" => 0,
+            "uniform" => face,
+            "faceVarying" => corner,
+            _ => point,
+        };
+        let index = self.indices.get(index).map_or(index, |&i| i as usize);
+        (index < self.values.len() / self.size).then_some(index)
+    }
+}
+
+/// A `UsdPreviewSurface`, whose inputs may be read from textures
+struct PreviewSurface<T> {
+    params: PrincipledParameters<T>,
+    base_color: Option<ImageTexture<T>>,
+    /// The textures of the roughness and metallic inputs, with the channel they are read from
+    roughness: Option<(ImageTexture<T>, usize)>,
+    metallic: Option<(ImageTexture<T>, usize)>,
+}
+
+impl<T> PreviewSurface<T> {
+    fn new(params: PrincipledParameters<T>) -> Self {
+        Self {
+            params,
+            base_color: None,
+            roughness: None,
+            metallic: None,
+        }
+    }
+}
+
+impl<T: Float + FloatConst + Send + Sync + 'static, U> Material<T, U> for PreviewSurface<T> {
+    fn bsdf(&self, si: &SurfaceInteraction<T, U>) -> Box<dyn Bsdf<T>> {
+        let mut params = self.params;
+        if let Some(texture) = &self.base_color {
+            params.base_color = texture.evaluate(si);
+        }
+        if let Some((texture, channel)) = &self.roughness {
+            params.roughness = texture.evaluate(si).to_array()[*channel];
+        }
+        if let Some((texture, channel)) = &self.metallic {
+            params.metallic = texture.evaluate(si).to_array()[*channel];
+        }
+        Box::new(PrincipledBsdf::new(&params))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::geometry::UnknownUnit;
+
+    #[test]
+    fn test_read_usda() {
+        let usda = r#"#usda 1.0
+            (
+                defaultPrim = "World"
+                upAxis = "Y"
+            )
+
+            def Xform "World" (
+                kind = "assembly"
+            )
+            {
+                double3 xformOp:translate = (0, 0, -5)
+                uniform token[] xformOpOrder = ["xformOp:translate"]
+
+                def Mesh "Quad" (
+                    prepend apiSchemas = ["MaterialBindingAPI"]
+                )
+                {
+                    int[] faceVertexCounts = [4]
+                    int[] faceVertexIndices = [0, 1, 2, 3]
+                    point3f[] points = [(0, 0, 0), (1, 0, 0), (1, 1, 0), (0, 1, 0)]
+                    texCoord2f[] primvars:st = [(0, 0), (1, 0), (1, 1), (0, 1)] (
+                        interpolation = "faceVarying"
+                    )
+                    int[] primvars:st:indices = [0, 1, 2, 3]
+                    rel material:binding = </World/Looks/Red>
+                    float3 xformOp:rotateXYZ.timeSamples = {
+                        10: (0, 0, 0),
+                        1: (0, 0, 90),
+                    }
+                    float3 xformOp:scale = (2, 2, 2)
+                    uniform token[] xformOpOrder = ["xformOp:rotateXYZ", "xformOp:scale"]
+                }
+
+                def Scope "Looks"
+                {
+                    def Material "Red"
+                    {
+                        token outputs:surface.connect = </World/Looks/Red/Surface.outputs:surface>
+
+                        def Shader "Surface"
+                        {
+                            uniform token info:id = "UsdPreviewSurface"
+                            color3f inputs:diffuseColor = (0.8, 0.1, 0.1)
+                            float inputs:roughness = 0.25
+                            token outputs:surface
+                        }
+                    }
+                }
+
+                class "Template"
+                {
+                    def Sphere "Ignored" {}
+                }
+            }
+        "#;
+        let mut usd = Usd::<f64, UnknownUnit>::read(usda.as_bytes(), "").unwrap();
+        let primitives = usd.primitives().unwrap();
+        assert_eq!(primitives.len(), 1);
+        assert!(primitives[0].material().is_some());
+
+        // The quad is scaled, then turned a quarter about z by its earliest sample
+        let bounds = primitives[0].shape().bounds();
+        let expected = Box3::new(Point3::new(-2., 0., -5.), Point3::new(0., 2., -5.));
+        assert!((bounds.min - expected.min).length() < 1e-9);
+        assert!((bounds.max - expected.max).length() < 1e-9);
+
+        let error = Usd::<f64, UnknownUnit>::read(&b"#usda 1.0\ndef Xform \"A\" {\n  = }"[..], "");
+        assert!(matches!(error, Err(SceneError::Parse { line: 3, .. })));
+
+        // Infinite transforms and points are refused at their prims, as are points beyond the
+        // range of `T`
+        let infinite = usda.replace("(0, 0, -5)", "(0, 0, -1e999)");
+        let error = Usd::<f64, UnknownUnit>::read(infinite.as_bytes(), "");
+        assert!(matches!(error, Err(SceneError::Parse { line: 7, .. })));
+        let huge = usda.replace("(1, 1, 0)", "(1, 1e300, 0)");
+        assert!(Usd::<f64, UnknownUnit>::read(huge.as_bytes(), "").is_ok());
+        let error = Usd::<f32, UnknownUnit>::read(huge.as_bytes(), "");
+        assert!(matches!(error, Err(SceneError::Parse { line: 14, .. })));
+    }
+}
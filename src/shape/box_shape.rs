@@ -0,0 +1,156 @@
+use crate::{
+    core::{
+        geometry::{Axis3, Box3, Point2, Ray, Vector3},
+        units::Time,
+    },
+    shape::{Shape, SurfaceInteraction},
+};
+use num_traits::Float;
+
+/// A solid axis-aligned box
+///
+/// Each face is parameterized over `[0, 1]^2` such that `dpdu x dpdv` points outwards.
+pub struct BoxShape<T, U> {
+    pub bounds: Box3<T, U>,
+}
+
+common_impls!(BoxShape { bounds });
+
+impl<T, U> BoxShape<T, U> {
+    #[inline]
+    #[must_use]
+    pub const fn new(bounds: Box3<T, U>) -> Self {
+        Self { bounds }
+    }
+}
+
+impl<T, U> From<Box3<T, U>> for BoxShape<T, U> {
+    #[inline]
+    fn from(bounds: Box3<T, U>) -> Self {
+        Self::new(bounds)
+    }
+}
+
+impl<T: Float, U> BoxShape<T, U> {
+    /// Returns the ray parameter where the ray enters the box, or leaves it if it starts inside
+    #[inline]
+    fn hit_t(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<T> {
+        let (t0, t1) = self.bounds.intersect_ray(ray, Time(T::infinity()))?;
+        let t = if t0 > T::zero() { t0 } else { t1 };
+        (t > T::zero() && t <= t_max.0).then_some(t)
+    }
+}
+
+impl<T: Float, U> Shape<T, U> for BoxShape<T, U> {
+    #[inline]
+    fn bounds(&self) -> Box3<T, U> {
+        self.bounds
+    }
+
+    fn intersect(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<SurfaceInteraction<T, U>> {
+        let t = Time(self.hit_t(ray, t_max)?);
+        let mut p = ray.at(t);
+        let (min, max) = (self.bounds.min, self.bounds.max);
+
+        // The hit face is the one the hit point lies closest to
+        let mut face = (Axis3::X, false);
+        let mut closest = T::infinity();
+        for axis in Axis3::AXES {
+            for (bound, is_max) in [(min[axis], false), (max[axis], true)] {
+                let dist = (p[axis] - bound).abs();
+                if dist < closest {
+                    closest = dist;
+                    face = (axis, is_max);
+                }
+            }
+        }
+        let (axis, is_max) = face;
+        p[axis] = if is_max { max[axis] } else { min[axis] };
+
+        let (u_axis, v_axis) = (axis.next(), axis.next().next());
+        let extent = max - min;
+        let mut uv = Point2::new(
+            (p[u_axis] - min[u_axis]) / extent[u_axis],
+            (p[v_axis] - min[v_axis]) / extent[v_axis],
+        );
+        let mut dpdu = Vector3::zero();
+        dpdu[u_axis] = extent[u_axis];
+        let mut dpdv = Vector3::zero();
+        dpdv[v_axis] = extent[v_axis];
+        let mut n = Vector3::zero();
+        n[axis] = T::one();
+        // `u` runs backwards on the faces towards the minimum to keep them facing outwards
+        if !is_max {
+            uv.x = T::one() - uv.x;
+            dpdu = -dpdu;
+            n = -n;
+        }
+
+        let wo = -ray.dir.normalize();
+        Some(SurfaceInteraction::new(
+            p,
+            t,
+            wo,
+            n.to_normal(),
+            uv,
+            dpdu,
+            dpdv,
+        ))
+    }
+
+    #[inline]
+    fn intersect_any(&self, ray: &Ray<T, U>, t_max: Time<T>) -> bool {
+        self.hit_t(ray, t_max).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::geometry::{Point3, UnknownUnit};
+
+    type P = Point3<f64, UnknownUnit>;
+    type V = Vector3<f64, UnknownUnit>;
+
+    #[test]
+    fn test_faces() {
+        let shape = BoxShape::new(Box3::new(P::new(1., 0., -1.), P::new(3., 4., 1.)));
+        let (center, half_extent) = (P::new(2., 2., 0.), V::new(1., 2., 1.));
+        let inf = Time(f64::INFINITY);
+        for axis in Axis3::AXES {
+            for sign in [-1., 1.] {
+                let mut outward = V::zero();
+                outward[axis] = sign;
+                // Off-center, so that `u` and `v` tell the sides of the face apart
+                let offset = V::new(0.3, 0.5, 0.2);
+                let target =
+                    center + offset - outward * offset.dot(outward) + outward * half_extent[axis];
+
+                // From outside, and from inside towards the same face
+                for origin in [target + outward * 10., center] {
+                    let ray = Ray::new(origin, target - origin);
+                    let hit = shape.intersect(&ray, inf).unwrap();
+                    assert!((hit.p - target).length() < 1e-12, "{hit:?}");
+                    assert_eq!(hit.n.to_vector(), outward);
+                    assert!(hit.dpdu.cross(hit.dpdv).normalize().dot(outward) > 1. - 1e-12);
+                    assert!(hit.uv.x > 0. && hit.uv.x < 1. && hit.uv.y > 0. && hit.uv.y < 1.);
+
+                    // Moving along `u` on the face moves the hit point along `dpdu`
+                    let nudged = Ray::new(origin, target + hit.dpdu * 1e-3 - origin);
+                    let nudged = shape.intersect(&nudged, inf).unwrap();
+                    assert!((nudged.uv.x - hit.uv.x - 1e-3).abs() < 1e-9);
+                    assert!((nudged.uv.y - hit.uv.y).abs() < 1e-9);
+                }
+            }
+        }
+
+        // The +x face spans y then z
+        let ray = Ray::new(P::new(5., 1., 0.5), V::new(-1., 0., 0.));
+        let hit = shape.intersect(&ray, inf).unwrap();
+        assert_eq!((hit.t, hit.uv), (Time(2.), Point2::new(0.25, 0.75)));
+        assert!(!shape.intersect_any(&ray, Time(1.5)));
+        assert!(shape
+            .intersect(&Ray::new(P::new(5., 5., 0.), V::new(-1., 0., 0.)), inf)
+            .is_none());
+    }
+}
@@ -0,0 +1,226 @@
+use crate::{
+    core::{
+        geometry::{Box3, Point2, Point3, Ray, Vector3},
+        num::solve_quadratic,
+        units::Time,
+    },
+    shape::{azimuth, Shape, SurfaceInteraction},
+};
+use num_traits::{Float, FloatConst};
+
+/// The set of points within `radius` of the segment from `a` to `b`
+///
+/// `u` goes around the segment, and `v` along the outline from the pole beyond `a` to the one
+/// beyond `b`, in proportion to arc length.
+pub struct Capsule<T, U> {
+    pub a: Point3<T, U>,
+    pub b: Point3<T, U>,
+    pub radius: T,
+}
+
+common_impls!(Capsule { a, b, radius });
+
+impl<T, U> Capsule<T, U> {
+    #[inline]
+    #[must_use]
+    pub const fn new(a: Point3<T, U>, b: Point3<T, U>, radius: T) -> Self {
+        Self { a, b, radius }
+    }
+}
+
+impl<T: Float, U> Capsule<T, U> {
+    /// Returns the unit direction from `a` to `b`, or +z if they coincide
+    #[inline]
+    fn axis(&self) -> Vector3<T, U> {
+        (self.b - self.a)
+            .try_normalize()
+            .unwrap_or_else(|| Vector3::new(T::zero(), T::zero(), T::one()))
+    }
+
+    /// Returns the point on the segment closest to `p`
+    #[must_use]
+    pub fn closest_point_on_segment(&self, p: Point3<T, U>) -> Point3<T, U> {
+        let ab = self.b - self.a;
+        let length_squared = ab.length_squared();
+        if length_squared == T::zero() {
+            return self.a;
+        }
+        let t = ((p - self.a).dot(ab) / length_squared)
+            .max(T::zero())
+            .min(T::one());
+        self.a + ab * t
+    }
+
+    /// Returns the point on the capsule's surface closest to `p`
+    #[must_use]
+    pub fn closest_point(&self, p: Point3<T, U>) -> Point3<T, U> {
+        let q = self.closest_point_on_segment(p);
+        let dir = (p - q)
+            .try_normalize()
+            .unwrap_or_else(|| self.axis().coordinate_system().0);
+        q + dir * self.radius
+    }
+
+    /// Returns the distance from `p` to the capsule's surface, negative inside of it
+    #[inline]
+    #[must_use]
+    pub fn signed_distance(&self, p: Point3<T, U>) -> T {
+        (p - self.closest_point_on_segment(p)).length() - self.radius
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn contains(&self, p: Point3<T, U>) -> bool {
+        (p - self.closest_point_on_segment(p)).length_squared() <= self.radius * self.radius
+    }
+}
+
+impl<T: Float + FloatConst, U> Shape<T, U> for Capsule<T, U> {
+    #[inline]
+    fn bounds(&self) -> Box3<T, U> {
+        let r = Vector3::splat(self.radius);
+        Box3::new(self.a.min(self.b) - r, self.a.max(self.b) + r)
+    }
+
+    fn intersect(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<SurfaceInteraction<T, U>> {
+        let ab = self.b - self.a;
+        let ab_ab = ab.dot(ab);
+        let d = ray.dir;
+        let r2 = self.radius * self.radius;
+        let two = T::one() + T::one();
+        let in_range = |t: T| t > T::zero() && t <= t_max.0;
+
+        // Projecting out the axis turns the side into a circle; scaling by `ab_ab` avoids a division
+        let ao = ray.origin - self.a;
+        let (ab_d, ab_ao) = (ab.dot(d), ab.dot(ao));
+        let side = solve_quadratic(
+            ab_ab * d.dot(d) - ab_d * ab_d,
+            two * (ab_ab * d.dot(ao) - ab_ao * ab_d),
+            ab_ab * ao.dot(ao) - ab_ao * ab_ao - r2 * ab_ab,
+        )
+        .and_then(|(t0, t1)| {
+            [t0, t1].into_iter().find(|&t| {
+                let y = ab_ao + t * ab_d;
+                in_range(t) && y > T::zero() && y < ab_ab
+            })
+        });
+
+        // Each cap only counts where it lies beyond its end of the segment
+        let cap = |center: Point3<T, U>, outside: &dyn Fn(T) -> bool| {
+            let oc = ray.origin - center;
+            let (t0, t1) = solve_quadratic(d.dot(d), two * d.dot(oc), oc.dot(oc) - r2)?;
+            [t0, t1]
+                .into_iter()
+                .find(|&t| in_range(t) && outside(ab_ao + t * ab_d))
+        };
+        let cap_a = cap(self.a, &|y| y <= T::zero());
+        let cap_b = cap(self.b, &|y| y >= ab_ab);
+
+        let t = [side, cap_a, cap_b]
+            .into_iter()
+            .flatten()
+            .min_by(|x, y| x.partial_cmp(y).unwrap())?;
+
+        let t = Time(t);
+        let p = ray.at(t);
+        let q = self.closest_point_on_segment(p);
+        let n = ((p - q) / self.radius).normalize();
+
+        let axis = self.axis();
+        let (t1, t2) = axis.coordinate_system();
+        let local = p - self.a;
+        let radial = local - axis * local.dot(axis);
+        let phi = azimuth(radial.dot(t1), radial.dot(t2));
+        let (sin_phi, cos_phi) = phi.sin_cos();
+
+        // `v` is the arc length along the outline from the pole beyond `a` to the one beyond `b`
+        let length = ab_ab.sqrt();
+        let half_pi = T::FRAC_PI_2();
+        let arc = if local.dot(axis) <= T::zero() {
+            self.radius * (-n.dot(axis)).max(-T::one()).min(T::one()).acos()
+        } else if local.dot(axis) >= length {
+            self.radius * (half_pi + n.dot(axis).max(-T::one()).min(T::one()).asin()) + length
+        } else {
+            self.radius * half_pi + local.dot(axis)
+        };
+        let outline = length + self.radius * T::PI();
+        let uv = Point2::new(phi / T::TAU(), arc / outline);
+
+        let tangent = t2 * cos_phi - t1 * sin_phi;
+        let dpdu = tangent * (T::TAU() * radial.length());
+        let dpdv = n.cross(tangent) * outline;
+        let wo = -ray.dir.normalize();
+        Some(SurfaceInteraction::new(
+            p,
+            t,
+            wo,
+            n.to_normal(),
+            uv,
+            dpdu,
+            dpdv,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::geometry::UnknownUnit;
+
+    type P = Point3<f64, UnknownUnit>;
+
+    #[test]
+    fn test_closest_point() {
+        let capsule = Capsule::new(P::new(0., 0., 0.), P::new(0., 0., 2.), 0.5);
+        let close = |a: P, b: P| (a - b).length() < 1e-12;
+
+        // Beside the side, and beyond either cap
+        let beyond_a = P::new(0., 2., -1.);
+        let cases = [
+            (P::new(3., 0., 1.), P::new(0., 0., 1.), P::new(0.5, 0., 1.)),
+            (
+                beyond_a,
+                P::origin(),
+                P::origin() + (beyond_a - P::origin()).normalize() * 0.5,
+            ),
+            (P::new(0., 0., 5.), P::new(0., 0., 2.), P::new(0., 0., 2.5)),
+            // Inside
+            (
+                P::new(0.1, 0., 1.2),
+                P::new(0., 0., 1.2),
+                P::new(0.5, 0., 1.2),
+            ),
+        ];
+        for (p, on_segment, on_surface) in cases {
+            assert!(
+                close(capsule.closest_point_on_segment(p), on_segment),
+                "{p:?}"
+            );
+            let q = capsule.closest_point(p);
+            assert!(close(q, on_surface), "{p:?}: {q:?}");
+            let distance = capsule.signed_distance(p);
+            assert!((distance.abs() - (p - q).length()).abs() < 1e-12);
+            assert_eq!(capsule.contains(p), distance <= 0.);
+
+            // Rays from outside towards the closest point hit the surface there first
+            if distance > 0. {
+                let ray = Ray::new(p, (q - p).normalize());
+                let hit = capsule.intersect(&ray, Time(f64::INFINITY)).unwrap();
+                assert!((hit.t.0 - distance).abs() < 1e-9 && close(hit.p, q));
+            }
+        }
+        assert!((capsule.signed_distance(P::new(0.1, 0., 1.2)) + 0.4).abs() < 1e-12);
+
+        // Points on the axis pick any direction across it
+        let q = capsule.closest_point(P::new(0., 0., 1.));
+        assert!((q.z - 1.).abs() < 1e-12 && (q.x.hypot(q.y) - 0.5).abs() < 1e-12);
+
+        // Without a segment, the capsule is a sphere
+        let sphere = Capsule::new(P::new(1., 1., 1.), P::new(1., 1., 1.), 2.);
+        assert!(close(
+            sphere.closest_point(P::new(1., 1., 5.)),
+            P::new(1., 1., 3.)
+        ));
+        assert_eq!(sphere.signed_distance(P::new(1., 1., 5.)), 2.);
+    }
+}
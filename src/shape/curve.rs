@@ -0,0 +1,481 @@
+use crate::{
+    core::{
+        geometry::{Box3, Point2, Point3, Ray, Vector3},
+        prelude::Normal3,
+        units::Time,
+    },
+    shape::{Shape, SurfaceInteraction},
+};
+use num_traits::{Float, FloatConst};
+use std::fmt;
+
+/// The deepest the control points of curves are subdivided when intersecting them
+const MAX_DEPTH: i32 = 10;
+
+/// How the width of a curve is oriented
+pub enum CurveKind<T, U> {
+    /// A flat ribbon always facing the ray, for thin hairs and fur
+    Flat,
+    /// A ribbon always facing the ray, whose normal turns around the curve like that of a tube
+    /// from one side of it to the other, for thicker hairs
+    Cylinder,
+    /// A ribbon facing normals given at the start and end of the curve, interpolated spherically
+    /// between them, such as blades of grass
+    Ribbon([Normal3<T, U>; 2]),
+}
+
+impl<T: fmt::Debug, U> fmt::Debug for CurveKind<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Flat => f.write_str("Flat"),
+            Self::Cylinder => f.write_str("Cylinder"),
+            Self::Ribbon(normals) => f.debug_tuple("Ribbon").field(normals).finish(),
+        }
+    }
+}
+
+impl<T: Copy, U> Copy for CurveKind<T, U> {}
+
+impl<T: Copy, U> Clone for CurveKind<T, U> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+/// How the control points of a strand describe its curves
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum CurveBasis {
+    /// Cubic Bezier curves, each sharing its last control point with the next one
+    Bezier,
+    /// A uniform cubic B-spline, each 4 consecutive control points making a curve
+    BSpline,
+}
+
+/// A cubic Bezier curve swept with a varying width, such as a strand of hair, intersected by
+/// recursively subdividing it (Nakamaru and Ohno, "Ray tracing for curves primitive")
+///
+/// The width is interpolated linearly from the start of the curve to its end. `u` goes along
+/// the curve and `v` across it, so that the offset of hits from its middle, which hair
+/// scattering depends on, is `2v - 1`.
+///
+/// Curves only cover a part of their control points, given by `u_range`, so that long curves
+/// can be [split](Self::split) into pieces with tighter bounds.
+pub struct Curve<T, U> {
+    pub control_points: [Point3<T, U>; 4],
+    pub widths: [T; 2],
+    pub kind: CurveKind<T, U>,
+    pub u_range: [T; 2],
+}
+
+impl<T: fmt::Debug, U> fmt::Debug for Curve<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Curve")
+            .field("control_points", &self.control_points)
+            .field("widths", &self.widths)
+            .field("kind", &self.kind)
+            .field("u_range", &self.u_range)
+            .finish()
+    }
+}
+
+impl<T: Copy, U> Copy for Curve<T, U> {}
+
+impl<T: Copy, U> Clone for Curve<T, U> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+/// Evaluates the blossom of a cubic Bezier curve, whose control points are those of its
+/// blossom at `(0, 0, 0)`, `(0, 0, 1)`, `(0, 1, 1)` and `(1, 1, 1)`
+fn blossom<T: Float, U>(p: &[Point3<T, U>; 4], u0: T, u1: T, u2: T) -> Point3<T, U> {
+    let a = [0, 1, 2].map(|i| p[i].lerp(p[i + 1], u0));
+    let b = [0, 1].map(|i| a[i].lerp(a[i + 1], u1));
+    b[0].lerp(b[1], u2)
+}
+
+/// Returns the control points of the part of a curve within `u0..=u1`
+fn segment<T: Float, U>(p: &[Point3<T, U>; 4], u0: T, u1: T) -> [Point3<T, U>; 4] {
+    [
+        blossom(p, u0, u0, u0),
+        blossom(p, u0, u0, u1),
+        blossom(p, u0, u1, u1),
+        blossom(p, u1, u1, u1),
+    ]
+}
+
+/// Splits a curve in halves by de Casteljau's algorithm
+fn subdivide<T: Float, U>(p: &[Point3<T, U>; 4]) -> [[Point3<T, U>; 4]; 2] {
+    let half = T::from(0.5).unwrap();
+    let a = [0, 1, 2].map(|i| p[i].lerp(p[i + 1], half));
+    let b = [0, 1].map(|i| a[i].lerp(a[i + 1], half));
+    let middle = b[0].lerp(b[1], half);
+    [[p[0], a[0], b[0], middle], [middle, b[1], a[2], p[3]]]
+}
+
+/// Returns the point on a curve and its derivative
+fn evaluate<T: Float, U>(p: &[Point3<T, U>; 4], u: T) -> (Point3<T, U>, Vector3<T, U>) {
+    let a = [0, 1, 2].map(|i| p[i].lerp(p[i + 1], u));
+    let b = [0, 1].map(|i| a[i].lerp(a[i + 1], u));
+    let derivative = b[1] - b[0];
+    // The derivative vanishes at ends whose control points coincide
+    let derivative = if derivative.length_squared() > T::zero() {
+        derivative * T::from(3).unwrap()
+    } else {
+        p[3] - p[0]
+    };
+    (b[0].lerp(b[1], u), derivative)
+}
+
+/// Interpolates spherically between unit vectors
+fn slerp<T: Float, U>(a: Vector3<T, U>, b: Vector3<T, U>, t: T) -> Vector3<T, U> {
+    let cos = a.dot(b).max(-T::one()).min(T::one());
+    let angle = cos.acos();
+    let sin = angle.sin();
+    if sin < T::from(1e-5).unwrap() {
+        return a.lerp(b, t).try_normalize().unwrap_or(a);
+    }
+    a * (((T::one() - t) * angle).sin() / sin) + b * ((t * angle).sin() / sin)
+}
+
+/// The axes of the space of a ray, in which it starts at the origin and goes along z
+type Frame<T, U> = [Vector3<T, U>; 3];
+
+/// The part of a hit found in the space of the ray
+struct Hit<T> {
+    /// The distance along the ray
+    z: T,
+    u: T,
+    v: T,
+    width: T,
+}
+
+impl<T: Float, U> Curve<T, U> {
+    /// Creates a curve covering all of its control points
+    #[inline]
+    #[must_use]
+    pub fn new(control_points: [Point3<T, U>; 4], widths: [T; 2], kind: CurveKind<T, U>) -> Self {
+        Self {
+            control_points,
+            widths,
+            kind,
+            u_range: [T::zero(), T::one()],
+        }
+    }
+
+    /// Creates the curves of a strand through its control points, whose width and ribbon
+    /// normals are interpolated from its root to its tip
+    ///
+    /// Panics if a strand of Bezier curves does not have `3n + 1` control points, or one of
+    /// B-spline curves has fewer than 4.
+    #[must_use]
+    pub fn strand(
+        basis: CurveBasis,
+        points: &[Point3<T, U>],
+        widths: [T; 2],
+        kind: CurveKind<T, U>,
+    ) -> Vec<Self> {
+        let curves: Vec<[Point3<T, U>; 4]> = match basis {
+            CurveBasis::Bezier => {
+                assert!(
+                    points.len() >= 4 && (points.len() - 1).is_multiple_of(3),
+                    "strands of Bezier curves need 3n + 1 control points"
+                );
+                (0..points.len() / 3)
+                    .map(|i| [0, 1, 2, 3].map(|k| points[3 * i + k]))
+                    .collect()
+            }
+            CurveBasis::BSpline => {
+                assert!(points.len() >= 4, "B-spline strands need 4 control points");
+                let third = T::from(3).unwrap().recip();
+                let half = T::from(0.5).unwrap();
+                // Where curves meet, computed alike for both so that they join exactly
+                let knot = |p: &[Point3<T, U>]| p[1].lerp(p[0].lerp(p[2], half), third);
+                points
+                    .windows(4)
+                    .map(|p| {
+                        // The Bezier control points of the same curve
+                        let (p1, p2) = (p[1].lerp(p[2], third), p[2].lerp(p[1], third));
+                        [knot(&p[..3]), p1, p2, knot(&p[1..])]
+                    })
+                    .collect()
+            }
+        };
+
+        let n = T::from(curves.len()).unwrap();
+        let at = |i: usize| T::from(i).unwrap() / n;
+        curves
+            .iter()
+            .enumerate()
+            .map(|(i, &control_points)| {
+                let (t0, t1) = (at(i), at(i + 1));
+                let width = |t: T| widths[0] + (widths[1] - widths[0]) * t;
+                let kind = match kind {
+                    CurveKind::Ribbon([n0, n1]) => {
+                        let (n0, n1) = (n0.to_vector().normalize(), n1.to_vector().normalize());
+                        let normal = |t: T| slerp(n0, n1, t).to_normal();
+                        CurveKind::Ribbon([normal(t0), normal(t1)])
+                    }
+                    kind => kind,
+                };
+                Self::new(control_points, [width(t0), width(t1)], kind)
+            })
+            .collect()
+    }
+
+    /// Splits the curve into pieces of equal parametric length, to be put into an accelerator
+    pub fn split(&self, pieces: usize) -> impl Iterator<Item = Self> + '_ {
+        let [u0, u1] = self.u_range;
+        let at = move |i: usize| u0 + (u1 - u0) * T::from(i).unwrap() / T::from(pieces).unwrap();
+        (0..pieces).map(move |i| Self {
+            u_range: [at(i), at(i + 1)],
+            ..*self
+        })
+    }
+
+    #[inline]
+    fn width(&self, u: T) -> T {
+        self.widths[0] + (self.widths[1] - self.widths[0]) * u
+    }
+
+    #[inline]
+    fn normal(&self, u: T) -> Option<Vector3<T, U>> {
+        match self.kind {
+            CurveKind::Ribbon([n0, n1]) => Some(slerp(
+                n0.to_vector().normalize(),
+                n1.to_vector().normalize(),
+                u,
+            )),
+            _ => None,
+        }
+    }
+
+    /// Returns the axes of the space of a ray, with the curve along x as far as possible
+    fn ray_frame(&self, dir: Vector3<T, U>) -> Frame<T, U> {
+        let z = dir.normalize();
+        let chord = self.control_points[3] - self.control_points[0];
+        let y = z
+            .cross(chord)
+            .try_normalize()
+            .unwrap_or_else(|| z.coordinate_system().0);
+        [y.cross(z), y, z]
+    }
+
+    /// Finds the closest hit within the part `u` of the curve, given by control points in the
+    /// space of the ray, by subdividing them until they are nearly straight
+    fn hit(
+        &self,
+        ray_dir: Vector3<T, U>,
+        p: &[Point3<T, U>; 4],
+        u: [T; 2],
+        depth: i32,
+        mut z_max: T,
+    ) -> Option<Hit<T>> {
+        let half = T::from(0.5).unwrap();
+        if depth > 0 {
+            let halves = subdivide(p);
+            let u_mid = (u[0] + u[1]) * half;
+            let mut closest = None;
+            for (p, u) in halves.iter().zip([[u[0], u_mid], [u_mid, u[1]]]) {
+                let r = self.width(u[0]).max(self.width(u[1])) * half;
+                let (min, max) = p[1..]
+                    .iter()
+                    .fold((p[0], p[0]), |(min, max), &q| (min.min(q), max.max(q)));
+                if min.x > r || max.x < -r || min.y > r || max.y < -r {
+                    continue;
+                }
+                if min.z > z_max + r || max.z < -r {
+                    continue;
+                }
+                if let Some(hit) = self.hit(ray_dir, p, u, depth - 1, z_max) {
+                    z_max = hit.z;
+                    closest = Some(hit);
+                }
+            }
+            return closest;
+        }
+
+        // Hits beyond the ends of the nearly straight segment belong to its neighbors
+        let edge = (p[1].y - p[0].y) * -p[0].y + p[0].x * (p[0].x - p[1].x);
+        if edge < T::zero() {
+            return None;
+        }
+        let edge = (p[2].y - p[3].y) * -p[3].y + p[3].x * (p[3].x - p[2].x);
+        if edge < T::zero() {
+            return None;
+        }
+
+        // The point of the segment closest to the ray
+        let direction = Point2::<T, U>::new(p[3].x - p[0].x, p[3].y - p[0].y).to_vector();
+        let denom = direction.length_squared();
+        if denom == T::zero() {
+            return None;
+        }
+        let w = -(p[0].x * direction.x + p[0].y * direction.y) / denom;
+        let hit_u = (u[0] + (u[1] - u[0]) * w).max(u[0]).min(u[1]);
+        let mut width = self.width(hit_u);
+        if let Some(n) = self.normal(hit_u) {
+            // Ribbons narrow as they turn edge on
+            width = width * n.dot(ray_dir).abs();
+        }
+        let (pc, dpcdw) = evaluate(p, w.max(T::zero()).min(T::one()));
+        let dist_squared = pc.x * pc.x + pc.y * pc.y;
+        if dist_squared > width * width * T::from(0.25).unwrap() {
+            return None;
+        }
+        if !(pc.z > T::zero() && pc.z <= z_max) {
+            return None;
+        }
+
+        // Which side of the curve the ray passes
+        let dist = dist_squared.sqrt();
+        let side = dpcdw.x * -pc.y + pc.x * dpcdw.y;
+        let v = if side > T::zero() {
+            half + dist / width
+        } else {
+            half - dist / width
+        };
+        Some(Hit {
+            z: pc.z,
+            u: hit_u,
+            v,
+            width,
+        })
+    }
+
+    /// Returns the closest hit with its ray parameter and the basis of the space of the ray
+    fn find(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<(Hit<T>, Frame<T, U>)> {
+        let frame = self.ray_frame(ray.dir);
+        let [u0, u1] = self.u_range;
+        let p = segment(&self.control_points, u0, u1).map(|q| {
+            let v = q - ray.origin;
+            Point3::new(v.dot(frame[0]), v.dot(frame[1]), v.dot(frame[2]))
+        });
+
+        // Deep enough for the segments to be within a small fraction of the width of straight
+        let mut curvature = T::zero();
+        for i in 0..2 {
+            let d = (p[i] - p[i + 1]) - (p[i + 1] - p[i + 2]);
+            curvature = curvature.max(d.x.abs()).max(d.y.abs()).max(d.z.abs());
+        }
+        let eps = self.width(u0).max(self.width(u1)) * T::from(0.05).unwrap();
+        let ratio = T::from(6. * std::f64::consts::SQRT_2 / 8.).unwrap() * curvature / eps;
+        let depth = if ratio.is_nan() || ratio <= T::one() {
+            0
+        } else {
+            let log = ratio.log2().floor().to_i32().unwrap_or(MAX_DEPTH);
+            ((log + 1) / 2).min(MAX_DEPTH)
+        };
+
+        let speed = ray.dir.length();
+        let hit = self.hit(frame[2], &p, self.u_range, depth, t_max.0 * speed)?;
+        Some((hit, frame))
+    }
+}
+
+impl<T: Float + FloatConst, U> Shape<T, U> for Curve<T, U> {
+    fn bounds(&self) -> Box3<T, U> {
+        let [u0, u1] = self.u_range;
+        let p = segment(&self.control_points, u0, u1);
+        let (min, max) = p[1..]
+            .iter()
+            .fold((p[0], p[0]), |(min, max), &q| (min.min(q), max.max(q)));
+        let r = self.width(u0).max(self.width(u1)) * T::from(0.5).unwrap();
+        Box3::new(min, max).inflate(r, r, r)
+    }
+
+    fn intersect(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<SurfaceInteraction<T, U>> {
+        let (hit, [x, y, z]) = self.find(ray, t_max)?;
+        let t = Time(hit.z / ray.dir.length());
+        let (_, dpdu) = evaluate(&self.control_points, hit.u);
+        let dpdv = if let Some(n) = self.normal(hit.u) {
+            n.cross(dpdu).normalize() * hit.width
+        } else {
+            // Across the curve as seen along the ray, in the space of the ray
+            let dpdu: Vector3<T, U> = Vector3::new(dpdu.dot(x), dpdu.dot(y), dpdu.dot(z));
+            let mut dpdv = Vector3::new(-dpdu.y, dpdu.x, T::zero()).normalize() * hit.width;
+            if let CurveKind::Cylinder = self.kind {
+                // Turned around the curve as far as the normal of a tube through the hit
+                let axis = dpdu.normalize();
+                let sin = hit.v + hit.v - T::one();
+                let cos = (T::one() - sin * sin).max(T::zero()).sqrt();
+                dpdv =
+                    dpdv * cos + axis.cross(dpdv) * sin + axis * axis.dot(dpdv) * (T::one() - cos);
+            }
+            x * dpdv.x + y * dpdv.y + z * dpdv.z
+        };
+        let n = dpdu.cross(dpdv).try_normalize()?;
+        let wo = -ray.dir.normalize();
+        let uv = Point2::new(hit.u, hit.v);
+        Some(SurfaceInteraction::new(
+            ray.at(t),
+            t,
+            wo,
+            n.to_normal(),
+            uv,
+            dpdu,
+            dpdv,
+        ))
+    }
+
+    #[inline]
+    fn intersect_any(&self, ray: &Ray<T, U>, t_max: Time<T>) -> bool {
+        self.find(ray, t_max).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{accel::Bvh, core::geometry::UnknownUnit};
+
+    type P = Point3<f64, UnknownUnit>;
+    type V = Vector3<f64, UnknownUnit>;
+
+    #[test]
+    fn test_curves() {
+        let line = [0., 1., 2., 3.].map(|x| P::new(x, 0., 0.));
+        let down = |x: f64, y: f64| Ray::new(P::new(x, y, 5.), V::new(0., 0., -2.));
+        let t_max = Time(f64::INFINITY);
+
+        // Flat curves face the ray
+        let flat = Curve::new(line, [0.2, 0.2], CurveKind::Flat);
+        let hit = flat.intersect(&down(1., 0.05), t_max).unwrap();
+        assert!((hit.t.0 - 2.5).abs() < 1e-9);
+        assert!((hit.uv.x - 1. / 3.).abs() < 1e-9);
+        assert!((hit.uv.y - 0.5).abs() - 0.25 < 1e-9);
+        assert!(hit.n.to_vector().z.abs() > 0.999);
+        assert!(hit.dpdu.cross(hit.dpdv).dot(hit.n.to_vector()) > 0.);
+        assert!(!flat.intersect_any(&down(1., 0.15), t_max));
+        assert!(!flat.intersect_any(&down(3.2, 0.), t_max));
+
+        // Round curves bend their normals around like a tube
+        let tube = Curve::new(line, [0.2, 0.2], CurveKind::Cylinder);
+        let n = tube
+            .intersect(&down(2., 0.08), t_max)
+            .unwrap()
+            .n
+            .to_vector();
+        assert!((n.y.abs() - 0.8).abs() < 1e-6 && n.y * n.z > 0.);
+
+        // Ribbons seen edge on vanish
+        let up = V::new(0., 0., 1.).to_normal();
+        let side = V::new(0., 1., 0.).to_normal();
+        let ribbon = Curve::new(line, [0.2, 0.2], CurveKind::Ribbon([up, side]));
+        assert!(ribbon.intersect_any(&down(0.1, 0.05), t_max));
+        assert!(!ribbon.intersect_any(&down(2.9, 0.05), t_max));
+
+        // An arched strand of B-spline curves, tapering to its tip
+        let points = [(0., 0.), (1., 2.), (2., 3.), (3., 3.), (4., 2.), (5., 0.)];
+        let points = points.map(|(x, z)| P::new(x, 0., z));
+        let strand = Curve::strand(CurveBasis::BSpline, &points, [0.4, 0.], CurveKind::Flat);
+        assert_eq!(strand.len(), 3);
+        assert_eq!(strand[0].control_points[3], strand[1].control_points[0]);
+        let pieces = strand.iter().flat_map(|curve| curve.split(4)).collect();
+        let strand = Bvh::new(pieces);
+        let hit = strand.intersect(&down(2.5, 0.), t_max).unwrap();
+        assert!((hit.p.z - 2.5).abs() < 0.5 && hit.p.z > 2.);
+        assert!(strand.intersect_any(&down(2.5, 0.08), t_max));
+        assert!(!strand.intersect_any(&down(2.5, 0.12), t_max));
+    }
+}
@@ -0,0 +1,82 @@
+use crate::{
+    core::{
+        geometry::{Box3, Point2, Point3, Ray, Vector3},
+        num::solve_quadratic,
+        units::Time,
+    },
+    shape::{azimuth, Shape, SurfaceInteraction},
+};
+use num_traits::{Float, FloatConst};
+
+/// An open cylinder around the z axis through `center`, spanning `z_min..=z_max` relative to it
+///
+/// `u` goes around the z axis from +x, and `v` up from `z_min`.
+pub struct Cylinder<T, U> {
+    pub center: Point3<T, U>,
+    pub radius: T,
+    pub z_min: T,
+    pub z_max: T,
+}
+
+common_impls!(Cylinder {
+    center,
+    radius,
+    z_min,
+    z_max
+});
+
+impl<T, U> Cylinder<T, U> {
+    #[inline]
+    #[must_use]
+    pub const fn new(center: Point3<T, U>, radius: T, z_min: T, z_max: T) -> Self {
+        Self {
+            center,
+            radius,
+            z_min,
+            z_max,
+        }
+    }
+}
+
+impl<T: Float + FloatConst, U> Shape<T, U> for Cylinder<T, U> {
+    #[inline]
+    fn bounds(&self) -> Box3<T, U> {
+        let r = self.radius;
+        Box3::new(
+            self.center + Vector3::new(-r, -r, self.z_min),
+            self.center + Vector3::new(r, r, self.z_max),
+        )
+    }
+
+    fn intersect(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<SurfaceInteraction<T, U>> {
+        let o = ray.origin - self.center;
+        let d = ray.dir;
+
+        let two = T::one() + T::one();
+        let a = d.x * d.x + d.y * d.y;
+        let b = two * (d.x * o.x + d.y * o.y);
+        let c = o.x * o.x + o.y * o.y - self.radius * self.radius;
+        let (t0, t1) = solve_quadratic(a, b, c)?;
+        let t = [t0, t1].into_iter().find(|&t| {
+            let z = o.z + d.z * t;
+            t > T::zero() && t <= t_max.0 && z >= self.z_min && z <= self.z_max
+        })?;
+
+        let t = Time(t);
+        let p = ray.at(t);
+        let local = p - self.center;
+
+        let phi = azimuth(local.x, local.y);
+        let uv = Point2::new(
+            phi / T::TAU(),
+            (local.z - self.z_min) / (self.z_max - self.z_min),
+        );
+        let dpdu = Vector3::new(-local.y, local.x, T::zero()) * T::TAU();
+        let dpdv = Vector3::new(T::zero(), T::zero(), self.z_max - self.z_min);
+        let n = Vector3::new(local.x, local.y, T::zero())
+            .normalize()
+            .to_normal();
+        let wo = -ray.dir.normalize();
+        Some(SurfaceInteraction::new(p, t, wo, n, uv, dpdu, dpdv))
+    }
+}
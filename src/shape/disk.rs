@@ -0,0 +1,120 @@
+use crate::{
+    core::{
+        geometry::{Box3, Point2, Point3, Ray, UnknownUnit, Vector3},
+        prelude::Normal3,
+        units::Time,
+    },
+    shape::{azimuth, SampleShape, Shape, ShapeSample, SurfaceInteraction},
+};
+use num_traits::{Float, FloatConst};
+
+/// A disk facing +z, optionally with a hole of `inner_radius` around its center
+///
+/// `u` goes around the z axis from +x, and `v` from the rim to the hole.
+pub struct Disk<T, U> {
+    pub center: Point3<T, U>,
+    pub radius: T,
+    pub inner_radius: T,
+}
+
+common_impls!(Disk {
+    center,
+    radius,
+    inner_radius
+});
+
+impl<T, U> Disk<T, U> {
+    #[inline]
+    #[must_use]
+    pub const fn new(center: Point3<T, U>, radius: T, inner_radius: T) -> Self {
+        Self {
+            center,
+            radius,
+            inner_radius,
+        }
+    }
+}
+
+impl<T: Float, U> Disk<T, U> {
+    /// Returns the ray parameter and the hit position relative to the center
+    #[inline]
+    fn hit(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<(T, T, T)> {
+        let o = ray.origin - self.center;
+        let d = ray.dir;
+        if d.z == T::zero() {
+            return None;
+        }
+        let t = -o.z / d.z;
+        if !(t > T::zero() && t <= t_max.0) {
+            return None;
+        }
+
+        let (x, y) = (o.x + d.x * t, o.y + d.y * t);
+        let dist_squared = x * x + y * y;
+        if dist_squared > self.radius * self.radius
+            || dist_squared < self.inner_radius * self.inner_radius
+        {
+            return None;
+        }
+        Some((t, x, y))
+    }
+}
+
+impl<T: Float + FloatConst, U> Shape<T, U> for Disk<T, U> {
+    #[inline]
+    fn bounds(&self) -> Box3<T, U> {
+        let r = Vector3::new(self.radius, self.radius, T::zero());
+        Box3::new(self.center - r, self.center + r)
+    }
+
+    fn intersect(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<SurfaceInteraction<T, U>> {
+        let (t, x, y) = self.hit(ray, t_max)?;
+        let dist_squared = x * x + y * y;
+        let t = Time(t);
+        let mut p = ray.at(t);
+        // The hit is known to lie exactly in the disk's plane
+        p.z = self.center.z;
+
+        let dist = dist_squared.sqrt();
+        let phi = azimuth(x, y);
+        let (sin_phi, cos_phi) = phi.sin_cos();
+        let uv = Point2::new(
+            phi / T::TAU(),
+            (self.radius - dist) / (self.radius - self.inner_radius),
+        );
+        let dpdu = Vector3::new(-y, x, T::zero()) * T::TAU();
+        let dpdv = Vector3::new(cos_phi, sin_phi, T::zero()) * (self.inner_radius - self.radius);
+        let n = Vector3::new(T::zero(), T::zero(), T::one()).to_normal();
+        let wo = -ray.dir.normalize();
+        Some(SurfaceInteraction::new(p, t, wo, n, uv, dpdu, dpdv))
+    }
+
+    #[inline]
+    fn intersect_any(&self, ray: &Ray<T, U>, t_max: Time<T>) -> bool {
+        self.hit(ray, t_max).is_some()
+    }
+}
+
+impl<T: Float + FloatConst, U> SampleShape<T, U> for Disk<T, U> {
+    #[inline]
+    fn area(&self) -> T {
+        T::PI() * (self.radius * self.radius - self.inner_radius * self.inner_radius)
+    }
+
+    #[inline]
+    fn normal(&self) -> Option<Normal3<T, U>> {
+        Some(Vector3::new(T::zero(), T::zero(), T::one()).to_normal())
+    }
+
+    fn sample(&self, u: Point2<T, UnknownUnit>) -> ShapeSample<T, U> {
+        // Uniform in the squared radius, which is proportional to the area within it
+        let inner = self.inner_radius * self.inner_radius;
+        let r = (inner + u.x * (self.radius * self.radius - inner)).sqrt();
+        let (sin, cos) = (T::TAU() * u.y).sin_cos();
+        ShapeSample {
+            p: self.center + Vector3::new(r * cos, r * sin, T::zero()),
+            n: Vector3::new(T::zero(), T::zero(), T::one()).to_normal(),
+            pdf: self.area().recip(),
+        }
+    }
+}
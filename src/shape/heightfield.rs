@@ -0,0 +1,333 @@
+use crate::{
+    core::{
+        geometry::{Box3, Point2, Point3, Ray, UnknownUnit, Vector2, Vector3},
+        units::Time,
+    },
+    shape::{
+        triangle::{hit, Vertices},
+        Shape, SurfaceInteraction, TriangleMesh,
+    },
+};
+use num_traits::Float;
+use std::fmt;
+
+/// The columns and rows of the points of the grid at the corners of a triangle
+type Corners = [(usize, usize); 3];
+
+/// Terrain over a regular grid of elevations, such as a digital elevation model
+///
+/// The grid starts at `origin` and spans `extent` along x and y, with the elevation of each of
+/// its points added to the z of `origin`. Each cell is split into two triangles along the
+/// diagonal from its corner nearest the origin, which face +z. Normals are interpolated from
+/// those of the points, estimated from their neighbors, and `uv` spans `[0, 1]^2` over the
+/// grid.
+///
+/// Rays walk the cells they cross with a 2D-DDA, testing the triangles of those whose range of
+/// elevations they pass through, so that the terrain costs one number per point rather than a
+/// mesh and a hierarchy over its triangles.
+pub struct Heightfield<T, U> {
+    origin: Point3<T, U>,
+    /// The size of the cells along x and y
+    spacing: Vector2<T, U>,
+    /// The number of points along x and y
+    resolution: [usize; 2],
+    /// The elevations of the points, row by row along x
+    heights: Vec<T>,
+    bounds: Box3<T, U>,
+}
+
+impl<T: fmt::Debug, U> fmt::Debug for Heightfield<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Heightfield")
+            .field("origin", &self.origin)
+            .field("spacing", &self.spacing)
+            .field("resolution", &self.resolution)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Float, U> Heightfield<T, U> {
+    /// # Panics
+    ///
+    /// Panics if the extent is not positive along both axes, if there are fewer than two points
+    /// along either, or if there is not one elevation for each point.
+    #[must_use]
+    pub fn new(
+        origin: Point3<T, U>,
+        extent: Vector2<T, U>,
+        resolution: [usize; 2],
+        heights: Vec<T>,
+    ) -> Self {
+        let [nx, ny] = resolution;
+        assert!(
+            extent.x > T::zero() && extent.y > T::zero(),
+            "heightfields need a positive extent"
+        );
+        assert!(
+            nx >= 2 && ny >= 2,
+            "heightfields need two points along each axis"
+        );
+        assert_eq!(
+            heights.len(),
+            nx * ny,
+            "heightfields need one elevation for each point"
+        );
+        let cells = |n: usize| T::from(n - 1).unwrap();
+        let spacing = Vector2::new(extent.x / cells(nx), extent.y / cells(ny));
+        let (low, high) = heights
+            .iter()
+            .fold((T::infinity(), T::neg_infinity()), |(low, high), &h| {
+                (low.min(h), high.max(h))
+            });
+        let base = origin + Vector3::new(T::zero(), T::zero(), low);
+        let corner = origin + Vector3::new(extent.x, extent.y, high);
+        let bounds = Box3::new(base.min(corner), base.max(corner));
+        Self {
+            origin,
+            spacing,
+            resolution,
+            heights,
+            bounds,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn resolution(&self) -> [usize; 2] {
+        self.resolution
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn heights(&self) -> &[T] {
+        &self.heights
+    }
+
+    /// Returns the point of the grid at column `i` and row `j`
+    #[inline]
+    #[must_use]
+    pub fn point(&self, i: usize, j: usize) -> Point3<T, U> {
+        let h = self.heights[j * self.resolution[0] + i];
+        let x = self.spacing.x * T::from(i).unwrap();
+        let y = self.spacing.y * T::from(j).unwrap();
+        self.origin + Vector3::new(x, y, h)
+    }
+
+    /// Returns the normal of the terrain at a point of the grid, from the slopes to its
+    /// neighbors
+    fn normal(&self, i: usize, j: usize) -> Vector3<T, U> {
+        let [nx, ny] = self.resolution;
+        let (x0, x1) = (i.saturating_sub(1), (i + 1).min(nx - 1));
+        let (y0, y1) = (j.saturating_sub(1), (j + 1).min(ny - 1));
+        let dx = self.point(x1, j) - self.point(x0, j);
+        let dy = self.point(i, y1) - self.point(i, y0);
+        dx.cross(dy).normalize()
+    }
+
+    /// Returns the corners of the lower or upper triangle of the cell at column `i` and row `j`
+    fn corners(i: usize, j: usize, upper: bool) -> Corners {
+        if upper {
+            [(i, j), (i + 1, j + 1), (i, j + 1)]
+        } else {
+            [(i, j), (i + 1, j), (i + 1, j + 1)]
+        }
+    }
+
+    /// Returns the triangles of the terrain, as a mesh with the same normals and `uv`
+    #[must_use]
+    pub fn to_mesh(&self) -> TriangleMesh<T, U> {
+        let [nx, ny] = self.resolution;
+        let points: Vec<_> = (0..ny).flat_map(|j| (0..nx).map(move |i| (i, j))).collect();
+        let index = |(i, j): (usize, usize)| (j * nx + i) as u32;
+        let mut indices = Vec::with_capacity(2 * (nx - 1) * (ny - 1));
+        for (i, j) in points.iter().filter(|(i, j)| *i < nx - 1 && *j < ny - 1) {
+            for upper in [false, true] {
+                indices.push(Self::corners(*i, *j, upper).map(index));
+            }
+        }
+        let positions = points.iter().map(|&(i, j)| self.point(i, j)).collect();
+        let mut mesh = TriangleMesh::new(positions, indices);
+        let normals = points.iter().map(|&(i, j)| self.normal(i, j).to_normal());
+        mesh.normals = Some(normals.collect());
+        mesh.uvs = Some(points.iter().map(|&(i, j)| self.uv(i, j)).collect());
+        mesh
+    }
+
+    /// Returns the texture coordinates of a point of the grid
+    fn uv(&self, i: usize, j: usize) -> Point2<T, UnknownUnit> {
+        let [nx, ny] = self.resolution;
+        Point2::new(
+            T::from(i).unwrap() / T::from(nx - 1).unwrap(),
+            T::from(j).unwrap() / T::from(ny - 1).unwrap(),
+        )
+    }
+
+    /// Returns the ray parameter, the barycentric coordinates of the second and third corners
+    /// and the corners of the closest triangle the ray hits
+    fn hit(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<(T, T, T, Corners)> {
+        let (mut t, t_exit) = self.bounds.intersect_ray(ray, t_max)?;
+        let [nx, ny] = self.resolution;
+        let o = ray.origin - self.origin;
+        let d = ray.dir;
+
+        // The cell the ray enters the bounds in, the parameters at which it crosses into the
+        // next column and row, and between the lines of cells
+        let cell = |o: T, d: T, spacing: T, n: usize| {
+            let position = (o + d * t) / spacing;
+            let cell = position.floor().max(T::zero()).min(T::from(n - 2).unwrap());
+            let cell = cell.to_usize().unwrap_or(0);
+            let boundary = if d > T::zero() { cell + 1 } else { cell };
+            let next = (T::from(boundary).unwrap() * spacing - o) / d;
+            let delta = spacing / d.abs();
+            // Rays parallel to an axis never cross into the next cell along it
+            if d == T::zero() {
+                (cell, T::infinity(), T::infinity())
+            } else {
+                (cell, next, delta)
+            }
+        };
+        let (mut i, mut next_x, delta_x) = cell(o.x, d.x, self.spacing.x, nx);
+        let (mut j, mut next_y, delta_y) = cell(o.y, d.y, self.spacing.y, ny);
+
+        loop {
+            let t_next = next_x.min(next_y).min(t_exit);
+            // The elevations the ray passes through in the cell, against those of the cell
+            let (z0, z1) = (o.z + d.z * t, o.z + d.z * t_next);
+            let corners = [(i, j), (i + 1, j), (i, j + 1), (i + 1, j + 1)];
+            let heights = corners.map(|(i, j)| self.heights[j * nx + i]);
+            let low = heights.iter().fold(T::infinity(), |low, &h| low.min(h));
+            let high = heights
+                .iter()
+                .fold(T::neg_infinity(), |high, &h| high.max(h));
+            if z0.min(z1) <= high && z0.max(z1) >= low {
+                let mut closest = None;
+                let mut t_max = t_max;
+                for upper in [false, true] {
+                    let corners = Self::corners(i, j, upper);
+                    let p = corners.map(|(i, j)| self.point(i, j));
+                    if let Some((t, b1, b2)) = hit(p, ray, t_max) {
+                        t_max = Time(t);
+                        closest = Some((t, b1, b2, corners));
+                    }
+                }
+                if closest.is_some() {
+                    return closest;
+                }
+            }
+
+            if t_next >= t_exit {
+                return None;
+            }
+            t = t_next;
+            if next_x < next_y {
+                i = if d.x > T::zero() {
+                    i + 1
+                } else {
+                    i.checked_sub(1)?
+                };
+                next_x = next_x + delta_x;
+                if i >= nx - 1 {
+                    return None;
+                }
+            } else {
+                j = if d.y > T::zero() {
+                    j + 1
+                } else {
+                    j.checked_sub(1)?
+                };
+                next_y = next_y + delta_y;
+                if j >= ny - 1 {
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+impl<T: Float, U> Shape<T, U> for Heightfield<T, U> {
+    #[inline]
+    fn bounds(&self) -> Box3<T, U> {
+        self.bounds
+    }
+
+    fn intersect(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<SurfaceInteraction<T, U>> {
+        let (t, b1, b2, corners) = self.hit(ray, t_max)?;
+        let vertices = Vertices {
+            p: corners.map(|(i, j)| self.point(i, j)),
+            normals: Some(corners.map(|(i, j)| self.normal(i, j))),
+            tangents: None,
+            uvs: Some(corners.map(|(i, j)| self.uv(i, j))),
+        };
+        Some(vertices.interaction(ray, t, b1, b2))
+    }
+
+    #[inline]
+    fn intersect_any(&self, ray: &Ray<T, U>, t_max: Time<T>) -> bool {
+        self.hit(ray, t_max).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{accel::Bvh, sampler::Pcg32, shape::Triangle};
+    use std::sync::Arc;
+
+    type P = Point3<f64, UnknownUnit>;
+    type V = Vector3<f64, UnknownUnit>;
+
+    #[test]
+    fn test_heightfield_matches_mesh() {
+        // Rolling hills, on a grid with more columns than rows
+        let [nx, ny] = [13, 7];
+        let heights = (0..ny)
+            .flat_map(|j| (0..nx).map(move |i| (i as f64 * 0.7).sin() + (j as f64 * 1.3).cos()))
+            .collect();
+        let terrain = Heightfield::new(
+            P::new(-3., 1., 0.5),
+            Vector2::new(6., 3.),
+            [nx, ny],
+            heights,
+        );
+        let mesh = Arc::new(terrain.to_mesh());
+        let triangles = Bvh::new(Triangle::from_mesh(&mesh).collect());
+        assert_eq!(terrain.bounds(), triangles.bounds());
+
+        let mut rng = Pcg32::new(7);
+        let (center, size) = (
+            terrain.bounds().center(),
+            terrain.bounds().max - terrain.bounds().min,
+        );
+        let mut hits = 0;
+        for n in 0..5000 {
+            let mut u = || rng.uniform::<f64>();
+            let origin = center + V::new(u() - 0.5, u() - 0.5, u() - 0.5) * (3. * size.length());
+            let target = center
+                + V::new(
+                    (u() - 0.5) * size.x,
+                    (u() - 0.5) * size.y,
+                    (u() - 0.5) * size.z,
+                );
+            // Some rays straight down, and some along the rows
+            let dir = match n % 10 {
+                0 => V::new(0., 0., -1.),
+                1 => V::new(target.x - origin.x, 0., target.z - origin.z),
+                _ => target - origin,
+            };
+            let ray = Ray::new(origin, dir);
+            let t_max = Time(f64::INFINITY);
+            let expected = triangles.intersect(&ray, t_max);
+            let hit = terrain.intersect(&ray, t_max);
+            assert_eq!(hit.is_some(), expected.is_some(), "{ray:?}");
+            assert_eq!(terrain.intersect_any(&ray, t_max), hit.is_some());
+            let (Some(hit), Some(expected)) = (hit, expected) else {
+                continue;
+            };
+            hits += 1;
+            assert!((hit.t.0 - expected.t.0).abs() < 1e-9, "{ray:?}");
+            assert!((hit.uv - expected.uv).length() < 1e-9);
+            assert!((hit.shading.n.to_vector() - expected.shading.n.to_vector()).length() < 1e-9);
+        }
+        assert!(hits > 1000, "{hits}");
+    }
+}
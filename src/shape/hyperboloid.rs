@@ -0,0 +1,107 @@
+use crate::{
+    core::{
+        geometry::{Box3, Point2, Point3, Ray, Vector3},
+        num::solve_quadratic,
+        units::Time,
+    },
+    shape::{azimuth, Shape, SurfaceInteraction},
+};
+use num_traits::{Float, FloatConst};
+
+/// The surface swept by revolving the segment between `center + p1` and `center + p2` around
+/// the z axis through `center`
+///
+/// The endpoints must lie at different heights. `u` goes around the z axis from +x, and `v` up
+/// from the lower endpoint.
+pub struct Hyperboloid<T, U> {
+    pub center: Point3<T, U>,
+    pub p1: Vector3<T, U>,
+    pub p2: Vector3<T, U>,
+}
+
+common_impls!(Hyperboloid { center, p1, p2 });
+
+impl<T, U> Hyperboloid<T, U> {
+    #[inline]
+    #[must_use]
+    pub const fn new(center: Point3<T, U>, p1: Vector3<T, U>, p2: Vector3<T, U>) -> Self {
+        Self { center, p1, p2 }
+    }
+}
+
+impl<T: Float, U> Hyperboloid<T, U> {
+    /// Returns the endpoints ordered by height
+    #[inline]
+    fn endpoints(&self) -> (Vector3<T, U>, Vector3<T, U>) {
+        if self.p1.z <= self.p2.z {
+            (self.p1, self.p2)
+        } else {
+            (self.p2, self.p1)
+        }
+    }
+
+    /// Returns the coefficients of the squared radius as a quadratic in `z`
+    fn radius_squared_coefficients(&self) -> (T, T, T) {
+        let (lo, hi) = self.endpoints();
+        let dz = hi.z - lo.z;
+        let (ax, ay) = ((hi.x - lo.x) / dz, (hi.y - lo.y) / dz);
+        let (bx, by) = (lo.x - lo.z * ax, lo.y - lo.z * ay);
+        let two = T::one() + T::one();
+        (
+            ax * ax + ay * ay,
+            two * (ax * bx + ay * by),
+            bx * bx + by * by,
+        )
+    }
+}
+
+impl<T: Float + FloatConst, U> Shape<T, U> for Hyperboloid<T, U> {
+    fn bounds(&self) -> Box3<T, U> {
+        // The squared radius is convex in `z`, so it is largest at one of the endpoints
+        let (lo, hi) = self.endpoints();
+        let r = (lo.x * lo.x + lo.y * lo.y)
+            .max(hi.x * hi.x + hi.y * hi.y)
+            .sqrt();
+        Box3::new(
+            self.center + Vector3::new(-r, -r, lo.z),
+            self.center + Vector3::new(r, r, hi.z),
+        )
+    }
+
+    fn intersect(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<SurfaceInteraction<T, U>> {
+        let o = ray.origin - self.center;
+        let d = ray.dir;
+        let (lo, hi) = self.endpoints();
+
+        // Points on the surface satisfy `x^2 + y^2 = ah z^2 + bh z + ch`
+        let (ah, bh, ch) = self.radius_squared_coefficients();
+        let two = T::one() + T::one();
+        let a = d.x * d.x + d.y * d.y - ah * d.z * d.z;
+        let b = two * (d.x * o.x + d.y * o.y - ah * d.z * o.z) - bh * d.z;
+        let c = o.x * o.x + o.y * o.y - ah * o.z * o.z - bh * o.z - ch;
+        let (t0, t1) = solve_quadratic(a, b, c)?;
+        let t = [t0, t1].into_iter().find(|&t| {
+            let z = o.z + d.z * t;
+            t > T::zero() && t <= t_max.0 && z >= lo.z && z <= hi.z
+        })?;
+
+        let t = Time(t);
+        let p = ray.at(t);
+        let local = p - self.center;
+
+        let phi = azimuth(local.x, local.y);
+        let height = hi.z - lo.z;
+        let uv = Point2::new(phi / T::TAU(), (local.z - lo.z) / height);
+        let dpdu = Vector3::new(-local.y, local.x, T::zero()) * T::TAU();
+        // d(r^2)/dz, which relates the change in radius to the change in height
+        let slope = two * ah * local.z + bh;
+        let radius_squared = (local.x * local.x + local.y * local.y).max(T::min_positive_value());
+        let radial = slope / (two * radius_squared);
+        let dpdv = Vector3::new(local.x * radial, local.y * radial, T::one()) * height;
+        let n = Vector3::new(local.x, local.y, -slope / two)
+            .normalize()
+            .to_normal();
+        let wo = -ray.dir.normalize();
+        Some(SurfaceInteraction::new(p, t, wo, n, uv, dpdu, dpdv))
+    }
+}
@@ -0,0 +1,345 @@
+use crate::core::{
+    geometry::{
+        transform::{Transform3, Transformation},
+        Point2, Point3, RayDifferential, UnknownUnit, Vector2, Vector3,
+    },
+    num::Zero,
+    prelude::Normal3,
+    units::Time,
+};
+use num_traits::Float;
+
+/// How many ulps of its coordinates ray origins are moved off surfaces, with a wide margin over
+/// the error of the points computed by shapes
+const OFFSET_ULPS: f64 = 256.;
+/// How many ulps of one ray origins are moved off surfaces at least, for coordinates near zero
+/// whose own ulps vanish
+const OFFSET_MIN_ULPS: f64 = 128.;
+/// The largest change of `uv` per pixel, which bounds the footprint of grazing hits
+const MAX_UV_DIFFERENTIAL: f64 = 1e8;
+
+pub struct Shading<T, U> {
+    pub n: Normal3<T, U>,
+    pub dpdu: Vector3<T, U>,
+    pub dpdv: Vector3<T, U>,
+    /// The point rays leave from towards the front of the surface, which may be moved off a
+    /// flat facet towards the smooth surface that the shading normals describe
+    pub p: Point3<T, U>,
+}
+
+common_impls!(Shading { n, dpdu, dpdv, p });
+
+/// The local differential geometry at a ray-surface intersection
+pub struct SurfaceInteraction<T, U> {
+    pub p: Point3<T, U>,
+    /// The ray parameter of the hit
+    pub t: Time<T>,
+    /// The moment of the shutter interval the hit was found at, which rays leaving the surface
+    /// keep
+    pub time: T,
+    /// Direction towards the ray origin
+    pub wo: Vector3<T, U>,
+    /// Geometric normal
+    pub n: Normal3<T, U>,
+    pub uv: Point2<T, UnknownUnit>,
+    pub dpdu: Vector3<T, U>,
+    pub dpdv: Vector3<T, U>,
+    /// Change of `p` per pixel along the x axis of the image, or zero if unknown
+    pub dpdx: Vector3<T, U>,
+    /// Change of `p` per pixel along the y axis of the image
+    pub dpdy: Vector3<T, U>,
+    /// Change of `uv` per pixel along the x axis of the image, for filtering textures, or zero
+    /// if unknown
+    pub duvdx: Vector2<T, UnknownUnit>,
+    /// Change of `uv` per pixel along the y axis of the image
+    pub duvdy: Vector2<T, UnknownUnit>,
+    /// Possibly perturbed geometry used for shading
+    pub shading: Shading<T, U>,
+    /// Index of the hit primitive within the aggregate that produced the hit
+    pub primitive: usize,
+    /// Index of the instance containing the hit primitive, for two-level hierarchies
+    pub instance: Option<usize>,
+}
+
+common_impls!(SurfaceInteraction {
+    p,
+    t,
+    time,
+    wo,
+    n,
+    uv,
+    dpdu,
+    dpdv,
+    dpdx,
+    dpdy,
+    duvdx,
+    duvdy,
+    shading,
+    primitive,
+    instance
+});
+
+impl<T: Copy + Zero, U> SurfaceInteraction<T, U> {
+    /// Creates an interaction whose shading geometry matches the geometric one
+    #[inline]
+    #[must_use]
+    pub fn new(
+        p: Point3<T, U>,
+        t: Time<T>,
+        wo: Vector3<T, U>,
+        n: Normal3<T, U>,
+        uv: Point2<T, UnknownUnit>,
+        dpdu: Vector3<T, U>,
+        dpdv: Vector3<T, U>,
+    ) -> Self {
+        Self {
+            p,
+            t,
+            time: T::zero(),
+            wo,
+            n,
+            uv,
+            dpdu,
+            dpdv,
+            dpdx: Vector3::zero(),
+            dpdy: Vector3::zero(),
+            duvdx: Vector2::zero(),
+            duvdy: Vector2::zero(),
+            shading: Shading { n, dpdu, dpdv, p },
+            primitive: 0,
+            instance: None,
+        }
+    }
+
+    /// Replaces the shading normal and tangents, keeping the point rays leave from
+    #[inline]
+    pub fn set_shading_geometry(
+        &mut self,
+        n: Normal3<T, U>,
+        dpdu: Vector3<T, U>,
+        dpdv: Vector3<T, U>,
+    ) {
+        self.shading = Shading {
+            n,
+            dpdu,
+            dpdv,
+            p: self.shading.p,
+        };
+    }
+}
+
+impl<T: Float, U> SurfaceInteraction<T, U> {
+    /// Returns the origin of a ray leaving the surface in the direction `w`, moved off the
+    /// surface along the geometric normal to the side of `w` so that the ray doesn't hit it
+    /// again
+    ///
+    /// Each coordinate is moved by a number of ulps of its own magnitude, which grows like the
+    /// error of computing the point, and by a small minimum near zero. Rays towards the front
+    /// start from the shading point, which avoids faceted shadow terminators on smoothly shaded
+    /// meshes.
+    #[must_use]
+    pub fn offset_origin(&self, w: Vector3<T, U>) -> Point3<T, U> {
+        let n = self.n.to_vector();
+        let (p, n) = if n.dot(w) < T::zero() {
+            (self.p, -n)
+        } else {
+            (self.shading.p, n)
+        };
+        let ulps = T::from(OFFSET_ULPS).unwrap() * T::epsilon();
+        let min = T::from(OFFSET_MIN_ULPS).unwrap() * T::epsilon();
+        let offset = |p: T, n: T| p + n * (p.abs() * ulps + min);
+        Point3::new(offset(p.x, n.x), offset(p.y, n.y), offset(p.z, n.z))
+    }
+
+    /// Estimates the footprint of a pixel on the surface from where the offset rays of
+    /// `differential` meet the tangent plane, filling in `dpdx`, `dpdy`, `duvdx` and `duvdy`
+    ///
+    /// The changes of `uv` are found by projecting those of `p` onto `dpdu` and `dpdv` in the
+    /// least squares sense. Everything is left at zero where the offset rays are parallel to
+    /// the plane or the parameterization is degenerate.
+    pub fn compute_differentials(&mut self, differential: &RayDifferential<T, U>) {
+        let n = self.n.to_vector();
+        let offset = |origin: Point3<T, U>, dir: Vector3<T, U>| {
+            let t = n.dot(self.p - origin) / n.dot(dir);
+            t.is_finite().then(|| origin + dir * t - self.p)
+        };
+        let (Some(dpdx), Some(dpdy)) = (
+            offset(differential.rx_origin, differential.rx_dir),
+            offset(differential.ry_origin, differential.ry_dir),
+        ) else {
+            self.dpdx = Vector3::zero();
+            self.dpdy = Vector3::zero();
+            self.duvdx = Vector2::zero();
+            self.duvdy = Vector2::zero();
+            return;
+        };
+
+        let (a00, a01, a11) = (
+            self.dpdu.dot(self.dpdu),
+            self.dpdu.dot(self.dpdv),
+            self.dpdv.dot(self.dpdv),
+        );
+        let inv_det = (a00 * a11 - a01 * a01).recip();
+        let max = T::from(MAX_UV_DIFFERENTIAL).unwrap();
+        let solve = |dp: Vector3<T, U>| {
+            let (b0, b1) = (self.dpdu.dot(dp), self.dpdv.dot(dp));
+            let clamp = |x: T| {
+                if x.is_finite() {
+                    x.max(-max).min(max)
+                } else {
+                    T::zero()
+                }
+            };
+            Vector2::new(
+                clamp((a11 * b0 - a01 * b1) * inv_det),
+                clamp((a00 * b1 - a01 * b0) * inv_det),
+            )
+        };
+        self.dpdx = dpdx;
+        self.dpdy = dpdy;
+        self.duvdx = solve(dpdx);
+        self.duvdy = solve(dpdy);
+    }
+
+    /// Returns the interaction as seen in another space
+    ///
+    /// Returns `None` if the transform is projective and maps the hit point to infinity.
+    #[must_use]
+    pub fn transform<V>(
+        &self,
+        transform: &Transform3<T, U, V>,
+    ) -> Option<SurfaceInteraction<T, V>> {
+        let normal = |n: Normal3<T, U>| transform.transform(n).normalize();
+        Some(SurfaceInteraction {
+            p: transform.transform(self.p).try_into().ok()?,
+            t: self.t,
+            time: self.time,
+            wo: transform.transform(self.wo).normalize(),
+            n: normal(self.n),
+            uv: self.uv,
+            dpdu: transform.transform(self.dpdu),
+            dpdv: transform.transform(self.dpdv),
+            dpdx: transform.transform(self.dpdx),
+            dpdy: transform.transform(self.dpdy),
+            duvdx: self.duvdx,
+            duvdy: self.duvdy,
+            shading: Shading {
+                n: normal(self.shading.n),
+                dpdu: transform.transform(self.shading.dpdu),
+                dpdv: transform.transform(self.shading.dpdv),
+                p: transform.transform(self.shading.p).try_into().ok()?,
+            },
+            primitive: self.primitive,
+            instance: self.instance,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        camera::{Camera, CameraSample, ThinLensCamera},
+        core::{
+            geometry::{
+                transform::{Scale, Transform3},
+                Point2, Point3, Ray, UnknownUnit, Vector2, Vector3,
+            },
+            units::{Angle, Time},
+        },
+        shape::{Rectangle, Shape, Sphere},
+    };
+
+    #[test]
+    fn test_offset_origin() {
+        // Far from the origin a fixed epsilon is below the precision of single floats
+        let center = Point3::<f32, UnknownUnit>::new(1e5, -3e4, 2e4);
+        let sphere = Sphere::new(center, 10.);
+        for i in 0..100 {
+            let angle = i as f32 * 0.7;
+            let target = center + Vector3::new(angle.cos(), angle.sin(), 0.3) * 5.;
+            let origin = center + Vector3::new(-30., 10., 5.);
+            let ray = Ray::new(origin, (target - origin).normalize());
+            let hit = sphere.intersect(&ray, Time(f32::INFINITY)).unwrap();
+
+            // Rays leaving outwards escape, and rays leaving inwards reach the far side
+            let w = hit.n.to_vector() + ray.dir * 0.5;
+            let out = Ray::new(hit.offset_origin(w), w);
+            assert!(!sphere.intersect_any(&out, Time(f32::INFINITY)));
+            let inside = Ray::new(hit.offset_origin(ray.dir), ray.dir);
+            let exit = sphere.intersect(&inside, Time(f32::INFINITY)).unwrap();
+            assert!((exit.p - hit.p).length() > 1.);
+        }
+    }
+
+    #[test]
+    fn test_compute_differentials() {
+        // A square of side 4 seen head-on from 2 away with a field of view of 90 degrees spans
+        // 2 / 4 of the image, so a pixel out of 100 covers 1 / 50 of its texture
+        let camera = ThinLensCamera::look_at(
+            Point3::<f64, UnknownUnit>::new(0., 0., 2.),
+            Point3::origin(),
+            Vector3::new(0., 1., 0.),
+            Angle::from_degrees(90.),
+            1.,
+        );
+        let square = Rectangle::new(
+            Point3::new(-2., -2., 0.),
+            Vector3::new(4., 0., 0.),
+            Vector3::new(0., 4., 0.),
+        );
+        let sample = CameraSample {
+            film: Point2::new(0.5, 0.5),
+            lens: Point2::new(0.5, 0.5),
+            time: 0.,
+        };
+        let (ray, differential) = camera.generate_ray_differential(&sample, 0.01, 0.01);
+        let mut hit = square.intersect(&ray, Time(f64::INFINITY)).unwrap();
+        hit.compute_differentials(&differential);
+        assert!((hit.dpdx - Vector3::new(0.04, 0., 0.)).length() < 1e-9);
+        // Rows of the image go down as `v` goes up
+        assert!((hit.duvdx - Vector2::new(0.01, 0.)).length() < 1e-9);
+        assert!((hit.duvdy - Vector2::new(0., -0.01)).length() < 1e-9);
+
+        // Offset rays parallel to the surface leave the footprint unknown
+        let mut parallel = differential;
+        parallel.rx_dir = Vector3::new(1., 0., 0.);
+        hit.compute_differentials(&parallel);
+        assert_eq!(hit.duvdx, Vector2::new(0., 0.));
+    }
+
+    #[test]
+    fn test_hit_record() {
+        let sphere = Sphere::new(Point3::<f64, UnknownUnit>::origin(), 1.);
+        let origin = Point3::new(3., 0., 3.);
+        let ray = Ray::new(origin, Vector3::new(-2., 0., -2.));
+        let mut hit = sphere.intersect(&ray, Time(f64::INFINITY)).unwrap();
+        let s = std::f64::consts::FRAC_1_SQRT_2;
+        assert!((hit.p - Point3::new(s, 0., s)).length() < 1e-12);
+        assert!((hit.t.0 - (3. - s) / 2.).abs() < 1e-12);
+        assert!((hit.wo - Vector3::new(s, 0., s)).length() < 1e-12);
+        assert!((hit.n.to_vector() - Vector3::new(s, 0., s)).length() < 1e-12);
+        // The shading geometry starts out as the geometric one
+        assert_eq!((hit.shading.n, hit.shading.p), (hit.n, hit.p));
+        assert_eq!((hit.shading.dpdu, hit.shading.dpdv), (hit.dpdu, hit.dpdv));
+        assert_eq!((hit.primitive, hit.instance), (0, None));
+
+        let n = Vector3::new(0., 0., 1.).to_normal();
+        hit.set_shading_geometry(n, hit.dpdv, hit.dpdu);
+        assert_eq!((hit.shading.n, hit.shading.p), (n, hit.p));
+        hit.instance = Some(3);
+
+        // Normals stay perpendicular to the tangents under non-uniform scaling
+        let transform = Transform3::<_, UnknownUnit, UnknownUnit>::scale(
+            Scale::new(2.),
+            Scale::new(1.),
+            Scale::new(1.),
+        );
+        let moved = hit.transform(&transform).unwrap();
+        assert!((moved.p - Point3::new(2. * s, 0., s)).length() < 1e-12);
+        assert!((moved.n.to_vector().length() - 1.).abs() < 1e-12);
+        assert!(moved.n.to_vector().dot(moved.dpdu).abs() < 1e-12);
+        assert!(moved.n.to_vector().dot(moved.dpdv).abs() < 1e-12);
+        assert_eq!((moved.t, moved.uv), (hit.t, hit.uv));
+        assert_eq!((moved.primitive, moved.instance), (0, Some(3)));
+    }
+}
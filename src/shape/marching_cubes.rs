@@ -0,0 +1,297 @@
+use crate::{
+    core::geometry::{Box3, Point3, Vector3},
+    medium::DensityGrid,
+    shape::{DistanceField, NormalWeighting, TriangleMesh},
+};
+use num_traits::Float;
+use std::collections::HashMap;
+
+/// The corners of a cell, indexed by their offsets along x, y and z as bits 0, 1 and 2
+const CORNERS: usize = 8;
+
+/// The edges of a cell, as pairs of corners, ordered by axis
+const EDGES: [[usize; 2]; 12] = {
+    let mut edges = [[0; 2]; 12];
+    let mut i = 0;
+    let mut axis = 0;
+    while axis < 3 {
+        let mut corner = 0;
+        while corner < CORNERS {
+            if corner & (1 << axis) == 0 {
+                edges[i] = [corner, corner | 1 << axis];
+                i += 1;
+            }
+            corner += 1;
+        }
+        axis += 1;
+    }
+    edges
+};
+
+/// Returns the edge between two adjacent corners
+fn edge(a: usize, b: usize) -> usize {
+    let pair = [a.min(b), a.max(b)];
+    EDGES.iter().position(|&e| e == pair).unwrap()
+}
+
+/// For every combination of corners inside the surface, the loops of edges crossed by it
+///
+/// Loops are built from the segments the surface leaves on each face of the cell, rather than
+/// listed by hand. Going around a face counter-clockwise as seen from outside, each segment
+/// runs from an edge entering the inside to the next one leaving it, which isolates the inside
+/// corners of faces with two of them diagonally opposite. Since that only depends on the face,
+/// neighboring cells agree on it and meshes have no cracks. Each crossed edge starts a segment
+/// on one of its faces and ends one on the other, so the segments close into loops, which wind
+/// counter-clockwise around the normal pointing outside.
+fn loop_table() -> Vec<Vec<Vec<usize>>> {
+    // The corners of each face, counter-clockwise as seen from outside
+    let mut faces = Vec::with_capacity(6);
+    for axis in 0..3 {
+        let (i, j) = ((axis + 1) % 3, (axis + 2) % 3);
+        for side in 0..2 {
+            let mut face =
+                [(0, 0), (1, 0), (1, 1), (0, 1)].map(|(u, v)| side << axis | u << i | v << j);
+            if side == 0 {
+                face.reverse();
+            }
+            faces.push(face);
+        }
+    }
+
+    (0..1 << CORNERS)
+        .map(|mask: usize| {
+            let inside = |corner: usize| mask & 1 << corner != 0;
+            let mut next = [None; 12];
+            for face in &faces {
+                let side = |k: usize| [face[k % 4], face[(k + 1) % 4]];
+                for k in 0..4 {
+                    let [a, b] = side(k);
+                    if inside(a) || !inside(b) {
+                        continue;
+                    }
+                    let end = (k + 1..k + 4)
+                        .map(side)
+                        .find(|&[a, b]| inside(a) && !inside(b))
+                        .unwrap();
+                    next[edge(a, b)] = Some(edge(end[0], end[1]));
+                }
+            }
+
+            let mut loops = Vec::new();
+            let mut visited = [false; 12];
+            for start in 0..12 {
+                if visited[start] || next[start].is_none() {
+                    continue;
+                }
+                let mut edges = Vec::new();
+                let mut e = start;
+                while !visited[e] {
+                    visited[e] = true;
+                    edges.push(e);
+                    e = next[e].unwrap();
+                }
+                loops.push(edges);
+            }
+            loops
+        })
+        .collect()
+}
+
+/// Extracts the surface where a function is zero, negative inside, as a triangle mesh
+///
+/// The function is sampled at the corners of `resolution` cells along each axis of `bounds`,
+/// and vertices are placed on the edges of cells by interpolating linearly between them
+/// (Lorensen and Cline, "Marching cubes"). Vertices are shared between neighboring cells, and
+/// their normals follow the gradient of the function. Surfaces reaching the bounds are left
+/// open there.
+///
+/// Panics if the resolution is zero along an axis.
+#[must_use]
+pub fn marching_cubes<T: Float, U>(
+    bounds: Box3<T, U>,
+    resolution: [usize; 3],
+    f: impl Fn(Point3<T, U>) -> T,
+) -> TriangleMesh<T, U> {
+    assert!(
+        resolution.iter().all(|&n| n > 0),
+        "marching cubes need at least one cell along each axis"
+    );
+    let [nx, ny, nz] = resolution.map(|n| n + 1);
+    let [sx, sy, sz] = resolution.map(|n| T::from(n).unwrap());
+    let cell = (bounds.max - bounds.min).component_div(Vector3::new(sx, sy, sz));
+    let point = |i: usize| {
+        let (x, y, z) = (i % nx, i / nx % ny, i / (nx * ny));
+        let offset = Vector3::new(
+            T::from(x).unwrap(),
+            T::from(y).unwrap(),
+            T::from(z).unwrap(),
+        );
+        bounds.min + offset.component_mul(cell)
+    };
+    let values: Vec<T> = (0..nx * ny * nz).map(|i| f(point(i))).collect();
+
+    let table = loop_table();
+    let mut positions = Vec::new();
+    let mut indices = Vec::new();
+    // The vertex on each crossed edge of the grid, by the points at its ends
+    let mut vertices = HashMap::new();
+    for z in 0..nz - 1 {
+        for y in 0..ny - 1 {
+            for x in 0..nx - 1 {
+                let corner =
+                    |c: usize| (x + (c & 1)) + ((y + (c >> 1 & 1)) + (z + (c >> 2)) * ny) * nx;
+                let mask = (0..CORNERS)
+                    .filter(|&c| values[corner(c)] < T::zero())
+                    .fold(0, |mask, c| mask | 1 << c);
+                for edges in &table[mask] {
+                    let ids: Vec<u32> = edges
+                        .iter()
+                        .map(|&e| {
+                            let [a, b] = EDGES[e].map(corner);
+                            *vertices.entry((a, b)).or_insert_with(|| {
+                                let w = values[a] / (values[a] - values[b]);
+                                positions.push(point(a) + (point(b) - point(a)) * w);
+                                (positions.len() - 1) as u32
+                            })
+                        })
+                        .collect();
+                    indices.extend((1..ids.len() - 1).map(|k| [ids[0], ids[k], ids[k + 1]]));
+                }
+            }
+        }
+    }
+
+    let mut mesh = TriangleMesh::new(positions, indices);
+    mesh.compute_vertex_normals(NormalWeighting::Angle);
+    let h = cell.x.min(cell.y).min(cell.z) * T::from(0.5).unwrap();
+    let gradient = |p: Point3<T, U>| {
+        let axis = |v: Vector3<T, U>| f(p + v) - f(p - v);
+        Vector3::new(
+            axis(Vector3::new(h, T::zero(), T::zero())),
+            axis(Vector3::new(T::zero(), h, T::zero())),
+            axis(Vector3::new(T::zero(), T::zero(), h)),
+        )
+    };
+    if let Some(normals) = &mut mesh.normals {
+        for (n, &p) in normals.iter_mut().zip(&mesh.positions) {
+            if let Some(g) = gradient(p).try_normalize() {
+                *n = g.to_normal();
+            }
+        }
+    }
+    mesh
+}
+
+/// Returns bounds grown by a cell on each side, and the resolution covering them, so that
+/// surfaces touching the bounds are closed
+fn padded<T: Float, U>(bounds: Box3<T, U>, resolution: [usize; 3]) -> (Box3<T, U>, [usize; 3]) {
+    let [sx, sy, sz] = resolution.map(|n| T::from(n.max(1)).unwrap());
+    let cell = (bounds.max - bounds.min).component_div(Vector3::new(sx, sy, sz));
+    let bounds = bounds.inflate(cell.x, cell.y, cell.z);
+    (bounds, resolution.map(|n| n.max(1) + 2))
+}
+
+impl<T: Float, U> TriangleMesh<T, U> {
+    /// Extracts the surface of a distance field with [`marching_cubes`], with `resolution`
+    /// cells along each axis of its bounds
+    #[must_use]
+    pub fn from_distance_field(field: &impl DistanceField<T, U>, resolution: [usize; 3]) -> Self {
+        let (bounds, resolution) = padded(field.bounds(), resolution);
+        marching_cubes(bounds, resolution, |p| field.distance(p))
+    }
+
+    /// Extracts the surface where a density grid stretched over `bounds`, like that of a
+    /// [`GridMedium`](crate::medium::GridMedium), crosses `iso`, with [`marching_cubes`]
+    ///
+    /// The density is taken to be zero outside of the bounds.
+    #[must_use]
+    pub fn from_density_grid(
+        grid: &DensityGrid<T>,
+        bounds: Box3<T, U>,
+        iso: T,
+        resolution: [usize; 3],
+    ) -> Self {
+        let extent = bounds.max - bounds.min;
+        let density = |p: Point3<T, U>| {
+            if !bounds.contains(p) {
+                return T::zero();
+            }
+            grid.lookup((p - bounds.min).component_div(extent).to_array())
+        };
+        let (padded, resolution) = padded(bounds, resolution);
+        marching_cubes(padded, resolution, |p| iso - density(p))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::geometry::UnknownUnit,
+        shape::{BoxField, SphereField},
+    };
+    use std::collections::HashSet;
+
+    type P = Point3<f64, UnknownUnit>;
+    type V = Vector3<f64, UnknownUnit>;
+
+    /// Checks that every edge is shared by exactly two triangles going along it in opposite
+    /// directions, so that the mesh is closed and consistently oriented
+    fn check_closed(name: &str, mesh: &TriangleMesh<f64, UnknownUnit>) {
+        let mut edges = HashSet::new();
+        for &[a, b, c] in &mesh.indices {
+            for edge in [(a, b), (b, c), (c, a)] {
+                assert!(edges.insert(edge), "{name}: {edge:?} repeated");
+            }
+        }
+        for &(a, b) in &edges {
+            assert!(edges.contains(&(b, a)), "{name}: {:?} is open", (a, b));
+        }
+        assert!(!mesh.indices.is_empty(), "{name}");
+    }
+
+    #[test]
+    fn test_marching_cubes() {
+        let (c, r) = (P::new(1., -2., 0.5), 2.);
+        let sphere = TriangleMesh::from_distance_field(&SphereField::new(c, r), [16; 3]);
+        check_closed("sphere", &sphere);
+        let normals = sphere.normals.as_ref().unwrap();
+        for (p, n) in sphere.positions.iter().zip(normals) {
+            assert!(((*p - c).length() - r).abs() < 0.02);
+            assert!(n.to_vector().dot((*p - c).normalize()) > 0.999);
+        }
+        for triangle in 0..sphere.triangle_count() {
+            let [p0, p1, p2] = sphere.vertices(triangle);
+            assert!((p1 - p0).cross(p2 - p0).dot(p0 - c) > 0.);
+        }
+
+        // Surfaces touching the bounds of fields are closed too
+        let cube = BoxField::new(Box3::new(P::new(-1., -1., -1.), P::new(1., 1., 1.)), 0.);
+        check_closed("box", &TriangleMesh::from_distance_field(&cube, [4, 5, 6]));
+
+        // A gyroid clipped by a ball crosses cells in every way, including those where the
+        // surface is ambiguous
+        let bounds = Box3::new(P::new(-4., -4., -4.), P::new(4., 4., 4.));
+        let gyroid = marching_cubes(bounds, [23, 24, 25], |p| {
+            let v = p.x.sin() * p.y.cos() + p.y.sin() * p.z.cos() + p.z.sin() * p.x.cos();
+            v.max(p.to_vector().length() - 3.5)
+        });
+        check_closed("gyroid", &gyroid);
+
+        // A dense ball in the middle of a grid, extracted halfway between its density and none
+        let values = (0..512)
+            .map(|i| {
+                let p = V::new((i % 8) as f64, (i / 8 % 8) as f64, (i / 64) as f64);
+                if (p - V::new(3.5, 3.5, 3.5)).length() < 2. {
+                    1.
+                } else {
+                    0.
+                }
+            })
+            .collect();
+        let grid = DensityGrid::new([8; 3], values);
+        let ball = TriangleMesh::from_density_grid(&grid, bounds, 0.5, [16; 3]);
+        check_closed("density", &ball);
+        assert!(ball.bounds().max.x < 3. && ball.bounds().min.x > -3.);
+    }
+}
@@ -0,0 +1,282 @@
+use crate::core::{
+    geometry::{Box3, Point2, Point3, UnknownUnit, Vector3},
+    prelude::Normal3,
+};
+use num_traits::Float;
+use std::fmt;
+
+/// A per-vertex tangent for normal mapping
+///
+/// The bitangent is recovered as `sign * cross(n, tangent)`, so that mirrored texture
+/// coordinates are supported.
+pub struct Tangent<T, U> {
+    pub tangent: Vector3<T, U>,
+    pub sign: T,
+}
+
+common_impls!(Tangent { tangent, sign });
+
+impl<T: Float, U> Tangent<T, U> {
+    #[inline]
+    #[must_use]
+    pub fn bitangent(&self, n: Normal3<T, U>) -> Vector3<T, U> {
+        n.to_vector().cross(self.tangent) * self.sign
+    }
+}
+
+/// How much each face contributes to the normals of its vertices
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum NormalWeighting {
+    /// In proportion to the face's area, favoring large faces
+    Area,
+    /// In proportion to the face's angle at the vertex, which makes the result independent of how
+    /// a surface is tessellated
+    Angle,
+}
+
+/// An indexed triangle mesh
+///
+/// Triangles are counter-clockwise when seen from the front. The optional per-vertex attributes
+/// must have one entry per position.
+pub struct TriangleMesh<T, U> {
+    pub positions: Vec<Point3<T, U>>,
+    pub indices: Vec<[u32; 3]>,
+    pub normals: Option<Vec<Normal3<T, U>>>,
+    pub tangents: Option<Vec<Tangent<T, U>>>,
+    pub uvs: Option<Vec<Point2<T, UnknownUnit>>>,
+}
+
+impl<T: fmt::Debug, U> fmt::Debug for TriangleMesh<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TriangleMesh")
+            .field("positions", &self.positions)
+            .field("indices", &self.indices)
+            .field("normals", &self.normals)
+            .field("tangents", &self.tangents)
+            .field("uvs", &self.uvs)
+            .finish()
+    }
+}
+
+impl<T: Clone, U> Clone for TriangleMesh<T, U> {
+    fn clone(&self) -> Self {
+        Self {
+            positions: self.positions.clone(),
+            indices: self.indices.clone(),
+            normals: self.normals.clone(),
+            tangents: self.tangents.clone(),
+            uvs: self.uvs.clone(),
+        }
+    }
+}
+
+impl<T, U> TriangleMesh<T, U> {
+    #[inline]
+    #[must_use]
+    pub fn new(positions: Vec<Point3<T, U>>, indices: Vec<[u32; 3]>) -> Self {
+        Self {
+            positions,
+            indices,
+            normals: None,
+            tangents: None,
+            uvs: None,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn vertex_indices(&self, triangle: usize) -> [usize; 3] {
+        self.indices[triangle].map(|i| i as usize)
+    }
+}
+
+impl<T: Float, U> TriangleMesh<T, U> {
+    #[inline]
+    #[must_use]
+    pub fn vertices(&self, triangle: usize) -> [Point3<T, U>; 3] {
+        self.vertex_indices(triangle).map(|i| self.positions[i])
+    }
+
+    #[must_use]
+    pub fn bounds(&self) -> Box3<T, U> {
+        let mut positions = self.positions.iter().copied();
+        let Some(first) = positions.next() else {
+            return Box3::new(Point3::origin(), Point3::origin());
+        };
+        let (min, max) = positions.fold((first, first), |(min, max), p| (min.min(p), max.max(p)));
+        Box3::new(min, max)
+    }
+
+    /// Computes smooth vertex normals, replacing any existing ones
+    pub fn compute_vertex_normals(&mut self, weighting: NormalWeighting) {
+        let mut normals = vec![Vector3::zero(); self.positions.len()];
+        for triangle in 0..self.triangle_count() {
+            let indices = self.vertex_indices(triangle);
+            let p = indices.map(|i| self.positions[i]);
+            // Twice the area, in the direction of the face normal
+            let area_normal = (p[1] - p[0]).cross(p[2] - p[0]);
+            let face_normal = match weighting {
+                NormalWeighting::Area => area_normal,
+                NormalWeighting::Angle => match area_normal.try_normalize() {
+                    Some(n) => n,
+                    None => continue,
+                },
+            };
+            for corner in 0..3 {
+                let weight = match weighting {
+                    NormalWeighting::Area => T::one(),
+                    NormalWeighting::Angle => {
+                        let e1 = p[(corner + 1) % 3] - p[corner];
+                        let e2 = p[(corner + 2) % 3] - p[corner];
+                        e1.cross(e2).length().atan2(e1.dot(e2))
+                    }
+                };
+                let i = indices[corner];
+                normals[i] = normals[i] + face_normal * weight;
+            }
+        }
+
+        let fallback = Vector3::new(T::zero(), T::zero(), T::one());
+        self.normals = Some(
+            normals
+                .into_iter()
+                .map(|n| n.try_normalize().unwrap_or(fallback).to_normal())
+                .collect(),
+        );
+    }
+
+    /// Computes per-vertex tangents aligned with the direction of increasing `u`, replacing any
+    /// existing ones
+    ///
+    /// Vertex normals are computed first if the mesh has none. Without texture coordinates there
+    /// is no preferred direction, so an arbitrary tangent perpendicular to the normal is chosen.
+    pub fn compute_tangents(&mut self) {
+        if self.normals.is_none() {
+            self.compute_vertex_normals(NormalWeighting::Angle);
+        }
+
+        let vertex_count = self.positions.len();
+        let mut tangents = vec![Vector3::zero(); vertex_count];
+        let mut bitangents = vec![Vector3::zero(); vertex_count];
+        if let Some(uvs) = &self.uvs {
+            for triangle in 0..self.triangle_count() {
+                let indices = self.vertex_indices(triangle);
+                let p = indices.map(|i| self.positions[i]);
+                let uv = indices.map(|i| uvs[i]);
+                let (dp1, dp2) = (p[1] - p[0], p[2] - p[0]);
+                let (duv1, duv2) = (uv[1] - uv[0], uv[2] - uv[0]);
+                let determinant = duv1.x * duv2.y - duv1.y * duv2.x;
+                if determinant == T::zero() {
+                    continue;
+                }
+                // Left unnormalized, so larger faces get a larger say
+                let r = determinant.recip();
+                let tangent = (dp1 * duv2.y - dp2 * duv1.y) * r;
+                let bitangent = (dp2 * duv1.x - dp1 * duv2.x) * r;
+                for i in indices {
+                    tangents[i] = tangents[i] + tangent;
+                    bitangents[i] = bitangents[i] + bitangent;
+                }
+            }
+        }
+
+        let normals = self.normals.as_ref().unwrap();
+        self.tangents = Some(
+            (0..vertex_count)
+                .map(|i| {
+                    let n = normals[i].to_vector();
+                    // Gram-Schmidt against the normal
+                    let tangent = (tangents[i] - n * n.dot(tangents[i]))
+                        .try_normalize()
+                        .unwrap_or_else(|| n.coordinate_system().0);
+                    let sign = if n.cross(tangent).dot(bitangents[i]) < T::zero() {
+                        -T::one()
+                    } else {
+                        T::one()
+                    };
+                    Tangent { tangent, sign }
+                })
+                .collect(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::num::ApproxEq;
+
+    type P = Point3<f32, UnknownUnit>;
+    type V = Vector3<f32, UnknownUnit>;
+
+    #[test]
+    fn test_compute_vertex_normals() {
+        // Two perpendicular faces sharing the edge from (0, 0, 0) to (0, 1, 0), one of them split
+        // into more triangles than the other
+        let mut mesh = TriangleMesh::new(
+            vec![
+                P::new(0., 0., 0.),
+                P::new(0., 1., 0.),
+                P::new(1., 1., 0.),
+                P::new(1., 0., 0.),
+                P::new(0., 0., 1.),
+                P::new(0., 1., 1.),
+                P::new(0.5, 0.5, 0.),
+            ],
+            vec![
+                [0, 6, 1],
+                [1, 6, 2],
+                [2, 6, 3],
+                [3, 6, 0],
+                [0, 1, 5],
+                [0, 5, 4],
+            ],
+        );
+
+        mesh.compute_vertex_normals(NormalWeighting::Angle);
+        let normals = mesh.normals.as_ref().unwrap();
+        let expected = V::new(1., 0., 1.).normalize();
+        assert!(normals[0].to_vector().approx_eq(&expected));
+        assert!(normals[1].to_vector().approx_eq(&expected));
+        assert!(normals[6].to_vector().approx_eq(&V::new(0., 0., 1.)));
+
+        mesh.compute_vertex_normals(NormalWeighting::Area);
+        let normals = mesh.normals.as_ref().unwrap();
+        let expected = V::new(2., 0., 1.).normalize();
+        assert!(normals[0].to_vector().approx_eq(&expected));
+    }
+
+    #[test]
+    fn test_compute_tangents() {
+        let mut mesh = TriangleMesh::new(
+            vec![
+                P::new(0., 0., 0.),
+                P::new(2., 0., 0.),
+                P::new(2., 2., 0.),
+                P::new(0., 2., 0.),
+            ],
+            vec![[0, 1, 2], [0, 2, 3]],
+        );
+        // Mirrored horizontally
+        mesh.uvs = Some(vec![
+            Point2::new(1., 0.),
+            Point2::new(0., 0.),
+            Point2::new(0., 1.),
+            Point2::new(1., 1.),
+        ]);
+        mesh.compute_tangents();
+
+        let n = mesh.normals.as_ref().unwrap()[0];
+        assert!(n.to_vector().approx_eq(&V::new(0., 0., 1.)));
+        for tangent in mesh.tangents.as_ref().unwrap() {
+            assert!(tangent.tangent.approx_eq(&V::new(-1., 0., 0.)));
+            assert!(tangent.bitangent(n).approx_eq(&V::new(0., 1., 0.)));
+        }
+    }
+}
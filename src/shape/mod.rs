@@ -0,0 +1,395 @@
+mod box_shape;
+mod capsule;
+mod curve;
+mod cylinder;
+mod disk;
+mod heightfield;
+mod hyperboloid;
+mod interaction;
+mod marching_cubes;
+mod mesh;
+mod moving_mesh;
+mod paraboloid;
+mod plane;
+mod point_cloud;
+mod rectangle;
+mod sdf;
+mod simplify;
+mod sphere;
+mod torus;
+mod triangle;
+
+pub use box_shape::BoxShape;
+pub use capsule::Capsule;
+pub use curve::{Curve, CurveBasis, CurveKind};
+pub use cylinder::Cylinder;
+pub use disk::Disk;
+pub use heightfield::Heightfield;
+pub use hyperboloid::Hyperboloid;
+pub use interaction::{Shading, SurfaceInteraction};
+pub use marching_cubes::marching_cubes;
+pub use mesh::{NormalWeighting, Tangent, TriangleMesh};
+pub use moving_mesh::{MovingMesh, MovingTriangle};
+pub use paraboloid::Paraboloid;
+pub use plane::{Plane, PlaneSide};
+pub use point_cloud::{PointCloud, Surfel};
+pub use rectangle::Rectangle;
+pub use sdf::{
+    BoxField, Difference, DistanceField, FnField, GridField, Intersection, Sdf, SmoothUnion,
+    SphereField, TorusField, Union,
+};
+pub use sphere::Sphere;
+pub use torus::Torus;
+pub use triangle::Triangle;
+
+use crate::core::{
+    geometry::{Box3, Point2, Point3, Ray, UnknownUnit, Vector3},
+    prelude::Normal3,
+    units::Time,
+};
+use num_traits::{Float, FloatConst};
+use std::{rc::Rc, sync::Arc};
+
+/// Geometry that can be intersected by rays
+///
+/// Only hits with `0 < t <= t_max` are reported, where `t` is measured in multiples of the ray
+/// direction.
+///
+/// Hits carry texture coordinates, within `[0, 1]^2` on bounded shapes, and their partial
+/// derivatives `dpdu` and `dpdv`, which are tangent to the surface with `dpdu x dpdv` along the
+/// geometric normal so that tangent frames are oriented alike on every shape.
+pub trait Shape<T, U> {
+    #[must_use]
+    fn bounds(&self) -> Box3<T, U>;
+
+    /// Returns the closest hit along the ray
+    #[must_use]
+    fn intersect(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<SurfaceInteraction<T, U>>;
+
+    /// Returns whether there is any hit along the ray
+    ///
+    /// Shapes override this to skip computing the interaction, and aggregates to stop at the
+    /// first hit found instead of the closest one, which makes it much cheaper for shadow rays.
+    #[inline]
+    #[must_use]
+    fn intersect_any(&self, ray: &Ray<T, U>, t_max: Time<T>) -> bool {
+        self.intersect(ray, t_max).is_some()
+    }
+
+    /// Returns the vertices of shapes that are a single triangle, whose hits are entirely
+    /// given by them, so that other code such as [`GpuBvh`](crate::accel::GpuBvh) may trace
+    /// them
+    #[inline]
+    #[must_use]
+    fn triangle(&self) -> Option<[Point3<T, U>; 3]> {
+        None
+    }
+}
+
+macro_rules! deref_impls {
+    ($($ty:ty),+) => {$(
+        impl<T, U, S: Shape<T, U> + ?Sized> Shape<T, U> for $ty {
+            #[inline]
+            fn bounds(&self) -> Box3<T, U> {
+                (**self).bounds()
+            }
+
+            #[inline]
+            fn intersect(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<SurfaceInteraction<T, U>> {
+                (**self).intersect(ray, t_max)
+            }
+
+            #[inline]
+            fn intersect_any(&self, ray: &Ray<T, U>, t_max: Time<T>) -> bool {
+                (**self).intersect_any(ray, t_max)
+            }
+
+            #[inline]
+            fn triangle(&self) -> Option<[Point3<T, U>; 3]> {
+                (**self).triangle()
+            }
+        }
+    )+};
+}
+
+deref_impls!(&S, Box<S>, Rc<S>, Arc<S>);
+
+/// A point sampled on the surface of a shape
+pub struct ShapeSample<T, U> {
+    pub p: Point3<T, U>,
+    /// The geometric normal, oriented like that of the hits at `p`
+    pub n: Normal3<T, U>,
+    /// The density of the sample with respect to surface area
+    pub pdf: T,
+}
+
+common_impls!(ShapeSample { p, n, pdf });
+
+/// Shapes whose surface can be sampled, such as those emitting light
+pub trait SampleShape<T, U>: Shape<T, U> {
+    #[must_use]
+    fn area(&self) -> T;
+
+    /// Samples a point uniformly over the surface
+    #[must_use]
+    fn sample(&self, u: Point2<T, UnknownUnit>) -> ShapeSample<T, U>;
+
+    /// Returns the normal shared by every point of the surface, for flat shapes
+    #[inline]
+    #[must_use]
+    fn normal(&self) -> Option<Normal3<T, U>>
+    where
+        T: Float,
+    {
+        None
+    }
+
+    /// Samples a point of the surface seen from `p`, with a density with respect to the solid
+    /// angle at `p`
+    ///
+    /// By default, this converts the density of a uniform sample from area to solid angle.
+    /// Shapes which can sample the directions they subtend directly override this, since that
+    /// spends no samples on the far side and is less noisy up close.
+    #[must_use]
+    fn sample_from(&self, p: Point3<T, U>, u: Point2<T, UnknownUnit>) -> Option<ShapeSample<T, U>>
+    where
+        T: Float,
+    {
+        to_solid_angle(self.sample(u), p)
+    }
+
+    /// Returns the density with which [`sample_from`](Self::sample_from) picks the direction
+    /// `wi` at `p`
+    #[must_use]
+    fn pdf_from(&self, p: Point3<T, U>, wi: Vector3<T, U>) -> T
+    where
+        T: Float,
+    {
+        solid_angle_pdf(self, p, wi)
+    }
+}
+
+/// Converts the density of a sample of a surface from area to the solid angle at `p`
+fn to_solid_angle<T: Float, U>(
+    mut sample: ShapeSample<T, U>,
+    p: Point3<T, U>,
+) -> Option<ShapeSample<T, U>> {
+    let wi = (sample.p - p).try_normalize()?;
+    let cos = sample.n.to_vector().dot(wi).abs();
+    sample.pdf = sample.pdf * (sample.p - p).length_squared() / cos;
+    sample.pdf.is_finite().then_some(sample)
+}
+
+/// Returns the solid angle density at `p` of sampling the surface uniformly and hitting it in
+/// the direction `wi`
+fn solid_angle_pdf<T: Float, U, S: SampleShape<T, U> + ?Sized>(
+    shape: &S,
+    p: Point3<T, U>,
+    wi: Vector3<T, U>,
+) -> T {
+    let Some(hit) = shape.intersect(&Ray::with_time(p, wi, T::zero()), Time(T::infinity())) else {
+        return T::zero();
+    };
+    let cos = hit.n.to_vector().dot(wi).abs() / wi.length();
+    let pdf = (hit.p - p).length_squared() / (cos * shape.area());
+    if pdf.is_finite() {
+        pdf
+    } else {
+        T::zero()
+    }
+}
+
+macro_rules! deref_sample_impls {
+    ($($ty:ty),+) => {$(
+        impl<T, U, S: SampleShape<T, U> + ?Sized> SampleShape<T, U> for $ty {
+            #[inline]
+            fn area(&self) -> T {
+                (**self).area()
+            }
+
+            #[inline]
+            fn sample(&self, u: Point2<T, UnknownUnit>) -> ShapeSample<T, U> {
+                (**self).sample(u)
+            }
+
+            #[inline]
+            fn normal(&self) -> Option<Normal3<T, U>>
+            where
+                T: Float,
+            {
+                (**self).normal()
+            }
+
+            #[inline]
+            fn sample_from(
+                &self,
+                p: Point3<T, U>,
+                u: Point2<T, UnknownUnit>,
+            ) -> Option<ShapeSample<T, U>>
+            where
+                T: Float,
+            {
+                (**self).sample_from(p, u)
+            }
+
+            #[inline]
+            fn pdf_from(&self, p: Point3<T, U>, wi: Vector3<T, U>) -> T
+            where
+                T: Float,
+            {
+                (**self).pdf_from(p, wi)
+            }
+        }
+    )+};
+}
+
+deref_sample_impls!(&S, Box<S>, Rc<S>, Arc<S>);
+
+/// Returns the angle of `(x, y)` around the z axis, in `[0, 2pi)`
+#[inline]
+fn azimuth<T: Float + FloatConst>(x: T, y: T) -> T {
+    let phi = y.atan2(x);
+    if phi < T::zero() {
+        phi + T::TAU()
+    } else {
+        phi
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{core::geometry::Vector3, sampler::Pcg32};
+
+    type P = Point3<f64, UnknownUnit>;
+    type V = Vector3<f64, UnknownUnit>;
+
+    /// Checks that the texture coordinates of hits span `[0, 1]^2` and that the partial
+    /// derivatives are tangent, face like the normal and match the change of `uv` between
+    /// nearby hits
+    fn check_parameterization(name: &str, shape: &dyn Shape<f64, UnknownUnit>) {
+        let mut rng = Pcg32::new(3);
+        let bounds = shape.bounds();
+        let (center, size) = (bounds.center(), bounds.max - bounds.min);
+        let mut hits = 0;
+        for _ in 0..2000 {
+            let mut u = || rng.uniform::<f64>();
+            let dir = V::new(u() - 0.5, u() - 0.5, u() - 0.5).normalize();
+            let origin = center + dir * (2. * size.length());
+            let target = bounds.min + V::new(u() * size.x, u() * size.y, u() * size.z);
+            let ray = Ray::new(origin, target - origin);
+            let Some(hit) = shape.intersect(&ray, Time(f64::INFINITY)) else {
+                continue;
+            };
+            hits += 1;
+            let n = hit.n.to_vector();
+            let (uv, dpdu, dpdv) = (hit.uv, hit.dpdu, hit.dpdv);
+            assert!(
+                (0. ..=1.).contains(&uv.x) && (0. ..=1.).contains(&uv.y),
+                "{name}: {uv:?}"
+            );
+            assert!(dpdu.dot(n).abs() < 1e-6 * dpdu.length(), "{name}: {dpdu:?}");
+            assert!(dpdv.dot(n).abs() < 1e-6 * dpdv.length(), "{name}: {dpdv:?}");
+            assert!(
+                dpdu.cross(dpdv).dot(n) > 0.,
+                "{name}: faces inwards at {uv:?}"
+            );
+
+            // Skip grazing hits, whose neighbors may be far along the ray
+            if ray.dir.normalize().dot(n).abs() < 0.3 {
+                continue;
+            }
+            for (dp, axis) in [(dpdu, 0), (dpdv, 1)] {
+                // A step of the same small length on every shape
+                let h = 1e-7 / dp.length();
+                let mut expected = [0.; 2];
+                expected[axis] = h;
+                let ray = Ray::new(origin, hit.p + dp * h - origin);
+                let Some(next) = shape.intersect(&ray, Time(f64::INFINITY)) else {
+                    continue;
+                };
+                // Across seams and edges
+                if next.n.to_vector().dot(n) < 0.99 || (next.uv.x - uv.x).abs() > 0.5 {
+                    continue;
+                }
+                let du = [
+                    next.uv.x - uv.x - expected[0],
+                    next.uv.y - uv.y - expected[1],
+                ];
+                assert!(
+                    du[0].abs() < 1e-2 * h && du[1].abs() < 1e-2 * h,
+                    "{name}: dpdu and dpdv disagree with uv at {uv:?}"
+                );
+            }
+        }
+        assert!(hits > 100, "{name}");
+    }
+
+    #[test]
+    fn test_parameterizations() {
+        let c = P::new(1., -2., 0.5);
+        check_parameterization("sphere", &Sphere::new(c, 2.));
+        check_parameterization("disk", &Disk::new(c, 2., 0.5));
+        check_parameterization("cylinder", &Cylinder::new(c, 1., -1., 2.));
+        check_parameterization("box", &BoxShape::new(Box3::new(c, P::new(3., 1., 1.))));
+        check_parameterization("capsule", &Capsule::new(c, P::new(2., 0., 3.), 0.5));
+        check_parameterization("torus", &Torus::new(c, 2., 0.5));
+        check_parameterization(
+            "hyperboloid",
+            &Hyperboloid::new(c, V::new(1., 0., -1.), V::new(0.5, 0.5, 1.)),
+        );
+        check_parameterization("paraboloid", &Paraboloid::new(c, 1., 0., 2.));
+        check_parameterization(
+            "rectangle",
+            &Rectangle::new(c, V::new(2., 1., 0.), V::new(0., 1., 3.)),
+        );
+        let mut cloud = PointCloud::new(vec![c], 2.);
+        cloud.normals = Some(vec![V::new(1., -2., 2.).to_normal()]);
+        check_parameterization("surfel", &Surfel::new(Arc::new(cloud), 0));
+    }
+
+    #[test]
+    fn test_intersect_any() {
+        let c = P::new(1., -2., 0.5);
+        let mesh = Arc::new(TriangleMesh::new(
+            vec![c, P::new(3., -1., 0.), P::new(0., 1., 2.)],
+            vec![[0, 1, 2]],
+        ));
+        let shapes: [(&str, &dyn Shape<f64, UnknownUnit>); 6] = [
+            ("sphere", &Sphere::new(c, 2.)),
+            ("disk", &Disk::new(c, 2., 0.5)),
+            (
+                "plane",
+                &Plane::from_point_normal(c, V::new(1., 1., -2.).to_normal()),
+            ),
+            ("box", &BoxShape::new(Box3::new(c, P::new(3., 1., 1.)))),
+            ("triangle", &Triangle::new(mesh, 0)),
+            ("torus", &Torus::new(c, 2., 0.5)),
+        ];
+        let mut rng = Pcg32::new(5);
+        for (name, shape) in shapes {
+            let mut hits = 0;
+            for i in 0..1000 {
+                let mut u = || rng.uniform::<f64>() - 0.5;
+                // From outside, and from around the center of the shape
+                let scale = if i % 2 == 0 { 10. } else { 1. };
+                let origin = c + V::new(u(), u(), u()) * scale;
+                let ray = Ray::new(origin, V::new(u(), u(), u()));
+                let hit = shape.intersect(&ray, Time(f64::INFINITY));
+                assert_eq!(
+                    shape.intersect_any(&ray, Time(f64::INFINITY)),
+                    hit.is_some()
+                );
+                let Some(hit) = hit else {
+                    continue;
+                };
+                hits += 1;
+                // The closest hit decides, whether the ray stops just short of it or past it
+                let t = hit.t.0;
+                assert!(!shape.intersect_any(&ray, Time(t * (1. - 1e-9))), "{name}");
+                assert!(shape.intersect_any(&ray, Time(t * (1. + 1e-9))), "{name}");
+            }
+            assert!(hits > 40, "{name}: {hits}");
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use crate::{
+    core::{
+        geometry::{Box3, Ray},
+        units::Time,
+    },
+    shape::{
+        triangle::{hit, Vertices},
+        Shape, SurfaceInteraction, TriangleMesh,
+    },
+};
+use num_traits::Float;
+use std::{fmt, sync::Arc};
+
+/// A triangle mesh whose vertices move over the shutter interval, between keys at increasing
+/// moments of it, such as baked vertex animation
+///
+/// Between keys, positions and normals are interpolated linearly. Before the first key and
+/// after the last, the mesh stays at that key. Every key has the same triangles, and the
+/// texture coordinates of the first are used throughout.
+pub struct MovingMesh<T, U> {
+    keys: Vec<(T, TriangleMesh<T, U>)>,
+}
+
+impl<T: fmt::Debug, U> fmt::Debug for MovingMesh<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MovingMesh")
+            .field("keys", &self.keys)
+            .finish()
+    }
+}
+
+impl<T: Float, U> MovingMesh<T, U> {
+    /// # Panics
+    ///
+    /// Panics if there are no keys, their moments are not increasing, or their triangles or
+    /// numbers of vertices differ.
+    #[must_use]
+    pub fn new(keys: Vec<(T, TriangleMesh<T, U>)>) -> Self {
+        assert!(!keys.is_empty(), "moving meshes need a key");
+        assert!(
+            keys.windows(2).all(|pair| pair[0].0 < pair[1].0),
+            "the keys of moving meshes must be in increasing order"
+        );
+        let first = &keys[0].1;
+        assert!(
+            keys.iter().all(|(_, mesh)| mesh.indices == first.indices
+                && mesh.positions.len() == first.positions.len()
+                && mesh.normals.as_ref().map(Vec::len) == first.normals.as_ref().map(Vec::len)),
+            "the keys of moving meshes must have the same triangles"
+        );
+        Self { keys }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn keys(&self) -> &[(T, TriangleMesh<T, U>)] {
+        &self.keys
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn triangle_count(&self) -> usize {
+        self.keys[0].1.triangle_count()
+    }
+
+    /// Returns the vertices of a triangle at a moment of the shutter interval
+    fn vertices(&self, triangle: usize, time: T) -> Vertices<T, U> {
+        let next = self.keys.partition_point(|(t, _)| *t <= time);
+        let (a, b, w) = match next {
+            0 => (0, 0, T::zero()),
+            next if next == self.keys.len() => (next - 1, next - 1, T::zero()),
+            next => {
+                let (t0, t1) = (self.keys[next - 1].0, self.keys[next].0);
+                (next - 1, next, (time - t0) / (t1 - t0))
+            }
+        };
+        let (a, b) = (&self.keys[a].1, &self.keys[b].1);
+        let indices = a.vertex_indices(triangle);
+        let normals = a.normals.as_ref().zip(b.normals.as_ref());
+        Vertices {
+            p: indices.map(|i| a.positions[i] + (b.positions[i] - a.positions[i]) * w),
+            normals: normals.map(|(na, nb)| {
+                indices.map(|i| {
+                    let n = na[i].to_vector();
+                    n + (nb[i].to_vector() - n) * w
+                })
+            }),
+            tangents: None,
+            uvs: a.uvs.as_ref().map(|uvs| indices.map(|i| uvs[i])),
+        }
+    }
+}
+
+/// A single triangle of a shared [`MovingMesh`]
+pub struct MovingTriangle<T, U> {
+    pub mesh: Arc<MovingMesh<T, U>>,
+    pub index: usize,
+}
+
+impl<T: fmt::Debug, U> fmt::Debug for MovingTriangle<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MovingTriangle")
+            .field("index", &self.index)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, U> Clone for MovingTriangle<T, U> {
+    fn clone(&self) -> Self {
+        Self {
+            mesh: Arc::clone(&self.mesh),
+            index: self.index,
+        }
+    }
+}
+
+impl<T: Float, U> MovingTriangle<T, U> {
+    #[inline]
+    #[must_use]
+    pub fn new(mesh: Arc<MovingMesh<T, U>>, index: usize) -> Self {
+        Self { mesh, index }
+    }
+
+    /// Returns every triangle of the mesh
+    pub fn from_mesh(mesh: &Arc<MovingMesh<T, U>>) -> impl Iterator<Item = Self> + '_ {
+        (0..mesh.triangle_count()).map(|index| Self::new(Arc::clone(mesh), index))
+    }
+}
+
+impl<T: Float, U> Shape<T, U> for MovingTriangle<T, U> {
+    /// Returns the bounds of the triangle over the whole shutter interval, which are those of
+    /// its keys since it moves in straight lines between them
+    fn bounds(&self) -> Box3<T, U> {
+        let mut points = self
+            .mesh
+            .keys
+            .iter()
+            .flat_map(|(_, mesh)| mesh.vertices(self.index));
+        let first = points.next().unwrap();
+        let (min, max) = points.fold((first, first), |(min, max), p| (min.min(p), max.max(p)));
+        Box3::new(min, max)
+    }
+
+    fn intersect(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<SurfaceInteraction<T, U>> {
+        let vertices = self.mesh.vertices(self.index, ray.time);
+        let (t, b1, b2) = hit(vertices.p, ray, t_max)?;
+        Some(vertices.interaction(ray, t, b1, b2))
+    }
+
+    #[inline]
+    fn intersect_any(&self, ray: &Ray<T, U>, t_max: Time<T>) -> bool {
+        hit(self.mesh.vertices(self.index, ray.time).p, ray, t_max).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::geometry::{Point3, UnknownUnit, Vector3};
+
+    #[test]
+    fn test_moving_triangle() {
+        // A triangle sliding two units along x over the shutter interval, with a pause between
+        // the last keys
+        let key = |x: f64| {
+            TriangleMesh::<f64, UnknownUnit>::new(
+                vec![
+                    Point3::new(x, 0., 0.),
+                    Point3::new(x + 1., 0., 0.),
+                    Point3::new(x, 1., 0.),
+                ],
+                vec![[0, 1, 2]],
+            )
+        };
+        let mesh = MovingMesh::new(vec![(0., key(0.)), (0.5, key(2.)), (1., key(2.))]);
+        let triangle = MovingTriangle::new(Arc::new(mesh), 0);
+        let bounds = triangle.bounds();
+        assert_eq!(bounds.min, Point3::new(0., 0., 0.));
+        assert_eq!(bounds.max, Point3::new(3., 1., 0.));
+
+        let ray = |x: f64, time: f64| {
+            Ray::with_time(Point3::new(x, 0.25, 1.), Vector3::new(0., 0., -1.), time)
+        };
+        let t_max = Time(f64::INFINITY);
+        assert!(triangle.intersect_any(&ray(0.5, 0.), t_max));
+        assert!(!triangle.intersect_any(&ray(0.5, 0.5), t_max));
+        let hit = triangle.intersect(&ray(1.5, 0.25), t_max).unwrap();
+        assert!((hit.p - Point3::new(1.5, 0.25, 0.)).length() < 1e-12);
+        assert!((hit.uv.x - 0.75).abs() < 1e-12);
+        assert!(triangle.intersect_any(&ray(2.5, 0.75), t_max));
+    }
+}
@@ -0,0 +1,85 @@
+use crate::{
+    core::{
+        geometry::{Box3, Point2, Point3, Ray, Vector3},
+        num::solve_quadratic,
+        units::Time,
+    },
+    shape::{azimuth, Shape, SurfaceInteraction},
+};
+use num_traits::{Float, FloatConst};
+
+/// A paraboloid opening towards +z with its apex at `center`, reaching `radius` at `z_max`
+///
+/// Only the part between `z_min` and `z_max` above the apex is kept. `u` goes around the z axis
+/// from +x, and `v` up from `z_min`.
+pub struct Paraboloid<T, U> {
+    pub center: Point3<T, U>,
+    pub radius: T,
+    pub z_min: T,
+    pub z_max: T,
+}
+
+common_impls!(Paraboloid {
+    center,
+    radius,
+    z_min,
+    z_max
+});
+
+impl<T, U> Paraboloid<T, U> {
+    #[inline]
+    #[must_use]
+    pub const fn new(center: Point3<T, U>, radius: T, z_min: T, z_max: T) -> Self {
+        Self {
+            center,
+            radius,
+            z_min,
+            z_max,
+        }
+    }
+}
+
+impl<T: Float + FloatConst, U> Shape<T, U> for Paraboloid<T, U> {
+    #[inline]
+    fn bounds(&self) -> Box3<T, U> {
+        let r = self.radius;
+        Box3::new(
+            self.center + Vector3::new(-r, -r, self.z_min),
+            self.center + Vector3::new(r, r, self.z_max),
+        )
+    }
+
+    fn intersect(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<SurfaceInteraction<T, U>> {
+        let o = ray.origin - self.center;
+        let d = ray.dir;
+
+        // Points on the surface satisfy `z = k (x^2 + y^2)`
+        let two = T::one() + T::one();
+        let k = self.z_max / (self.radius * self.radius);
+        let a = k * (d.x * d.x + d.y * d.y);
+        let b = two * k * (d.x * o.x + d.y * o.y) - d.z;
+        let c = k * (o.x * o.x + o.y * o.y) - o.z;
+        let (t0, t1) = solve_quadratic(a, b, c)?;
+        let t = [t0, t1].into_iter().find(|&t| {
+            let z = o.z + d.z * t;
+            t > T::zero() && t <= t_max.0 && z >= self.z_min && z <= self.z_max
+        })?;
+
+        let t = Time(t);
+        let p = ray.at(t);
+        let local = p - self.center;
+
+        let phi = azimuth(local.x, local.y);
+        let height = self.z_max - self.z_min;
+        let uv = Point2::new(phi / T::TAU(), (local.z - self.z_min) / height);
+        let dpdu = Vector3::new(-local.y, local.x, T::zero()) * T::TAU();
+        // The radial derivative is unbounded at the apex, where x and y vanish as well
+        let z = local.z.max(T::min_positive_value());
+        let dpdv = Vector3::new(local.x / (two * z), local.y / (two * z), T::one()) * height;
+        let n = Vector3::new(two * k * local.x, two * k * local.y, -T::one())
+            .normalize()
+            .to_normal();
+        let wo = -ray.dir.normalize();
+        Some(SurfaceInteraction::new(p, t, wo, n, uv, dpdu, dpdv))
+    }
+}
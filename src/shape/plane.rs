@@ -0,0 +1,224 @@
+use crate::{
+    core::{
+        geometry::{Box3, Point2, Point3, Ray},
+        prelude::Normal3,
+        units::Time,
+    },
+    shape::{Shape, Sphere, SurfaceInteraction},
+};
+use num_traits::Float;
+
+/// Which side of a plane a volume lies on
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum PlaneSide {
+    /// Entirely on the side the normal points to
+    Front,
+    /// Entirely on the side opposite to the normal
+    Back,
+    /// Crossing the plane
+    Intersecting,
+}
+
+/// The set of points `p` for which `dot(normal, p) == distance`
+///
+/// The normal is expected to be normalized.
+pub struct Plane<T, U> {
+    pub normal: Normal3<T, U>,
+    pub distance: T,
+}
+
+common_impls!(Plane { normal, distance });
+
+impl<T, U> Plane<T, U> {
+    #[inline]
+    #[must_use]
+    pub const fn new(normal: Normal3<T, U>, distance: T) -> Self {
+        Self { normal, distance }
+    }
+}
+
+impl<T: Float, U> Plane<T, U> {
+    #[inline]
+    #[must_use]
+    pub fn from_point_normal(p: Point3<T, U>, normal: Normal3<T, U>) -> Self {
+        let normal = normal.to_vector().normalize();
+        Self::new(normal.to_normal(), normal.dot(p.to_vector()))
+    }
+
+    /// Returns the plane through the three points, facing the side from which they appear in
+    /// counter-clockwise order
+    #[must_use]
+    pub fn from_points(a: Point3<T, U>, b: Point3<T, U>, c: Point3<T, U>) -> Option<Self> {
+        let normal = (b - a).cross(c - a).try_normalize()?;
+        Some(Self::new(normal.to_normal(), normal.dot(a.to_vector())))
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn flip(self) -> Self {
+        Self::new(-self.normal, -self.distance)
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn signed_distance(&self, p: Point3<T, U>) -> T {
+        self.normal.to_vector().dot(p.to_vector()) - self.distance
+    }
+
+    /// Returns the point on the plane closest to `p`
+    #[inline]
+    #[must_use]
+    pub fn project(&self, p: Point3<T, U>) -> Point3<T, U> {
+        p - self.normal.to_vector() * self.signed_distance(p)
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn classify_box(&self, b: &Box3<T, U>) -> PlaneSide {
+        let two = T::one() + T::one();
+        let half_extent = (b.max - b.min) / two;
+        let n = self.normal;
+        let radius =
+            n.x.abs() * half_extent.x + n.y.abs() * half_extent.y + n.z.abs() * half_extent.z;
+        self.classify(self.signed_distance(b.center()), radius)
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn classify_sphere(&self, s: &Sphere<T, U>) -> PlaneSide {
+        self.classify(self.signed_distance(s.center), s.radius)
+    }
+
+    /// Returns the ray parameter where the ray crosses the plane
+    #[inline]
+    fn hit_t(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<T> {
+        let denom = self.normal.to_vector().dot(ray.dir);
+        if denom == T::zero() {
+            return None;
+        }
+        let t = -self.signed_distance(ray.origin) / denom;
+        (t > T::zero() && t <= t_max.0).then_some(t)
+    }
+
+    #[inline]
+    fn classify(&self, distance: T, radius: T) -> PlaneSide {
+        if distance > radius {
+            PlaneSide::Front
+        } else if distance < -radius {
+            PlaneSide::Back
+        } else {
+            PlaneSide::Intersecting
+        }
+    }
+}
+
+impl<T: Float, U> Shape<T, U> for Plane<T, U> {
+    /// Planes are unbounded, so only axis-aligned ones have finite extent along any axis
+    fn bounds(&self) -> Box3<T, U> {
+        let mut min = Point3::splat(T::neg_infinity());
+        let mut max = Point3::splat(T::infinity());
+        let n = self.normal;
+        if n.y == T::zero() && n.z == T::zero() {
+            (min.x, max.x) = (self.distance / n.x, self.distance / n.x);
+        } else if n.x == T::zero() && n.z == T::zero() {
+            (min.y, max.y) = (self.distance / n.y, self.distance / n.y);
+        } else if n.x == T::zero() && n.y == T::zero() {
+            (min.z, max.z) = (self.distance / n.z, self.distance / n.z);
+        }
+        Box3::new(min, max)
+    }
+
+    fn intersect(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<SurfaceInteraction<T, U>> {
+        let t = Time(self.hit_t(ray, t_max)?);
+        let p = ray.at(t);
+        let (dpdu, dpdv) = self.normal.to_vector().coordinate_system();
+        let local = p.to_vector();
+        let uv = Point2::new(local.dot(dpdu), local.dot(dpdv));
+        let wo = -ray.dir.normalize();
+        Some(SurfaceInteraction::new(
+            p,
+            t,
+            wo,
+            self.normal,
+            uv,
+            dpdu,
+            dpdv,
+        ))
+    }
+
+    #[inline]
+    fn intersect_any(&self, ray: &Ray<T, U>, t_max: Time<T>) -> bool {
+        self.hit_t(ray, t_max).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::geometry::{UnknownUnit, Vector3};
+
+    type P = Point3<f64, UnknownUnit>;
+
+    #[test]
+    fn test_classify() {
+        // The plane z = 1
+        let plane = Plane::from_point_normal(P::new(3., -2., 1.), Normal3::new(0., 0., 2.));
+        assert_eq!(plane.signed_distance(P::new(5., 7., 4.)), 3.);
+        assert_eq!(plane.project(P::new(5., 7., -4.)), P::new(5., 7., 1.));
+
+        let boxes = [
+            (
+                Box3::new(P::new(-1., -1., 2.), P::new(1., 1., 3.)),
+                PlaneSide::Front,
+            ),
+            (
+                Box3::new(P::new(-1., -1., -3.), P::new(1., 1., 0.)),
+                PlaneSide::Back,
+            ),
+            (
+                Box3::new(P::new(-1., -1., 0.), P::new(1., 1., 3.)),
+                PlaneSide::Intersecting,
+            ),
+            // Touching counts as crossing
+            (
+                Box3::new(P::new(-1., -1., 1.), P::new(1., 1., 2.)),
+                PlaneSide::Intersecting,
+            ),
+        ];
+        let spheres = [
+            (Sphere::new(P::new(4., 0., 3.), 1.), PlaneSide::Front),
+            (Sphere::new(P::new(0., 4., -1.), 1.), PlaneSide::Back),
+            (
+                Sphere::new(P::new(0., 0., 1.5), 1.),
+                PlaneSide::Intersecting,
+            ),
+        ];
+        let flipped = |side| match side {
+            PlaneSide::Front => PlaneSide::Back,
+            PlaneSide::Back => PlaneSide::Front,
+            PlaneSide::Intersecting => PlaneSide::Intersecting,
+        };
+        for (b, side) in &boxes {
+            assert_eq!(plane.classify_box(b), *side, "{b:?}");
+            assert_eq!(plane.flip().classify_box(b), flipped(*side), "{b:?}");
+        }
+        for (s, side) in &spheres {
+            assert_eq!(plane.classify_sphere(s), *side, "{s:?}");
+            assert_eq!(plane.flip().classify_sphere(s), flipped(*side), "{s:?}");
+        }
+
+        // Tilted planes account for the extent of boxes along their normal
+        let tilted =
+            Plane::from_points(P::origin(), P::new(-1., 1., 0.), P::new(0., 0., 1.)).unwrap();
+        let corner = Box3::new(P::new(0.6, 0.6, 0.), P::new(2., 2., 1.));
+        assert_eq!(tilted.classify_box(&corner), PlaneSide::Front);
+        let straddling = Box3::new(P::new(-0.4, -0.4, 0.), P::new(0.5, 0.5, 1.));
+        assert_eq!(tilted.classify_box(&straddling), PlaneSide::Intersecting);
+        assert_eq!(tilted.flip().classify_box(&corner), PlaneSide::Back);
+
+        let ray = Ray::new(P::new(0., 0., 5.), Vector3::new(0., 0., -2.));
+        let hit = plane.intersect(&ray, Time(f64::INFINITY)).unwrap();
+        assert_eq!((hit.t, hit.p), (Time(2.), P::new(0., 0., 1.)));
+        assert!(!plane.intersect_any(&ray, Time(1.9)));
+    }
+}
@@ -0,0 +1,236 @@
+use crate::{
+    core::{
+        geometry::{Box3, Point2, Point3, Ray, Vector3},
+        prelude::Normal3,
+        units::Time,
+    },
+    shape::{Shape, Sphere, SurfaceInteraction},
+};
+use num_traits::{Float, FloatConst};
+use std::{fmt, sync::Arc};
+
+/// A cloud of points sampled from a surface, such as by a scanner, rendered as small disks
+/// called surfels
+///
+/// Points with normals become disks facing them, which can be hit from either side, and points
+/// without become spheres. The attributes must have one entry per position.
+pub struct PointCloud<T, U> {
+    pub positions: Vec<Point3<T, U>>,
+    pub radii: Vec<T>,
+    pub normals: Option<Vec<Normal3<T, U>>>,
+}
+
+impl<T: fmt::Debug, U> fmt::Debug for PointCloud<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PointCloud")
+            .field("positions", &self.positions)
+            .field("radii", &self.radii)
+            .field("normals", &self.normals)
+            .finish()
+    }
+}
+
+impl<T: Clone, U> Clone for PointCloud<T, U> {
+    fn clone(&self) -> Self {
+        Self {
+            positions: self.positions.clone(),
+            radii: self.radii.clone(),
+            normals: self.normals.clone(),
+        }
+    }
+}
+
+impl<T: Copy, U> PointCloud<T, U> {
+    /// Creates a cloud of points of the same radius, without normals
+    #[inline]
+    #[must_use]
+    pub fn new(positions: Vec<Point3<T, U>>, radius: T) -> Self {
+        Self {
+            radii: vec![radius; positions.len()],
+            positions,
+            normals: None,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+}
+
+/// A single point of a shared [`PointCloud`]
+///
+/// Disks are parameterized over the square around them, with `u` and `v` along the tangents
+/// of their normal, so that the `uv` of their hits lie within the inscribed circle.
+pub struct Surfel<T, U> {
+    pub cloud: Arc<PointCloud<T, U>>,
+    pub index: usize,
+}
+
+impl<T: fmt::Debug, U> fmt::Debug for Surfel<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Surfel")
+            .field("index", &self.index)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, U> Clone for Surfel<T, U> {
+    fn clone(&self) -> Self {
+        Self {
+            cloud: Arc::clone(&self.cloud),
+            index: self.index,
+        }
+    }
+}
+
+impl<T, U> Surfel<T, U> {
+    #[inline]
+    #[must_use]
+    pub fn new(cloud: Arc<PointCloud<T, U>>, index: usize) -> Self {
+        Self { cloud, index }
+    }
+
+    /// Returns every point of the cloud, to be put into an accelerator
+    pub fn from_cloud(cloud: &Arc<PointCloud<T, U>>) -> impl Iterator<Item = Self> + '_ {
+        (0..cloud.positions.len()).map(|index| Self::new(Arc::clone(cloud), index))
+    }
+}
+
+impl<T: Float, U> Surfel<T, U> {
+    #[inline]
+    fn center(&self) -> Point3<T, U> {
+        self.cloud.positions[self.index]
+    }
+
+    #[inline]
+    fn radius(&self) -> T {
+        self.cloud.radii[self.index]
+    }
+
+    /// Returns the normal of the disk, or none for a sphere, also when it is degenerate
+    #[inline]
+    fn normal(&self) -> Option<Vector3<T, U>> {
+        let normals = self.cloud.normals.as_ref()?;
+        normals[self.index].to_vector().try_normalize()
+    }
+
+    /// Returns the ray parameter and the hit position relative to the center of the disk
+    /// facing `n`
+    #[inline]
+    fn hit(&self, n: Vector3<T, U>, ray: &Ray<T, U>, t_max: Time<T>) -> Option<(T, Vector3<T, U>)> {
+        let cos = ray.dir.dot(n);
+        if cos == T::zero() {
+            return None;
+        }
+        let center = self.center();
+        let t = (center - ray.origin).dot(n) / cos;
+        if !(t > T::zero() && t <= t_max.0) {
+            return None;
+        }
+        let offset = ray.at(Time(t)) - center;
+        // The hit is projected so that it lies exactly in the disk's plane
+        let offset = offset - n * offset.dot(n);
+        let r = self.radius();
+        (offset.length_squared() <= r * r).then_some((t, offset))
+    }
+}
+
+impl<T: Float + FloatConst, U> Shape<T, U> for Surfel<T, U> {
+    fn bounds(&self) -> Box3<T, U> {
+        let (center, r) = (self.center(), self.radius());
+        // A disk reaches out along each axis by the radius times the sine of its tilt from it
+        let extent = match self.normal() {
+            Some(n) => {
+                let reach = |a: T| r * (T::one() - a * a).max(T::zero()).sqrt();
+                Vector3::new(reach(n.x), reach(n.y), reach(n.z))
+            }
+            None => Vector3::new(r, r, r),
+        };
+        Box3::new(center - extent, center + extent)
+    }
+
+    fn intersect(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<SurfaceInteraction<T, U>> {
+        let Some(n) = self.normal() else {
+            return Sphere::new(self.center(), self.radius()).intersect(ray, t_max);
+        };
+        let (t, offset) = self.hit(n, ray, t_max)?;
+        let r = self.radius();
+        let (s, t_axis) = n.coordinate_system();
+        let half = T::from(0.5).unwrap();
+        let uv = Point2::new(
+            half + offset.dot(s) / (r + r),
+            half + offset.dot(t_axis) / (r + r),
+        );
+        let wo = -ray.dir.normalize();
+        let p = self.center() + offset;
+        Some(SurfaceInteraction::new(
+            p,
+            Time(t),
+            wo,
+            n.to_normal(),
+            uv,
+            s * (r + r),
+            t_axis * (r + r),
+        ))
+    }
+
+    #[inline]
+    fn intersect_any(&self, ray: &Ray<T, U>, t_max: Time<T>) -> bool {
+        match self.normal() {
+            Some(n) => self.hit(n, ray, t_max).is_some(),
+            None => Sphere::new(self.center(), self.radius()).intersect_any(ray, t_max),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{accel::Bvh, core::geometry::UnknownUnit};
+
+    type P = Point3<f64, UnknownUnit>;
+    type V = Vector3<f64, UnknownUnit>;
+
+    #[test]
+    fn test_point_cloud() {
+        // A row of disks tilted about y, behind which is a sphere
+        let mut cloud = PointCloud::new(
+            vec![
+                P::new(0., 0., 0.),
+                P::new(2., 0., 0.),
+                P::new(4., 0., 0.),
+                P::new(2., 0., -5.),
+            ],
+            0.5,
+        );
+        let tilted = V::new(1., 0., 1.).normalize().to_normal();
+        // Points with a degenerate normal are spheres too
+        cloud.normals = Some(vec![tilted, tilted, tilted, V::zero().to_normal()]);
+        cloud.radii[3] = 3.;
+        let cloud = Arc::new(cloud);
+        let points = Bvh::new(Surfel::from_cloud(&cloud).collect());
+        let bounds = Surfel::new(Arc::clone(&cloud), 0).bounds();
+        let reach = 0.5 * 0.5f64.sqrt();
+        assert!((bounds.max - P::new(reach, 0.5, reach)).length() < 1e-12);
+
+        let down = |x: f64, y: f64| Ray::new(P::new(x, y, 10.), V::new(0., 0., -1.));
+        let t_max = Time(f64::INFINITY);
+        let hit = points.intersect(&down(2.2, 0.1), t_max).unwrap();
+        assert!((hit.p - P::new(2.2, 0.1, -0.2)).length() < 1e-12);
+        assert!(hit.dpdu.cross(hit.dpdv).dot(hit.n.to_vector()) > 0.);
+        assert!((0. ..=1.).contains(&hit.uv.x) && (0. ..=1.).contains(&hit.uv.y));
+
+        // Past the rim of the disks, rays reach the sphere
+        let hit = points.intersect(&down(2., 0.6), t_max).unwrap();
+        assert!((hit.p.z - 0.6f64.mul_add(-0.6, 9.).sqrt() + 5.).abs() < 1e-9);
+        assert!(!points.intersect_any(&down(6., 0.), t_max));
+    }
+}
@@ -0,0 +1,150 @@
+use crate::{
+    core::{
+        geometry::{Box3, Point2, Point3, Ray, UnknownUnit, Vector3},
+        prelude::Normal3,
+        units::Time,
+    },
+    shape::{SampleShape, Shape, ShapeSample, SurfaceInteraction},
+};
+use num_traits::Float;
+
+/// A parallelogram spanned by two edges from a corner, facing along `edge0 × edge1`
+///
+/// `u` goes along `edge0` and `v` along `edge1`, so a rectangle is the special case of
+/// perpendicular edges.
+pub struct Rectangle<T, U> {
+    pub corner: Point3<T, U>,
+    pub edge0: Vector3<T, U>,
+    pub edge1: Vector3<T, U>,
+}
+
+common_impls!(Rectangle {
+    corner,
+    edge0,
+    edge1
+});
+
+impl<T, U> Rectangle<T, U> {
+    #[inline]
+    #[must_use]
+    pub const fn new(corner: Point3<T, U>, edge0: Vector3<T, U>, edge1: Vector3<T, U>) -> Self {
+        Self {
+            corner,
+            edge0,
+            edge1,
+        }
+    }
+}
+
+impl<T: Float, U> Rectangle<T, U> {
+    /// Returns the ray parameter and the coordinates of the hit along the edges
+    #[inline]
+    fn hit(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<(T, T, T)> {
+        let n = self.edge0.cross(self.edge1);
+        let denom = n.dot(ray.dir);
+        if denom == T::zero() {
+            return None;
+        }
+        let t = n.dot(self.corner - ray.origin) / denom;
+        if !(t > T::zero() && t <= t_max.0) {
+            return None;
+        }
+
+        let d = ray.origin + ray.dir * t - self.corner;
+        let inv_len_squared = n.length_squared().recip();
+        let u = d.cross(self.edge1).dot(n) * inv_len_squared;
+        let v = self.edge0.cross(d).dot(n) * inv_len_squared;
+        let unit = |x: T| x >= T::zero() && x <= T::one();
+        (unit(u) && unit(v)).then_some((t, u, v))
+    }
+}
+
+impl<T: Float, U> Shape<T, U> for Rectangle<T, U> {
+    #[inline]
+    fn bounds(&self) -> Box3<T, U> {
+        let c = self.corner;
+        let far = c + self.edge0 + self.edge1;
+        let (p0, p1) = (c + self.edge0, c + self.edge1);
+        Box3::new(c.min(p0).min(p1).min(far), c.max(p0).max(p1).max(far))
+    }
+
+    fn intersect(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<SurfaceInteraction<T, U>> {
+        let (t, u, v) = self.hit(ray, t_max)?;
+        // Interpolating the edges is more accurate than evaluating the ray
+        let p = self.corner + self.edge0 * u + self.edge1 * v;
+        let n = self.edge0.cross(self.edge1).normalize().to_normal();
+        let wo = -ray.dir.normalize();
+        Some(SurfaceInteraction::new(
+            p,
+            Time(t),
+            wo,
+            n,
+            Point2::new(u, v),
+            self.edge0,
+            self.edge1,
+        ))
+    }
+
+    #[inline]
+    fn intersect_any(&self, ray: &Ray<T, U>, t_max: Time<T>) -> bool {
+        self.hit(ray, t_max).is_some()
+    }
+}
+
+impl<T: Float, U> SampleShape<T, U> for Rectangle<T, U> {
+    #[inline]
+    fn area(&self) -> T {
+        self.edge0.cross(self.edge1).length()
+    }
+
+    #[inline]
+    fn normal(&self) -> Option<Normal3<T, U>> {
+        Some(self.edge0.cross(self.edge1).normalize().to_normal())
+    }
+
+    fn sample(&self, u: Point2<T, UnknownUnit>) -> ShapeSample<T, U> {
+        let n = self.edge0.cross(self.edge1);
+        let area = n.length();
+        ShapeSample {
+            p: self.corner + self.edge0 * u.x + self.edge1 * u.y,
+            n: (n / area).to_normal(),
+            pdf: area.recip(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rectangle() {
+        let rect = Rectangle::<f64, UnknownUnit>::new(
+            Point3::new(-1., -1., 2.),
+            Vector3::new(2., 0., 0.),
+            Vector3::new(0., 4., 0.),
+        );
+        assert_eq!(rect.area(), 8.);
+        assert_eq!(
+            rect.bounds(),
+            Box3::new(Point3::new(-1., -1., 2.), Point3::new(1., 3., 2.))
+        );
+
+        let ray = Ray::new(Point3::new(0.5, 2., 0.), Vector3::new(0., 0., 1.));
+        let hit = rect.intersect(&ray, Time(f64::INFINITY)).unwrap();
+        assert_eq!(hit.t, Time(2.));
+        assert_eq!(hit.uv, Point2::new(0.75, 0.75));
+        assert_eq!(hit.n, Vector3::new(0., 0., 1.).to_normal());
+        let outside = Ray::new(Point3::new(1.5, 0., 0.), Vector3::new(0., 0., 1.));
+        assert!(!rect.intersect_any(&outside, Time(f64::INFINITY)));
+
+        // The solid angle density of a sample matches the density of its direction
+        let p = Point3::new(0., 0., 0.);
+        let sample = rect.sample_from(p, Point2::new(0.5, 0.25)).unwrap();
+        assert_eq!(sample.p, Point3::new(0., 0., 2.));
+        assert!((sample.pdf - 4. / 8.).abs() < 1e-12);
+        let wi = sample.p - p;
+        assert!((rect.pdf_from(p, wi) - sample.pdf).abs() < 1e-12);
+        assert_eq!(rect.pdf_from(p, -wi), 0.);
+    }
+}
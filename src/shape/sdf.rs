@@ -0,0 +1,547 @@
+use crate::{
+    core::{
+        geometry::{Box3, Point2, Point3, Ray, Vector3},
+        units::Time,
+    },
+    medium::DensityGrid,
+    shape::{Shape, SurfaceInteraction},
+};
+use num_traits::Float;
+use std::{fmt, rc::Rc, sync::Arc};
+
+/// The default number of steps taken along a ray before giving up on finding a hit
+const MAX_STEPS: usize = 256;
+
+/// A surface described by the signed distance to it from every point of space
+///
+/// Distances are negative inside the surface. Fields may also return any smaller magnitude,
+/// such as combinations of fields do, at the cost of more steps to find hits.
+pub trait DistanceField<T, U> {
+    #[must_use]
+    fn distance(&self, p: Point3<T, U>) -> T;
+
+    /// Returns bounds enclosing the surface
+    #[must_use]
+    fn bounds(&self) -> Box3<T, U>;
+}
+
+macro_rules! deref_impls {
+    ($($ty:ty),+) => {$(
+        impl<T, U, F: DistanceField<T, U> + ?Sized> DistanceField<T, U> for $ty {
+            #[inline]
+            fn distance(&self, p: Point3<T, U>) -> T {
+                (**self).distance(p)
+            }
+
+            #[inline]
+            fn bounds(&self) -> Box3<T, U> {
+                (**self).bounds()
+            }
+        }
+    )+};
+}
+
+deref_impls!(&F, Box<F>, Rc<F>, Arc<F>);
+
+/// Returns the distance from a point to a box, which is zero inside
+#[inline]
+fn outside<T: Float, U>(bounds: &Box3<T, U>, p: Point3<T, U>) -> T {
+    let zero = Vector3::zero();
+    ((bounds.min - p).max(zero) + (p - bounds.max).max(zero)).length()
+}
+
+pub struct SphereField<T, U> {
+    pub center: Point3<T, U>,
+    pub radius: T,
+}
+
+common_impls!(SphereField { center, radius });
+
+impl<T, U> SphereField<T, U> {
+    #[inline]
+    #[must_use]
+    pub const fn new(center: Point3<T, U>, radius: T) -> Self {
+        Self { center, radius }
+    }
+}
+
+impl<T: Float, U> DistanceField<T, U> for SphereField<T, U> {
+    #[inline]
+    fn distance(&self, p: Point3<T, U>) -> T {
+        (p - self.center).length() - self.radius
+    }
+
+    #[inline]
+    fn bounds(&self) -> Box3<T, U> {
+        let r = Vector3::new(self.radius, self.radius, self.radius);
+        Box3::new(self.center - r, self.center + r)
+    }
+}
+
+/// A box whose edges and corners are rounded off by `radius`, within its bounds
+pub struct BoxField<T, U> {
+    pub bounds: Box3<T, U>,
+    pub radius: T,
+}
+
+common_impls!(BoxField { bounds, radius });
+
+impl<T, U> BoxField<T, U> {
+    #[inline]
+    #[must_use]
+    pub const fn new(bounds: Box3<T, U>, radius: T) -> Self {
+        Self { bounds, radius }
+    }
+}
+
+impl<T: Float, U> DistanceField<T, U> for BoxField<T, U> {
+    fn distance(&self, p: Point3<T, U>) -> T {
+        let r = Vector3::new(self.radius, self.radius, self.radius);
+        let inner = Box3::new(self.bounds.min + r, self.bounds.max - r);
+        // Inside, the distance is to the nearest face
+        let below = p - inner.min;
+        let above = inner.max - p;
+        let inside = below.min(above);
+        let depth = inside.x.min(inside.y).min(inside.z).max(T::zero());
+        outside(&inner, p) - depth - self.radius
+    }
+
+    #[inline]
+    fn bounds(&self) -> Box3<T, U> {
+        self.bounds
+    }
+}
+
+/// A torus around the z axis through `center`, like [`Torus`](crate::shape::Torus)
+pub struct TorusField<T, U> {
+    pub center: Point3<T, U>,
+    pub major_radius: T,
+    pub minor_radius: T,
+}
+
+common_impls!(TorusField {
+    center,
+    major_radius,
+    minor_radius
+});
+
+impl<T, U> TorusField<T, U> {
+    #[inline]
+    #[must_use]
+    pub const fn new(center: Point3<T, U>, major_radius: T, minor_radius: T) -> Self {
+        Self {
+            center,
+            major_radius,
+            minor_radius,
+        }
+    }
+}
+
+impl<T: Float, U> DistanceField<T, U> for TorusField<T, U> {
+    #[inline]
+    fn distance(&self, p: Point3<T, U>) -> T {
+        let v = p - self.center;
+        let ring = v.x.hypot(v.y) - self.major_radius;
+        ring.hypot(v.z) - self.minor_radius
+    }
+
+    #[inline]
+    fn bounds(&self) -> Box3<T, U> {
+        let (r, h) = (self.major_radius + self.minor_radius, self.minor_radius);
+        let extent = Vector3::new(r, r, h);
+        Box3::new(self.center - extent, self.center + extent)
+    }
+}
+
+/// A field computed by a function, for procedural surfaces within `bounds`
+pub struct FnField<T, U, F> {
+    pub bounds: Box3<T, U>,
+    pub f: F,
+}
+
+impl<T: fmt::Debug, U, F> fmt::Debug for FnField<T, U, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FnField")
+            .field("bounds", &self.bounds)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, U, F> FnField<T, U, F> {
+    #[inline]
+    #[must_use]
+    pub const fn new(bounds: Box3<T, U>, f: F) -> Self {
+        Self { bounds, f }
+    }
+}
+
+impl<T: Copy, U, F: Fn(Point3<T, U>) -> T> DistanceField<T, U> for FnField<T, U, F> {
+    #[inline]
+    fn distance(&self, p: Point3<T, U>) -> T {
+        (self.f)(p)
+    }
+
+    #[inline]
+    fn bounds(&self) -> Box3<T, U> {
+        self.bounds
+    }
+}
+
+/// Distances sampled at the centers of the cells of a grid stretched over `bounds`, and
+/// interpolated trilinearly between them
+///
+/// Outside of the bounds, the distance to them is added to that at the nearest point within.
+pub struct GridField<T, U> {
+    pub bounds: Box3<T, U>,
+    pub grid: DensityGrid<T>,
+}
+
+impl<T: fmt::Debug, U> fmt::Debug for GridField<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GridField")
+            .field("bounds", &self.bounds)
+            .field("grid", &self.grid)
+            .finish()
+    }
+}
+
+impl<T: Clone, U> Clone for GridField<T, U> {
+    fn clone(&self) -> Self {
+        Self {
+            bounds: self.bounds.clone(),
+            grid: self.grid.clone(),
+        }
+    }
+}
+
+impl<T: Float, U> GridField<T, U> {
+    #[inline]
+    #[must_use]
+    pub const fn new(bounds: Box3<T, U>, grid: DensityGrid<T>) -> Self {
+        Self { bounds, grid }
+    }
+
+    /// Samples another field, such as an expensive combination of many, over its bounds
+    ///
+    /// Panics if the size is zero along an axis.
+    #[must_use]
+    pub fn sample(field: &impl DistanceField<T, U>, size: [usize; 3]) -> Self {
+        let bounds = field.bounds();
+        let extent = bounds.max - bounds.min;
+        let half = T::from(0.5).unwrap();
+        let at =
+            |i: usize, axis: usize| (T::from(i).unwrap() + half) / T::from(size[axis]).unwrap();
+        let mut values = Vec::with_capacity(size.iter().product());
+        for z in 0..size[2] {
+            for y in 0..size[1] {
+                for x in 0..size[0] {
+                    let offset = Vector3::new(at(x, 0), at(y, 1), at(z, 2)).component_mul(extent);
+                    values.push(field.distance(bounds.min + offset));
+                }
+            }
+        }
+        Self::new(bounds, DensityGrid::new(size, values))
+    }
+}
+
+impl<T: Float, U> DistanceField<T, U> for GridField<T, U> {
+    fn distance(&self, p: Point3<T, U>) -> T {
+        let inside = p.clamp(self.bounds.min, self.bounds.max);
+        let unit = (inside - self.bounds.min).component_div(self.bounds.max - self.bounds.min);
+        self.grid.lookup(unit.to_array()) + (p - inside).length()
+    }
+
+    #[inline]
+    fn bounds(&self) -> Box3<T, U> {
+        self.bounds
+    }
+}
+
+/// The surface enclosing either field
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Union<A, B>(pub A, pub B);
+
+impl<T: Float, U, A: DistanceField<T, U>, B: DistanceField<T, U>> DistanceField<T, U>
+    for Union<A, B>
+{
+    #[inline]
+    fn distance(&self, p: Point3<T, U>) -> T {
+        self.0.distance(p).min(self.1.distance(p))
+    }
+
+    #[inline]
+    fn bounds(&self) -> Box3<T, U> {
+        self.0.bounds().union(&self.1.bounds())
+    }
+}
+
+/// The surface enclosing both fields
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Intersection<A, B>(pub A, pub B);
+
+impl<T: Float, U, A: DistanceField<T, U>, B: DistanceField<T, U>> DistanceField<T, U>
+    for Intersection<A, B>
+{
+    #[inline]
+    fn distance(&self, p: Point3<T, U>) -> T {
+        self.0.distance(p).max(self.1.distance(p))
+    }
+
+    #[inline]
+    fn bounds(&self) -> Box3<T, U> {
+        self.0.bounds().intersection_unchecked(&self.1.bounds())
+    }
+}
+
+/// The surface enclosing the first field but not the second
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Difference<A, B>(pub A, pub B);
+
+impl<T: Float, U, A: DistanceField<T, U>, B: DistanceField<T, U>> DistanceField<T, U>
+    for Difference<A, B>
+{
+    #[inline]
+    fn distance(&self, p: Point3<T, U>) -> T {
+        self.0.distance(p).max(-self.1.distance(p))
+    }
+
+    #[inline]
+    fn bounds(&self) -> Box3<T, U> {
+        self.0.bounds()
+    }
+}
+
+/// A union blending the fields into each other where they are closer than `k`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SmoothUnion<T, A, B> {
+    pub a: A,
+    pub b: B,
+    pub k: T,
+}
+
+impl<T, A, B> SmoothUnion<T, A, B> {
+    #[inline]
+    #[must_use]
+    pub const fn new(a: A, b: B, k: T) -> Self {
+        Self { a, b, k }
+    }
+}
+
+impl<T: Float, U, A: DistanceField<T, U>, B: DistanceField<T, U>> DistanceField<T, U>
+    for SmoothUnion<T, A, B>
+{
+    fn distance(&self, p: Point3<T, U>) -> T {
+        // The polynomial smooth minimum (Quilez, "Smooth minimum")
+        let (a, b) = (self.a.distance(p), self.b.distance(p));
+        if self.k <= T::zero() {
+            return a.min(b);
+        }
+        let h = (self.k - (a - b).abs()).max(T::zero()) / self.k;
+        a.min(b) - h * h * self.k / T::from(4).unwrap()
+    }
+
+    #[inline]
+    fn bounds(&self) -> Box3<T, U> {
+        // The blend grows the surface by at most a quarter of `k`
+        let r = self.k.max(T::zero()) / T::from(4).unwrap();
+        self.a.bounds().union(&self.b.bounds()).inflate(r, r, r)
+    }
+}
+
+/// A shape whose surface is the zero set of a [`DistanceField`], intersected by sphere tracing
+/// (Hart, "Sphere tracing")
+///
+/// Rays step forward by the distance to the surface until they are within `epsilon` of it,
+/// and normals are estimated from the gradient of the field. Hits have no parameterization:
+/// `uv` is zero and `dpdu` and `dpdv` are an arbitrary tangent frame.
+pub struct Sdf<T, F> {
+    pub field: F,
+    epsilon: T,
+    max_steps: usize,
+}
+
+impl<T: fmt::Debug, F: fmt::Debug> fmt::Debug for Sdf<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sdf")
+            .field("field", &self.field)
+            .field("epsilon", &self.epsilon)
+            .field("max_steps", &self.max_steps)
+            .finish()
+    }
+}
+
+impl<T: Float, F> Sdf<T, F> {
+    /// Creates a shape whose hits are found to within a small fraction of the size of the
+    /// field's bounds
+    #[must_use]
+    pub fn new<U>(field: F) -> Self
+    where
+        F: DistanceField<T, U>,
+    {
+        let bounds = field.bounds();
+        let size = (bounds.max - bounds.min).length();
+        Self {
+            field,
+            epsilon: size * T::from(1e-5).unwrap(),
+            max_steps: MAX_STEPS,
+        }
+    }
+
+    /// Sets the distance to the surface within which rays hit it
+    #[inline]
+    #[must_use]
+    pub fn with_epsilon(self, epsilon: T) -> Self {
+        Self { epsilon, ..self }
+    }
+
+    /// Sets the number of steps after which rays are considered to miss
+    #[inline]
+    #[must_use]
+    pub fn with_max_steps(self, max_steps: usize) -> Self {
+        Self { max_steps, ..self }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn epsilon(&self) -> T {
+        self.epsilon
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn max_steps(&self) -> usize {
+        self.max_steps
+    }
+
+    /// Returns the ray parameter of the first hit
+    fn march<U>(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<T>
+    where
+        F: DistanceField<T, U>,
+    {
+        let bounds = Shape::bounds(self);
+        let (t0, t1) = bounds.intersect_ray(ray, t_max)?;
+        let speed = ray.dir.length();
+        // Rays leaving the surface, such as those scattered from it, must get away from it
+        // before they can hit it
+        let mut armed = self.field.distance(ray.origin).abs() >= self.epsilon;
+        let mut t = t0;
+        for _ in 0..self.max_steps {
+            if t > t1 {
+                return None;
+            }
+            let d = self.field.distance(ray.at(Time(t))).abs();
+            if d < self.epsilon {
+                if armed && t > T::zero() {
+                    return Some(t);
+                }
+            } else {
+                armed = true;
+            }
+            t = t + d.max(self.epsilon) / speed;
+        }
+        None
+    }
+
+    /// Estimates the gradient of the field by central differences
+    fn gradient<U>(&self, p: Point3<T, U>) -> Vector3<T, U>
+    where
+        F: DistanceField<T, U>,
+    {
+        let h = self.epsilon;
+        let axis = |v: Vector3<T, U>| self.field.distance(p + v) - self.field.distance(p - v);
+        Vector3::new(
+            axis(Vector3::new(h, T::zero(), T::zero())),
+            axis(Vector3::new(T::zero(), h, T::zero())),
+            axis(Vector3::new(T::zero(), T::zero(), h)),
+        )
+    }
+}
+
+impl<T: Float, U, F: DistanceField<T, U>> Shape<T, U> for Sdf<T, F> {
+    /// Returns the bounds of the field grown by `epsilon`, since hits can be as far from the
+    /// surface
+    #[inline]
+    fn bounds(&self) -> Box3<T, U> {
+        let e = self.epsilon;
+        self.field.bounds().inflate(e, e, e)
+    }
+
+    fn intersect(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<SurfaceInteraction<T, U>> {
+        let t = self.march(ray, t_max)?;
+        let p = ray.at(Time(t));
+        let wo = -ray.dir.normalize();
+        let n = self.gradient(p).try_normalize().unwrap_or(wo);
+        let (dpdu, dpdv) = n.coordinate_system();
+        Some(SurfaceInteraction::new(
+            p,
+            Time(t),
+            wo,
+            n.to_normal(),
+            Point2::origin(),
+            dpdu,
+            dpdv,
+        ))
+    }
+
+    #[inline]
+    fn intersect_any(&self, ray: &Ray<T, U>, t_max: Time<T>) -> bool {
+        self.march(ray, t_max).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{core::geometry::UnknownUnit, sampler::Pcg32, shape::Sphere};
+
+    type P = Point3<f64, UnknownUnit>;
+    type V = Vector3<f64, UnknownUnit>;
+
+    #[test]
+    fn test_sdf() {
+        // Sphere tracing finds the same hits as the analytic sphere
+        let (c, r) = (P::new(1., -2., 0.5), 2.);
+        let sdf = Sdf::new(SphereField::new(c, r));
+        let sphere = Sphere::new(c, r);
+        let mut rng = Pcg32::new(5);
+        let t_max = Time(f64::INFINITY);
+        for _ in 0..200 {
+            let mut u = || rng.uniform::<f64>() - 0.5;
+            let origin = c + V::new(u(), u(), u()).normalize() * 5.;
+            let ray = Ray::new(origin, c + V::new(u(), u(), u()) * 5. - origin);
+            let expected = sphere.intersect(&ray, t_max);
+            assert_eq!(sdf.intersect_any(&ray, t_max), expected.is_some());
+            let (Some(hit), Some(expected)) = (sdf.intersect(&ray, t_max), expected) else {
+                continue;
+            };
+            // Grazing rays pass within epsilon of the surface along a long stretch of it
+            if ray.dir.normalize().dot(expected.n.to_vector()) > -0.3 {
+                continue;
+            }
+            assert!((hit.p - expected.p).length() < 1e-3);
+            assert!(hit.n.to_vector().dot(expected.n.to_vector()) > 0.999);
+
+            // Rays leaving the surface only hit its other side
+            let out = Ray::new(hit.p, expected.n.to_vector());
+            assert!(!sdf.intersect_any(&out, t_max));
+            let through = Ray::new(hit.p, ray.dir);
+            let far = sdf.intersect(&through, t_max).unwrap();
+            assert!((far.p - c).length() - r < 1e-3 && (far.p - hit.p).length() > 1e-2);
+        }
+
+        // A cube with a ball carved out of its middle, through which rays pass
+        let cube = BoxField::new(Box3::new(P::new(-1., -1., -1.), P::new(1., 1., 1.)), 0.);
+        let carved = Sdf::new(Difference(cube, SphereField::new(P::origin(), 1.2)));
+        let down = |x: f64, y: f64| Ray::new(P::new(x, y, 5.), V::new(0., 0., -1.));
+        assert!(!carved.intersect_any(&down(0., 0.), t_max));
+        let hit = carved.intersect(&down(0.9, 0.9), t_max).unwrap();
+        assert!((hit.p.z - 1.).abs() < 1e-3);
+        assert!(hit.n.to_vector().z > 0.999);
+
+        // Sampled fields approximate the surface to within their cells
+        let grid = Sdf::new(GridField::sample(&SphereField::new(c, r), [32; 3]));
+        let hit = grid.intersect(&down(1.5, -1.5), t_max).unwrap();
+        let expected = sphere.intersect(&down(1.5, -1.5), t_max).unwrap();
+        assert!((hit.p - expected.p).length() < 0.1);
+    }
+}
@@ -0,0 +1,471 @@
+use crate::{
+    core::geometry::{Point2, Point3, Vector3},
+    shape::{NormalWeighting, TriangleMesh},
+};
+use num_traits::Float;
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+};
+
+/// How much more moving away from the boundary of a mesh costs than from its faces
+const BOUNDARY_WEIGHT: f64 = 1e3;
+
+/// The sum of squared distances to a set of planes, as a symmetric 4x4 matrix
+#[derive(Debug, Copy, Clone)]
+struct Quadric<T>([T; 10]);
+
+impl<T: Float> Quadric<T> {
+    fn zero() -> Self {
+        Self([T::zero(); 10])
+    }
+
+    /// The plane through `p` facing `n`, which must be normalized, counted `weight` times
+    fn plane<U>(n: Vector3<T, U>, p: Point3<T, U>, weight: T) -> Self {
+        let d = -n.dot(p.to_vector());
+        let [a, b, c] = [n.x, n.y, n.z];
+        Self([
+            a * a,
+            a * b,
+            a * c,
+            a * d,
+            b * b,
+            b * c,
+            b * d,
+            c * c,
+            c * d,
+            d * d,
+        ])
+        .scale(weight)
+    }
+
+    fn scale(self, weight: T) -> Self {
+        Self(self.0.map(|x| x * weight))
+    }
+
+    fn add(self, other: Self) -> Self {
+        Self(std::array::from_fn(|i| self.0[i] + other.0[i]))
+    }
+
+    fn error<U>(&self, p: Point3<T, U>) -> T {
+        let [aa, ab, ac, ad, bb, bc, bd, cc, cd, dd] = self.0;
+        let (x, y, z) = (p.x, p.y, p.z);
+        let two = T::one() + T::one();
+        aa * x * x
+            + bb * y * y
+            + cc * z * z
+            + two * (ab * x * y + ac * x * z + bc * y * z + ad * x + bd * y + cd * z)
+            + dd
+    }
+
+    /// Returns the point of least error, unless it is not unique, such as along flat regions
+    fn optimum<U>(&self) -> Option<Point3<T, U>> {
+        let [aa, ab, ac, ad, bb, bc, bd, cc, cd, _] = self.0;
+        // Cramer's rule on the gradient vanishing
+        let det = |m: [[T; 3]; 3]| {
+            m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+                - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+                + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+        };
+        let a = [[aa, ab, ac], [ab, bb, bc], [ac, bc, cc]];
+        let rhs = [-ad, -bd, -cd];
+        let d = det(a);
+        let scale = aa + bb + cc;
+        let singular = T::from(1e-10).unwrap() * scale * scale * scale;
+        if d.abs().partial_cmp(&singular) != Some(Ordering::Greater) {
+            return None;
+        }
+        let solve = |column: usize| {
+            let mut m = a;
+            for row in 0..3 {
+                m[row][column] = rhs[row];
+            }
+            det(m) / d
+        };
+        Some(Point3::new(solve(0), solve(1), solve(2)))
+    }
+}
+
+/// An edge to collapse, ordered with the cheapest first
+struct Candidate<T, U> {
+    cost: T,
+    edge: [usize; 2],
+    /// The versions of both vertices when the candidate was made, after which it is stale
+    versions: [u32; 2],
+    p: Point3<T, U>,
+}
+
+impl<T: PartialOrd, U> PartialEq for Candidate<T, U> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T: PartialOrd, U> Eq for Candidate<T, U> {}
+
+impl<T: PartialOrd, U> PartialOrd for Candidate<T, U> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: PartialOrd, U> Ord for Candidate<T, U> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .partial_cmp(&self.cost)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+/// The state of a mesh while its edges are collapsed
+struct Decimation<'a, T, U> {
+    mesh: &'a TriangleMesh<T, U>,
+    positions: Vec<Point3<T, U>>,
+    faces: Vec<Option<[usize; 3]>>,
+    /// The faces around each vertex, some of which may have been removed
+    vertex_faces: Vec<Vec<usize>>,
+    quadrics: Vec<Quadric<T>>,
+    versions: Vec<u32>,
+    removed: Vec<bool>,
+    /// The vertices moved by collapses, in order, with the vertices and weights interpolating
+    /// where each ended up
+    collapses: Vec<(usize, [usize; 3], [T; 3])>,
+}
+
+impl<T: Float, U> Decimation<'_, T, U> {
+    fn neighbors(&self, v: usize) -> Vec<usize> {
+        let mut neighbors: Vec<usize> = self.vertex_faces[v]
+            .iter()
+            .filter_map(|&f| self.faces[f])
+            .flatten()
+            .filter(|&n| n != v)
+            .collect();
+        neighbors.sort_unstable();
+        neighbors.dedup();
+        neighbors
+    }
+
+    fn candidate(&self, a: usize, b: usize) -> Candidate<T, U> {
+        let q = self.quadrics[a].add(self.quadrics[b]);
+        let (pa, pb) = (self.positions[a], self.positions[b]);
+        let p = q.optimum().unwrap_or_else(|| {
+            let middle = pa.lerp(pb, T::from(0.5).unwrap());
+            [pa, pb, middle]
+                .into_iter()
+                .min_by(|&x, &y| {
+                    q.error(x)
+                        .partial_cmp(&q.error(y))
+                        .unwrap_or(Ordering::Equal)
+                })
+                .unwrap()
+        });
+        Candidate {
+            cost: q.error(p),
+            edge: [a, b],
+            versions: [self.versions[a], self.versions[b]],
+            p,
+        }
+    }
+
+    /// Returns whether collapsing `b` into `a` at `p` keeps the mesh a manifold and turns over
+    /// none of its faces
+    fn can_collapse(&self, a: usize, b: usize, p: Point3<T, U>) -> bool {
+        let shared = self.vertex_faces[a]
+            .iter()
+            .filter(|&&f| self.faces[f].is_some_and(|face| face.contains(&b)))
+            .count();
+        let (na, nb) = (self.neighbors(a), self.neighbors(b));
+        let common = na.iter().filter(|n| nb.binary_search(n).is_ok()).count();
+        if common != shared {
+            return false;
+        }
+
+        for (v, other) in [(a, b), (b, a)] {
+            for &f in &self.vertex_faces[v] {
+                let Some(face) = self.faces[f] else {
+                    continue;
+                };
+                if face.contains(&other) {
+                    continue;
+                }
+                let before = face.map(|i| self.positions[i]);
+                let after = face.map(|i| if i == v { p } else { self.positions[i] });
+                let normal = |[p0, p1, p2]: [Point3<T, U>; 3]| (p1 - p0).cross(p2 - p0);
+                if normal(after).dot(normal(before)) <= T::zero() {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// Returns the barycentric coordinates of `p` in a face along the edge from `a` to `b`,
+    /// extended past the face since the optimum of a collapse may lie outside of it
+    fn weights(&self, a: usize, b: usize, p: Point3<T, U>) -> ([usize; 3], [T; 3]) {
+        let (pa, pb) = (self.positions[a], self.positions[b]);
+        let edge = pb - pa;
+        let t = ((p - pa).dot(edge) / edge.length_squared())
+            .max(T::zero())
+            .min(T::one());
+        let along_edge = ([a, b, b], [T::one() - t, t, T::zero()]);
+        let Some(face) = self.vertex_faces[a]
+            .iter()
+            .filter_map(|&f| self.faces[f])
+            .find(|face| face.contains(&b))
+        else {
+            return along_edge;
+        };
+
+        let [p0, p1, p2] = face.map(|i| self.positions[i]);
+        let (e1, e2, d) = (p1 - p0, p2 - p0, p - p0);
+        let (d11, d12, d22) = (e1.dot(e1), e1.dot(e2), e2.dot(e2));
+        let det = d11 * d22 - d12 * d12;
+        if det.partial_cmp(&T::zero()) != Some(Ordering::Greater) {
+            return along_edge;
+        }
+        let w1 = (d22 * d.dot(e1) - d12 * d.dot(e2)) / det;
+        let w2 = (d11 * d.dot(e2) - d12 * d.dot(e1)) / det;
+        (face, [T::one() - w1 - w2, w1, w2])
+    }
+
+    /// Collapses `b` into `a` at `p`, returning the number of faces removed
+    fn collapse(&mut self, a: usize, b: usize, p: Point3<T, U>) -> usize {
+        let (vertices, weights) = self.weights(a, b, p);
+        self.removed[b] = true;
+        self.collapses.push((a, vertices, weights));
+        self.positions[a] = p;
+        self.quadrics[a] = self.quadrics[a].add(self.quadrics[b]);
+        self.versions[a] += 1;
+        self.versions[b] += 1;
+
+        let mut removed = 0;
+        for f in std::mem::take(&mut self.vertex_faces[b]) {
+            let Some(face) = &mut self.faces[f] else {
+                continue;
+            };
+            if face.contains(&a) {
+                self.faces[f] = None;
+                removed += 1;
+            } else {
+                *face = face.map(|i| if i == b { a } else { i });
+                self.vertex_faces[a].push(f);
+            }
+        }
+        let faces = &self.faces;
+        self.vertex_faces[a].retain(|&f| faces[f].is_some());
+        removed
+    }
+}
+
+impl<T: Float, U> TriangleMesh<T, U> {
+    /// Returns a simplified mesh of about `target` triangles, by repeatedly collapsing the edge
+    /// that moves the surface the least, as measured by quadric error metrics (Garland and
+    /// Heckbert, "Surface simplification using quadric error metrics")
+    ///
+    /// Collapses are skipped where they would turn over faces or pinch the mesh, so more
+    /// triangles than asked may remain. Boundaries are weighted heavily to keep them in place,
+    /// and so are seams where vertices are split for their attributes, since those are
+    /// boundaries too. Texture coordinates are interpolated across the faces around collapsed
+    /// edges, and normals and tangents are computed again for meshes which had them.
+    #[must_use]
+    pub fn simplify(&self, target: usize) -> Self {
+        let n = self.positions.len();
+        let mut decimation = Decimation {
+            mesh: self,
+            positions: self.positions.clone(),
+            faces: (0..self.triangle_count())
+                .map(|f| Some(self.vertex_indices(f)))
+                .collect(),
+            vertex_faces: vec![Vec::new(); n],
+            quadrics: vec![Quadric::zero(); n],
+            versions: vec![0; n],
+            removed: vec![false; n],
+            collapses: Vec::new(),
+        };
+
+        let mut edges = HashMap::new();
+        for f in 0..self.triangle_count() {
+            let indices = self.vertex_indices(f);
+            let p = indices.map(|i| self.positions[i]);
+            let area_normal = (p[1] - p[0]).cross(p[2] - p[0]);
+            // Weighted by area, so that small faces count for less, and degenerate ones not at all
+            let plane = area_normal.try_normalize().map_or_else(Quadric::zero, |n| {
+                Quadric::plane(n, p[0], area_normal.length())
+            });
+            for k in 0..3 {
+                let i = indices[k];
+                decimation.vertex_faces[i].push(f);
+                decimation.quadrics[i] = decimation.quadrics[i].add(plane);
+                let j = indices[(k + 1) % 3];
+                edges.entry([i.min(j), i.max(j)]).or_insert((0, f, k)).0 += 1;
+            }
+        }
+
+        // Boundary edges are kept in place by planes perpendicular to their face
+        for (&[i, j], &(count, f, k)) in &edges {
+            if count != 1 {
+                continue;
+            }
+            let p = self.vertices(f);
+            let face_normal = (p[1] - p[0]).cross(p[2] - p[0]);
+            let edge = p[(k + 1) % 3] - p[k];
+            let Some(normal) = edge.cross(face_normal).try_normalize() else {
+                continue;
+            };
+            let weight = T::from(BOUNDARY_WEIGHT).unwrap() * edge.length_squared();
+            let plane = Quadric::plane(normal, p[k], weight);
+            for v in [i, j] {
+                decimation.quadrics[v] = decimation.quadrics[v].add(plane);
+            }
+        }
+
+        let mut heap: BinaryHeap<_> = edges
+            .keys()
+            .map(|&[a, b]| decimation.candidate(a, b))
+            .collect();
+        let mut remaining = decimation.faces.iter().flatten().count();
+        while remaining > target {
+            let Some(Candidate {
+                edge: [a, b],
+                versions,
+                p,
+                ..
+            }) = heap.pop()
+            else {
+                break;
+            };
+            if versions != [decimation.versions[a], decimation.versions[b]]
+                || decimation.removed[a]
+                || decimation.removed[b]
+                || !decimation.can_collapse(a, b, p)
+            {
+                continue;
+            }
+            remaining -= decimation.collapse(a, b, p);
+            for neighbor in decimation.neighbors(a) {
+                heap.push(decimation.candidate(a, neighbor));
+            }
+        }
+        decimation.finish()
+    }
+}
+
+impl<T: Float, U> Decimation<'_, T, U> {
+    /// Builds the mesh of the remaining faces, with the vertices they use
+    fn finish(self) -> TriangleMesh<T, U> {
+        let mesh = self.mesh;
+        // Texture coordinates move with their vertices across the faces around them
+        let mut uvs = mesh.uvs.clone();
+        if let Some(uvs) = &mut uvs {
+            for &(a, vertices, weights) in &self.collapses {
+                let [uv0, uv1, uv2] = vertices.map(|v| uvs[v]);
+                let [w0, w1, w2] = weights;
+                uvs[a] = Point2::new(
+                    w0 * uv0.x + w1 * uv1.x + w2 * uv2.x,
+                    w0 * uv0.y + w1 * uv1.y + w2 * uv2.y,
+                );
+            }
+        }
+
+        let mut remap = vec![None; self.positions.len()];
+        let mut positions = Vec::new();
+        let mut new_uvs = uvs.as_ref().map(|_| Vec::new());
+        let indices = self
+            .faces
+            .iter()
+            .flatten()
+            .map(|face| {
+                face.map(|v| {
+                    *remap[v].get_or_insert_with(|| {
+                        positions.push(self.positions[v]);
+                        if let (Some(new_uvs), Some(uvs)) = (&mut new_uvs, &uvs) {
+                            new_uvs.push(uvs[v]);
+                        }
+                        (positions.len() - 1) as u32
+                    })
+                })
+            })
+            .collect();
+
+        let mut simplified = TriangleMesh::new(positions, indices);
+        simplified.uvs = new_uvs;
+        if mesh.normals.is_some() {
+            simplified.compute_vertex_normals(NormalWeighting::Angle);
+        }
+        if mesh.tangents.is_some() {
+            simplified.compute_tangents();
+        }
+        simplified
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::geometry::{Box3, UnknownUnit},
+        shape::SphereField,
+    };
+    use std::collections::HashSet;
+
+    type P = Point3<f64, UnknownUnit>;
+
+    #[test]
+    fn test_simplify() {
+        let (c, r) = (P::new(1., -2., 0.5), 2.);
+        let sphere = TriangleMesh::from_distance_field(&SphereField::new(c, r), [24; 3]);
+        let simplified = sphere.simplify(300);
+        assert!(simplified.triangle_count() <= 300 && simplified.triangle_count() > 250);
+        assert!(simplified.positions.len() < sphere.positions.len() / 5);
+        assert!(simplified.normals.is_some());
+        for p in &simplified.positions {
+            assert!(((*p - c).length() - r).abs() < 0.1 * r);
+        }
+        // Still closed, with every edge going both ways once
+        let mut edges = HashSet::new();
+        for &[a, b, c] in &simplified.indices {
+            for edge in [(a, b), (b, c), (c, a)] {
+                assert!(edges.insert(edge));
+            }
+        }
+        assert!(edges.iter().all(|&(a, b)| edges.contains(&(b, a))));
+
+        // Flat grids shrink to a few triangles without their boundary moving
+        let n = 9;
+        let positions = (0..n * n)
+            .map(|i| P::new((i % n) as f64, (i / n) as f64, 0.))
+            .collect();
+        let mut indices = Vec::new();
+        for j in 0..n - 1 {
+            for i in 0..n - 1 {
+                let v = (j * n + i) as u32;
+                let n = n as u32;
+                indices.push([v, v + 1, v + n + 1]);
+                indices.push([v, v + n + 1, v + n]);
+            }
+        }
+        let mut grid = TriangleMesh::new(positions, indices);
+        grid.uvs = Some(
+            grid.positions
+                .iter()
+                .map(|p| Point2::new(p.x / 8., p.y / 8.))
+                .collect(),
+        );
+        let simplified = grid.simplify(32);
+        assert!(simplified.triangle_count() <= 32);
+        let bounds = simplified.bounds();
+        assert_eq!(bounds, Box3::new(P::new(0., 0., 0.), P::new(8., 8., 0.)));
+        let area: f64 = (0..simplified.triangle_count())
+            .map(|f| {
+                let [p0, p1, p2] = simplified.vertices(f);
+                (p1 - p0).cross(p2 - p0).z * 0.5
+            })
+            .sum();
+        assert!((area - 64.).abs() < 1e-9);
+        let uvs = simplified.uvs.as_ref().unwrap();
+        for (p, uv) in simplified.positions.iter().zip(uvs) {
+            assert!((uv.x - p.x / 8.).abs() < 1e-9 && (uv.y - p.y / 8.).abs() < 1e-9);
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use crate::{
+    core::{
+        geometry::{Box3, Point2, Point3, Ray, UnknownUnit, Vector3},
+        units::Time,
+    },
+    sampling::sample_uniform_sphere,
+    shape::{
+        azimuth, solid_angle_pdf, to_solid_angle, SampleShape, Shape, ShapeSample,
+        SurfaceInteraction,
+    },
+};
+use num_traits::{Float, FloatConst};
+
+/// A sphere, with `u` going around the z axis from +x and `v` from the bottom pole to the top
+pub struct Sphere<T, U> {
+    pub center: Point3<T, U>,
+    pub radius: T,
+}
+
+common_impls!(Sphere { center, radius });
+
+impl<T, U> Sphere<T, U> {
+    #[inline]
+    #[must_use]
+    pub const fn new(center: Point3<T, U>, radius: T) -> Self {
+        Self { center, radius }
+    }
+}
+
+impl<T: Float, U> Sphere<T, U> {
+    /// Returns the ray parameters of both intersections with the sphere's surface, nearest first
+    #[must_use]
+    pub fn intersect_t(&self, ray: &Ray<T, U>) -> Option<(T, T)> {
+        let oc = ray.origin - self.center;
+        let d = ray.dir;
+
+        let a = d.length_squared();
+        let half_b = oc.dot(d);
+        let c = oc.length_squared() - self.radius * self.radius;
+
+        // Computing the discriminant from the distance between the sphere's center and the ray
+        // loses far less precision than `b^2 - ac` for spheres that are small or far away
+        let f = oc - d * (half_b / a);
+        let discrim = a * (self.radius * self.radius - f.length_squared());
+        if discrim < T::zero() {
+            return None;
+        }
+
+        let root = discrim.sqrt();
+        let q = if half_b > T::zero() {
+            -(half_b + root)
+        } else {
+            root - half_b
+        };
+        if q == T::zero() {
+            return None;
+        }
+
+        let (t0, t1) = (q / a, c / q);
+        Some(if t0 <= t1 { (t0, t1) } else { (t1, t0) })
+    }
+
+    /// Returns the ray parameter of the first hit within `0 < t <= t_max`
+    #[inline]
+    fn hit_t(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<T> {
+        let (t0, t1) = self.intersect_t(ray)?;
+        let t = if t0 > T::zero() { t0 } else { t1 };
+        (t > T::zero() && t <= t_max.0).then_some(t)
+    }
+}
+
+impl<T: Float + FloatConst, U> Shape<T, U> for Sphere<T, U> {
+    #[inline]
+    fn bounds(&self) -> Box3<T, U> {
+        let r = Vector3::splat(self.radius);
+        Box3::new(self.center - r, self.center + r)
+    }
+
+    fn intersect(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<SurfaceInteraction<T, U>> {
+        let t = Time(self.hit_t(ray, t_max)?);
+        let p = ray.at(t);
+        let local = p - self.center;
+
+        let phi = azimuth(local.x, local.y);
+        let cos_theta = (local.z / self.radius).max(-T::one()).min(T::one());
+        let theta = cos_theta.acos();
+        let sin_theta = theta.sin();
+        let (sin_phi, cos_phi) = phi.sin_cos();
+
+        // `v` runs from the bottom pole to the top one so that `dpdu x dpdv` points outwards
+        let uv = Point2::new(phi / T::TAU(), T::one() - theta / T::PI());
+        let dpdu = Vector3::new(-local.y, local.x, T::zero()) * T::TAU();
+        let dpdv = Vector3::new(
+            -local.z * cos_phi,
+            -local.z * sin_phi,
+            self.radius * sin_theta,
+        ) * T::PI();
+        let n = (local / self.radius).to_normal();
+        let wo = -ray.dir.normalize();
+        Some(SurfaceInteraction::new(p, t, wo, n, uv, dpdu, dpdv))
+    }
+
+    #[inline]
+    fn intersect_any(&self, ray: &Ray<T, U>, t_max: Time<T>) -> bool {
+        self.hit_t(ray, t_max).is_some()
+    }
+}
+
+impl<T: Float + FloatConst, U> SampleShape<T, U> for Sphere<T, U> {
+    #[inline]
+    fn area(&self) -> T {
+        T::PI() * (self.radius + self.radius) * (self.radius + self.radius)
+    }
+
+    fn sample(&self, u: Point2<T, UnknownUnit>) -> ShapeSample<T, U> {
+        let n: Vector3<T, U> = sample_uniform_sphere(u);
+        ShapeSample {
+            p: self.center + n * self.radius,
+            n: n.to_normal(),
+            pdf: self.area().recip(),
+        }
+    }
+
+    /// Samples the cone of directions from `p` that the sphere subtends, rather than points
+    /// on its whole surface, half of which are hidden from `p`
+    fn sample_from(&self, p: Point3<T, U>, u: Point2<T, UnknownUnit>) -> Option<ShapeSample<T, U>> {
+        let Some((cos_theta_max, one_minus_cos_theta_max)) = self.cone(p) else {
+            // From inside of the sphere every direction sees its surface
+            return to_solid_angle(self.sample(u), p);
+        };
+        let to_center = self.center - p;
+        let sin2_theta_max = self.radius * self.radius / to_center.length_squared();
+        let sin_theta_max = sin2_theta_max.sqrt();
+
+        let mut cos_theta = (cos_theta_max - T::one()) * u.x + T::one();
+        let mut sin2_theta = T::one() - cos_theta * cos_theta;
+        if sin2_theta_max < T::from(SMALL_CONE).unwrap() {
+            // Keeps precision for small or distant spheres, with a Taylor expansion
+            sin2_theta = sin2_theta_max * u.x;
+            cos_theta = (T::one() - sin2_theta).sqrt();
+        }
+
+        // The angle at the center between `p` and the sampled point
+        let cos_alpha = sin2_theta / sin_theta_max
+            + cos_theta
+                * (T::one() - sin2_theta / sin2_theta_max)
+                    .max(T::zero())
+                    .sqrt();
+        let sin_alpha = (T::one() - cos_alpha * cos_alpha).max(T::zero()).sqrt();
+        let (sin_phi, cos_phi) = (T::TAU() * u.y).sin_cos();
+
+        let w = to_center.normalize();
+        let (x, y) = w.coordinate_system();
+        let n = -(x * (sin_alpha * cos_phi) + y * (sin_alpha * sin_phi) + w * cos_alpha);
+        Some(ShapeSample {
+            p: self.center + n * self.radius,
+            n: n.to_normal(),
+            pdf: (T::TAU() * one_minus_cos_theta_max).recip(),
+        })
+    }
+
+    fn pdf_from(&self, p: Point3<T, U>, wi: Vector3<T, U>) -> T {
+        let Some((cos_theta_max, one_minus_cos_theta_max)) = self.cone(p) else {
+            return solid_angle_pdf(self, p, wi);
+        };
+        let cos_theta = (self.center - p).normalize().dot(wi) / wi.length();
+        if cos_theta < cos_theta_max {
+            T::zero()
+        } else {
+            (T::TAU() * one_minus_cos_theta_max).recip()
+        }
+    }
+}
+
+/// The squared sine below which the cone a sphere subtends is sampled with a Taylor expansion
+const SMALL_CONE: f64 = 0.000_685_23;
+/// The squared sine above which the cone a sphere subtends is too wide to be worth sampling
+const NEAR_SURFACE: f64 = 1e-3;
+
+impl<T: Float, U> Sphere<T, U> {
+    /// Returns the cosine of the half angle of the cone that the sphere subtends from `p`, and
+    /// one minus it, or `None` if `p` is inside of the sphere or on its surface
+    #[inline]
+    fn cone(&self, p: Point3<T, U>) -> Option<(T, T)> {
+        let sin2_theta_max = self.radius * self.radius / (self.center - p).length_squared();
+        // Points on the surface may be off to either side by rounding, and are treated as
+        // inside, since uniform sampling is correct from both sides
+        if sin2_theta_max >= T::one() - T::from(NEAR_SURFACE).unwrap() {
+            return None;
+        }
+        let cos_theta_max = (T::one() - sin2_theta_max).sqrt();
+        // `1 - cos` cancels catastrophically for small cones
+        let one_minus = if sin2_theta_max < T::from(SMALL_CONE).unwrap() {
+            sin2_theta_max / (T::one() + T::one())
+        } else {
+            T::one() - cos_theta_max
+        };
+        Some((cos_theta_max, one_minus))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::num::ApproxEq;
+
+    type S = Sphere<f32, UnknownUnit>;
+    type R = Ray<f32, UnknownUnit>;
+    type P = Point3<f32, UnknownUnit>;
+    type V = Vector3<f32, UnknownUnit>;
+
+    #[test]
+    fn test_intersect() {
+        let s = S::new(P::new(0., 0., 5.), 1.);
+        let hit = s
+            .intersect(
+                &R::new(P::origin(), V::new(0., 0., 1.)),
+                Time(f32::INFINITY),
+            )
+            .unwrap();
+        assert!(hit.t.approx_eq(&Time(4.)));
+        assert!(hit.p.approx_eq(&P::new(0., 0., 4.)));
+        assert!(hit.n.to_vector().approx_eq(&V::new(0., 0., -1.)));
+
+        // From the inside only the far side is visible
+        let hit = s
+            .intersect(
+                &R::new(P::new(0., 0., 5.), V::new(2., 0., 0.)),
+                Time(f32::INFINITY),
+            )
+            .unwrap();
+        assert!(hit.t.approx_eq(&Time(0.5)));
+
+        assert!(!s.intersect_any(&R::new(P::origin(), V::new(0., 0., 1.)), Time(3.)));
+        assert!(!s.intersect_any(
+            &R::new(P::origin(), V::new(0., 0., -1.)),
+            Time(f32::INFINITY)
+        ));
+        assert!(!s.intersect_any(
+            &R::new(P::origin(), V::new(1., 0., 0.)),
+            Time(f32::INFINITY)
+        ));
+    }
+    #[test]
+    fn test_sample_from() {
+        let s = Sphere::<f64, UnknownUnit>::new(Point3::new(0., 0., 2.), 1.);
+        let p = Point3::origin();
+        // The cone has a half angle of 30 degrees
+        let pdf = 1. / (std::f64::consts::TAU * (1. - 0.75_f64.sqrt()));
+        for u in [(0., 0.), (0.5, 0.3), (0.999, 0.7)] {
+            let sample = s.sample_from(p, Point2::new(u.0, u.1)).unwrap();
+            assert!(((sample.p - s.center).length() - 1.).abs() < 1e-9);
+            // The sampled points face `p`
+            assert!(sample.n.to_vector().dot(p - sample.p) > 0.);
+            assert!((sample.pdf - pdf).abs() < 1e-9);
+            assert!((s.pdf_from(p, sample.p - p) - pdf).abs() < 1e-9);
+        }
+        assert_eq!(s.pdf_from(p, Vector3::new(1., 0., 1.)), 0.);
+
+        // Tiny cones are sampled without losing their density
+        let far = Sphere::<f64, UnknownUnit>::new(Point3::new(0., 0., 1e4), 1.);
+        let sample = far.sample_from(p, Point2::new(0.5, 0.5)).unwrap();
+        assert!((sample.pdf * std::f64::consts::PI * 1e-8 - 1.).abs() < 1e-6);
+
+        // From the inside, the surface is sampled uniformly
+        let sample = s.sample_from(s.center, Point2::new(0.5, 0.5)).unwrap();
+        assert!((sample.pdf - 1. / (4. * std::f64::consts::PI)).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,236 @@
+use crate::{
+    core::{
+        geometry::{Box3, Point2, Point3, Ray, Vector3},
+        units::Time,
+    },
+    shape::{azimuth, Shape, SurfaceInteraction},
+};
+use num_traits::{Float, FloatConst};
+
+/// A torus around the z axis through `center`
+///
+/// `major_radius` is the distance from the center to the middle of the tube, and `minor_radius`
+/// the radius of the tube itself. `u` goes around the z axis from +x, and `v` around the tube
+/// from its outer equator, upwards first.
+pub struct Torus<T, U> {
+    pub center: Point3<T, U>,
+    pub major_radius: T,
+    pub minor_radius: T,
+}
+
+common_impls!(Torus {
+    center,
+    major_radius,
+    minor_radius
+});
+
+impl<T, U> Torus<T, U> {
+    #[inline]
+    #[must_use]
+    pub const fn new(center: Point3<T, U>, major_radius: T, minor_radius: T) -> Self {
+        Self {
+            center,
+            major_radius,
+            minor_radius,
+        }
+    }
+}
+
+impl<T: Float + FloatConst, U> Shape<T, U> for Torus<T, U> {
+    #[inline]
+    fn bounds(&self) -> Box3<T, U> {
+        let xy = self.major_radius + self.minor_radius;
+        let r = Vector3::new(xy, xy, self.minor_radius);
+        Box3::new(self.center - r, self.center + r)
+    }
+
+    fn intersect(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<SurfaceInteraction<T, U>> {
+        // The quartic is badly conditioned, so it is always solved in double precision
+        let f = |x: T| x.to_f64().unwrap();
+        let o = ray.origin - self.center;
+        let (ox, oy, oz) = (f(o.x), f(o.y), f(o.z));
+        let (dx, dy, dz) = (f(ray.dir.x), f(ray.dir.y), f(ray.dir.z));
+        let big_r2 = f(self.major_radius).powi(2);
+        let small_r2 = f(self.minor_radius).powi(2);
+
+        // Substituting the ray into `(x^2 + y^2 + z^2 + R^2 - r^2)^2 = 4 R^2 (x^2 + y^2)`
+        let g = dx * dx + dy * dy + dz * dz;
+        let h = 2. * (ox * dx + oy * dy + oz * dz);
+        let k = ox * ox + oy * oy + oz * oz + big_r2 - small_r2;
+        let (roots, count) = solve_quartic(
+            g * g,
+            2. * g * h,
+            h * h + 2. * g * k - 4. * big_r2 * (dx * dx + dy * dy),
+            2. * h * k - 8. * big_r2 * (ox * dx + oy * dy),
+            k * k - 4. * big_r2 * (ox * ox + oy * oy),
+        );
+        let t = roots[..count]
+            .iter()
+            .filter_map(|&t| T::from(t))
+            .find(|&t| t > T::zero() && t <= t_max.0)?;
+
+        let t = Time(t);
+        let p = ray.at(t);
+        let local = p - self.center;
+
+        let phi = azimuth(local.x, local.y);
+        let (sin_phi, cos_phi) = phi.sin_cos();
+        // Offset of the hit from the middle of the tube, in the plane containing the z axis
+        let radial = (local.x * local.x + local.y * local.y).sqrt() - self.major_radius;
+        let theta = azimuth(radial, local.z);
+
+        let uv = Point2::new(phi / T::TAU(), theta / T::TAU());
+        let dpdu = Vector3::new(-local.y, local.x, T::zero()) * T::TAU();
+        let dpdv = Vector3::new(-local.z * cos_phi, -local.z * sin_phi, radial) * T::TAU();
+        let n = Vector3::new(radial * cos_phi, radial * sin_phi, local.z)
+            .normalize()
+            .to_normal();
+        let wo = -ray.dir.normalize();
+        Some(SurfaceInteraction::new(p, t, wo, n, uv, dpdu, dpdv))
+    }
+}
+
+/// Returns the largest real root of `x^3 + a x^2 + b x + c`
+fn largest_cubic_root(a: f64, b: f64, c: f64) -> f64 {
+    // Substituting `x = y - a/3` gives `y^3 + p y + q`
+    let p = b - a * a / 3.;
+    let q = 2. * a * a * a / 27. - a * b / 3. + c;
+    let discrim = (q / 2.).powi(2) + (p / 3.).powi(3);
+
+    let y = if discrim > 0. {
+        let s = discrim.sqrt();
+        (-q / 2. + s).cbrt() + (-q / 2. - s).cbrt()
+    } else if p < 0. {
+        let m = 2. * (-p / 3.).sqrt();
+        let cos = (3. * q / (p * m)).clamp(-1., 1.);
+        m * (cos.acos() / 3.).cos()
+    } else {
+        0.
+    };
+
+    let mut x = y - a / 3.;
+    // Cardano's formula suffers from cancellation, which a Newton step mostly recovers
+    let derivative = (3. * x + 2. * a) * x + b;
+    if derivative != 0. {
+        x -= (((x + a) * x + b) * x + c) / derivative;
+    }
+    x
+}
+
+/// Returns the real roots of `a x^4 + b x^3 + c x^2 + d x + e` in ascending order, followed by
+/// their number
+fn solve_quartic(a: f64, b: f64, c: f64, d: f64, e: f64) -> ([f64; 4], usize) {
+    let mut roots = [0.; 4];
+    let mut count = 0;
+    if a == 0. {
+        return (roots, count);
+    }
+
+    let (b, c, d, e) = (b / a, c / a, d / a, e / a);
+    // Substituting `x = y - b/4` gives `y^4 + p y^2 + q y + r`
+    let b2 = b * b;
+    let p = c - 3. * b2 / 8.;
+    let q = d - b * c / 2. + b2 * b / 8.;
+    let r = e - b * d / 4. + b2 * c / 16. - 3. * b2 * b2 / 256.;
+
+    let mut push_quadratic = |lin: f64, constant: f64| {
+        let discrim = lin * lin - 4. * constant;
+        if discrim >= 0. {
+            let s = discrim.sqrt();
+            roots[count] = (-lin - s) / 2.;
+            roots[count + 1] = (-lin + s) / 2.;
+            count += 2;
+        }
+    };
+
+    // Ferrari's method: for a root `m` of the resolvent cubic, the depressed quartic factors as
+    // `(y^2 + p/2 + m)^2 - 2m (y - q/4m)^2`
+    let m = largest_cubic_root(p, p * p / 4. - r, -q * q / 8.);
+    if m > 0. {
+        let s = (2. * m).sqrt();
+        push_quadratic(-s, p / 2. + m + q / (2. * s));
+        push_quadratic(s, p / 2. + m - q / (2. * s));
+    } else {
+        // The quartic is biquadratic in `y`
+        let discrim = p * p - 4. * r;
+        if discrim >= 0. {
+            let s = discrim.sqrt();
+            for z in [(-p - s) / 2., (-p + s) / 2.] {
+                if z >= 0. {
+                    push_quadratic(0., -z);
+                }
+            }
+        }
+    }
+
+    for x in &mut roots[..count] {
+        *x -= b / 4.;
+        // Polish against the undepressed polynomial
+        for _ in 0..2 {
+            let value = (((*x + b) * *x + c) * *x + d) * *x + e;
+            let derivative = ((4. * *x + 3. * b) * *x + 2. * c) * *x + d;
+            if derivative == 0. {
+                break;
+            }
+            *x -= value / derivative;
+        }
+    }
+    roots[..count].sort_by(f64::total_cmp);
+    (roots, count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{geometry::UnknownUnit, num::ApproxEq};
+
+    type R = Ray<f32, UnknownUnit>;
+    type P = Point3<f32, UnknownUnit>;
+    type V = Vector3<f32, UnknownUnit>;
+
+    #[test]
+    fn test_solve_quartic() {
+        // (x - 1)(x - 2)(x - 3)(x - 4)
+        let (roots, count) = solve_quartic(1., -10., 35., -50., 24.);
+        assert_eq!(count, 4);
+        for (root, expected) in roots.iter().zip([1., 2., 3., 4.]) {
+            assert!((root - expected).abs() < 1e-9);
+        }
+
+        // (x^2 + 1)(x - 2)(x + 3)
+        let (roots, count) = solve_quartic(1., 1., -5., 1., -6.);
+        assert_eq!(count, 2);
+        assert!((roots[0] + 3.).abs() < 1e-9);
+        assert!((roots[1] - 2.).abs() < 1e-9);
+
+        assert_eq!(solve_quartic(1., 0., 0., 0., 1.).1, 0);
+    }
+
+    #[test]
+    fn test_intersect() {
+        let torus = Torus::<f32, UnknownUnit>::new(P::origin(), 2., 0.5);
+        let hit = torus
+            .intersect(
+                &R::new(P::new(-5., 0., 0.), V::new(1., 0., 0.)),
+                Time(f32::INFINITY),
+            )
+            .unwrap();
+        assert!(hit.t.approx_eq(&Time(2.5)));
+        assert!(hit.n.to_vector().approx_eq(&V::new(-1., 0., 0.)));
+
+        // Starting inside the hole, the inner side of the tube is hit
+        let hit = torus
+            .intersect(
+                &R::new(P::origin(), V::new(0., 1., 0.)),
+                Time(f32::INFINITY),
+            )
+            .unwrap();
+        assert!(hit.t.approx_eq(&Time(1.5)));
+        assert!(hit.n.to_vector().approx_eq(&V::new(0., -1., 0.)));
+
+        assert!(!torus.intersect_any(
+            &R::new(P::new(0., 0., -5.), V::new(0., 0., 1.)),
+            Time(f32::INFINITY)
+        ));
+    }
+}
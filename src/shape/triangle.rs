@@ -0,0 +1,274 @@
+use crate::{
+    core::{
+        geometry::{Box3, Point2, Point3, Ray, UnknownUnit, Vector3},
+        prelude::Normal3,
+        units::Time,
+    },
+    sampling::sample_uniform_triangle,
+    shape::{SampleShape, Shape, ShapeSample, SurfaceInteraction, TriangleMesh},
+};
+use num_traits::Float;
+use std::{fmt, sync::Arc};
+
+/// A single triangle of a shared [`TriangleMesh`]
+pub struct Triangle<T, U> {
+    pub mesh: Arc<TriangleMesh<T, U>>,
+    pub index: usize,
+}
+
+impl<T: fmt::Debug, U> fmt::Debug for Triangle<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Triangle")
+            .field("index", &self.index)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, U> Clone for Triangle<T, U> {
+    fn clone(&self) -> Self {
+        Self {
+            mesh: Arc::clone(&self.mesh),
+            index: self.index,
+        }
+    }
+}
+
+impl<T, U> Triangle<T, U> {
+    #[inline]
+    #[must_use]
+    pub fn new(mesh: Arc<TriangleMesh<T, U>>, index: usize) -> Self {
+        Self { mesh, index }
+    }
+
+    /// Returns every triangle of the mesh
+    pub fn from_mesh(mesh: &Arc<TriangleMesh<T, U>>) -> impl Iterator<Item = Self> + '_ {
+        (0..mesh.triangle_count()).map(|index| Self::new(Arc::clone(mesh), index))
+    }
+}
+
+/// Returns the ray parameter and the barycentric coordinates of the second and third vertices
+/// where a ray hits the triangle with the vertices `p`, using Möller-Trumbore
+#[inline]
+pub(super) fn hit<T: Float, U>(
+    [p0, p1, p2]: [Point3<T, U>; 3],
+    ray: &Ray<T, U>,
+    t_max: Time<T>,
+) -> Option<(T, T, T)> {
+    let (e1, e2) = (p1 - p0, p2 - p0);
+    let pvec = ray.dir.cross(e2);
+    let det = e1.dot(pvec);
+    if det == T::zero() {
+        return None;
+    }
+    let inv_det = det.recip();
+    let tvec = ray.origin - p0;
+    let b1 = tvec.dot(pvec) * inv_det;
+    if b1 < T::zero() || b1 > T::one() {
+        return None;
+    }
+    let qvec = tvec.cross(e1);
+    let b2 = ray.dir.dot(qvec) * inv_det;
+    if b2 < T::zero() || b1 + b2 > T::one() {
+        return None;
+    }
+    let t = e2.dot(qvec) * inv_det;
+    (t > T::zero() && t <= t_max.0).then_some((t, b1, b2))
+}
+
+/// The attributes of the vertices of a triangle, as a mesh gives them
+pub(super) struct Vertices<T, U> {
+    pub p: [Point3<T, U>; 3],
+    pub normals: Option<[Vector3<T, U>; 3]>,
+    pub tangents: Option<[Vector3<T, U>; 3]>,
+    pub uvs: Option<[Point2<T, UnknownUnit>; 3]>,
+}
+
+impl<T: Float, U> Vertices<T, U> {
+    /// Returns the interaction where a ray hits the triangle, at the ray parameter `t` and the
+    /// barycentric coordinates `b1` and `b2` of the second and third vertices
+    pub fn interaction(&self, ray: &Ray<T, U>, t: T, b1: T, b2: T) -> SurfaceInteraction<T, U> {
+        let [p0, p1, p2] = self.p;
+        let (e1, e2) = (p1 - p0, p2 - p0);
+        let b0 = T::one() - b1 - b2;
+
+        // Interpolating the vertices is more accurate than evaluating the ray
+        let p = p0 + e1 * b1 + e2 * b2;
+        let n = e1.cross(e2).normalize();
+
+        let [uv0, uv1, uv2] = self.uvs.unwrap_or([
+            Point2::new(T::zero(), T::zero()),
+            Point2::new(T::one(), T::zero()),
+            Point2::new(T::one(), T::one()),
+        ]);
+        let uv = uv0 + (uv1 - uv0) * b1 + (uv2 - uv0) * b2;
+
+        let (duv02, duv12) = (uv0 - uv2, uv1 - uv2);
+        let (dp02, dp12) = (p0 - p2, p1 - p2);
+        let uv_det = duv02.x * duv12.y - duv02.y * duv12.x;
+        let (dpdu, dpdv) = Some(uv_det)
+            .filter(|&det| det != T::zero())
+            .map(|det| {
+                let inv_det = det.recip();
+                (
+                    (dp02 * duv12.y - dp12 * duv02.y) * inv_det,
+                    (dp12 * duv02.x - dp02 * duv12.x) * inv_det,
+                )
+            })
+            .filter(|(dpdu, dpdv)| dpdu.cross(*dpdv).length_squared() > T::zero())
+            .unwrap_or_else(|| n.coordinate_system());
+
+        let wo = -ray.dir.normalize();
+        let mut hit = SurfaceInteraction::new(p, Time(t), wo, n.to_normal(), uv, dpdu, dpdv);
+
+        if let Some([n0, n1, n2]) = self.normals {
+            let Some(ns) = (n0 * b0 + n1 * b1 + n2 * b2).try_normalize() else {
+                return hit;
+            };
+            // The geometric normal follows the orientation implied by the vertex normals
+            hit.n = hit.n.face_towards(ns);
+
+            let ss = match self.tangents {
+                Some([t0, t1, t2]) => t0 * b0 + t1 * b1 + t2 * b2,
+                None => dpdu,
+            };
+            let ss = (ss - ns * ns.dot(ss))
+                .try_normalize()
+                .unwrap_or_else(|| ns.coordinate_system().0);
+            let ts = ns.cross(ss);
+            hit.set_shading_geometry(ns.to_normal(), ss * dpdu.length(), ts * dpdv.length());
+
+            // Rays leave from the surface the vertex normals describe rather than the flat
+            // facet, which would shadow its points facing away from the light before the
+            // shading normals do, by moving the point above the planes tangent to the vertex
+            // normals (Hanika, "Hacking the shadow terminator")
+            let lift = |pi: Point3<T, U>, ni: Vector3<T, U>| {
+                let d = p - pi;
+                d - ni * d.dot(ni).min(T::zero())
+            };
+            let [n0, n1, n2] = [n0, n1, n2].map(|ni| ni.try_normalize().unwrap_or(ns));
+            hit.shading.p = p + lift(p0, n0) * b0 + lift(p1, n1) * b1 + lift(p2, n2) * b2;
+        }
+        hit
+    }
+}
+
+impl<T: Float, U> Shape<T, U> for Triangle<T, U> {
+    #[inline]
+    fn bounds(&self) -> Box3<T, U> {
+        let [p0, p1, p2] = self.mesh.vertices(self.index);
+        Box3::new(p0.min(p1).min(p2), p0.max(p1).max(p2))
+    }
+
+    fn intersect(&self, ray: &Ray<T, U>, t_max: Time<T>) -> Option<SurfaceInteraction<T, U>> {
+        let mesh = &*self.mesh;
+        let (t, b1, b2) = hit(mesh.vertices(self.index), ray, t_max)?;
+        let indices = mesh.vertex_indices(self.index);
+        let vertices = Vertices {
+            p: indices.map(|i| mesh.positions[i]),
+            normals: mesh
+                .normals
+                .as_ref()
+                .map(|normals| indices.map(|i| normals[i].to_vector())),
+            tangents: mesh
+                .tangents
+                .as_ref()
+                .map(|tangents| indices.map(|i| tangents[i].tangent)),
+            uvs: mesh.uvs.as_ref().map(|uvs| indices.map(|i| uvs[i])),
+        };
+        Some(vertices.interaction(ray, t, b1, b2))
+    }
+
+    #[inline]
+    fn intersect_any(&self, ray: &Ray<T, U>, t_max: Time<T>) -> bool {
+        hit(self.mesh.vertices(self.index), ray, t_max).is_some()
+    }
+
+    #[inline]
+    fn triangle(&self) -> Option<[Point3<T, U>; 3]> {
+        Some(self.mesh.vertices(self.index))
+    }
+}
+
+impl<T: Float, U> SampleShape<T, U> for Triangle<T, U> {
+    #[inline]
+    fn area(&self) -> T {
+        let [p0, p1, p2] = self.mesh.vertices(self.index);
+        (p1 - p0).cross(p2 - p0).length() * T::from(0.5).unwrap()
+    }
+
+    fn normal(&self) -> Option<Normal3<T, U>> {
+        let [p0, p1, p2] = self.mesh.vertices(self.index);
+        let n = (p1 - p0).cross(p2 - p0).try_normalize()?;
+        let Some(normals) = &self.mesh.normals else {
+            return Some(n.to_normal());
+        };
+        // Vertex normals on either side leave the orientation of points undecided
+        let sides = self
+            .mesh
+            .vertex_indices(self.index)
+            .map(|i| normals[i].to_vector().dot(n));
+        if sides.iter().all(|&side| side > T::zero()) {
+            Some(n.to_normal())
+        } else if sides.iter().all(|&side| side < T::zero()) {
+            Some((-n).to_normal())
+        } else {
+            None
+        }
+    }
+
+    fn sample(&self, u: Point2<T, UnknownUnit>) -> ShapeSample<T, U> {
+        let mesh = &*self.mesh;
+        let indices = mesh.vertex_indices(self.index);
+        let [p0, p1, p2] = indices.map(|i| mesh.positions[i]);
+        let [b0, b1, b2] = sample_uniform_triangle(u);
+        let cross = (p1 - p0).cross(p2 - p0);
+        let mut n = cross.normalize().to_normal();
+        if let Some(normals) = &mesh.normals {
+            let [n0, n1, n2] = indices.map(|i| normals[i].to_vector());
+            n = n.face_towards(n0 * b0 + n1 * b1 + n2 * b2);
+        }
+        ShapeSample {
+            p: p0 + (p1 - p0) * b1 + (p2 - p0) * b2,
+            n,
+            pdf: (cross.length() * T::from(0.5).unwrap()).recip(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shadow_terminator() {
+        // A facet of a coarse sphere around the origin, with the normals of the sphere
+        let positions = vec![
+            Point3::<f64, UnknownUnit>::new(1., 0., 0.),
+            Point3::new(0., 1., 0.),
+            Point3::new(0., 0., 1.),
+        ];
+        let mut mesh = TriangleMesh::new(positions.clone(), vec![[0, 1, 2]]);
+        mesh.normals = Some(
+            positions
+                .iter()
+                .map(|p| (*p - Point3::origin()).to_normal())
+                .collect(),
+        );
+        let triangle = Triangle::new(Arc::new(mesh), 0);
+
+        // Rays leave the center of the facet from above it, closer to the sphere
+        let ray = Ray::new(Point3::origin(), Vector3::new(1., 1., 1.));
+        let hit = triangle.intersect(&ray, Time(f64::INFINITY)).unwrap();
+        let lift = (hit.shading.p - hit.p).dot(hit.n.to_vector());
+        assert!(
+            lift > 0.1 && (hit.shading.p - Point3::origin()).length() < 1.,
+            "{lift}"
+        );
+        assert!((hit.shading.p - hit.p - hit.n.to_vector() * lift).length() < 1e-12);
+
+        // Points at the vertices stay in place
+        let ray = Ray::new(Point3::origin(), Vector3::new(1., 1e-9, 1e-9));
+        let hit = triangle.intersect(&ray, Time(f64::INFINITY)).unwrap();
+        assert!((hit.shading.p - hit.p).length() < 1e-6);
+    }
+}
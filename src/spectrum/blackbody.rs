@@ -0,0 +1,91 @@
+use crate::spectrum::Spectrum;
+use num_traits::Float;
+
+const PLANCK: f64 = 6.626_070_15e-34;
+const SPEED_OF_LIGHT: f64 = 299_792_458.;
+const BOLTZMANN: f64 = 1.380_649e-23;
+/// Wien's displacement constant, in meter kelvins
+const WIEN: f64 = 2.897_771_955e-3;
+
+/// Returns the spectral radiance of a black body at a temperature in kelvins, at a wavelength in
+/// nanometers, in W/(sr m² m)
+#[must_use]
+pub fn blackbody<T: Float>(lambda: T, temperature: T) -> T {
+    let (Some(lambda), Some(temperature)) = (lambda.to_f64(), temperature.to_f64()) else {
+        return T::zero();
+    };
+    if temperature <= 0. {
+        return T::zero();
+    }
+    let lambda = lambda * 1e-9;
+    let radiance = 2. * PLANCK * SPEED_OF_LIGHT * SPEED_OF_LIGHT
+        / (lambda.powi(5)
+            * ((PLANCK * SPEED_OF_LIGHT / (lambda * BOLTZMANN * temperature)).exp() - 1.));
+    T::from(radiance).unwrap()
+}
+
+/// The emission of a black body at a temperature in kelvins, scaled to a peak of one
+///
+/// The scale keeps the color of the light separate from its intensity, which is usually given
+/// alongside.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct BlackbodySpectrum<T> {
+    temperature: T,
+    normalization: T,
+}
+
+impl<T: Float> BlackbodySpectrum<T> {
+    #[must_use]
+    pub fn new(temperature: T) -> Self {
+        let peak = T::from(WIEN * 1e9).unwrap() / temperature;
+        let max = blackbody(peak, temperature);
+        Self {
+            temperature,
+            normalization: if max > T::zero() {
+                max.recip()
+            } else {
+                T::zero()
+            },
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn temperature(&self) -> T {
+        self.temperature
+    }
+}
+
+impl<T: Float> Spectrum<T> for BlackbodySpectrum<T> {
+    #[inline]
+    fn evaluate(&self, lambda: T) -> T {
+        blackbody(lambda, self.temperature) * self.normalization
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spectrum::spectrum_to_xyz;
+
+    #[test]
+    fn test_blackbody() {
+        // The sun is close to a black body at 5778 K, peaking around 501 nm
+        let sun = BlackbodySpectrum::new(5778_f64);
+        assert!((sun.evaluate(501.5) - 1.).abs() < 1e-6);
+        assert!(sun.evaluate(400.) < 1. && sun.evaluate(700.) < 1.);
+        assert!((blackbody(500_f64, 6000.) / 3.17e13 - 1.).abs() < 1e-2);
+        assert_eq!(blackbody(500_f64, 0.), 0.);
+
+        // Cooler bodies are redder
+        let xy = |t: f64| {
+            spectrum_to_xyz(&BlackbodySpectrum::new(t))
+                .to_xyy()
+                .chromaticity
+        };
+        let (warm, cool) = (xy(2700.), xy(6500.));
+        assert!(warm.x > cool.x);
+        // 6500 K lies near the D65 white point
+        assert!((cool.x - 0.3135).abs() < 5e-3 && (cool.y - 0.3237).abs() < 5e-3);
+    }
+}
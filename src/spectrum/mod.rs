@@ -0,0 +1,90 @@
+//! Spectral distributions over the visible wavelengths, and their conversion to color
+
+mod blackbody;
+mod rgb;
+mod tabulated;
+
+pub use blackbody::{blackbody, BlackbodySpectrum};
+pub use rgb::{RgbAlbedoSpectrum, RgbUnboundedSpectrum, SigmoidPolynomial};
+pub use tabulated::{PiecewiseLinearSpectrum, SpectrumError};
+
+use crate::color::Xyz;
+use num_traits::Float;
+
+/// The shortest wavelength considered, in nanometers
+pub const LAMBDA_MIN: f64 = 360.;
+/// The longest wavelength considered, in nanometers
+pub const LAMBDA_MAX: f64 = 830.;
+
+/// The spacing of the wavelengths at which spectra are integrated, in nanometers
+const INTEGRATION_STEP: f64 = 5.;
+
+/// A function of wavelength in nanometers, such as a reflectance or an emission spectrum
+pub trait Spectrum<T> {
+    #[must_use]
+    fn evaluate(&self, lambda: T) -> T;
+}
+
+/// A spectrum with the same value at every wavelength
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct ConstantSpectrum<T>(pub T);
+
+impl<T: Copy> Spectrum<T> for ConstantSpectrum<T> {
+    #[inline]
+    fn evaluate(&self, _: T) -> T {
+        self.0
+    }
+}
+
+impl<T, S: Spectrum<T> + ?Sized> Spectrum<T> for &S {
+    #[inline]
+    fn evaluate(&self, lambda: T) -> T {
+        (**self).evaluate(lambda)
+    }
+}
+
+/// Evaluates the CIE 1931 2° color matching functions with the multi-lobe fit of Wyman et al.,
+/// "Simple Analytic Approximations to the CIE XYZ Color Matching Functions" (2013)
+#[must_use]
+pub fn cie_xyz<T: Float>(lambda: T) -> Xyz<T> {
+    let lambda = lambda.to_f64().unwrap_or(0.);
+    let g = |mu: f64, sigma_lo: f64, sigma_hi: f64| {
+        let t = (lambda - mu) / if lambda < mu { sigma_lo } else { sigma_hi };
+        (-0.5 * t * t).exp()
+    };
+    let x =
+        1.056 * g(599.8, 37.9, 31.0) + 0.362 * g(442.0, 16.0, 26.7) - 0.065 * g(501.1, 20.4, 26.2);
+    let y = 0.821 * g(568.8, 46.9, 40.5) + 0.286 * g(530.9, 16.3, 31.1);
+    let z = 1.217 * g(437.0, 11.8, 36.0) + 0.681 * g(459.0, 26.0, 13.8);
+    let cast = |c: f64| T::from(c).unwrap();
+    Xyz::new(cast(x), cast(y), cast(z))
+}
+
+/// Integrates a spectrum against the color matching functions, normalized so that a constant
+/// spectrum of one has a luminance of one
+///
+/// For reflectances, this is their color under an equal-energy illuminant.
+#[must_use]
+pub fn spectrum_to_xyz<T: Float>(s: &impl Spectrum<T>) -> Xyz<T> {
+    let [x, y, z] = integrate(|lambda| s.evaluate(T::from(lambda).unwrap()).to_f64().unwrap_or(0.));
+    let cast = |c: f64| T::from(c).unwrap();
+    Xyz::new(cast(x), cast(y), cast(z))
+}
+
+/// Integrates with the trapezoidal rule, normalized by the integral of `y`
+fn integrate(mut f: impl FnMut(f64) -> f64) -> [f64; 3] {
+    let steps = ((LAMBDA_MAX - LAMBDA_MIN) / INTEGRATION_STEP) as usize;
+    let mut sum = [0.; 3];
+    let mut y_integral = 0.;
+    for i in 0..=steps {
+        let lambda = LAMBDA_MIN + i as f64 * INTEGRATION_STEP;
+        let weight = if i == 0 || i == steps { 0.5 } else { 1. };
+        let cmf: Xyz<f64> = cie_xyz(lambda);
+        let value = f(lambda) * weight;
+        for (s, c) in sum.iter_mut().zip(cmf.to_array()) {
+            *s += c * value;
+        }
+        y_integral += cmf.y * weight;
+    }
+    sum.map(|s| s / y_integral)
+}
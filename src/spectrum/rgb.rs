@@ -0,0 +1,241 @@
+use crate::{
+    color::{chromatic_adaptation, invert, mul, Chromaticity, Matrix3, Rgb, RgbColorSpace, Xyz},
+    spectrum::{integrate, Spectrum, LAMBDA_MAX, LAMBDA_MIN},
+};
+use num_traits::Float;
+
+/// Each fit moves the target away from gray in this many steps, starting from the previous
+/// solution, which keeps Gauss-Newton from diverging for saturated colors
+const CONTINUATION_STEPS: usize = 8;
+const MAX_ITERATIONS: usize = 15;
+/// The CIELAB distance below which a fit is considered exact
+const TOLERANCE: f64 = 1e-6;
+const MAX_COEFFICIENT: f64 = 200.;
+
+/// A smooth spectrum bounded by `[0, 1]`, a sigmoid of a quadratic in the wavelength
+///
+/// The coefficients apply to the wavelength normalized from
+/// [`LAMBDA_MIN`]..[`LAMBDA_MAX`] to `[0, 1]`.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct SigmoidPolynomial<T> {
+    pub coefficients: [T; 3],
+}
+
+impl<T: Float> SigmoidPolynomial<T> {
+    #[inline]
+    #[must_use]
+    pub const fn new(coefficients: [T; 3]) -> Self {
+        Self { coefficients }
+    }
+
+    /// Fits the spectrum whose color matches a reflectance in `[0, 1]`, following Jakob and
+    /// Hanika, "A Low-Dimensional Function Space for Efficient Spectral Upsampling" (2019)
+    ///
+    /// Colors are matched in CIELAB under an equal-energy illuminant, after adapting them from the
+    /// white point of their color space, so that white becomes a constant spectrum of one. The
+    /// fit is solved for each color rather than looked up in a precomputed table.
+    #[must_use]
+    pub fn fit(space: &RgbColorSpace<T>, rgb: Rgb<T>) -> Self {
+        let rgb = rgb.map(|c| c.to_f64().unwrap_or(0.).clamp(0., 1.));
+        let cast = |c: [f64; 3]| Self::new(c.map(|c| T::from(c).unwrap()));
+        if rgb.r == rgb.g && rgb.g == rgb.b {
+            // Constant spectra are exact, and infinite coefficients give black and white
+            let c = rgb.r;
+            return cast([0., 0., (c - 0.5) / (c * (1. - c)).sqrt()]);
+        }
+
+        let chromaticity = |c: Chromaticity<T>| Chromaticity::new(to_f64(c.x), to_f64(c.y));
+        let space = RgbColorSpace::new(
+            chromaticity(space.r),
+            chromaticity(space.g),
+            chromaticity(space.b),
+            chromaticity(space.white),
+        );
+        let [x, y, z] = integrate(|_| 1.);
+        let white = Xyz::new(x, y, z);
+        let white_chromaticity = white.to_xyy().chromaticity;
+        let mut coefficients = [0.; 3];
+        for step in 1..=CONTINUATION_STEPS {
+            let t = step as f64 / CONTINUATION_STEPS as f64;
+            // Saturated channels would need infinite coefficients
+            let target: Rgb<f64> = rgb.map(|c| (0.5 + (c - 0.5) * t).clamp(1e-3, 1. - 1e-3));
+            let xyz = chromatic_adaptation(space.white, white_chromaticity, space.to_xyz(target));
+            coefficients = gauss_newton(coefficients, lab(xyz, white), white);
+        }
+        cast(coefficients)
+    }
+
+    /// Evaluates the spectrum at a wavelength in nanometers
+    #[inline]
+    #[must_use]
+    pub fn evaluate(&self, lambda: T) -> T {
+        let min = T::from(LAMBDA_MIN).unwrap();
+        let x = (lambda - min) / (T::from(LAMBDA_MAX).unwrap() - min);
+        let [c0, c1, c2] = self.coefficients;
+        sigmoid(c0 * x * x + c1 * x + c2)
+    }
+}
+
+impl<T: Float> Spectrum<T> for SigmoidPolynomial<T> {
+    #[inline]
+    fn evaluate(&self, lambda: T) -> T {
+        Self::evaluate(self, lambda)
+    }
+}
+
+/// A reflectance spectrum with the given color, see [`SigmoidPolynomial::fit`]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct RgbAlbedoSpectrum<T> {
+    polynomial: SigmoidPolynomial<T>,
+}
+
+impl<T: Float> RgbAlbedoSpectrum<T> {
+    /// Channels outside `[0, 1]` are clamped
+    #[must_use]
+    pub fn new(space: &RgbColorSpace<T>, rgb: Rgb<T>) -> Self {
+        Self {
+            polynomial: SigmoidPolynomial::fit(space, rgb),
+        }
+    }
+}
+
+impl<T: Float> Spectrum<T> for RgbAlbedoSpectrum<T> {
+    #[inline]
+    fn evaluate(&self, lambda: T) -> T {
+        self.polynomial.evaluate(lambda)
+    }
+}
+
+/// A spectrum with the given color and no upper bound, such as for scattering coefficients
+///
+/// The color is fit at half the brightness of its largest channel, and scaled back up.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct RgbUnboundedSpectrum<T> {
+    scale: T,
+    polynomial: SigmoidPolynomial<T>,
+}
+
+impl<T: Float> RgbUnboundedSpectrum<T> {
+    /// Negative channels are clamped to zero
+    #[must_use]
+    pub fn new(space: &RgbColorSpace<T>, rgb: Rgb<T>) -> Self {
+        let rgb = rgb.map(|c| c.max(T::zero()));
+        let scale = rgb.max_component() * T::from(2).unwrap();
+        let polynomial = if scale > T::zero() {
+            SigmoidPolynomial::fit(space, rgb / scale)
+        } else {
+            SigmoidPolynomial::fit(space, Rgb::black())
+        };
+        Self { scale, polynomial }
+    }
+}
+
+impl<T: Float> Spectrum<T> for RgbUnboundedSpectrum<T> {
+    #[inline]
+    fn evaluate(&self, lambda: T) -> T {
+        self.scale * self.polynomial.evaluate(lambda)
+    }
+}
+
+#[inline]
+fn sigmoid<T: Float>(x: T) -> T {
+    if x.is_infinite() {
+        return if x > T::zero() { T::one() } else { T::zero() };
+    }
+    let half = T::from(0.5).unwrap();
+    half + x * half / (T::one() + x * x).sqrt()
+}
+
+#[inline]
+fn to_f64<T: Float>(x: T) -> f64 {
+    x.to_f64().unwrap_or(0.)
+}
+
+/// Returns the CIELAB coordinates of a color relative to a white
+fn lab(xyz: Xyz<f64>, white: Xyz<f64>) -> [f64; 3] {
+    let f = |t: f64| {
+        const DELTA: f64 = 6. / 29.;
+        if t > DELTA * DELTA * DELTA {
+            t.cbrt()
+        } else {
+            t / (3. * DELTA * DELTA) + 4. / 29.
+        }
+    };
+    let [x, y, z] = [xyz.x / white.x, xyz.y / white.y, xyz.z / white.z].map(f);
+    [116. * y - 16., 500. * (x - y), 200. * (y - z)]
+}
+
+fn residual(coefficients: [f64; 3], target: [f64; 3], white: Xyz<f64>) -> [f64; 3] {
+    let polynomial = SigmoidPolynomial::new(coefficients);
+    let [x, y, z] = integrate(|lambda| polynomial.evaluate(lambda));
+    let xyz = Xyz::new(x, y, z);
+    let lab = lab(xyz, white);
+    [0, 1, 2].map(|i| lab[i] - target[i])
+}
+
+fn gauss_newton(mut coefficients: [f64; 3], target: [f64; 3], white: Xyz<f64>) -> [f64; 3] {
+    const EPSILON: f64 = 1e-5;
+    for _ in 0..MAX_ITERATIONS {
+        let r = residual(coefficients, target, white);
+        if r.iter().map(|r| r * r).sum::<f64>().sqrt() < TOLERANCE {
+            break;
+        }
+        // Central differences
+        let mut jacobian: Matrix3<f64> = [[0.; 3]; 3];
+        for i in 0..3 {
+            let (mut lo, mut hi) = (coefficients, coefficients);
+            lo[i] -= EPSILON;
+            hi[i] += EPSILON;
+            let (r_lo, r_hi) = (residual(lo, target, white), residual(hi, target, white));
+            for (row, (lo, hi)) in jacobian.iter_mut().zip(r_lo.into_iter().zip(r_hi)) {
+                row[i] = (hi - lo) / (2. * EPSILON);
+            }
+        }
+        let Some(inverse) = invert(jacobian) else {
+            break;
+        };
+        let step = mul(inverse, r);
+        for (c, s) in coefficients.iter_mut().zip(step) {
+            *c -= s;
+        }
+        let max = coefficients.iter().fold(0., |max: f64, c| max.max(c.abs()));
+        if max > MAX_COEFFICIENT {
+            coefficients = coefficients.map(|c| c * MAX_COEFFICIENT / max);
+        }
+    }
+    coefficients
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spectrum::spectrum_to_xyz;
+
+    #[test]
+    fn test_fit() {
+        let space = RgbColorSpace::<f64>::srgb();
+        let white = spectrum_to_xyz(&crate::spectrum::ConstantSpectrum(1.));
+        for rgb in [
+            Rgb::new(0.8, 0.2, 0.1),
+            Rgb::new(0.1, 0.6, 0.3),
+            Rgb::new(0.2, 0.3, 0.9),
+            Rgb::new(0.95, 0.9, 0.02),
+        ] {
+            let spectrum = RgbAlbedoSpectrum::new(&space, rgb);
+            let xyz = spectrum_to_xyz(&spectrum);
+            let xyz = chromatic_adaptation(white.to_xyy().chromaticity, space.white, xyz);
+            let round_trip = space.from_xyz(xyz);
+            let error = (round_trip - rgb).map(f64::abs).max_component();
+            assert!(error < 2e-3, "{rgb:?} became {round_trip:?}");
+        }
+
+        let gray = RgbAlbedoSpectrum::new(&space, Rgb::splat(0.5));
+        assert_eq!(gray.evaluate(500.), 0.5);
+        let white = RgbAlbedoSpectrum::new(&space, Rgb::splat(1.));
+        assert_eq!(white.evaluate(400.), 1.);
+
+        let bright = RgbUnboundedSpectrum::new(&space, Rgb::new(4., 2., 1.));
+        assert!(bright.evaluate(650.) > bright.evaluate(450.));
+        assert!(bright.evaluate(650.) <= 8.);
+    }
+}
@@ -0,0 +1,178 @@
+use crate::spectrum::{spectrum_to_xyz, Spectrum};
+use num_traits::Float;
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader, Read},
+    path::Path,
+};
+
+/// Why a tabulated spectrum could not be loaded
+#[derive(Debug)]
+pub enum SpectrumError {
+    Io(io::Error),
+    /// The data is not a list of wavelength and value pairs
+    InvalidFormat,
+    /// The wavelengths are not strictly increasing
+    Unsorted,
+}
+
+impl fmt::Display for SpectrumError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "failed to read spectrum: {e}"),
+            Self::InvalidFormat => f.write_str("invalid spectrum data"),
+            Self::Unsorted => f.write_str("spectrum wavelengths are not increasing"),
+        }
+    }
+}
+
+impl std::error::Error for SpectrumError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SpectrumError {
+    #[inline]
+    fn from(e: io::Error) -> Self {
+        if e.kind() == io::ErrorKind::UnexpectedEof {
+            Self::InvalidFormat
+        } else {
+            Self::Io(e)
+        }
+    }
+}
+
+/// A spectrum interpolated linearly between measured samples, and zero outside of them
+///
+/// This is how measured emission spectra of lamps, and the indices of refraction of metals and
+/// dielectrics, are usually distributed.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PiecewiseLinearSpectrum<T> {
+    lambdas: Vec<T>,
+    values: Vec<T>,
+}
+
+impl<T: Float> PiecewiseLinearSpectrum<T> {
+    /// Returns the spectrum with `values` at the wavelengths `lambdas` in nanometers, or `None`
+    /// if their lengths differ or the wavelengths are not strictly increasing
+    #[must_use]
+    pub fn new(lambdas: Vec<T>, values: Vec<T>) -> Option<Self> {
+        let sorted = lambdas.windows(2).all(|w| w[0] < w[1]);
+        (lambdas.len() == values.len() && sorted).then_some(Self { lambdas, values })
+    }
+
+    /// Returns the spectrum from alternating wavelengths and values, see [`new`](Self::new)
+    #[must_use]
+    pub fn from_interleaved(data: &[T]) -> Option<Self> {
+        if !data.len().is_multiple_of(2) {
+            return None;
+        }
+        let (lambdas, values) = data.chunks_exact(2).map(|pair| (pair[0], pair[1])).unzip();
+        Self::new(lambdas, values)
+    }
+
+    /// Reads pairs of a wavelength in nanometers and a value, separated by whitespace or commas
+    ///
+    /// Text after a `#` on a line is ignored, as are lines that start with a letter, such as
+    /// the header of a CSV file.
+    pub fn read(reader: impl Read) -> Result<Self, SpectrumError> {
+        let mut data = Vec::new();
+        for line in BufReader::new(reader).lines() {
+            let line = line?;
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.starts_with(|c: char| c.is_alphabetic()) {
+                continue;
+            }
+            for s in line.split(|c: char| c == ',' || c.is_whitespace()) {
+                if s.is_empty() {
+                    continue;
+                }
+                let x: f64 = s.parse().map_err(|_| SpectrumError::InvalidFormat)?;
+                data.push(T::from(x).ok_or(SpectrumError::InvalidFormat)?);
+            }
+        }
+        if data.is_empty() || !data.len().is_multiple_of(2) {
+            return Err(SpectrumError::InvalidFormat);
+        }
+        Self::from_interleaved(&data).ok_or(SpectrumError::Unsorted)
+    }
+
+    /// Reads a spectrum from the file at `path`, see [`read`](Self::read)
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SpectrumError> {
+        Self::read(File::open(path)?)
+    }
+
+    /// Scales the spectrum so that its luminance is one, as for the emission of a light whose
+    /// intensity is given separately
+    #[must_use]
+    pub fn normalized(mut self) -> Self {
+        let luminance = spectrum_to_xyz(&self).y;
+        if luminance > T::zero() {
+            self.values.iter_mut().for_each(|v| *v = *v / luminance);
+        }
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn lambdas(&self) -> &[T] {
+        &self.lambdas
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+}
+
+impl<T: Float> Spectrum<T> for PiecewiseLinearSpectrum<T> {
+    fn evaluate(&self, lambda: T) -> T {
+        let (Some(&first), Some(&last)) = (self.lambdas.first(), self.lambdas.last()) else {
+            return T::zero();
+        };
+        if !(lambda >= first && lambda <= last) {
+            return T::zero();
+        }
+        let i = self.lambdas.partition_point(|&l| l <= lambda);
+        if i == self.lambdas.len() {
+            return self.values[i - 1];
+        }
+        let (l0, l1) = (self.lambdas[i - 1], self.lambdas[i]);
+        let t = (lambda - l0) / (l1 - l0);
+        self.values[i - 1] * (T::one() - t) + self.values[i] * t
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read() {
+        let data = "# Measured\nlambda,value\n400, 1\n500 3 # peak\n\n600\t2\n";
+        let s = PiecewiseLinearSpectrum::<f32>::read(data.as_bytes()).unwrap();
+        assert_eq!(s.lambdas(), [400., 500., 600.]);
+        assert_eq!(s.evaluate(450.), 2.);
+        assert_eq!(s.evaluate(600.), 2.);
+        assert_eq!(s.evaluate(399.), 0.);
+        assert_eq!(s.evaluate(f32::NAN), 0.);
+
+        let normalized = s.normalized();
+        assert!((spectrum_to_xyz(&normalized).y - 1.).abs() < 1e-5);
+
+        assert!(matches!(
+            PiecewiseLinearSpectrum::<f32>::read(&b"400 1 500"[..]),
+            Err(SpectrumError::InvalidFormat)
+        ));
+        assert!(matches!(
+            PiecewiseLinearSpectrum::<f32>::read(&b"500 1 400 2"[..]),
+            Err(SpectrumError::Unsorted)
+        ));
+    }
+}
@@ -0,0 +1,215 @@
+use crate::{
+    color::Rgb,
+    core::geometry::{Point2, UnknownUnit, Vector2},
+    image::Image,
+    image_io::ImageError,
+    shape::SurfaceInteraction,
+    texture::{FilterMode, MipMap, Texture},
+};
+use num_traits::Float;
+use std::{path::Path, sync::Arc};
+
+/// How texture coordinates outside `[0, 1]` are mapped into the image
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum WrapMode {
+    /// Tiles the image
+    #[default]
+    Repeat,
+    /// Extends the edge pixels
+    Clamp,
+    /// Tiles the image, flipping every other tile so that tiles meet seamlessly
+    Mirror,
+}
+
+impl WrapMode {
+    /// Maps a pixel coordinate into `0..n`
+    #[inline]
+    #[must_use]
+    pub fn wrap(self, i: i64, n: usize) -> usize {
+        let n = n as i64;
+        let i = match self {
+            Self::Repeat => i.rem_euclid(n),
+            Self::Clamp => i.clamp(0, n - 1),
+            Self::Mirror => {
+                let i = i.rem_euclid(2 * n);
+                if i < n {
+                    i
+                } else {
+                    2 * n - 1 - i
+                }
+            }
+        };
+        i as usize
+    }
+}
+
+/// A texture which looks up a linear image, filtered over the footprint of the pixel being
+/// shaded
+///
+/// The image covers the unit square of texture space, with `v` going up from the bottom row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageTexture<T> {
+    mipmap: Arc<MipMap<T>>,
+    pub wrap: WrapMode,
+    pub filter: FilterMode,
+    /// The largest ratio of the axes of elliptical footprints, see [`MipMap::ewa`]
+    pub max_anisotropy: T,
+}
+
+impl<T: Float> ImageTexture<T> {
+    /// Returns a texture with EWA filtering up to an anisotropy of 8
+    ///
+    /// Panics if the image is empty.
+    #[must_use]
+    pub fn new(image: Image<T>, wrap: WrapMode) -> Self {
+        Self {
+            mipmap: Arc::new(MipMap::new(image)),
+            wrap,
+            filter: FilterMode::default(),
+            max_anisotropy: T::from(8).unwrap(),
+        }
+    }
+
+    /// Returns a texture of an image whose values are encoded with the sRGB transfer function,
+    /// such as one painted on screen
+    #[must_use]
+    pub fn from_srgb(mut image: Image<T>, wrap: WrapMode) -> Self {
+        for p in image.pixels_mut() {
+            *p = p.from_srgb();
+        }
+        Self::new(image, wrap)
+    }
+
+    /// Loads a texture from an image file by its extension, see [`Image::load`]
+    pub fn load(path: impl AsRef<Path>, wrap: WrapMode) -> Result<Self, ImageError> {
+        let image = Image::load(path)?;
+        if image.width() == 0 || image.height() == 0 {
+            return Err(ImageError::InvalidFormat);
+        }
+        Ok(Self::new(image, wrap))
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn with_filter(self, filter: FilterMode) -> Self {
+        Self { filter, ..self }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn with_max_anisotropy(self, max_anisotropy: T) -> Self {
+        Self {
+            max_anisotropy,
+            ..self
+        }
+    }
+
+    /// Returns the full-resolution image
+    #[inline]
+    #[must_use]
+    pub fn image(&self) -> &Image<T> {
+        &self.mipmap.levels()[0]
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn mipmap(&self) -> &Arc<MipMap<T>> {
+        &self.mipmap
+    }
+
+    /// Interpolates the four pixels nearest to `uv`, with pixel centers at half-integer
+    /// coordinates
+    #[must_use]
+    pub fn bilinear(&self, uv: Point2<T, UnknownUnit>) -> Rgb<T> {
+        self.mipmap.bilinear(0, to_st(uv), self.wrap)
+    }
+
+    /// Filters the footprint spanned by the changes of `uv` along the axes of the image
+    #[must_use]
+    pub fn lookup(
+        &self,
+        uv: Point2<T, UnknownUnit>,
+        duvdx: Vector2<T, UnknownUnit>,
+        duvdy: Vector2<T, UnknownUnit>,
+    ) -> Rgb<T> {
+        let st = to_st(uv);
+        // Rows go down as `v` goes up
+        let (dst0, dst1) = (
+            Vector2::new(duvdx.x, -duvdx.y),
+            Vector2::new(duvdy.x, -duvdy.y),
+        );
+        match self.filter {
+            FilterMode::Bilinear => self.mipmap.bilinear(0, st, self.wrap),
+            FilterMode::Trilinear => {
+                let width = dst0
+                    .x
+                    .abs()
+                    .max(dst0.y.abs())
+                    .max(dst1.x.abs().max(dst1.y.abs()));
+                let two = T::from(2).unwrap();
+                self.mipmap.trilinear(st, two * width, self.wrap)
+            }
+            FilterMode::Ewa => self
+                .mipmap
+                .ewa(st, dst0, dst1, self.max_anisotropy, self.wrap),
+        }
+    }
+}
+
+#[inline]
+fn to_st<T: Float>(uv: Point2<T, UnknownUnit>) -> Point2<T, UnknownUnit> {
+    Point2::new(uv.x, T::one() - uv.y)
+}
+
+impl<T: Float + Send + Sync, U> Texture<T, U, Rgb<T>> for ImageTexture<T> {
+    #[inline]
+    fn evaluate(&self, si: &SurfaceInteraction<T, U>) -> Rgb<T> {
+        self.lookup(si.uv, si.duvdx, si.duvdy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_texture() {
+        assert_eq!(WrapMode::Repeat.wrap(-1, 4), 3);
+        assert_eq!(WrapMode::Clamp.wrap(-1, 4), 0);
+        assert_eq!(WrapMode::Mirror.wrap(-1, 4), 0);
+        assert_eq!(WrapMode::Mirror.wrap(5, 4), 2);
+
+        // Black on the left and white on the right
+        let image = Image::new(
+            2,
+            2,
+            vec![
+                Rgb::splat(0.),
+                Rgb::splat(1.),
+                Rgb::splat(0.),
+                Rgb::splat(1.),
+            ],
+        );
+        let texture = ImageTexture::new(image.clone(), WrapMode::Clamp);
+        let at = |texture: &ImageTexture<f64>, u, v| texture.bilinear(Point2::new(u, v)).r;
+        assert_eq!(at(&texture, 0.25, 0.5), 0.);
+        assert_eq!(at(&texture, 0.5, 0.5), 0.5);
+        assert_eq!(at(&texture, 0.75, 0.1), 1.);
+        assert_eq!(at(&texture, 1.5, 0.5), 1.);
+        let texture = ImageTexture::new(image.clone(), WrapMode::Repeat);
+        assert_eq!(at(&texture, 0., 0.5), 0.5);
+        assert_eq!(at(&texture, 1.25, 0.5), 0.);
+        let texture = ImageTexture::new(image, WrapMode::Mirror);
+        assert_eq!(at(&texture, 0., 0.5), 0.);
+        assert_eq!(at(&texture, 1.25, 0.5), 1.);
+
+        // The top row is at the top of texture space
+        let image = Image::new(1, 2, vec![Rgb::splat(1.), Rgb::splat(0.)]);
+        let texture = ImageTexture::new(image, WrapMode::Clamp);
+        assert_eq!(at(&texture, 0.5, 0.9), 1.);
+
+        let srgb = Image::new(1, 1, vec![Rgb::splat(0.5)]);
+        let texture = ImageTexture::from_srgb(srgb, WrapMode::Repeat);
+        assert!((at(&texture, 0.5, 0.5) - 0.214).abs() < 1e-3);
+    }
+}
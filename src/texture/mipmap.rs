@@ -0,0 +1,282 @@
+use crate::{
+    color::Rgb,
+    core::geometry::{Point2, UnknownUnit, Vector2},
+    image::Image,
+    texture::WrapMode,
+};
+use num_traits::Float;
+
+/// How a [`MipMap`] averages the pixels under a footprint
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum FilterMode {
+    /// Interpolates the full-resolution image, ignoring the footprint
+    Bilinear,
+    /// Interpolates between the two levels whose pixels best match the width of the footprint,
+    /// which blurs footprints that are much longer than they are wide
+    Trilinear,
+    /// Weights the pixels under the elliptical footprint with a gaussian, on the level matching
+    /// its minor axis
+    #[default]
+    Ewa,
+}
+
+/// An image with successively halved copies of itself, for filtering textures over large
+/// footprints in constant time
+///
+/// Positions `st` span the image over `[0, 1]`, with `t` going down its rows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MipMap<T> {
+    levels: Vec<Image<T>>,
+}
+
+impl<T: Float> MipMap<T> {
+    /// Builds the levels by averaging blocks of 2×2 pixels, repeating the last row or column
+    /// of odd sizes, down to a single pixel
+    ///
+    /// Panics if the image is empty.
+    #[must_use]
+    pub fn new(image: Image<T>) -> Self {
+        assert!(
+            image.width() > 0 && image.height() > 0,
+            "the image must not be empty"
+        );
+        let mut levels = vec![image];
+        loop {
+            let prev = levels.last().unwrap();
+            let (w, h) = (prev.width(), prev.height());
+            if w == 1 && h == 1 {
+                break;
+            }
+            let (width, height) = (w.div_ceil(2), h.div_ceil(2));
+            let quarter = T::from(0.25).unwrap();
+            let pixels = (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .map(|(x, y)| {
+                    let at = |dx: usize, dy: usize| {
+                        let (x, y) = ((2 * x + dx).min(w - 1), (2 * y + dy).min(h - 1));
+                        prev.pixels()[y * w + x]
+                    };
+                    (at(0, 0) + at(1, 0) + at(0, 1) + at(1, 1)) * quarter
+                })
+                .collect();
+            levels.push(Image::new(width, height, pixels));
+        }
+        Self { levels }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn levels(&self) -> &[Image<T>] {
+        &self.levels
+    }
+
+    /// Returns the pixel at `(x, y)` of a level after wrapping the coordinates
+    #[inline]
+    #[must_use]
+    pub fn texel(&self, level: usize, x: i64, y: i64, wrap: WrapMode) -> Rgb<T> {
+        let image = &self.levels[level];
+        let x = wrap.wrap(x, image.width());
+        let y = wrap.wrap(y, image.height());
+        image.pixels()[y * image.width() + x]
+    }
+
+    /// Interpolates the four pixels of a level nearest to `st`, with pixel centers at
+    /// half-integer coordinates
+    #[must_use]
+    pub fn bilinear(&self, level: usize, st: Point2<T, UnknownUnit>, wrap: WrapMode) -> Rgb<T> {
+        let (x, y) = self.pixel_coordinates(level, st);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (dx, dy) = (x - x0, y - y0);
+        let (x0, y0) = (x0.to_i64().unwrap_or(0), y0.to_i64().unwrap_or(0));
+        let lerp = |t: T, a: Rgb<T>, b: Rgb<T>| a * (T::one() - t) + b * t;
+        let texel = |x, y| self.texel(level, x, y, wrap);
+        lerp(
+            dy,
+            lerp(dx, texel(x0, y0), texel(x0 + 1, y0)),
+            lerp(dx, texel(x0, y0 + 1), texel(x0 + 1, y0 + 1)),
+        )
+    }
+
+    /// Filters a square footprint of the given width, in the units of `st`
+    #[must_use]
+    pub fn trilinear(&self, st: Point2<T, UnknownUnit>, width: T, wrap: WrapMode) -> Rgb<T> {
+        let level = self.level_of(width);
+        if level <= T::zero() {
+            return self.bilinear(0, st, wrap);
+        }
+        let last = self.levels.len() - 1;
+        let i = level.floor().to_usize().unwrap_or(last);
+        if i >= last {
+            return self.texel(last, 0, 0, wrap);
+        }
+        let delta = level - T::from(i).unwrap();
+        self.bilinear(i, st, wrap) * (T::one() - delta) + self.bilinear(i + 1, st, wrap) * delta
+    }
+
+    /// Filters the elliptical footprint spanned by the axes `dst0` and `dst1`, with elliptically
+    /// weighted averaging (Heckbert, "Fundamentals of Texture Mapping and Image Warping", 1989)
+    ///
+    /// Footprints more eccentric than `max_anisotropy` are widened along their minor axis, which
+    /// bounds the number of pixels read at the cost of some blur.
+    #[must_use]
+    pub fn ewa(
+        &self,
+        st: Point2<T, UnknownUnit>,
+        mut dst0: Vector2<T, UnknownUnit>,
+        mut dst1: Vector2<T, UnknownUnit>,
+        max_anisotropy: T,
+        wrap: WrapMode,
+    ) -> Rgb<T> {
+        if dst0.length_squared() < dst1.length_squared() {
+            std::mem::swap(&mut dst0, &mut dst1);
+        }
+        let major = dst0.length_squared().sqrt();
+        let mut minor = dst1.length_squared().sqrt();
+        if minor > T::zero() && minor * max_anisotropy < major {
+            let scale = major / (minor * max_anisotropy);
+            dst1 = dst1 * scale;
+            minor = minor * scale;
+        }
+        if minor == T::zero() {
+            return self.bilinear(0, st, wrap);
+        }
+
+        let level = self.level_of(minor).max(T::zero());
+        let i = level.floor().to_usize().unwrap_or(self.levels.len());
+        let delta = level - T::from(i).unwrap();
+        let lower = self.ewa_level(i, st, dst0, dst1, wrap);
+        if delta == T::zero() {
+            return lower;
+        }
+        lower * (T::one() - delta) + self.ewa_level(i + 1, st, dst0, dst1, wrap) * delta
+    }
+
+    fn ewa_level(
+        &self,
+        level: usize,
+        st: Point2<T, UnknownUnit>,
+        dst0: Vector2<T, UnknownUnit>,
+        dst1: Vector2<T, UnknownUnit>,
+        wrap: WrapMode,
+    ) -> Rgb<T> {
+        let last = self.levels.len() - 1;
+        if level >= last {
+            return self.texel(last, 0, 0, wrap);
+        }
+        let (s, t) = self.pixel_coordinates(level, st);
+        let image = &self.levels[level];
+        let (w, h) = (
+            T::from(image.width()).unwrap(),
+            T::from(image.height()).unwrap(),
+        );
+        let (ds0, dt0, ds1, dt1) = (dst0.x * w, dst0.y * h, dst1.x * w, dst1.y * h);
+
+        // The implicit ellipse `a s² + b s t + c t² = 1`, widened by a pixel to cover at least
+        // one sample
+        let one = T::one();
+        let two = T::from(2).unwrap();
+        let mut a = dt0 * dt0 + dt1 * dt1 + one;
+        let mut b = -two * (ds0 * dt0 + ds1 * dt1);
+        let mut c = ds0 * ds0 + ds1 * ds1 + one;
+        let inv_f = one / (a * c - b * b / T::from(4).unwrap());
+        a = a * inv_f;
+        b = b * inv_f;
+        c = c * inv_f;
+
+        // The bounding box of the ellipse
+        let det = T::from(4).unwrap() * a * c - b * b;
+        let s_radius = two * (det * c).max(T::zero()).sqrt() / det;
+        let t_radius = two * (det * a).max(T::zero()).sqrt() / det;
+        let to_i64 = |x: T| x.to_i64().unwrap_or(0);
+        let (s0, s1) = (
+            to_i64((s - s_radius).ceil()),
+            to_i64((s + s_radius).floor()),
+        );
+        let (t0, t1) = (
+            to_i64((t - t_radius).ceil()),
+            to_i64((t + t_radius).floor()),
+        );
+
+        // A gaussian that falls to zero at the edge of the ellipse
+        let falloff = (-two).exp();
+        let mut sum = Rgb::black();
+        let mut total = T::zero();
+        for y in t0..=t1 {
+            let dt = T::from(y).unwrap() - t;
+            for x in s0..=s1 {
+                let ds = T::from(x).unwrap() - s;
+                let r2 = a * ds * ds + b * ds * dt + c * dt * dt;
+                if r2 < one {
+                    let weight = (-two * r2).exp() - falloff;
+                    sum += self.texel(level, x, y, wrap) * weight;
+                    total = total + weight;
+                }
+            }
+        }
+        if total > T::zero() {
+            sum / total
+        } else {
+            self.bilinear(level, st, wrap)
+        }
+    }
+
+    /// Returns the fractional level whose pixels are `width` wide, in the units of `st`
+    fn level_of(&self, width: T) -> T {
+        let image = &self.levels[0];
+        let resolution = T::from(image.width().max(image.height())).unwrap();
+        (width * resolution).max(T::from(1e-8).unwrap()).log2()
+    }
+
+    /// Converts `st` to continuous pixel coordinates of a level, with pixel centers at integers
+    fn pixel_coordinates(&self, level: usize, st: Point2<T, UnknownUnit>) -> (T, T) {
+        let image = &self.levels[level];
+        let half = T::from(0.5).unwrap();
+        (
+            st.x * T::from(image.width()).unwrap() - half,
+            st.y * T::from(image.height()).unwrap() - half,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mipmap() {
+        // Vertical stripes one pixel wide
+        let (w, h) = (64, 64);
+        let pixels = (0..w * h)
+            .map(|i| Rgb::splat(if i % 2 == 0 { 0. } else { 1. }))
+            .collect();
+        let mipmap = MipMap::new(Image::new(w, h, pixels));
+        assert_eq!(mipmap.levels().len(), 7);
+        assert_eq!(mipmap.levels()[1].width(), 32);
+        assert_eq!(mipmap.texel(6, 0, 0, WrapMode::Repeat), Rgb::splat(0.5));
+
+        let st = Point2::new(0.3, 0.6);
+        let wrap = WrapMode::Repeat;
+        let tiny = Vector2::new(1e-6, 0.);
+        let pixel = mipmap.bilinear(0, Point2::new(0.5 / 64., 0.5), wrap);
+        assert_eq!(pixel, Rgb::splat(0.));
+        assert_eq!(mipmap.trilinear(st, 0.1, wrap), Rgb::splat(0.5));
+
+        // A footprint stretched along the stripes keeps them sharp with EWA, unlike with a
+        // square filter of its length
+        let st = Point2::new(1.5 / 64., 0.5);
+        let along = Vector2::new(0., 0.2);
+        let across = Vector2::new(0.2 / 64., 0.);
+        let sharp = mipmap.ewa(st, along, across, 64., wrap);
+        assert!(sharp.r > 0.8, "{sharp:?}");
+        assert!((mipmap.trilinear(st, 0.2, wrap).r - 0.5).abs() < 1e-6);
+        // Limiting the anisotropy blurs it instead
+        let blurred = mipmap.ewa(st, along, across, 2., wrap);
+        assert!((blurred.r - 0.5).abs() < 0.05, "{blurred:?}");
+        // Vanishing footprints fall back to bilinear interpolation
+        assert_eq!(mipmap.ewa(st, tiny, Vector2::new(0., 0.), 8., wrap).r, 1.);
+
+        let odd = MipMap::new(Image::new(3, 1, vec![Rgb::splat(1.); 3]));
+        assert_eq!(odd.levels().len(), 3);
+        assert_eq!(odd.levels()[1].pixels(), &[Rgb::splat(1.); 2]);
+    }
+}
@@ -0,0 +1,62 @@
+//! Values that vary over surfaces, such as the colors and roughnesses of materials
+
+mod image;
+mod mipmap;
+mod noise;
+mod pattern;
+
+pub use image::{ImageTexture, WrapMode};
+pub use mipmap::{FilterMode, MipMap};
+pub use noise::{Fbm, Noise, NoiseTexture, Octaves, Perlin, Ridged, Turbulence, Worley};
+pub use pattern::{CheckerTexture, MixTexture, UvTexture};
+
+use crate::{color::Rgb, shape::SurfaceInteraction};
+use num_traits::Float;
+use std::sync::Arc;
+
+/// A function over surfaces, giving a value of type `V` at each surface point
+///
+/// Plain values are textures which are the same everywhere, so that parameters of materials
+/// can be given either way.
+pub trait Texture<T, U, V = T>: Send + Sync {
+    #[must_use]
+    fn evaluate(&self, si: &SurfaceInteraction<T, U>) -> V;
+}
+
+/// A texture with the same value everywhere
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct ConstantTexture<V>(pub V);
+
+impl<T, U, V: Copy + Send + Sync> Texture<T, U, V> for ConstantTexture<V> {
+    #[inline]
+    fn evaluate(&self, _si: &SurfaceInteraction<T, U>) -> V {
+        self.0
+    }
+}
+
+macro_rules! impl_constant {
+    ($($t:ty),*) => {$(
+        impl<U> Texture<$t, U> for $t {
+            #[inline]
+            fn evaluate(&self, _si: &SurfaceInteraction<$t, U>) -> $t {
+                *self
+            }
+        }
+    )*};
+}
+
+impl_constant!(f32, f64);
+
+impl<T: Float + Send + Sync, U> Texture<T, U, Rgb<T>> for Rgb<T> {
+    #[inline]
+    fn evaluate(&self, _si: &SurfaceInteraction<T, U>) -> Rgb<T> {
+        *self
+    }
+}
+
+impl<T, U, V, X: Texture<T, U, V> + ?Sized> Texture<T, U, V> for Arc<X> {
+    #[inline]
+    fn evaluate(&self, si: &SurfaceInteraction<T, U>) -> V {
+        (**self).evaluate(si)
+    }
+}
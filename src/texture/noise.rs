@@ -0,0 +1,273 @@
+use crate::{
+    core::geometry::{Point3, UnknownUnit},
+    sampler::hash,
+    shape::SurfaceInteraction,
+    texture::Texture,
+};
+use num_traits::Float;
+
+/// A scalar function of 3D space with detail at a characteristic scale of one unit
+pub trait Noise<T>: Send + Sync {
+    #[must_use]
+    fn noise(&self, p: Point3<T, UnknownUnit>) -> T;
+}
+
+/// Gradient noise, following Perlin, "Improving Noise" (2002), with values in about `[-1, 1]`
+/// which vanish at integer points
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Perlin {
+    /// Selects among unrelated but equally distributed noises
+    pub seed: u64,
+}
+
+impl<T: Float + Send + Sync> Noise<T> for Perlin {
+    fn noise(&self, p: Point3<T, UnknownUnit>) -> T {
+        let [x, y, z] = [p.x, p.y, p.z];
+        let (x0, y0, z0) = (x.floor(), y.floor(), z.floor());
+        let (dx, dy, dz) = (x - x0, y - y0, z - z0);
+        let [ix, iy, iz] = [x0, y0, z0].map(|c| c.to_i64().unwrap_or(0));
+
+        let one = T::one();
+        let corner = |i: i64, j: i64, k: i64| {
+            let h = hash(&[self.seed, (ix + i) as u64, (iy + j) as u64, (iz + k) as u64]);
+            let offset = |c: T, o: i64| if o == 0 { c } else { c - one };
+            gradient(h, offset(dx, i), offset(dy, j), offset(dz, k))
+        };
+        let (u, v, w) = (fade(dx), fade(dy), fade(dz));
+        let lerp = |t: T, a: T, b: T| a + t * (b - a);
+        lerp(
+            w,
+            lerp(
+                v,
+                lerp(u, corner(0, 0, 0), corner(1, 0, 0)),
+                lerp(u, corner(0, 1, 0), corner(1, 1, 0)),
+            ),
+            lerp(
+                v,
+                lerp(u, corner(0, 0, 1), corner(1, 0, 1)),
+                lerp(u, corner(0, 1, 1), corner(1, 1, 1)),
+            ),
+        )
+    }
+}
+
+/// The quintic that eases between lattice points with continuous second derivatives
+#[inline]
+fn fade<T: Float>(t: T) -> T {
+    let c = |x: f64| T::from(x).unwrap();
+    t * t * t * (t * (t * c(6.) - c(15.)) + c(10.))
+}
+
+/// Dots an offset with one of the 12 directions to the edges of a cube, picked by a hash
+#[inline]
+fn gradient<T: Float>(h: u64, x: T, y: T, z: T) -> T {
+    let (a, b) = match h % 12 {
+        0..=3 => (x, y),
+        4..=7 => (x, z),
+        _ => (y, z),
+    };
+    let a = if h & 1 == 0 { a } else { -a };
+    let b = if h & 2 == 0 { b } else { -b };
+    a + b
+}
+
+/// Cellular noise, following Worley, "A Cellular Texture Basis Function" (1996): the distance
+/// to the nearest of points scattered one per unit cube, with values in about `[0, 1]`
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Worley {
+    pub seed: u64,
+}
+
+impl<T: Float + Send + Sync> Noise<T> for Worley {
+    fn noise(&self, p: Point3<T, UnknownUnit>) -> T {
+        let [x, y, z] = [p.x, p.y, p.z];
+        let [ix, iy, iz] = [x, y, z].map(|c| c.floor().to_i64().unwrap_or(0));
+        let to_unit = |bits: u64| T::from((bits >> 11) as f64 / (1_u64 << 53) as f64).unwrap();
+        let mut nearest = T::infinity();
+        for i in ix - 1..=ix + 1 {
+            for j in iy - 1..=iy + 1 {
+                for k in iz - 1..=iz + 1 {
+                    let h = hash(&[self.seed, i as u64, j as u64, k as u64]);
+                    let feature = [(i, h), (j, hash(&[h])), (k, hash(&[h, 1]))]
+                        .map(|(c, h)| T::from(c).unwrap() + to_unit(h));
+                    let d = [x - feature[0], y - feature[1], z - feature[2]];
+                    nearest = nearest.min(d[0] * d[0] + d[1] * d[1] + d[2] * d[2]);
+                }
+            }
+        }
+        nearest.sqrt()
+    }
+}
+
+/// The parameters shared by fractal sums of octaves of a noise
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Octaves<N, T> {
+    pub noise: N,
+    pub octaves: u32,
+    /// The ratio of the frequencies of successive octaves
+    pub lacunarity: T,
+    /// The ratio of the amplitudes of successive octaves
+    pub gain: T,
+}
+
+impl<N, T: Float> Octaves<N, T> {
+    /// Returns octaves which double in frequency and halve in amplitude
+    #[inline]
+    #[must_use]
+    pub fn new(noise: N, octaves: u32) -> Self {
+        Self {
+            noise,
+            octaves,
+            lacunarity: T::from(2).unwrap(),
+            gain: T::from(0.5).unwrap(),
+        }
+    }
+
+    /// Sums `f` of each octave, normalized by the sum of the amplitudes so that the range of
+    /// `f` is kept
+    fn sum(&self, p: Point3<T, UnknownUnit>, f: impl Fn(T) -> T) -> T
+    where
+        N: Noise<T>,
+    {
+        let (mut sum, mut total) = (T::zero(), T::zero());
+        let (mut frequency, mut amplitude) = (T::one(), T::one());
+        for _ in 0..self.octaves {
+            sum = sum + amplitude * f(self.noise.noise(p * frequency));
+            total = total + amplitude;
+            frequency = frequency * self.lacunarity;
+            amplitude = amplitude * self.gain;
+        }
+        if total > T::zero() {
+            sum / total
+        } else {
+            T::zero()
+        }
+    }
+}
+
+/// Fractional Brownian motion, a sum of octaves of a noise with the range of the noise
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Fbm<N, T>(pub Octaves<N, T>);
+
+impl<T: Float + Send + Sync, N: Noise<T>> Noise<T> for Fbm<N, T> {
+    #[inline]
+    fn noise(&self, p: Point3<T, UnknownUnit>) -> T {
+        self.0.sum(p, |n| n)
+    }
+}
+
+/// A sum of octaves of the absolute value of a noise, with billowy creases where it changes
+/// sign and values in `[0, 1]` for signed noises
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Turbulence<N, T>(pub Octaves<N, T>);
+
+impl<T: Float + Send + Sync, N: Noise<T>> Noise<T> for Turbulence<N, T> {
+    #[inline]
+    fn noise(&self, p: Point3<T, UnknownUnit>) -> T {
+        self.0.sum(p, T::abs)
+    }
+}
+
+/// A sum of octaves of the inverted absolute value of a noise, with sharp ridges where it
+/// changes sign and values in `[0, 1]` for signed noises, as for mountain ranges
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Ridged<N, T>(pub Octaves<N, T>);
+
+impl<T: Float + Send + Sync, N: Noise<T>> Noise<T> for Ridged<N, T> {
+    #[inline]
+    fn noise(&self, p: Point3<T, UnknownUnit>) -> T {
+        self.0.sum(p, |n| {
+            let ridge = (T::one() - n.abs()).max(T::zero());
+            ridge * ridge
+        })
+    }
+}
+
+/// A texture of a noise over the positions of surface points, as `scale * noise + offset`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct NoiseTexture<N, T> {
+    pub noise: N,
+    /// The number of noise units per unit of the surface's space
+    pub frequency: T,
+    pub scale: T,
+    pub offset: T,
+}
+
+impl<N, T: Float> NoiseTexture<N, T> {
+    #[inline]
+    #[must_use]
+    pub fn new(noise: N, frequency: T) -> Self {
+        Self {
+            noise,
+            frequency,
+            scale: T::one(),
+            offset: T::zero(),
+        }
+    }
+
+    /// Maps the values of the noise to `scale * noise + offset`, such as `[-1, 1]` to `[0, 1]`
+    /// with a scale and an offset of one half
+    #[inline]
+    #[must_use]
+    pub fn remapped(self, scale: T, offset: T) -> Self {
+        Self {
+            scale,
+            offset,
+            ..self
+        }
+    }
+}
+
+impl<T: Float + Send + Sync, U, N: Noise<T>> Texture<T, U> for NoiseTexture<N, T> {
+    #[inline]
+    fn evaluate(&self, si: &SurfaceInteraction<T, U>) -> T {
+        let p = si.p.erase_unit() * self.frequency;
+        self.scale * self.noise.noise(p) + self.offset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::Pcg32;
+
+    #[test]
+    fn test_noise() {
+        let mut rng = Pcg32::new(7);
+        let mut point = || {
+            let mut c = || rng.uniform::<f64>() * 20. - 10.;
+            Point3::<f64, UnknownUnit>::new(c(), c(), c())
+        };
+
+        let perlin = Perlin::default();
+        assert_eq!(perlin.noise(Point3::new(3., -2., 5.)), 0.);
+        let (mut min, mut max) = (f64::INFINITY, f64::NEG_INFINITY);
+        for _ in 0..10_000 {
+            let p = point();
+            let n = perlin.noise(p);
+            (min, max) = (min.min(n), max.max(n));
+            // Continuity
+            let nearby = perlin.noise(p + crate::core::geometry::Vector3::new(1e-4, 0., 0.));
+            assert!((n - nearby).abs() < 1e-3);
+        }
+        assert!(
+            min > -1.1 && max < 1.1 && min < -0.5 && max > 0.5,
+            "{min} {max}"
+        );
+        assert_ne!(
+            Perlin { seed: 1 }.noise(Point3::new(0.5, 0.5, 0.5)),
+            perlin.noise(Point3::new(0.5, 0.5, 0.5))
+        );
+
+        let worley = Worley::default();
+        let octaves = Octaves::new(perlin, 5);
+        for _ in 0..1000 {
+            let p = point();
+            let w = worley.noise(p);
+            assert!((0. ..1.8).contains(&w));
+            assert!(Fbm(octaves).noise(p).abs() <= 1.1);
+            assert!((0. ..=1.).contains(&Turbulence(octaves).noise(p)));
+            assert!((0. ..=1.).contains(&Ridged(octaves).noise(p)));
+        }
+    }
+}
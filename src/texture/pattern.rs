@@ -0,0 +1,145 @@
+use crate::{color::Rgb, shape::SurfaceInteraction, texture::Texture};
+use num_traits::Float;
+use std::ops::{Add, Mul};
+
+/// Alternates between two textures over the squares of a grid in texture space
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct CheckerTexture<A, B, T> {
+    /// The texture of the square at the origin, and those an even number of squares away
+    pub even: A,
+    pub odd: B,
+    /// The number of squares per unit of `u`
+    pub scale_u: T,
+    /// The number of squares per unit of `v`
+    pub scale_v: T,
+}
+
+impl<A, B, T: Copy> CheckerTexture<A, B, T> {
+    /// Returns a checkerboard with `scale` squares per unit of `u` and `v`
+    #[inline]
+    #[must_use]
+    pub fn new(even: A, odd: B, scale: T) -> Self {
+        Self {
+            even,
+            odd,
+            scale_u: scale,
+            scale_v: scale,
+        }
+    }
+}
+
+impl<T, U, V, A, B> Texture<T, U, V> for CheckerTexture<A, B, T>
+where
+    T: Float + Send + Sync,
+    A: Texture<T, U, V>,
+    B: Texture<T, U, V>,
+{
+    fn evaluate(&self, si: &SurfaceInteraction<T, U>) -> V {
+        let u = (si.uv.x * self.scale_u).floor().to_i64().unwrap_or(0);
+        let v = (si.uv.y * self.scale_v).floor().to_i64().unwrap_or(0);
+        if (u + v) % 2 == 0 {
+            self.even.evaluate(si)
+        } else {
+            self.odd.evaluate(si)
+        }
+    }
+}
+
+/// Blends two textures by a third, such as a noise, from `a` at zero to `b` at one
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct MixTexture<A, B, F> {
+    pub a: A,
+    pub b: B,
+    /// The weight of `b`, clamped to `[0, 1]`
+    pub amount: F,
+}
+
+impl<A, B, F> MixTexture<A, B, F> {
+    #[inline]
+    #[must_use]
+    pub fn new(a: A, b: B, amount: F) -> Self {
+        Self { a, b, amount }
+    }
+}
+
+impl<T, U, V, A, B, F> Texture<T, U, V> for MixTexture<A, B, F>
+where
+    T: Float + Send + Sync,
+    V: Add<Output = V> + Mul<T, Output = V>,
+    A: Texture<T, U, V>,
+    B: Texture<T, U, V>,
+    F: Texture<T, U>,
+{
+    fn evaluate(&self, si: &SurfaceInteraction<T, U>) -> V {
+        let t = self.amount.evaluate(si).max(T::zero()).min(T::one());
+        // Skip the texture without weight, which may be costly
+        if t == T::zero() {
+            self.a.evaluate(si)
+        } else if t == T::one() {
+            self.b.evaluate(si)
+        } else {
+            self.a.evaluate(si) * (T::one() - t) + self.b.evaluate(si) * t
+        }
+    }
+}
+
+/// Shows the texture coordinates, with the fractional parts of `u` and `v` in red and green,
+/// to debug parameterizations
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct UvTexture;
+
+impl<T: Float, U> Texture<T, U, Rgb<T>> for UvTexture {
+    #[inline]
+    fn evaluate(&self, si: &SurfaceInteraction<T, U>) -> Rgb<T> {
+        let fract = |x: T| x - x.floor();
+        Rgb::new(fract(si.uv.x), fract(si.uv.y), T::zero())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::{
+            geometry::{Point2, Point3, UnknownUnit, Vector3},
+            prelude::Normal3,
+            units::Time,
+        },
+        material::{DiffuseMaterial, Material},
+        texture::ConstantTexture,
+    };
+
+    #[test]
+    fn test_patterns() {
+        let at = |u: f64, v: f64| {
+            SurfaceInteraction::<f64, UnknownUnit>::new(
+                Point3::new(0., 0., 0.),
+                Time(1.),
+                Vector3::new(0., 0., 1.),
+                Normal3::new(0., 0., 1.),
+                Point2::new(u, v),
+                Vector3::new(1., 0., 0.),
+                Vector3::new(0., 1., 0.),
+            )
+        };
+        let checker = CheckerTexture::new(0.2, ConstantTexture(0.8), 4.);
+        assert_eq!(checker.evaluate(&at(0.1, 0.1)), 0.2);
+        assert_eq!(checker.evaluate(&at(0.3, 0.1)), 0.8);
+        assert_eq!(checker.evaluate(&at(0.3, 0.3)), 0.2);
+        assert_eq!(checker.evaluate(&at(-0.1, 0.1)), 0.8);
+        assert_eq!(
+            UvTexture.evaluate(&at(1.25, -0.25)),
+            Rgb::new(0.25, 0.75, 0.)
+        );
+        let mix = MixTexture::new(Rgb::splat(1.), Rgb::new(0., 1., 0.), 0.25);
+        assert_eq!(mix.evaluate(&at(0., 0.)), Rgb::new(0.75, 1., 0.75));
+        assert_eq!(MixTexture::new(0., 1., 2.).evaluate(&at(0., 0.)), 1.);
+
+        // Materials take textures in place of constants
+        let checker = CheckerTexture::new(Rgb::splat(0.), Rgb::splat(1.), 4.);
+        let material = DiffuseMaterial::new(checker);
+        let (wo, wi) = (Vector3::new(0., 0., 1.), Vector3::new(0., 0.6, 0.8));
+        assert!(material.bsdf(&at(0.1, 0.1)).f(wo, wi).is_black());
+        assert!(!material.bsdf(&at(0.3, 0.1)).f(wo, wi).is_black());
+    }
+}
@@ -0,0 +1,291 @@
+//! Rendering in the browser, through a [`WasmRender`] driven by functions exported to
+//! JavaScript when compiled to WebAssembly
+//!
+//! The exports take and return plain numbers and pointers into the memory of the module, so
+//! that no bindings generator is needed. The module is built with
+//!
+//! ```text
+//! cargo rustc --lib --release --target wasm32-unknown-unknown --crate-type cdylib
+//! ```
+//!
+//! and renders on the calling thread. Built with wasm threads, renders use rayon's global pool
+//! once its threads have been started as web workers, such as by `wasm-bindgen-rayon`.
+//!
+//! Scenes are given in the format of pbrt-v4, and must not refer to other files. A render
+//! is run a pass at a time, so that the page stays responsive in between:
+//!
+//! ```js
+//! const { instance } = await WebAssembly.instantiateStreaming(fetch("rt3.wasm"));
+//! const rt3 = instance.exports;
+//! const text = new TextEncoder().encode(scene);
+//! const ptr = rt3.rt3_alloc(text.length);
+//! new Uint8Array(rt3.memory.buffer, ptr, text.length).set(text);
+//! const render = rt3.rt3_render_new(ptr, text.length);
+//! rt3.rt3_free(ptr, text.length);
+//! if (render === 0) {
+//!     const error = new Uint8Array(rt3.memory.buffer, rt3.rt3_error(), rt3.rt3_error_len());
+//!     throw new Error(new TextDecoder().decode(error));
+//! }
+//! const [width, height] = [rt3.rt3_render_width(render), rt3.rt3_render_height(render)];
+//! const frame = () => {
+//!     const taken = rt3.rt3_render_pass(render, 1);
+//!     const pixels = rt3.rt3_render_framebuffer(render);
+//!     const rgba = new Uint8ClampedArray(rt3.memory.buffer, pixels, width * height * 4);
+//!     context.putImageData(new ImageData(rgba, width, height), 0, 0);
+//!     if (taken < rt3.rt3_render_samples_per_pixel(render)) {
+//!         requestAnimationFrame(frame);
+//!     }
+//! };
+//! requestAnimationFrame(frame);
+//! ```
+
+use crate::{
+    accel::Bvh,
+    camera::ThinLensCamera,
+    core::geometry::UnknownUnit,
+    film::Film,
+    integrator::{Integrator, PathIntegrator, RenderSettings},
+    sampler::{IndependentSampler, Sampler},
+    scene::Scene,
+    scene_io::{Pbrt, SceneError},
+};
+use std::fmt;
+
+/// A render of a pbrt-v4 scene with a [`PathIntegrator`], taken in passes
+pub struct WasmRender {
+    scene: Scene<f32, UnknownUnit>,
+    camera: ThinLensCamera<f32, UnknownUnit>,
+    sampler: IndependentSampler<f32>,
+    integrator: PathIntegrator,
+    film: Film<f32>,
+    samples_taken: usize,
+    /// The last image resolved from the film, as 8-bit RGBA
+    framebuffer: Vec<u8>,
+}
+
+impl fmt::Debug for WasmRender {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WasmRender")
+            .field("scene", &self.scene)
+            .field("samples_taken", &self.samples_taken)
+            .finish_non_exhaustive()
+    }
+}
+
+impl WasmRender {
+    /// Reads a scene in the format of pbrt-v4, with the resolution, samples per pixel and
+    /// depth it sets
+    pub fn new(scene: &str) -> Result<Self, SceneError> {
+        let pbrt = Pbrt::read(scene.as_bytes(), "")?;
+        let camera = pbrt
+            .camera
+            .ok_or_else(|| SceneError::Unsupported("scene without a perspective camera".into()))?;
+        let (width, height) = pbrt.resolution;
+        Ok(Self {
+            scene: Scene::new(Bvh::new(pbrt.primitives), pbrt.lights),
+            camera,
+            sampler: IndependentSampler::new(pbrt.samples_per_pixel, 0),
+            integrator: PathIntegrator::new(pbrt.max_depth),
+            film: Film::new(width, height),
+            samples_taken: 0,
+            framebuffer: vec![0; width * height * 4],
+        })
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn film(&self) -> &Film<f32> {
+        &self.film
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn samples_per_pixel(&self) -> usize {
+        self.sampler.samples_per_pixel()
+    }
+
+    /// Takes up to `samples` more samples per pixel, returning the number taken so far
+    pub fn render_pass(&mut self, samples: usize) -> usize {
+        let start = self.samples_taken;
+        let end = start.saturating_add(samples).min(self.samples_per_pixel());
+        self.integrator.render_samples(
+            &self.scene,
+            &self.camera,
+            &self.sampler,
+            &self.film,
+            &RenderSettings::new(),
+            start..end,
+        );
+        self.samples_taken = end;
+        end
+    }
+
+    /// Resolves the film into 8-bit sRGB RGBA, row by row from the top
+    pub fn framebuffer(&mut self) -> &[u8] {
+        self.framebuffer = self.film.resolve().to_rgba8();
+        &self.framebuffer
+    }
+}
+
+/// The functions exported to JavaScript
+#[cfg(target_family = "wasm")]
+mod exports {
+    use super::WasmRender;
+    use std::{cell::RefCell, ptr, slice};
+
+    thread_local! {
+        /// The message of the last error of a call from JavaScript
+        static ERROR: RefCell<String> = const { RefCell::new(String::new()) };
+    }
+
+    /// Allocates `len` bytes in the memory of the module, such as for JavaScript to write a
+    /// scene into
+    #[no_mangle]
+    pub extern "C" fn rt3_alloc(len: usize) -> *mut u8 {
+        Box::into_raw(vec![0_u8; len].into_boxed_slice()).cast()
+    }
+
+    /// Frees bytes allocated by [`rt3_alloc`]
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by [`rt3_alloc`] for `len` bytes, and not freed since.
+    #[no_mangle]
+    pub unsafe extern "C" fn rt3_free(ptr: *mut u8, len: usize) {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(ptr, len)));
+    }
+
+    /// The message of the last error, as UTF-8, which is valid until the next call
+    #[no_mangle]
+    pub extern "C" fn rt3_error() -> *const u8 {
+        ERROR.with(|error| error.borrow().as_ptr())
+    }
+
+    /// The length in bytes of the message of the last error
+    #[no_mangle]
+    pub extern "C" fn rt3_error_len() -> usize {
+        ERROR.with(|error| error.borrow().len())
+    }
+
+    /// Reads a scene in the format of pbrt-v4 from `len` bytes of UTF-8 at `scene`, returning a
+    /// render of it, or null if the scene cannot be read, with the reason in [`rt3_error`]
+    ///
+    /// # Safety
+    ///
+    /// `scene` must point to `len` readable bytes.
+    #[no_mangle]
+    pub unsafe extern "C" fn rt3_render_new(scene: *const u8, len: usize) -> *mut WasmRender {
+        let text = std::str::from_utf8(slice::from_raw_parts(scene, len));
+        let render = match text {
+            Ok(text) => WasmRender::new(text).map_err(|e| e.to_string()),
+            Err(e) => Err(format!("scene is not UTF-8: {e}")),
+        };
+        match render {
+            Ok(render) => Box::into_raw(Box::new(render)),
+            Err(message) => {
+                ERROR.with(|error| *error.borrow_mut() = message);
+                ptr::null_mut()
+            }
+        }
+    }
+
+    /// Frees a render
+    ///
+    /// # Safety
+    ///
+    /// `render` must have been returned by [`rt3_render_new`], and not freed since.
+    #[no_mangle]
+    pub unsafe extern "C" fn rt3_render_free(render: *mut WasmRender) {
+        drop(Box::from_raw(render));
+    }
+
+    /// The width of the image in pixels
+    ///
+    /// # Safety
+    ///
+    /// `render` must be a live render from [`rt3_render_new`].
+    #[no_mangle]
+    pub unsafe extern "C" fn rt3_render_width(render: *const WasmRender) -> usize {
+        (*render).film.width()
+    }
+
+    /// The height of the image in pixels
+    ///
+    /// # Safety
+    ///
+    /// `render` must be a live render from [`rt3_render_new`].
+    #[no_mangle]
+    pub unsafe extern "C" fn rt3_render_height(render: *const WasmRender) -> usize {
+        (*render).film.height()
+    }
+
+    /// The number of samples per pixel the render is complete at
+    ///
+    /// # Safety
+    ///
+    /// `render` must be a live render from [`rt3_render_new`].
+    #[no_mangle]
+    pub unsafe extern "C" fn rt3_render_samples_per_pixel(render: *const WasmRender) -> usize {
+        (*render).samples_per_pixel()
+    }
+
+    /// Renders a pass, see [`WasmRender::render_pass`]
+    ///
+    /// # Safety
+    ///
+    /// `render` must be a live render from [`rt3_render_new`].
+    #[no_mangle]
+    pub unsafe extern "C" fn rt3_render_pass(render: *mut WasmRender, samples: usize) -> usize {
+        (*render).render_pass(samples)
+    }
+
+    /// Resolves the image rendered so far into width × height × 4 bytes of RGBA, which are valid
+    /// until the next call for the render, see [`WasmRender::framebuffer`]
+    ///
+    /// # Safety
+    ///
+    /// `render` must be a live render from [`rt3_render_new`].
+    #[no_mangle]
+    pub unsafe extern "C" fn rt3_render_framebuffer(render: *mut WasmRender) -> *const u8 {
+        (*render).framebuffer().as_ptr()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wasm_render() {
+        let scene = r#"
+            LookAt 0 0 5  0 0 0  0 1 0
+            Camera "perspective" "float fov" [ 45 ]
+            Film "rgb" "integer xresolution" 8 "integer yresolution" 6
+            Sampler "independent" "integer pixelsamples" 4
+            WorldBegin
+            LightSource "point" "point3 from" [ 0 3 3 ] "rgb I" [ 20 20 20 ]
+            Material "diffuse" "rgb reflectance" [ 0.8 0.4 0.2 ]
+            Shape "sphere" "float radius" 1
+        "#;
+        let mut render = WasmRender::new(scene).unwrap();
+        assert_eq!(render.samples_per_pixel(), 4);
+        assert_eq!(render.render_pass(3), 3);
+        // Passes stop at the samples of the sampler, however many are asked for
+        assert_eq!(render.render_pass(usize::MAX), 4);
+        assert_eq!(render.render_pass(usize::MAX), 4);
+        assert!(render.film().statistics().iter().all(|s| s.samples == 4));
+
+        let framebuffer = render.framebuffer();
+        assert_eq!(framebuffer.len(), 8 * 6 * 4);
+        // The sphere is lit in the middle, and the background black and opaque
+        let center = (3 * 8 + 4) * 4;
+        assert!(framebuffer[center] > framebuffer[center + 2]);
+        assert_eq!(framebuffer[..4], [0, 0, 0, 255]);
+
+        assert!(matches!(
+            WasmRender::new("WorldBegin\nShape \"sphere\""),
+            Err(SceneError::Unsupported(_))
+        ));
+        assert!(WasmRender::new("Shap").is_err());
+    }
+}